@@ -19,28 +19,51 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::config::{APP_ID, APP_NAME, APP_REPO, VCS_TAG, VERSION};
+use crate::globals::{CACHE_THUMBNAILS_SUBDIR, THUMBNAIL_CACHE_VERSION};
 use crate::i18n::gettext_f;
+use crate::library::list_model::MemoriesLibraryListModel;
+use crate::library::media_grid;
+use crate::library::scan_service::{MemoriesScanService, ThumbnailSettings};
+use crate::library::shotwell::ImportedMetadata;
+use crate::util::background;
+use crate::util::crash;
 use crate::util::enums::PreferredAdwaitaTheme;
+use crate::util::media_database::{MediaDatabase, MediaList, METADATA_NAMES};
+use crate::util::metadata::get_metadata_with_hash;
+use crate::util::passphrase;
+use crate::util::wallpaper;
+use crate::util::xmp::{self, SidecarUpdate};
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use gettextrs::gettext;
-use glib::{g_critical, g_debug, g_error};
+use glib::{clone, g_critical, g_debug, g_info, g_warning};
 use gtk::{gio, glib, License};
+use std::cell::{Cell, RefMut};
+use std::collections::HashMap;
+use std::fs::{DirBuilder, File};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 mod imp {
     use crate::config::{APP_ID, GRESOURCE_DOMAIN};
-    use crate::globals::CACHE_THUMBNAILS_SUBDIR;
+    use crate::library::dbus_service;
     use crate::library::list_model::MemoriesLibraryListModel;
+    use crate::library::scan_service::MemoriesScanService;
+    use crate::util::crash;
     use crate::util::enums::PreferredAdwaitaTheme;
+    use crate::util::media_database::MediaDatabase;
+    use crate::util::profiling;
+    use crate::util::shortcuts;
     use crate::window::MemoriesApplicationWindow;
     use adw::prelude::*;
     use adw::subclass::prelude::*;
     use gettextrs::gettext;
-    use glib::g_debug;
+    use glib::subclass::Signal;
+    use glib::{clone, g_critical, g_debug, g_info, g_warning};
     use gtk::{gdk, gio, glib};
-    use std::cell::{Cell, OnceCell};
-    use std::fs::{DirBuilder, File};
-    use std::path::Path;
+    use std::cell::{Cell, OnceCell, RefCell};
+    use std::collections::HashMap;
+    use std::sync::OnceLock;
 
     #[derive(Debug, glib::Properties)]
     #[properties(wrapper_type = super::MemoriesApplication)]
@@ -50,9 +73,28 @@ mod imp {
         /// Initialized after the application window is presented.
         #[property(get, set)]
         pub library_list_model: OnceCell<MemoriesLibraryListModel>,
+        /// Indexes the library in the background. Created on first use.
+        pub(super) scan_service: OnceCell<MemoriesScanService>,
         // Bound to GSchema key, stores a `PreferredAdwaitaTheme` value.
         #[property(get, set)]
         pub(super) adwaita_theme: Cell<i32>,
+        /// Favorites, ratings, tags and other metadata of media files. Opened on first use.
+        pub(super) media_database: RefCell<Option<MediaDatabase>>,
+        /// Cache of the star ratings stored in the media database, by file URI.
+        pub(super) ratings: RefCell<Option<HashMap<String, u32>>>,
+        /// Cache of the tags stored in the media database, by file URI.
+        pub(super) tags: RefCell<Option<HashMap<String, Vec<String>>>>,
+        /// Timeout that changes the desktop wallpaper next, while a wallpaper slideshow is set.
+        pub(super) wallpaper_timeout: RefCell<Option<glib::SourceId>>,
+        /// Number of long-running jobs, like imports and exports, in progress.
+        pub(super) running_jobs: Cell<u32>,
+        /// Keeps Memories running in the background while its window is closed.
+        pub(super) background_hold: RefCell<Option<gio::ApplicationHoldGuard>>,
+        /// Registration of the D-Bus interface, while the application is registered on the bus.
+        pub(super) dbus_registration: RefCell<Option<gio::RegistrationId>>,
+        /// Whether Memories was started with `--index-only`, to index the library
+        /// without showing a window, and to quit once the library is indexed.
+        pub(super) index_only: Cell<bool>,
     }
 
     impl Default for MemoriesApplication {
@@ -60,7 +102,16 @@ mod imp {
             Self {
                 gsettings: gio::Settings::new(APP_ID),
                 library_list_model: OnceCell::default(),
+                scan_service: OnceCell::default(),
                 adwaita_theme: Cell::new(PreferredAdwaitaTheme::System.value()),
+                media_database: RefCell::default(),
+                ratings: RefCell::default(),
+                tags: RefCell::default(),
+                wallpaper_timeout: RefCell::default(),
+                running_jobs: Cell::new(0),
+                background_hold: RefCell::default(),
+                dbus_registration: RefCell::default(),
+                index_only: Cell::new(false),
             }
         }
     }
@@ -74,6 +125,20 @@ mod imp {
 
     #[glib::derived_properties]
     impl ObjectImpl for MemoriesApplication {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    // Emitted with the name of the metadata of media files that changed, such
+                    // as "ratings" or the name of a `MediaList`, which is also the detail.
+                    Signal::builder("metadata-changed")
+                        .param_types([String::static_type()])
+                        .detailed()
+                        .build(),
+                ]
+            })
+        }
+
         fn constructed(&self) {
             g_debug!("Application", "Reached constructed()");
 
@@ -82,33 +147,107 @@ mod imp {
 
             obj.setup_gactions();
 
-            obj.set_accels_for_action("app.system-theme", &["<primary><shift>s"]);
-            obj.set_accels_for_action("app.light-theme", &["<primary><shift>l"]);
-            obj.set_accels_for_action("app.dark-theme", &["<primary><shift>d"]);
-            obj.set_accels_for_action("app.quit", &["<primary>q"]);
+            // Shortcuts changed in the preferences dialog are applied right away.
+            self.gsettings.connect_changed(
+                Some("custom-accels"),
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_: &gio::Settings, _: &str| shortcuts::apply(&obj)
+                ),
+            );
+            shortcuts::apply(&*obj);
 
-            // crate::window::MemoriesApplicationWindow
-            obj.set_accels_for_action("win.preferences", &["<primary>comma"]);
-            obj.set_accels_for_action("win.show-help-overlay", &["<primary>question"]);
-            obj.set_accels_for_action("win.fullscreen", &["F11"]);
-            obj.set_accels_for_action("win.leave-fullscreen", &["Escape"]);
+            self.gsettings.connect_changed(
+                Some("run-in-background"),
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_: &gio::Settings, _: &str| obj.update_background_mode()
+                ),
+            );
 
-            // crate::library::viewer::MemoriesViewer
-            obj.set_accels_for_action("viewer.favorite", &["<Ctrl>f"]);
-            obj.set_accels_for_action("viewer.add", &["<Ctrl>a"]);
-            obj.set_accels_for_action("viewer.set_background", &["<Ctrl>F8"]);
-            obj.set_accels_for_action("viewer.copy", &["<Ctrl>c"]);
-            obj.set_accels_for_action("viewer.trash", &["Delete"]);
-            obj.set_accels_for_action("viewer.delete", &["<shift>Delete"]);
-            obj.set_accels_for_action("viewer.properties", &["F9", "<Alt>Return"]);
-            obj.set_accels_for_action("viewer.exit", &["<Ctrl>w"]);
+            for key in ["wallpaper-slideshow", "wallpaper-slideshow-interval"] {
+                self.gsettings.connect_changed(
+                    Some(key),
+                    clone!(
+                        #[weak]
+                        obj,
+                        move |_: &gio::Settings, _: &str| obj.schedule_wallpaper_change()
+                    ),
+                );
+            }
         }
     }
 
     impl ApplicationImpl for MemoriesApplication {
+        fn dbus_register(
+            &self,
+            connection: &gio::DBusConnection,
+            object_path: &str,
+        ) -> Result<(), glib::Error> {
+            self.parent_dbus_register(connection, object_path)?;
+
+            // Automation is optional, so failing to export it does not stop Memories.
+            match dbus_service::register(connection, object_path) {
+                Ok(registration) => {
+                    self.dbus_registration.replace(Some(registration));
+                }
+                Err(err) => g_warning!("Application", "Failed to export the D-Bus interface: {}", err),
+            }
+            Ok(())
+        }
+
+        fn dbus_unregister(&self, connection: &gio::DBusConnection, object_path: &str) {
+            if let Some(registration) = self.dbus_registration.take() {
+                if let Err(err) = connection.unregister_object(registration) {
+                    g_warning!("Application", "Failed to unexport the D-Bus interface: {}", err);
+                }
+            }
+            self.parent_dbus_unregister(connection, object_path);
+        }
+
+        fn handle_local_options(&self, options: &glib::VariantDict) -> glib::ExitCode {
+            if options.contains("index-only") {
+                self.index_only.set(true);
+
+                if let Err(err) = self.obj().register(None::<&gio::Cancellable>) {
+                    g_critical!("Application", "Failed to register the application: {}", err);
+                    return glib::ExitCode::FAILURE;
+                }
+                // The library is already indexed by the running instance.
+                if self.obj().is_remote() {
+                    g_info!(
+                        "Application",
+                        "Memories is already running, and indexes the library itself."
+                    );
+                    return glib::ExitCode::SUCCESS;
+                }
+            }
+            self.parent_handle_local_options(options)
+        }
+
+        fn startup(&self) {
+            self.parent_startup();
+
+            if self.index_only.get() {
+                return;
+            }
+            // Only the primary instance changes the wallpaper.
+            self.obj().schedule_wallpaper_change();
+            self.obj().update_background_mode();
+        }
+
         fn activate(&self) {
             let application = self.obj();
 
+            // Only the first activation indexes the library without a window.
+            // Launching Memories while it is indexed shows the main window.
+            if self.index_only.replace(false) {
+                application.index_library();
+                return;
+            }
+
             application.connect_adwaita_theme_notify(move |app: &super::MemoriesApplication| {
                 let gschema_key_value: i32 = app.adwaita_theme();
 
@@ -125,7 +264,15 @@ mod imp {
                         app.set_adwaita_color_scheme(adw::ColorScheme::ForceDark);
                         app.update_theme_action_states("dark-theme");
                     }
-                    _ => glib::g_error!("Application", "GSchema theme key out of range."),
+                    _ => {
+                        g_warning!(
+                            "Application",
+                            "GSchema theme key out of range: {}. Following the system theme.",
+                            gschema_key_value
+                        );
+                        app.set_adwaita_color_scheme(adw::ColorScheme::Default);
+                        app.update_theme_action_states("system-theme");
+                    }
                 };
             });
 
@@ -133,49 +280,31 @@ mod imp {
                 .bind("adwaita-theme", &application.clone(), "adwaita-theme")
                 .build();
 
-            let app_cache_dir: String = super::MemoriesApplication::get_app_cache_directory();
-            let cache_subdirs: &[&str] = &[CACHE_THUMBNAILS_SUBDIR];
-
-            // Before initializing the window, let's check our cache directory.
-            // If the cache is missing, set the 'fresh-cache' gschema flag to true.
-            for subdirectory in cache_subdirs {
-                let absolute_path: String = format!("{}/{}", app_cache_dir, subdirectory);
-
-                match File::open(Path::new(&absolute_path)) {
-                    Ok(_) => (),
-                    Err(e) => match e.kind() {
-                        std::io::ErrorKind::NotFound => {
-                            g_debug!(
-                                "Application",
-                                "Cache subdirectory '{}' does not exist. A new one will be made.",
-                                absolute_path,
-                            );
-                            DirBuilder::new()
-                                .recursive(true)
-                                .create(absolute_path)
-                                .expect("Failed to create new cache subdirectory.");
-
-                            let _ = self.gsettings.set_boolean("fresh-cache", true);
-                        }
-                        _ => todo!(), // TODO: Extend error handling for cache check
-                    },
-                }
-            }
+            application.prepare_cache();
 
             // The activate() callback also notifies us when the user tries
             // to launch a "second instance" of the application. When they try
             // to do that, we'll just present any existing window.
-            let window = if let Some(window) = application.active_window() {
+            let (window, new_window) = if let Some(window) = application.active_window() {
                 g_debug!("Application", "Application has an active window present!");
-                window
+                (window, false)
             } else {
                 g_debug!("Application", "No active window found; Creating a new window.");
                 let window = MemoriesApplicationWindow::new(&application);
-                window.upcast()
+                (window.upcast(), true)
             };
 
             window.set_title(Some(&gettext("Memories")));
             window.present();
+            // The window is shown again after running in the background.
+            self.background_hold.take();
+
+            // Let the user report the crash if Memories crashed the last time.
+            // Startup is profiled until the first frame of the new window.
+            if new_window {
+                profiling::mark_first_frame(&window);
+                crash::show_crash_report();
+            }
 
             // Setup our own CSS provider from gresource
             let gdk_screen: gdk::Display = gdk::Display::default().unwrap();
@@ -189,6 +318,14 @@ mod imp {
                 gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
             );
         }
+
+        fn shutdown(&self) {
+            // Stop indexing, so that no thumbnails are left half written.
+            if let Some(scan_service) = self.scan_service.get() {
+                scan_service.cancel();
+            }
+            self.parent_shutdown();
+        }
     }
 
     impl GtkApplicationImpl for MemoriesApplication {}
@@ -214,6 +351,60 @@ impl MemoriesApplication {
         self.imp().gsettings.clone()
     }
 
+    /// Returns the service that indexes the library in the background.
+    pub fn scan_service(&self) -> MemoriesScanService {
+        self.imp()
+            .scan_service
+            .get_or_init(MemoriesScanService::new)
+            .clone()
+    }
+
+    /// Indexes the library without showing a window, for `memories --index-only`,
+    /// so that thumbnails are cached by the time that Memories is opened next.
+    /// Memories quits once every library item is indexed, or once indexing waits
+    /// to save power, unless a window was opened in the meantime.
+    fn index_library(&self) {
+        g_info!("Application", "Indexing the library without a window.");
+        self.prepare_cache();
+        MemoriesLibraryListModel::initialize_new_model(self);
+
+        let scan_service: MemoriesScanService = self.scan_service();
+
+        scan_service.connect_progress(|_: &MemoriesScanService, indexed: u32, total: u32| {
+            g_info!("Application", "Indexed {} of {} library items.", indexed, total);
+        });
+
+        // Memories runs until the hold is released.
+        let hold: Rc<Cell<Option<gio::ApplicationHoldGuard>>> = Rc::new(Cell::new(Some(self.hold())));
+
+        scan_service.connect_finished(clone!(
+            #[strong]
+            hold,
+            move |_: &MemoriesScanService| {
+                if hold.take().is_some() {
+                    g_info!("Application", "Indexed the library.");
+                }
+            }
+        ));
+        // Indexing waits while paused, on battery or in power saver mode,
+        // which could last indefinitely, so the rest is indexed next time.
+        scan_service.connect_waiting_notify(move |scan_service: &MemoriesScanService| {
+            if scan_service.waiting() && hold.take().is_some() {
+                g_warning!(
+                    "Application",
+                    "Indexing waits to save power. The remaining items are indexed the next time."
+                );
+            }
+        });
+
+        let thumbnail_settings: ThumbnailSettings = media_grid::default_thumbnail_settings();
+
+        if let Err(err) = scan_service.start(&self.library_list_model(), thumbnail_settings, None) {
+            g_critical!("Application", "Failed to index the library: {}", err);
+            self.quit();
+        }
+    }
+
     fn setup_gactions(&self) {
         // The reason we have a separate action per theme is for allowing the
         // user to be able to set the application theme via keyboard shortcuts.
@@ -284,6 +475,24 @@ impl MemoriesApplication {
             .activate(move |app: &Self, _, _| app.show_clear_app_cache_prompt())
             .build();
 
+        // Opens the file given by URI, such as from the notification of a finished job.
+        let open_file_action = gio::ActionEntry::builder("open-file")
+            .parameter_type(Some(glib::VariantTy::STRING))
+            .activate(move |app: &Self, _, parameter: Option<&glib::Variant>| {
+                let Some(uri) = parameter.and_then(String::from_variant) else {
+                    return;
+                };
+                let launcher: gtk::FileLauncher = gtk::FileLauncher::new(Some(&gio::File::for_uri(&uri)));
+                let window: Option<gtk::Window> = app.active_window();
+
+                glib::spawn_future_local(async move {
+                    if let Err(err) = launcher.launch_future(window.as_ref()).await {
+                        g_warning!("Application", "Failed to open '{}': {}", uri, err);
+                    }
+                });
+            })
+            .build();
+
         let about_action = gio::ActionEntry::builder("about")
             .activate(move |app: &Self, _, _| app.show_about())
             .build();
@@ -298,6 +507,7 @@ impl MemoriesApplication {
             toggle_autoplay_action,
             toggle_hwaccel_action,
             clear_cache_action,
+            open_file_action,
             about_action,
             quit_action,
         ]);
@@ -320,9 +530,10 @@ impl MemoriesApplication {
                 self.change_action_state("dark-theme", &true.to_variant());
                 self.change_action_state("light-theme", &false.to_variant());
             }
-            _ => g_error!(
+            _ => g_critical!(
                 "Application",
-                "update_theme_action_states() received an invalid action name."
+                "update_theme_action_states() received an invalid action name: {}",
+                action_name
             ),
         }
     }
@@ -346,6 +557,528 @@ impl MemoriesApplication {
         self.toggle_gschema_key("ffmpeg-hardware-acceleration", toggle);
     }
 
+    /// Connects to changes of the metadata of media files, such as ratings or the
+    /// archive, of only the metadata named `name` if given, like `gio::Settings`.
+    pub fn connect_metadata_changed<F: Fn(&Self, &str) + 'static>(
+        &self,
+        name: Option<&str>,
+        callback: F,
+    ) -> glib::SignalHandlerId {
+        let signal_name: String = match name {
+            Some(name) => format!("metadata-changed::{}", name),
+            None => "metadata-changed".to_string(),
+        };
+        self.connect_closure(
+            &signal_name,
+            false,
+            glib::closure_local!(move |app: &Self, name: &str| {
+                callback(app, name);
+            }),
+        )
+    }
+
+    fn media_database(&self) -> RefMut<'_, MediaDatabase> {
+        RefMut::map(self.imp().media_database.borrow_mut(), |database| {
+            database.get_or_insert_with(|| {
+                MediaDatabase::open_default().unwrap_or_else(|err| {
+                    g_critical!("Application", "Failed to open media database: {}", err);
+                    // Changes are lost on exit, but Memories can still be used.
+                    MediaDatabase::open_in_memory().expect("Failed to create a database in memory")
+                })
+            })
+        })
+    }
+
+    /// Reads from the media database, or returns the default value if it fails.
+    fn read_media_database<T: Default>(&self, read: impl FnOnce(&MediaDatabase) -> rusqlite::Result<T>) -> T {
+        read(&self.media_database()).unwrap_or_else(|err| {
+            g_critical!("Application", "Failed to read media database: {}", err);
+            T::default()
+        })
+    }
+
+    /// Writes to the media database, and emits `metadata-changed` for the metadata named `name`.
+    fn write_media_database(
+        &self,
+        name: &str,
+        write: impl FnOnce(&mut MediaDatabase) -> rusqlite::Result<()>,
+    ) {
+        if let Err(err) = write(&mut self.media_database()) {
+            g_critical!("Application", "Failed to write media database: {}", err);
+        }
+        self.emit_metadata_changed(name);
+    }
+
+    fn emit_metadata_changed(&self, name: &str) {
+        self.emit_by_name_with_details::<()>("metadata-changed", glib::Quark::from_str(name), &[&name]);
+    }
+
+    /// Returns the star rating of the given media file, from 1 to 5, or 0 if unrated.
+    pub fn rating(&self, file: &gio::File) -> u32 {
+        let mut ratings = self.imp().ratings.borrow_mut();
+        let ratings: &HashMap<String, u32> =
+            ratings.get_or_insert_with(|| self.read_media_database(MediaDatabase::ratings));
+
+        ratings.get(file.uri().as_str()).copied().unwrap_or(0)
+    }
+
+    /// Sets the star rating of the given media file, where 0 removes its rating.
+    /// The rating is also written to the file's XMP sidecar if it is enabled in GSettings.
+    pub fn set_rating(&self, file: &gio::File, rating: u32) {
+        let rating: u32 = rating.min(5);
+
+        self.imp().ratings.replace(None);
+        self.write_media_database("ratings", |database: &mut MediaDatabase| {
+            database.set_rating(file.uri().as_str(), rating)
+        });
+
+        self.write_xmp_sidecar(
+            file,
+            SidecarUpdate {
+                rating: Some(rating as u8),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Returns `true` if the given media file is marked as a favorite.
+    pub fn is_favorite(&self, file: &gio::File) -> bool {
+        self.read_media_database(|database: &MediaDatabase| {
+            database.is_listed(MediaList::Favorites, file.uri().as_str())
+        })
+    }
+
+    /// Marks or unmarks the given media file as a favorite. The favorite state
+    /// is also written to the file's XMP sidecar if it is enabled in GSettings.
+    pub fn set_favorite(&self, file: &gio::File, favorite: bool) {
+        self.set_listed(MediaList::Favorites, std::slice::from_ref(file), favorite);
+
+        self.write_xmp_sidecar(
+            file,
+            SidecarUpdate {
+                favorite: Some(favorite),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Returns the tags of the given media file, in the order they were added.
+    pub fn tags(&self, file: &gio::File) -> Vec<String> {
+        let mut tags = self.imp().tags.borrow_mut();
+        let tags: &HashMap<String, Vec<String>> =
+            tags.get_or_insert_with(|| self.read_media_database(MediaDatabase::tags));
+
+        tags.get(file.uri().as_str()).cloned().unwrap_or_default()
+    }
+
+    /// Returns every tag in use with the number of media files tagged with it, sorted by name.
+    pub fn all_tags(&self) -> Vec<(String, u32)> {
+        let mut cache = self.imp().tags.borrow_mut();
+        let tags: &HashMap<String, Vec<String>> =
+            cache.get_or_insert_with(|| self.read_media_database(MediaDatabase::tags));
+
+        let mut counts: HashMap<&str, u32> = HashMap::new();
+
+        for tag in tags.values().flatten() {
+            *counts.entry(tag.as_str()).or_default() += 1;
+        }
+        let mut all_tags: Vec<(String, u32)> = counts
+            .into_iter()
+            .map(|(tag, count)| (tag.to_string(), count))
+            .collect();
+
+        all_tags.sort_by_key(|(tag, _)| tag.to_lowercase());
+        all_tags
+    }
+
+    /// Sets the tags of the given media file, where an empty list removes all of its tags.
+    /// Tags are also written as XMP keywords to the file's sidecar if it is enabled in GSettings.
+    pub fn set_tags(&self, file: &gio::File, file_tags: Vec<String>) {
+        self.imp().tags.replace(None);
+        self.write_media_database("tags", |database: &mut MediaDatabase| {
+            database.set_tags(file.uri().as_str(), &file_tags)
+        });
+
+        self.write_xmp_sidecar(
+            file,
+            SidecarUpdate {
+                keywords: Some(file_tags),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Adds the metadata imported from another app, such as Shotwell, to the
+    /// metadata of the library, without changing ratings set in Memories.
+    pub fn import_metadata(&self, metadata: ImportedMetadata) {
+        let mut favorites: Vec<String> =
+            self.read_media_database(|database: &MediaDatabase| database.list(MediaList::Favorites));
+        let mut ratings: HashMap<String, u32> = self.read_media_database(MediaDatabase::ratings);
+        let mut tags: HashMap<String, Vec<String>> = self.read_media_database(MediaDatabase::tags);
+
+        metadata.merge_into(&mut favorites, &mut ratings, &mut tags);
+
+        if let Err(err) = self.media_database().import(&favorites, &ratings, &tags) {
+            g_critical!("Application", "Failed to write media database: {}", err);
+        }
+        self.imp().ratings.replace(Some(ratings));
+        self.imp().tags.replace(Some(tags));
+
+        for name in [MediaList::Favorites.name(), "ratings", "tags"] {
+            self.emit_metadata_changed(name);
+        }
+    }
+
+    /// Moves the metadata of media files that were renamed, such as their ratings and
+    /// tags, from their old file to their new file, along with their XMP sidecars.
+    pub async fn move_media_metadata(&self, moves: &[(gio::File, gio::File)]) {
+        let uris: Vec<(String, String)> = moves
+            .iter()
+            .map(|(file, new_file)| (file.uri().to_string(), new_file.uri().to_string()))
+            .collect();
+
+        if let Err(err) = self.media_database().move_files(&uris) {
+            g_critical!("Application", "Failed to write media database: {}", err);
+        }
+        self.imp().ratings.replace(None);
+        self.imp().tags.replace(None);
+
+        for name in METADATA_NAMES {
+            self.emit_metadata_changed(name);
+        }
+        for (file, new_file) in moves {
+            if let Err(err) = xmp::rename_sidecar(file, new_file).await {
+                g_warning!("Application", "Failed to rename XMP sidecar: {}", err);
+            }
+        }
+    }
+
+    /// Returns the URIs of the media files in a list of the media database.
+    pub fn media_list(&self, list: MediaList) -> glib::StrV {
+        self.read_media_database(|database: &MediaDatabase| database.list(list))
+            .into()
+    }
+
+    /// Returns the URIs of the media files moved to the archive.
+    pub fn archived(&self) -> glib::StrV {
+        self.media_list(MediaList::Archived)
+    }
+
+    /// Moves the given media files to the archive, which hides them from the
+    /// library, or moves them back to the library if `archived` is `false`.
+    pub fn set_archived(&self, files: &[gio::File], archived: bool) {
+        self.set_listed(MediaList::Archived, files, archived);
+    }
+
+    /// Returns the URIs of the media files moved to the Private album.
+    pub fn private(&self) -> glib::StrV {
+        self.media_list(MediaList::Private)
+    }
+
+    /// Moves the given media files to the Private album, which hides them from the library
+    /// and search, or moves them back to the library if `private` is `false`. Cached
+    /// thumbnails of private media are removed, so they are not left in the shared cache.
+    pub fn set_private(&self, files: &[gio::File], private: bool) {
+        self.set_listed(MediaList::Private, files, private);
+
+        if private {
+            Self::remove_cached_thumbnails(files.to_vec());
+        }
+    }
+
+    /// Returns whether a passphrase was set up to unlock the Private album.
+    pub fn has_private_passphrase(&self) -> bool {
+        !self.gsettings().string("private-passphrase").is_empty()
+    }
+
+    /// Sets the passphrase that unlocks the Private album, of which only a salted hash is stored.
+    /// Returns `false` if the passphrase could not be set, which is reported to the user.
+    pub fn set_private_passphrase(&self, new_passphrase: &str) -> bool {
+        let stored: String = match passphrase::hash_passphrase(new_passphrase) {
+            Ok(stored) => stored,
+            Err(err) => {
+                crash::report_error(
+                    "Application",
+                    &format!("Failed to generate a salt for the passphrase: {}", err),
+                );
+                return false;
+            }
+        };
+        if let Err(err_msg) = self.gsettings().set_string("private-passphrase", &stored) {
+            g_critical!("Application", "GSettings returned error: {}", err_msg);
+            return false;
+        }
+        true
+    }
+
+    /// Returns whether the given passphrase unlocks the Private album.
+    pub fn verify_private_passphrase(&self, input: &str) -> bool {
+        passphrase::verify_passphrase(input, &self.gsettings().string("private-passphrase"))
+    }
+
+    /// Sets the photos that the desktop wallpaper rotates through, starting
+    /// with the first photo right away. An empty list stops the slideshow.
+    pub fn set_wallpaper_slideshow(&self, files: &[gio::File]) {
+        let gsettings: gio::Settings = self.gsettings();
+        let uris: Vec<glib::GString> = files.iter().map(gio::File::uri).collect();
+
+        // The slideshow is rescheduled once the photos are set,
+        // so its position is reset beforehand.
+        let result: Result<(), glib::BoolError> = gsettings
+            .set_uint("wallpaper-slideshow-position", 0)
+            .and_then(|_| gsettings.set_int64("wallpaper-slideshow-changed", 0))
+            .and_then(|_| gsettings.set_strv("wallpaper-slideshow", uris));
+
+        if let Err(err_msg) = result {
+            g_critical!("Application", "GSettings returned error: {}", err_msg);
+        }
+    }
+
+    /// Schedules the next change of the desktop wallpaper, replacing
+    /// any change scheduled before, if a wallpaper slideshow is set.
+    fn schedule_wallpaper_change(&self) {
+        if let Some(source_id) = self.imp().wallpaper_timeout.take() {
+            source_id.remove();
+        }
+        let gsettings: gio::Settings = self.gsettings();
+
+        if gsettings.strv("wallpaper-slideshow").is_empty() {
+            return;
+        }
+        let interval: u64 = u64::from(gsettings.uint("wallpaper-slideshow-interval")) * 60;
+        let now: i64 = glib::DateTime::now_utc().map(|now| now.to_unix()).unwrap_or(0);
+        let delay: u64 =
+            wallpaper::next_change_delay(gsettings.int64("wallpaper-slideshow-changed"), now, interval);
+
+        let source_id: glib::SourceId = glib::timeout_add_seconds_local_once(
+            delay.try_into().unwrap_or(u32::MAX),
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                move || {
+                    // The source is removed once this returns.
+                    let _ = this.imp().wallpaper_timeout.take();
+                    this.change_wallpaper();
+                }
+            ),
+        );
+        self.imp().wallpaper_timeout.replace(Some(source_id));
+    }
+
+    /// Shows the next photo of the wallpaper slideshow as the
+    /// desktop wallpaper, and schedules the change after it.
+    fn change_wallpaper(&self) {
+        let gsettings: gio::Settings = self.gsettings();
+        let uris: glib::StrV = gsettings.strv("wallpaper-slideshow");
+
+        if uris.is_empty() {
+            return;
+        }
+        let position: usize = gsettings.uint("wallpaper-slideshow-position") as usize % uris.len();
+        let uri: String = uris[position].to_string();
+        let now: i64 = glib::DateTime::now_utc().map(|now| now.to_unix()).unwrap_or(0);
+
+        let next_position: u32 = ((position + 1) % uris.len()).try_into().unwrap_or(0);
+        let result: Result<(), glib::BoolError> = gsettings
+            .set_uint("wallpaper-slideshow-position", next_position)
+            .and_then(|_| gsettings.set_int64("wallpaper-slideshow-changed", now));
+
+        if let Err(err_msg) = result {
+            g_critical!("Application", "GSettings returned error: {}", err_msg);
+        }
+        self.schedule_wallpaper_change();
+
+        glib::spawn_future_local(async move {
+            g_debug!("Application", "Changing the desktop wallpaper to '{}'.", uri);
+
+            if let Err(err) = wallpaper::set_wallpaper_uri(&uri).await {
+                g_warning!("Application", "Failed to change the desktop wallpaper: {}", err);
+            }
+        });
+    }
+
+    /// Adds the given media files to a list of the media database,
+    /// or removes them from it if `listed` is `false`.
+    fn set_listed(&self, list: MediaList, files: &[gio::File], listed: bool) {
+        let uris: Vec<String> = files
+            .iter()
+            .map(|file: &gio::File| file.uri().to_string())
+            .collect();
+
+        self.write_media_database(list.name(), |database: &mut MediaDatabase| {
+            database.set_listed(list, &uris, listed)
+        });
+    }
+
+    /// Migrates the thumbnail cache and creates its directories if they are missing.
+    fn prepare_cache(&self) {
+        // Thumbnails cached by an older version are moved away before the check
+        // below, so that the thumbnails directory is created again if needed.
+        self.migrate_thumbnail_cache();
+
+        let app_cache_dir: String = Self::get_app_cache_directory();
+        let cache_subdirs: &[&str] = &[CACHE_THUMBNAILS_SUBDIR];
+
+        // Before the library is loaded, let's check our cache directory.
+        // If the cache is missing, set the 'fresh-cache' gschema flag to true.
+        for subdirectory in cache_subdirs {
+            let absolute_path: String = format!("{}/{}", app_cache_dir, subdirectory);
+
+            match File::open(Path::new(&absolute_path)) {
+                Ok(_) => (),
+                Err(e) => match e.kind() {
+                    std::io::ErrorKind::NotFound => {
+                        g_debug!(
+                            "Application",
+                            "Cache subdirectory '{}' does not exist. A new one will be made.",
+                            absolute_path,
+                        );
+                        DirBuilder::new()
+                            .recursive(true)
+                            .create(absolute_path)
+                            .expect("Failed to create new cache subdirectory.");
+
+                        let _ = self.gsettings().set_boolean("fresh-cache", true);
+                    }
+                    _ => g_warning!(
+                        "Application",
+                        "Failed to check cache subdirectory '{}': {}",
+                        absolute_path,
+                        e
+                    ),
+                },
+            }
+        }
+    }
+
+    /// Removes the thumbnails cached by an older version of the thumbnail cache keys,
+    /// which are never looked up again. The thumbnails directory is moved away first,
+    /// so that it is removed in the background while new thumbnails are cached.
+    fn migrate_thumbnail_cache(&self) {
+        let gsettings: gio::Settings = self.gsettings();
+
+        if gsettings.int("thumbnail-cache-version") >= THUMBNAIL_CACHE_VERSION {
+            return;
+        }
+        let app_cache_dir: PathBuf = PathBuf::from(Self::get_app_cache_directory());
+        let thumbnails_dir: PathBuf = app_cache_dir.join(CACHE_THUMBNAILS_SUBDIR);
+        let outdated_dir: PathBuf = app_cache_dir.join(format!("{}-outdated", CACHE_THUMBNAILS_SUBDIR));
+
+        // Remove what is left of an earlier migration that was interrupted.
+        let _ = std::fs::remove_dir_all(&outdated_dir);
+
+        match std::fs::rename(&thumbnails_dir, &outdated_dir) {
+            Ok(()) => {
+                glib::spawn_future_local(async move {
+                    if let Err(err) = async_fs::remove_dir_all(&outdated_dir).await {
+                        g_warning!("Application", "Failed to remove outdated thumbnails: {}", err);
+                    }
+                });
+            }
+            // There are no thumbnails to remove on the first start up.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+            Err(err) => {
+                g_warning!("Application", "Failed to move outdated thumbnails: {}", err);
+                return;
+            }
+        }
+        let _ = gsettings.set_int("thumbnail-cache-version", THUMBNAIL_CACHE_VERSION);
+    }
+
+    /// Removes the cached thumbnails of every size of the given media files.
+    fn remove_cached_thumbnails(files: Vec<gio::File>) {
+        glib::spawn_future_local(async move {
+            let mut prefixes: Vec<String> = vec![];
+
+            for file in files {
+                let Some(file_path) = file.path() else {
+                    continue;
+                };
+                if let Ok((_, hash)) = get_metadata_with_hash(&file_path).await {
+                    prefixes.push(format!("{}-", hash));
+                }
+            }
+            let thumbnails_dir: PathBuf =
+                Path::new(&Self::get_app_cache_directory()).join(CACHE_THUMBNAILS_SUBDIR);
+
+            let result = gio::spawn_blocking(move || -> std::io::Result<()> {
+                for entry in std::fs::read_dir(thumbnails_dir)? {
+                    let entry: std::fs::DirEntry = entry?;
+
+                    if prefixes
+                        .iter()
+                        .any(|prefix: &String| entry.file_name().to_string_lossy().starts_with(prefix))
+                    {
+                        std::fs::remove_file(entry.path())?;
+                    }
+                }
+                Ok(())
+            })
+            .await;
+
+            if let Ok(Err(err)) = result {
+                g_warning!("Application", "Failed to remove cached thumbnails: {}", err);
+            }
+        });
+    }
+
+    /// Returns the URIs of the photos picked as the best frame of their burst.
+    pub fn burst_picks(&self) -> glib::StrV {
+        self.media_list(MediaList::BurstPicks)
+    }
+
+    /// Picks a photo as the best frame of its burst, which replaces
+    /// the pick of any other frame of the burst given in `frames`.
+    pub fn pick_burst_frame(&self, frames: &[String], pick: &gio::File) {
+        let list: MediaList = MediaList::BurstPicks;
+
+        self.write_media_database(list.name(), |database: &mut MediaDatabase| {
+            database.set_listed(list, frames, false)?;
+            database.set_listed(list, &[pick.uri().to_string()], true)
+        });
+    }
+
+    /// Returns the media files moved to the trash by Memories, as a map of their original
+    /// URIs to the time that they were moved to the trash, in seconds since the Unix epoch.
+    pub fn trashed_files(&self) -> HashMap<String, i64> {
+        self.read_media_database(MediaDatabase::trashed_files)
+    }
+
+    /// Moves a media file to the trash, and records it so that
+    /// it is listed on the Recently Deleted page.
+    pub async fn trash_file(&self, file: &gio::File) -> Result<(), glib::Error> {
+        file.trash_future(glib::Priority::DEFAULT).await?;
+
+        let now: i64 = glib::DateTime::now_utc().map(|now| now.to_unix()).unwrap_or(0);
+
+        self.write_media_database("trashed-files", |database: &mut MediaDatabase| {
+            database.add_trashed_file(file.uri().as_str(), now)
+        });
+        Ok(())
+    }
+
+    /// Stops listing the given media files on the Recently Deleted page,
+    /// such as when they are restored or deleted permanently.
+    pub fn forget_trashed_files(&self, uris: &[String]) {
+        self.write_media_database("trashed-files", |database: &mut MediaDatabase| {
+            database.remove_trashed_files(uris)
+        });
+    }
+
+    /// Writes the given changes to the media file's XMP sidecar if it is enabled in GSettings.
+    fn write_xmp_sidecar(&self, file: &gio::File, update: SidecarUpdate) {
+        if !self.gsettings().boolean("write-xmp-sidecars") {
+            return;
+        }
+        let file: gio::File = file.clone();
+
+        glib::spawn_future_local(async move {
+            if let Err(err) = xmp::write_sidecar(&file, update).await {
+                g_warning!("Application", "Failed to write XMP sidecar: {}", err);
+            }
+        });
+    }
+
     fn show_clear_app_cache_prompt(&self) {
         let window: gtk::Window = self.active_window().unwrap();
 
@@ -369,9 +1102,10 @@ impl MemoriesApplication {
                                 "Application",
                                 "Insufficient permissions to clear cache directory."
                             ),
-                            _ => g_error!(
+                            _ => g_critical!(
                                 "Application",
-                                "Received an unexpected error kind after trying to clear the cache."
+                                "Failed to clear the cache directory: {}",
+                                io_error
                             ),
                         }
                     }
@@ -565,9 +1299,12 @@ impl MemoriesApplication {
                             }
                         }
                     }
-                    _ => {
-                        g_error!("Application", "Unexpected std::env::VarError variant received.");
-                        panic!(); // g_error! terminates for us; this just silences the compiler.
+                    std::env::VarError::NotUnicode(_) => {
+                        g_warning!(
+                            "Application",
+                            "$XDG_CACHE_HOME is not valid Unicode; Using fallback."
+                        );
+                        glib::user_cache_dir().to_string_lossy().to_string()
                     }
                 }
             }
@@ -587,10 +1324,142 @@ impl MemoriesApplication {
         }
     }
 
+    /// Marks the start of a long-running job, like an import, export, or transcode,
+    /// which ends once the returned guard is dropped. Memories keeps running until
+    /// the last job ends, even if its window is closed in the meantime, and the
+    /// system does not suspend while the job runs. `reason` is shown to the user.
+    pub fn start_job(&self, reason: &str) -> RunningJob {
+        self.imp().running_jobs.set(self.imp().running_jobs.get() + 1);
+
+        RunningJob {
+            application: self.clone(),
+            _inhibitor: self.inhibit_suspend(reason),
+        }
+    }
+
+    /// Prevents the system from suspending until the returned inhibitor
+    /// is dropped. `reason` is shown to the user if they try to suspend.
+    pub fn inhibit_suspend(&self, reason: &str) -> SuspendInhibitor {
+        let cookie: u32 = self.inhibit(
+            self.active_window().as_ref(),
+            gtk::ApplicationInhibitFlags::SUSPEND,
+            Some(reason),
+        );
+        if cookie == 0 {
+            g_debug!("Application", "Failed to inhibit suspending: {}", reason);
+        }
+        SuspendInhibitor {
+            application: self.clone(),
+            cookie,
+        }
+    }
+
+    /// Returns `true` if a long-running job is in progress.
+    pub fn has_running_jobs(&self) -> bool {
+        self.imp().running_jobs.get() > 0
+    }
+
+    /// Returns `true` if Memories keeps running after its window is closed.
+    pub fn runs_in_background(&self) -> bool {
+        self.gsettings().boolean("run-in-background")
+    }
+
+    /// Hides a window that is being closed instead of destroying it, if a job is in
+    /// progress or Memories runs in the background, so that reopening the window is
+    /// instant. Returns `true` if the window was hidden.
+    pub fn hide_closed_window(&self, window: &impl IsA<gtk::Window>) -> bool {
+        if !self.has_running_jobs() && !self.runs_in_background() {
+            return false;
+        }
+        window.set_visible(false);
+
+        if self.runs_in_background() {
+            self.imp().background_hold.replace(Some(self.hold()));
+        }
+        true
+    }
+
+    /// Destroys the windows hidden by `hide_closed_window()`, once no job is in
+    /// progress and Memories does not run in the background, which lets it quit.
+    fn destroy_hidden_windows(&self) {
+        if self.has_running_jobs() || self.runs_in_background() {
+            return;
+        }
+        self.imp().background_hold.take();
+
+        for window in self.windows() {
+            if !window.is_visible() {
+                window.destroy();
+            }
+        }
+    }
+
+    /// Asks the background portal for permission to run in the background if it is
+    /// enabled in GSettings, and turns it off if that is not allowed. Otherwise, lets
+    /// Memories quit if its window was closed.
+    fn update_background_mode(&self) {
+        if !self.runs_in_background() {
+            self.destroy_hidden_windows();
+            return;
+        }
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let reason: String = gettext("Keep the library loaded, so that Memories opens instantly");
+
+                match background::request_background(&reason).await {
+                    Ok(true) => (),
+                    Ok(false) => {
+                        g_warning!("Application", "Running in the background was not allowed.");
+                        this.toggle_gschema_key("run-in-background", false);
+                    }
+                    // Only sandboxed applications need the permission of the portal.
+                    Err(err) => {
+                        g_debug!(
+                            "Application",
+                            "Failed to request running in the background: {}",
+                            err
+                        );
+
+                        if Self::is_flatpak().is_some() {
+                            this.toggle_gschema_key("run-in-background", false);
+                        }
+                    }
+                }
+            }
+        ));
+    }
+
+    /// Sends a desktop notification that a long-running job finished, if no window of
+    /// Memories is focused, since a toast would go unnoticed. Clicking the notification
+    /// or its "Open" button opens `file`, if given. Sending a notification with the same
+    /// `id` replaces the notification sent before.
+    pub fn notify_job_finished(&self, id: &str, title: &str, body: &str, file: Option<&gio::File>) {
+        if self
+            .active_window()
+            .is_some_and(|window: gtk::Window| window.is_active())
+        {
+            return;
+        }
+        let notification: gio::Notification = gio::Notification::new(title);
+        notification.set_body(Some(body));
+
+        if let Some(file) = file {
+            let target: glib::Variant = file.uri().to_variant();
+
+            notification.set_default_action_and_target_value("app.open-file", Some(&target));
+            notification.add_button_with_target_value(&gettext("Open"), "app.open-file", Some(&target));
+        }
+        self.send_notification(Some(id), &notification);
+    }
+
     /// Returns Some($FLATPAK_ID) if in a Flatpak sandbox environment.
     pub fn is_flatpak() -> Option<String> {
         if let Ok(var) = std::env::var("FLATPAK_ID") {
-            assert!(var == APP_ID, "$FLATPAK_ID doesn't match APP_ID!");
+            if var != APP_ID {
+                g_warning!("Application", "$FLATPAK_ID '{}' doesn't match APP_ID!", var);
+            }
             Some(var)
         } else {
             None
@@ -605,3 +1474,42 @@ impl Default for MemoriesApplication {
             .unwrap()
     }
 }
+
+/// Guard of a long-running job, returned by [`MemoriesApplication::start_job`].
+pub struct RunningJob {
+    application: MemoriesApplication,
+    _inhibitor: SuspendInhibitor,
+}
+
+impl Drop for RunningJob {
+    fn drop(&mut self) {
+        let imp = self.application.imp();
+        imp.running_jobs.set(imp.running_jobs.get().saturating_sub(1));
+
+        if imp.running_jobs.get() > 0 {
+            return;
+        }
+        // Windows closed while the job ran were only hidden, so that Memories kept running.
+        glib::idle_add_local_once(clone!(
+            #[weak(rename_to = application)]
+            self.application,
+            move || application.destroy_hidden_windows()
+        ));
+    }
+}
+
+/// Lets the system suspend again once dropped.
+/// Returned by [`MemoriesApplication::inhibit_suspend`].
+#[derive(Debug)]
+pub struct SuspendInhibitor {
+    application: MemoriesApplication,
+    cookie: u32,
+}
+
+impl Drop for SuspendInhibitor {
+    fn drop(&mut self) {
+        if self.cookie != 0 {
+            self.application.uninhibit(self.cookie);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Archive page, which lists the media files that were archived. Archived
+//! media files are hidden from the library without being deleted, until
+//! they are unarchived from this page.
+
+use crate::application::MemoriesApplication;
+use crate::globals::ARCHIVE_THUMBNAIL_SIZE;
+use crate::library::justified_view::MemoriesJustifiedView;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::clone;
+use gtk::{gio, glib};
+
+mod imp {
+    use crate::application::MemoriesApplication;
+    use crate::util::media_database::MediaList;
+    use adw::prelude::*;
+    use adw::subclass::prelude::*;
+    use glib::clone;
+    use gtk::glib;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/archive.ui")]
+    pub struct MemoriesArchiveView {
+        #[template_child]
+        pub(super) unarchive_all_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) archive_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) items_list: TemplateChild<gtk::ListBox>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesArchiveView {
+        const NAME: &'static str = "MemoriesArchiveView";
+        type Type = super::MemoriesArchiveView;
+        type ParentType = adw::NavigationPage;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesArchiveView {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+
+            MemoriesApplication::default().connect_metadata_changed(
+                Some(MediaList::Archived.name()),
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_: &MemoriesApplication, _: &str| obj.update_list()
+                ),
+            );
+            obj.update_list();
+        }
+    }
+
+    impl WidgetImpl for MemoriesArchiveView {}
+    impl NavigationPageImpl for MemoriesArchiveView {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesArchiveView(ObjectSubclass<imp::MemoriesArchiveView>)
+        @extends gtk::Widget, adw::NavigationPage;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesArchiveView {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    fn archived_files() -> Vec<gio::File> {
+        MemoriesApplication::default()
+            .archived()
+            .iter()
+            .map(|uri: &glib::GStringPtr| gio::File::for_uri(uri.as_str()))
+            .collect()
+    }
+
+    fn update_list(&self) {
+        let files: Vec<gio::File> = Self::archived_files();
+        let items_list: &gtk::ListBox = &self.imp().items_list;
+        items_list.remove_all();
+
+        // The most recently archived files are listed first.
+        for file in files.iter().rev() {
+            let row: adw::ActionRow = adw::ActionRow::builder()
+                .title(glib::markup_escape_text(
+                    &file
+                        .basename()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                ))
+                .subtitle(glib::markup_escape_text(
+                    &file
+                        .parent()
+                        .map(|parent: gio::File| parent.parse_name().to_string())
+                        .unwrap_or_default(),
+                ))
+                .build();
+
+            let picture: gtk::Picture = gtk::Picture::builder()
+                .content_fit(gtk::ContentFit::Cover)
+                .width_request(ARCHIVE_THUMBNAIL_SIZE)
+                .height_request(ARCHIVE_THUMBNAIL_SIZE)
+                .valign(gtk::Align::Center)
+                .build();
+            MemoriesJustifiedView::load_preview(&picture, file);
+
+            let unarchive_button: gtk::Button = gtk::Button::builder()
+                .icon_name("edit-undo-symbolic")
+                .tooltip_text(gettext("Unarchive"))
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+
+            unarchive_button.connect_clicked(clone!(
+                #[strong]
+                file,
+                move |_: &gtk::Button| {
+                    MemoriesApplication::default().set_archived(&[file.clone()], false);
+                }
+            ));
+            row.add_prefix(&picture);
+            row.add_suffix(&unarchive_button);
+            items_list.append(&row);
+        }
+
+        self.imp().unarchive_all_button.set_sensitive(!files.is_empty());
+        self.imp()
+            .archive_stack
+            .set_visible_child_name(if files.is_empty() { "empty" } else { "items" });
+    }
+
+    #[template_callback]
+    fn unarchive_all_clicked(&self) {
+        MemoriesApplication::default().set_archived(&Self::archived_files(), false);
+    }
+}
+
+impl Default for MemoriesArchiveView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
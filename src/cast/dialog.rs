@@ -0,0 +1,187 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Dialog that searches the local network for devices to cast to,
+//! and starts or stops casting the media shown in the viewer.
+
+use super::upnp::{self, Renderer};
+use crate::globals::CAST_DISCOVERY_TIMEOUT;
+use crate::i18n::gettext_f;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::{clone, g_warning};
+use gtk::{gio, glib};
+
+mod imp {
+    use adw::subclass::prelude::*;
+    use gtk::{gio, glib};
+    use std::cell::{OnceCell, RefCell};
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/cast-dialog.ui")]
+    pub struct MemoriesCastDialog {
+        pub(super) file: OnceCell<gio::File>,
+        /// Rows of the devices found by the last search.
+        pub(super) device_rows: RefCell<Vec<adw::ActionRow>>,
+        #[template_child]
+        pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) casting_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) casting_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub(super) devices_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) search_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) search_spinner: TemplateChild<gtk::Spinner>,
+        #[template_child]
+        pub(super) empty_row: TemplateChild<adw::ActionRow>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesCastDialog {
+        const NAME: &'static str = "MemoriesCastDialog";
+        type Type = super::MemoriesCastDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesCastDialog {}
+    impl WidgetImpl for MemoriesCastDialog {}
+    impl AdwDialogImpl for MemoriesCastDialog {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesCastDialog(ObjectSubclass<imp::MemoriesCastDialog>)
+        @extends gtk::Widget, adw::Dialog;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesCastDialog {
+    /// Creates a dialog that casts `file` to the chosen device.
+    pub fn new(file: &gio::File) -> Self {
+        let obj: Self = glib::Object::new();
+        obj.imp().file.set(file.clone()).unwrap();
+
+        obj.update_casting_group();
+        obj.search();
+        obj
+    }
+
+    #[template_callback]
+    fn search_clicked(&self) {
+        self.search();
+    }
+
+    #[template_callback]
+    fn stop_clicked(&self) {
+        super::stop();
+        self.update_casting_group();
+    }
+
+    fn update_casting_group(&self) {
+        let renderer_name: Option<String> = super::renderer_name();
+
+        if let Some(name) = &renderer_name {
+            self.imp()
+                .casting_row
+                .set_title(&gettext_f("Casting to {DEVICE}", &[("DEVICE", name)]));
+        }
+        self.imp().casting_group.set_visible(renderer_name.is_some());
+    }
+
+    /// Searches the local network for devices, and lists the devices found.
+    fn search(&self) {
+        for row in self.imp().device_rows.take() {
+            self.imp().devices_group.remove(&row);
+        }
+        self.imp().empty_row.set_visible(false);
+        self.imp().search_stack.set_visible_child_name("searching");
+        self.imp().search_spinner.start();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let renderers = gio::spawn_blocking(|| upnp::discover(CAST_DISCOVERY_TIMEOUT)).await;
+
+                let renderers: Vec<Renderer> = match renderers {
+                    Ok(Ok(renderers)) => renderers,
+                    Ok(Err(err)) => {
+                        g_warning!("CastDialog", "Failed to search for devices: {}", err);
+                        vec![]
+                    }
+                    Err(_) => {
+                        g_warning!("CastDialog", "Device search thread panicked.");
+                        vec![]
+                    }
+                };
+                this.imp().search_spinner.stop();
+                this.imp().search_stack.set_visible_child_name("done");
+                this.imp().empty_row.set_visible(renderers.is_empty());
+
+                for renderer in renderers {
+                    this.add_device_row(renderer);
+                }
+            }
+        ));
+    }
+
+    fn add_device_row(&self, renderer: Renderer) {
+        let row: adw::ActionRow = adw::ActionRow::builder()
+            .title(&renderer.name)
+            .use_markup(false)
+            .activatable(true)
+            .build();
+        row.add_prefix(&gtk::Image::from_icon_name("video-display-symbolic"));
+
+        row.connect_activated(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_: &adw::ActionRow| {
+                let file: &gio::File = this.imp().file.get().unwrap();
+
+                match super::start(renderer.clone(), file) {
+                    Ok(()) => {
+                        this.close();
+                    }
+                    Err(err) => {
+                        g_warning!("CastDialog", "Failed to start casting: {}", err);
+                        this.imp()
+                            .toast_overlay
+                            .add_toast(adw::Toast::new(&gettext("Failed to start casting")));
+                    }
+                }
+            }
+        ));
+        self.imp().devices_group.add(&row);
+        self.imp().device_rows.borrow_mut().push(row);
+    }
+}
@@ -0,0 +1,130 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Casting of photos and videos to TVs and other media renderers on the local
+//! network, with DLNA. The renderer downloads the cast media from a small HTTP
+//! server, and the media viewer casts each media file that it shows next, so
+//! that slideshows on the TV are advanced from the viewer.
+
+mod dialog;
+mod server;
+mod upnp;
+
+pub use dialog::MemoriesCastDialog;
+
+use glib::{g_debug, g_warning};
+use gtk::{gio, glib};
+use server::MediaServer;
+use std::cell::RefCell;
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::rc::Rc;
+use upnp::Renderer;
+
+/// The renderer that media is cast to, and the server that it downloads media from.
+#[derive(Debug)]
+struct CastSession {
+    renderer: Renderer,
+    server: MediaServer,
+}
+
+thread_local! {
+    /// The current cast session. Casting is only controlled from the main thread.
+    static SESSION: RefCell<Option<Rc<CastSession>>> = RefCell::default();
+}
+
+/// Returns the name of the renderer that media is cast to, if casting.
+pub fn renderer_name() -> Option<String> {
+    SESSION.with_borrow(|session| session.as_ref().map(|session| session.renderer.name.clone()))
+}
+
+/// Starts casting to `renderer`, beginning with `file`.
+/// Casting to another renderer is stopped first.
+fn start(renderer: Renderer, file: &gio::File) -> io::Result<()> {
+    let server: MediaServer = MediaServer::start()?;
+    stop();
+
+    SESSION.set(Some(Rc::new(CastSession { renderer, server })));
+    cast_file(file);
+    Ok(())
+}
+
+/// Casts `file` to the renderer, if casting. Errors are only
+/// logged, since the renderer may be turned off at any time.
+pub fn cast_file(file: &gio::File) {
+    let Some(session) = SESSION.with_borrow(Option::clone) else {
+        return;
+    };
+    let Some(path) = file.path() else {
+        g_warning!(
+            "Cast",
+            "'{}' is not a local file, so it can not be cast.",
+            file.uri()
+        );
+        return;
+    };
+    let title: String = file
+        .basename()
+        .map(|basename: PathBuf| basename.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let (content_type, _) = gio::content_type_guess(Some(&path), &[]);
+    let mime_type: String = gio::content_type_get_mime_type(&content_type)
+        .map(|mime_type: glib::GString| mime_type.to_string())
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+
+    let url_path: String = session
+        .server
+        .serve(path, &mime_type, &glib::uuid_string_random());
+    let port: u16 = session.server.port();
+    let renderer: Renderer = session.renderer.clone();
+
+    glib::spawn_future_local(async move {
+        let result = gio::spawn_blocking(move || {
+            let address: SocketAddr = SocketAddr::new(renderer.local_address()?, port);
+            let media_url: String = format!("http://{}{}", address, url_path);
+
+            renderer.play(&media_url, &mime_type, &title)
+        })
+        .await;
+
+        match result {
+            Ok(Ok(())) => g_debug!("Cast", "Cast '{}' to the renderer.", title),
+            Ok(Err(err)) => g_warning!("Cast", "Failed to cast '{}': {}", title, err),
+            Err(_) => g_warning!("Cast", "Cast thread panicked."),
+        }
+    });
+}
+
+/// Stops casting, and stops the renderer from playing the cast media.
+pub fn stop() {
+    let Some(session) = SESSION.take() else {
+        return;
+    };
+    let renderer: Renderer = session.renderer.clone();
+
+    // The server is stopped once the session is dropped, after the renderer stops.
+    glib::spawn_future_local(async move {
+        if let Ok(Err(err)) = gio::spawn_blocking(move || renderer.stop()).await {
+            g_debug!("Cast", "Failed to stop the renderer: {}", err);
+        }
+        drop(session);
+    });
+}
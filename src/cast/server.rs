@@ -0,0 +1,319 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! HTTP server that media renderers download cast media from. Only the media
+//! currently cast is served, at a URL with a random token, so that other media
+//! of the library can not be downloaded by other devices on the network. Since
+//! any device on the network can connect, connections and requests are limited.
+
+use crate::globals::{CAST_MAX_CONNECTIONS, CAST_MAX_HEADERS, CAST_MAX_LINE_LENGTH, CAST_NETWORK_TIMEOUT};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Media file served at a URL path.
+#[derive(Debug, Clone)]
+struct ServedMedia {
+    url_path: String,
+    path: PathBuf,
+    mime_type: String,
+}
+
+/// Serves the cast media on a port chosen by the system, until dropped.
+#[derive(Debug)]
+pub struct MediaServer {
+    port: u16,
+    media: Arc<Mutex<Option<ServedMedia>>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl MediaServer {
+    pub fn start() -> io::Result<Self> {
+        let listener: TcpListener = TcpListener::bind("0.0.0.0:0")?;
+        let port: u16 = listener.local_addr()?.port();
+        let media: Arc<Mutex<Option<ServedMedia>>> = Arc::default();
+        let stopped: Arc<AtomicBool> = Arc::default();
+
+        let served_media: Arc<Mutex<Option<ServedMedia>>> = media.clone();
+        let server_stopped: Arc<AtomicBool> = stopped.clone();
+        let connections: Arc<AtomicUsize> = Arc::default();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if server_stopped.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                // Connections past the limit are closed right away.
+                let Some(connection) = Connection::open(&connections) else {
+                    continue;
+                };
+                let media: Option<ServedMedia> = served_media.lock().unwrap().clone();
+
+                // Renderers download media with several requests at once to seek in videos.
+                thread::spawn(move || {
+                    let _connection: Connection = connection;
+                    let _ = handle_request(stream, media.as_ref());
+                });
+            }
+        });
+        Ok(Self { port, media, stopped })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Serves the file at `path` instead of the previous file,
+    /// and returns the URL path that it is served at.
+    pub fn serve(&self, path: PathBuf, mime_type: &str, token: &str) -> String {
+        let extension: String = path
+            .extension()
+            .map(|extension| format!(".{}", extension.to_string_lossy()))
+            .unwrap_or_default();
+        let url_path: String = format!("/media/{}{}", token, extension);
+
+        self.media.lock().unwrap().replace(ServedMedia {
+            url_path: url_path.clone(),
+            path,
+            mime_type: mime_type.to_string(),
+        });
+        url_path
+    }
+}
+
+impl Drop for MediaServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        // Wakes up the server thread, which is waiting for a connection.
+        let _ = TcpStream::connect(("127.0.0.1", self.port));
+    }
+}
+
+/// Connection answered by the media server, counted until it is dropped.
+struct Connection(Arc<AtomicUsize>);
+
+impl Connection {
+    /// Counts a new connection, or returns `None` if there are too many already.
+    fn open(connections: &Arc<AtomicUsize>) -> Option<Self> {
+        connections
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count: usize| {
+                (count < CAST_MAX_CONNECTIONS).then_some(count + 1)
+            })
+            .ok()
+            .map(|_| Self(connections.clone()))
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Reads a line of a request, of at most `CAST_MAX_LINE_LENGTH` bytes.
+/// Returns `None` if the line is longer, or if the request ended before it.
+fn read_request_line(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line: String = String::new();
+    reader.by_ref().take(CAST_MAX_LINE_LENGTH).read_line(&mut line)?;
+
+    Ok(line.ends_with('\n').then_some(line))
+}
+
+/// Reads the request line and the `Range` header of a request.
+/// Returns `None` if the request has too long lines or too many headers.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Option<(String, Option<String>)>> {
+    let Some(request_line) = read_request_line(reader)? else {
+        return Ok(None);
+    };
+    let mut range_header: Option<String> = None;
+
+    for _ in 0..=CAST_MAX_HEADERS {
+        let Some(line) = read_request_line(reader)? else {
+            return Ok(None);
+        };
+        if line.trim().is_empty() {
+            return Ok(Some((request_line, range_header)));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("range") {
+                range_header = Some(value.trim().to_string());
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Answers a GET or HEAD request for the served media, with the requested
+/// byte range of the file if the request has a `Range` header.
+fn handle_request(mut stream: TcpStream, media: Option<&ServedMedia>) -> io::Result<()> {
+    stream.set_read_timeout(Some(CAST_NETWORK_TIMEOUT))?;
+
+    let mut reader: BufReader<&TcpStream> = BufReader::new(&stream);
+    let Some((request_line, range_header)) = read_request(&mut reader)? else {
+        stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")?;
+        return Ok(());
+    };
+
+    let mut parts = request_line.split_whitespace();
+    let (method, url_path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+
+    let media: &ServedMedia = match media {
+        Some(media) if media.url_path == url_path && matches!(method, "GET" | "HEAD") => media,
+        _ => {
+            stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")?;
+            return Ok(());
+        }
+    };
+    let mut file: File = File::open(&media.path)?;
+    let length: u64 = file.metadata()?.len();
+
+    let range: Option<(u64, u64)> = range_header
+        .as_deref()
+        .and_then(|range: &str| byte_range(range, length));
+    let (start, end) = range.unwrap_or((0, length.saturating_sub(1)));
+    let content_length: u64 = if length == 0 { 0 } else { end - start + 1 };
+
+    let mut headers: String = match range {
+        Some(_) => format!(
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\n",
+            start, end, length
+        ),
+        None => "HTTP/1.1 200 OK\r\n".to_string(),
+    };
+    headers.push_str(&format!(
+        "Content-Type: {}\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\n\
+         transferMode.dlna.org: Streaming\r\nConnection: close\r\n\r\n",
+        media.mime_type, content_length
+    ));
+    stream.write_all(headers.as_bytes())?;
+
+    if method == "GET" {
+        file.seek(SeekFrom::Start(start))?;
+        io::copy(&mut file.take(content_length), &mut stream)?;
+    }
+    stream.shutdown(Shutdown::Both)
+}
+
+/// Returns the first and last byte of the range of a `Range` header, such as
+/// `bytes=100-`, or `None` if the range is invalid for a file of `length` bytes.
+fn byte_range(header: &str, length: u64) -> Option<(u64, u64)> {
+    let (start, end) = header
+        .strip_prefix("bytes=")?
+        .split(',')
+        .next()?
+        .split_once('-')?;
+    let last: u64 = length.checked_sub(1)?;
+
+    let (start, end): (u64, u64) = match (start.trim(), end.trim()) {
+        // A suffix range, which is the given number of bytes at the end of the file.
+        ("", suffix) => (length.saturating_sub(suffix.parse().ok()?), last),
+        (start, "") => (start.parse().ok()?, last),
+        (start, end) => (start.parse().ok()?, end.parse::<u64>().ok()?.min(last)),
+    };
+    (start <= end).then_some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_ranges() {
+        assert_eq!(byte_range("bytes=0-", 100), Some((0, 99)));
+        assert_eq!(byte_range("bytes=10-19", 100), Some((10, 19)));
+        assert_eq!(byte_range("bytes=90-200", 100), Some((90, 99)));
+        assert_eq!(byte_range("bytes=-10", 100), Some((90, 99)));
+        assert_eq!(byte_range("bytes=100-", 100), None);
+        assert_eq!(byte_range("bytes=20-10", 100), None);
+        assert_eq!(byte_range("items=0-10", 100), None);
+        assert_eq!(byte_range("bytes=0-", 0), None);
+    }
+
+    #[test]
+    fn serves_only_the_cast_media() {
+        let folder = tempfile::tempdir().unwrap();
+        let path: PathBuf = folder.path().join("photo.jpg");
+        std::fs::write(&path, "0123456789").unwrap();
+
+        let server: MediaServer = MediaServer::start().unwrap();
+        let url_path: String = server.serve(path, "image/jpeg", "token");
+
+        let request = |request: String| -> String {
+            let mut stream: TcpStream = TcpStream::connect(("127.0.0.1", server.port())).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response: String = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        assert_eq!(url_path, "/media/token.jpg");
+        assert!(request(format!("GET {} HTTP/1.1\r\n\r\n", url_path)).ends_with("\r\n\r\n0123456789"));
+
+        let partial: String = request(format!("GET {} HTTP/1.1\r\nRange: bytes=2-4\r\n\r\n", url_path));
+        assert!(partial.starts_with("HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 2-4/10\r\n"));
+        assert!(partial.ends_with("\r\n\r\n234"));
+
+        assert!(request("GET /media/other.jpg HTTP/1.1\r\n\r\n".to_string()).starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn limits_requests() {
+        let read = |request: String| read_request(&mut request.as_bytes()).unwrap();
+
+        assert_eq!(
+            read("GET / HTTP/1.1\r\nHost: a\r\nRange: bytes=0-\r\n\r\n".to_string()),
+            Some(("GET / HTTP/1.1\r\n".to_string(), Some("bytes=0-".to_string())))
+        );
+        assert_eq!(read("GET / HTTP/1.1\r\nHost: a\r\n".to_string()), None);
+
+        let long_line: String = "a".repeat(CAST_MAX_LINE_LENGTH as usize);
+        assert_eq!(read(format!("GET /{} HTTP/1.1\r\n\r\n", long_line)), None);
+        assert_eq!(
+            read(format!("GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n", long_line)),
+            None
+        );
+
+        let headers: String = "Host: a\r\n".repeat(CAST_MAX_HEADERS);
+        assert!(read(format!("GET / HTTP/1.1\r\n{}\r\n", headers)).is_some());
+        assert_eq!(
+            read(format!("GET / HTTP/1.1\r\n{}Host: a\r\n\r\n", headers)),
+            None
+        );
+    }
+
+    #[test]
+    fn limits_connections() {
+        let connections: Arc<AtomicUsize> = Arc::default();
+        let open: Vec<Connection> = (0..CAST_MAX_CONNECTIONS)
+            .map(|_| Connection::open(&connections).unwrap())
+            .collect();
+
+        assert!(Connection::open(&connections).is_none());
+        drop(open);
+        assert!(Connection::open(&connections).is_some());
+    }
+}
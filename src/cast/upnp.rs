@@ -0,0 +1,386 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! The parts of UPnP that casting uses: discovering media renderers on the local
+//! network with SSDP, reading their device descriptions, and telling them what
+//! to play through their AVTransport service with SOAP requests over HTTP.
+
+use crate::globals::{CAST_NETWORK_TIMEOUT, SSDP_MULTICAST_ADDRESS};
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+const AV_TRANSPORT_SERVICE: &str = "urn:schemas-upnp-org:service:AVTransport:1";
+
+/// A device on the local network that media can be cast to, such as a TV.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Renderer {
+    /// Name of the device, as set by its owner.
+    pub name: String,
+    /// URL of the AVTransport service of the device.
+    pub control_url: String,
+}
+
+impl Renderer {
+    /// Returns the address of this computer on the network of the renderer,
+    /// which the renderer can download the media that it is told to play from.
+    pub fn local_address(&self) -> io::Result<IpAddr> {
+        let (host, port, _) = split_url(&self.control_url).ok_or_else(invalid_url)?;
+        Ok(connect(&host, port)?.local_addr()?.ip())
+    }
+
+    /// Tells the renderer to play the media at `media_url`.
+    pub fn play(&self, media_url: &str, mime_type: &str, title: &str) -> io::Result<()> {
+        let metadata: String = didl_metadata(title, media_url, mime_type);
+
+        self.send_action(
+            "SetAVTransportURI",
+            &[
+                ("InstanceID", "0"),
+                ("CurrentURI", media_url),
+                ("CurrentURIMetaData", &metadata),
+            ],
+        )?;
+        self.send_action("Play", &[("InstanceID", "0"), ("Speed", "1")])
+    }
+
+    /// Tells the renderer to stop playing.
+    pub fn stop(&self) -> io::Result<()> {
+        self.send_action("Stop", &[("InstanceID", "0")])
+    }
+
+    fn send_action(&self, action: &str, arguments: &[(&str, &str)]) -> io::Result<()> {
+        let body: String = soap_envelope(action, arguments);
+        let soap_action: String = format!("\"{}#{}\"", AV_TRANSPORT_SERVICE, action);
+
+        let (status, _) = http_request(
+            "POST",
+            &self.control_url,
+            &[
+                ("Content-Type", "text/xml; charset=\"utf-8\""),
+                ("SOAPAction", &soap_action),
+            ],
+            &body,
+        )?;
+        match status {
+            200..=299 => Ok(()),
+            _ => Err(io::Error::other(format!(
+                "{} failed with HTTP status {}",
+                action, status
+            ))),
+        }
+    }
+}
+
+/// Searches the local network for media renderers, for `timeout`.
+/// Called on a separate thread, since it blocks until the timeout.
+pub fn discover(timeout: Duration) -> io::Result<Vec<Renderer>> {
+    let socket: UdpSocket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.send_to(search_request().as_bytes(), SSDP_MULTICAST_ADDRESS)?;
+
+    let deadline: Instant = Instant::now() + timeout;
+    let mut locations: HashSet<String> = HashSet::new();
+    let mut renderers: Vec<Renderer> = vec![];
+    let mut buffer: [u8; 2048] = [0; 2048];
+
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        socket.set_read_timeout(Some(remaining.max(Duration::from_millis(1))))?;
+
+        let length: usize = match socket.recv_from(&mut buffer) {
+            Ok((length, _)) => length,
+            Err(err) if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => break,
+            Err(err) => return Err(err),
+        };
+        let Some(location) = response_header(&String::from_utf8_lossy(&buffer[..length]), "location") else {
+            continue;
+        };
+        // Devices answer several times, once for each of their services.
+        if !locations.insert(location.clone()) {
+            continue;
+        }
+        let description = http_request("GET", &location, &[], "");
+
+        if let Ok((200, description)) = description {
+            renderers.extend(parse_description(&description, &location));
+        }
+    }
+    Ok(renderers)
+}
+
+/// Returns the SSDP request that media renderers answer to.
+fn search_request() -> String {
+    format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+        SSDP_MULTICAST_ADDRESS, AV_TRANSPORT_SERVICE
+    )
+}
+
+/// Returns the value of a header of an HTTP or SSDP response. Header names
+/// are compared ignoring case, since devices do not agree on their case.
+fn response_header(response: &str, name: &str) -> Option<String> {
+    response.lines().skip(1).find_map(|line: &str| {
+        let (header, value) = line.split_once(':')?;
+        header
+            .trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// Returns the renderer described by the device description at `location`,
+/// or `None` if the device has no AVTransport service to cast with.
+fn parse_description(description: &str, location: &str) -> Option<Renderer> {
+    let name: String = element_text(description, "friendlyName")?;
+    let base: String = element_text(description, "URLBase").unwrap_or_else(|| location.to_string());
+
+    let control_url: String = description.split("<service>").skip(1).find_map(|service: &str| {
+        (element_text(service, "serviceType")? == AV_TRANSPORT_SERVICE)
+            .then(|| element_text(service, "controlURL"))
+            .flatten()
+    })?;
+
+    Some(Renderer {
+        name: unescape_xml(&name),
+        control_url: resolve_url(&base, &control_url)?,
+    })
+}
+
+/// Returns the trimmed text of the first element with the given name.
+/// Device descriptions are simple enough to not need an XML parser.
+fn element_text(xml: &str, name: &str) -> Option<String> {
+    let start: usize = xml.find(&format!("<{}>", name))? + name.len() + 2;
+    let end: usize = start + xml[start..].find(&format!("</{}>", name))?;
+
+    Some(xml[start..end].trim().to_string())
+}
+
+/// Resolves a URL given in a device description against the base URL of the device.
+fn resolve_url(base: &str, url: &str) -> Option<String> {
+    if url.starts_with("http://") {
+        return Some(url.to_string());
+    }
+    let (host, port, base_path) = split_url(base)?;
+
+    let path: String = match url.strip_prefix('/') {
+        Some(absolute_path) => format!("/{}", absolute_path),
+        None => format!("{}{}", &base_path[..=base_path.rfind('/')?], url),
+    };
+    Some(format!("http://{}:{}{}", host, port, path))
+}
+
+/// Splits an HTTP URL into its host, port, and path.
+fn split_url(url: &str) -> Option<(String, u16, String)> {
+    let rest: &str = url.strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (authority, 80),
+    };
+    (!host.is_empty()).then(|| (host.to_string(), port, path.to_string()))
+}
+
+/// Returns the SOAP request that calls an action of the AVTransport service.
+fn soap_envelope(action: &str, arguments: &[(&str, &str)]) -> String {
+    let arguments: String = arguments
+        .iter()
+        .map(|(name, value)| format!("<{0}>{1}</{0}>", name, escape_xml(value)))
+        .collect();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+         s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body><u:{0} xmlns:u=\"{1}\">{2}</u:{0}></s:Body></s:Envelope>",
+        action, AV_TRANSPORT_SERVICE, arguments
+    )
+}
+
+/// Returns the DIDL-Lite metadata of cast media, which
+/// renderers use to tell how to play it and what to show.
+fn didl_metadata(title: &str, media_url: &str, mime_type: &str) -> String {
+    let class: &str = match mime_type.starts_with("video/") {
+        true => "object.item.videoItem",
+        false => "object.item.imageItem.photo",
+    };
+    format!(
+        "<DIDL-Lite xmlns=\"urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+         xmlns:upnp=\"urn:schemas-upnp-org:metadata-1-0/upnp/\">\
+         <item id=\"0\" parentID=\"-1\" restricted=\"1\">\
+         <dc:title>{}</dc:title><upnp:class>{}</upnp:class>\
+         <res protocolInfo=\"http-get:*:{}:*\">{}</res></item></DIDL-Lite>",
+        escape_xml(title),
+        class,
+        escape_xml(mime_type),
+        escape_xml(media_url)
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn invalid_url() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "Invalid renderer URL")
+}
+
+fn connect(host: &str, port: u16) -> io::Result<TcpStream> {
+    let address = (host, port).to_socket_addrs()?.next().ok_or_else(invalid_url)?;
+    let stream: TcpStream = TcpStream::connect_timeout(&address, CAST_NETWORK_TIMEOUT)?;
+
+    stream.set_read_timeout(Some(CAST_NETWORK_TIMEOUT))?;
+    stream.set_write_timeout(Some(CAST_NETWORK_TIMEOUT))?;
+    Ok(stream)
+}
+
+/// Sends an HTTP/1.0 request, and returns the status and body of the response.
+/// HTTP/1.0 is used so that responses are not chunked, and end with the connection.
+fn http_request(method: &str, url: &str, headers: &[(&str, &str)], body: &str) -> io::Result<(u16, String)> {
+    let (host, port, path) = split_url(url).ok_or_else(invalid_url)?;
+    let mut stream: TcpStream = connect(&host, port)?;
+
+    let mut request: String = format!("{} {} HTTP/1.0\r\nHost: {}:{}\r\n", method, path, host, port);
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    ));
+    stream.write_all(request.as_bytes())?;
+
+    let mut response: Vec<u8> = vec![];
+    stream.read_to_end(&mut response)?;
+
+    let response: String = String::from_utf8_lossy(&response).to_string();
+    let status: u16 = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status: &str| status.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid HTTP response"))?;
+    let body: String = response
+        .split_once("\r\n\r\n")
+        .map(|(_, body)| body.to_string())
+        .unwrap_or_default();
+
+    Ok((status, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESCRIPTION: &str = "<?xml version=\"1.0\"?>
+<root xmlns=\"urn:schemas-upnp-org:device-1-0\">
+  <device>
+    <friendlyName>Living Room &amp; Kitchen TV</friendlyName>
+    <serviceList>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:RenderingControl:1</serviceType>
+        <controlURL>/upnp/control/RenderingControl</controlURL>
+      </service>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:AVTransport:1</serviceType>
+        <controlURL>upnp/control/AVTransport</controlURL>
+      </service>
+    </serviceList>
+  </device>
+</root>";
+
+    #[test]
+    fn response_headers() {
+        let response: &str = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=1800\r\n\
+                              Location: http://192.168.1.20:8080/description.xml\r\n\r\n";
+
+        assert_eq!(
+            response_header(response, "LOCATION").as_deref(),
+            Some("http://192.168.1.20:8080/description.xml")
+        );
+        assert_eq!(response_header(response, "ST"), None);
+    }
+
+    #[test]
+    fn parsed_description() {
+        assert_eq!(
+            parse_description(DESCRIPTION, "http://192.168.1.20:8080/dmr/description.xml"),
+            Some(Renderer {
+                name: "Living Room & Kitchen TV".to_string(),
+                control_url: "http://192.168.1.20:8080/dmr/upnp/control/AVTransport".to_string(),
+            })
+        );
+        let without_transport: String = DESCRIPTION.replace("AVTransport:1", "ConnectionManager:1");
+        assert_eq!(
+            parse_description(&without_transport, "http://192.168.1.20/"),
+            None
+        );
+    }
+
+    #[test]
+    fn resolved_urls() {
+        let base: &str = "http://10.0.0.5:49152/description.xml";
+
+        assert_eq!(
+            resolve_url(base, "/control").as_deref(),
+            Some("http://10.0.0.5:49152/control")
+        );
+        assert_eq!(
+            resolve_url(base, "control").as_deref(),
+            Some("http://10.0.0.5:49152/control")
+        );
+        assert_eq!(
+            resolve_url(base, "http://10.0.0.6/control").as_deref(),
+            Some("http://10.0.0.6/control")
+        );
+        assert_eq!(
+            split_url("http://tv.local"),
+            Some(("tv.local".to_string(), 80, "/".to_string()))
+        );
+        assert_eq!(split_url("https://tv.local/"), None);
+    }
+
+    #[test]
+    fn soap_envelopes() {
+        let envelope: String = soap_envelope("SetAVTransportURI", &[("CurrentURI", "http://a/b?c=1&d=2")]);
+
+        assert!(envelope.contains(
+            "<u:SetAVTransportURI xmlns:u=\"urn:schemas-upnp-org:service:AVTransport:1\">\
+             <CurrentURI>http://a/b?c=1&amp;d=2</CurrentURI></u:SetAVTransportURI>"
+        ));
+        assert!(didl_metadata("<Beach>", "http://a/b", "video/mp4").contains("object.item.videoItem"));
+        assert!(didl_metadata("<Beach>", "http://a/b", "image/jpeg")
+            .contains("<dc:title>&lt;Beach&gt;</dc:title>"));
+    }
+}
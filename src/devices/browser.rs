@@ -0,0 +1,409 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Browser for the media in the DCIM folders of a removable device,
+//! from which selected items can be imported into the library. Media
+//! whose contents are already in the library is marked as imported.
+
+use crate::application::{MemoriesApplication, RunningJob};
+use crate::globals::DEFAULT_CAMERA_REL_DIR;
+use crate::i18n::ngettext_f;
+use crate::library::justified_view::MemoriesJustifiedView;
+use crate::library::list_model::MemoriesLibraryListModel;
+use crate::library::media_item::MemoriesMediaItem;
+use crate::library::media_viewer::ViewerContentType;
+use crate::library::share_dialog;
+use crate::util::content_hash;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::{clone, g_warning};
+use gtk::{gio, glib};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+mod imp {
+    use crate::library::list_model::MemoriesLibraryListModel;
+    use adw::subclass::prelude::*;
+    use gtk::glib;
+    use std::cell::RefCell;
+    use std::collections::{HashMap, HashSet};
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/device-browser.ui")]
+    pub struct MemoriesDeviceBrowser {
+        pub(super) list_model: RefCell<Option<MemoriesLibraryListModel>>,
+        /// Content hashes of the media on the device by file URI.
+        pub(super) content_hashes: RefCell<HashMap<String, String>>,
+        /// Content hashes of the media in the library, including the media imported since.
+        pub(super) library_hashes: RefCell<HashSet<String>>,
+        /// Badges of the bound grid cells by file URI, shown if the media was imported.
+        pub(super) imported_badges: RefCell<HashMap<String, gtk::Image>>,
+        #[template_child]
+        pub(super) progress_bar: TemplateChild<gtk::ProgressBar>,
+        #[template_child]
+        pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) import_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) grid_view: TemplateChild<gtk::GridView>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesDeviceBrowser {
+        const NAME: &'static str = "MemoriesDeviceBrowser";
+        type Type = super::MemoriesDeviceBrowser;
+        type ParentType = adw::NavigationPage;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesDeviceBrowser {}
+    impl WidgetImpl for MemoriesDeviceBrowser {}
+    impl NavigationPageImpl for MemoriesDeviceBrowser {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesDeviceBrowser(ObjectSubclass<imp::MemoriesDeviceBrowser>)
+        @extends gtk::Widget, adw::NavigationPage;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesDeviceBrowser {
+    /// Creates a browser for the media in the given DCIM folders. Its items are
+    /// enumerated in a separate list model, so they never enter the library.
+    pub fn new(device_name: &str, dcim_folders: &[gio::File]) -> Self {
+        let obj: Self = glib::Object::builder().property("title", device_name).build();
+
+        obj.imp()
+            .library_hashes
+            .replace(MemoriesApplication::default().scan_service().content_hashes());
+
+        let list_model: MemoriesLibraryListModel =
+            glib::Object::builder().property("sort-by-date", true).build();
+
+        let selection_model: gtk::MultiSelection = gtk::MultiSelection::new(Some(list_model.clone()));
+
+        selection_model.connect_selection_changed(clone!(
+            #[weak]
+            obj,
+            move |model: &gtk::MultiSelection, _, _| {
+                obj.imp()
+                    .import_button
+                    .set_sensitive(!model.selection().is_empty());
+            }
+        ));
+
+        let factory: gtk::SignalListItemFactory = gtk::SignalListItemFactory::new();
+
+        factory.connect_bind(clone!(
+            #[weak]
+            obj,
+            move |_: &gtk::SignalListItemFactory, list_item: &glib::Object| {
+                let list_item: &gtk::ListItem = list_item.downcast_ref().unwrap();
+
+                let Some(item) = list_item.item().and_downcast::<MemoriesMediaItem>() else {
+                    return;
+                };
+                let imported_badge: gtk::Image = gtk::Image::builder()
+                    .icon_name("emblem-ok-symbolic")
+                    .tooltip_text(gettext("Already Imported"))
+                    .halign(gtk::Align::End)
+                    .valign(gtk::Align::End)
+                    .margin_end(6)
+                    .margin_bottom(6)
+                    .visible(obj.is_imported(&item.uri()))
+                    .css_classes(["osd", "circular"])
+                    .build();
+
+                let overlay: gtk::Overlay = gtk::Overlay::builder()
+                    .child(&Self::preview_widget(&item))
+                    .build();
+                overlay.add_overlay(&imported_badge);
+
+                obj.imp()
+                    .imported_badges
+                    .borrow_mut()
+                    .insert(item.uri(), imported_badge);
+                list_item.set_child(Some(&overlay));
+            }
+        ));
+        factory.connect_unbind(clone!(
+            #[weak]
+            obj,
+            move |_: &gtk::SignalListItemFactory, list_item: &glib::Object| {
+                let list_item: &gtk::ListItem = list_item.downcast_ref().unwrap();
+
+                if let Some(item) = list_item.item().and_downcast::<MemoriesMediaItem>() {
+                    obj.imp().imported_badges.borrow_mut().remove(&item.uri());
+                }
+                list_item.set_child(gtk::Widget::NONE);
+            }
+        ));
+
+        obj.imp().grid_view.set_factory(Some(&factory));
+        obj.imp().grid_view.set_model(Some(&selection_model));
+
+        list_model.connect_models_loaded_notify(clone!(
+            #[weak]
+            obj,
+            move |model: &MemoriesLibraryListModel| {
+                if model.models_loaded() {
+                    glib::spawn_future_local(clone!(
+                        #[weak]
+                        obj,
+                        async move { obj.check_imported().await }
+                    ));
+                }
+            }
+        ));
+        list_model.set_subdirectories(glib::StrV::from_iter(dcim_folders.iter().map(gio::File::uri)));
+        obj.imp().list_model.replace(Some(list_model));
+        obj
+    }
+
+    /// Returns whether the contents of the media at `uri` on the device are in the library.
+    fn is_imported(&self, uri: &str) -> bool {
+        self.imp()
+            .content_hashes
+            .borrow()
+            .get(uri)
+            .is_some_and(|hash: &String| self.imp().library_hashes.borrow().contains(hash))
+    }
+
+    fn update_imported_badge(&self, uri: &str) {
+        if let Some(imported_badge) = self.imp().imported_badges.borrow().get(uri) {
+            imported_badge.set_visible(self.is_imported(uri));
+        }
+    }
+
+    /// Returns the content hash of a media file on the device, which is read from
+    /// the device the first time, or `None` if the media could not be read.
+    async fn content_hash(&self, item: &MemoriesMediaItem) -> Option<String> {
+        let uri: String = item.uri();
+
+        if let Some(hash) = self.imp().content_hashes.borrow().get(&uri) {
+            return Some(hash.clone());
+        }
+        match content_hash::file_content_hash(&item.file(), item.size()).await {
+            Ok(hash) => {
+                self.imp().content_hashes.borrow_mut().insert(uri, hash.clone());
+                Some(hash)
+            }
+            Err(err) => {
+                g_warning!("DeviceBrowser", "Failed to read '{}': {}", uri, err);
+                None
+            }
+        }
+    }
+
+    /// Hashes the contents of every media file on the device, to mark the
+    /// media whose contents are already in the library as imported.
+    async fn check_imported(&self) {
+        let Some(list_model) = self.imp().list_model.borrow().clone() else {
+            return;
+        };
+        let items: Vec<MemoriesMediaItem> = list_model
+            .iter::<MemoriesMediaItem>()
+            .filter_map(Result::ok)
+            .collect();
+
+        for item in &items {
+            // Stop reading from the device once the browser is closed.
+            if self.parent().is_none() {
+                return;
+            }
+            if self.content_hash(item).await.is_some() {
+                self.update_imported_badge(&item.uri());
+            }
+        }
+    }
+
+    /// Creates the widget shown in a grid cell. A new widget is created on each
+    /// bind so that a preview that finishes loading late never lands in a cell
+    /// that was recycled for another item.
+    fn preview_widget(item: &MemoriesMediaItem) -> gtk::Widget {
+        let file: gio::File = item.file();
+        let content_type: ViewerContentType = PathBuf::from(item.basename().as_str())
+            .extension()
+            .map(ViewerContentType::from_ext)
+            .unwrap_or(ViewerContentType::Invalid);
+
+        let widget: gtk::Widget = match content_type {
+            ViewerContentType::Image | ViewerContentType::VectorGraphics => {
+                let picture: gtk::Picture = gtk::Picture::builder()
+                    .content_fit(gtk::ContentFit::Cover)
+                    .can_shrink(true)
+                    .build();
+                MemoriesJustifiedView::load_preview(&picture, &file);
+                picture.upcast()
+            }
+            _ => gtk::Image::builder()
+                .icon_name("video-x-generic-symbolic")
+                .icon_size(gtk::IconSize::Large)
+                .build()
+                .upcast(),
+        };
+        widget.set_size_request(128, 128);
+        widget.set_tooltip_text(Some(&item.basename()));
+        widget
+    }
+
+    /// Returns the items currently selected in the grid.
+    fn selected_items(&self) -> Vec<MemoriesMediaItem> {
+        let Some(model) = self.imp().grid_view.model() else {
+            return vec![];
+        };
+        let selection: gtk::Bitset = model.selection();
+
+        (0..selection.size())
+            .filter_map(|i: u64| model.item(selection.nth(i as u32)))
+            .filter_map(|item: glib::Object| item.downcast::<MemoriesMediaItem>().ok())
+            .collect()
+    }
+
+    /// Copies a file into a folder with the given name. The contents of the file are
+    /// not in the library, so if a different file has the name, such as a photo of
+    /// the same name from another camera, the copy is named with a number added.
+    async fn copy_unique(file: &gio::File, folder: &gio::File, name: &str) -> Result<(), glib::Error> {
+        let mut taken: HashSet<String> = HashSet::new();
+
+        loop {
+            let target: gio::File = folder.child(share_dialog::unique_name(name, &mut taken));
+            let (copy, _) = file.copy_future(&target, gio::FileCopyFlags::NONE, glib::Priority::LOW);
+
+            match copy.await {
+                Err(err) if err.matches(gio::IOErrorEnum::Exists) => continue,
+                result => return result,
+            }
+        }
+    }
+
+    /// Copies the selected items into the camera folder of the library. Items
+    /// whose contents are already in the library, under any name, are skipped.
+    #[template_callback]
+    fn import_clicked(&self) {
+        let items: Vec<MemoriesMediaItem> = self.selected_items();
+
+        let Some(pictures_dir) = glib::user_special_dir(glib::UserDirectory::Pictures) else {
+            g_warning!("DeviceBrowser", "XDG_PICTURES_DIR is not set.");
+            return;
+        };
+        let destination: gio::File = gio::File::for_path(pictures_dir.join(DEFAULT_CAMERA_REL_DIR));
+
+        self.imp().import_button.set_sensitive(false);
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let application: MemoriesApplication = MemoriesApplication::default();
+                let _job: RunningJob = application.start_job(&gettext("Importing media"));
+
+                let progress_bar: &gtk::ProgressBar = &this.imp().progress_bar;
+                progress_bar.set_fraction(0.0);
+                progress_bar.set_visible(true);
+
+                let mut imported: u32 = 0;
+                let mut skipped: u32 = 0;
+                let mut failed: u32 = 0;
+
+                if let Err(err) = destination.make_directory_with_parents(gio::Cancellable::NONE) {
+                    if !err.matches(gio::IOErrorEnum::Exists) {
+                        g_warning!("DeviceBrowser", "Failed to create import folder: {}", err);
+                    }
+                }
+
+                for (i, item) in items.iter().enumerate() {
+                    progress_bar.set_fraction(i as f64 / items.len() as f64);
+
+                    let file: gio::File = item.file();
+                    let hash: Option<String> = this.content_hash(item).await;
+
+                    if hash
+                        .as_ref()
+                        .is_some_and(|hash: &String| this.imp().library_hashes.borrow().contains(hash))
+                    {
+                        skipped += 1;
+                        continue;
+                    }
+                    let Some(basename) = file.basename() else {
+                        failed += 1;
+                        continue;
+                    };
+                    match Self::copy_unique(&file, &destination, &basename.to_string_lossy()).await {
+                        Ok(()) => {
+                            imported += 1;
+
+                            if let Some(hash) = hash {
+                                this.imp().library_hashes.borrow_mut().insert(hash);
+                                this.update_imported_badge(&item.uri());
+                            }
+                        }
+                        Err(err) => {
+                            g_warning!("DeviceBrowser", "Failed to import '{}': {}", file.uri(), err);
+                            failed += 1;
+                        }
+                    }
+                }
+                progress_bar.set_visible(false);
+
+                let message: String = if failed > 0 {
+                    ngettext_f(
+                        "Failed to import {COUNT} item",
+                        "Failed to import {COUNT} items",
+                        failed,
+                        &[("COUNT", &failed.to_string())],
+                    )
+                } else if imported == 0 && skipped > 0 {
+                    ngettext_f(
+                        "{COUNT} item was already imported",
+                        "{COUNT} items were already imported",
+                        skipped,
+                        &[("COUNT", &skipped.to_string())],
+                    )
+                } else {
+                    ngettext_f(
+                        "Imported {COUNT} item",
+                        "Imported {COUNT} items",
+                        imported,
+                        &[("COUNT", &imported.to_string())],
+                    )
+                };
+                this.imp().toast_overlay.add_toast(adw::Toast::new(&message));
+                this.imp().import_button.set_sensitive(true);
+
+                application.notify_job_finished(
+                    "import",
+                    &gettext("Import Finished"),
+                    &message,
+                    Some(&destination),
+                );
+            }
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Devices page, which lists the mounted removable devices that store
+//! photos and videos, such as cameras, memory cards, and phones connected
+//! over MTP or PTP, so that media on them can be browsed and imported
+//! without adding them to the library. Devices are only read from.
+
+mod browser;
+
+use crate::globals::DCIM_FOLDER_NAME;
+use crate::util::mounts;
+use crate::window::MemoriesApplicationWindow;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use browser::MemoriesDeviceBrowser;
+use glib::{clone, g_debug};
+use gtk::{gio, glib};
+
+mod imp {
+    use adw::prelude::*;
+    use adw::subclass::prelude::*;
+    use glib::clone;
+    use gtk::{gio, glib};
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/devices.ui")]
+    pub struct MemoriesDevicesView {
+        pub(super) volume_monitor: RefCell<Option<gio::VolumeMonitor>>,
+        pub(super) monitor_signals: RefCell<Vec<glib::SignalHandlerId>>,
+        #[template_child]
+        pub(super) devices_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) devices_list: TemplateChild<gtk::ListBox>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesDevicesView {
+        const NAME: &'static str = "MemoriesDevicesView";
+        type Type = super::MemoriesDevicesView;
+        type ParentType = adw::NavigationPage;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesDevicesView {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+
+            // The volume monitor must be kept alive for its signals to be emitted.
+            let volume_monitor: gio::VolumeMonitor = gio::VolumeMonitor::get();
+
+            let mount_added = volume_monitor.connect_mount_added(clone!(
+                #[weak]
+                obj,
+                move |_: &gio::VolumeMonitor, _: &gio::Mount| {
+                    obj.queue_refresh();
+                }
+            ));
+            let mount_removed = volume_monitor.connect_mount_removed(clone!(
+                #[weak]
+                obj,
+                move |_: &gio::VolumeMonitor, _: &gio::Mount| {
+                    obj.queue_refresh();
+                }
+            ));
+            self.monitor_signals.replace(vec![mount_added, mount_removed]);
+            self.volume_monitor.replace(Some(volume_monitor));
+
+            obj.queue_refresh();
+        }
+
+        fn dispose(&self) {
+            if let Some(volume_monitor) = self.volume_monitor.take() {
+                for handler_id in self.monitor_signals.take() {
+                    volume_monitor.disconnect(handler_id);
+                }
+            }
+        }
+    }
+
+    impl WidgetImpl for MemoriesDevicesView {}
+    impl NavigationPageImpl for MemoriesDevicesView {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesDevicesView(ObjectSubclass<imp::MemoriesDevicesView>)
+        @extends gtk::Widget, adw::NavigationPage;
+}
+
+impl MemoriesDevicesView {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    fn queue_refresh(&self) {
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                this.refresh().await;
+            }
+        ));
+    }
+
+    /// Lists the mounted removable devices that have a DCIM folder.
+    async fn refresh(&self) {
+        let mounts: Vec<gio::Mount> = gio::VolumeMonitor::get()
+            .mounts()
+            .into_iter()
+            .filter(|mount: &gio::Mount| mounts::is_removable_mount(mount) || mounts::is_device_mount(mount))
+            .collect();
+
+        let mut devices: Vec<(gio::Mount, Vec<gio::File>)> = vec![];
+
+        for mount in mounts {
+            let dcim_folders: Vec<gio::File> = Self::dcim_folders(&mount).await;
+
+            if !dcim_folders.is_empty() {
+                devices.push((mount, dcim_folders));
+            }
+        }
+
+        let devices_list: &gtk::ListBox = &self.imp().devices_list;
+        devices_list.remove_all();
+
+        for (mount, dcim_folders) in &devices {
+            devices_list.append(&self.device_row(mount, dcim_folders));
+        }
+        self.imp()
+            .devices_stack
+            .set_visible_child_name(if devices.is_empty() { "empty" } else { "devices" });
+    }
+
+    /// Returns the DCIM folders of a mount. Phones connected over MTP keep a DCIM
+    /// folder in each of their storages, such as their internal storage and SD card.
+    async fn dcim_folders(mount: &gio::Mount) -> Vec<gio::File> {
+        let root: gio::File = mount.root();
+        let mut folders: Vec<gio::File> = vec![root.clone()];
+
+        if mounts::is_device_mount(mount) {
+            match root
+                .enumerate_children_future(
+                    gio::FILE_ATTRIBUTE_STANDARD_NAME.as_str(),
+                    gio::FileQueryInfoFlags::NONE,
+                    glib::Priority::DEFAULT,
+                )
+                .await
+            {
+                Ok(enumerator) => {
+                    while let Ok(infos) = enumerator.next_files_future(32, glib::Priority::DEFAULT).await {
+                        if infos.is_empty() {
+                            break;
+                        }
+                        folders.extend(infos.iter().map(|info: &gio::FileInfo| root.child(info.name())));
+                    }
+                }
+                Err(err) => g_debug!(
+                    "DevicesView",
+                    "Failed to list storages of '{}': {}",
+                    mount.name(),
+                    err
+                ),
+            }
+        }
+
+        let mut dcim_folders: Vec<gio::File> = vec![];
+
+        for folder in folders {
+            let dcim_folder: gio::File = folder.child(DCIM_FOLDER_NAME);
+
+            match dcim_folder
+                .query_info_future(
+                    gio::FILE_ATTRIBUTE_STANDARD_TYPE.as_str(),
+                    gio::FileQueryInfoFlags::NONE,
+                    glib::Priority::DEFAULT,
+                )
+                .await
+            {
+                Ok(info) if info.file_type() == gio::FileType::Directory => dcim_folders.push(dcim_folder),
+                Ok(_) => (),
+                Err(err) => g_debug!("DevicesView", "No DCIM folder in '{}': {}", folder.uri(), err),
+            }
+        }
+        dcim_folders
+    }
+
+    fn device_row(&self, mount: &gio::Mount, dcim_folders: &[gio::File]) -> adw::ActionRow {
+        let name: String = mount.name().to_string();
+
+        let row: adw::ActionRow = adw::ActionRow::builder()
+            .title(glib::markup_escape_text(&name))
+            .subtitle(glib::markup_escape_text(&mount.root().parse_name()))
+            .activatable(true)
+            .build();
+
+        row.add_prefix(&gtk::Image::from_gicon(&mount.symbolic_icon()));
+        row.add_suffix(&gtk::Image::from_icon_name("go-next-symbolic"));
+
+        let dcim_folders: Vec<gio::File> = dcim_folders.to_vec();
+
+        row.connect_activated(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[strong]
+            dcim_folders,
+            move |_: &adw::ActionRow| {
+                let Some(win) = this.root().and_downcast::<MemoriesApplicationWindow>() else {
+                    return;
+                };
+                win.imp()
+                    .window_navigation
+                    .push(&MemoriesDeviceBrowser::new(&name, &dcim_folders));
+            }
+        ));
+        row
+    }
+}
+
+impl Default for MemoriesDevicesView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,308 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Folders page, which lists the library folders and the subfolders found in
+//! them, with the amount of media in each folder and its newest media as its
+//! cover. Folders are opened in a page that only shows the media in them.
+
+mod page;
+
+pub use page::MemoriesFolderPage;
+
+use crate::application::MemoriesApplication;
+use crate::globals::FOLDER_COVER_SIZE;
+use crate::i18n::ngettext_f;
+use crate::library::justified_view::MemoriesJustifiedView;
+use crate::library::list_model::MemoriesLibraryListModel;
+use crate::library::media_item::MemoriesMediaItem;
+use crate::window::MemoriesApplicationWindow;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::clone;
+use gtk::{gio, glib};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+mod imp {
+    use adw::prelude::*;
+    use adw::subclass::prelude::*;
+    use glib::clone;
+    use gtk::glib;
+    use std::cell::{Cell, RefCell};
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/folders.ui")]
+    pub struct MemoriesFoldersView {
+        #[template_child]
+        pub(super) folders_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) folders_page: TemplateChild<adw::PreferencesPage>,
+        /// Groups of the library folders that are currently listed.
+        pub(super) groups: RefCell<Vec<adw::PreferencesGroup>>,
+        /// Set once the page follows the changes of the library.
+        pub(super) connected: Cell<bool>,
+        /// Set if the library changed while the page was not shown.
+        pub(super) outdated: Cell<bool>,
+        pub(super) update_pending: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesFoldersView {
+        const NAME: &'static str = "MemoriesFoldersView";
+        type Type = super::MemoriesFoldersView;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesFoldersView {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+
+            // The library is only listed while the page is shown.
+            obj.connect_map(clone!(
+                #[weak]
+                obj,
+                move |_: &super::MemoriesFoldersView| obj.page_shown()
+            ));
+        }
+    }
+
+    impl WidgetImpl for MemoriesFoldersView {}
+    impl BinImpl for MemoriesFoldersView {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesFoldersView(ObjectSubclass<imp::MemoriesFoldersView>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+impl MemoriesFoldersView {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Lists the folders when the page is first shown, and follows the changes of
+    /// the library from then on, since the library model is created after the window.
+    fn page_shown(&self) {
+        if !self.imp().connected.replace(true) {
+            let library_model: MemoriesLibraryListModel = MemoriesApplication::default().library_list_model();
+
+            library_model.connect_items_changed(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &MemoriesLibraryListModel, _, _, _| this.library_changed()
+            ));
+            self.update_list();
+        } else if self.imp().outdated.get() {
+            self.update_list();
+        }
+    }
+
+    /// Lists the folders again once the main loop is idle if the page is shown,
+    /// so that it is only done once for a batch of changes, or once it is shown.
+    fn library_changed(&self) {
+        if !self.is_mapped() {
+            self.imp().outdated.set(true);
+            return;
+        }
+        if self.imp().update_pending.replace(true) {
+            return;
+        }
+        glib::idle_add_local_once(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move || {
+                this.imp().update_pending.set(false);
+                this.update_list();
+            }
+        ));
+    }
+
+    fn update_list(&self) {
+        self.imp().outdated.set(false);
+
+        let memories: MemoriesApplication = MemoriesApplication::default();
+        let library_model: MemoriesLibraryListModel = memories.library_list_model();
+
+        // Archived and private media are not counted, like they are hidden from the library.
+        let hidden: HashSet<String> = memories
+            .archived()
+            .iter()
+            .chain(memories.private().iter())
+            .map(|uri: &glib::GStringPtr| uri.to_string())
+            .collect();
+
+        let summaries: HashMap<String, (u32, gio::File)> = summarize_folders(
+            library_model
+                .iter::<MemoriesMediaItem>()
+                .filter_map(Result::ok)
+                .map(|item: MemoriesMediaItem| item.file())
+                .filter(|file: &gio::File| !hidden.contains(file.uri().as_str()))
+                .filter_map(|file: gio::File| Some((file.parent()?.uri().to_string(), file))),
+        );
+
+        for group in self.imp().groups.take() {
+            self.imp().folders_page.remove(&group);
+        }
+        let folders: Vec<(gio::File, Vec<(gio::File, bool)>)> = library_model.folders();
+
+        for (root, subfolders) in &folders {
+            let group: adw::PreferencesGroup = adw::PreferencesGroup::builder()
+                .title(glib::markup_escape_text(&folder_name(root)))
+                .description(glib::markup_escape_text(&root.parse_name()))
+                .build();
+
+            group.add(&self.create_folder_row(root, summaries.get(root.uri().as_str()), true));
+
+            for (subfolder, enumerated) in subfolders {
+                let summary: Option<&(u32, gio::File)> = summaries.get(subfolder.uri().as_str());
+                group.add(&self.create_folder_row(subfolder, summary, *enumerated));
+            }
+            self.imp().folders_page.add(&group);
+            self.imp().groups.borrow_mut().push(group);
+        }
+        self.imp()
+            .folders_stack
+            .set_visible_child_name(if folders.is_empty() { "empty" } else { "folders" });
+    }
+
+    /// Creates the row of a folder, which shows the amount of media in the
+    /// folder and its cover, and opens the folder in its own page.
+    fn create_folder_row(
+        &self,
+        folder: &gio::File,
+        summary: Option<&(u32, gio::File)>,
+        enumerated: bool,
+    ) -> adw::ActionRow {
+        let subtitle: String = match summary {
+            Some((count, _)) => ngettext_f(
+                "{COUNT} item",
+                "{COUNT} items",
+                *count,
+                &[("COUNT", &count.to_string())],
+            ),
+            None if enumerated => gettext("No media"),
+            None => gettext("Not loaded yet"),
+        };
+        let row: adw::ActionRow = adw::ActionRow::builder()
+            .title(glib::markup_escape_text(&folder_name(folder)))
+            .subtitle(subtitle)
+            .activatable(true)
+            .build();
+
+        // Folders without media show a folder icon in place of their cover.
+        if let Some((_, cover)) = summary {
+            let picture: gtk::Picture = gtk::Picture::builder()
+                .content_fit(gtk::ContentFit::Cover)
+                .width_request(FOLDER_COVER_SIZE)
+                .height_request(FOLDER_COVER_SIZE)
+                .valign(gtk::Align::Center)
+                .build();
+
+            MemoriesJustifiedView::load_preview(&picture, cover);
+            row.add_prefix(&picture);
+        } else {
+            let icon: gtk::Image = gtk::Image::from_icon_name("folder-symbolic");
+            icon.set_pixel_size(FOLDER_COVER_SIZE / 2);
+            icon.set_size_request(FOLDER_COVER_SIZE, FOLDER_COVER_SIZE);
+            row.add_prefix(&icon);
+        }
+        row.add_suffix(&gtk::Image::from_icon_name("go-next-symbolic"));
+
+        row.connect_activated(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[strong]
+            folder,
+            move |_: &adw::ActionRow| this.open_folder(&folder)
+        ));
+        row
+    }
+
+    /// Opens a folder in a page that only shows the media in the folder.
+    /// Folders that are enumerated on demand are enumerated once opened.
+    fn open_folder(&self, folder: &gio::File) {
+        let Some(win) = self.root().and_downcast::<MemoriesApplicationWindow>() else {
+            return;
+        };
+        MemoriesApplication::default()
+            .library_list_model()
+            .enumerate_folder(folder);
+
+        win.imp().window_navigation.push(&MemoriesFolderPage::new(folder));
+    }
+}
+
+impl Default for MemoriesFoldersView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the name of a folder to show, which is its display name.
+fn folder_name(folder: &gio::File) -> String {
+    folder
+        .basename()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| folder.parse_name().to_string())
+}
+
+/// Counts the media in each folder, keeping the first media of each folder as
+/// its cover. The library is sorted newest first, so covers are the newest media.
+fn summarize_folders<K: Eq + Hash, V>(media: impl IntoIterator<Item = (K, V)>) -> HashMap<K, (u32, V)> {
+    let mut summaries: HashMap<K, (u32, V)> = HashMap::new();
+
+    for (folder, item) in media {
+        summaries
+            .entry(folder)
+            .and_modify(|(count, _): &mut (u32, V)| *count += 1)
+            .or_insert((1, item));
+    }
+    summaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folders_are_counted_with_their_first_media_as_cover() {
+        let summaries: HashMap<&str, (u32, &str)> = summarize_folders([
+            ("Pictures", "new.jpg"),
+            ("Pictures/Trip", "beach.jpg"),
+            ("Pictures", "old.jpg"),
+            ("Pictures/Trip", "hotel.jpg"),
+            ("Pictures/Trip", "airport.jpg"),
+        ]);
+
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries["Pictures"], (2, "new.jpg"));
+        assert_eq!(summaries["Pictures/Trip"], (3, "beach.jpg"));
+    }
+}
@@ -0,0 +1,132 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::application::MemoriesApplication;
+use crate::library::hidden_filter_func;
+use crate::library::media_item::MemoriesMediaItem;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::clone;
+use gtk::{gio, glib};
+
+mod imp {
+    use crate::library::justified_view::MemoriesJustifiedView;
+    use adw::subclass::prelude::*;
+    use gtk::glib;
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/folder-page.ui")]
+    pub struct MemoriesFolderPage {
+        #[template_child]
+        pub(super) folder_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) justified_view: TemplateChild<MemoriesJustifiedView>,
+        pub(super) model: RefCell<Option<gtk::FilterListModel>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesFolderPage {
+        const NAME: &'static str = "MemoriesFolderPage";
+        type Type = super::MemoriesFolderPage;
+        type ParentType = adw::NavigationPage;
+
+        fn class_init(klass: &mut Self::Class) {
+            MemoriesJustifiedView::ensure_type();
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesFolderPage {}
+    impl WidgetImpl for MemoriesFolderPage {}
+    impl NavigationPageImpl for MemoriesFolderPage {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesFolderPage(ObjectSubclass<imp::MemoriesFolderPage>)
+        @extends gtk::Widget, adw::NavigationPage;
+}
+
+impl MemoriesFolderPage {
+    /// Creates a page that shows the media directly in the given folder, by
+    /// filtering the library model, so the page follows changes to the library.
+    pub fn new(folder: &gio::File) -> Self {
+        let title: String = folder
+            .basename()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let folder: gio::File = folder.clone();
+
+        Self::with_filter(&title, move |item: &MemoriesMediaItem| {
+            item.file()
+                .parent()
+                .is_some_and(|parent: gio::File| parent.equal(&folder))
+        })
+    }
+
+    /// Creates a page titled `title` that shows the library media
+    /// for which `filter_func` returns `true`, such as the media of a year.
+    pub fn with_filter(title: &str, filter_func: impl Fn(&MemoriesMediaItem) -> bool + 'static) -> Self {
+        let page: Self = glib::Object::new();
+
+        if !title.is_empty() {
+            page.set_title(title);
+        }
+        let memories: MemoriesApplication = MemoriesApplication::default();
+        let filter: gtk::EveryFilter = gtk::EveryFilter::new();
+
+        filter.append(gtk::CustomFilter::new(move |item: &glib::Object| {
+            item.downcast_ref::<MemoriesMediaItem>().is_some_and(&filter_func)
+        }));
+        // Archived and private media are hidden, like they are from the library.
+        filter.append(gtk::CustomFilter::new(hidden_filter_func(&memories.archived())));
+        filter.append(gtk::CustomFilter::new(hidden_filter_func(&memories.private())));
+
+        let model: gtk::FilterListModel =
+            gtk::FilterListModel::new(Some(memories.library_list_model()), Some(filter));
+
+        model.connect_items_changed(clone!(
+            #[weak]
+            page,
+            move |_: &gtk::FilterListModel, _, _, _| page.update_empty_state()
+        ));
+        page.imp().justified_view.bind_model(Some(model.upcast_ref()));
+        page.imp().model.replace(Some(model));
+        page.update_empty_state();
+        page
+    }
+
+    fn update_empty_state(&self) {
+        let is_empty: bool = self
+            .imp()
+            .model
+            .borrow()
+            .as_ref()
+            .map_or(true, |model: &gtk::FilterListModel| model.n_items() == 0);
+
+        self.imp()
+            .folder_stack
+            .set_visible_child_name(if is_empty { "empty" } else { "media" });
+    }
+}
@@ -21,6 +21,8 @@
 use crate::util::enums::XDGUserDir;
 use cfg_if::cfg_if;
 use gtk::glib::source::Priority;
+use gtk::glib::UserDirectory;
+use std::time::Duration;
 
 #[cfg(debug_assertions)]
 pub static DEVELOPMENT_BUILD: bool = true;
@@ -40,25 +42,205 @@ cfg_if! {
 /// Can be read by other modules to display to the user
 /// what binary needs to be installed to use Memories.
 pub static FFMPEG_BINARY: &str = "ffmpeg";
+/// Used to find the duration of videos, to show the progress of compressing them.
+pub static FFPROBE_BINARY: &str = "ffprobe";
+
+/// Programs used to lower the CPU and I/O priority of thumbnail processes.
+pub static NICE_BINARY: &str = "nice";
+pub static IONICE_BINARY: &str = "ionice";
+/// Niceness that thumbnail processes are run with. Higher is lower priority.
+pub static THUMBNAIL_NICE_LEVEL: i32 = 10;
 
 cfg_if! {
     // We're gonna assume that if we're targeting ARM,
     // we are targeting mobile devices.
     if #[cfg(target_arch = "aarch64")] {
-        /// The number of permits given to the async semaphore
-        /// used to control the amount of ffmpeg processes spawned.
+        /// The default number of permits given to the async semaphore used to
+        /// control the amount of ffmpeg processes spawned. Can be changed in
+        /// the preferences.
         pub static FFMPEG_CONCURRENT_PROCESSES: usize = 2;
     } else {
         pub static FFMPEG_CONCURRENT_PROCESSES: usize = 5;
     }
 }
 
+/// The maximum number of ffmpeg processes that can be set in the preferences.
+pub static FFMPEG_MAX_CONCURRENT_PROCESSES: usize = 16;
+/// The number of ffmpeg processes spawned at once while power saver mode is active.
+pub static FFMPEG_POWER_SAVER_PROCESSES: usize = 1;
+/// The number of ffmpeg processes spawned at once for media on spinning hard
+/// drives and removable media, which slow down when reading several files at once.
+pub static STORAGE_SEEKING_PROCESSES: usize = 1;
+/// The number of ffmpeg processes spawned at once for media on network locations.
+pub static STORAGE_NETWORK_PROCESSES: usize = 2;
+/// The maximum number of library items indexed at once by the scan service.
+/// Fewer items are indexed at once on machines with fewer cores.
+pub static SCAN_MAX_CONCURRENT_ITEMS: usize = 8;
+
+/// D-Bus name, object path, and interface of the UPower daemon, which
+/// is used to pause indexing the library while running on battery.
+pub static UPOWER_BUS_NAME: &str = "org.freedesktop.UPower";
+pub static UPOWER_OBJECT_PATH: &str = "/org/freedesktop/UPower";
+pub static UPOWER_INTERFACE: &str = "org.freedesktop.UPower";
+
+/// D-Bus name, object path, and interface of the document portal, which shares
+/// folders chosen through the file chooser portal with sandboxed applications.
+pub static DOCUMENT_PORTAL_BUS_NAME: &str = "org.freedesktop.portal.Documents";
+pub static DOCUMENT_PORTAL_OBJECT_PATH: &str = "/org/freedesktop/portal/documents";
+pub static DOCUMENT_PORTAL_INTERFACE: &str = "org.freedesktop.portal.Documents";
+/// Name of the document portal's mount point in `$XDG_RUNTIME_DIR`, which
+/// is used if the mount point cannot be looked up from the portal.
+pub static DOCUMENT_PORTAL_FALLBACK_DIR: &str = "doc";
+
+/// D-Bus name and object path of the desktop portal, and the interface
+/// of the wallpaper portal that the wallpaper slideshow is shown with.
+pub static DESKTOP_PORTAL_BUS_NAME: &str = "org.freedesktop.portal.Desktop";
+pub static DESKTOP_PORTAL_OBJECT_PATH: &str = "/org/freedesktop/portal/desktop";
+pub static WALLPAPER_PORTAL_INTERFACE: &str = "org.freedesktop.portal.Wallpaper";
+/// Interface of the background portal, which allows running after the window is closed.
+pub static BACKGROUND_PORTAL_INTERFACE: &str = "org.freedesktop.portal.Background";
+/// Interface and object path prefix of the requests made to portals,
+/// which signal the response of the user once they are answered.
+pub static PORTAL_REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+pub static PORTAL_REQUEST_OBJECT_PATH: &str = "/org/freedesktop/portal/desktop/request";
+
 /// IO priority for new `GtkDirectoryList` models. We override
 /// the default since it is usually higher than GTK redraw priority.
 pub static DIRECTORY_MODEL_PRIORITY: Priority = Priority::LOW;
+/// File attributes queried by `GtkDirectoryList` models, which media items are
+/// created from. The modification time is used to sort the library when enumerating
+/// in sorted mode, and both times are shown in the properties of the media.
+pub static DIRECTORY_MODEL_ATTRIBUTES: &str = "standard::*,time::modified,time::created";
+/// Amount of bytes read from the start of a media file to tell its content
+/// type from its contents, when its file extension is missing or unknown.
+pub static CONTENT_SNIFF_BYTES: usize = 4096;
+/// Extensions of photo formats that are thumbnailed with glycin,
+/// since ffmpeg and gdk-pixbuf can often not decode them.
+pub static GLYCIN_THUMBNAIL_EXTENSIONS: &[&str] = &["avif", "jxl"];
+/// Amount of recently used folders that are enumerated at start up
+/// even if library subfolders are enumerated on demand.
+pub static RECENT_FOLDERS_LIMIT: usize = 10;
 
 /// The following statics are related to the application cache.
 pub static CACHE_THUMBNAILS_SUBDIR: &str = "thumbnails";
+/// Version of the keys that thumbnails are cached by. Thumbnails cached by an older
+/// version are never looked up again, so they are removed once this is increased.
+pub static THUMBNAIL_CACHE_VERSION: i32 = 1;
+/// File in the app cache directory that the low fidelity previews of media are stored in.
+pub static CACHE_PREVIEW_INDEX_FILE: &str = "previews.json";
+/// File in the app cache directory that the text recognized in screenshots is stored in.
+pub static CACHE_TEXT_INDEX_FILE: &str = "text.json";
+/// File in the app cache directory that the image embeddings of media are stored in.
+pub static CACHE_EMBEDDING_INDEX_FILE: &str = "embeddings.json";
+/// File in the app cache directory that the perceptual hashes of photos are stored in.
+pub static CACHE_PERCEPTUAL_HASH_INDEX_FILE: &str = "hashes.json";
+/// File in the app cache directory that the content hashes of media are stored in.
+pub static CACHE_CONTENT_HASH_INDEX_FILE: &str = "content-hashes.json";
+/// File in the app cache directory that a report is written to when Memories crashes.
+pub static CACHE_CRASH_REPORT_FILE: &str = "crash-report.txt";
+/// Seconds to wait for more previews before the preview index is saved to disk.
+pub static PREVIEW_INDEX_SAVE_DELAY: u32 = 5;
+/// Milliseconds to wait for more changes to a media file opened in an external
+/// editor before the viewer reloads it, as editors may write a file in steps.
+pub static EXTERNAL_EDIT_RELOAD_DELAY: u64 = 500;
+
+/// Edge lengths in pixels of the square thumbnails generated for the media grid.
+/// The grid requests the smallest size that covers a cell at the display scale.
+pub static THUMBNAIL_SIZES: &[u32] = &[128, 256, 512];
+/// Bytes read from the start of a JPEG file on a network location to find the
+/// thumbnail embedded in its EXIF data, instead of reading the whole file.
+pub static REMOTE_THUMBNAIL_READ_SIZE: u64 = 64 * 1024;
+/// Bytes read from the start of a media file to find its EXIF original date.
+pub static EXIF_DATE_READ_SIZE: u64 = 64 * 1024;
+/// Bytes read from the start of a photo to find its camera, dimensions,
+/// and location in its EXIF data.
+pub static EXIF_DETAILS_READ_SIZE: u64 = 64 * 1024;
+
+/// Images with more pixels than this are drawn by the viewer with a tiled
+/// paintable, which decodes only the visible regions of the image on demand.
+pub static TILED_RENDERING_MIN_PIXELS: u64 = 50_000_000;
+/// Edge length in pixels of the tiles decoded for tiled rendering.
+pub static TILED_RENDERING_TILE_SIZE: u32 = 512;
+/// Maximum amount of decoded tiles kept in memory per tiled image.
+pub static TILED_RENDERING_MAX_TILES: usize = 64;
+/// Maximum edge length in pixels of the preview drawn below the tiles.
+pub static TILED_RENDERING_PREVIEW_SIZE: u32 = 2048;
+
+/// Maximum zoom level of the media viewer, where 1.0 is the actual size.
+pub static VIEWER_MAX_ZOOM: f64 = 4.0;
+/// Factor that the zoom level is multiplied or divided by per zoom step.
+pub static VIEWER_ZOOM_STEP: f64 = 1.25;
+/// Mouse buttons that browsers and file managers use to go back and forward.
+pub static MOUSE_BACK_BUTTON: u32 = 8;
+pub static MOUSE_FORWARD_BUTTON: u32 = 9;
+/// Distance, in logical pixels, that a two-finger swipe on a touchpad
+/// must travel horizontally to show the previous or next media.
+pub static VIEWER_SWIPE_DISTANCE: f64 = 120.0;
+/// Velocity, in logical pixels per second, that a three-finger
+/// swipe on a touchpad must reach to leave the media viewer.
+pub static VIEWER_SWIPE_BACK_VELOCITY: f64 = 500.0;
+/// Minimum ratio of the width to the height of an image for the
+/// viewer to offer showing it as a panorama.
+pub static PANORAMA_MIN_ASPECT_RATIO: f64 = 2.5;
+/// Speed, in logical pixels per second, that panoramas are scrolled at automatically.
+pub static PANORAMA_SCROLL_SPEED: f64 = 80.0;
+/// Vertical field of view, in degrees, that 360° photos are first shown with.
+pub static PHOTO_SPHERE_DEFAULT_FOV: f64 = 75.0;
+/// Narrowest vertical field of view, in degrees, that 360° photos can be zoomed in to.
+pub static PHOTO_SPHERE_MIN_FOV: f64 = 30.0;
+/// Widest vertical field of view, in degrees, that 360° photos can be zoomed out to.
+pub static PHOTO_SPHERE_MAX_FOV: f64 = 120.0;
+
+/// Address that SSDP requests are multicast to, to find devices to cast to.
+pub static SSDP_MULTICAST_ADDRESS: &str = "239.255.255.250:1900";
+/// Time that the local network is searched for devices to cast to.
+pub static CAST_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+/// Time to wait for a device that media is cast to before giving up.
+pub static CAST_NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Maximum amount of connections that the media server answers at once.
+/// Renderers only open a few at once, to seek in videos while playing them.
+pub static CAST_MAX_CONNECTIONS: usize = 8;
+/// Maximum length in bytes of the request line and of each header line
+/// of a request to the media server. Longer requests are refused.
+pub static CAST_MAX_LINE_LENGTH: u64 = 8 * 1024;
+/// Maximum amount of header lines of a request to the media server.
+pub static CAST_MAX_HEADERS: usize = 64;
+
+/// Maximum amount of bits that the perceptual hashes of two photos
+/// can differ by for the photos to be shown as similar in the viewer.
+pub static SIMILAR_MAX_DISTANCE: u32 = 10;
+/// Maximum amount of similar photos shown in the viewer.
+pub static SIMILAR_MAX_RESULTS: usize = 24;
+/// Edge length in pixels of the thumbnails of similar photos shown in the viewer.
+pub static SIMILAR_THUMBNAIL_SIZE: i32 = 96;
+
+/// File name of the face detection model, installed in the package data directory.
+pub static FACE_DETECTION_MODEL_FILE: &str = "face-detection.bin";
+/// Images are scaled down so that their longest edge is at most this many pixels to detect faces.
+pub static FACE_DETECTION_MAX_EDGE: u32 = 1024;
+/// Edge length in pixels of the smallest face detected in the scaled down image.
+pub static FACE_DETECTION_MIN_FACE_SIZE: u32 = 40;
+/// Maximum descriptor distance between a face and the average face of
+/// a person for the face to be grouped with that person.
+pub static FACE_MATCH_THRESHOLD: f32 = 0.35;
+/// Edge length in pixels of the face thumbnails shown on the People page.
+pub static FACE_THUMBNAIL_SIZE: u32 = 128;
+
+/// Tesseract languages that text in screenshots is recognized in, joined by `+`.
+pub static OCR_LANGUAGES: &str = "eng";
+
+/// File names of the image and text encoders of the embedding
+/// model, and its tokenizer, installed in the package data directory.
+pub static EMBEDDING_IMAGE_MODEL_FILE: &str = "embedding-image.onnx";
+pub static EMBEDDING_TEXT_MODEL_FILE: &str = "embedding-text.onnx";
+pub static EMBEDDING_TOKENIZER_FILE: &str = "embedding-tokenizer.json";
+/// Edge length in pixels of the square images that the image encoder takes.
+pub static EMBEDDING_IMAGE_SIZE: usize = 224;
+/// Amount of tokens that the text encoder takes. Longer queries are truncated.
+pub static EMBEDDING_TEXT_LENGTH: usize = 77;
+/// Minimum similarity between the embeddings of media and of a search query for the media
+/// to match the query. Similarities of related images and text are low with CLIP models.
+pub static EMBEDDING_MATCH_THRESHOLD: f32 = 0.24;
 
 /// The following statics are related to XDG user directories.
 /// These strings are paths relative to $HOME.
@@ -68,16 +250,96 @@ pub static FALLBACK_XDG_VIDEOS_DIR: &str = "Videos";
 /// Default library list model root directories. Must be XDG user directories.
 pub static DEFAULT_LIBRARY_COLLECTION: &[XDGUserDir] = &[XDGUserDir::Pictures, XDGUserDir::Videos];
 
+/// Folders suggested on the onboarding page if they have media in them.
+pub static ONBOARDING_SUGGESTED_DIRS: &[UserDirectory] = &[
+    UserDirectory::Downloads,
+    UserDirectory::Desktop,
+    UserDirectory::Documents,
+];
+/// Amount of files checked in a suggested folder before giving up on finding media.
+pub static ONBOARDING_MAX_FILES_CHECKED: i32 = 200;
+
 /// Following paths relative to DEFAULT_LIBRARY_DIRECTORY.
 pub static DEFAULT_SCREENSHOTS_REL_DIR: &str = "Screenshots";
 pub static DEFAULT_CAMERA_REL_DIR: &str = "Camera";
 
+/// Name of the folder that cameras store photos and videos in, as in the
+/// Design rule for Camera File system (DCF). Used to find removable devices.
+pub static DCIM_FOLDER_NAME: &str = "DCIM";
+
+/// Maximum amount of characters shown for the folder path in the
+/// media properties widget. Longer paths are truncated at the start.
+pub static PROPERTIES_FOLDER_MAX_CHARS: usize = 32;
+
+/// Resize presets offered by the export dialog. Values are the maximum
+/// length, in pixels, of the longest edge of the exported image.
+pub static EXPORT_RESIZE_PRESETS: &[Option<u32>] = &[None, Some(3840), Some(1920), Some(1280), Some(640)];
+
+/// JPEG quality, from 1 to 100, of photos downscaled while exported for sharing.
+pub static SHARE_JPEG_QUALITY: u32 = 90;
+/// Template passed to `g_mkdtemp()` for the folder that files are staged
+/// in while they are added to a zip archive exported for sharing.
+pub static SHARE_STAGING_DIR_TEMPLATE: &str = "memories-share-XXXXXX";
+/// Edge length in pixels of the thumbnails of web galleries exported for sharing.
+pub static WEB_GALLERY_THUMBNAIL_SIZE: u32 = 480;
+/// Folders of web galleries that the media and its thumbnails are exported into.
+pub static WEB_GALLERY_MEDIA_DIR: &str = "media";
+pub static WEB_GALLERY_THUMBNAILS_DIR: &str = "thumbnails";
+
+/// Frame rate of slideshow videos, in frames per second.
+pub static SLIDESHOW_FRAME_RATE: u32 = 30;
+/// Seconds that transitions between photos in slideshow videos last.
+pub static SLIDESHOW_TRANSITION_SECONDS: f64 = 1.0;
+/// Seconds that background music fades out for at the end of slideshow videos.
+pub static SLIDESHOW_MUSIC_FADE_SECONDS: f64 = 2.0;
+
+/// Video bitrates, in kilobits per second, of the quality presets of the
+/// compress video dialog, from the highest to the lowest quality.
+pub static COMPRESS_VIDEO_BITRATES: &[u32] = &[4000, 2000, 1000];
+/// Audio bitrate, in kilobits per second, of compressed videos.
+pub static COMPRESS_AUDIO_BITRATE: u32 = 128;
+
+/// Maximum seconds between neighboring photos with sequential file
+/// names for them to be grouped into a burst in the library.
+pub static BURST_MAX_INTERVAL: i64 = 60;
+/// Edge length in pixels of the thumbnails of the photos in the burst dialog.
+pub static BURST_FRAME_THUMBNAIL_SIZE: i32 = 128;
+/// Edge length in pixels of the covers of folders in the Folders page.
+pub static FOLDER_COVER_SIZE: i32 = 48;
+
+/// Edge length in pixels of the thumbnails of the media listed on the Archive page.
+pub static ARCHIVE_THUMBNAIL_SIZE: i32 = 48;
+
+/// Edge length in pixels of the thumbnails of the media listed in the Private album.
+pub static PRIVATE_THUMBNAIL_SIZE: i32 = 48;
+
 /// Default `height-request` used in list item widgets
 /// displayed on the library grid view on mobile.
 pub static DEFAULT_GRID_WIDGET_HEIGHT: i32 = 66;
 
-/// Grid zoom levels are tuples where the first integer is the amount
-/// of min/max columns in the `GtkGridView`, and the second integer
-/// is the value to set on the `grid-widget-height` property of the media grid view.
-pub static GRID_MOBILE_ZOOM_LEVELS: &[(u32, i32)] = &[(5, 66), (3, 114), (2, 173)];
-pub static GRID_DESKTOP_ZOOM_LEVELS: &[(u32, i32)] = &[(10, 112), (5, 234)];
+/// Grid zoom levels are the target sizes, in logical pixels, of cells in the
+/// library grid view. The amount of grid columns is derived from the available
+/// width, so that cells are as close as possible to the target size.
+pub static GRID_ZOOM_LEVELS: &[i32] = &[64, 96, 128, 192, 256];
+/// Index in `GRID_ZOOM_LEVELS` of the zoom level that the grid starts at.
+pub static DEFAULT_GRID_ZOOM_LEVEL: usize = 1;
+/// Horizontal space, in logical pixels, taken by the padding of a grid cell.
+pub static GRID_CELL_PADDING: i32 = 6;
+/// Height in pixels of the previews shown in the justified library layout.
+pub static JUSTIFIED_PREVIEW_HEIGHT: i32 = 256;
+/// Spacing in logical pixels between items of the justified library layout.
+pub static JUSTIFIED_LAYOUT_SPACING: i32 = 4;
+/// Factor that a pinch gesture on the grid has to scale by, relative to the
+/// scale of the last zoom step, to step to the next grid zoom level.
+pub static GRID_PINCH_ZOOM_STEP: f64 = 1.3;
+/// Rows of thumbnails generated ahead of the direction that the library
+/// grid is scrolled in, so that they are cached before they become visible.
+pub static GRID_PREFETCH_ROWS: u32 = 4;
+/// Distance in pixels that the library has to be pulled down
+/// from the top with a touchscreen to refresh the library.
+pub static PULL_TO_REFRESH_DISTANCE: f64 = 96.0;
+
+/// Number of cameras, folders, and files listed in each ranking of the Statistics page.
+pub static STATISTICS_RANKING_SIZE: usize = 10;
+/// Width in pixels of the bars of the charts on the Statistics page.
+pub static STATISTICS_BAR_WIDTH: i32 = 120;
@@ -0,0 +1,212 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::application::MemoriesApplication;
+use crate::globals::BURST_FRAME_THUMBNAIL_SIZE;
+use crate::i18n::ngettext_f;
+use crate::library::justified_view::MemoriesJustifiedView;
+use crate::util::feedback::{self, FeedbackEvent};
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::{clone, g_warning};
+use gtk::{gio, glib};
+
+mod imp {
+    use adw::subclass::prelude::*;
+    use gtk::{gio, glib};
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/burst-dialog.ui")]
+    pub struct MemoriesBurstDialog {
+        pub(super) frames: RefCell<Vec<gio::File>>,
+        #[template_child]
+        pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) frames_box: TemplateChild<gtk::FlowBox>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesBurstDialog {
+        const NAME: &'static str = "MemoriesBurstDialog";
+        type Type = super::MemoriesBurstDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesBurstDialog {}
+    impl WidgetImpl for MemoriesBurstDialog {}
+    impl AdwDialogImpl for MemoriesBurstDialog {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesBurstDialog(ObjectSubclass<imp::MemoriesBurstDialog>)
+        @extends gtk::Widget, adw::Dialog;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesBurstDialog {
+    /// Creates a new dialog that shows every frame of a burst, with
+    /// its cover selected, to pick the best frame of the burst.
+    pub fn new(frames: &[gio::File], cover: &gio::File) -> Self {
+        let obj: Self = glib::Object::new();
+        let frames_box: &gtk::FlowBox = &obj.imp().frames_box;
+
+        for file in frames {
+            let basename: String = file
+                .basename()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            let picture: gtk::Picture = gtk::Picture::builder()
+                .content_fit(gtk::ContentFit::Cover)
+                .width_request(BURST_FRAME_THUMBNAIL_SIZE)
+                .height_request(BURST_FRAME_THUMBNAIL_SIZE)
+                .tooltip_text(&basename)
+                .build();
+            picture.update_property(&[gtk::accessible::Property::Label(&basename)]);
+            MemoriesJustifiedView::load_preview(&picture, file);
+
+            frames_box.append(&picture);
+
+            if file.equal(cover) {
+                if let Some(child) = frames_box.last_child().and_downcast::<gtk::FlowBoxChild>() {
+                    frames_box.select_child(&child);
+                }
+            }
+        }
+        obj.imp().frames.replace(frames.to_vec());
+        obj
+    }
+
+    /// Returns the frame selected as the best frame of the burst.
+    fn picked_frame(&self) -> Option<gio::File> {
+        let child: gtk::FlowBoxChild = self.imp().frames_box.selected_children().into_iter().next()?;
+
+        self.imp().frames.borrow().get(child.index() as usize).cloned()
+    }
+
+    fn frame_uris(&self) -> Vec<String> {
+        self.imp()
+            .frames
+            .borrow()
+            .iter()
+            .map(|file: &gio::File| file.uri().to_string())
+            .collect()
+    }
+
+    #[template_callback]
+    fn pick_clicked(&self) {
+        if let Some(pick) = self.picked_frame() {
+            MemoriesApplication::default().pick_burst_frame(&self.frame_uris(), &pick);
+        }
+        self.close();
+    }
+
+    /// Asks to move every frame of the burst but the selected one to the trash.
+    #[template_callback]
+    fn trash_others_clicked(&self) {
+        let Some(pick) = self.picked_frame() else {
+            return;
+        };
+        let others: Vec<gio::File> = self
+            .imp()
+            .frames
+            .borrow()
+            .iter()
+            .filter(|file: &&gio::File| !file.equal(&pick))
+            .cloned()
+            .collect();
+        let count: usize = others.len();
+
+        let dialog: adw::AlertDialog = adw::AlertDialog::builder()
+            .heading(gettext("Move Other Photos to Trash?"))
+            .body(ngettext_f(
+                "The {COUNT} other photo of this burst will be moved to the trash.",
+                "The {COUNT} other photos of this burst will be moved to the trash.",
+                count.try_into().unwrap_or(u32::MAX),
+                &[("COUNT", &count.to_string())],
+            ))
+            .default_response("cancel")
+            .close_response("cancel")
+            .build();
+
+        dialog.add_responses(&[
+            ("cancel", &gettext("_Cancel")),
+            ("trash", &gettext("_Move to Trash")),
+        ]);
+        dialog.set_response_appearance("trash", adw::ResponseAppearance::Destructive);
+
+        dialog.connect_response(
+            Some("trash"),
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &adw::AlertDialog, _: &str| {
+                    glib::spawn_future_local(clone!(
+                        #[weak]
+                        this,
+                        #[strong]
+                        pick,
+                        #[strong]
+                        others,
+                        async move { this.trash_others(&pick, &others).await }
+                    ));
+                }
+            ),
+        );
+        dialog.present(Some(self));
+    }
+
+    /// Picks the best frame of the burst, then moves the other frames to the trash.
+    async fn trash_others(&self, pick: &gio::File, others: &[gio::File]) {
+        let memories: MemoriesApplication = MemoriesApplication::default();
+        let mut failed: u32 = 0;
+
+        memories.pick_burst_frame(&self.frame_uris(), pick);
+
+        for file in others {
+            if let Err(err) = memories.trash_file(file).await {
+                g_warning!("BurstDialog", "Failed to move '{}' to trash: {}", file.uri(), err);
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            self.imp().toast_overlay.add_toast(adw::Toast::new(&ngettext_f(
+                "Failed to move {COUNT} photo to the trash",
+                "Failed to move {COUNT} photos to the trash",
+                failed,
+                &[("COUNT", &failed.to_string())],
+            )));
+            return;
+        }
+        feedback::emit(FeedbackEvent::DeleteConfirmed);
+        self.close();
+    }
+}
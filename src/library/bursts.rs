@@ -0,0 +1,187 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Detection of bursts, which are photos taken in rapid sequence. Neighboring
+//! items in the library are frames of the same burst if they are in the same
+//! folder, have sequential file names, such as `IMG_0041.JPG` and `IMG_0042.JPG`,
+//! and were taken within `BURST_MAX_INTERVAL` seconds of each other.
+//!
+//! Bursts are collapsed into their cover frame in the library, which is the
+//! frame picked as the best one by the user, or else the first frame.
+
+use crate::globals::BURST_MAX_INTERVAL;
+use std::collections::HashMap;
+
+/// Media item in the library, as needed to detect bursts.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<'a> {
+    pub uri: &'a str,
+    pub unix_time: Option<i64>,
+}
+
+/// Bursts detected in the library, by the URIs of their frames.
+#[derive(Debug, Default)]
+pub struct Bursts {
+    /// Frames of each burst in library order, by the URI of the burst's cover.
+    frames: HashMap<String, Vec<String>>,
+    /// URI of the cover of the burst that each frame belongs to, by frame URI.
+    covers: HashMap<String, String>,
+}
+
+impl Bursts {
+    /// Detects the bursts in the given items, in library order. `picks` are the URIs
+    /// of the frames picked as the best frame of their burst, which become its cover.
+    pub fn detect(items: &[Frame], picks: &[&str]) -> Self {
+        let mut bursts: Self = Self::default();
+        let mut start: usize = 0;
+
+        for end in 1..=items.len() {
+            if end < items.len() && is_next_frame(&items[end - 1], &items[end]) {
+                continue;
+            }
+            let run: &[Frame] = &items[start..end];
+            start = end;
+
+            if run.len() < 2 {
+                continue;
+            }
+            let cover: &str = run
+                .iter()
+                .find(|frame: &&Frame| picks.contains(&frame.uri))
+                .unwrap_or(&run[0])
+                .uri;
+
+            for frame in run {
+                bursts.covers.insert(frame.uri.to_string(), cover.to_string());
+            }
+            bursts.frames.insert(
+                cover.to_string(),
+                run.iter().map(|frame: &Frame| frame.uri.to_string()).collect(),
+            );
+        }
+        bursts
+    }
+
+    /// Returns whether the item is a frame of a burst other than its
+    /// cover, in which case it is collapsed into the cover in the library.
+    pub fn is_collapsed(&self, uri: &str) -> bool {
+        self.covers.get(uri).is_some_and(|cover: &String| cover != uri)
+    }
+
+    /// Returns the frames of the burst that the given item is the cover of.
+    pub fn frames_of(&self, cover_uri: &str) -> Option<&[String]> {
+        self.frames.get(cover_uri).map(Vec::as_slice)
+    }
+}
+
+/// Returns whether `next` directly follows `frame` in a burst.
+fn is_next_frame(frame: &Frame, next: &Frame) -> bool {
+    let (Some(time), Some(next_time)) = (frame.unix_time, next.unix_time) else {
+        return false;
+    };
+    if (time - next_time).abs() > BURST_MAX_INTERVAL {
+        return false;
+    }
+    let (Some((folder, name)), Some((next_folder, next_name))) =
+        (frame.uri.rsplit_once('/'), next.uri.rsplit_once('/'))
+    else {
+        return false;
+    };
+    match (sequence_number(name), sequence_number(next_name)) {
+        (Some((prefix, number)), Some((next_prefix, next_number))) => {
+            folder == next_folder && prefix == next_prefix && number.abs_diff(next_number) == 1
+        }
+        _ => false,
+    }
+}
+
+/// Splits a file name into the part before its sequence number and the
+/// sequence number, such as `IMG_` and 41 for `IMG_0041.JPG`. The `_COVER`
+/// suffix that some phones add to the cover of a burst is ignored.
+fn sequence_number(name: &str) -> Option<(&str, u64)> {
+    let stem: &str = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    let stem: &str = stem.strip_suffix("_COVER").unwrap_or(stem);
+    let prefix: &str = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+
+    stem[prefix.len()..]
+        .parse::<u64>()
+        .ok()
+        .map(|number: u64| (prefix, number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(uri: &str, unix_time: i64) -> Frame<'_> {
+        Frame {
+            uri,
+            unix_time: Some(unix_time),
+        }
+    }
+
+    #[test]
+    fn sequence_numbers() {
+        assert_eq!(sequence_number("IMG_0041.JPG"), Some(("IMG_", 41)));
+        assert_eq!(
+            sequence_number("IMG_20240101_BURST002_COVER.jpg"),
+            Some(("IMG_20240101_BURST", 2))
+        );
+        assert_eq!(sequence_number("beach.png"), None);
+    }
+
+    #[test]
+    fn detects_sequential_frames() {
+        let items: Vec<Frame> = vec![
+            frame("file:///a/IMG_0043.JPG", 102),
+            frame("file:///a/IMG_0042.JPG", 101),
+            frame("file:///a/IMG_0041.JPG", 100),
+            // Taken too long after the previous frame.
+            frame("file:///a/IMG_0040.JPG", 10),
+            // Not sequential, or in another folder.
+            frame("file:///a/IMG_0038.JPG", 10),
+            frame("file:///b/IMG_0037.JPG", 10),
+        ];
+        let bursts: Bursts = Bursts::detect(&items, &[]);
+
+        assert_eq!(
+            bursts.frames_of("file:///a/IMG_0043.JPG").map(<[String]>::len),
+            Some(3)
+        );
+        assert!(!bursts.is_collapsed("file:///a/IMG_0043.JPG"));
+        assert!(bursts.is_collapsed("file:///a/IMG_0041.JPG"));
+        assert!(!bursts.is_collapsed("file:///a/IMG_0040.JPG"));
+        assert!(bursts.frames_of("file:///a/IMG_0040.JPG").is_none());
+        assert!(!bursts.is_collapsed("file:///b/IMG_0037.JPG"));
+    }
+
+    #[test]
+    fn picked_frame_is_cover() {
+        let items: Vec<Frame> = vec![
+            frame("file:///a/IMG_0002.JPG", 100),
+            frame("file:///a/IMG_0001.JPG", 100),
+        ];
+        let bursts: Bursts = Bursts::detect(&items, &["file:///a/IMG_0001.JPG"]);
+
+        assert!(bursts.is_collapsed("file:///a/IMG_0002.JPG"));
+        assert!(!bursts.is_collapsed("file:///a/IMG_0001.JPG"));
+        assert!(bursts.frames_of("file:///a/IMG_0001.JPG").is_some());
+    }
+}
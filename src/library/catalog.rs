@@ -0,0 +1,279 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Catalog of the library media, exported as CSV or JSON so that the library
+//! can be analyzed in other applications, such as spreadsheets.
+
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+
+/// A column of an exported catalog, which may span several fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CatalogColumn {
+    Path,
+    Date,
+    Size,
+    Dimensions,
+    Camera,
+    Location,
+    Tags,
+    Rating,
+}
+
+impl CatalogColumn {
+    pub const ALL: [Self; 8] = [
+        Self::Path,
+        Self::Date,
+        Self::Size,
+        Self::Dimensions,
+        Self::Camera,
+        Self::Location,
+        Self::Tags,
+        Self::Rating,
+    ];
+
+    /// Returns the names of the fields of the column in exported catalogs,
+    /// which are not translated, so that catalogs can be processed by scripts.
+    pub fn fields(self) -> &'static [&'static str] {
+        match self {
+            Self::Path => &["path"],
+            Self::Date => &["date"],
+            Self::Size => &["size"],
+            Self::Dimensions => &["width", "height"],
+            Self::Camera => &["camera"],
+            Self::Location => &["latitude", "longitude"],
+            Self::Tags => &["tags"],
+            Self::Rating => &["rating"],
+        }
+    }
+
+    /// Whether the values of the column are read from the EXIF data of the files.
+    pub fn reads_exif(self) -> bool {
+        matches!(
+            self,
+            Self::Date | Self::Dimensions | Self::Camera | Self::Location
+        )
+    }
+}
+
+/// File format of an exported catalog.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CatalogFormat {
+    Csv,
+    Json,
+}
+
+impl CatalogFormat {
+    pub fn from_index(index: u32) -> Self {
+        match index {
+            1 => Self::Json,
+            _ => Self::Csv,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv",
+            Self::Json => "application/json",
+        }
+    }
+}
+
+/// A media file in an exported catalog.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CatalogRecord {
+    pub path: String,
+    /// Date that the media was taken, or else last modified, in ISO 8601 format.
+    pub date: Option<String>,
+    /// Size of the file in bytes.
+    pub size: u64,
+    pub dimensions: Option<(u32, u32)>,
+    pub camera: Option<String>,
+    /// Latitude and longitude in decimal degrees.
+    pub location: Option<(f64, f64)>,
+    pub tags: Vec<String>,
+    pub rating: u32,
+}
+
+impl CatalogRecord {
+    /// Returns the values of the fields of a column, in the order of `CatalogColumn::fields()`.
+    fn values(&self, column: CatalogColumn) -> Vec<Value> {
+        match column {
+            CatalogColumn::Path => vec![self.path.clone().into()],
+            CatalogColumn::Date => vec![self.date.clone().into()],
+            CatalogColumn::Size => vec![self.size.into()],
+            CatalogColumn::Dimensions => match self.dimensions {
+                Some((width, height)) => vec![width.into(), height.into()],
+                None => vec![Value::Null, Value::Null],
+            },
+            CatalogColumn::Camera => vec![self.camera.clone().into()],
+            CatalogColumn::Location => match self.location {
+                Some((latitude, longitude)) => vec![latitude.into(), longitude.into()],
+                None => vec![Value::Null, Value::Null],
+            },
+            CatalogColumn::Tags => vec![self.tags.clone().into()],
+            CatalogColumn::Rating => vec![self.rating.into()],
+        }
+    }
+}
+
+/// Returns the contents of a catalog of the records, with the given columns.
+pub fn write_catalog(records: &[CatalogRecord], columns: &[CatalogColumn], format: CatalogFormat) -> String {
+    match format {
+        CatalogFormat::Csv => write_csv(records, columns),
+        CatalogFormat::Json => write_json(records, columns),
+    }
+}
+
+/// Writes the records as CSV, as specified by RFC 4180, with a header row.
+/// Tags are separated by semicolons, and missing values are left empty.
+fn write_csv(records: &[CatalogRecord], columns: &[CatalogColumn]) -> String {
+    let header: Vec<&str> = columns
+        .iter()
+        .flat_map(|column| column.fields())
+        .copied()
+        .collect();
+    let mut csv: String = header.join(",") + "\r\n";
+
+    for record in records {
+        let fields: Vec<String> = columns
+            .iter()
+            .flat_map(|column: &CatalogColumn| record.values(*column))
+            .map(|value: Value| match value {
+                Value::Null => String::new(),
+                Value::String(text) => csv_field(&text).into_owned(),
+                Value::Array(values) => csv_field(
+                    &values
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<&str>>()
+                        .join("; "),
+                )
+                .into_owned(),
+                value => value.to_string(),
+            })
+            .collect();
+
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// Writes the records as a JSON array of objects, with `null` for missing values.
+fn write_json(records: &[CatalogRecord], columns: &[CatalogColumn]) -> String {
+    let objects: Vec<Value> = records
+        .iter()
+        .map(|record: &CatalogRecord| {
+            let mut object: Map<String, Value> = Map::new();
+
+            for column in columns {
+                for (field, value) in column.fields().iter().zip(record.values(*column)) {
+                    object.insert(field.to_string(), value);
+                }
+            }
+            Value::Object(object)
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&Value::Array(objects)).unwrap_or_default()
+}
+
+/// Returns a CSV field, quoted if it contains a separator, a quote, or a line break.
+fn csv_field(text: &str) -> Cow<'_, str> {
+    if text.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", text.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<CatalogRecord> {
+        vec![
+            CatalogRecord {
+                path: "/photos/beach, 2023.jpg".into(),
+                date: Some("2023-07-01T10:30:00".into()),
+                size: 2048,
+                dimensions: Some((4000, 3000)),
+                camera: Some("Pixel 7".into()),
+                location: Some((48.86, -2.35)),
+                tags: vec!["Trip".into(), "Beach".into()],
+                rating: 4,
+            },
+            CatalogRecord {
+                path: "/photos/\"quoted\".png".into(),
+                size: 10,
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn csv_catalog() {
+        let columns: [CatalogColumn; 4] = [
+            CatalogColumn::Path,
+            CatalogColumn::Dimensions,
+            CatalogColumn::Tags,
+            CatalogColumn::Rating,
+        ];
+        assert_eq!(
+            write_catalog(&records(), &columns, CatalogFormat::Csv),
+            "path,width,height,tags,rating\r\n\
+             \"/photos/beach, 2023.jpg\",4000,3000,Trip; Beach,4\r\n\
+             \"/photos/\"\"quoted\"\".png\",,,,0\r\n"
+        );
+    }
+
+    #[test]
+    fn json_catalog() {
+        let columns: [CatalogColumn; 3] = [CatalogColumn::Path, CatalogColumn::Location, CatalogColumn::Tags];
+        let json: Value =
+            serde_json::from_str(&write_catalog(&records(), &columns, CatalogFormat::Json)).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {
+                    "path": "/photos/beach, 2023.jpg",
+                    "latitude": 48.86,
+                    "longitude": -2.35,
+                    "tags": ["Trip", "Beach"],
+                },
+                {
+                    "path": "/photos/\"quoted\".png",
+                    "latitude": null,
+                    "longitude": null,
+                    "tags": [],
+                },
+            ])
+        );
+    }
+}
@@ -0,0 +1,329 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Dialog that exports a catalog of the library media, with the chosen columns
+//! of metadata, as a CSV or JSON file. The metadata of large libraries takes
+//! a while to read, so the export shows its progress and can be cancelled.
+
+use crate::application::{MemoriesApplication, RunningJob};
+use crate::globals::EXIF_DETAILS_READ_SIZE;
+use crate::i18n::{gettext_f, ngettext_f};
+use crate::library::catalog::{self, CatalogColumn, CatalogFormat, CatalogRecord};
+use crate::library::hidden_filter_func;
+use crate::library::media_item::MemoriesMediaItem;
+use crate::util::metadata::{self, ExifDetails};
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::{clone, g_warning};
+use gtk::gdk_pixbuf::Pixbuf;
+use gtk::{gio, glib};
+use std::io::Read;
+use std::path::Path;
+
+mod imp {
+    use crate::library::catalog::CatalogColumn;
+    use adw::subclass::prelude::*;
+    use gtk::{gio, glib};
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/catalog-dialog.ui")]
+    pub struct MemoriesCatalogDialog {
+        pub(super) column_rows: RefCell<Vec<(CatalogColumn, adw::SwitchRow)>>,
+        /// Cancels the export. `None` while no export runs.
+        pub(super) cancellable: RefCell<Option<gio::Cancellable>>,
+        #[template_child]
+        pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) export_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) content_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) format_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub(super) columns_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) progress_bar: TemplateChild<gtk::ProgressBar>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesCatalogDialog {
+        const NAME: &'static str = "MemoriesCatalogDialog";
+        type Type = super::MemoriesCatalogDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesCatalogDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().add_column_rows();
+        }
+    }
+
+    impl WidgetImpl for MemoriesCatalogDialog {}
+    impl AdwDialogImpl for MemoriesCatalogDialog {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesCatalogDialog(ObjectSubclass<imp::MemoriesCatalogDialog>)
+        @extends gtk::Widget, adw::Dialog;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesCatalogDialog {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    fn column_title(column: CatalogColumn) -> String {
+        match column {
+            CatalogColumn::Path => gettext("File Path"),
+            CatalogColumn::Date => gettext("Date"),
+            CatalogColumn::Size => gettext("File Size"),
+            CatalogColumn::Dimensions => gettext("Dimensions"),
+            CatalogColumn::Camera => gettext("Camera"),
+            CatalogColumn::Location => gettext("GPS Location"),
+            CatalogColumn::Tags => gettext("Tags"),
+            CatalogColumn::Rating => gettext("Rating"),
+        }
+    }
+
+    fn add_column_rows(&self) {
+        for column in CatalogColumn::ALL {
+            let row: adw::SwitchRow = adw::SwitchRow::builder()
+                .title(Self::column_title(column))
+                .active(true)
+                .build();
+
+            row.connect_active_notify(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &adw::SwitchRow| {
+                    this.imp()
+                        .export_button
+                        .set_sensitive(!this.selected_columns().is_empty());
+                }
+            ));
+            self.imp().columns_group.add(&row);
+            self.imp().column_rows.borrow_mut().push((column, row));
+        }
+    }
+
+    fn selected_columns(&self) -> Vec<CatalogColumn> {
+        self.imp()
+            .column_rows
+            .borrow()
+            .iter()
+            .filter(|(_, row)| row.is_active())
+            .map(|(column, _)| *column)
+            .collect()
+    }
+
+    fn selected_format(&self) -> CatalogFormat {
+        CatalogFormat::from_index(self.imp().format_row.selected())
+    }
+
+    /// Closing the dialog while exporting cancels the export instead.
+    #[template_callback]
+    fn close_attempted(&self) {
+        if let Some(cancellable) = self.imp().cancellable.borrow().as_ref() {
+            cancellable.cancel();
+        }
+    }
+
+    #[template_callback]
+    fn export_clicked(&self) {
+        let format: CatalogFormat = self.selected_format();
+
+        let filter: gtk::FileFilter = gtk::FileFilter::new();
+        filter.set_name(Some(&format.extension().to_uppercase()));
+        filter.add_mime_type(format.mime_type());
+
+        let filters: gio::ListStore = gio::ListStore::new::<gtk::FileFilter>();
+        filters.append(&filter);
+
+        let file_dialog: gtk::FileDialog = gtk::FileDialog::builder()
+            .title(gettext("Save Catalog"))
+            .initial_name(format!("{}.{}", gettext("Catalog"), format.extension()))
+            .filters(&filters)
+            .modal(true)
+            .build();
+        let window: Option<gtk::Window> = self.root().and_downcast();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                // An error is returned if the user dismisses the file chooser.
+                if let Ok(file) = file_dialog.save_future(window.as_ref()).await {
+                    this.export(&file, format).await;
+                }
+            }
+        ));
+    }
+
+    /// Reads the metadata of the library media for the selected columns, and
+    /// writes the catalog to `destination`. Private media is left out, since it
+    /// is only shown from the Private album, but archived media is kept.
+    async fn export(&self, destination: &gio::File, format: CatalogFormat) {
+        let imp = self.imp();
+        let memories: MemoriesApplication = MemoriesApplication::default();
+        let columns: Vec<CatalogColumn> = self.selected_columns();
+        let reads_exif: bool = columns.iter().any(|column: &CatalogColumn| column.reads_exif());
+
+        let not_private = hidden_filter_func(&memories.private());
+        let items: Vec<MemoriesMediaItem> = memories
+            .library_list_model()
+            .iter::<glib::Object>()
+            .filter_map(Result::ok)
+            .filter(|item: &glib::Object| not_private(item))
+            .filter_map(|item: glib::Object| item.downcast().ok())
+            .collect();
+
+        let cancellable: gio::Cancellable = gio::Cancellable::new();
+        imp.cancellable.replace(Some(cancellable.clone()));
+        self.set_can_close(false);
+        imp.export_button.set_sensitive(false);
+        imp.content_stack.set_visible_child_name("progress");
+
+        let _job: RunningJob = memories.start_job(&gettext("Exporting catalog"));
+        let total: usize = items.len();
+        let mut records: Vec<CatalogRecord> = Vec::with_capacity(total);
+
+        for (i, item) in items.iter().enumerate() {
+            if cancellable.is_cancelled() {
+                break;
+            }
+            self.set_progress(i, total);
+
+            let file: gio::File = item.file();
+            let (details, dimensions) = match file.path().filter(|_| reads_exif && !item.is_video()) {
+                Some(path) => gio::spawn_blocking(move || Self::read_details(&path))
+                    .await
+                    .unwrap_or_else(|_| {
+                        g_warning!("CatalogDialog", "Metadata reading thread panicked.");
+                        (None, None)
+                    }),
+                None => (None, None),
+            };
+            let details: ExifDetails = details.unwrap_or_default();
+
+            records.push(CatalogRecord {
+                path: file.parse_name().to_string(),
+                date: details
+                    .date_time_original
+                    .map(|date: exif::DateTime| {
+                        format!(
+                            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                            date.year, date.month, date.day, date.hour, date.minute, date.second
+                        )
+                    })
+                    .or_else(|| {
+                        item.modified()
+                            .and_then(|modified: glib::DateTime| modified.to_local().ok())
+                            .and_then(|modified: glib::DateTime| modified.format("%Y-%m-%dT%H:%M:%S").ok())
+                            .map(|modified: glib::GString| modified.to_string())
+                    }),
+                size: item.size(),
+                dimensions: details.dimensions.or(dimensions),
+                camera: details.camera,
+                location: details.location,
+                tags: memories.tags(&file),
+                rating: memories.rating(&file),
+            });
+        }
+
+        if !cancellable.is_cancelled() {
+            let contents: String = catalog::write_catalog(&records, &columns, format);
+
+            let message: String = match destination
+                .replace_contents_future(
+                    contents.into_bytes(),
+                    None,
+                    false,
+                    gio::FileCreateFlags::REPLACE_DESTINATION,
+                )
+                .await
+            {
+                Ok(_) => ngettext_f(
+                    "Exported the catalog of {COUNT} item",
+                    "Exported the catalog of {COUNT} items",
+                    records.len().try_into().unwrap_or(u32::MAX),
+                    &[("COUNT", &records.len().to_string())],
+                ),
+                Err((_, err)) => {
+                    g_warning!("CatalogDialog", "Failed to write the catalog: {}", err);
+                    gettext("Could not save the catalog")
+                }
+            };
+            imp.toast_overlay.add_toast(adw::Toast::new(&message));
+        }
+        imp.cancellable.take();
+        self.set_can_close(true);
+        imp.export_button.set_sensitive(true);
+        imp.content_stack.set_visible_child_name("options");
+    }
+
+    fn set_progress(&self, done: usize, total: usize) {
+        let progress_bar: &gtk::ProgressBar = &self.imp().progress_bar;
+
+        progress_bar.set_fraction(done as f64 / total.max(1) as f64);
+        progress_bar.set_text(Some(&gettext_f(
+            "{DONE} of {TOTAL}",
+            &[("DONE", &done.to_string()), ("TOTAL", &total.to_string())],
+        )));
+    }
+
+    /// Returns the EXIF details of a photo, and its dimensions as told by the
+    /// header of the image, for photos whose EXIF data has no dimensions.
+    fn read_details(path: &Path) -> (Option<ExifDetails>, Option<(u32, u32)>) {
+        let mut file_head: Vec<u8> = vec![];
+        let details: Option<ExifDetails> = std::fs::File::open(path)
+            .and_then(|file| file.take(EXIF_DETAILS_READ_SIZE).read_to_end(&mut file_head))
+            .ok()
+            .and_then(|_| metadata::read_exif_details(&file_head));
+
+        if details
+            .as_ref()
+            .is_some_and(|details: &ExifDetails| details.dimensions.is_some())
+        {
+            return (details, None);
+        }
+        let dimensions: Option<(u32, u32)> = Pixbuf::file_info(path)
+            .and_then(|(_, width, height)| Some((u32::try_from(width).ok()?, u32::try_from(height).ok()?)));
+        (details, dimensions)
+    }
+}
+
+impl Default for MemoriesCatalogDialog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,536 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Dialog that compresses a video into a smaller copy with ffmpeg,
+//! such as to send it from a phone over a slow mobile connection.
+
+use super::slideshow_dialog::parse_progress;
+use crate::application::{MemoriesApplication, RunningJob};
+use crate::globals::{COMPRESS_AUDIO_BITRATE, COMPRESS_VIDEO_BITRATES, FFMPEG_BINARY, FFPROBE_BINARY};
+use crate::i18n::gettext_f;
+use crate::util::format::{self, SizeUnits};
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use async_process::{Child, Command, Output, Stdio};
+use futures_lite::io::{AsyncBufReadExt, BufReader};
+use futures_lite::StreamExt;
+use gettextrs::gettext;
+use glib::{clone, g_warning};
+use gtk::{gio, glib};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+mod imp {
+    use adw::subclass::prelude::*;
+    use gtk::{gio, glib};
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/compress-dialog.ui")]
+    pub struct MemoriesCompressDialog {
+        pub(super) video: RefCell<Option<gio::File>>,
+        /// Cancels the video being compressed. `None` while not compressing.
+        pub(super) cancellable: RefCell<Option<gio::Cancellable>>,
+        /// The compressed copy of the video, once it is saved.
+        pub(super) output: RefCell<Option<gio::File>>,
+        #[template_child]
+        pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) cancel_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) compress_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) content_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) resolution_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub(super) codec_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub(super) quality_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub(super) progress_status_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub(super) progress_bar: TemplateChild<gtk::ProgressBar>,
+        #[template_child]
+        pub(super) open_button: TemplateChild<gtk::Button>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesCompressDialog {
+        const NAME: &'static str = "MemoriesCompressDialog";
+        type Type = super::MemoriesCompressDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesCompressDialog {}
+    impl WidgetImpl for MemoriesCompressDialog {}
+    impl AdwDialogImpl for MemoriesCompressDialog {}
+}
+
+/// Enum that represents the resolution that a video is compressed to.
+/// Variant order matches the resolution combo row model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressResolution {
+    Original,
+    FullHd,
+    Hd,
+    Sd,
+}
+
+impl CompressResolution {
+    pub fn from_index(index: u32) -> CompressResolution {
+        match index {
+            0 => CompressResolution::Original,
+            1 => CompressResolution::FullHd,
+            3 => CompressResolution::Sd,
+            _ => CompressResolution::Hd,
+        }
+    }
+
+    /// Returns the maximum length, in pixels, of the shorter edge of the
+    /// video, so that portrait videos are scaled down as much as landscape ones.
+    pub fn short_edge(&self) -> Option<u32> {
+        match *self {
+            CompressResolution::Original => None,
+            CompressResolution::FullHd => Some(1080),
+            CompressResolution::Hd => Some(720),
+            CompressResolution::Sd => Some(480),
+        }
+    }
+}
+
+/// Enum that represents the video codec of compressed videos.
+/// Variant order matches the codec combo row model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressCodec {
+    H264,
+    Av1,
+}
+
+impl CompressCodec {
+    pub fn from_index(index: u32) -> CompressCodec {
+        match index {
+            1 => CompressCodec::Av1,
+            _ => CompressCodec::H264,
+        }
+    }
+
+    /// Returns the name of the ffmpeg encoder of the codec.
+    pub fn encoder(&self) -> &str {
+        match *self {
+            CompressCodec::H264 => "libx264",
+            CompressCodec::Av1 => "libsvtav1",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressOptions {
+    pub resolution: CompressResolution,
+    pub codec: CompressCodec,
+    /// Video bitrate, in kilobits per second.
+    pub bitrate: u32,
+}
+
+impl CompressOptions {
+    /// Returns the ffmpeg filter that scales the shorter edge of the video
+    /// down to `short_edge`. Videos that are smaller are left as they are,
+    /// and the other edge is kept even, which most encoders require.
+    fn scale_filter(short_edge: u32) -> String {
+        format!(
+            "scale=w='if(gte(iw,ih),-2,min({0},iw))':h='if(gte(iw,ih),min({0},ih),-2)'",
+            short_edge
+        )
+    }
+
+    /// Returns the ffmpeg arguments that compress `input` into the MP4 video at
+    /// `output`. The metadata of the video, such as its creation date, is kept.
+    pub fn ffmpeg_arguments(&self, input: &Path, output: &Path) -> Vec<OsString> {
+        // Progress is written to stdout as `key=value` lines.
+        let mut arguments: Vec<OsString> = [
+            "-y", "-loglevel", "error", "-nostats", "-progress", "pipe:1", "-i",
+        ]
+        .map(OsString::from)
+        .to_vec();
+        arguments.push(input.into());
+
+        if let Some(short_edge) = self.resolution.short_edge() {
+            arguments.extend(["-vf".into(), Self::scale_filter(short_edge).into()]);
+        }
+        let video_bitrate: String = format!("{}k", self.bitrate);
+        let audio_bitrate: String = format!("{}k", COMPRESS_AUDIO_BITRATE);
+
+        arguments.extend(
+            [
+                "-map_metadata",
+                "0",
+                "-c:v",
+                self.codec.encoder(),
+                "-b:v",
+                video_bitrate.as_str(),
+                "-pix_fmt",
+                "yuv420p",
+                "-c:a",
+                "aac",
+                "-b:a",
+                audio_bitrate.as_str(),
+                "-movflags",
+                "+faststart",
+            ]
+            .map(OsString::from),
+        );
+        arguments.push(output.into());
+        arguments
+    }
+}
+
+/// Returns the name suggested for the compressed copy of a video.
+fn compressed_name(basename: &Path) -> String {
+    format!(
+        "{}-compressed.mp4",
+        basename
+            .file_stem()
+            .unwrap_or(basename.as_os_str())
+            .to_string_lossy()
+    )
+}
+
+/// Returns the duration of a video in seconds, as found by ffprobe.
+pub async fn probe_duration(path: &Path) -> Option<f64> {
+    let output: Output = Command::new(FFPROBE_BINARY)
+        .args([
+            "-v", "error", "-show_entries", "format=duration", "-of", "csv=p=0",
+        ])
+        .arg(path)
+        .output()
+        .await
+        .ok()?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()
+        .filter(|duration: &f64| *duration > 0.0)
+}
+
+glib::wrapper! {
+    pub struct MemoriesCompressDialog(ObjectSubclass<imp::MemoriesCompressDialog>)
+        @extends gtk::Widget, adw::Dialog;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesCompressDialog {
+    pub fn new(video: &gio::File) -> Self {
+        let obj: Self = glib::Object::new();
+        obj.imp().video.replace(Some(video.clone()));
+        obj
+    }
+
+    fn options(&self) -> CompressOptions {
+        let quality_index: usize = self.imp().quality_row.selected().try_into().unwrap();
+
+        CompressOptions {
+            resolution: CompressResolution::from_index(self.imp().resolution_row.selected()),
+            codec: CompressCodec::from_index(self.imp().codec_row.selected()),
+            bitrate: COMPRESS_VIDEO_BITRATES
+                .get(quality_index)
+                .copied()
+                .unwrap_or(COMPRESS_VIDEO_BITRATES[0]),
+        }
+    }
+
+    #[template_callback]
+    fn cancel_clicked(&self) {
+        match self.imp().cancellable.borrow().as_ref() {
+            Some(cancellable) => cancellable.cancel(),
+            None => {
+                self.close();
+            }
+        }
+    }
+
+    /// Closing the dialog while compressing cancels the compression instead.
+    #[template_callback]
+    fn close_attempted(&self) {
+        if let Some(cancellable) = self.imp().cancellable.borrow().as_ref() {
+            cancellable.cancel();
+        }
+    }
+
+    #[template_callback]
+    fn compress_clicked(&self) {
+        let Some(video) = self.imp().video.borrow().clone() else {
+            return;
+        };
+        let filter: gtk::FileFilter = gtk::FileFilter::new();
+        filter.set_name(Some(&gettext("MP4 Video")));
+        filter.add_mime_type("video/mp4");
+
+        let filters: gio::ListStore = gio::ListStore::new::<gtk::FileFilter>();
+        filters.append(&filter);
+
+        let mut file_dialog_builder = gtk::FileDialog::builder()
+            .title(gettext("Save Compressed Video"))
+            .filters(&filters)
+            .modal(true);
+
+        if let Some(basename) = video.basename() {
+            file_dialog_builder = file_dialog_builder.initial_name(compressed_name(&basename));
+        }
+        if let Some(folder) = video.parent() {
+            file_dialog_builder = file_dialog_builder.initial_folder(&folder);
+        }
+        let file_dialog: gtk::FileDialog = file_dialog_builder.build();
+        let window: Option<gtk::Window> = self.root().and_downcast();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                // An error is returned if the user dismisses the file chooser.
+                if let Ok(file) = file_dialog.save_future(window.as_ref()).await {
+                    this.compress(&video, &file).await;
+                }
+            }
+        ));
+    }
+
+    #[template_callback]
+    fn open_clicked(&self) {
+        let Some(output) = self.imp().output.borrow().clone() else {
+            return;
+        };
+        let launcher: gtk::FileLauncher = gtk::FileLauncher::new(Some(&output));
+        let window: Option<gtk::Window> = self.root().and_downcast();
+
+        glib::spawn_future_local(async move {
+            if let Err(err) = launcher.open_containing_folder_future(window.as_ref()).await {
+                g_warning!("CompressDialog", "Failed to show the compressed video: {}", err);
+            }
+        });
+    }
+
+    /// Compresses `video` into `output` with ffmpeg, updating the progress bar
+    /// from ffmpeg's progress output, or pulsing it if the duration is unknown.
+    async fn compress(&self, video: &gio::File, output: &gio::File) {
+        let (Some(input_path), Some(output_path)) = (video.path(), output.path()) else {
+            self.show_error_toast();
+            return;
+        };
+        if input_path == output_path {
+            self.imp().toast_overlay.add_toast(adw::Toast::new(&gettext(
+                "The compressed video cannot replace the original",
+            )));
+            return;
+        }
+        let options: CompressOptions = self.options();
+        let duration: Option<f64> = probe_duration(&input_path).await;
+
+        let mut ffmpeg_command: Command = Command::new(FFMPEG_BINARY);
+
+        ffmpeg_command
+            .kill_on_drop(true)
+            .args(options.ffmpeg_arguments(&input_path, &output_path))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut ffmpeg_child: Child = match ffmpeg_command.spawn() {
+            Ok(child) => child,
+            Err(io_error) => {
+                g_warning!("CompressDialog", "Failed to execute ffmpeg: {}", io_error);
+                self.show_error_toast();
+                return;
+            }
+        };
+        let ffmpeg_stdout = ffmpeg_child.stdout.take().unwrap();
+
+        let cancellable: gio::Cancellable = gio::Cancellable::new();
+        self.imp().cancellable.replace(Some(cancellable.clone()));
+        self.set_can_close(false);
+        self.imp().compress_button.set_sensitive(false);
+        self.imp().progress_bar.set_fraction(0.0);
+        self.imp().content_stack.set_visible_child_name("progress");
+
+        let _job: RunningJob = MemoriesApplication::default().start_job(&gettext("Compressing a video"));
+
+        // Dropping the ffmpeg child process kills it, so canceling this future stops compressing.
+        let compressing = async {
+            let mut lines = BufReader::new(ffmpeg_stdout).lines();
+
+            while let Some(Ok(line)) = lines.next().await {
+                let Some(seconds) = parse_progress(&line) else {
+                    continue;
+                };
+                match duration {
+                    Some(duration) => self
+                        .imp()
+                        .progress_bar
+                        .set_fraction((seconds / duration).clamp(0.0, 1.0)),
+                    None => self.imp().progress_bar.pulse(),
+                }
+            }
+            ffmpeg_child.output().await
+        };
+        let ffmpeg_output: Result<std::io::Result<Output>, gio::Cancelled> =
+            gio::CancellableFuture::new(compressing, cancellable.clone()).await;
+
+        self.imp().cancellable.take();
+        self.set_can_close(true);
+        self.imp().compress_button.set_sensitive(true);
+
+        match ffmpeg_output {
+            Ok(Ok(ffmpeg_output)) if ffmpeg_output.status.success() => {
+                self.show_result(&input_path, &output_path);
+                self.imp().output.replace(Some(output.clone()));
+                return;
+            }
+            Ok(Ok(ffmpeg_output)) => {
+                g_warning!(
+                    "CompressDialog",
+                    "FFmpeg failed to compress the video: {}",
+                    String::from_utf8_lossy(&ffmpeg_output.stderr)
+                );
+                self.show_error_toast();
+            }
+            Ok(Err(io_error)) => {
+                g_warning!("CompressDialog", "Failed to compress the video: {}", io_error);
+                self.show_error_toast();
+            }
+            Err(_) => {
+                self.imp()
+                    .toast_overlay
+                    .add_toast(adw::Toast::new(&gettext("Compression canceled")));
+            }
+        }
+        // Do not leave a partially compressed video behind.
+        let _ = std::fs::remove_file(&output_path);
+        self.imp().content_stack.set_visible_child_name("options");
+    }
+
+    /// Shows how much smaller the compressed video is, and offers to show it.
+    fn show_result(&self, input_path: &Path, output_path: &Path) {
+        let size = |path: &Path| -> String {
+            let bytes: u64 = std::fs::metadata(path)
+                .map(|metadata| metadata.len())
+                .unwrap_or(0);
+            format::file_size(bytes, SizeUnits::Decimal)
+        };
+        let description: String = gettext_f(
+            "Reduced from {ORIGINAL} to {COMPRESSED}",
+            &[
+                ("ORIGINAL", &size(input_path)),
+                ("COMPRESSED", &size(output_path)),
+            ],
+        );
+        self.imp()
+            .progress_status_page
+            .set_title(&gettext("Video Compressed"));
+        self.imp()
+            .progress_status_page
+            .set_description(Some(&description));
+        self.imp().progress_bar.set_visible(false);
+        self.imp().open_button.set_visible(true);
+        self.imp().compress_button.set_visible(false);
+        self.imp().cancel_button.set_label(&gettext("_Close"));
+
+        MemoriesApplication::default().notify_job_finished(
+            "compress",
+            &gettext("Video Compressed"),
+            &description,
+            Some(&gio::File::for_path(output_path)),
+        );
+    }
+
+    fn show_error_toast(&self) {
+        let toast: adw::Toast = adw::Toast::new(&gettext("Failed to compress the video"));
+        self.imp().toast_overlay.add_toast(toast);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arguments(options: CompressOptions) -> Vec<String> {
+        options
+            .ffmpeg_arguments(Path::new("in.mov"), Path::new("out.mp4"))
+            .iter()
+            .map(|argument: &OsString| argument.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn compress_arguments_keep_original_resolution() {
+        let options: CompressOptions = CompressOptions {
+            resolution: CompressResolution::Original,
+            codec: CompressCodec::H264,
+            bitrate: 2000,
+        };
+        let arguments: Vec<String> = arguments(options);
+
+        assert_eq!(
+            arguments[..8],
+            ["-y", "-loglevel", "error", "-nostats", "-progress", "pipe:1", "-i", "in.mov"]
+        );
+        assert_eq!(
+            arguments[8..],
+            [
+                "-map_metadata", "0", "-c:v", "libx264", "-b:v", "2000k", "-pix_fmt", "yuv420p", "-c:a",
+                "aac", "-b:a", "128k", "-movflags", "+faststart", "out.mp4",
+            ]
+        );
+    }
+
+    #[test]
+    fn compress_arguments_scale_video() {
+        let options: CompressOptions = CompressOptions {
+            resolution: CompressResolution::Hd,
+            codec: CompressCodec::Av1,
+            bitrate: 1000,
+        };
+        let arguments: Vec<String> = arguments(options);
+        let filter_index: usize = arguments.iter().position(|argument| argument == "-vf").unwrap();
+
+        assert_eq!(
+            arguments[filter_index + 1],
+            "scale=w='if(gte(iw,ih),-2,min(720,iw))':h='if(gte(iw,ih),min(720,ih),-2)'"
+        );
+        assert!(arguments.contains(&"libsvtav1".to_string()));
+        assert!(arguments.contains(&"1000k".to_string()));
+    }
+
+    #[test]
+    fn compressed_names() {
+        assert_eq!(
+            compressed_name(Path::new("VID_0001.mov")),
+            "VID_0001-compressed.mp4"
+        );
+        assert_eq!(compressed_name(Path::new("clip")), "clip-compressed.mp4");
+    }
+}
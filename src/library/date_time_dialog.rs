@@ -0,0 +1,399 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Dialog to adjust the date and time of media files, such as scanned photos
+//! that are dated by when they were scanned. A single item is set to a new
+//! date, and the dates of a selection are shifted by the same offset. The
+//! date is written to the EXIF data of JPEG photos, and the modification
+//! date of every file is changed too, which places it in the library timeline.
+
+use crate::application::MemoriesApplication;
+use crate::globals::EXIF_DATE_READ_SIZE;
+use crate::i18n::ngettext_f;
+use crate::util::format;
+use crate::util::metadata;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::{clone, g_warning};
+use gtk::{gio, glib};
+use std::io::Read;
+
+mod imp {
+    use super::DateEntry;
+    use adw::subclass::prelude::*;
+    use gtk::glib;
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/date-time-dialog.ui")]
+    pub struct MemoriesDateTimeDialog {
+        pub(super) entries: RefCell<Vec<DateEntry>>,
+        #[template_child]
+        pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) adjust_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) spinner: TemplateChild<gtk::Spinner>,
+        #[template_child]
+        pub(super) set_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) date_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub(super) calendar: TemplateChild<gtk::Calendar>,
+        #[template_child]
+        pub(super) hour_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) minute_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) offset_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) days_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) hours_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) minutes_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) preview_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) preview_list: TemplateChild<gtk::ListBox>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesDateTimeDialog {
+        const NAME: &'static str = "MemoriesDateTimeDialog";
+        type Type = super::MemoriesDateTimeDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesDateTimeDialog {}
+    impl WidgetImpl for MemoriesDateTimeDialog {}
+    impl AdwDialogImpl for MemoriesDateTimeDialog {}
+}
+
+/// A file to adjust the date of, with its current date if it is known.
+#[derive(Debug)]
+pub struct DateEntry {
+    file: gio::File,
+    date: Option<glib::DateTime>,
+}
+
+glib::wrapper! {
+    pub struct MemoriesDateTimeDialog(ObjectSubclass<imp::MemoriesDateTimeDialog>)
+        @extends gtk::Widget, adw::Dialog;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesDateTimeDialog {
+    /// Creates a new dialog for the given files, and queries their
+    /// current dates in the background. A single file is set to a new
+    /// date, and multiple files are shifted by an offset instead.
+    pub fn new(files: &[gio::File]) -> Self {
+        let obj: Self = glib::Object::new();
+
+        let entries: Vec<DateEntry> = files
+            .iter()
+            .map(|file: &gio::File| DateEntry {
+                file: file.clone(),
+                date: None,
+            })
+            .collect();
+
+        obj.imp().entries.replace(entries);
+        obj.imp().set_group.set_visible(files.len() == 1);
+        obj.imp().offset_group.set_visible(files.len() != 1);
+        obj.imp().preview_group.set_title(&ngettext_f(
+            "Adjusting {COUNT} Item",
+            "Adjusting {COUNT} Items",
+            files.len().try_into().unwrap_or(u32::MAX),
+            &[("COUNT", &files.len().to_string())],
+        ));
+        obj.update_preview();
+
+        glib::spawn_future_local(clone!(
+            #[weak]
+            obj,
+            async move {
+                obj.query_dates().await;
+
+                // A single item starts out at its current date.
+                let first_date: Option<glib::DateTime> = match obj.imp().entries.borrow().as_slice() {
+                    [entry] => entry.date.clone(),
+                    _ => None,
+                };
+                if let Some(date) = first_date {
+                    obj.imp().calendar.select_day(&date);
+                    obj.imp().hour_row.set_value(date.hour().into());
+                    obj.imp().minute_row.set_value(date.minute().into());
+                }
+                obj.update_preview();
+            }
+        ));
+        obj
+    }
+
+    /// Queries the EXIF original date of each file, or its modification date if it has none.
+    async fn query_dates(&self) {
+        let files: Vec<gio::File> = self
+            .imp()
+            .entries
+            .borrow()
+            .iter()
+            .map(|entry: &DateEntry| entry.file.clone())
+            .collect();
+
+        for (i, file) in files.into_iter().enumerate() {
+            let date: Option<glib::DateTime> = gio::spawn_blocking(move || {
+                Self::original_date(&file).or_else(|| {
+                    file.query_info(
+                        gio::FILE_ATTRIBUTE_TIME_MODIFIED,
+                        gio::FileQueryInfoFlags::NONE,
+                        gio::Cancellable::NONE,
+                    )
+                    .ok()?
+                    .modification_date_time()?
+                    .to_local()
+                    .ok()
+                })
+            })
+            .await
+            .ok()
+            .flatten();
+
+            if let Some(entry) = self.imp().entries.borrow_mut().get_mut(i) {
+                entry.date = date;
+            }
+        }
+    }
+
+    /// Returns the EXIF `DateTimeOriginal` value of a file, as a local date.
+    fn original_date(file: &gio::File) -> Option<glib::DateTime> {
+        let mut file_head: Vec<u8> = vec![];
+        std::fs::File::open(file.path()?)
+            .ok()?
+            .take(EXIF_DATE_READ_SIZE)
+            .read_to_end(&mut file_head)
+            .ok()?;
+
+        let date: exif::DateTime = metadata::read_date_time_original(&file_head)?;
+
+        glib::DateTime::from_local(
+            date.year.into(),
+            date.month.into(),
+            date.day.into(),
+            date.hour.into(),
+            date.minute.into(),
+            date.second.into(),
+        )
+        .ok()
+    }
+
+    /// Returns the new date of each entry, or `None` for entries that have
+    /// no current date to be shifted by the offset.
+    fn new_dates(&self) -> Vec<Option<glib::DateTime>> {
+        let imp = self.imp();
+        let entries = imp.entries.borrow();
+
+        if let [_] = entries.as_slice() {
+            let day: glib::DateTime = imp.calendar.date();
+
+            return vec![glib::DateTime::from_local(
+                day.year(),
+                day.month(),
+                day.day_of_month(),
+                imp.hour_row.value() as i32,
+                imp.minute_row.value() as i32,
+                0.0,
+            )
+            .ok()];
+        }
+        let days: i32 = imp.days_row.value() as i32;
+        let hours: i32 = imp.hours_row.value() as i32;
+        let minutes: i32 = imp.minutes_row.value() as i32;
+
+        entries
+            .iter()
+            .map(|entry: &DateEntry| {
+                entry
+                    .date
+                    .as_ref()?
+                    .add_days(days)
+                    .and_then(|date: glib::DateTime| date.add_hours(hours))
+                    .and_then(|date: glib::DateTime| date.add_minutes(minutes))
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// Rebuilds the list of files with their new dates.
+    #[template_callback]
+    fn update_preview(&self) {
+        let imp = self.imp();
+        imp.date_button
+            .set_label(&imp.calendar.date().format("%x").unwrap_or_default());
+
+        let preview_list: &gtk::ListBox = &imp.preview_list;
+        preview_list.remove_all();
+
+        let new_dates: Vec<Option<glib::DateTime>> = self.new_dates();
+        let mut adjustable: bool = false;
+
+        for (entry, new_date) in imp.entries.borrow().iter().zip(new_dates.iter()) {
+            let name: String = entry
+                .file
+                .basename()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+
+            let new_date: Option<String> = new_date.as_ref().and_then(format::date_time);
+            adjustable |= new_date.is_some();
+
+            let row: adw::ActionRow = adw::ActionRow::builder()
+                .title(glib::markup_escape_text(&name))
+                .subtitle(new_date.unwrap_or_else(|| gettext("Unknown date")))
+                .build();
+
+            preview_list.append(&row);
+        }
+        imp.adjust_button.set_sensitive(adjustable);
+    }
+
+    #[template_callback]
+    fn cancel_clicked(&self) {
+        self.close();
+    }
+
+    #[template_callback]
+    fn adjust_clicked(&self) {
+        let new_dates: Vec<Option<glib::DateTime>> = self.new_dates();
+        let files: Vec<gio::File> = self
+            .imp()
+            .entries
+            .borrow()
+            .iter()
+            .map(|entry: &DateEntry| entry.file.clone())
+            .collect();
+
+        self.imp().adjust_button.set_sensitive(false);
+        self.imp().spinner.set_visible(true);
+        self.imp().spinner.start();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let mut failed: u32 = 0;
+
+                for (file, date) in files.into_iter().zip(new_dates) {
+                    let Some(date) = date else {
+                        continue;
+                    };
+                    let uri: glib::GString = file.uri();
+
+                    let result: Result<(), glib::Error> =
+                        gio::spawn_blocking(move || Self::set_file_date(&file, &date))
+                            .await
+                            .unwrap_or_else(|_| {
+                                Err(glib::Error::new(gio::IOErrorEnum::Failed, "Thread panicked"))
+                            });
+
+                    if let Err(glib_error) = result {
+                        g_warning!(
+                            "DateTimeDialog",
+                            "Failed to adjust the date of '{}': {}",
+                            uri,
+                            glib_error
+                        );
+                        failed += 1;
+                    }
+                }
+                this.imp().spinner.stop();
+                this.imp().spinner.set_visible(false);
+
+                // The library is sorted by modification date, so refresh it to move the items.
+                MemoriesApplication::default().library_list_model().refresh();
+
+                if failed == 0 {
+                    this.close();
+                    return;
+                }
+                let toast: adw::Toast = adw::Toast::new(&ngettext_f(
+                    "Failed to adjust the date of {COUNT} item",
+                    "Failed to adjust the date of {COUNT} items",
+                    failed,
+                    &[("COUNT", &failed.to_string())],
+                ));
+                this.imp().toast_overlay.add_toast(toast);
+            }
+        ));
+    }
+
+    /// Writes the date to the EXIF data of a JPEG file, and sets its modification date.
+    fn set_file_date(file: &gio::File, date: &glib::DateTime) -> Result<(), glib::Error> {
+        let is_jpeg: bool = file
+            .basename()
+            .and_then(|name| name.extension().map(|ext| ext.to_string_lossy().to_lowercase()))
+            .is_some_and(|ext: String| matches!(ext.as_str(), "jpg" | "jpeg"));
+
+        // Dates are local, since EXIF dates have no time zone.
+        if is_jpeg {
+            let exif_date: exif::DateTime = exif::DateTime {
+                year: date.year().try_into().unwrap_or_default(),
+                month: date.month().try_into().unwrap_or_default(),
+                day: date.day_of_month().try_into().unwrap_or_default(),
+                hour: date.hour().try_into().unwrap_or_default(),
+                minute: date.minute().try_into().unwrap_or_default(),
+                second: date.second().try_into().unwrap_or_default(),
+                nanosecond: None,
+                offset: None,
+            };
+            let (contents, _) = file.load_contents(gio::Cancellable::NONE)?;
+
+            let contents: Vec<u8> =
+                metadata::write_date_time_original(&contents, &exif_date).map_err(|err: exif::Error| {
+                    glib::Error::new(gio::IOErrorEnum::InvalidData, &err.to_string())
+                })?;
+
+            file.replace_contents(
+                &contents,
+                None,
+                false,
+                gio::FileCreateFlags::NONE,
+                gio::Cancellable::NONE,
+            )?;
+        }
+        file.set_attribute_uint64(
+            gio::FILE_ATTRIBUTE_TIME_MODIFIED,
+            date.to_unix().try_into().unwrap_or_default(),
+            gio::FileQueryInfoFlags::NONE,
+            gio::Cancellable::NONE,
+        )
+    }
+}
@@ -0,0 +1,270 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! D-Bus interface of Memories, which lets scripts and other apps automate the
+//! library: searching it, opening media, tagging media, and rescanning it. The
+//! interface is exported on the object path of the application, for example:
+//!
+//! ```sh
+//! gdbus call --session --dest com.maxrdz.Memories --object-path /com/maxrdz/Memories \
+//!     --method com.maxrdz.Memories.SearchItems "tag:Holidays beach"
+//! ```
+
+use crate::application::MemoriesApplication;
+use crate::library::hidden_filter_func;
+use crate::library::list_model::MemoriesLibraryListModel;
+use crate::library::media_item::MemoriesMediaItem;
+use crate::library::media_viewer::ViewerContentType;
+use crate::library::search::SearchQuery;
+use crate::library::viewer_window::MemoriesViewerWindow;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::g_debug;
+use gtk::{gio, glib};
+use std::path::Path;
+
+/// Name of the D-Bus interface, which is the same for development builds.
+const DBUS_INTERFACE_NAME: &str = "com.maxrdz.Memories";
+
+/// Introspection data of the D-Bus interface. Search queries use the syntax of the
+/// library search bar, and files are given as URIs or as absolute paths.
+const DBUS_INTERFACE_XML: &str = r#"
+<node>
+  <interface name="com.maxrdz.Memories">
+    <method name="SearchItems">
+      <arg name="query" type="s" direction="in"/>
+      <arg name="uris" type="as" direction="out"/>
+    </method>
+    <method name="OpenFile">
+      <arg name="file" type="s" direction="in"/>
+    </method>
+    <method name="AddTag">
+      <arg name="files" type="as" direction="in"/>
+      <arg name="tag" type="s" direction="in"/>
+    </method>
+    <method name="TriggerRescan"/>
+  </interface>
+</node>
+"#;
+
+/// Exports the D-Bus interface on the object path of the application.
+pub fn register(
+    connection: &gio::DBusConnection,
+    object_path: &str,
+) -> Result<gio::RegistrationId, glib::Error> {
+    let node_info: gio::DBusNodeInfo = gio::DBusNodeInfo::for_xml(DBUS_INTERFACE_XML)?;
+    let interface_info: gio::DBusInterfaceInfo = node_info
+        .lookup_interface(DBUS_INTERFACE_NAME)
+        .expect("D-Bus interface not found in its introspection data.");
+
+    connection
+        .register_object(object_path, &interface_info)
+        .method_call(
+            |_, _, _, _, method: &str, parameters: glib::Variant, invocation: gio::DBusMethodInvocation| {
+                g_debug!("DBusService", "Method '{}' was called over D-Bus.", method);
+                let method: String = method.to_owned();
+
+                // Methods may read files, so they are handled without blocking the main loop.
+                glib::spawn_future_local(async move {
+                    invocation.return_result(handle_method_call(&method, &parameters).await);
+                });
+            },
+        )
+        .build()
+}
+
+async fn handle_method_call(
+    method: &str,
+    parameters: &glib::Variant,
+) -> Result<Option<glib::Variant>, glib::Error> {
+    let invalid_args = || glib::Error::new(gio::DBusError::InvalidArgs, "Invalid arguments");
+
+    match method {
+        "SearchItems" => {
+            let (query,) = parameters.get::<(String,)>().ok_or_else(invalid_args)?;
+            Ok(Some((search_items(&query)?,).to_variant()))
+        }
+        "OpenFile" => {
+            let (file,) = parameters.get::<(String,)>().ok_or_else(invalid_args)?;
+            open_file(&file_arg(&file)?).await?;
+            Ok(None)
+        }
+        "AddTag" => {
+            let (files, tag) = parameters
+                .get::<(Vec<String>, String)>()
+                .ok_or_else(invalid_args)?;
+            let files: Vec<gio::File> = files
+                .iter()
+                .map(|file: &String| file_arg(file))
+                .collect::<Result<_, _>>()?;
+            add_tag(&files, &tag)?;
+            Ok(None)
+        }
+        "TriggerRescan" => {
+            library_model()?.refresh();
+            Ok(None)
+        }
+        _ => Err(glib::Error::new(
+            gio::DBusError::UnknownMethod,
+            &format!("Unknown method '{}'", method),
+        )),
+    }
+}
+
+/// Returns the file of a URI or an absolute path given over D-Bus. Relative paths
+/// are rejected, as they would be resolved against the working directory of
+/// Memories, instead of that of the caller.
+fn file_arg(arg: &str) -> Result<gio::File, glib::Error> {
+    if Path::new(arg).is_absolute() {
+        Ok(gio::File::for_path(arg))
+    } else if glib::Uri::peek_scheme(arg).is_some() {
+        Ok(gio::File::for_uri(arg))
+    } else {
+        Err(glib::Error::new(
+            gio::DBusError::InvalidArgs,
+            &format!("'{}' is neither a URI nor an absolute path", arg),
+        ))
+    }
+}
+
+/// Returns the library model, which is only loaded once the window is shown.
+fn library_model() -> Result<MemoriesLibraryListModel, glib::Error> {
+    MemoriesApplication::default()
+        .imp()
+        .library_list_model
+        .get()
+        .cloned()
+        .ok_or_else(|| glib::Error::new(gio::DBusError::Failed, "The library is not loaded yet"))
+}
+
+/// Returns the URIs of the library media that match a search query. Like in the
+/// library, archived and private media is not searched, and neither is the
+/// media found by its meaning, since only the plain search terms are matched.
+fn search_items(query: &str) -> Result<Vec<String>, glib::Error> {
+    let memories: MemoriesApplication = MemoriesApplication::default();
+    let query: SearchQuery = SearchQuery::parse(query);
+    let not_archived = hidden_filter_func(&memories.archived());
+    let not_private = hidden_filter_func(&memories.private());
+
+    Ok(library_model()?
+        .iter::<glib::Object>()
+        .filter_map(Result::ok)
+        .filter(|item: &glib::Object| not_archived(item) && not_private(item))
+        .filter_map(|item: glib::Object| item.downcast::<MemoriesMediaItem>().ok())
+        .filter(|item: &MemoriesMediaItem| {
+            let file: gio::File = item.file();
+            let text: String = memories
+                .scan_service()
+                .recognized_text(&file.uri())
+                .unwrap_or_default();
+            let tags: Vec<String> = if query.has_tags() {
+                memories.tags(&file)
+            } else {
+                vec![]
+            };
+            query.is_empty() || query.matches(&item.basename(), &text, &tags)
+        })
+        .map(|item: MemoriesMediaItem| item.uri())
+        .collect())
+}
+
+/// Opens a media file in a new viewer window. Private media can
+/// only be opened from the Private album, once it is unlocked.
+/// Media with a missing or unknown extension is told by its contents.
+async fn open_file(file: &gio::File) -> Result<(), glib::Error> {
+    let memories: MemoriesApplication = MemoriesApplication::default();
+
+    if memories
+        .private()
+        .iter()
+        .any(|uri: &glib::GStringPtr| uri.as_str() == file.uri().as_str())
+    {
+        return Err(glib::Error::new(
+            gio::DBusError::AccessDenied,
+            "Private media can only be opened from the Private album",
+        ));
+    }
+    if !file.query_exists(None::<&gio::Cancellable>) {
+        return Err(glib::Error::new(
+            gio::DBusError::FileNotFound,
+            &format!("'{}' does not exist", file.uri()),
+        ));
+    }
+    let content_type: ViewerContentType = match ViewerContentType::for_file(file).await {
+        ViewerContentType::Invalid => {
+            return Err(glib::Error::new(
+                gio::DBusError::InvalidArgs,
+                &format!("'{}' is not a supported media file", file.uri()),
+            ))
+        }
+        content_type => content_type,
+    };
+
+    MemoriesViewerWindow::new(&memories, file, &content_type).present();
+    Ok(())
+}
+
+/// Adds a tag to each of the files, which keep their other tags.
+fn add_tag(files: &[gio::File], tag: &str) -> Result<(), glib::Error> {
+    let tag: &str = tag.trim();
+
+    if tag.is_empty() {
+        return Err(glib::Error::new(gio::DBusError::InvalidArgs, "The tag is empty"));
+    }
+    let memories: MemoriesApplication = MemoriesApplication::default();
+
+    for file in files {
+        let mut file_tags: Vec<String> = memories.tags(file);
+
+        if !file_tags.iter().any(|file_tag: &String| file_tag == tag) {
+            file_tags.push(tag.to_string());
+            memories.set_tags(file, file_tags);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn introspection_data() {
+        let node_info: gio::DBusNodeInfo = gio::DBusNodeInfo::for_xml(DBUS_INTERFACE_XML).unwrap();
+        let interface_info: gio::DBusInterfaceInfo = node_info.lookup_interface(DBUS_INTERFACE_NAME).unwrap();
+
+        for method in ["SearchItems", "OpenFile", "AddTag", "TriggerRescan"] {
+            assert!(interface_info.lookup_method(method).is_some());
+        }
+    }
+
+    #[test]
+    fn file_arguments() {
+        assert_eq!(file_arg("/tmp/photo.jpg").unwrap().uri(), "file:///tmp/photo.jpg");
+        assert_eq!(
+            file_arg("sftp://host/photo.jpg").unwrap().uri(),
+            "sftp://host/photo.jpg"
+        );
+        assert!(file_arg("photo.jpg")
+            .unwrap_err()
+            .matches(gio::DBusError::InvalidArgs));
+        assert!(file_arg("").is_err());
+    }
+}
@@ -0,0 +1,305 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::globals::{EXPORT_RESIZE_PRESETS, FFMPEG_BINARY};
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use async_process::{Command, Output};
+use gettextrs::gettext;
+use glib::{clone, g_warning};
+use gtk::{gio, glib};
+use std::path::PathBuf;
+
+mod imp {
+    use adw::subclass::prelude::*;
+    use gtk::{gio, glib};
+    use std::cell::OnceCell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/export-dialog.ui")]
+    pub struct MemoriesExportDialog {
+        pub(super) source_file: OnceCell<gio::File>,
+        #[template_child]
+        pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) export_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) spinner: TemplateChild<gtk::Spinner>,
+        #[template_child]
+        pub(super) format_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub(super) quality_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub(super) quality_scale: TemplateChild<gtk::Scale>,
+        #[template_child]
+        pub(super) size_row: TemplateChild<adw::ComboRow>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesExportDialog {
+        const NAME: &'static str = "MemoriesExportDialog";
+        type Type = super::MemoriesExportDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesExportDialog {}
+    impl WidgetImpl for MemoriesExportDialog {}
+    impl AdwDialogImpl for MemoriesExportDialog {}
+}
+
+/// Enum that represents the image formats that media can be
+/// exported to. Variant order matches the format combo row model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Jpeg,
+    Png,
+    Webp,
+    Avif,
+}
+
+impl ExportFormat {
+    pub fn from_index(index: u32) -> ExportFormat {
+        match index {
+            1 => ExportFormat::Png,
+            2 => ExportFormat::Webp,
+            3 => ExportFormat::Avif,
+            _ => ExportFormat::Jpeg,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match *self {
+            ExportFormat::Jpeg => "JPEG",
+            ExportFormat::Png => "PNG",
+            ExportFormat::Webp => "WebP",
+            ExportFormat::Avif => "AVIF",
+        }
+    }
+
+    pub fn extension(&self) -> &str {
+        match *self {
+            ExportFormat::Jpeg => "jpg",
+            ExportFormat::Png => "png",
+            ExportFormat::Webp => "webp",
+            ExportFormat::Avif => "avif",
+        }
+    }
+
+    pub fn mime_type(&self) -> &str {
+        match *self {
+            ExportFormat::Jpeg => "image/jpeg",
+            ExportFormat::Png => "image/png",
+            ExportFormat::Webp => "image/webp",
+            ExportFormat::Avif => "image/avif",
+        }
+    }
+
+    /// Returns `true` if the format's encoder has a quality setting.
+    pub fn has_quality(&self) -> bool {
+        !matches!(*self, ExportFormat::Png)
+    }
+
+    /// Returns the ffmpeg encoder arguments for the format,
+    /// given a `quality` value in the range of 1 to 100.
+    pub fn ffmpeg_arguments(&self, quality: u32) -> Vec<String> {
+        let quality: u32 = quality.clamp(1, 100);
+
+        match *self {
+            // The JPEG encoder's `q:v` scale ranges from 2 (best) to 31 (worst).
+            ExportFormat::Jpeg => vec!["-q:v".into(), (2 + (100 - quality) * 29 / 99).to_string()],
+            ExportFormat::Png => vec![],
+            ExportFormat::Webp => vec!["-quality".into(), quality.to_string()],
+            // The AV1 encoder's CRF scale ranges from 0 (best) to 63 (worst).
+            ExportFormat::Avif => vec![
+                "-c:v".into(),
+                "libaom-av1".into(),
+                "-still-picture".into(),
+                "1".into(),
+                "-crf".into(),
+                ((100 - quality) * 63 / 99).to_string(),
+            ],
+        }
+    }
+}
+
+/// Returns an ffmpeg video filter that downscales an image so that its
+/// longest edge is at most `max_edge` pixels, preserving its aspect ratio.
+pub fn ffmpeg_scale_filter(max_edge: u32) -> String {
+    format!(
+        "scale=w='min({0},iw)':h='min({0},ih)':force_original_aspect_ratio=decrease",
+        max_edge
+    )
+}
+
+glib::wrapper! {
+    pub struct MemoriesExportDialog(ObjectSubclass<imp::MemoriesExportDialog>)
+        @extends gtk::Widget, adw::Dialog;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesExportDialog {
+    pub fn new(source_file: &gio::File) -> Self {
+        let obj: Self = glib::Object::new();
+        let _ = obj.imp().source_file.set(source_file.clone());
+        obj
+    }
+
+    fn selected_format(&self) -> ExportFormat {
+        ExportFormat::from_index(self.imp().format_row.selected())
+    }
+
+    #[template_callback]
+    fn format_changed(&self) {
+        let has_quality: bool = self.selected_format().has_quality();
+        self.imp().quality_row.set_sensitive(has_quality);
+    }
+
+    #[template_callback]
+    fn cancel_clicked(&self) {
+        self.close();
+    }
+
+    #[template_callback]
+    fn export_clicked(&self) {
+        let format: ExportFormat = self.selected_format();
+        let source: gio::File = self.imp().source_file.get().unwrap().clone();
+
+        let file_stem: String = source
+            .basename()
+            .and_then(|name: PathBuf| name.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+            .unwrap_or_default();
+
+        let filter: gtk::FileFilter = gtk::FileFilter::new();
+        filter.set_name(Some(format.name()));
+        filter.add_mime_type(format.mime_type());
+
+        let filters: gio::ListStore = gio::ListStore::new::<gtk::FileFilter>();
+        filters.append(&filter);
+
+        let file_dialog: gtk::FileDialog = gtk::FileDialog::builder()
+            .title(gettext("Export As"))
+            .initial_name(format!("{}.{}", file_stem, format.extension()))
+            .filters(&filters)
+            .modal(true)
+            .build();
+
+        let window: Option<gtk::Window> = self.root().and_downcast();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                // An error is returned if the user dismisses the file chooser.
+                if let Ok(destination) = file_dialog.save_future(window.as_ref()).await {
+                    this.export_to_file(&source, &destination, format).await;
+                }
+            }
+        ));
+    }
+
+    /// Converts the source file into the given format with ffmpeg
+    /// and writes the result to the `destination` file.
+    async fn export_to_file(&self, source: &gio::File, destination: &gio::File, format: ExportFormat) {
+        let (Some(source_path), Some(destination_path)) = (source.path(), destination.path()) else {
+            self.show_error_toast();
+            return;
+        };
+        let quality: u32 = self.imp().quality_scale.value() as u32;
+        let size_index: usize = self.imp().size_row.selected().try_into().unwrap();
+
+        let mut ffmpeg_command: Command = Command::new(FFMPEG_BINARY);
+
+        ffmpeg_command
+            .arg("-y")
+            .arg("-i")
+            .arg(source_path)
+            .args(["-loglevel", "error", "-frames:v", "1"]);
+
+        if let Some(Some(max_edge)) = EXPORT_RESIZE_PRESETS.get(size_index) {
+            ffmpeg_command.args(["-vf", &ffmpeg_scale_filter(*max_edge)]);
+        }
+        ffmpeg_command
+            .args(format.ffmpeg_arguments(quality))
+            .arg(destination_path);
+
+        self.imp().export_button.set_sensitive(false);
+        self.imp().spinner.set_visible(true);
+        self.imp().spinner.start();
+
+        let ffmpeg_output: std::io::Result<Output> = ffmpeg_command.output().await;
+
+        self.imp().spinner.stop();
+        self.imp().spinner.set_visible(false);
+        self.imp().export_button.set_sensitive(true);
+
+        match ffmpeg_output {
+            Ok(output) if output.status.success() => {
+                self.close();
+            }
+            Ok(output) => {
+                g_warning!(
+                    "ExportDialog",
+                    "FFmpeg failed to export the image: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                self.show_error_toast();
+            }
+            Err(io_error) => {
+                g_warning!("ExportDialog", "Failed to execute ffmpeg: {}", io_error);
+                self.show_error_toast();
+            }
+        }
+    }
+
+    fn show_error_toast(&self) {
+        let toast: adw::Toast = adw::Toast::new(&gettext("Failed to export the image"));
+        self.imp().toast_overlay.add_toast(toast);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_format_from_index() {
+        assert_eq!(ExportFormat::from_index(0), ExportFormat::Jpeg);
+        assert_eq!(ExportFormat::from_index(3), ExportFormat::Avif);
+        assert_eq!(ExportFormat::from_index(42), ExportFormat::Jpeg);
+    }
+
+    #[test]
+    fn ffmpeg_quality_arguments() {
+        assert_eq!(ExportFormat::Jpeg.ffmpeg_arguments(100), vec!["-q:v", "2"]);
+        assert_eq!(ExportFormat::Jpeg.ffmpeg_arguments(1), vec!["-q:v", "31"]);
+        assert_eq!(ExportFormat::Webp.ffmpeg_arguments(250), vec!["-quality", "100"]);
+        assert!(ExportFormat::Png.ffmpeg_arguments(50).is_empty());
+        assert_eq!(ExportFormat::Avif.ffmpeg_arguments(1).last().unwrap(), "63");
+    }
+}
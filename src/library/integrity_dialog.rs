@@ -0,0 +1,381 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Dialog that records checksums of the media in the library, and verifies
+//! the media against them later, listing the files that were corrupted or
+//! that went missing, which can then be excluded or checksummed again.
+
+use crate::application::{MemoriesApplication, RunningJob};
+use crate::i18n::{gettext_f, ngettext_f};
+use crate::library::media_item::MemoriesMediaItem;
+use crate::util::checksum::{self, FileChecksum, Verification};
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::{clone, g_warning};
+use gtk::{gio, glib};
+use std::path::PathBuf;
+
+mod imp {
+    use crate::util::checksum::ChecksumIndex;
+    use adw::subclass::prelude::*;
+    use gtk::{gio, glib};
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/integrity-dialog.ui")]
+    pub struct MemoriesIntegrityDialog {
+        pub(super) index: RefCell<ChecksumIndex>,
+        /// Cancels recording or verifying checksums. `None` while neither runs.
+        pub(super) cancellable: RefCell<Option<gio::Cancellable>>,
+        #[template_child]
+        pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) content_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) record_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub(super) verify_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) progress_status_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub(super) progress_bar: TemplateChild<gtk::ProgressBar>,
+        #[template_child]
+        pub(super) results_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) intact_status_page: TemplateChild<adw::StatusPage>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesIntegrityDialog {
+        const NAME: &'static str = "MemoriesIntegrityDialog";
+        type Type = super::MemoriesIntegrityDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesIntegrityDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.index.replace(ChecksumIndex::load());
+            self.obj().update_overview();
+        }
+    }
+
+    impl WidgetImpl for MemoriesIntegrityDialog {}
+    impl AdwDialogImpl for MemoriesIntegrityDialog {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesIntegrityDialog(ObjectSubclass<imp::MemoriesIntegrityDialog>)
+        @extends gtk::Widget, adw::Dialog;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesIntegrityDialog {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    fn update_overview(&self) {
+        let count: usize = self.imp().index.borrow().len();
+
+        self.imp().record_row.set_subtitle(&match count {
+            0 => gettext("No checksums are recorded yet"),
+            _ => ngettext_f(
+                "{COUNT} file has a checksum. New media is added when recording again.",
+                "{COUNT} files have a checksum. New media is added when recording again.",
+                count.try_into().unwrap_or(u32::MAX),
+                &[("COUNT", &count.to_string())],
+            ),
+        });
+        self.imp().verify_button.set_sensitive(count > 0);
+    }
+
+    fn save_index(&self) {
+        if let Err(err) = self.imp().index.borrow().save() {
+            g_warning!("IntegrityDialog", "Failed to save checksum index: {}", err);
+            self.imp()
+                .toast_overlay
+                .add_toast(adw::Toast::new(&gettext("Could not save the checksums")));
+        }
+    }
+
+    /// Shows the progress page, and returns the cancellable that stops the work.
+    fn start(&self, title: &str) -> gio::Cancellable {
+        let cancellable: gio::Cancellable = gio::Cancellable::new();
+        self.imp().cancellable.replace(Some(cancellable.clone()));
+        self.set_can_close(false);
+
+        self.imp().progress_status_page.set_title(title);
+        self.imp().progress_bar.set_fraction(0.0);
+        self.imp().content_stack.set_visible_child_name("progress");
+        cancellable
+    }
+
+    fn set_progress(&self, done: usize, total: usize) {
+        let progress_bar: &gtk::ProgressBar = &self.imp().progress_bar;
+
+        progress_bar.set_fraction(done as f64 / total.max(1) as f64);
+        progress_bar.set_text(Some(&gettext_f(
+            "{DONE} of {TOTAL}",
+            &[("DONE", &done.to_string()), ("TOTAL", &total.to_string())],
+        )));
+    }
+
+    fn finish(&self) {
+        self.imp().cancellable.take();
+        self.set_can_close(true);
+        self.save_index();
+    }
+
+    /// Closing the dialog while working cancels the work instead.
+    #[template_callback]
+    fn close_attempted(&self) {
+        if let Some(cancellable) = self.imp().cancellable.borrow().as_ref() {
+            cancellable.cancel();
+        }
+    }
+
+    /// Records the checksums of the library media that have none yet.
+    #[template_callback]
+    fn record_clicked(&self) {
+        let paths: Vec<(String, PathBuf)> = MemoriesApplication::default()
+            .library_list_model()
+            .iter::<MemoriesMediaItem>()
+            .filter_map(Result::ok)
+            .filter(|item: &MemoriesMediaItem| !self.imp().index.borrow().contains(&item.uri()))
+            .filter_map(|item: MemoriesMediaItem| Some((item.uri(), item.file().path()?)))
+            .collect();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let cancellable: gio::Cancellable = this.start(&gettext("Recording Checksums…"));
+                let _job: RunningJob =
+                    MemoriesApplication::default().start_job(&gettext("Recording checksums"));
+
+                let total: usize = paths.len();
+                let mut recorded: u32 = 0;
+
+                for (i, (uri, path)) in paths.into_iter().enumerate() {
+                    if cancellable.is_cancelled() {
+                        break;
+                    }
+                    this.set_progress(i, total);
+
+                    match gio::spawn_blocking(move || checksum::record(&path)).await {
+                        Ok(Ok(file_checksum)) => {
+                            this.imp().index.borrow_mut().insert(uri, file_checksum);
+                            recorded += 1;
+                        }
+                        Ok(Err(err)) => {
+                            g_warning!("IntegrityDialog", "Failed to checksum '{}': {}", uri, err)
+                        }
+                        Err(_) => g_warning!("IntegrityDialog", "Checksum thread panicked."),
+                    }
+                }
+                this.finish();
+                this.update_overview();
+                this.imp().content_stack.set_visible_child_name("overview");
+
+                this.imp().toast_overlay.add_toast(adw::Toast::new(&ngettext_f(
+                    "Recorded the checksum of {COUNT} file",
+                    "Recorded the checksums of {COUNT} files",
+                    recorded,
+                    &[("COUNT", &recorded.to_string())],
+                )));
+            }
+        ));
+    }
+
+    /// Verifies every file that has a checksum, and lists the damaged files.
+    /// Files that were modified since are checksummed again.
+    #[template_callback]
+    fn verify_clicked(&self) {
+        let checksums: Vec<(String, FileChecksum)> = self.imp().index.borrow().checksums();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let cancellable: gio::Cancellable = this.start(&gettext("Verifying Files…"));
+                let _job: RunningJob = MemoriesApplication::default().start_job(&gettext("Verifying files"));
+
+                let total: usize = checksums.len();
+                let mut damaged: Vec<(gio::File, Verification)> = vec![];
+
+                for (i, (uri, recorded)) in checksums.into_iter().enumerate() {
+                    if cancellable.is_cancelled() {
+                        break;
+                    }
+                    this.set_progress(i, total);
+
+                    let file: gio::File = gio::File::for_uri(&uri);
+                    let Some(path) = file.path() else {
+                        continue;
+                    };
+                    let verification: Verification =
+                        match gio::spawn_blocking(move || checksum::verify(&path, &recorded)).await {
+                            Ok(verification) => verification,
+                            Err(_) => {
+                                g_warning!("IntegrityDialog", "Checksum thread panicked.");
+                                continue;
+                            }
+                        };
+                    match verification {
+                        Verification::Intact => (),
+                        Verification::Modified(file_checksum) => {
+                            this.imp().index.borrow_mut().insert(uri, file_checksum);
+                        }
+                        Verification::Corrupted | Verification::Missing => damaged.push((file, verification)),
+                    }
+                }
+                let cancelled: bool = cancellable.is_cancelled();
+                this.finish();
+
+                if cancelled {
+                    this.imp().content_stack.set_visible_child_name("overview");
+                } else {
+                    this.show_results(damaged, total);
+                }
+            }
+        ));
+    }
+
+    fn show_results(&self, damaged: Vec<(gio::File, Verification)>, verified: usize) {
+        if damaged.is_empty() {
+            self.imp().intact_status_page.set_description(Some(&ngettext_f(
+                "{COUNT} file was verified",
+                "{COUNT} files were verified",
+                verified.try_into().unwrap_or(u32::MAX),
+                &[("COUNT", &verified.to_string())],
+            )));
+            self.imp().content_stack.set_visible_child_name("intact");
+            return;
+        }
+        for (file, verification) in damaged {
+            self.imp()
+                .results_group
+                .add(&self.damaged_row(&file, &verification));
+        }
+        self.imp().content_stack.set_visible_child_name("results");
+    }
+
+    /// Creates the row of a damaged file, with buttons that exclude the file
+    /// from verification, or that record its current checksum if it is fine.
+    fn damaged_row(&self, file: &gio::File, verification: &Verification) -> adw::ActionRow {
+        let row: adw::ActionRow = adw::ActionRow::builder()
+            .title(glib::markup_escape_text(
+                &file.basename().unwrap_or_default().to_string_lossy(),
+            ))
+            .subtitle(match verification {
+                Verification::Missing => gettext("Missing"),
+                _ => gettext("Corrupted or unreadable"),
+            })
+            .build();
+
+        let exclude_button: gtk::Button = gtk::Button::builder()
+            .icon_name("list-remove-symbolic")
+            .tooltip_text(gettext("Exclude From Verification"))
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .build();
+
+        exclude_button.connect_clicked(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            row,
+            #[strong]
+            file,
+            move |_: &gtk::Button| {
+                this.imp().index.borrow_mut().remove(file.uri().as_str());
+                this.save_index();
+                this.imp().results_group.remove(&row);
+                this.update_overview();
+            }
+        ));
+        row.add_suffix(&exclude_button);
+
+        if let Some(path) = file.path().filter(|_| *verification != Verification::Missing) {
+            let record_button: gtk::Button = gtk::Button::builder()
+                .icon_name("view-refresh-symbolic")
+                .tooltip_text(gettext("Record Checksum Again"))
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+
+            record_button.connect_clicked(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                row,
+                #[strong]
+                file,
+                move |_: &gtk::Button| {
+                    let path: PathBuf = path.clone();
+
+                    glib::spawn_future_local(clone!(
+                        #[weak]
+                        this,
+                        #[weak]
+                        row,
+                        #[strong]
+                        file,
+                        async move {
+                            match gio::spawn_blocking(move || checksum::record(&path)).await {
+                                Ok(Ok(file_checksum)) => {
+                                    this.imp()
+                                        .index
+                                        .borrow_mut()
+                                        .insert(file.uri().to_string(), file_checksum);
+                                    this.save_index();
+                                    this.imp().results_group.remove(&row);
+                                }
+                                _ => this
+                                    .imp()
+                                    .toast_overlay
+                                    .add_toast(adw::Toast::new(&gettext("The file could not be read"))),
+                            }
+                        }
+                    ));
+                }
+            ));
+            row.add_suffix(&record_button);
+        }
+        row
+    }
+}
+
+impl Default for MemoriesIntegrityDialog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
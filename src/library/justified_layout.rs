@@ -0,0 +1,258 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use gtk::glib;
+
+mod imp {
+    use super::{justified_rows, JustifiedRow};
+    use gtk::glib;
+    use gtk::prelude::*;
+    use gtk::subclass::prelude::*;
+    use std::cell::Cell;
+
+    /// `GtkLayoutManager` that lays out children in rows of equal height,
+    /// like Flickr's justified layout. Children keep the aspect ratio of
+    /// their natural size, and each row is scaled to fill the full width.
+    #[derive(Debug, glib::Properties)]
+    #[properties(wrapper_type = super::MemoriesJustifiedLayout)]
+    pub struct MemoriesJustifiedLayout {
+        /// Height that rows are scaled from to fill the available width.
+        #[property(get, set = Self::set_row_height)]
+        row_height: Cell<i32>,
+        #[property(get, set)]
+        spacing: Cell<i32>,
+    }
+
+    impl Default for MemoriesJustifiedLayout {
+        fn default() -> Self {
+            Self {
+                row_height: Cell::new(96),
+                spacing: Cell::new(0),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesJustifiedLayout {
+        const NAME: &'static str = "MemoriesJustifiedLayout";
+        type ParentType = gtk::LayoutManager;
+        type Type = super::MemoriesJustifiedLayout;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for MemoriesJustifiedLayout {}
+
+    impl LayoutManagerImpl for MemoriesJustifiedLayout {
+        fn request_mode(&self, _: &gtk::Widget) -> gtk::SizeRequestMode {
+            gtk::SizeRequestMode::HeightForWidth
+        }
+
+        fn measure(
+            &self,
+            widget: &gtk::Widget,
+            orientation: gtk::Orientation,
+            for_size: i32,
+        ) -> (i32, i32, i32, i32) {
+            if orientation == gtk::Orientation::Horizontal || for_size < 0 {
+                return (0, self.row_height.get(), -1, -1);
+            }
+            let rows: Vec<JustifiedRow> = self.rows(widget, for_size);
+            let spacing: i32 = self.spacing.get() * (rows.len() as i32 - 1).max(0);
+            let height: i32 = rows
+                .iter()
+                .map(|row: &JustifiedRow| row.height.round() as i32)
+                .sum();
+
+            (height + spacing, height + spacing, -1, -1)
+        }
+
+        fn allocate(&self, widget: &gtk::Widget, width: i32, _: i32, _: i32) {
+            let children: Vec<gtk::Widget> = Self::children(widget);
+            let spacing: i32 = self.spacing.get();
+            let mut y: i32 = 0;
+
+            for row in self.rows(widget, width) {
+                let row_height: i32 = row.height.round() as i32;
+                let mut x: f64 = 0.0;
+
+                for (i, child) in children[row.start..row.end].iter().enumerate() {
+                    let child_x: i32 = x.round() as i32;
+                    x += Self::aspect_ratio(child) * row.height;
+
+                    // The last child of a full row takes the remaining width, so
+                    // that rounding never leaves a gap at the end of the row.
+                    let child_width: i32 = if row.filled && row.start + i == row.end - 1 {
+                        width - child_x
+                    } else {
+                        x.round() as i32 - child_x
+                    };
+                    x += spacing as f64;
+
+                    child.size_allocate(&gtk::Allocation::new(child_x, y, child_width, row_height), -1);
+                }
+                y += row_height + spacing;
+            }
+        }
+    }
+
+    impl MemoriesJustifiedLayout {
+        fn set_row_height(&self, row_height: i32) {
+            self.row_height.set(row_height.max(1));
+            self.obj().layout_changed();
+        }
+
+        /// Returns the children of the widget that should be laid out.
+        fn children(widget: &gtk::Widget) -> Vec<gtk::Widget> {
+            let mut children: Vec<gtk::Widget> = vec![];
+            let mut child: Option<gtk::Widget> = widget.first_child();
+
+            while let Some(widget) = child {
+                if widget.should_layout() {
+                    children.push(widget.clone());
+                }
+                child = widget.next_sibling();
+            }
+            children
+        }
+
+        /// Returns the aspect ratio of the natural size of a child,
+        /// or 1.0 if the child has no natural size yet.
+        fn aspect_ratio(child: &gtk::Widget) -> f64 {
+            let (_, natural_width, _, _) = child.measure(gtk::Orientation::Horizontal, -1);
+            let (_, natural_height, _, _) = child.measure(gtk::Orientation::Vertical, -1);
+
+            if natural_width > 0 && natural_height > 0 {
+                natural_width as f64 / natural_height as f64
+            } else {
+                1.0
+            }
+        }
+
+        fn rows(&self, widget: &gtk::Widget, width: i32) -> Vec<JustifiedRow> {
+            let aspect_ratios: Vec<f64> = Self::children(widget).iter().map(Self::aspect_ratio).collect();
+
+            justified_rows(
+                &aspect_ratios,
+                width as f64,
+                self.row_height.get() as f64,
+                self.spacing.get() as f64,
+            )
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct MemoriesJustifiedLayout(ObjectSubclass<imp::MemoriesJustifiedLayout>)
+        @extends gtk::LayoutManager;
+}
+
+impl MemoriesJustifiedLayout {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+}
+
+impl Default for MemoriesJustifiedLayout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A row of the justified layout, made of the children in `start..end`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JustifiedRow {
+    pub start: usize,
+    pub end: usize,
+    pub height: f64,
+    /// Whether the row is scaled to fill the available width. The last
+    /// row is left at the target height if it is not full enough.
+    pub filled: bool,
+}
+
+/// Splits children with the given aspect ratios into rows that fill the
+/// given width. Children are added to a row until it is as wide as the width
+/// at the target height, then the row is scaled down to fit the width exactly.
+pub fn justified_rows(
+    aspect_ratios: &[f64],
+    width: f64,
+    target_height: f64,
+    spacing: f64,
+) -> Vec<JustifiedRow> {
+    let mut rows: Vec<JustifiedRow> = vec![];
+    let mut start: usize = 0;
+    let mut aspect_sum: f64 = 0.0;
+
+    for (i, aspect_ratio) in aspect_ratios.iter().enumerate() {
+        aspect_sum += aspect_ratio;
+
+        let spacing_width: f64 = spacing * (i - start) as f64;
+
+        if aspect_sum * target_height + spacing_width >= width {
+            rows.push(JustifiedRow {
+                start,
+                end: i + 1,
+                height: ((width - spacing_width) / aspect_sum).max(1.0),
+                filled: true,
+            });
+            start = i + 1;
+            aspect_sum = 0.0;
+        }
+    }
+    if start < aspect_ratios.len() {
+        rows.push(JustifiedRow {
+            start,
+            end: aspect_ratios.len(),
+            height: target_height,
+            filled: false,
+        });
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_fill_width() {
+        let rows: Vec<JustifiedRow> = justified_rows(&[1.0, 1.0, 1.0, 2.0, 1.0], 300.0, 100.0, 0.0);
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!((rows[0].start, rows[0].end, rows[0].height), (0, 3, 100.0));
+        assert_eq!((rows[1].start, rows[1].end, rows[1].height), (3, 5, 100.0));
+        assert!(rows[1].filled);
+    }
+
+    #[test]
+    fn rows_are_scaled_down() {
+        let rows: Vec<JustifiedRow> = justified_rows(&[1.0, 1.5, 0.5], 200.0, 100.0, 10.0);
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!((rows[0].start, rows[0].end), (0, 2));
+        assert_eq!(rows[0].height, 190.0 / 2.5);
+        assert_eq!((rows[1].start, rows[1].end, rows[1].height), (2, 3, 100.0));
+        assert!(!rows[1].filled);
+    }
+
+    #[test]
+    fn no_rows_without_children() {
+        assert!(justified_rows(&[], 300.0, 100.0, 0.0).is_empty());
+    }
+}
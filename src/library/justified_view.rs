@@ -0,0 +1,267 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::globals::JUSTIFIED_PREVIEW_HEIGHT;
+use crate::library::justified_layout::MemoriesJustifiedLayout;
+use crate::library::media_grid::MemoriesMediaGridView;
+use crate::library::media_item::MemoriesMediaItem;
+use crate::library::media_viewer::{MemoriesMediaViewer, ViewerContentType};
+use crate::window::MemoriesApplicationWindow;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::{clone, g_debug};
+use gtk::gdk_pixbuf::Pixbuf;
+use gtk::{gdk, gio, glib};
+
+mod imp {
+    use crate::globals::JUSTIFIED_LAYOUT_SPACING;
+    use crate::library::justified_layout::MemoriesJustifiedLayout;
+    use adw::prelude::*;
+    use adw::subclass::prelude::*;
+    use gtk::{gio, glib};
+    use std::cell::RefCell;
+
+    /// Alternative to the library grid view that shows media in
+    /// justified rows, without cropping the media to squares.
+    /// Unlike the grid view, a widget is created for every item.
+    #[derive(Debug, Default)]
+    pub struct MemoriesJustifiedView {
+        pub(super) model: RefCell<Option<gio::ListModel>>,
+        pub(super) items_changed_handler: RefCell<Option<glib::SignalHandlerId>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesJustifiedView {
+        const NAME: &'static str = "MemoriesJustifiedView";
+        type ParentType = gtk::Widget;
+        type Type = super::MemoriesJustifiedView;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.set_layout_manager_type::<MemoriesJustifiedLayout>();
+            klass.set_css_name("justifiedview");
+        }
+    }
+
+    impl ObjectImpl for MemoriesJustifiedView {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().layout().set_spacing(JUSTIFIED_LAYOUT_SPACING);
+        }
+
+        fn dispose(&self) {
+            self.obj().bind_model(None);
+        }
+    }
+
+    impl WidgetImpl for MemoriesJustifiedView {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesJustifiedView(ObjectSubclass<imp::MemoriesJustifiedView>)
+        @extends gtk::Widget;
+}
+
+impl MemoriesJustifiedView {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    fn layout(&self) -> MemoriesJustifiedLayout {
+        self.layout_manager().and_downcast().unwrap()
+    }
+
+    /// Sets the height that rows of the layout are scaled from.
+    pub fn set_row_height(&self, row_height: i32) {
+        self.layout().set_row_height(row_height);
+    }
+
+    /// Shows the media items of the given model, and keeps the shown items in
+    /// sync with the model. Passing `None` removes all items from the view.
+    pub fn bind_model(&self, model: Option<&gio::ListModel>) {
+        if let Some(old_model) = self.imp().model.take() {
+            if let Some(handler_id) = self.imp().items_changed_handler.take() {
+                old_model.disconnect(handler_id);
+            }
+        }
+        while let Some(child) = self.first_child() {
+            child.unparent();
+        }
+        let Some(model) = model else {
+            return;
+        };
+        self.update_items(model, 0, 0, model.n_items());
+
+        let handler_id: glib::SignalHandlerId = model.connect_items_changed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |model: &gio::ListModel, position: u32, removed: u32, added: u32| {
+                this.update_items(model, position, removed, added);
+            }
+        ));
+        self.imp().model.replace(Some(model.clone()));
+        self.imp().items_changed_handler.replace(Some(handler_id));
+    }
+
+    /// Returns the items shown in the view, in their order.
+    fn shown_items(&self) -> Vec<MemoriesMediaItem> {
+        let Some(model) = self.imp().model.borrow().clone() else {
+            return vec![];
+        };
+        model.iter::<MemoriesMediaItem>().filter_map(Result::ok).collect()
+    }
+
+    /// Returns the name of the collection that the view is shown in, which
+    /// is that of the media grid or the title of the page, such as a folder.
+    fn collection_title(&self) -> String {
+        if let Some(media_grid) = self
+            .ancestor(MemoriesMediaGridView::static_type())
+            .and_downcast::<MemoriesMediaGridView>()
+        {
+            return media_grid.collection_title();
+        }
+        self.ancestor(adw::NavigationPage::static_type())
+            .and_downcast::<adw::NavigationPage>()
+            .map(|page: adw::NavigationPage| page.title().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Replaces the widgets of removed items with widgets for the added items.
+    fn update_items(&self, model: &gio::ListModel, position: u32, removed: u32, added: u32) {
+        let mut previous: Option<gtk::Widget> = None;
+        let mut child: Option<gtk::Widget> = self.first_child();
+
+        for _ in 0..position {
+            previous = child.clone();
+            child = child.and_then(|widget: gtk::Widget| widget.next_sibling());
+        }
+        for _ in 0..removed {
+            let Some(widget) = child else {
+                break;
+            };
+            child = widget.next_sibling();
+            widget.unparent();
+        }
+        for i in position..position + added {
+            let Some(item) = model.item(i).and_downcast::<MemoriesMediaItem>() else {
+                continue;
+            };
+            let widget: gtk::Widget = self.create_item_widget(&item);
+
+            widget.insert_after(self, previous.as_ref());
+            previous = Some(widget);
+        }
+    }
+
+    /// Creates the widget for a media item, which shows a preview
+    /// of the media with its original aspect ratio once loaded.
+    fn create_item_widget(&self, item: &MemoriesMediaItem) -> gtk::Widget {
+        let file: gio::File = item.file();
+        let content_type: ViewerContentType =
+            item.viewer_content_type().unwrap_or(ViewerContentType::Invalid);
+
+        let widget: gtk::Widget = match content_type {
+            ViewerContentType::Image | ViewerContentType::VectorGraphics => {
+                let picture: gtk::Picture = gtk::Picture::builder()
+                    .content_fit(gtk::ContentFit::Cover)
+                    .can_shrink(true)
+                    .build();
+                Self::load_preview(&picture, &file);
+                picture.upcast()
+            }
+            _ => gtk::Image::builder()
+                .icon_name("video-x-generic-symbolic")
+                .icon_size(gtk::IconSize::Large)
+                .build()
+                .upcast(),
+        };
+        widget.set_tooltip_text(Some(&item.display_name()));
+        widget.set_cursor_from_name(Some("pointer"));
+
+        let click_gesture: gtk::GestureClick = gtk::GestureClick::new();
+
+        click_gesture.connect_released(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[strong]
+            item,
+            move |_: &gtk::GestureClick, _, _, _| {
+                let Some(win) = this.root().and_downcast::<MemoriesApplicationWindow>() else {
+                    return;
+                };
+                if let Some(viewer) = MemoriesMediaViewer::open(&win, &item.file(), &content_type) {
+                    viewer.imp().properties_widget.update_item_details(&item);
+                    viewer.set_siblings(this.shown_items(), &this.collection_title());
+                }
+            }
+        ));
+        widget.add_controller(click_gesture);
+        widget
+    }
+
+    /// Loads a downscaled preview of an image into the picture on a separate thread.
+    pub(crate) fn load_preview(picture: &gtk::Picture, file: &gio::File) {
+        let Some(path) = file.path() else {
+            return;
+        };
+        glib::spawn_future_local(clone!(
+            #[weak]
+            picture,
+            async move {
+                let result = gio::spawn_blocking(move || {
+                    let mut pixbuf: Pixbuf =
+                        Pixbuf::from_file_at_scale(&path, -1, JUSTIFIED_PREVIEW_HEIGHT, true)
+                            .map_err(|err: glib::Error| err.to_string())?;
+
+                    if let Some(oriented) = pixbuf.apply_embedded_orientation() {
+                        pixbuf = oriented;
+                    }
+                    let format: gdk::MemoryFormat = if pixbuf.has_alpha() {
+                        gdk::MemoryFormat::R8g8b8a8
+                    } else {
+                        gdk::MemoryFormat::R8g8b8
+                    };
+                    Ok::<_, String>((
+                        pixbuf.width(),
+                        pixbuf.height(),
+                        format,
+                        pixbuf.read_pixel_bytes(),
+                        pixbuf.rowstride() as usize,
+                    ))
+                })
+                .await;
+
+                match result {
+                    Ok(Ok((width, height, format, bytes, stride))) => {
+                        let texture = gdk::MemoryTexture::new(width, height, format, &bytes, stride);
+                        picture.set_paintable(Some(&texture));
+                    }
+                    Ok(Err(err)) => g_debug!("JustifiedView", "Failed to load preview: {}", err),
+                    Err(_) => g_debug!("JustifiedView", "Preview thread panicked."),
+                }
+            }
+        ));
+    }
+}
+
+impl Default for MemoriesJustifiedView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -18,20 +18,31 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::window::MemoriesApplicationWindow;
+use crate::application::MemoriesApplication;
+use crate::globals::{DEFAULT_LIBRARY_COLLECTION, RECENT_FOLDERS_LIMIT};
+use crate::library::roots::{self, LibraryRoot};
+use crate::util::media_database::MediaList;
+use adw::prelude::*;
 use adw::subclass::prelude::*;
+use glib::{clone, g_debug};
 use gtk::{gio, glib};
+use std::collections::{HashMap, HashSet};
 
 mod imp {
-    use crate::globals::{DEFAULT_LIBRARY_COLLECTION, DIRECTORY_MODEL_PRIORITY};
+    use super::{diff_runs, merge_sorted};
+    use crate::globals::{DIRECTORY_MODEL_ATTRIBUTES, DIRECTORY_MODEL_PRIORITY};
     use crate::library::media_item::MemoriesMediaItem;
+    use crate::util::crash;
+    use crate::util::profiling::ProfileSpan;
     use adw::prelude::*;
     use adw::subclass::prelude::*;
-    use glib::{clone, g_debug, g_error};
+    use glib::subclass::Signal;
+    use glib::{clone, g_debug, g_warning};
     use gtk::{gio, glib};
     use std::cell::{Cell, RefCell, RefMut};
-    use std::path::PathBuf;
+    use std::collections::HashSet;
     use std::rc::Rc;
+    use std::sync::OnceLock;
 
     #[derive(Debug)]
     pub(super) struct RootListModel {
@@ -39,14 +50,51 @@ mod imp {
         subdir_models: RefCell<Vec<SubdirectoryListModel>>,
         public_items: Cell<u32>,
         items_changed_callback: RefCell<Option<glib::SignalHandlerId>>,
+        loading_callback: RefCell<Option<glib::SignalHandlerId>>,
+        error_callback: RefCell<Option<glib::SignalHandlerId>>,
+        /// Error returned when enumerating the root folder, such as
+        /// when the folder is on a drive that is not mounted.
+        pub(super) error: RefCell<Option<glib::Error>>,
     }
 
     #[derive(Debug)]
     struct SubdirectoryListModel {
         model: gtk::DirectoryList,
+        /// Folder of the model, which is only given to the model
+        /// once the folder is enumerated if it is enumerated on demand.
+        folder: gio::File,
+        /// Time that the folder was last modified, in seconds since the Unix epoch.
+        modified: Option<i64>,
         public_items: u32,
-        _items_changed_callback: glib::SignalHandlerId,
-        _loading_callback: glib::SignalHandlerId,
+        items_changed_callback: glib::SignalHandlerId,
+        loading_callback: glib::SignalHandlerId,
+        error_callback: glib::SignalHandlerId,
+    }
+
+    impl RootListModel {
+        /// Disconnects the signal handlers of this root model and its subdirectory
+        /// models, so that they no longer update the library list model, and
+        /// stops any enumeration that is still running in the models.
+        fn disconnect(&self) {
+            if let Some(handler_id) = self.items_changed_callback.take() {
+                self.model.disconnect(handler_id);
+            }
+            if let Some(handler_id) = self.loading_callback.take() {
+                self.model.disconnect(handler_id);
+            }
+            if let Some(handler_id) = self.error_callback.take() {
+                self.model.disconnect(handler_id);
+            }
+            self.model.set_file(None::<&gio::File>);
+
+            for subdir in self.subdir_models.take() {
+                subdir.model.disconnect(subdir.items_changed_callback);
+                subdir.model.disconnect(subdir.loading_callback);
+                subdir.model.disconnect(subdir.error_callback);
+                subdir.model.set_file(None::<&gio::File>);
+            }
+            self.public_items.set(0);
+        }
     }
 
     /// Custom implementation of GListModel that uses
@@ -55,36 +103,76 @@ mod imp {
     #[derive(glib::Properties, Debug)]
     #[properties(wrapper_type = super::MemoriesLibraryListModel)]
     pub struct MemoriesLibraryListModel {
+        /// Library folders to enumerate. Empty until set, such as to the
+        /// configured folders by [`super::MemoriesLibraryListModel::initialize_new_model`].
         #[property(get, set)]
         subdirectories: RefCell<glib::StrV>,
         #[property(get, set)]
         models_loaded: Cell<bool>,
+        /// Whether items are inserted in chronological order, newest first,
+        /// instead of in the order that their directories are enumerated.
+        #[property(get, set, construct_only)]
+        sort_by_date: Cell<bool>,
         #[property(get)]
         refresh_widget_rows: Cell<bool>,
+        /// Whether subfolders that were not used recently are only enumerated
+        /// once `enumerate_folders_modified_since()` reaches their modification time.
+        #[property(get, set)]
+        enumerate_lazily: Cell<bool>,
+        /// Oldest modification time that deferred subfolders were enumerated for,
+        /// kept when the library is rebuilt so the same subfolders are enumerated.
+        deferred_since: Cell<i64>,
+        /// Number of media items enumerated so far.
+        #[property(get)]
+        items_enumerated: Cell<u32>,
+        /// Number of directories that are still being enumerated.
+        #[property(get)]
+        directories_pending: Cell<u32>,
+        /// Fraction of the known directories that are done enumerating.
+        /// May go backwards, as new subdirectories are found during a scan.
+        #[property(get)]
+        progress: Cell<f64>,
 
         pub(super) root_models: RefCell<Vec<Rc<RootListModel>>>,
         loading_notifies: Cell<u32>,
         public_items: RefCell<Vec<glib::Object>>,
+        /// Set while the library is rebuilt after its folders changed. Items of
+        /// the new folders are collected in `rebuilt_items` instead, and are swapped
+        /// in with a single `items_changed` emission once all folders are enumerated.
+        rebuilding: Cell<bool>,
+        rebuilt_items: RefCell<Vec<glib::Object>>,
+        /// Position and items of contiguous insertions that are not yet
+        /// added to the public items, which are added on an idle callback.
+        pending_insertion: RefCell<Option<(u32, Vec<glib::Object>)>>,
+        /// Items that are not yet added to the public items in sorted mode.
+        pending_sorted: RefCell<Vec<glib::Object>>,
+        /// Measures the enumeration of the library folders, until all models are loaded.
+        enumeration_span: RefCell<Option<ProfileSpan>>,
+        /// URIs of the favorite media, which the `favorite` property of items is set from.
+        favorites: RefCell<HashSet<String>>,
     }
 
     impl Default for MemoriesLibraryListModel {
         fn default() -> Self {
             Self {
-                subdirectories: RefCell::new({
-                    let mut strv: glib::StrV = glib::StrV::new();
-
-                    for xdg_user_dir in DEFAULT_LIBRARY_COLLECTION {
-                        let env_var: &str = xdg_user_dir.value().0;
-                        let path: String = std::env::var(env_var).unwrap();
-                        strv.push(path.into());
-                    }
-                    strv
-                }),
+                subdirectories: RefCell::default(),
                 models_loaded: Cell::new(false),
+                sort_by_date: Cell::new(false),
                 refresh_widget_rows: Cell::new(false),
+                enumerate_lazily: Cell::new(false),
+                deferred_since: Cell::new(i64::MAX),
+                items_enumerated: Cell::new(0),
+                directories_pending: Cell::new(0),
+                progress: Cell::new(0.0),
                 root_models: RefCell::new(vec![]),
                 loading_notifies: Cell::new(0_u32),
                 public_items: RefCell::new(vec![]),
+                rebuilding: Cell::new(false),
+                rebuilt_items: RefCell::new(vec![]),
+                pending_insertion: RefCell::new(None),
+                pending_sorted: RefCell::new(vec![]),
+                enumeration_span: RefCell::new(None),
+                favorites: RefCell::new(HashSet::new()),
             }
         }
     }
@@ -98,6 +186,24 @@ mod imp {
 
     #[glib::derived_properties]
     impl ObjectImpl for MemoriesLibraryListModel {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                // Emitted with the path or URI of a folder that could not be
+                // enumerated, and the error returned by its directory model.
+                vec![Signal::builder("enumeration-error")
+                    .param_types([String::static_type(), glib::Error::static_type()])
+                    .build()]
+            })
+        }
+
+        fn dispose(&self) {
+            self.cleanup_model();
+            self.public_items.borrow_mut().clear();
+            self.rebuilt_items.borrow_mut().clear();
+            self.rebuilding.set(false);
+        }
+
         fn constructed(&self) {
             let obj = self.obj();
 
@@ -113,19 +219,33 @@ mod imp {
                     // displays the current subdirectories configured for the library list model.
                     obj.notify_refresh_widget_rows();
 
+                    // If folders were enumerated before, the current items are kept
+                    // visible until the new folders are done enumerating.
+                    if !this.root_models.borrow().is_empty() {
+                        this.begin_rebuild();
+                    }
                     let subdirs: glib::StrV = model.subdirectories();
 
+                    this.enumeration_span.replace(Some(ProfileSpan::start(
+                        "library enumeration",
+                        format_args!("{} folders", subdirs.len()),
+                    )));
+
                     for folder in &subdirs {
                         let folder_path: String = folder.to_string();
                         g_debug!("LibraryListModel", "Creating root list model for {}", folder_path);
 
-                        let gfile: gio::File = gio::File::for_path(folder_path);
+                        // Library folders are paths, or URIs of GVFS locations.
+                        let gfile: gio::File = gio::File::for_commandline_arg(folder_path);
 
                         let new_model: RootListModel = RootListModel {
-                            model: gtk::DirectoryList::new(None, Some(&gfile)),
+                            model: gtk::DirectoryList::new(Some(DIRECTORY_MODEL_ATTRIBUTES), Some(&gfile)),
                             subdir_models: RefCell::new(vec![]),
                             public_items: Cell::new(0_u32),
                             items_changed_callback: RefCell::new(None),
+                            loading_callback: RefCell::new(None),
+                            error_callback: RefCell::new(None),
+                            error: RefCell::new(None),
                         };
 
                         // Connect the root model's `items_changed` signal with our model
@@ -146,19 +266,37 @@ mod imp {
                                 }
                             ));
 
-                        new_model.model.connect_loading_notify(clone!(
-                            #[weak]
-                            this,
-                            move |dl: &gtk::DirectoryList| {
-                                this.register_model_loading_notify(dl);
-                            }
-                        ));
+                        let loading_handler_id: glib::SignalHandlerId =
+                            new_model.model.connect_loading_notify(clone!(
+                                #[weak]
+                                this,
+                                move |dl: &gtk::DirectoryList| {
+                                    this.register_model_loading_notify(dl);
+                                }
+                            ));
+
+                        let error_handler_id: glib::SignalHandlerId =
+                            this.connect_model_error_notify(&new_model.model);
 
                         new_model.items_changed_callback.replace(Some(signal_handler_id));
+                        new_model.loading_callback.replace(Some(loading_handler_id));
+                        new_model.error_callback.replace(Some(error_handler_id));
                         new_model.model.set_io_priority(DIRECTORY_MODEL_PRIORITY);
 
                         this.root_models.borrow_mut().push(Rc::new(new_model));
                     }
+                    this.update_directory_progress();
+                }
+            ));
+
+            // Subfolders that were deferred are enumerated once they are no longer enumerated lazily.
+            obj.connect_enumerate_lazily_notify(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |model: &super::MemoriesLibraryListModel| {
+                    if !model.enumerate_lazily() {
+                        this.enumerate_deferred_folders(i64::MIN);
+                    }
                 }
             ));
         }
@@ -193,17 +331,121 @@ mod imp {
             None
         }
 
-        /// Returns the total number of `GtkDirectoryList` models
-        /// used within this `GListModel` implementation.
+        /// Returns the total number of `GtkDirectoryList` models used within
+        /// this `GListModel` implementation, except for the models of subfolders
+        /// that are enumerated on demand and were not enumerated yet.
         fn directory_list_count(&self) -> u32 {
             let mut count: u32 = 0;
             for root_model in self.root_models.borrow().iter() {
-                count += TryInto::<u32>::try_into(root_model.subdir_models.borrow().len()).unwrap();
+                count += TryInto::<u32>::try_into(
+                    root_model
+                        .subdir_models
+                        .borrow()
+                        .iter()
+                        .filter(|subdir: &&SubdirectoryListModel| subdir.model.file().is_some())
+                        .count(),
+                )
+                .unwrap();
                 count += 1; // also take this root model into account
             }
             count
         }
 
+        /// Enumerates the deferred subfolders that were modified at or after the
+        /// given time, or whose modification time is unknown. Returns the amount
+        /// of subfolders that started to be enumerated.
+        pub(super) fn enumerate_deferred_folders(&self, modified_since: i64) -> usize {
+            if modified_since >= self.deferred_since.get() {
+                return 0;
+            }
+            self.deferred_since.set(modified_since);
+
+            let deferred: Vec<(gtk::DirectoryList, gio::File)> = self
+                .root_models
+                .borrow()
+                .iter()
+                .flat_map(|root_model| {
+                    root_model
+                        .subdir_models
+                        .borrow()
+                        .iter()
+                        .filter(|subdir: &&SubdirectoryListModel| {
+                            subdir.model.file().is_none()
+                                && subdir
+                                    .modified
+                                    .map_or(true, |modified: i64| modified >= modified_since)
+                        })
+                        .map(|subdir: &SubdirectoryListModel| (subdir.model.clone(), subdir.folder.clone()))
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+
+            for (model, folder) in &deferred {
+                g_debug!(
+                    "LibraryListModel",
+                    "Enumerating deferred subdirectory: {}",
+                    folder.uri()
+                );
+                model.set_file(Some(folder));
+            }
+            self.update_directory_progress();
+            deferred.len()
+        }
+
+        /// Enumerates a deferred subfolder right away, such as when it is opened.
+        pub(super) fn enumerate_deferred_folder(&self, folder: &gio::File) {
+            let model: Option<gtk::DirectoryList> = self.root_models.borrow().iter().find_map(|root_model| {
+                root_model
+                    .subdir_models
+                    .borrow()
+                    .iter()
+                    .find(|subdir: &&SubdirectoryListModel| subdir.folder.equal(folder))
+                    .filter(|subdir: &&SubdirectoryListModel| subdir.model.file().is_none())
+                    .map(|subdir: &SubdirectoryListModel| subdir.model.clone())
+            });
+            if let Some(model) = model {
+                model.set_file(Some(folder));
+                self.update_directory_progress();
+            }
+        }
+
+        /// Returns the library folders, each with the subfolders found in it, and
+        /// whether they were enumerated, since subfolders may be enumerated on demand.
+        pub(super) fn folders(&self) -> Vec<(gio::File, Vec<(gio::File, bool)>)> {
+            self.root_models
+                .borrow()
+                .iter()
+                .filter_map(|root_model| {
+                    let subfolders: Vec<(gio::File, bool)> = root_model
+                        .subdir_models
+                        .borrow()
+                        .iter()
+                        .map(|subdir: &SubdirectoryListModel| {
+                            (subdir.folder.clone(), subdir.model.file().is_some())
+                        })
+                        .collect();
+                    Some((root_model.model.file()?, subfolders))
+                })
+                .collect()
+        }
+
+        /// Sets the URIs of the favorite media, and updates the `favorite`
+        /// property of every item, including items that are not public yet.
+        pub(super) fn set_favorites(&self, favorites: HashSet<String>) {
+            let update_favorite = |item: &glib::Object| {
+                if let Some(item) = item.downcast_ref::<MemoriesMediaItem>() {
+                    item.set_favorite(favorites.contains(&item.key()));
+                }
+            };
+            for items in [&self.public_items, &self.rebuilt_items, &self.pending_sorted] {
+                items.borrow().iter().for_each(update_favorite);
+            }
+            if let Some((_, items)) = self.pending_insertion.borrow().as_ref() {
+                items.iter().for_each(update_favorite);
+            }
+            self.favorites.replace(favorites);
+        }
+
         /// Called by a `GtkDirectoryList` model upon its 'loading_notify' signal.
         pub(super) fn register_model_loading_notify(&self, model: &gtk::DirectoryList) {
             let notifies: u32 = self.loading_notifies.get();
@@ -213,9 +455,172 @@ mod imp {
                 self.loading_notifies.set(updated_notifies);
 
                 if updated_notifies == self.directory_list_count() {
+                    // Without any media in the folders enumerated so far, the library
+                    // would be shown as empty, so the deferred subfolders are enumerated.
+                    if self.target_items().borrow().is_empty()
+                        && self.pending_sorted.borrow().is_empty()
+                        && self.pending_insertion.borrow().is_none()
+                        && self.enumerate_deferred_folders(i64::MIN) > 0
+                    {
+                        return;
+                    }
+                    // Add the last items before the view is told that loading is done.
+                    self.flush_pending_insertion();
+
+                    if self.rebuilding.get() {
+                        self.finish_rebuild();
+                    }
                     self.obj().set_models_loaded(true);
+                    self.enumeration_span.take();
                 }
             }
+            self.update_directory_progress();
+        }
+
+        /// Connects a `GtkDirectoryList` model's `error` notify to the
+        /// `enumeration-error` signal of the library list model.
+        fn connect_model_error_notify(&self, model: &gtk::DirectoryList) -> glib::SignalHandlerId {
+            model.connect_error_notify(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |model: &gtk::DirectoryList| {
+                    let (Some(error), Some(folder)) = (model.error(), model.file()) else {
+                        return;
+                    };
+                    let folder_name: String = folder.path().map_or_else(
+                        || folder.uri().to_string(),
+                        |path| path.to_string_lossy().to_string(),
+                    );
+
+                    g_warning!(
+                        "LibraryListModel",
+                        "Failed to enumerate '{}': {}",
+                        folder_name,
+                        error
+                    );
+                    if let Some(root_model) = this.lookup_root_model(model) {
+                        root_model.error.replace(Some(error.clone()));
+                    }
+                    this.obj()
+                        .emit_by_name::<()>("enumeration-error", &[&folder_name, &error]);
+                }
+            ))
+        }
+
+        /// Updates the `directories-pending` and `progress` properties
+        /// from the number of directory models that finished loading.
+        fn update_directory_progress(&self) {
+            let total: u32 = self.directory_list_count();
+            let done: u32 = self.loading_notifies.get().min(total);
+
+            let pending: u32 = total - done;
+            let progress: f64 = if total == 0 {
+                0.0
+            } else {
+                done as f64 / total as f64
+            };
+
+            if self.directories_pending.replace(pending) != pending {
+                self.obj().notify_directories_pending();
+            }
+            if self.progress.replace(progress) != progress {
+                self.obj().notify_progress();
+            }
+        }
+
+        /// Drops the directory models of the current library folders, and starts
+        /// collecting the items of the new folders off-screen, so that the view
+        /// does not churn while the library is enumerated again.
+        fn begin_rebuild(&self) {
+            g_debug!("LibraryListModel", "Rebuilding the library list model.");
+
+            self.flush_pending_insertion();
+            self.cleanup_model();
+            self.rebuilt_items.borrow_mut().clear();
+            self.rebuilding.set(true);
+            self.obj().set_models_loaded(false);
+        }
+
+        /// Drops the directory models of the library folders after disconnecting
+        /// their signal handlers, and resets the enumeration counters. Items that
+        /// were not added to the public items yet are dropped with them.
+        fn cleanup_model(&self) {
+            for root_model in self.root_models.take() {
+                root_model.disconnect();
+            }
+            self.pending_insertion.take();
+            self.pending_sorted.take();
+            self.loading_notifies.set(0);
+            self.update_directory_progress();
+        }
+
+        /// Replaces the public items with the items collected during a rebuild.
+        /// Only the items that were removed or added are changed, so that the
+        /// items that are still in the library keep their widgets, selection and
+        /// scroll position in the view. Items are identified by their URI, and
+        /// items that were reordered are removed and added again.
+        fn finish_rebuild(&self) {
+            self.rebuilding.set(false);
+
+            let rebuilt_items: Vec<glib::Object> = self.rebuilt_items.take();
+            let old_keys: Vec<String> = self.public_items.borrow().iter().map(item_key).collect();
+            let new_keys: Vec<String> = rebuilt_items.iter().map(item_key).collect();
+
+            let (removed_runs, added_runs) = diff_runs(&old_keys, &new_keys);
+
+            // Remove from the end, so that the positions of earlier runs stay valid.
+            for (position, removed) in removed_runs.into_iter().rev() {
+                let start: usize = position.try_into().unwrap();
+                let end: usize = start + TryInto::<usize>::try_into(removed).unwrap();
+
+                self.public_items.borrow_mut().drain(start..end);
+                self.obj().items_changed(position, removed, 0);
+            }
+            // The remaining items are in the same order as in the rebuilt items,
+            // so inserting from the start puts each run at its final position.
+            for (position, added) in added_runs {
+                let start: usize = position.try_into().unwrap();
+                let end: usize = start + TryInto::<usize>::try_into(added).unwrap();
+
+                self.public_items
+                    .borrow_mut()
+                    .splice(start..start, rebuilt_items[start..end].iter().cloned());
+                self.obj().items_changed(position, 0, added);
+            }
+            self.update_items_enumerated();
+        }
+
+        /// Returns the items that enumerated files are added to, which are
+        /// the public items, unless the library is being rebuilt.
+        fn target_items(&self) -> &RefCell<Vec<glib::Object>> {
+            if self.rebuilding.get() {
+                &self.rebuilt_items
+            } else {
+                &self.public_items
+            }
+        }
+
+        /// Emits `items_changed` for a change to the public items,
+        /// which are not changed while the library is being rebuilt.
+        fn emit_items_changed(&self, position: u32, removed: u32, added: u32) {
+            if !self.rebuilding.get() {
+                self.obj().items_changed(position, removed, added);
+            }
+        }
+
+        /// Updates the `items-enumerated` property from the enumerated items count.
+        fn update_items_enumerated(&self) {
+            let pending: usize = self
+                .pending_insertion
+                .borrow()
+                .as_ref()
+                .map_or(0, |(_, items): &(u32, Vec<glib::Object>)| items.len())
+                + self.pending_sorted.borrow().len();
+            let items: u32 = (self.target_items().borrow().len() + pending).try_into().unwrap();
+
+            if self.items_enumerated.replace(items) != items {
+                self.obj().notify_items_enumerated();
+            }
         }
 
         /// Called by the handler for a root model's `items_changed` signal event.
@@ -265,21 +670,25 @@ mod imp {
         ) {
             let obj = self.obj();
 
-            // Extract the parent directory absolute path from its `GFile` object.
+            // Subdirectories are children of the parent directory's `GFile` object,
+            // instead of paths, since library folders may be GVFS locations.
             let parent_file: gio::File = parent_list_model.model.file().unwrap();
-            let file_path: PathBuf = parent_file.path().unwrap();
-            let parent_dir_path: String = file_path.to_string_lossy().to_string();
-
-            let subdirectory_absolute_path: String =
-                format!("{}/{}", parent_dir_path, item_file_info.name().to_str().unwrap());
+            let subdirectory: gio::File = parent_file.child(item_file_info.name());
 
             g_debug!(
                 "LibraryListModel",
                 "Enumerated new subdirectory: {}",
-                subdirectory_absolute_path
+                subdirectory.uri()
             );
 
-            let new_model = gtk::DirectoryList::new(None, None::<&gio::File>);
+            // Subfolders that are enumerated on demand are only given to their model once
+            // the timeline is scrolled near them, unless they were used recently.
+            let deferred: bool = self.enumerate_lazily.get() && !super::is_recent_folder(&subdirectory);
+            let modified: Option<i64> = item_file_info
+                .modification_date_time()
+                .map(|date_time: glib::DateTime| date_time.to_unix());
+
+            let new_model = gtk::DirectoryList::new(Some(DIRECTORY_MODEL_ATTRIBUTES), None::<&gio::File>);
 
             let items_changed_signal_id: glib::SignalHandlerId = new_model.connect_items_changed(clone!(
                 #[weak(rename_to = this)]
@@ -300,20 +709,30 @@ mod imp {
                 }
             ));
 
+            let error_signal_id: glib::SignalHandlerId = self.connect_model_error_notify(&new_model);
+
             new_model.set_io_priority(DIRECTORY_MODEL_PRIORITY);
-            new_model.set_file(Some(&gio::File::for_path(subdirectory_absolute_path)));
+
+            if !deferred || modified.map_or(true, |modified: i64| modified >= self.deferred_since.get()) {
+                new_model.set_file(Some(&subdirectory));
+            }
 
             let mut subdirs: RefMut<'_, Vec<SubdirectoryListModel>> =
                 parent_list_model.subdir_models.borrow_mut();
 
             subdirs.push(SubdirectoryListModel {
                 model: new_model,
+                folder: subdirectory,
+                modified,
                 public_items: 0_u32,
-                _items_changed_callback: items_changed_signal_id,
-                _loading_callback: loading_signal_id,
+                items_changed_callback: items_changed_signal_id,
+                loading_callback: loading_signal_id,
+                error_callback: error_signal_id,
             });
 
             drop(subdirs); // drop to avoid double mutable borrow error at `self.n_items`
+
+            self.update_directory_progress();
         }
 
         /// Updates the `public_items` vector and emits the `items_changed`
@@ -326,10 +745,7 @@ mod imp {
             removed: u32,
             added: u32,
         ) {
-            let obj = self.obj();
-
             let model_file: gio::File = model.file().unwrap();
-            let mut private_index_offset: u32 = 0;
             let mut added_items: Vec<glib::Object> = vec![];
 
             g_debug!(
@@ -341,71 +757,220 @@ mod imp {
                 added
             );
 
-            for i in 0..removed {
-                self.public_items
-                    .borrow_mut()
-                    .remove((pos + i).try_into().unwrap());
-            }
-
             for i in 0..added {
                 if let Some(object) = model.item(pos + i) {
-                    added_items.push(object);
+                    let item: MemoriesMediaItem = MemoriesMediaItem::new(&object);
+
+                    item.set_favorite(self.favorites.borrow().contains(&item.key()));
+                    added_items.push(item.upcast());
                 } else {
-                    g_error!(
+                    crash::report_error(
                         "LibraryListModel",
-                        "update_public_items(): model.item(pos) returned None."
+                        "update_public_items(): model.item(pos) returned None.",
                     );
                 }
             }
 
-            let mut public_vec: RefMut<'_, Vec<glib::Object>> = self.public_items.borrow_mut();
+            // Public items are ordered by root model, and the items of a
+            // root model are followed by the items of its subdirectory models.
+            let mut private_index_offset: u32 = self.root_model_offset(&parent_model);
 
             // First, check if the `model` given is the root `GtkDirectoryList` model.
             if parent_model.model.file().unwrap() == model_file {
-                for added_item in added_items.iter() {
-                    public_vec.insert(
-                        pos.try_into().unwrap(),
-                        MemoriesMediaItem::new_and_upcast(added_item),
-                    );
-                }
-                drop(public_vec);
-
                 // Update the `RootListModel`s `public_items` count.
                 let previous_public_count: u32 = parent_model.public_items.get();
                 parent_model
                     .public_items
-                    .swap(&Cell::new(previous_public_count + added - removed));
-
-                obj.items_changed(pos, removed, added);
+                    .set(previous_public_count + added - removed);
             } else {
                 private_index_offset += parent_model.public_items.get();
 
                 let mut subdirs: RefMut<'_, Vec<SubdirectoryListModel>> =
                     parent_model.subdir_models.borrow_mut();
 
-                for subdir in subdirs.iter_mut() {
-                    if subdir.model.file().unwrap() == model_file {
-                        for added_item in added_items.iter() {
-                            public_vec.insert(
-                                TryInto::<usize>::try_into(private_index_offset + pos).unwrap(),
-                                MemoriesMediaItem::new_and_upcast(added_item),
-                            );
-                        }
-                        drop(public_vec);
-
-                        subdir.public_items += added - removed;
+                let Some(subdir) = subdirs.iter_mut().find(|subdir: &&mut SubdirectoryListModel| {
+                    let found: bool = subdir.folder == model_file;
 
-                        obj.items_changed(private_index_offset + pos, removed, added);
-                        return;
+                    if !found {
+                        private_index_offset += subdir.public_items;
                     }
-                    private_index_offset += subdir.public_items;
+                    found
+                }) else {
+                    crash::report_error(
+                        "LibraryListModel",
+                        "Model given doesn't exist. Should not be possible.",
+                    );
+                    return;
+                };
+                subdir.public_items = subdir.public_items + added - removed;
+            }
+            if self.sort_by_date.get() {
+                if removed > 0 {
+                    self.remove_missing_items(model);
+                }
+                self.insert_sorted_items(added_items);
+            } else {
+                self.remove_items(private_index_offset + pos, removed);
+                self.insert_items(private_index_offset + pos, added_items);
+            }
+            self.update_items_enumerated();
+        }
+
+        /// Returns the position of the first public item of a root model,
+        /// after the items of the root models before it.
+        fn root_model_offset(&self, root_model: &Rc<RootListModel>) -> u32 {
+            let mut offset: u32 = 0;
+
+            for other in self.root_models.borrow().iter() {
+                if Rc::ptr_eq(other, root_model) {
+                    break;
+                }
+                offset += other.public_items.get();
+                offset += other
+                    .subdir_models
+                    .borrow()
+                    .iter()
+                    .map(|subdir: &SubdirectoryListModel| subdir.public_items)
+                    .sum::<u32>();
+            }
+            offset
+        }
+
+        /// Inserts items at the given position. Insertions that are contiguous with
+        /// the pending insertion are added to it, so that `items_changed` is emitted
+        /// once per batch of items when the main loop is idle, instead of per file.
+        fn insert_items(&self, position: u32, items: Vec<glib::Object>) {
+            if items.is_empty() {
+                return;
+            }
+            if self.rebuilding.get() {
+                let position: usize = position.try_into().unwrap();
+                self.rebuilt_items.borrow_mut().splice(position..position, items);
+                return;
+            }
+            if let Some((start, batch)) = self.pending_insertion.borrow_mut().as_mut() {
+                let end: u32 = *start + TryInto::<u32>::try_into(batch.len()).unwrap();
+
+                if (*start..=end).contains(&position) {
+                    let offset: usize = (position - *start).try_into().unwrap();
+                    batch.splice(offset..offset, items);
+                    return;
+                }
+            }
+            self.flush_pending_insertion();
+            self.pending_insertion.replace(Some((position, items)));
+
+            glib::idle_add_local_once(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move || this.flush_pending_insertion()
+            ));
+        }
+
+        /// Inserts items in chronological order, newest first. Items are collected
+        /// until the main loop is idle, and are then merged into the public items.
+        fn insert_sorted_items(&self, items: Vec<glib::Object>) {
+            if items.is_empty() {
+                return;
+            }
+            if self.rebuilding.get() {
+                merge_sorted(&mut self.rebuilt_items.borrow_mut(), items, sort_key);
+                return;
+            }
+            let mut pending: RefMut<'_, Vec<glib::Object>> = self.pending_sorted.borrow_mut();
+
+            if pending.is_empty() {
+                glib::idle_add_local_once(clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    move || this.flush_pending_insertion()
+                ));
+            }
+            pending.extend(items);
+        }
+
+        /// Removes the items of a directory model's folder that are no longer
+        /// in the model. Used in sorted mode, where the positions of the items
+        /// in the public items are unrelated to their positions in the model.
+        fn remove_missing_items(&self, model: &gtk::DirectoryList) {
+            self.flush_pending_insertion();
+
+            let folder: gio::File = model.file().unwrap();
+            let present: HashSet<String> = (0..model.n_items())
+                .filter_map(|i: u32| model.item(i).and_downcast::<gio::FileInfo>())
+                .filter_map(|info: gio::FileInfo| info.attribute_object("standard::file"))
+                .filter_map(|file: glib::Object| file.downcast::<gio::File>().ok())
+                .map(|file: gio::File| file.uri().to_string())
+                .collect();
+
+            let items: &RefCell<Vec<glib::Object>> = self.target_items();
+            let mut position: usize = 0;
+
+            while position < items.borrow().len() {
+                let item: MemoriesMediaItem = items.borrow()[position].clone().downcast().unwrap();
+                let file: gio::File = item.file();
+
+                if file.parent().as_ref() == Some(&folder) && !present.contains(file.uri().as_str()) {
+                    items.borrow_mut().remove(position);
+                    self.emit_items_changed(position.try_into().unwrap(), 1, 0);
+                } else {
+                    position += 1;
                 }
-                g_error!(
-                    "LibraryListModel",
-                    "Model given doesn't exist. Should not be possible."
-                );
             }
         }
+
+        /// Removes items at the given position, emitting `items_changed` right away.
+        fn remove_items(&self, position: u32, count: u32) {
+            if count == 0 {
+                return;
+            }
+            // Positions given are relative to the items including the pending insertion.
+            self.flush_pending_insertion();
+
+            let range: std::ops::Range<usize> =
+                position.try_into().unwrap()..(position + count).try_into().unwrap();
+            self.target_items().borrow_mut().drain(range);
+            self.emit_items_changed(position, count, 0);
+        }
+
+        /// Adds the pending insertion to the public items,
+        /// and emits `items_changed` for the whole insertion.
+        fn flush_pending_insertion(&self) {
+            if let Some((position, items)) = self.pending_insertion.take() {
+                let added: u32 = items.len().try_into().unwrap();
+                let index: usize = position.try_into().unwrap();
+
+                self.public_items.borrow_mut().splice(index..index, items);
+                self.obj().items_changed(position, 0, added);
+            }
+            let sorted_items: Vec<glib::Object> = self.pending_sorted.take();
+
+            if !sorted_items.is_empty() {
+                let runs: Vec<(u32, u32)> =
+                    merge_sorted(&mut self.public_items.borrow_mut(), sorted_items, sort_key);
+
+                // Runs are in ascending order, so each position is
+                // valid once the runs before it have been emitted.
+                for (position, added) in runs {
+                    self.obj().items_changed(position, 0, added);
+                }
+            }
+        }
+    }
+
+    /// Returns the key that identifies an item when the library is rebuilt.
+    fn item_key(item: &glib::Object) -> String {
+        item.downcast_ref::<MemoriesMediaItem>()
+            .map(MemoriesMediaItem::key)
+            .unwrap_or_default()
+    }
+
+    /// Returns the key that items are sorted by in sorted mode,
+    /// where items without a modification time are sorted last.
+    fn sort_key(item: &glib::Object) -> i64 {
+        item.downcast_ref::<MemoriesMediaItem>()
+            .and_then(MemoriesMediaItem::unix_time)
+            .unwrap_or(i64::MIN)
     }
 }
 
@@ -419,26 +984,201 @@ impl MemoriesLibraryListModel {
         glib::Object::new()
     }
 
-    pub fn connect_error_notify<F>(&self, callback: F) -> glib::signal::SignalHandlerId
+    /// Connects to the `enumeration-error` signal, which is emitted for every
+    /// library folder or subfolder that fails to be enumerated.
+    pub fn connect_enumeration_error<F>(&self, callback: F) -> glib::SignalHandlerId
     where
-        F: Fn(&gtk::DirectoryList) + 'static,
+        F: Fn(&Self, &str, &glib::Error) + 'static,
     {
-        // FIXME: temp compiler silence fix
+        self.connect_closure(
+            "enumeration-error",
+            false,
+            glib::closure_local!(move |model: &Self, folder: &str, error: &glib::Error| {
+                callback(model, folder, error)
+            }),
+        )
+    }
+
+    /// Setup code for initialize the library list model at start up of Memories.
+    /// Passes newly constructed list model to the Memories application object.
+    pub fn initialize_new_model(application: &MemoriesApplication) {
+        // The library was already loaded, such as when it was indexed without a window.
+        if application.imp().library_list_model.get().is_some() {
+            return;
+        }
+        let new_library_model: MemoriesLibraryListModel = glib::Object::builder()
+            .property("sort-by-date", true)
+            .property("subdirectories", Self::configured_folders())
+            .build();
+
+        // Rebuild the library when folders are added or removed in the preferences.
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+
+        gsettings.connect_changed(
+            Some("library-folders"),
+            clone!(
+                #[weak]
+                new_library_model,
+                move |_: &gio::Settings, _: &str| {
+                    new_library_model.set_subdirectories(Self::configured_folders());
+                }
+            ),
+        );
+        gsettings
+            .bind("lazy-folder-enumeration", &new_library_model, "enumerate-lazily")
+            .build();
+
+        // Keep the `favorite` property of media items in sync with the favorites.
+        application.connect_metadata_changed(
+            Some(MediaList::Favorites.name()),
+            clone!(
+                #[weak]
+                new_library_model,
+                move |application: &MemoriesApplication, _: &str| {
+                    new_library_model
+                        .imp()
+                        .set_favorites(Self::favorites(application));
+                }
+            ),
+        );
+        new_library_model
+            .imp()
+            .set_favorites(Self::favorites(application));
+
+        application.set_library_list_model(new_library_model);
+    }
+
+    /// Returns the URIs of the favorite media.
+    fn favorites(application: &MemoriesApplication) -> HashSet<String> {
+        application
+            .media_list(MediaList::Favorites)
+            .iter()
+            .map(|uri: &glib::GStringPtr| uri.to_string())
+            .collect()
+    }
+
+    /// Enumerates the subfolders that are enumerated on demand which were modified
+    /// at or after the given time, in seconds since the Unix epoch. Media is sorted
+    /// newest first, so these are the subfolders that could have media which is
+    /// shown before media from that time.
+    pub fn enumerate_folders_modified_since(&self, unix_time: i64) {
+        self.imp().enumerate_deferred_folders(unix_time);
+    }
+
+    /// Returns the library folders, each with the subfolders found in it so far,
+    /// and whether each subfolder was enumerated or is enumerated on demand.
+    pub fn folders(&self) -> Vec<(gio::File, Vec<(gio::File, bool)>)> {
+        self.imp().folders()
+    }
+
+    /// Enumerates a subfolder that is enumerated on demand, if it was not
+    /// enumerated yet, such as when it is opened in the Folders page.
+    pub fn enumerate_folder(&self, folder: &gio::File) {
+        self.imp().enumerate_deferred_folder(folder);
+    }
+
+    /// Remembers the folder of a media file that was opened, so that it is
+    /// enumerated at start up even if subfolders are enumerated on demand.
+    pub fn remember_recent_folder(file: &gio::File) {
+        let Some(folder) = file.parent() else {
+            return;
+        };
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+        let recent: glib::StrV = gsettings.strv("recent-folders");
+        let folder_uri: glib::GString = folder.uri();
+
+        if recent
+            .first()
+            .is_some_and(|uri: &glib::GStringPtr| uri.as_str() == folder_uri.as_str())
+        {
+            return;
+        }
+        let recent: Vec<&str> = recent.iter().map(|uri: &glib::GStringPtr| uri.as_str()).collect();
+        let _ = gsettings.set_strv(
+            "recent-folders",
+            push_recent(&recent, &folder_uri, RECENT_FOLDERS_LIMIT),
+        );
+    }
+
+    /// Removes a folder that was added in the preferences from the library
+    /// folders. Returns `false` if the folder was not added in the preferences.
+    pub fn remove_configured_folder(folder: &gio::File) -> bool {
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+        let folders: glib::StrV = gsettings.strv("library-folders");
+
+        let remaining: Vec<&str> = folders
+            .iter()
+            .map(|f: &glib::GStringPtr| f.as_str())
+            .filter(|f: &&str| LibraryRoot::from_setting(f).file() != *folder)
+            .collect();
+
+        if remaining.len() == folders.len() {
+            return false;
+        }
+        let _ = gsettings.set_strv("library-folders", remaining);
+        true
+    }
+
+    /// Returns the library folders: the XDG user directories of the default
+    /// library collection, followed by the folders added in the preferences.
+    /// Folders shared by the document portal are resolved to their current path.
+    pub fn configured_folders() -> glib::StrV {
+        let mut folders: glib::StrV = glib::StrV::new();
+
+        for xdg_user_dir in DEFAULT_LIBRARY_COLLECTION {
+            folders.push(xdg_user_dir.get_path().into());
+        }
+        for root in roots::configured_roots() {
+            let file: gio::File = root.file();
+
+            // Local folders are enumerated by path, and other locations by URI.
+            let folder: String = match file.path().filter(|_| file.is_native()) {
+                Some(path) => path.to_string_lossy().to_string(),
+                None => file.uri().to_string(),
+            };
+            if !folders
+                .iter()
+                .any(|f: &glib::GStringPtr| f.as_str() == folder.as_str())
+            {
+                folders.push(folder.into());
+            }
+        }
+        folders
+    }
+
+    /// Enumerates the library folders again to pick up changes made outside
+    /// of Memories. The current items stay visible while enumerating, and only
+    /// the items that were added or removed are changed once it is done.
+    pub fn refresh(&self) {
+        g_debug!("LibraryListModel", "Refreshing the library list model.");
+        self.notify_subdirectories();
+    }
+
+    /// Returns the library folders that could not be enumerated, with the
+    /// errors returned for them. Subfolders that could not be read are not
+    /// included, since the rest of their library folder is still available.
+    pub fn unavailable_folders(&self) -> Vec<(gio::File, glib::Error)> {
         self.imp()
             .root_models
             .borrow()
-            .first()
-            .unwrap()
-            .model
-            .connect_error_notify(callback)
+            .iter()
+            .filter_map(|root_model| {
+                let error: glib::Error = root_model.error.borrow().clone()?;
+                Some((root_model.model.file()?, error))
+            })
+            .collect()
     }
 
-    /// Setup code for initialize the library list model at start up of Memories.
-    /// Passes newly constructed list model to the Memories application object.
-    pub fn initialize_new_model(window: &MemoriesApplicationWindow) {
-        let new_library_model = MemoriesLibraryListModel::default();
+    /// Removes a folder from the library folders, which rebuilds the library.
+    pub fn remove_folder(&self, folder: &gio::File) {
+        let subdirectories: glib::StrV = self
+            .subdirectories()
+            .iter()
+            .filter(|path: &&glib::GStringPtr| gio::File::for_commandline_arg(path.as_str()) != *folder)
+            .map(|path: &glib::GStringPtr| glib::GString::from(path.as_str()))
+            .collect();
 
-        window.app().unwrap().set_library_list_model(new_library_model);
+        self.set_subdirectories(subdirectories);
     }
 
     pub fn start_enumerating_items(&self) -> Result<(), String> {
@@ -457,3 +1197,364 @@ impl Default for MemoriesLibraryListModel {
         Self::new()
     }
 }
+
+/// Returns whether media in a folder was opened recently.
+fn is_recent_folder(folder: &gio::File) -> bool {
+    let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+    let folder_uri: glib::GString = folder.uri();
+
+    gsettings
+        .strv("recent-folders")
+        .iter()
+        .any(|uri: &glib::GStringPtr| uri.as_str() == folder_uri.as_str())
+}
+
+/// Moves an entry to the front of a most recent first list,
+/// dropping the oldest entries past the given limit.
+fn push_recent<'a>(recent: &[&'a str], entry: &'a str, limit: usize) -> Vec<&'a str> {
+    std::iter::once(entry)
+        .chain(recent.iter().copied().filter(|other: &&str| *other != entry))
+        .take(limit)
+        .collect()
+}
+
+/// Merges new items into items that are sorted by descending key, keeping
+/// them sorted. Items with equal keys are kept in the order they were added.
+/// Returns the runs of consecutive positions that the new items were inserted
+/// at, as `(position, count)` tuples in ascending order of position.
+fn merge_sorted<T>(items: &mut Vec<T>, mut new_items: Vec<T>, key: impl Fn(&T) -> i64) -> Vec<(u32, u32)> {
+    new_items.sort_by_key(|item: &T| std::cmp::Reverse(key(item)));
+
+    let mut existing = std::mem::take(items).into_iter().peekable();
+    let mut runs: Vec<(u32, u32)> = vec![];
+
+    for item in new_items {
+        let item_key: i64 = key(&item);
+
+        while let Some(existing_item) = existing.next_if(|existing_item: &T| key(existing_item) >= item_key) {
+            items.push(existing_item);
+        }
+        let position: u32 = items.len().try_into().unwrap();
+        items.push(item);
+
+        match runs.last_mut() {
+            Some((start, count)) if *start + *count == position => *count += 1,
+            _ => runs.push((position, 1)),
+        }
+    }
+    items.extend(existing);
+    runs
+}
+
+/// Compares the keys of the old and new items of a list, and returns the runs of
+/// positions that were removed from the old items and the runs of positions that
+/// were added in the new items, as `(position, count)` tuples in ascending order
+/// of position. The most items that are in the same order in both lists are kept,
+/// so items that were reordered are removed and added again at their new position.
+fn diff_runs(old_keys: &[String], new_keys: &[String]) -> (Vec<(u32, u32)>, Vec<(u32, u32)>) {
+    let mut old_positions: HashMap<&String, usize> = HashMap::with_capacity(old_keys.len());
+
+    for (position, key) in old_keys.iter().enumerate() {
+        old_positions.entry(key).or_insert(position);
+    }
+    // Pairs of new and old positions of the items in both lists, in new order.
+    let common: Vec<(usize, usize)> = new_keys
+        .iter()
+        .enumerate()
+        .filter_map(|(new_position, key)| Some((new_position, *old_positions.get(key)?)))
+        .collect();
+    let kept: Vec<usize> = longest_increasing_run(&common);
+
+    let kept_old: HashSet<usize> = kept.iter().map(|i: &usize| common[*i].1).collect();
+    let kept_new: HashSet<usize> = kept.iter().map(|i: &usize| common[*i].0).collect();
+
+    (
+        position_runs((0..old_keys.len()).filter(|position: &usize| !kept_old.contains(position))),
+        position_runs((0..new_keys.len()).filter(|position: &usize| !kept_new.contains(position))),
+    )
+}
+
+/// Returns the indices into `pairs` of the longest subsequence whose
+/// second values are strictly increasing, in ascending order.
+fn longest_increasing_run(pairs: &[(usize, usize)]) -> Vec<usize> {
+    // Index of the pair that ends the smallest tail of each subsequence length.
+    let mut tails: Vec<usize> = vec![];
+    // Index of the pair before each pair in its subsequence.
+    let mut previous: Vec<Option<usize>> = vec![None; pairs.len()];
+
+    for (i, (_, value)) in pairs.iter().enumerate() {
+        let length: usize = tails.partition_point(|tail: &usize| pairs[*tail].1 < *value);
+
+        previous[i] = length.checked_sub(1).map(|length: usize| tails[length]);
+
+        match tails.get_mut(length) {
+            Some(tail) => *tail = i,
+            None => tails.push(i),
+        }
+    }
+    let mut run: Vec<usize> = vec![];
+    let mut next: Option<usize> = tails.last().copied();
+
+    while let Some(i) = next {
+        run.push(i);
+        next = previous[i];
+    }
+    run.reverse();
+    run
+}
+
+/// Groups ascending positions into runs of consecutive positions.
+fn position_runs(positions: impl Iterator<Item = usize>) -> Vec<(u32, u32)> {
+    let mut runs: Vec<(u32, u32)> = vec![];
+
+    for position in positions {
+        let position: u32 = position.try_into().unwrap();
+
+        match runs.last_mut() {
+            Some((start, count)) if *start + *count == position => *count += 1,
+            _ => runs.push((position, 1)),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::media_item::MemoriesMediaItem;
+    use gtk::prelude::*;
+    use std::cell::RefCell;
+    use std::path::{Path, PathBuf};
+    use std::rc::Rc;
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key: &&str| key.to_string()).collect()
+    }
+
+    #[test]
+    fn recent_entries_move_to_front() {
+        assert_eq!(push_recent(&["a", "b", "c"], "b", 3), ["b", "a", "c"]);
+        assert_eq!(push_recent(&["a", "b", "c"], "d", 3), ["d", "a", "b"]);
+        assert_eq!(push_recent(&[], "a", 3), ["a"]);
+    }
+
+    #[test]
+    fn diff_finds_removed_and_added_runs() {
+        let old: Vec<String> = keys(&["a", "b", "c", "d", "e"]);
+        let new: Vec<String> = keys(&["x", "a", "d", "y", "z", "e"]);
+
+        let (removed, added) = diff_runs(&old, &new);
+        assert_eq!(removed, vec![(1, 2)]);
+        assert_eq!(added, vec![(0, 1), (3, 2)]);
+    }
+
+    #[test]
+    fn diff_of_reordered_items() {
+        // Only "c" moved, so the other items are kept.
+        let old: Vec<String> = keys(&["a", "b", "c", "d", "e"]);
+        let new: Vec<String> = keys(&["c", "a", "b", "d", "e"]);
+
+        assert_eq!(diff_runs(&old, &new), (vec![(2, 1)], vec![(0, 1)]));
+        assert_eq!(
+            diff_runs(&keys(&["a", "b"]), &keys(&["b", "a"])),
+            (vec![(1, 1)], vec![(0, 1)])
+        );
+        assert_eq!(diff_runs(&keys(&["a"]), &keys(&["a"])), (vec![], vec![]));
+    }
+
+    #[test]
+    fn longest_run_of_increasing_positions() {
+        let pairs: Vec<(usize, usize)> = vec![(0, 3), (1, 0), (2, 1), (3, 4), (4, 2), (5, 5)];
+
+        assert_eq!(longest_increasing_run(&pairs), vec![1, 2, 4, 5]);
+        assert_eq!(longest_increasing_run(&[]), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn merge_keeps_descending_order() {
+        let mut items: Vec<i64> = vec![9, 7, 5, 1];
+        let runs: Vec<(u32, u32)> = merge_sorted(&mut items, vec![6, 10, 8, 0, 6], |item: &i64| *item);
+
+        assert_eq!(items, vec![10, 9, 8, 7, 6, 6, 5, 1, 0]);
+        assert_eq!(runs, vec![(0, 1), (2, 1), (4, 2), (8, 1)]);
+    }
+
+    #[test]
+    fn merge_into_empty_items() {
+        let mut items: Vec<i64> = vec![];
+        let runs: Vec<(u32, u32)> = merge_sorted(&mut items, vec![1, 3, 2], |item: &i64| *item);
+
+        assert_eq!(items, vec![3, 2, 1]);
+        assert_eq!(runs, vec![(0, 3)]);
+    }
+
+    /// Creates the given files under a new temporary library folder. Each
+    /// file gets the given modification time, in seconds since the Unix epoch.
+    fn library_fixture(files: &[(&str, u64)]) -> tempfile::TempDir {
+        let folder: tempfile::TempDir = tempfile::tempdir().unwrap();
+
+        for (relative_path, modified) in files {
+            create_file(&folder.path().join(relative_path), *modified);
+        }
+        folder
+    }
+
+    /// Creates an empty file and its parent directories.
+    fn create_file(path: &Path, modified: u64) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, b"").unwrap();
+
+        gio::File::for_path(path)
+            .set_attribute_uint64(
+                gio::FILE_ATTRIBUTE_TIME_MODIFIED,
+                modified,
+                gio::FileQueryInfoFlags::NONE,
+                gio::Cancellable::NONE,
+            )
+            .unwrap();
+    }
+
+    /// Creates a library list model for the given folders, and iterates
+    /// the main context until all of its directories are enumerated.
+    fn enumerated_model(folders: &[&Path], sort_by_date: bool) -> MemoriesLibraryListModel {
+        let model: MemoriesLibraryListModel = glib::Object::builder()
+            .property("sort-by-date", sort_by_date)
+            .build();
+
+        model.set_subdirectories(
+            folders
+                .iter()
+                .map(|folder: &&Path| glib::GString::from(folder.to_str().unwrap()))
+                .collect::<glib::StrV>(),
+        );
+        wait_until_loaded(&model);
+        model
+    }
+
+    /// Iterates the main context until the model is done enumerating and
+    /// its pending items are added, failing the test after a few seconds.
+    fn wait_until_loaded(model: &MemoriesLibraryListModel) {
+        let context: glib::MainContext = glib::MainContext::default();
+        let deadline: std::time::Instant = std::time::Instant::now() + std::time::Duration::from_secs(5);
+
+        while !model.models_loaded() {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "Library list model did not load."
+            );
+            context.iteration(false);
+        }
+        while context.iteration(false) {}
+    }
+
+    /// Returns the paths of the model's items, relative to the given folder.
+    fn item_paths(model: &MemoriesLibraryListModel, folder: &Path) -> Vec<String> {
+        (0..model.n_items())
+            .map(|position: u32| {
+                let item: MemoriesMediaItem = model.item(position).and_downcast().unwrap();
+                let path: PathBuf = item.file().path().unwrap();
+                path.strip_prefix(folder).unwrap().to_string_lossy().to_string()
+            })
+            .collect()
+    }
+
+    /// Sorts the paths in each of the given ranges, since
+    /// directories are enumerated in no particular order.
+    fn sorted_ranges(mut paths: Vec<String>, ranges: &[std::ops::Range<usize>]) -> Vec<String> {
+        for range in ranges {
+            paths[range.clone()].sort();
+        }
+        paths
+    }
+
+    #[gtk::test]
+    fn subdirectory_items_follow_root_items() {
+        let folder: tempfile::TempDir =
+            library_fixture(&[("a.jpg", 1), ("b.png", 2), ("album/c.jpg", 3), ("album/d.mp4", 4)]);
+        let model: MemoriesLibraryListModel = enumerated_model(&[folder.path()], false);
+
+        assert_eq!(
+            sorted_ranges(item_paths(&model, folder.path()), &[0..2, 2..4]),
+            vec!["a.jpg", "b.png", "album/c.jpg", "album/d.mp4"],
+        );
+        assert_eq!(model.items_enumerated(), 4);
+        assert_eq!(model.directories_pending(), 0);
+    }
+
+    #[gtk::test]
+    fn items_are_ordered_by_root_folder() {
+        let first: tempfile::TempDir = library_fixture(&[("a.jpg", 1), ("nested/b.jpg", 2)]);
+        let second: tempfile::TempDir = library_fixture(&[("c.jpg", 3)]);
+        let model: MemoriesLibraryListModel = enumerated_model(&[first.path(), second.path()], false);
+
+        let files: Vec<String> = (0..model.n_items())
+            .map(|position: u32| {
+                let item: MemoriesMediaItem = model.item(position).and_downcast().unwrap();
+                item.basename().to_string()
+            })
+            .collect();
+        assert_eq!(files, vec!["a.jpg", "b.jpg", "c.jpg"]);
+    }
+
+    #[gtk::test]
+    fn hidden_files_count_towards_offsets() {
+        let folder: tempfile::TempDir = library_fixture(&[
+            (".hidden.jpg", 1),
+            ("a.jpg", 2),
+            ("album/.b.jpg", 3),
+            ("album/c.jpg", 4),
+        ]);
+        let model: MemoriesLibraryListModel = enumerated_model(&[folder.path()], false);
+
+        assert_eq!(
+            sorted_ranges(item_paths(&model, folder.path()), &[0..2, 2..4]),
+            vec![".hidden.jpg", "a.jpg", "album/.b.jpg", "album/c.jpg"],
+        );
+    }
+
+    #[gtk::test]
+    fn sorted_mode_orders_items_newest_first() {
+        let folder: tempfile::TempDir =
+            library_fixture(&[("old.jpg", 100), ("album/new.jpg", 300), ("middle.jpg", 200)]);
+        let model: MemoriesLibraryListModel = enumerated_model(&[folder.path()], true);
+
+        assert_eq!(
+            item_paths(&model, folder.path()),
+            vec!["album/new.jpg", "middle.jpg", "old.jpg"],
+        );
+    }
+
+    #[gtk::test]
+    fn refresh_applies_added_and_removed_files() {
+        let folder: tempfile::TempDir =
+            library_fixture(&[("a.jpg", 300), ("b.jpg", 200), ("album/c.jpg", 100)]);
+        let model: MemoriesLibraryListModel = enumerated_model(&[folder.path()], true);
+
+        let kept: glib::Object = model.item(0).unwrap();
+        let changes: Rc<RefCell<Vec<(u32, u32, u32)>>> = Rc::default();
+
+        model.connect_items_changed(glib::clone!(
+            #[strong]
+            changes,
+            move |_: &MemoriesLibraryListModel, position: u32, removed: u32, added: u32| {
+                changes.borrow_mut().push((position, removed, added));
+            }
+        ));
+        std::fs::remove_file(folder.path().join("b.jpg")).unwrap();
+        create_file(&folder.path().join("album/d.jpg"), 150);
+
+        model.refresh();
+        wait_until_loaded(&model);
+
+        assert_eq!(
+            item_paths(&model, folder.path()),
+            vec!["a.jpg", "album/d.jpg", "album/c.jpg"]
+        );
+        // Items that are still in the library keep their objects.
+        assert_eq!(model.item(0).unwrap(), kept);
+        assert!(changes
+            .borrow()
+            .iter()
+            .all(|(_, removed, added)| removed + added == 1));
+    }
+}
@@ -18,45 +18,70 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use crate::application::MemoriesApplication;
+use crate::i18n::{gettext_f, ngettext_f};
+use crate::library::burst_dialog::MemoriesBurstDialog;
+use crate::library::compress_dialog::probe_duration;
 use crate::library::media_grid::MemoriesMediaGridView;
 use crate::library::media_item::MemoriesMediaItem;
 use crate::library::media_viewer::{MemoriesMediaViewer, ViewerContentType};
 use crate::library::properties::{ContentDetails, PictureDetails};
+use crate::library::scan_service::MemoriesScanService;
+use crate::util::enums::ItemActivation;
+use crate::util::format;
+use crate::util::hwaccel::HardwareAccel;
 use crate::util::metadata::get_metadata_with_hash;
+use crate::util::preview;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
-use async_fs::File;
 use async_semaphore::Semaphore;
-use glib::{clone, g_critical, g_error, g_warning};
+use gettextrs::gettext;
+use glib::{clone, g_debug, g_warning};
 use glycin::Loader;
 #[cfg(feature = "disable-glycin-sandbox")]
 use glycin::SandboxMechanism;
-use gtk::{gio, glib};
+use gtk::{gdk, gio, glib};
 use std::cell::RefCell;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-mod imp {
+pub(super) mod imp {
     use crate::application::MemoriesApplication;
-    use crate::globals::{CACHE_THUMBNAILS_SUBDIR, FFMPEG_BINARY};
+    use crate::globals::{
+        CACHE_THUMBNAILS_SUBDIR, FFMPEG_BINARY, GLYCIN_THUMBNAIL_EXTENSIONS, REMOTE_THUMBNAIL_READ_SIZE,
+    };
     use crate::library::media_viewer::ViewerContentType;
     use crate::library::properties::ContentDetails;
-    use crate::util::metadata::MetadataInfo;
+    use crate::util::gpu;
+    use crate::util::hwaccel::HardwareAccel;
+    #[cfg(feature = "use-libav")]
+    use crate::util::libav;
+    use crate::util::metadata::{self, MetadataInfo};
+    use crate::util::process;
+    use crate::util::profiling::ProfileSpan;
+    use adw::prelude::*;
     use adw::subclass::prelude::*;
     use async_fs::File;
     use async_process::{Command, Output};
     use async_semaphore::{Semaphore, SemaphoreGuard};
     use glib::{g_debug, g_warning};
-    use gtk::{gio, glib};
+    use glycin::Loader;
+    #[cfg(feature = "disable-glycin-sandbox")]
+    use glycin::SandboxMechanism;
+    use gtk::gdk_pixbuf::{Colorspace, InterpType, Pixbuf, PixbufLoader};
+    use gtk::{gdk, gio, glib};
     use std::cell::{Cell, OnceCell, RefCell};
-    use std::io;
-    use std::path::Path;
+    use std::ffi::OsStr;
+    use std::io::{self, Read};
+    use std::path::{Path, PathBuf};
     use std::sync::Arc;
+    use std::time::Instant;
 
     /// `AdwBin` subclass to store arbitrary data for grid cells
     /// of the library photo grid view. Stores signal
     /// handler IDs, glib async join handles, metadata, etc.
-    #[derive(Default, gtk::CompositeTemplate)]
+    #[derive(glib::Properties, Default, gtk::CompositeTemplate)]
+    #[properties(wrapper_type = super::MemoriesMediaCell)]
     #[template(resource = "/com/maxrdz/Memories/ui/media-cell.ui")]
     pub struct MemoriesMediaCell {
         #[template_child]
@@ -64,13 +89,28 @@ mod imp {
         #[template_child]
         pub(super) aspect_frame: TemplateChild<gtk::AspectFrame>,
         #[template_child]
+        pub(super) placeholder: TemplateChild<gtk::Picture>,
+        #[template_child]
         pub thumbnail_image: TemplateChild<gtk::Image>,
         #[template_child]
-        favorited: TemplateChild<gtk::Image>,
+        pub(super) favorited: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub(super) rating_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub(super) burst_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) burst_content: TemplateChild<adw::ButtonContent>,
         #[template_child]
         media_type_icon: TemplateChild<gtk::Image>,
         #[template_child]
-        video_length: TemplateChild<gtk::Label>,
+        pub(super) video_length: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub(super) load_error_badge: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub(super) mime_badge: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub(super) context_menu_model: TemplateChild<gio::MenuModel>,
+        pub(super) context_menu: OnceCell<gtk::PopoverMenu>,
 
         pub img_file_notify: RefCell<OnceCell<glib::SignalHandlerId>>,
         pub tx_join_handle: Cell<Option<glib::JoinHandle<()>>>,
@@ -79,6 +119,21 @@ mod imp {
         pub file_metadata: OnceCell<MetadataInfo>,
         pub viewer_content_type: OnceCell<ViewerContentType>,
         pub content_details: RefCell<ContentDetails>,
+        /// Whether the pointer is over the cell, or the cell is selected.
+        /// The star rating of the media is only shown in either case.
+        pub(super) hovered: Cell<bool>,
+        /// Bound to the `selected` property of the list item of the cell.
+        #[property(get, set)]
+        pub(super) selected: Cell<bool>,
+        /// Edge length in pixels of the thumbnail currently shown, or 0 if none.
+        pub thumbnail_size: Cell<u32>,
+        /// File path and content hash the current thumbnail was generated from.
+        pub thumbnail_source: RefCell<Option<(PathBuf, String)>>,
+        /// Error that the thumbnail or the metadata of the media failed to load with.
+        pub(super) load_error: RefCell<Option<String>>,
+        /// Content type of the media if its format is not supported,
+        /// in which case it is opened in another app when activated.
+        pub(super) unsupported_type: RefCell<Option<glib::GString>>,
     }
 
     #[glib::object_subclass]
@@ -97,7 +152,14 @@ mod imp {
         }
     }
 
-    impl ObjectImpl for MemoriesMediaCell {}
+    #[glib::derived_properties]
+    impl ObjectImpl for MemoriesMediaCell {
+        fn dispose(&self) {
+            if let Some(context_menu) = self.context_menu.get() {
+                context_menu.unparent();
+            }
+        }
+    }
     impl WidgetImpl for MemoriesMediaCell {}
     impl BinImpl for MemoriesMediaCell {}
 
@@ -108,14 +170,18 @@ mod imp {
             file_path: &Path,
             cached_file_name: &str,
             semaphore: Arc<Semaphore>,
-            hwaccel: bool,
+            hwaccel: Option<HardwareAccel>,
+            ffmpeg_available: bool,
+            size: u32,
         ) -> io::Result<String> {
-            // This is the absolute outfile path for the thumbnail.
+            // This is the absolute outfile path for the thumbnail. Thumbnails of
+            // different sizes are cached separately, suffixed with their size.
             let absolute_out_path: String = format!(
-                "{}/{}/{}.jpg",
+                "{}/{}/{}-{}.jpg",
                 MemoriesApplication::get_app_cache_directory(),
                 CACHE_THUMBNAILS_SUBDIR,
-                cached_file_name
+                cached_file_name,
+                size
             );
 
             // Check if we have the thumbnail already cached, if so, return its path.
@@ -129,33 +195,138 @@ mod imp {
                         "'{}' not found in app cache. Generating new thumbnail.",
                         absolute_out_path,
                     ),
-                    _ => todo!(), // TODO: Extend error handling for cache check
+                    _ => g_warning!(
+                        "MediaCell",
+                        "Failed to open cached thumbnail '{}': {}. Generating new thumbnail.",
+                        absolute_out_path,
+                        e
+                    ),
                 },
             }
+            let _span: ProfileSpan = ProfileSpan::start("thumbnail", file_path.display());
 
-            let semaphore_guard: SemaphoreGuard<'_> = semaphore.acquire().await;
-
-            let file_extension: &str = file_path
+            // Files with a missing or unknown extension are thumbnailed by their contents.
+            let content_type: ViewerContentType =
+                ViewerContentType::for_file(&gio::File::for_path(file_path)).await;
+            let file_extension: String = file_path
                 .extension()
-                .expect("Was given file path with no file extension!")
-                .to_str()
-                .unwrap();
+                .map(|extension: &OsStr| extension.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+
+            // Files on network locations are read through GVFS, so only the start of
+            // remote JPEG files is read to use their embedded thumbnail if it is big enough.
+            if matches!(file_extension.as_str(), "jpg" | "jpeg") {
+                let in_path: PathBuf = file_path.to_path_buf();
+                let out_path: String = absolute_out_path.clone();
+
+                if let Ok(Ok(())) = gio::spawn_blocking(move || {
+                    Self::generate_embedded_thumbnail(&in_path, &out_path, size as i32)
+                })
+                .await
+                {
+                    return Ok(absolute_out_path);
+                }
+            }
+
+            // Photos are scaled on the GPU instead if enabled in the preferences. The time
+            // taken is logged, so that it can be compared to scaling on the CPU.
+            let gpu_scaling: bool = MemoriesApplication::default()
+                .gsettings()
+                .boolean("gpu-thumbnail-scaling");
+
+            if gpu_scaling && matches!(content_type, ViewerContentType::Image) {
+                let semaphore_guard: SemaphoreGuard<'_> = semaphore.acquire().await;
+                let started: Instant = Instant::now();
+                let gpu_result: io::Result<()> =
+                    gpu::generate_thumbnail(file_path, &absolute_out_path, size).await;
+                drop(semaphore_guard);
+
+                match gpu_result {
+                    Ok(()) => {
+                        g_debug!(
+                            "MediaCell",
+                            "Scaled a thumbnail on the GPU in {} ms.",
+                            started.elapsed().as_millis()
+                        );
+                        return Ok(absolute_out_path);
+                    }
+                    Err(e) => g_debug!(
+                        "MediaCell",
+                        "Failed to scale a thumbnail on the GPU, falling back to the CPU: {}",
+                        e
+                    ),
+                }
+            }
+
+            // Photo formats that ffmpeg and gdk-pixbuf often can not decode are decoded by glycin.
+            if GLYCIN_THUMBNAIL_EXTENSIONS.contains(&file_extension.as_str()) {
+                let _semaphore_guard: SemaphoreGuard<'_> = semaphore.acquire().await;
+
+                return Self::generate_glycin_thumbnail(file_path, &absolute_out_path, size as i32)
+                    .await
+                    .map(|()| absolute_out_path);
+            }
+
+            // Extract the thumbnail frame in-process with libav if available,
+            // and fall back to the ffmpeg binary (or gdk-pixbuf) if it fails.
+            #[cfg(feature = "use-libav")]
+            {
+                let in_path: PathBuf = file_path.to_path_buf();
+                let out_path: String = absolute_out_path.clone();
 
-            let extra_arguments: &[&str] = match file_extension.to_lowercase().as_str() {
-                "png" | "jpg" | "jpeg" | "webp" | "heic" | "heif" => {
-                    &["-vf", "crop='min(iw,ih):min(iw,ih)',scale=150:150"]
+                let semaphore_guard: SemaphoreGuard<'_> = semaphore.acquire().await;
+                let libav_result = gio::spawn_blocking(move || {
+                    libav::generate_thumbnail(&in_path, &out_path, size, hwaccel)
+                })
+                .await;
+                drop(semaphore_guard);
+
+                match libav_result {
+                    Ok(Ok(())) => return Ok(absolute_out_path),
+                    Ok(Err(e)) => g_debug!(
+                        "MediaCell",
+                        "libav failed to generate a thumbnail, falling back to ffmpeg: {}",
+                        e
+                    ),
+                    Err(_) => g_warning!("MediaCell", "libav thumbnail thread panicked."),
                 }
-                "mp4" | "webm" | "mkv" | "mov" | "avi" | "gif" => &[
-                    "-vf",
-                    "thumbnail,crop='min(iw,ih):min(iw,ih)',scale=150:150",
-                    "-frames:v",
-                    "1",
+            }
+
+            // Without ffmpeg, fall back to gdk-pixbuf for image thumbnails.
+            // Video thumbnails are not supported without ffmpeg.
+            if !ffmpeg_available {
+                let in_path: PathBuf = file_path.to_path_buf();
+                let out_path: String = absolute_out_path.clone();
+
+                return match gio::spawn_blocking(move || {
+                    Self::generate_pixbuf_thumbnail(&in_path, &out_path, size as i32)
+                })
+                .await
+                {
+                    Ok(Ok(())) => Ok(absolute_out_path),
+                    Ok(Err(e)) => Err(e),
+                    Err(_) => Err(io::Error::new(io::ErrorKind::Other, "Thumbnail thread panicked.")),
+                };
+            }
+
+            let semaphore_guard: SemaphoreGuard<'_> = semaphore.acquire().await;
+            let started: Instant = Instant::now();
+
+            let crop_filter: String = format!("crop='min(iw,ih):min(iw,ih)',scale={0}:{0}", size);
+
+            let extra_arguments: Vec<String> = match content_type {
+                ViewerContentType::Image => vec!["-vf".into(), crop_filter],
+                ViewerContentType::Video => vec![
+                    "-vf".into(),
+                    format!("thumbnail,{}", crop_filter),
+                    "-frames:v".into(),
+                    "1".into(),
                 ],
                 _ => {
                     g_warning!(
                         "MediaCell",
                         "'{}': unsupported file format, or an unrecognized extension.",
-                        file_extension
+                        file_path.display()
                     );
                     return Err(io::Error::new(
                         io::ErrorKind::InvalidInput,
@@ -163,10 +334,10 @@ mod imp {
                     ));
                 }
             };
-            let mut ffmpeg_command: &mut Command = &mut Command::new(FFMPEG_BINARY);
+            let mut ffmpeg_command: &mut Command = &mut process::low_priority_command(FFMPEG_BINARY);
 
-            if hwaccel {
-                ffmpeg_command = ffmpeg_command.args(["-hwaccel", "auto"]);
+            if let Some(backend) = hwaccel {
+                ffmpeg_command = ffmpeg_command.args(["-hwaccel", backend.ffmpeg_name()]);
             }
 
             let ffmpeg_output: Result<Output, io::Error> = ffmpeg_command
@@ -185,17 +356,162 @@ mod imp {
             match ffmpeg_output {
                 // An error should **never** occur here, since we check the existence
                 // of the ffmpeg binary installation at the start of the library load.
-                Err(e) => panic!("Failed to execute ffmpeg binary!\n\n{}", e),
+                Err(e) => {
+                    g_warning!("MediaCell", "Failed to execute ffmpeg binary: {}", e);
+                    Err(e)
+                }
                 Ok(v) => {
                     if !v.stderr.is_empty() {
                         g_debug!("MediaCell", "FFmpeg printed to stderr: {:?}", v);
                         Err(io::Error::new(io::ErrorKind::Other, "FFmpeg printed to stderr."))
                     } else {
+                        g_debug!(
+                            "MediaCell",
+                            "Scaled a thumbnail with ffmpeg in {} ms.",
+                            started.elapsed().as_millis()
+                        );
                         Ok(absolute_out_path)
                     }
                 }
             }
         }
+
+        /// Writes a cropped square JPEG thumbnail from the thumbnail embedded in the
+        /// EXIF data of a JPEG file, reading only the start of the file. Fails if
+        /// the file is not on a remote filesystem, or if its thumbnail is too small.
+        fn generate_embedded_thumbnail(file_path: &Path, out_path: &str, size: i32) -> io::Result<()> {
+            let to_io_error = |e: glib::Error| io::Error::new(io::ErrorKind::Other, e.to_string());
+
+            let remote: bool = gio::File::for_path(file_path)
+                .query_filesystem_info(gio::FILE_ATTRIBUTE_FILESYSTEM_REMOTE, gio::Cancellable::NONE)
+                .map_err(to_io_error)?
+                .boolean(gio::FILE_ATTRIBUTE_FILESYSTEM_REMOTE);
+
+            if !remote {
+                return Err(io::Error::new(io::ErrorKind::Unsupported, "Not a remote file."));
+            }
+            let mut file_head: Vec<u8> = vec![];
+            std::fs::File::open(file_path)?
+                .take(REMOTE_THUMBNAIL_READ_SIZE)
+                .read_to_end(&mut file_head)?;
+
+            let Some((jpeg, orientation)) = metadata::embedded_jpeg_thumbnail(&file_head) else {
+                return Err(io::Error::new(io::ErrorKind::NotFound, "No embedded thumbnail."));
+            };
+            let loader: PixbufLoader = PixbufLoader::new();
+            loader.write(&jpeg).map_err(to_io_error)?;
+            loader.close().map_err(to_io_error)?;
+
+            let Some(mut pixbuf) = loader.pixbuf() else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid embedded thumbnail.",
+                ));
+            };
+            let shortest_edge: i32 = pixbuf.width().min(pixbuf.height());
+
+            if shortest_edge < size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Embedded thumbnail is too small.",
+                ));
+            }
+            // The embedded thumbnail has the orientation of the primary image.
+            pixbuf.set_option("orientation", &orientation.to_string());
+
+            if let Some(oriented) = pixbuf.apply_embedded_orientation() {
+                pixbuf = oriented;
+            }
+            let scale: f64 = size as f64 / shortest_edge as f64;
+            let scaled_width: i32 = ((pixbuf.width() as f64 * scale).round() as i32).max(size);
+            let scaled_height: i32 = ((pixbuf.height() as f64 * scale).round() as i32).max(size);
+
+            let Some(scaled) = pixbuf.scale_simple(scaled_width, scaled_height, InterpType::Bilinear) else {
+                return Err(io::Error::new(io::ErrorKind::Other, "Failed to scale thumbnail."));
+            };
+            let thumbnail: Pixbuf =
+                scaled.new_subpixbuf((scaled_width - size) / 2, (scaled_height - size) / 2, size, size);
+            thumbnail.savev(out_path, "jpeg", &[]).map_err(to_io_error)
+        }
+
+        /// Writes a cropped square JPEG thumbnail of a photo decoded by glycin. Only the
+        /// scaling and the JPEG encoding are done on a separate thread.
+        async fn generate_glycin_thumbnail(file_path: &Path, out_path: &str, size: i32) -> io::Result<()> {
+            let to_io_error = |e: glycin::ErrorCtx| io::Error::new(io::ErrorKind::Other, e.to_string());
+
+            #[allow(unused_mut)]
+            let mut glycin_loader: Loader = Loader::new(gio::File::for_path(file_path));
+
+            #[cfg(feature = "disable-glycin-sandbox")]
+            glycin_loader.sandbox_mechanism(Some(SandboxMechanism::NotSandboxed));
+
+            let image: glycin::Image<'static> = glycin_loader.load().await.map_err(to_io_error)?;
+            let texture: gdk::Texture = image.next_frame().await.map_err(to_io_error)?.texture();
+
+            let mut downloader: gdk::TextureDownloader = gdk::TextureDownloader::new(&texture);
+            downloader.set_format(gdk::MemoryFormat::R8g8b8);
+
+            let (bytes, stride): (glib::Bytes, usize) = downloader.download_bytes();
+            let (width, height): (i32, i32) = (texture.width(), texture.height());
+            let out_path: String = out_path.to_owned();
+
+            gio::spawn_blocking(move || {
+                let to_io_error = |e: glib::Error| io::Error::new(io::ErrorKind::Other, e.to_string());
+                let pixbuf: Pixbuf =
+                    Pixbuf::from_bytes(&bytes, Colorspace::Rgb, false, 8, width, height, stride as i32);
+
+                // Scale the image so that its shortest edge matches the thumbnail size.
+                let scale: f64 = size as f64 / width.min(height).max(1) as f64;
+                let scaled_width: i32 = ((width as f64 * scale).round() as i32).max(size);
+                let scaled_height: i32 = ((height as f64 * scale).round() as i32).max(size);
+
+                let Some(scaled) = pixbuf.scale_simple(scaled_width, scaled_height, InterpType::Bilinear)
+                else {
+                    return Err(io::Error::new(io::ErrorKind::Other, "Failed to scale thumbnail."));
+                };
+                let thumbnail: Pixbuf =
+                    scaled.new_subpixbuf((scaled_width - size) / 2, (scaled_height - size) / 2, size, size);
+                thumbnail.savev(&out_path, "jpeg", &[]).map_err(to_io_error)
+            })
+            .await
+            .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "Thumbnail thread panicked.")))
+        }
+
+        /// Writes a cropped square JPEG thumbnail of an image file using gdk-pixbuf.
+        /// Used in place of ffmpeg when the ffmpeg binary is not available.
+        fn generate_pixbuf_thumbnail(file_path: &Path, out_path: &str, size: i32) -> io::Result<()> {
+            let Some((_, width, height)) = Pixbuf::file_info(file_path) else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Unrecognized image file.",
+                ));
+            };
+            if width <= 0 || height <= 0 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "Image has no size."));
+            }
+
+            // Scale the image so that its shortest edge matches the thumbnail size.
+            let scale: f64 = size as f64 / width.min(height) as f64;
+            let scaled_width: i32 = ((width as f64 * scale).round() as i32).max(size);
+            let scaled_height: i32 = ((height as f64 * scale).round() as i32).max(size);
+
+            let to_io_error = |e: glib::Error| io::Error::new(io::ErrorKind::Other, e.to_string());
+
+            let mut pixbuf: Pixbuf =
+                Pixbuf::from_file_at_scale(file_path, scaled_width, scaled_height, false)
+                    .map_err(to_io_error)?;
+
+            if let Some(oriented) = pixbuf.apply_embedded_orientation() {
+                pixbuf = oriented;
+            }
+            let thumbnail: Pixbuf = pixbuf.new_subpixbuf(
+                (pixbuf.width() - size) / 2,
+                (pixbuf.height() - size) / 2,
+                size,
+                size,
+            );
+            thumbnail.savev(out_path, "jpeg", &[]).map_err(to_io_error)
+        }
     }
 }
 
@@ -235,6 +551,7 @@ impl MemoriesMediaCell {
             #[weak(rename_to = this)]
             self,
             move |_: &gtk::Image| {
+                this.imp().placeholder.remove_css_class("skeleton");
                 this.imp().revealer.set_reveal_child(false);
                 this.imp().revealer.set_transition_duration(1000); // milliseconds
                 this.imp()
@@ -250,48 +567,504 @@ impl MemoriesMediaCell {
             .set(handler_id)
             .expect("Media cell's `img_file_notify` already initialized!");
 
+        // Bind the favorite indicator and the duration badge to the properties of the
+        // media item of the list item, so they are kept up to date as the item changes,
+        // or as the cell is bound to another item, without any work at bind time.
+        let item_expression: gtk::PropertyExpression = list_item.property_expression("item");
+
+        item_expression
+            .chain_property::<MemoriesMediaItem>("favorite")
+            .bind(&self.imp().favorited.get(), "visible", gtk::Widget::NONE);
+
+        let duration_expression: gtk::PropertyExpression =
+            item_expression.chain_property::<MemoriesMediaItem>("duration");
+
+        duration_expression
+            .chain_closure::<String>(glib::closure_local!(|_: Option<glib::Object>, duration: u32| {
+                format::duration(duration)
+            }))
+            .bind(&self.imp().video_length.get(), "label", gtk::Widget::NONE);
+        duration_expression
+            .chain_closure::<bool>(glib::closure_local!(
+                |_: Option<glib::Object>, duration: u32| duration > 0
+            ))
+            .bind(&self.imp().video_length.get(), "visible", gtk::Widget::NONE);
+
+        MemoriesApplication::default().connect_metadata_changed(
+            Some("ratings"),
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &MemoriesApplication, _: &str| this.update_rating_overlay()
+            ),
+        );
+
+        // Keep the burst badge in sync with the bursts detected in the library.
+        media_grid.connect_bursts_changed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |media_grid: &MemoriesMediaGridView| this.update_burst_badge(media_grid)
+        ));
+
+        // Regenerate the thumbnail once the media file is changed, such as by an external editor.
+        MemoriesApplication::default()
+            .scan_service()
+            .connect_file_changed(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                media_grid,
+                move |_: &MemoriesScanService, uri: &str| {
+                    let is_changed_file: bool = this
+                        .imp()
+                        .thumbnail_source
+                        .borrow()
+                        .as_ref()
+                        .is_some_and(|(path, _)| gio::File::for_path(path).uri() == uri);
+
+                    if is_changed_file {
+                        this.refresh_thumbnail(&media_grid);
+                    }
+                }
+            ));
+        self.imp().burst_button.connect_clicked(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            media_grid,
+            move |_: &gtk::Button| this.open_burst_dialog(&media_grid)
+        ));
+
+        // Show the star rating of the media on hover, or while the cell is selected.
+        let motion_controller: gtk::EventControllerMotion = gtk::EventControllerMotion::new();
+
+        motion_controller.connect_enter(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_: &gtk::EventControllerMotion, _, _| {
+                this.imp().hovered.set(true);
+                this.update_rating_overlay();
+            }
+        ));
+        motion_controller.connect_leave(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_: &gtk::EventControllerMotion| {
+                this.imp().hovered.set(false);
+                this.update_rating_overlay();
+            }
+        ));
+        self.add_controller(motion_controller);
+
+        list_item
+            .property_expression("selected")
+            .bind(self, "selected", gtk::Widget::NONE);
+        self.connect_selected_notify(|this: &MemoriesMediaCell| this.update_rating_overlay());
+
+        // Regenerate a larger thumbnail if the grid is zoomed in,
+        // or if the cell is moved to a monitor with a higher scale.
+        media_grid.connect_grid_widget_height_notify(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |media_grid: &MemoriesMediaGridView| {
+                this.update_thumbnail_size(media_grid);
+            }
+        ));
+        self.connect_scale_factor_notify(clone!(
+            #[weak]
+            media_grid,
+            move |this: &MemoriesMediaCell| {
+                this.update_thumbnail_size(&media_grid);
+            }
+        ));
+
         let click_gesture: gtk::GestureClick = gtk::GestureClick::default();
 
         self.imp().revealer.add_controller(click_gesture.clone());
 
+        // Clicks open the media as configured in GSettings. The grid selects the
+        // cell on the first click, or on hover if media open with a single click.
         click_gesture.connect_pressed(clone!(
             #[weak]
             media_grid,
             #[weak]
             list_item,
-            move |_, _, _, _| {
-                if list_item.is_selected() {
-                    let current_nav_page: adw::NavigationPage = media_grid
-                        .window()
-                        .imp()
-                        .window_navigation
-                        .visible_page()
-                        .unwrap();
+            move |_: &gtk::GestureClick, n_press: i32, _, _| {
+                let activation: ItemActivation = ItemActivation::from_value(
+                    MemoriesApplication::default().gsettings().int("item-activation"),
+                );
 
-                    // Do not proceed to push a new nav page if one is already open.
-                    if current_nav_page.tag().unwrap() != "window" {
-                        return;
-                    }
+                if activation.opens_on_click(n_press) && list_item.is_selected() {
                     let media_cell: MemoriesMediaCell = list_item.child().and_downcast().unwrap();
+                    media_cell.open_viewer(&media_grid, &list_item);
+                }
+            }
+        ));
+
+        let open_externally_action = gio::ActionEntry::builder("open-externally")
+            .activate(clone!(
+                #[weak]
+                media_grid,
+                #[weak]
+                list_item,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    if let Some(item) = list_item.item().and_downcast::<MemoriesMediaItem>() {
+                        Self::open_externally(&item.file(), &media_grid.window());
+                    }
+                }
+            ))
+            .build();
+
+        let retry_thumbnail_action = gio::ActionEntry::builder("retry-thumbnail")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                media_grid,
+                #[weak]
+                list_item,
+                move |_: &gio::SimpleActionGroup, _, _| this.retry_thumbnail(&media_grid, &list_item)
+            ))
+            .build();
+
+        let action_group = gio::SimpleActionGroup::new();
+        action_group.add_action_entries([open_externally_action, retry_thumbnail_action]);
+        self.insert_action_group("cell", Some(&action_group));
+
+        let context_menu: gtk::PopoverMenu =
+            gtk::PopoverMenu::from_model(Some(&self.imp().context_menu_model.get()));
+        context_menu.set_has_arrow(false);
+        context_menu.set_halign(gtk::Align::Start);
+        context_menu.set_parent(self);
+
+        // Right clicking a cell whose media failed to load or is not supported opens
+        // its context menu, from which the thumbnail can be loaded again, or the
+        // media can be opened in another app.
+        let context_gesture: gtk::GestureClick =
+            gtk::GestureClick::builder().button(gdk::BUTTON_SECONDARY).build();
+
+        context_gesture.connect_pressed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            context_menu,
+            move |gesture: &gtk::GestureClick, _, x: f64, y: f64| {
+                if this.imp().load_error.borrow().is_none() && this.imp().unsupported_type.borrow().is_none()
+                {
+                    return;
+                }
+                gesture.set_state(gtk::EventSequenceState::Claimed);
+                context_menu.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+                context_menu.popup();
+            }
+        ));
+        self.add_controller(context_gesture);
+        let _ = self.imp().context_menu.set(context_menu);
+    }
+
+    /// Opens the media of this cell in a new media viewer page,
+    /// unless a page is already pushed on top of the window.
+    fn open_viewer(&self, media_grid: &MemoriesMediaGridView, list_item: &gtk::ListItem) {
+        let model_item: MemoriesMediaItem = list_item.item().and_downcast().unwrap();
+
+        if self.imp().unsupported_type.borrow().is_some() {
+            Self::open_externally(&model_item.file(), &media_grid.window());
+            return;
+        }
+        let content_type: &ViewerContentType = self.imp().viewer_content_type.get().unwrap();
+
+        if let Some(viewer_content) =
+            MemoriesMediaViewer::open(&media_grid.window(), &model_item.file(), content_type)
+        {
+            viewer_content
+                .imp()
+                .properties_widget
+                .update_details(&model_item, self);
+            viewer_content.set_siblings(media_grid.shown_items(), &media_grid.collection_title());
+        }
+    }
+
+    /// Opens media in the app that is the default for its file type,
+    /// such as media whose format is not supported by Memories.
+    pub fn open_externally(file: &gio::File, window: &impl IsA<gtk::Window>) {
+        gtk::FileLauncher::new(Some(file)).launch(
+            Some(window),
+            gio::Cancellable::NONE,
+            clone!(
+                #[strong]
+                file,
+                move |result: Result<(), glib::Error>| {
+                    if let Err(glib_error) = result {
+                        g_warning!(
+                            "MediaCell",
+                            "Failed to open '{}' in another app: {}",
+                            file.uri(),
+                            glib_error
+                        );
+                    }
+                }
+            ),
+        );
+    }
+
+    /// Shows the icon of the file type of media whose format is not supported in place
+    /// of its thumbnail, with a badge of its format, such as "MTS" for `.mts` videos.
+    fn show_unsupported_media(&self, file: &gio::File, content_type: &glib::GString) {
+        let imp = self.imp();
+        let format: String = file
+            .basename()
+            .and_then(|name: PathBuf| name.extension().map(|ext| ext.to_string_lossy().to_uppercase()))
+            .unwrap_or_else(|| gio::content_type_get_description(content_type).to_string());
+
+        imp.placeholder.remove_css_class("skeleton");
+        imp.thumbnail_image
+            .set_from_gicon(&gio::content_type_get_icon(content_type));
+        imp.mime_badge.set_label(&format);
+        imp.mime_badge
+            .set_tooltip_text(Some(&gio::content_type_get_description(content_type)));
+        imp.mime_badge.set_visible(true);
+        imp.unsupported_type.replace(Some(content_type.clone()));
+    }
+
+    fn clear_unsupported_media(&self) {
+        self.imp().unsupported_type.replace(None);
+        self.imp().mime_badge.set_visible(false);
+    }
+
+    /// Describes the media of a grid cell to screen readers by its
+    /// file name and type, and by the date it was last modified.
+    fn update_accessible_properties(list_item: &gtk::ListItem, model_item: &MemoriesMediaItem) {
+        let media_type: String = if model_item.is_video() {
+            gettext("Video")
+        } else {
+            gettext("Photo")
+        };
+        list_item.set_accessible_label(&gettext_f(
+            "{NAME}, {TYPE}",
+            &[("NAME", &model_item.display_name()), ("TYPE", &media_type)],
+        ));
+
+        let modified: Option<String> = model_item
+            .modified()
+            .and_then(|date_time: glib::DateTime| date_time.to_local().ok())
+            .and_then(|date_time: glib::DateTime| format::date_time(&date_time));
+
+        if let Some(modified) = modified {
+            list_item.set_accessible_description(&gettext_f("Modified {DATE}", &[("DATE", &modified)]));
+        }
+    }
+
+    /// Shows an icon in place of the thumbnail, and stops the placeholder shimmer.
+    fn show_placeholder_icon(&self, icon_name: &str) {
+        self.imp().placeholder.remove_css_class("skeleton");
+        self.imp().thumbnail_image.set_icon_name(Some(icon_name));
+    }
+
+    /// Shows a broken image badge on the cell, with the error that the media
+    /// failed to load with as its tooltip. Errors are only logged for debugging,
+    /// since they are shown on the cell, and some libraries have many broken files.
+    fn show_load_error(&self, error: &str) {
+        let basename: String = self
+            .imp()
+            .file
+            .get()
+            .and_then(gio::File::basename)
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        g_debug!("MediaCell", "{}: Failed to load media: {}", basename, error);
+
+        self.imp().placeholder.remove_css_class("skeleton");
+        self.imp().load_error.replace(Some(error.to_owned()));
+
+        let badge: &gtk::Image = &self.imp().load_error_badge;
+
+        badge.set_tooltip_text(Some(&gettext_f(
+            "Failed to load this media: {ERROR}",
+            &[("ERROR", error)],
+        )));
+        badge.set_visible(true);
+    }
+
+    fn clear_load_error(&self) {
+        self.imp().load_error.replace(None);
+        self.imp().load_error_badge.set_visible(false);
+    }
+
+    /// Loads the thumbnail and the metadata of the cell's media again after they
+    /// failed to load, such as after a missing codec was installed.
+    fn retry_thumbnail(&self, media_grid: &MemoriesMediaGridView, list_item: &gtk::ListItem) {
+        let join_handles = [self.imp().tx_join_handle.take(), self.imp().rx_join_handle.take()];
+
+        for join_handle in join_handles.into_iter().flatten() {
+            join_handle.abort();
+        }
+        self.bind_item(media_grid, list_item);
+    }
+
+    /// Shows the star rating of the cell's media file while the cell is
+    /// hovered or selected, if the media file has a rating.
+    fn update_rating_overlay(&self) {
+        let rating: u32 = self
+            .imp()
+            .file
+            .get()
+            .map(|file: &gio::File| MemoriesApplication::default().rating(file))
+            .unwrap_or(0);
+
+        let rating_label: &gtk::Label = &self.imp().rating_label;
+
+        rating_label.set_label(&"★".repeat(rating as usize));
+        rating_label.set_tooltip_text(Some(&ngettext_f(
+            "{COUNT} Star",
+            "{COUNT} Stars",
+            rating,
+            &[("COUNT", &rating.to_string())],
+        )));
+        rating_label.set_visible(rating > 0 && (self.imp().hovered.get() || self.imp().selected.get()));
+    }
+
+    /// Shows the amount of photos in the burst that the cell's media file is the cover of.
+    fn update_burst_badge(&self, media_grid: &MemoriesMediaGridView) {
+        let frame_count: usize = self
+            .imp()
+            .file
+            .get()
+            .and_then(|file: &gio::File| {
+                media_grid
+                    .imp()
+                    .bursts
+                    .borrow()
+                    .frames_of(file.uri().as_str())
+                    .map(<[String]>::len)
+            })
+            .unwrap_or(0);
+
+        let description: String = ngettext_f(
+            "Burst of {COUNT} Photo",
+            "Burst of {COUNT} Photos",
+            frame_count.try_into().unwrap_or(u32::MAX),
+            &[("COUNT", &frame_count.to_string())],
+        );
+        let burst_button: &gtk::Button = &self.imp().burst_button;
+
+        self.imp().burst_content.set_label(&frame_count.to_string());
+        burst_button.set_tooltip_text(Some(&description));
+        burst_button.update_property(&[gtk::accessible::Property::Label(&description)]);
+        burst_button.set_visible(frame_count > 1);
+    }
 
-                    let model_item: MemoriesMediaItem = list_item.item().and_downcast().unwrap();
-                    let gfile: gio::File = model_item.file();
+    /// Shows every photo of the burst that the cell's media file is the cover of.
+    fn open_burst_dialog(&self, media_grid: &MemoriesMediaGridView) {
+        let Some(cover) = self.imp().file.get() else {
+            return;
+        };
+        let frames: Vec<gio::File> = match media_grid.imp().bursts.borrow().frames_of(cover.uri().as_str()) {
+            Some(frames) => frames
+                .iter()
+                .map(|uri: &String| gio::File::for_uri(uri))
+                .collect(),
+            None => return,
+        };
+        MemoriesBurstDialog::new(&frames, cover).present(Some(media_grid));
+    }
+
+    /// Lazily regenerates the thumbnail if the media grid now requests
+    /// a larger thumbnail than the one currently shown. Smaller thumbnails
+    /// are never regenerated, since the larger one scales down fine.
+    fn update_thumbnail_size(&self, media_grid: &MemoriesMediaGridView) {
+        let new_size: u32 = media_grid.thumbnail_size();
+        let current_size: u32 = self.imp().thumbnail_size.get();
+
+        if current_size == 0 || new_size <= current_size {
+            return;
+        }
+        let Some((file_path, hash)) = self.imp().thumbnail_source.borrow().clone() else {
+            return;
+        };
+        // Set the new size right away, so we do not request it again while generating.
+        self.imp().thumbnail_size.set(new_size);
+
+        let semaphore: Arc<Semaphore> = media_grid.subprocess_semaphore_for(&gio::File::for_path(&file_path));
+        let hwaccel: Option<HardwareAccel> = media_grid.hardware_accel_backend();
+        let ffmpeg_available: bool = media_grid.ffmpeg_available();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let thumbnail: std::io::Result<String> = imp::MemoriesMediaCell::generate_thumbnail_image(
+                    &file_path, &hash, semaphore, hwaccel, ffmpeg_available, new_size,
+                )
+                .await;
+
+                // Make sure the cell was not rebound to another file in the meantime.
+                let is_current: bool = this
+                    .imp()
+                    .thumbnail_source
+                    .borrow()
+                    .as_ref()
+                    .is_some_and(|(_, current_hash)| *current_hash == hash);
 
-                    let nav_view = media_grid.window().imp().window_navigation.clone();
+                match thumbnail {
+                    Ok(path) if is_current => this.imp().thumbnail_image.set_from_file(Some(&path)),
+                    Ok(_) => (),
+                    Err(e) => g_warning!("MediaCell", "Failed to regenerate a larger thumbnail: {}", e),
+                }
+            }
+        ));
+    }
 
-                    let viewer_content: MemoriesMediaViewer = MemoriesMediaViewer::default();
-                    viewer_content.set_content_type(media_cell.imp().viewer_content_type.get().unwrap());
-                    viewer_content.set_content_file(&gfile);
+    /// Generates the thumbnail of the cell again after its media file was changed.
+    /// The metadata hash of the file changes with its modification time, so the
+    /// thumbnail of the old contents in the cache is not reused.
+    fn refresh_thumbnail(&self, media_grid: &MemoriesMediaGridView) {
+        let Some((file_path, old_hash)) = self.imp().thumbnail_source.borrow().clone() else {
+            return;
+        };
+        let size: u32 = self.imp().thumbnail_size.get();
+        let semaphore: Arc<Semaphore> = media_grid.subprocess_semaphore_for(&gio::File::for_path(&file_path));
+        let hwaccel: Option<HardwareAccel> = media_grid.hardware_accel_backend();
+        let ffmpeg_available: bool = media_grid.ffmpeg_available();
 
-                    viewer_content.imp().properties_widget.update_details(&media_cell);
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            media_grid,
+            async move {
+                let hash: String = match get_metadata_with_hash(&file_path).await {
+                    Ok((_, hash)) => hash,
+                    Err(e) => {
+                        g_warning!("MediaCell", "Failed to read metadata of a changed file: {}", e);
+                        return;
+                    }
+                };
+                let thumbnail: std::io::Result<String> = imp::MemoriesMediaCell::generate_thumbnail_image(
+                    &file_path, &hash, semaphore, hwaccel, ffmpeg_available, size,
+                )
+                .await;
 
-                    let nav_page: adw::NavigationPage = viewer_content.wrap_in_navigation_page();
-                    nav_page.set_title(&model_item.basename());
+                // Make sure the cell was not rebound to another file in the meantime.
+                let is_current: bool = this
+                    .imp()
+                    .thumbnail_source
+                    .borrow()
+                    .as_ref()
+                    .is_some_and(|(_, current_hash)| *current_hash == old_hash);
 
-                    nav_view.push(&nav_page);
+                match thumbnail {
+                    Ok(path) if is_current => {
+                        this.imp().thumbnail_image.set_from_file(Some(&path));
+                        this.imp()
+                            .thumbnail_source
+                            .replace(Some((file_path.clone(), hash)));
 
-                    // See docstring of setup_gactions() for why we're calling it here.
-                    viewer_content.setup_gactions();
+                        let file: gio::File = gio::File::for_path(&file_path);
+                        media_grid.imp().cache_preview(&file, PathBuf::from(path)).await;
+                    }
+                    Ok(_) => (),
+                    Err(e) => g_warning!("MediaCell", "Failed to regenerate the thumbnail: {}", e),
                 }
             }
         ));
@@ -299,6 +1072,66 @@ impl MemoriesMediaCell {
 
     /// Called every time the list item widget factory fires the 'bind'
     /// event on the list item widget, which loads it with new data.
+    /// Generates the thumbnail of a media file into the thumbnail cache
+    /// before a grid cell is bound to it, such as while the grid is scrolled
+    /// towards it. Cells find the thumbnail in the cache once they are bound.
+    pub async fn prefetch_thumbnail(media_grid: &MemoriesMediaGridView, file: &gio::File, size: u32) {
+        let Some(file_path) = file.path() else {
+            return;
+        };
+        let Ok((_, hash)) = get_metadata_with_hash(&file_path).await else {
+            return;
+        };
+        if let Err(err) = imp::MemoriesMediaCell::generate_thumbnail_image(
+            &file_path,
+            &hash,
+            media_grid.subprocess_semaphore_for(file),
+            media_grid.hardware_accel_backend(),
+            media_grid.ffmpeg_available(),
+            size,
+        )
+        .await
+        {
+            g_debug!("MediaCell", "Failed to prefetch a thumbnail: {}", err);
+        }
+    }
+
+    /// Binds the cell to the media item of the list item. The content type of the
+    /// media is told by the media item, which is fast, or else by sniffing the
+    /// contents of the file if neither its extension nor its content type are known.
+    pub fn bind_item(&self, media_grid: &MemoriesMediaGridView, list_item: &gtk::ListItem) {
+        let Some(model_item) = list_item.item().and_downcast::<MemoriesMediaItem>() else {
+            return;
+        };
+        if let Some(content_type) = model_item.viewer_content_type() {
+            self.bind_cell(media_grid.imp(), content_type, list_item);
+            return;
+        }
+        let file: gio::File = model_item.file();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            media_grid,
+            #[weak]
+            list_item,
+            async move {
+                let content_type: ViewerContentType = ViewerContentType::sniff(&file).await;
+
+                // Make sure the list item was not rebound to another file in the meantime.
+                let is_current: bool = list_item
+                    .item()
+                    .and_downcast::<MemoriesMediaItem>()
+                    .is_some_and(|item: MemoriesMediaItem| item.file() == file);
+
+                if is_current {
+                    this.bind_cell(media_grid.imp(), content_type, &list_item);
+                }
+            }
+        ));
+    }
+
     pub fn bind_cell(
         &self,
         media_grid_imp: &super::media_grid::imp::MemoriesMediaGridView,
@@ -317,12 +1150,49 @@ impl MemoriesMediaCell {
 
         self.imp()
             .thumbnail_image
-            .set_tooltip_text(Some(&file.basename().unwrap().to_string_lossy()));
+            .set_tooltip_text(Some(&model_item.display_name()));
 
         // Store content type variant and `GFileInfo` object reference in our object.
         let _ = self.imp().viewer_content_type.set(content_type.clone());
         let _ = self.imp().file.set(file.clone());
 
+        self.clear_load_error();
+        self.clear_unsupported_media();
+        self.update_burst_badge(&media_grid_imp.obj());
+        Self::update_accessible_properties(list_item, &model_item);
+
+        self.update_rating_overlay();
+
+        // Show a low fidelity preview of the thumbnail right away if one is in the
+        // preview index, such as from an earlier run, and shimmer until the thumbnail
+        // is loaded. The preview is decoded to a tiny texture that is stretched out.
+        let preview_texture: Option<gdk::Texture> = media_grid_imp
+            .previews
+            .borrow()
+            .get(file.uri().as_str())
+            .and_then(preview::blurhash_texture);
+        let has_preview: bool = preview_texture.is_some();
+
+        // Show the thumbnail that was generated when the item was last bound right
+        // away, such as when scrolling back, while it is looked up in the cache again.
+        match model_item.thumbnail_path() {
+            Some(path) => self.imp().thumbnail_image.set_from_file(Some(&path)),
+            None => self.imp().thumbnail_image.clear(),
+        }
+        self.imp().placeholder.set_paintable(preview_texture.as_ref());
+        self.imp().placeholder.add_css_class("skeleton");
+
+        // Media that is recognized, but not supported, is not loaded. It is
+        // shown with the icon of its file type, and opened in another app.
+        let unsupported_type: Option<glib::GString> = match content_type {
+            ViewerContentType::Invalid => model_item.unsupported_content_type(),
+            _ => None,
+        };
+        if let Some(unsupported_type) = unsupported_type {
+            self.show_unsupported_media(&file, &unsupported_type);
+            return;
+        }
+
         // Match statement for choosing how to load the thumbnail image.
         match content_type {
             // SVGs can be rendered by GNOME's librsvg, so we don't need ffmpeg.
@@ -330,45 +1200,93 @@ impl MemoriesMediaCell {
                 self.imp().thumbnail_image.set_from_file(Some(&absolute_path))
             }
             _ => {
+                let ffmpeg_available: bool = media_grid_imp.obj().ffmpeg_available();
+
+                // Video thumbnails require ffmpeg or libav, so show a placeholder icon instead.
+                let show_thumbnail: bool = ffmpeg_available
+                    || cfg!(feature = "use-libav")
+                    || !matches!(content_type, ViewerContentType::Video);
+
+                if !show_thumbnail {
+                    self.show_placeholder_icon("video-x-generic-symbolic");
+                }
+
                 let (tx, rx) = async_channel::bounded(1);
-                let semaphore: Arc<Semaphore> = media_grid_imp.subprocess_semaphore.clone();
+                let semaphore: Arc<Semaphore> = media_grid_imp.obj().subprocess_semaphore_for(&file);
+                let thumbnail_size: u32 = media_grid_imp.obj().thumbnail_size();
+
+                self.imp().thumbnail_size.set(0);
+                self.imp().thumbnail_source.replace(None);
 
                 let tx_handle = glib::spawn_future_local(clone!(
                     #[weak(rename_to = this)]
                     self,
                     #[weak]
                     media_grid_imp,
+                    #[strong]
+                    file,
+                    #[strong]
+                    model_item,
                     async move {
                         // We need to get 3 things done in this closure:
                         // - file metadata
-                        // - metadata md5 digest
+                        // - thumbnail cache key
                         // - thumbnail image
-                        // So, first, we need to open the image/video file asynchronously.
+                        // The cache key is derived from the metadata, so the file is not read.
                         let in_path: &Path = Path::new(&absolute_path);
-                        let in_file: File = File::open(in_path).await.unwrap();
-
-                        let (metadata, hash) = get_metadata_with_hash(in_file).await.unwrap();
+                        let (metadata, hash) = match get_metadata_with_hash(in_path).await {
+                            Ok(metadata_with_hash) => metadata_with_hash,
+                            Err(e) => {
+                                this.show_load_error(&e.to_string());
+                                return;
+                            }
+                        };
 
                         // Store the `MetadataInfo` struct in our `MemoriesMediaCell` object.
                         let _ = this.imp().file_metadata.set(metadata);
 
-                        if let Ok(path) = imp::MemoriesMediaCell::generate_thumbnail_image(
-                            in_path,
-                            &hash,
-                            semaphore,
-                            media_grid_imp.obj().hardware_accel(),
-                        )
-                        .await
-                        {
-                            if let Err(err_string) = tx.send(path).await {
-                                g_critical!(
-                                    "MediaCell",
-                                    "Tried to transmit thumbnail path, async channel is not open.\n{}",
-                                    err_string
-                                );
+                        if !show_thumbnail {
+                            return;
+                        }
+
+                        let thumbnail: std::io::Result<String> =
+                            imp::MemoriesMediaCell::generate_thumbnail_image(
+                                in_path,
+                                &hash,
+                                semaphore,
+                                media_grid_imp.obj().hardware_accel_backend(),
+                                ffmpeg_available,
+                                thumbnail_size,
+                            )
+                            .await;
+
+                        match thumbnail {
+                            Ok(path) => {
+                                this.imp().thumbnail_size.set(thumbnail_size);
+                                this.imp()
+                                    .thumbnail_source
+                                    .replace(Some((in_path.to_path_buf(), hash)));
+                                model_item.set_thumbnail_path(Some(path.as_str()));
+
+                                // The receiver is dropped if the cell was rebound in the meantime.
+                                if let Err(err_string) = tx.send(path.clone()).await {
+                                    g_debug!(
+                                        "MediaCell",
+                                        "Tried to transmit thumbnail path, async channel is not open.\n{}",
+                                        err_string
+                                    );
+                                }
+                                if !has_preview {
+                                    media_grid_imp.cache_preview(&file, PathBuf::from(path)).await;
+                                }
+                                // Probe the duration of videos once, which is shown in the duration badge.
+                                if ffmpeg_available && model_item.is_video() && model_item.duration() == 0 {
+                                    if let Some(duration) = probe_duration(in_path).await {
+                                        model_item.set_duration(duration.ceil() as u32);
+                                    }
+                                }
                             }
-                        } else {
-                            g_warning!("MediaCell", "FFmpeg failed to generate a thumbnail image.");
+                            Err(e) => this.show_load_error(&e.to_string()),
                         }
                     }
                 ));
@@ -389,6 +1307,21 @@ impl MemoriesMediaCell {
             }
         }
 
+        // Reopen this media in the viewer if it was open when the application was last closed.
+        if media_grid_imp.obj().take_restored_viewer_file(&file) {
+            let media_grid: MemoriesMediaGridView = media_grid_imp.obj().clone();
+
+            glib::idle_add_local_once(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                media_grid,
+                #[weak]
+                list_item,
+                move || this.open_viewer(&media_grid, &list_item)
+            ));
+        }
+
         // Match statement for choosing how to get the media metadata.
         match content_type {
             // TODO: Currently video format metadata is not yet implemented.
@@ -414,21 +1347,17 @@ impl MemoriesMediaCell {
 
                                 this.imp().content_details.swap(&RefCell::new(details));
                             }
-                            Err(glycin_err) => g_warning!(
-                                "MediaCell",
-                                "{}: Glycin error: {}",
-                                file.basename().unwrap().to_string_lossy(),
-                                glycin_err
-                            ),
+                            Err(glycin_err) => this.show_load_error(&glycin_err.to_string()),
                         }
                     }
                 ));
             }
             ViewerContentType::Invalid => {
-                g_error!(
+                g_warning!(
                     "MediaCell",
                     "Received `ViewerContentType::Invalid`. Should not happen!"
                 );
+                self.show_placeholder_icon("image-missing-symbolic");
             }
         }
     }
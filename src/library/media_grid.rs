@@ -18,41 +18,138 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::globals::{GRID_DESKTOP_ZOOM_LEVELS, GRID_MOBILE_ZOOM_LEVELS};
+use crate::application::MemoriesApplication;
+use crate::globals::{
+    DEFAULT_GRID_ZOOM_LEVEL, FFMPEG_BINARY, FFMPEG_CONCURRENT_PROCESSES, FFMPEG_MAX_CONCURRENT_PROCESSES,
+    FFMPEG_POWER_SAVER_PROCESSES, GRID_CELL_PADDING, GRID_PINCH_ZOOM_STEP, GRID_PREFETCH_ROWS,
+    GRID_ZOOM_LEVELS, PULL_TO_REFRESH_DISTANCE, THUMBNAIL_SIZES,
+};
+use crate::i18n::ngettext_f;
+use crate::library::bursts::Bursts;
+use crate::library::date_time_dialog::MemoriesDateTimeDialog;
+use crate::library::list_model::MemoriesLibraryListModel;
+use crate::library::media_cell::MemoriesMediaCell;
+use crate::library::media_item::MemoriesMediaItem;
+use crate::library::media_viewer::{MemoriesMediaViewer, ViewerContentType};
+use crate::library::rename_dialog::MemoriesRenameDialog;
+use crate::library::roots;
+use crate::library::scan_service::ThumbnailSettings;
+use crate::library::share_dialog::MemoriesShareDialog;
+use crate::library::slideshow_dialog::{self, MemoriesSlideshowDialog};
+use crate::library::tags_dialog::MemoriesTagsDialog;
+use crate::util::enums::ItemActivation;
+use crate::util::feedback::{self, FeedbackEvent};
+use crate::util::format::{self, SizeUnits};
+use crate::util::hwaccel::HardwareAccel;
+use crate::util::power;
+use crate::util::storage::{self, StorageKind};
 use crate::window::MemoriesApplicationWindow;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
-use glib::clone;
+use async_semaphore::Semaphore;
+use gettextrs::gettext;
+use glib::{clone, g_debug};
 use gtk::{gio, glib};
+use std::cell::Cell;
+use std::ops::Range;
+use std::process::Command;
+use std::sync::Arc;
+
+/// Limits the thumbnail processes spawned at once for the media
+/// in a library folder, by the kind of storage the folder is on.
+#[derive(Debug)]
+struct RootSemaphore {
+    folder: gio::File,
+    storage: StorageKind,
+    /// Semaphore of fewer permits than the shared subprocess semaphore,
+    /// or `None` if the media in the folder use the shared semaphore.
+    semaphore: Option<(usize, Arc<Semaphore>)>,
+}
 
 pub mod imp {
+    use super::RootSemaphore;
     use crate::application::MemoriesApplication;
-    use crate::globals::{DEFAULT_GRID_WIDGET_HEIGHT, FFMPEG_CONCURRENT_PROCESSES};
+    use crate::globals::PREVIEW_INDEX_SAVE_DELAY;
+    use crate::globals::{DEFAULT_GRID_WIDGET_HEIGHT, DEFAULT_GRID_ZOOM_LEVEL, FFMPEG_CONCURRENT_PROCESSES};
+    use crate::library::bursts::Bursts;
+    use crate::library::justified_view::MemoriesJustifiedView;
     use crate::library::media_cell::MemoriesMediaCell;
     use crate::library::media_item::MemoriesMediaItem;
-    use crate::library::media_viewer::ViewerContentType;
+    use crate::util::crash;
+    use crate::util::preview::{self, PreviewIndex};
     use crate::window::MemoriesApplicationWindow;
     use adw::prelude::*;
     use adw::subclass::prelude::*;
     use async_semaphore::Semaphore;
-    use glib::{clone, g_warning};
-    use gtk::{gio, glib};
-    use std::cell::Cell;
-    use std::sync::Arc;
+    use glib::subclass::Signal;
+    use glib::{clone, g_debug, g_warning};
+    use gtk::{gdk, gio, glib};
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+    use std::sync::{Arc, OnceLock};
 
     #[derive(Debug, glib::Properties, gtk::CompositeTemplate)]
     #[template(resource = "/com/maxrdz/Memories/ui/media-grid.ui")]
     #[properties(wrapper_type = super::MemoriesMediaGridView)]
     pub struct MemoriesMediaGridView {
-        pub subprocess_semaphore: Arc<Semaphore>,
+        /// Limits the amount of thumbnail processes spawned at once. Replaced
+        /// with one of fewer permits while power saver mode is active.
+        pub(super) subprocess_semaphore: RefCell<Arc<Semaphore>>,
+        /// Amount of permits of the current subprocess semaphore.
+        pub(super) subprocess_permits: Cell<usize>,
+        pub(super) root_semaphores: RefCell<Vec<RootSemaphore>>,
+        /// Kept to be notified when power saver mode is toggled.
+        pub(super) power_profile_monitor: gio::PowerProfileMonitor,
         pub list_item_factory: gtk::SignalListItemFactory,
 
         #[property(get, set)]
         hardware_accel: Cell<bool>,
         #[property(get, set)]
+        hardware_accel_key: RefCell<String>,
+        /// Set to `false` by the library view if the ffmpeg binary
+        /// is unavailable, in which case video thumbnails are disabled.
+        #[property(get, set)]
+        ffmpeg_available: Cell<bool>,
+        #[property(get, set)]
         grid_widget_height: Cell<i32>,
+        /// Amount of media that is shown when not searching, which is shown
+        /// in the footer along with the amount of media that matches a search.
+        #[property(get, set)]
+        total_items: Cell<u32>,
+        #[property(get, set)]
+        searching: Cell<bool>,
+        /// Name of the collection of media shown in the grid, which
+        /// titles the viewer when media is opened from the grid.
         #[property(get, set)]
-        grid_desktop_zoom: Cell<bool>,
+        collection_title: RefCell<String>,
+        /// Index of the current zoom level in `GRID_ZOOM_LEVELS`.
+        pub(super) zoom_level: Cell<usize>,
+        /// Width that the grid columns were last computed for.
+        pub(super) layout_width: Cell<i32>,
+        /// Media file to reopen in the viewer once its grid cell is bound,
+        /// if it was open in the viewer when the application was last closed.
+        pub(super) restored_viewer_file: RefCell<Option<gio::File>>,
+        /// Scale of the current pinch gesture at its last grid zoom step.
+        pub(super) pinch_scale: Cell<f64>,
+        /// Whether the current touch drag started with the view scrolled to the top.
+        pub(super) pull_from_top: Cell<bool>,
+        /// Scroll offset of the grid when its thumbnails were last prefetched.
+        pub(super) last_scroll_value: Cell<f64>,
+        /// URIs and sizes of the thumbnails that were prefetched already.
+        pub(super) prefetched: RefCell<HashSet<(String, u32)>>,
+        /// Keys of the selected items, so that items which are removed and added
+        /// again when the library is enumerated again are selected again.
+        selected_keys: RefCell<HashSet<String>>,
+        /// Set while items are selected again, which is not feedback for the user.
+        restoring_selection: Cell<bool>,
+        /// Low fidelity previews of thumbnails, shown as
+        /// placeholders in grid cells while their thumbnail loads.
+        pub previews: RefCell<PreviewIndex>,
+        preview_save_pending: Cell<bool>,
+        /// Bursts detected in the library by the library view, which
+        /// are collapsed into their cover frame in the grid.
+        pub bursts: RefCell<Bursts>,
 
         #[template_child]
         pub toast_overlay: TemplateChild<adw::ToastOverlay>,
@@ -63,25 +160,60 @@ pub mod imp {
         #[template_child]
         pub photo_grid_controls: TemplateChild<gtk::MenuButton>,
         #[template_child]
+        layout_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
         pub photo_grid_view: TemplateChild<gtk::GridView>,
+        #[template_child]
+        pub(super) justified_view: TemplateChild<MemoriesJustifiedView>,
+        #[template_child]
+        scan_progress_bar: TemplateChild<gtk::ProgressBar>,
+        #[template_child]
+        footer_label: TemplateChild<gtk::Label>,
     }
 
     impl Default for MemoriesMediaGridView {
         fn default() -> Self {
             Self {
-                subprocess_semaphore: Arc::new(Semaphore::new(FFMPEG_CONCURRENT_PROCESSES)),
+                subprocess_semaphore: RefCell::new(Arc::new(Semaphore::new(FFMPEG_CONCURRENT_PROCESSES))),
+                subprocess_permits: Cell::new(FFMPEG_CONCURRENT_PROCESSES),
+                root_semaphores: RefCell::default(),
+                power_profile_monitor: gio::PowerProfileMonitor::get_default(),
                 list_item_factory: gtk::SignalListItemFactory::default(),
                 hardware_accel: Cell::new({
                     let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
                     gsettings.boolean("ffmpeg-hardware-acceleration")
                 }),
+                ffmpeg_available: Cell::new(true),
+                hardware_accel_key: RefCell::new({
+                    let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+                    gsettings
+                        .string("ffmpeg-hardware-acceleration-backend")
+                        .to_string()
+                }),
                 grid_widget_height: Cell::new(DEFAULT_GRID_WIDGET_HEIGHT),
-                grid_desktop_zoom: Cell::new(false),
+                total_items: Cell::new(0),
+                searching: Cell::new(false),
+                zoom_level: Cell::new(DEFAULT_GRID_ZOOM_LEVEL),
+                layout_width: Cell::new(0),
+                restored_viewer_file: RefCell::default(),
+                pinch_scale: Cell::new(1.0),
+                pull_from_top: Cell::new(false),
+                last_scroll_value: Cell::new(0.0),
+                prefetched: RefCell::default(),
+                selected_keys: RefCell::default(),
+                restoring_selection: Cell::new(false),
+                previews: RefCell::new(PreviewIndex::load()),
+                preview_save_pending: Cell::new(false),
+                bursts: RefCell::default(),
                 toast_overlay: TemplateChild::default(),
                 overlay_revealer: TemplateChild::default(),
                 overlay_header_buttons: TemplateChild::default(),
                 photo_grid_controls: TemplateChild::default(),
+                layout_stack: TemplateChild::default(),
                 photo_grid_view: TemplateChild::default(),
+                justified_view: TemplateChild::default(),
+                scan_progress_bar: TemplateChild::default(),
+                footer_label: TemplateChild::default(),
             }
         }
     }
@@ -103,19 +235,19 @@ pub mod imp {
 
     #[glib::derived_properties]
     impl ObjectImpl for MemoriesMediaGridView {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    // Emitted after the bursts detected in the library are replaced.
+                    Signal::builder("bursts-changed").build(),
+                ]
+            })
+        }
+
         fn constructed(&self) {
             let obj = self.obj();
 
-            obj.connect_grid_desktop_zoom_notify(move |media_grid: &super::MemoriesMediaGridView| {
-                let win: MemoriesApplicationWindow = media_grid.window();
-
-                // `grid_desktop_zoom` is modified only when the `AdwBreakpoint` is triggered.
-                // The default zoom settings for the grid view are always at the minimum zoom
-                // by default in the UI files, so we reset the grid controls to min zoom below.
-                win.action_set_enabled("mediagrid.zoom_in", true);
-                win.action_set_enabled("mediagrid.zoom_out", false);
-            });
-
             // Bind any application preferences to our application's GSettings.
             let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
 
@@ -127,6 +259,62 @@ pub mod imp {
                 )
                 .build();
 
+            gsettings
+                .bind(
+                    "ffmpeg-hardware-acceleration-backend",
+                    &self.obj().clone(),
+                    "hardware-accel-key",
+                )
+                .build();
+
+            // Save previews that were not written to disk yet if Memories crashes.
+            crash::save_state_on_panic(clone!(
+                #[weak]
+                obj,
+                move || {
+                    let imp = obj.imp();
+
+                    if imp.preview_save_pending.get() {
+                        if let Ok(previews) = imp.previews.try_borrow() {
+                            let _ = previews.save();
+                        }
+                    }
+                }
+            ));
+
+            // Spawn fewer thumbnail processes at once in power saver mode.
+            self.power_profile_monitor
+                .connect_power_saver_enabled_notify(clone!(
+                    #[weak]
+                    obj,
+                    move |_: &gio::PowerProfileMonitor| obj.update_subprocess_semaphore()
+                ));
+            for key in [
+                "power-saver-throttling",
+                "thumbnail-processes",
+                "storage-adaptive-concurrency",
+            ] {
+                gsettings.connect_changed(
+                    Some(key),
+                    clone!(
+                        #[weak]
+                        obj,
+                        move |_: &gio::Settings, _: &str| obj.update_subprocess_semaphore()
+                    ),
+                );
+            }
+            obj.update_subprocess_semaphore();
+
+            gsettings.connect_changed(
+                Some("library-folders"),
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_: &gio::Settings, _: &str| obj.detect_root_storage()
+                ),
+            );
+            obj.detect_root_storage();
+
             self.list_item_factory.connect_setup(clone!(
                 #[weak]
                 obj,
@@ -143,36 +331,205 @@ pub mod imp {
                 self,
                 move |_: &gtk::SignalListItemFactory, obj: &glib::Object| {
                     let list_item: gtk::ListItem = obj.clone().downcast().unwrap();
-                    let cell: MemoriesMediaCell = list_item.child().and_downcast().unwrap();
-
-                    let model_item: MemoriesMediaItem = list_item.item().and_downcast().unwrap();
 
-                    let file: gio::File = model_item.file();
-                    let file_path_buf: std::path::PathBuf = file.path().unwrap();
-
-                    // Convert file_path_buf to a String (not a string slice) since file_path_buf
-                    // does not live long enough to be borrowed in the futures spawned below.
-                    let absolute_path: String = file_path_buf.to_string_lossy().to_string();
-
-                    if let Some(ext) = file.basename().unwrap().extension() {
-                        cell.bind_cell(&this, ViewerContentType::from_ext(ext), &list_item);
-                    } else {
-                        g_warning!(
+                    let (Some(cell), Some(model_item)) = (
+                        list_item.child().and_downcast::<MemoriesMediaCell>(),
+                        list_item.item().and_downcast::<MemoriesMediaItem>(),
+                    ) else {
+                        crash::report_error(
                             "MediaGridView",
-                            "Found a file with no file extension, with file path '{}'.",
-                            absolute_path
+                            "Bound a grid cell without a media cell or item.",
                         );
+                        return;
+                    };
+                    let file: gio::File = model_item.file();
+
+                    if file.path().is_none() {
+                        g_warning!("MediaGridView", "'{}' has no local path.", file.uri());
+                        return;
                     }
+                    cell.bind_item(&this.obj(), &list_item);
                 }
             ));
 
             self.photo_grid_view.set_factory(Some(&self.list_item_factory));
+
+            // Switch between the grid and justified layouts with the GSettings key.
+            gsettings.connect_changed(
+                Some("justified-layout"),
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_: &gio::Settings, _: &str| obj.update_layout_mode()
+                ),
+            );
+            self.photo_grid_view.connect_model_notify(clone!(
+                #[weak]
+                obj,
+                move |_: &gtk::GridView| obj.update_layout_mode()
+            ));
+            obj.update_layout_mode();
+
+            // Hovering selects the cells if media open with a single click.
+            gsettings.connect_changed(
+                Some("item-activation"),
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_: &gio::Settings, _: &str| obj.update_item_activation()
+                ),
+            );
+            obj.update_item_activation();
+
+            // The Enter key opens the selected media, however media open with clicks.
+            let key_controller: gtk::EventControllerKey = gtk::EventControllerKey::builder()
+                .propagation_phase(gtk::PropagationPhase::Capture)
+                .build();
+
+            key_controller.connect_key_pressed(clone!(
+                #[weak]
+                obj,
+                #[upgrade_or]
+                glib::Propagation::Proceed,
+                move |_: &gtk::EventControllerKey, key: gdk::Key, _: u32, modifiers: gdk::ModifierType| {
+                    let is_enter: bool =
+                        matches!(key, gdk::Key::Return | gdk::Key::KP_Enter | gdk::Key::ISO_Enter);
+
+                    if is_enter && modifiers.is_empty() && obj.open_selected() {
+                        return glib::Propagation::Stop;
+                    }
+                    glib::Propagation::Proceed
+                }
+            ));
+            self.photo_grid_view.add_controller(key_controller);
+
+            let zoom_gesture: gtk::GestureZoom = gtk::GestureZoom::new();
+
+            zoom_gesture.connect_begin(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gtk::GestureZoom, _: Option<&gdk::EventSequence>| {
+                    this.pinch_scale.set(1.0);
+                }
+            ));
+            zoom_gesture.connect_scale_changed(clone!(
+                #[weak]
+                obj,
+                move |_: &gtk::GestureZoom, scale: f64| {
+                    obj.pinch_zoom(scale);
+                }
+            ));
+            self.photo_grid_view.add_controller(zoom_gesture);
+
+            // Pulling the library down from the top refreshes it on touchscreens.
+            // The gesture only observes the drag, so it does not stop scrolling.
+            let pull_gesture: gtk::GestureDrag = gtk::GestureDrag::builder()
+                .touch_only(true)
+                .propagation_phase(gtk::PropagationPhase::Capture)
+                .build();
+
+            pull_gesture.connect_drag_begin(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gtk::GestureDrag, _: f64, _: f64| {
+                    let at_top: bool = this
+                        .layout_stack
+                        .visible_child()
+                        .and_downcast::<gtk::ScrolledWindow>()
+                        .is_some_and(|scrolled_window: gtk::ScrolledWindow| {
+                            scrolled_window.vadjustment().value() <= 0.0
+                        });
+                    this.pull_from_top.set(at_top);
+                }
+            ));
+            pull_gesture.connect_drag_end(clone!(
+                #[weak]
+                obj,
+                move |_: &gtk::GestureDrag, _: f64, offset_y: f64| {
+                    obj.pull_to_refresh(offset_y);
+                }
+            ));
+            self.layout_stack.add_controller(pull_gesture);
+
+            // Generate thumbnails ahead of the direction that the grid is scrolled in,
+            // and enumerate the folders that are enumerated on demand near the view.
+            if let Some(vadjustment) = self.photo_grid_view.vadjustment() {
+                vadjustment.connect_value_changed(clone!(
+                    #[weak]
+                    obj,
+                    move |vadjustment: &gtk::Adjustment| {
+                        obj.prefetch_thumbnails(vadjustment);
+                        obj.enumerate_folders_near_view(vadjustment);
+                    }
+                ));
+                vadjustment.connect_changed(clone!(
+                    #[weak]
+                    obj,
+                    move |vadjustment: &gtk::Adjustment| obj.enumerate_folders_near_view(vadjustment)
+                ));
+            }
         }
     }
 
-    impl WidgetImpl for MemoriesMediaGridView {}
+    impl WidgetImpl for MemoriesMediaGridView {
+        fn size_allocate(&self, width: i32, height: i32, baseline: i32) {
+            self.parent_size_allocate(width, height, baseline);
+
+            if width == self.layout_width.get() {
+                return;
+            }
+            self.layout_width.set(width);
+
+            // The grid columns can not be changed while allocating,
+            // so the layout is updated once the allocation is done.
+            glib::idle_add_local_once(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move || this.obj().update_grid_layout()
+            ));
+        }
+    }
     impl BinImpl for MemoriesMediaGridView {}
     impl BreakpointBinImpl for MemoriesMediaGridView {}
+
+    impl MemoriesMediaGridView {
+        /// Encodes a preview of a media file's thumbnail on a separate thread, and
+        /// adds it to the preview index to show as the placeholder of the media's cell.
+        pub async fn cache_preview(&self, file: &gio::File, thumbnail_path: PathBuf) {
+            match gio::spawn_blocking(move || preview::thumbnail_blurhash(&thumbnail_path)).await {
+                Ok(Ok(blurhash)) => {
+                    self.previews
+                        .borrow_mut()
+                        .insert(file.uri().to_string(), blurhash);
+                    self.schedule_preview_save();
+                }
+                Ok(Err(err)) => g_debug!("MediaGridView", "Failed to encode thumbnail preview: {}", err),
+                Err(_) => g_warning!("MediaGridView", "Thumbnail preview thread panicked."),
+            }
+        }
+
+        /// Saves the preview index once no previews were added for a while,
+        /// instead of writing it to disk for every thumbnail generated.
+        fn schedule_preview_save(&self) {
+            if self.preview_save_pending.replace(true) {
+                return;
+            }
+            glib::timeout_add_seconds_local_once(
+                PREVIEW_INDEX_SAVE_DELAY,
+                clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    move || {
+                        this.preview_save_pending.set(false);
+
+                        if let Err(err) = this.previews.borrow().save() {
+                            g_warning!("MediaGridView", "Failed to save preview index: {}", err);
+                        }
+                    }
+                ),
+            );
+        }
+    }
 }
 
 glib::wrapper! {
@@ -185,6 +542,195 @@ impl MemoriesMediaGridView {
         glib::Object::new()
     }
 
+    /// Returns the edge length in pixels of thumbnails to request for grid
+    /// cells, based on the current cell height and the window's scale factor.
+    pub fn thumbnail_size(&self) -> u32 {
+        thumbnail_size_for(self.grid_widget_height(), self.scale_factor())
+    }
+
+    /// Returns the settings that the grid generates thumbnails with.
+    pub fn thumbnail_settings(&self) -> ThumbnailSettings {
+        ThumbnailSettings {
+            size: self.thumbnail_size(),
+            hardware_accel: self.hardware_accel_backend(),
+            ffmpeg_available: self.ffmpeg_available(),
+        }
+    }
+
+    /// Returns the model position of the first item in the
+    /// topmost visible row of the grid, used to persist the scroll position.
+    pub fn first_visible_position(&self) -> u32 {
+        let grid_view: &gtk::GridView = &self.imp().photo_grid_view;
+
+        let Some(model) = grid_view.model() else {
+            return 0;
+        };
+        let Some(vadjustment) = grid_view.vadjustment() else {
+            return 0;
+        };
+        first_visible_position_for(
+            vadjustment.value(),
+            vadjustment.upper(),
+            model.n_items(),
+            grid_view.max_columns(),
+        )
+    }
+
+    /// Restores the scroll position of the grid and the media file open in the
+    /// viewer from when the application was last closed, which are saved to
+    /// GSettings by the application window. Called once the library is loaded.
+    pub fn restore_session(&self) {
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+        let grid_view: &gtk::GridView = &self.imp().photo_grid_view;
+
+        let Some(model) = grid_view.model() else {
+            return;
+        };
+        let n_items: u32 = model.n_items();
+
+        if n_items == 0 {
+            return;
+        }
+        let last_viewed_uri: glib::GString = gsettings.string("last-viewed-file");
+
+        if !last_viewed_uri.is_empty() {
+            let last_viewed: gio::File = gio::File::for_uri(&last_viewed_uri);
+
+            let position: Option<u32> = (0..n_items).find(|i: &u32| {
+                model
+                    .item(*i)
+                    .and_downcast::<MemoriesMediaItem>()
+                    .is_some_and(|item: MemoriesMediaItem| item.file().equal(&last_viewed))
+            });
+
+            if let Some(position) = position {
+                // The viewer is opened by the grid cell once it is bound.
+                self.imp().restored_viewer_file.replace(Some(last_viewed));
+                grid_view.scroll_to(position, gtk::ListScrollFlags::SELECT, None);
+                return;
+            }
+            g_debug!("MediaGridView", "Last viewed file is no longer in the library.");
+        }
+        let position: u32 = gsettings.uint("last-grid-position").min(n_items - 1);
+        grid_view.scroll_to(position, gtk::ListScrollFlags::NONE, None);
+    }
+
+    /// Returns `true` if the given file should be reopened in the viewer
+    /// as part of restoring the last session, which is only done once.
+    pub fn take_restored_viewer_file(&self, file: &gio::File) -> bool {
+        let mut restored_file = self.imp().restored_viewer_file.borrow_mut();
+
+        if restored_file
+            .as_ref()
+            .is_some_and(|restored: &gio::File| restored.equal(file))
+        {
+            *restored_file = None;
+            return true;
+        }
+        false
+    }
+
+    /// Shows a thin progress bar over the grid while the library is rescanned
+    /// in the background, or hides it if `progress` is `None`.
+    pub fn set_scan_progress(&self, progress: Option<f64>) {
+        let progress_bar: &gtk::ProgressBar = &self.imp().scan_progress_bar;
+
+        progress_bar.set_visible(progress.is_some());
+        progress_bar.set_fraction(progress.unwrap_or(0.0));
+    }
+
+    /// Returns the semaphore that limits the amount of thumbnail processes spawned at once.
+    pub fn subprocess_semaphore(&self) -> Arc<Semaphore> {
+        self.imp().subprocess_semaphore.borrow().clone()
+    }
+
+    /// Returns the semaphore that limits the amount of thumbnail processes spawned at
+    /// once for the given media file, which has fewer permits for media on storage
+    /// that is slow to seek, like hard drives and memory cards.
+    pub fn subprocess_semaphore_for(&self, file: &gio::File) -> Arc<Semaphore> {
+        self.imp()
+            .root_semaphores
+            .borrow()
+            .iter()
+            .find(|root: &&RootSemaphore| file.has_prefix(&root.folder))
+            .and_then(|root: &RootSemaphore| root.semaphore.as_ref())
+            .map(|(_, semaphore): &(usize, Arc<Semaphore>)| semaphore.clone())
+            .unwrap_or_else(|| self.subprocess_semaphore())
+    }
+
+    /// Detects the kind of storage that each library folder is on, then
+    /// limits the thumbnail processes spawned at once for its media.
+    fn detect_root_storage(&self) {
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let mut root_semaphores: Vec<RootSemaphore> = vec![];
+
+                for root in roots::configured_roots() {
+                    let folder: gio::File = root.file();
+                    let storage: StorageKind = storage::detect(&folder).await;
+
+                    g_debug!(
+                        "MediaGridView",
+                        "Library folder '{}' is on {:?} storage.",
+                        folder.uri(),
+                        storage
+                    );
+                    root_semaphores.push(RootSemaphore {
+                        folder,
+                        storage,
+                        semaphore: None,
+                    });
+                }
+                this.imp().root_semaphores.replace(root_semaphores);
+                this.update_root_semaphores();
+            }
+        ));
+    }
+
+    /// Gives the library folders on slower storage a semaphore of fewer permits
+    /// than the shared subprocess semaphore, unless disabled in the preferences.
+    fn update_root_semaphores(&self) {
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+        let adaptive: bool = gsettings.boolean("storage-adaptive-concurrency");
+        let permits: usize = self.imp().subprocess_permits.get();
+
+        for root in self.imp().root_semaphores.borrow_mut().iter_mut() {
+            let limit: Option<usize> = Some(root.storage.process_limit(permits))
+                .filter(|limit: &usize| adaptive && *limit < permits);
+
+            if root.semaphore.as_ref().map(|(limit, _)| *limit) != limit {
+                root.semaphore = limit.map(|limit: usize| (limit, Arc::new(Semaphore::new(limit))));
+            }
+        }
+    }
+
+    /// Replaces the subprocess semaphore if the amount of thumbnail processes
+    /// allowed at once changed. Processes that hold a permit of the previous
+    /// semaphore finish as usual, so the new limit applies to new processes.
+    fn update_subprocess_semaphore(&self) {
+        let permits: usize = subprocess_permits();
+        if self.imp().subprocess_permits.replace(permits) != permits {
+            g_debug!(
+                "MediaGridView",
+                "Allowing {} thumbnail processes at once.",
+                permits
+            );
+
+            self.imp()
+                .subprocess_semaphore
+                .replace(Arc::new(Semaphore::new(permits)));
+        }
+        self.update_root_semaphores();
+    }
+
+    /// Returns the hardware acceleration backend to use for generating
+    /// thumbnails, or `None` if hardware acceleration is disabled.
+    pub fn hardware_accel_backend(&self) -> Option<HardwareAccel> {
+        HardwareAccel::selected(self.hardware_accel(), &self.hardware_accel_key())
+    }
+
     pub fn window(&self) -> MemoriesApplicationWindow {
         self.root()
             .expect("Must be in a GtkApplicationWindow.")
@@ -218,65 +764,642 @@ impl MemoriesMediaGridView {
             ))
             .build();
 
-        action_group.add_action_entries([zoom_in_action, zoom_out_action]);
+        let rename_action = gio::ActionEntry::builder("rename")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    let files: Vec<gio::File> = this.selected_files();
+
+                    if !files.is_empty() {
+                        MemoriesRenameDialog::new(&files).present(Some(&this));
+                    }
+                }
+            ))
+            .build();
+
+        let adjust_date_action = gio::ActionEntry::builder("adjust_date")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    let files: Vec<gio::File> = this.selected_files();
+
+                    if !files.is_empty() {
+                        MemoriesDateTimeDialog::new(&files).present(Some(&this));
+                    }
+                }
+            ))
+            .build();
+
+        let tags_action = gio::ActionEntry::builder("tags")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    let files: Vec<gio::File> = this.selected_files();
+
+                    if !files.is_empty() {
+                        MemoriesTagsDialog::new(&files).present(Some(&this));
+                    }
+                }
+            ))
+            .build();
+
+        // Hides the selected items from the library, until they are unarchived.
+        let archive_action = gio::ActionEntry::builder("archive")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    let files: Vec<gio::File> = this.selected_files();
+
+                    if !files.is_empty() {
+                        this.archive_files(files);
+                    }
+                }
+            ))
+            .build();
+
+        // Hides the selected items from the library, in the locked Private album.
+        let private_action = gio::ActionEntry::builder("private")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    let files: Vec<gio::File> = this.selected_files();
+
+                    if !files.is_empty() {
+                        this.make_files_private(files);
+                    }
+                }
+            ))
+            .build();
+
+        // Exports the selected items, or every item shown if none are selected.
+        let share_action = gio::ActionEntry::builder("share")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    let mut files: Vec<gio::File> = this.selected_files();
+
+                    if files.is_empty() {
+                        files = this.shown_files();
+                    }
+                    if !files.is_empty() {
+                        MemoriesShareDialog::new(&files, &gettext("Photos")).present(Some(&this));
+                    }
+                }
+            ))
+            .build();
+
+        // Renders the selected photos, or every photo shown if none are selected.
+        let slideshow_action = gio::ActionEntry::builder("slideshow")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    let mut files: Vec<gio::File> = this.selected_files();
+
+                    if files.is_empty() {
+                        files = this.shown_files();
+                    }
+                    files.retain(slideshow_dialog::is_photo);
+
+                    if files.is_empty() {
+                        let toast: adw::Toast =
+                            adw::Toast::new(&gettext("There are no photos for a slideshow"));
+                        this.imp().toast_overlay.add_toast(toast);
+                        return;
+                    }
+                    MemoriesSlideshowDialog::new(&files, &this.imp().toast_overlay).present(Some(&this));
+                }
+            ))
+            .build();
+
+        // Rotates the desktop wallpaper through the selected photos, or every photo shown.
+        let wallpaper_action = gio::ActionEntry::builder("wallpaper")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    let mut files: Vec<gio::File> = this.selected_files();
+
+                    if files.is_empty() {
+                        files = this.shown_files();
+                    }
+                    files.retain(slideshow_dialog::is_photo);
+
+                    let message: String = if files.is_empty() {
+                        gettext("There are no photos for a wallpaper slideshow")
+                    } else {
+                        MemoriesApplication::default().set_wallpaper_slideshow(&files);
+                        ngettext_f(
+                            "The wallpaper will rotate through {COUNT} photo",
+                            "The wallpaper will rotate through {COUNT} photos",
+                            files.len().try_into().unwrap_or(u32::MAX),
+                            &[("COUNT", &files.len().to_string())],
+                        )
+                    };
+                    this.imp().toast_overlay.add_toast(adw::Toast::new(&message));
+                }
+            ))
+            .build();
+
+        action_group.add_action_entries([
+            zoom_in_action, zoom_out_action, rename_action, adjust_date_action, tags_action, archive_action,
+            private_action, share_action, slideshow_action, wallpaper_action,
+        ]);
+
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+        action_group.add_action(&gsettings.create_action("justified-layout"));
         win.insert_action_group("mediagrid", Some(&action_group));
 
-        win.action_set_enabled("mediagrid.zoom_in", true);
-        win.action_set_enabled("mediagrid.zoom_out", false);
+        self.update_zoom_actions();
+        win.action_set_enabled("mediagrid.rename", false);
+        win.action_set_enabled("mediagrid.adjust_date", false);
+        win.action_set_enabled("mediagrid.tags", false);
+        win.action_set_enabled("mediagrid.archive", false);
+        win.action_set_enabled("mediagrid.private", false);
+
+        // Bulk actions are only available while items are selected.
+        self.imp().photo_grid_view.connect_model_notify(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            win,
+            move |grid_view: &gtk::GridView| {
+                let Some(model) = grid_view.model() else {
+                    return;
+                };
+                let had_selection: Cell<bool> = Cell::new(false);
+                this.imp().selected_keys.borrow_mut().clear();
+
+                model.connect_items_changed(clone!(
+                    #[weak]
+                    this,
+                    move |model: &gtk::SelectionModel, position: u32, _, added: u32| {
+                        this.restore_selection(model, position, added);
+                        this.update_footer();
+                    }
+                ));
+                model.connect_selection_changed(clone!(
+                    #[weak]
+                    this,
+                    #[weak]
+                    win,
+                    #[weak]
+                    grid_view,
+                    move |model: &gtk::SelectionModel, position: u32, n_items: u32| {
+                        this.track_selection(model, position, n_items);
+                        this.update_footer();
+
+                        if this.imp().restoring_selection.get() {
+                            return;
+                        }
+                        let has_selection: bool = !model.selection().is_empty();
+
+                        win.action_set_enabled("mediagrid.rename", has_selection);
+                        win.action_set_enabled("mediagrid.adjust_date", has_selection);
+                        win.action_set_enabled("mediagrid.tags", has_selection);
+                        win.action_set_enabled("mediagrid.archive", has_selection);
+                        win.action_set_enabled("mediagrid.private", has_selection);
+
+                        // Let screen reader users know when selection mode starts or ends.
+                        if had_selection.replace(has_selection) != has_selection {
+                            let message: String = match has_selection {
+                                true => gettext("Selection mode"),
+                                false => gettext("Selection mode ended"),
+                            };
+                            grid_view.announce(&message, gtk::AccessibleAnnouncementPriority::Medium);
+                        }
+
+                        feedback::emit(FeedbackEvent::SelectionToggled);
+                    }
+                ));
+                this.update_footer();
+            }
+        ));
+        self.connect_total_items_notify(|this: &Self| this.update_footer());
+        self.connect_searching_notify(|this: &Self| this.update_footer());
     }
 
-    fn gallery_grid_zoom(&self, zoom_in: bool) {
-        let current_columns: u32 = self.imp().photo_grid_view.max_columns();
-        let mut current_zoom_level: usize = 0;
+    /// Shows the amount of media shown in the footer, out of the total amount
+    /// of media while searching, or the amount and size of the selected media.
+    fn update_footer(&self) {
+        let Some(model) = self.imp().photo_grid_view.model() else {
+            return;
+        };
+        let selection: gtk::Bitset = model.selection();
+        let shown: u32 = model.n_items();
+
+        let label: String = if !selection.is_empty() {
+            let selected: u32 = selection.size().try_into().unwrap_or(u32::MAX);
+            let bytes: u64 = (0..selection.size())
+                .filter_map(|i: u64| model.item(selection.nth(i as u32)))
+                .filter_map(|item: glib::Object| item.downcast::<MemoriesMediaItem>().ok())
+                .map(|item: MemoriesMediaItem| item.size())
+                .sum();
+
+            ngettext_f(
+                "{COUNT} item selected · {SIZE}",
+                "{COUNT} items selected · {SIZE}",
+                selected,
+                &[
+                    ("COUNT", &selected.to_string()),
+                    ("SIZE", &format::file_size(bytes, SizeUnits::Decimal)),
+                ],
+            )
+        } else if self.searching() {
+            let total: u32 = self.total_items();
+
+            ngettext_f(
+                "{COUNT} of {TOTAL} item",
+                "{COUNT} of {TOTAL} items",
+                total,
+                &[("COUNT", &shown.to_string()), ("TOTAL", &total.to_string())],
+            )
+        } else {
+            ngettext_f(
+                "{COUNT} item",
+                "{COUNT} items",
+                shown,
+                &[("COUNT", &shown.to_string())],
+            )
+        };
+        self.imp().footer_label.set_label(&label);
+        self.imp().footer_label.set_visible(shown > 0);
+    }
 
-        let zoom_levels: &'static [(u32, i32)] = self.get_zoom_levels();
+    /// Updates the keys of the selected items for a change of the
+    /// selection of `n_items` items starting at `position`.
+    fn track_selection(&self, model: &gtk::SelectionModel, position: u32, n_items: u32) {
+        let mut selected_keys = self.imp().selected_keys.borrow_mut();
 
-        for (i, set) in zoom_levels.iter().enumerate() {
-            if set.0 == current_columns {
-                current_zoom_level = i;
+        for i in position..position + n_items {
+            let Some(item) = model.item(i).and_downcast::<MemoriesMediaItem>() else {
+                continue;
+            };
+            if model.is_selected(i) {
+                selected_keys.insert(item.key());
+            } else {
+                selected_keys.remove(&item.key());
             }
         }
-        if zoom_in {
-            if current_zoom_level == zoom_levels.len() - 1 {
+    }
+
+    /// Selects the `added` items starting at `position` again if they were selected
+    /// before they were removed, such as when they moved after a rescan.
+    fn restore_selection(&self, model: &gtk::SelectionModel, position: u32, added: u32) {
+        let positions: Vec<u32> = {
+            let selected_keys = self.imp().selected_keys.borrow();
+
+            if selected_keys.is_empty() {
                 return;
             }
+            (position..position + added)
+                .filter(|i: &u32| {
+                    model
+                        .item(*i)
+                        .and_downcast::<MemoriesMediaItem>()
+                        .is_some_and(|item: MemoriesMediaItem| selected_keys.contains(&item.key()))
+                })
+                .collect()
+        };
+        if positions.is_empty() {
+            return;
+        }
+        self.imp().restoring_selection.set(true);
+
+        for i in positions {
+            if !model.is_selected(i) {
+                model.select_item(i, false);
+            }
+        }
+        self.imp().restoring_selection.set(false);
+    }
+
+    /// Returns the files of the items currently selected in the grid.
+    pub fn selected_files(&self) -> Vec<gio::File> {
+        self.selected_items()
+            .iter()
+            .map(MemoriesMediaItem::file)
+            .collect()
+    }
+
+    /// Returns the selected media items, in the order that they are shown.
+    fn selected_items(&self) -> Vec<MemoriesMediaItem> {
+        let Some(model) = self.imp().photo_grid_view.model() else {
+            return vec![];
+        };
+        let selection: gtk::Bitset = model.selection();
+
+        (0..selection.size())
+            .filter_map(|i: u64| model.item(selection.nth(i as u32)))
+            .filter_map(|item: glib::Object| item.downcast::<MemoriesMediaItem>().ok())
+            .collect()
+    }
+
+    /// Moves media files to the archive, with a toast to undo it.
+    fn archive_files(&self, files: Vec<gio::File>) {
+        MemoriesApplication::default().set_archived(&files, true);
+
+        let toast: adw::Toast = adw::Toast::builder()
+            .title(ngettext_f(
+                "{COUNT} item archived",
+                "{COUNT} items archived",
+                files.len().try_into().unwrap_or(u32::MAX),
+                &[("COUNT", &files.len().to_string())],
+            ))
+            .button_label(gettext("_Undo"))
+            .build();
+
+        toast.connect_button_clicked(move |_: &adw::Toast| {
+            MemoriesApplication::default().set_archived(&files, false);
+        });
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    /// Moves media files to the Private album. Unlike archiving, there is no toast
+    /// to undo it, since that would reveal the private files to anyone watching.
+    fn make_files_private(&self, files: Vec<gio::File>) {
+        MemoriesApplication::default().set_private(&files, true);
+
+        self.imp().toast_overlay.add_toast(adw::Toast::new(&ngettext_f(
+            "{COUNT} item moved to the Private album",
+            "{COUNT} items moved to the Private album",
+            files.len().try_into().unwrap_or(u32::MAX),
+            &[("COUNT", &files.len().to_string())],
+        )));
+    }
+
+    /// Replaces the bursts detected in the library, so that
+    /// grid cells update the badges of the bursts they show.
+    pub fn set_bursts(&self, bursts: Bursts) {
+        self.imp().bursts.replace(bursts);
+        self.emit_by_name::<()>("bursts-changed", &[]);
+    }
+
+    pub fn connect_bursts_changed<F: Fn(&Self) + 'static>(&self, callback: F) -> glib::SignalHandlerId {
+        self.connect_closure(
+            "bursts-changed",
+            false,
+            glib::closure_local!(move |media_grid: &Self| {
+                callback(media_grid);
+            }),
+        )
+    }
+
+    /// Returns the files of every item shown in the grid.
+    pub fn shown_files(&self) -> Vec<gio::File> {
+        self.shown_items().iter().map(MemoriesMediaItem::file).collect()
+    }
+
+    /// Returns every media item shown in the grid, in their order.
+    pub fn shown_items(&self) -> Vec<MemoriesMediaItem> {
+        let Some(model) = self.imp().photo_grid_view.model() else {
+            return vec![];
+        };
+        model
+            .iter::<glib::Object>()
+            .filter_map(Result::ok)
+            .filter_map(|item: glib::Object| item.downcast::<MemoriesMediaItem>().ok())
+            .collect()
+    }
+
+    /// Generates the thumbnails of the rows just past the visible ones in the
+    /// direction that the grid is scrolled in, so that they are already cached
+    /// once their cells are bound, instead of popping in after a fast scroll.
+    /// Only photos are prefetched, since video thumbnails are slower to extract.
+    fn prefetch_thumbnails(&self, vadjustment: &gtk::Adjustment) {
+        let scroll_value: f64 = vadjustment.value();
+        let previous_value: f64 = self.imp().last_scroll_value.replace(scroll_value);
+        let grid_view: &gtk::GridView = &self.imp().photo_grid_view;
+
+        let Some(model) = grid_view.model() else {
+            return;
+        };
+        if scroll_value == previous_value {
+            return;
+        }
+        let range: Range<u32> = prefetch_range_for(
+            scroll_value,
+            vadjustment.page_size(),
+            vadjustment.upper(),
+            model.n_items(),
+            grid_view.max_columns(),
+            scroll_value > previous_value,
+        );
+        let thumbnail_size: u32 = self.thumbnail_size();
+
+        for position in range {
+            let Some(item) = model.item(position).and_downcast::<MemoriesMediaItem>() else {
+                continue;
+            };
+            let file: gio::File = item.file();
+            let is_photo: bool = matches!(item.viewer_content_type(), Some(ViewerContentType::Image));
+
+            if !is_photo
+                || !self
+                    .imp()
+                    .prefetched
+                    .borrow_mut()
+                    .insert((file.uri().to_string(), thumbnail_size))
+            {
+                continue;
+            }
+            glib::spawn_future_local(clone!(
+                #[weak(rename_to = this)]
+                self,
+                async move {
+                    MemoriesMediaCell::prefetch_thumbnail(&this, &file, thumbnail_size).await;
+                }
+            ));
+        }
+    }
+
+    /// Enumerates the library subfolders that are enumerated on demand which could
+    /// have media shown up to a few rows past the bottom of the view. Media is sorted
+    /// newest first, so these are the subfolders modified after the media there.
+    fn enumerate_folders_near_view(&self, vadjustment: &gtk::Adjustment) {
+        let library_model: MemoriesLibraryListModel = MemoriesApplication::default().library_list_model();
+        let grid_view: &gtk::GridView = &self.imp().photo_grid_view;
+
+        let Some(model) = grid_view.model() else {
+            return;
+        };
+        if !library_model.enumerate_lazily() || model.n_items() == 0 {
+            return;
+        }
+        let columns: u32 = grid_view.max_columns();
+        let position: u32 = first_visible_position_for(
+            vadjustment.value() + vadjustment.page_size(),
+            vadjustment.upper(),
+            model.n_items(),
+            columns,
+        ) + GRID_PREFETCH_ROWS * columns;
+
+        let unix_time: Option<i64> = model
+            .item(position.min(model.n_items() - 1))
+            .and_downcast::<MemoriesMediaItem>()
+            .and_then(|item: MemoriesMediaItem| item.unix_time());
+
+        if let Some(unix_time) = unix_time {
+            library_model.enumerate_folders_modified_since(unix_time);
+        }
+    }
+
+    /// Steps through the grid zoom levels while the grid is pinched, once the
+    /// pinch has scaled far enough since the last step. Spreading the fingers
+    /// zooms in, and each step gives a haptic tick on mobile devices.
+    fn pinch_zoom(&self, scale: f64) {
+        let relative_scale: f64 = scale / self.imp().pinch_scale.get();
+
+        let zoom_in: bool = if relative_scale >= GRID_PINCH_ZOOM_STEP {
+            true
+        } else if relative_scale <= 1.0 / GRID_PINCH_ZOOM_STEP {
+            false
+        } else {
+            return;
+        };
+        self.imp().pinch_scale.set(scale);
+
+        if self.gallery_grid_zoom(zoom_in) {
+            feedback::emit(FeedbackEvent::ZoomStep);
+        }
+    }
+
+    /// Refreshes the library if a touch drag that started at
+    /// the top of the library was pulled down far enough.
+    fn pull_to_refresh(&self, offset_y: f64) {
+        if !self.imp().pull_from_top.replace(false) || offset_y < PULL_TO_REFRESH_DISTANCE {
+            return;
+        }
+        if self.activate_action("win.refresh", None).is_ok() {
+            let toast: adw::Toast = adw::Toast::builder()
+                .title(gettext("Refreshing library…"))
+                .timeout(2)
+                .build();
+            self.imp().toast_overlay.add_toast(toast);
+        }
+    }
+
+    /// Zooms the grid in or out by one zoom level. Returns `false` if
+    /// the grid is already at the min/max zoom level.
+    fn gallery_grid_zoom(&self, zoom_in: bool) -> bool {
+        let current_zoom_level: usize = self.imp().zoom_level.get();
+
+        if zoom_in {
+            if current_zoom_level == GRID_ZOOM_LEVELS.len() - 1 {
+                feedback::emit(FeedbackEvent::ZoomLimit);
+                return false;
+            }
             self.set_grid_zoom_level(current_zoom_level + 1);
         } else {
             if current_zoom_level == 0 {
-                return;
+                feedback::emit(FeedbackEvent::ZoomLimit);
+                return false;
             }
             self.set_grid_zoom_level(current_zoom_level - 1);
         }
+        true
     }
 
-    /// Returns the zoom levels array for the appropriate window size.
-    fn get_zoom_levels(&self) -> &'static [(u32, i32)] {
-        if self.grid_desktop_zoom() {
-            GRID_DESKTOP_ZOOM_LEVELS
+    /// Sets the target cell size using the given zoom level index, updates
+    /// the grid layout, and updates the grid control popover menu if the
+    /// user has reached the min/max zoom setting.
+    fn set_grid_zoom_level(&self, zoom_level: usize) {
+        self.imp().zoom_level.set(zoom_level);
+        self.update_grid_layout();
+        self.update_zoom_actions();
+    }
+
+    /// Sets the grid view columns and list item widget height requests
+    /// from the width of the grid and the target cell size of the zoom level.
+    fn update_grid_layout(&self) {
+        let target_size: i32 = GRID_ZOOM_LEVELS[self.imp().zoom_level.get()];
+        let (columns, cell_height) = grid_layout_for(self.width(), target_size);
+
+        let grid_view: &gtk::GridView = &self.imp().photo_grid_view;
+
+        if grid_view.max_columns() != columns {
+            grid_view.set_min_columns(columns);
+            grid_view.set_max_columns(columns);
+        }
+        if self.grid_widget_height() != cell_height {
+            self.set_grid_widget_height(cell_height);
+        }
+        self.imp().justified_view.set_row_height(target_size);
+    }
+
+    /// Shows the grid or the justified layout, as set in GSettings. The
+    /// justified view is only bound to the grid's model while it is shown,
+    /// since it creates a widget for every item in the model.
+    fn update_layout_mode(&self) {
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+        let justified: bool = gsettings.boolean("justified-layout");
+
+        let model: Option<gio::ListModel> = self.imp().photo_grid_view.model().and_upcast();
+
+        if justified {
+            self.imp().justified_view.bind_model(model.as_ref());
+            self.imp().layout_stack.set_visible_child_name("justified");
         } else {
-            GRID_MOBILE_ZOOM_LEVELS
+            self.imp().layout_stack.set_visible_child_name("grid");
+            self.imp().justified_view.bind_model(None);
         }
     }
 
-    /// Sets the grid view columns and list item widget height requests
-    /// using the given zoom level index, and updates the grid control
-    /// popover menu if the user has reached the min/max zoom setting.
-    fn set_grid_zoom_level(&self, zoom_level: usize) {
-        let zoom_levels: &'static [(u32, i32)] = self.get_zoom_levels();
-        let new_zoom_level: (u32, i32) = zoom_levels[zoom_level];
+    /// Makes hovering select the cells of the grid if media open with a single
+    /// click, so that the click on a hovered cell opens it. Otherwise, clicking
+    /// a cell selects it, as set in the `item-activation` GSettings key.
+    fn update_item_activation(&self) {
+        let activation: ItemActivation =
+            ItemActivation::from_value(MemoriesApplication::default().gsettings().int("item-activation"));
 
-        self.set_grid_widget_height(new_zoom_level.1);
+        self.imp()
+            .photo_grid_view
+            .set_single_click_activate(activation == ItemActivation::SingleClick);
+    }
 
-        self.imp().photo_grid_view.set_min_columns(new_zoom_level.0);
-        self.imp().photo_grid_view.set_max_columns(new_zoom_level.0);
+    /// Opens the selected media in the viewer, if a single item is selected.
+    /// Returns whether the media was opened.
+    fn open_selected(&self) -> bool {
+        let items: Vec<MemoriesMediaItem> = self.selected_items();
+        let [item] = items.as_slice() else {
+            return false;
+        };
+        // Media whose format is not supported is opened in another app.
+        if item.unsupported_content_type().is_some() {
+            MemoriesMediaCell::open_externally(&item.file(), &self.window());
+            return true;
+        }
+        let Some(content_type) = item.viewer_content_type() else {
+            return false;
+        };
 
+        match MemoriesMediaViewer::open(&self.window(), &item.file(), &content_type) {
+            Some(viewer) => {
+                viewer.imp().properties_widget.update_item_details(item);
+                viewer.set_siblings(self.shown_items(), &self.collection_title());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Enables or disables the grid zoom actions depending on
+    /// whether the user has reached the min/max zoom level.
+    fn update_zoom_actions(&self) {
         let win: MemoriesApplicationWindow = self.window();
+        let zoom_level: usize = self.imp().zoom_level.get();
 
         if zoom_level == 0 {
             // Reached minimum zoom level
             win.action_set_enabled("mediagrid.zoom_in", true);
             win.action_set_enabled("mediagrid.zoom_out", false);
-        } else if zoom_level == zoom_levels.len() - 1 {
+        } else if zoom_level == GRID_ZOOM_LEVELS.len() - 1 {
             // Reached maximum zoom level
             win.action_set_enabled("mediagrid.zoom_in", false);
             win.action_set_enabled("mediagrid.zoom_out", true);
@@ -292,3 +1415,145 @@ impl Default for MemoriesMediaGridView {
         Self::new()
     }
 }
+
+/// Returns the amount of grid columns and the height of grid cells for the
+/// given available width, so that cells are as close as possible to the target
+/// cell size of a zoom level while filling the width of the grid.
+fn grid_layout_for(available_width: i32, target_size: i32) -> (u32, i32) {
+    let available_width: i32 = available_width.max(1);
+    let columns: i32 = (available_width as f64 / target_size.max(1) as f64)
+        .round()
+        .max(1.0) as i32;
+    let cell_height: i32 = (available_width / columns - GRID_CELL_PADDING).max(1);
+
+    (columns as u32, cell_height)
+}
+
+/// Returns the amount of thumbnail processes allowed at once, as configured
+/// by the user, and fewer if the system is saving power.
+pub fn subprocess_permits() -> usize {
+    let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+    let configured: usize = match gsettings.int("thumbnail-processes") {
+        0 => FFMPEG_CONCURRENT_PROCESSES,
+        processes => (processes as usize).min(FFMPEG_MAX_CONCURRENT_PROCESSES),
+    };
+    if power::should_throttle() {
+        configured.min(FFMPEG_POWER_SAVER_PROCESSES)
+    } else {
+        configured
+    }
+}
+
+/// Returns the settings that thumbnails are generated with while the library
+/// is not shown, which are those of a grid at its default zoom level.
+pub fn default_thumbnail_settings() -> ThumbnailSettings {
+    let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+    let cell_height: i32 = GRID_ZOOM_LEVELS[DEFAULT_GRID_ZOOM_LEVEL] - GRID_CELL_PADDING;
+
+    ThumbnailSettings {
+        size: thumbnail_size_for(cell_height, 1),
+        hardware_accel: HardwareAccel::selected(
+            gsettings.boolean("ffmpeg-hardware-acceleration"),
+            &gsettings.string("ffmpeg-hardware-acceleration-backend"),
+        ),
+        ffmpeg_available: Command::new(FFMPEG_BINARY).output().is_ok(),
+    }
+}
+
+/// Returns the smallest thumbnail size that is at least as large as a cell
+/// of the given logical height on a display with the given scale factor.
+/// Sizes are rounded up to `THUMBNAIL_SIZES` so fewer sizes are cached.
+fn thumbnail_size_for(cell_height: i32, scale_factor: i32) -> u32 {
+    let pixels: u32 = (cell_height.max(1) * scale_factor.max(1)) as u32;
+
+    THUMBNAIL_SIZES
+        .iter()
+        .copied()
+        .find(|size: &u32| *size >= pixels)
+        .unwrap_or(*THUMBNAIL_SIZES.last().unwrap())
+}
+
+/// Returns the model position of the first item in the topmost visible row,
+/// given the vertical scroll offset and the height of the scrollable content.
+/// Rows of the grid all have the same height, so the row is found by ratio.
+fn first_visible_position_for(scroll_value: f64, scroll_upper: f64, n_items: u32, columns: u32) -> u32 {
+    if n_items == 0 || columns == 0 || scroll_upper <= 0.0 {
+        return 0;
+    }
+    let rows: u32 = n_items.div_ceil(columns);
+    let row: u32 = ((scroll_value / scroll_upper) * rows as f64).floor() as u32;
+
+    (row.min(rows - 1) * columns).min(n_items - 1)
+}
+
+/// Returns the model positions of the items in the `GRID_PREFETCH_ROWS` rows after
+/// the last visible row if the grid is scrolled down, or before the first visible
+/// row if it is scrolled up, given the vertical scroll offset, the height of the
+/// viewport, and the height of the scrollable content.
+fn prefetch_range_for(
+    scroll_value: f64,
+    page_size: f64,
+    scroll_upper: f64,
+    n_items: u32,
+    columns: u32,
+    scrolling_down: bool,
+) -> Range<u32> {
+    if n_items == 0 || columns == 0 || scroll_upper <= 0.0 {
+        return 0..0;
+    }
+    let rows: u32 = n_items.div_ceil(columns);
+    let row_height: f64 = scroll_upper / rows as f64;
+
+    let (start_row, end_row): (u32, u32) = if scrolling_down {
+        let last_row: u32 = ((scroll_value + page_size) / row_height).ceil() as u32;
+        (last_row, last_row + GRID_PREFETCH_ROWS)
+    } else {
+        let first_row: u32 = (scroll_value / row_height).floor() as u32;
+        (first_row.saturating_sub(GRID_PREFETCH_ROWS), first_row)
+    };
+    let position = |row: u32| (row.min(rows) * columns).min(n_items);
+
+    position(start_row)..position(end_row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefetch_ranges_follow_scroll_direction() {
+        // 25 rows of 4 items, 100 pixels high each, with 5 rows visible.
+        assert_eq!(prefetch_range_for(0.0, 500.0, 2500.0, 100, 4, true), 20..36);
+        assert_eq!(prefetch_range_for(1000.0, 500.0, 2500.0, 100, 4, false), 24..40);
+        assert_eq!(prefetch_range_for(200.0, 500.0, 2500.0, 100, 4, false), 0..8);
+        assert_eq!(prefetch_range_for(2000.0, 500.0, 2500.0, 100, 4, true), 100..100);
+        assert_eq!(prefetch_range_for(1900.0, 500.0, 2500.0, 98, 4, true), 96..98);
+        assert_eq!(prefetch_range_for(0.0, 500.0, 0.0, 100, 4, true), 0..0);
+    }
+
+    #[test]
+    fn first_visible_positions() {
+        assert_eq!(first_visible_position_for(0.0, 1000.0, 100, 4), 0);
+        assert_eq!(first_visible_position_for(500.0, 1000.0, 100, 4), 48);
+        assert_eq!(first_visible_position_for(1000.0, 1000.0, 100, 4), 96);
+        assert_eq!(first_visible_position_for(10.0, 0.0, 100, 4), 0);
+        assert_eq!(first_visible_position_for(10.0, 100.0, 0, 4), 0);
+    }
+
+    #[test]
+    fn grid_layouts_for_widths() {
+        assert_eq!(grid_layout_for(360, 64), (6, 54));
+        assert_eq!(grid_layout_for(360, 96), (4, 84));
+        assert_eq!(grid_layout_for(360, 256), (1, 354));
+        assert_eq!(grid_layout_for(1200, 96), (13, 86));
+        assert_eq!(grid_layout_for(0, 96), (1, 1));
+    }
+
+    #[test]
+    fn thumbnail_sizes_for_cells() {
+        assert_eq!(thumbnail_size_for(66, 1), 128);
+        assert_eq!(thumbnail_size_for(66, 2), 256);
+        assert_eq!(thumbnail_size_for(234, 2), 512);
+        assert_eq!(thumbnail_size_for(234, 3), 512);
+    }
+}
@@ -18,26 +18,54 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use crate::globals::DIRECTORY_MODEL_ATTRIBUTES;
+use crate::library::media_viewer::ViewerContentType;
 use gtk::prelude::*;
+use gtk::subclass::prelude::*;
 use gtk::{gio, glib};
+use std::path::Path;
 
 mod imp {
     use gtk::prelude::*;
     use gtk::subclass::prelude::*;
     use gtk::{gio, glib};
-    use std::cell::{Cell, OnceCell};
+    use std::cell::{Cell, OnceCell, RefCell};
 
     #[derive(glib::Properties, Default, Debug)]
     #[properties(wrapper_type = super::MemoriesMediaItem)]
     pub struct MemoriesMediaItem {
         #[property(get, set)]
         basename: OnceCell<glib::GString>,
+        /// URI of the media file, which identifies the item in the library.
+        #[property(get)]
+        pub(super) uri: OnceCell<String>,
+        /// Name of the media file as shown in the UI, which is valid UTF-8.
+        #[property(get)]
+        pub(super) display_name: OnceCell<String>,
+        /// Content type of the media file, as told by GIO when it was enumerated.
+        #[property(get)]
+        pub(super) content_type: OnceCell<String>,
         #[property(get, set)]
-        timestamp: OnceCell<glib::DateTime>,
+        pub(super) timestamp: OnceCell<glib::DateTime>,
+        #[property(get)]
+        pub(super) created: RefCell<Option<glib::DateTime>>,
+        #[property(get)]
+        pub(super) is_video: Cell<bool>,
+        /// Size of the media file in bytes.
+        #[property(get)]
+        pub(super) size: Cell<u64>,
+        /// Duration of the video in seconds, or 0 if it is not known yet.
+        #[property(get, set)]
+        duration: Cell<u32>,
+        /// Whether the media is a favorite, kept in sync
+        /// with the favorites in the media database by the library model.
         #[property(get, set)]
         favorite: Cell<bool>,
         #[property(get, set)]
         hidden: Cell<bool>,
+        /// Path of the thumbnail last generated for the media, if any.
+        #[property(get, set, nullable)]
+        thumbnail_path: RefCell<Option<String>>,
         #[property(get, set)]
         file: OnceCell<gio::File>,
     }
@@ -57,19 +85,110 @@ glib::wrapper! {
 }
 
 impl MemoriesMediaItem {
+    /// Creates a media item from a `GFileInfo` object of a `GtkDirectoryList`
+    /// model, which holds the `GFile` of the media in its `standard::file` attribute.
     pub fn new(file_info_obj: &glib::Object) -> Self {
         let file_info: gio::FileInfo = file_info_obj.clone().downcast().unwrap();
         let file_obj: glib::Object = file_info.attribute_object("standard::file").unwrap();
         let gfile: gio::File = file_obj.downcast().unwrap();
 
+        Self::from_file_info(&gfile, &file_info)
+    }
+
+    /// Creates a media item from a media file and the `GFileInfo` object that was
+    /// queried for it with the `DIRECTORY_MODEL_ATTRIBUTES` attributes.
+    pub fn from_file_info(file: &gio::File, file_info: &gio::FileInfo) -> Self {
         let obj: Self = glib::Object::new();
+        let imp = obj.imp();
+
+        obj.set_file(file.clone());
+        obj.set_basename(file.basename().unwrap().to_string_lossy());
+
+        let _ = imp.uri.set(file.uri().to_string());
+        let _ = imp.display_name.set(file_info.display_name().to_string());
+        let _ = imp.content_type.set(
+            file_info
+                .content_type()
+                .map(|content_type: glib::GString| content_type.to_string())
+                .unwrap_or_default(),
+        );
 
-        obj.set_file(gfile.clone());
-        obj.set_basename(gfile.basename().unwrap().to_string_lossy());
+        imp.size.set(file_info.size().try_into().unwrap_or(0));
+
+        if let Some(modified) = file_info.modification_date_time() {
+            obj.set_timestamp(modified);
+        }
+        if file_info.has_attribute(gio::FILE_ATTRIBUTE_TIME_CREATED) {
+            let created: u64 = file_info.attribute_uint64(gio::FILE_ATTRIBUTE_TIME_CREATED);
+
+            imp.created.replace(
+                i64::try_from(created)
+                    .ok()
+                    .and_then(|unix_time: i64| glib::DateTime::from_unix_local(unix_time).ok()),
+            );
+        }
+        imp.is_video.set(matches!(
+            obj.viewer_content_type(),
+            Some(ViewerContentType::Video)
+        ));
         obj
     }
 
-    pub fn new_and_upcast(file_info_obj: &glib::Object) -> glib::Object {
-        MemoriesMediaItem::new(file_info_obj).upcast()
+    /// Queries the file info of a media file, and creates a media item from it.
+    /// Used for media that is not part of a library model, such as similar photos.
+    pub async fn for_file(file: &gio::File) -> Result<Self, glib::Error> {
+        let file_info: gio::FileInfo = file
+            .query_info_future(
+                DIRECTORY_MODEL_ATTRIBUTES,
+                gio::FileQueryInfoFlags::NONE,
+                glib::Priority::LOW,
+            )
+            .await?;
+
+        Ok(Self::from_file_info(file, &file_info))
+    }
+
+    /// Returns the modification time of the media file in seconds since the
+    /// Unix epoch, or `None` if it was not queried when the item was enumerated.
+    pub fn unix_time(&self) -> Option<i64> {
+        self.imp().timestamp.get().map(glib::DateTime::to_unix)
+    }
+
+    /// Returns the modification time of the media file, if it was queried.
+    pub fn modified(&self) -> Option<glib::DateTime> {
+        self.imp().timestamp.get().cloned()
+    }
+
+    /// Returns the URI of the media file, which identifies the item in the
+    /// library, so that it is recognized when the library is enumerated again.
+    pub fn key(&self) -> String {
+        self.uri()
+    }
+
+    /// Returns how the media is shown in the viewer, told by its file extension,
+    /// or else by the content type that GIO told when the file was enumerated.
+    /// Returns `None` if neither is of a supported format, in which case the
+    /// contents of the file can still be sniffed with `ViewerContentType::sniff()`.
+    pub fn viewer_content_type(&self) -> Option<ViewerContentType> {
+        ViewerContentType::from_file_name(Path::new(self.basename().as_str())).or_else(|| {
+            gio::content_type_get_mime_type(&self.content_type())
+                .map(|mime_type: glib::GString| ViewerContentType::from_mime_type(&mime_type))
+                .filter(|content_type: &ViewerContentType| {
+                    !matches!(content_type, ViewerContentType::Invalid)
+                })
+        })
+    }
+
+    /// Returns the content type of media whose format is not supported, such as
+    /// `.mts` videos. Returns `None` for supported media, and for files that are not media.
+    pub fn unsupported_content_type(&self) -> Option<glib::GString> {
+        if self.viewer_content_type().is_some() {
+            return None;
+        }
+        let content_type: glib::GString = self.content_type().into();
+
+        gio::content_type_get_mime_type(&content_type)
+            .is_some_and(|mime_type: glib::GString| ViewerContentType::is_media_mime_type(&mime_type))
+            .then_some(content_type)
     }
 }
@@ -18,25 +18,60 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use crate::application::MemoriesApplication;
+use crate::cast::{self, MemoriesCastDialog};
+use crate::globals::{
+    CONTENT_SNIFF_BYTES, EXTERNAL_EDIT_RELOAD_DELAY, MOUSE_BACK_BUTTON, MOUSE_FORWARD_BUTTON,
+    PANORAMA_MIN_ASPECT_RATIO, PANORAMA_SCROLL_SPEED, SIMILAR_THUMBNAIL_SIZE, TILED_RENDERING_MIN_PIXELS,
+    VIEWER_MAX_ZOOM, VIEWER_SWIPE_BACK_VELOCITY, VIEWER_SWIPE_DISTANCE, VIEWER_ZOOM_STEP,
+};
+use crate::i18n::gettext_f;
+use crate::library::compress_dialog::MemoriesCompressDialog;
+use crate::library::date_time_dialog::MemoriesDateTimeDialog;
+use crate::library::export_dialog::MemoriesExportDialog;
+use crate::library::justified_view::MemoriesJustifiedView;
+use crate::library::list_model::MemoriesLibraryListModel;
+use crate::library::media_item::MemoriesMediaItem;
+#[cfg(feature = "use-photo-sphere")]
+use crate::library::photo_sphere::MemoriesPhotoSphere;
+use crate::library::print::print_texture;
+use crate::library::tags_dialog::MemoriesTagsDialog;
+use crate::library::tiled_paintable::MemoriesTiledPaintable;
+use crate::library::viewer_window::MemoriesViewerWindow;
+#[cfg(feature = "use-face-detection")]
+use crate::people::faces::{Face, FaceIndex};
+use crate::util::enums::{OverlayRevealTrigger, ScrollWheelAction};
+use crate::util::feedback::{self, FeedbackEvent};
+use crate::util::format;
+use crate::util::metadata::embedded_jpeg_images;
+#[cfg(feature = "use-photo-sphere")]
+use crate::util::metadata::is_equirectangular;
 use crate::window::MemoriesApplicationWindow;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use gettextrs::gettext;
-use glib::{clone, g_debug, g_error};
+use glib::{clone, g_debug, g_warning};
 #[cfg(feature = "disable-glycin-sandbox")]
 use glycin::SandboxMechanism;
-use gtk::{gdk, gio, glib};
+use gtk::{gdk, gio, glib, graphene};
+use std::cell::Cell;
 use std::ffi::OsStr;
+use std::path::Path;
 use std::time::Duration;
 
 mod imp {
     use crate::application::MemoriesApplication;
+    use crate::library::media_item::MemoriesMediaItem;
+    #[cfg(feature = "use-photo-sphere")]
+    use crate::library::photo_sphere::MemoriesPhotoSphere;
     use crate::library::properties::MemoriesProperties;
     use adw::prelude::{ObjectExt, SettingsExtManual, WidgetExt};
     use adw::subclass::prelude::*;
     use glib::clone;
-    use gtk::{gio, glib};
-    use std::cell::Cell;
+    use gtk::{gdk, gio, glib};
+    #[cfg(feature = "use-photo-sphere")]
+    use std::cell::OnceCell;
+    use std::cell::{Cell, RefCell};
 
     #[derive(Default, gtk::CompositeTemplate)]
     #[template(resource = "/com/maxrdz/Memories/ui/media-viewer.ui")]
@@ -44,10 +79,60 @@ mod imp {
         pub(super) motion_last_x: Cell<f64>,
         pub(super) motion_last_y: Cell<f64>,
         pub(super) overlay_timeout_source: Cell<Option<glib::SourceId>>,
+        pub(super) content_file: RefCell<Option<gio::File>>,
+        /// Incremented whenever other media is shown or an image is loaded again,
+        /// so that loads started for the media shown before are dropped once done.
+        pub(super) load_generation: Cell<u64>,
+        pub(super) immersive: Cell<bool>,
+        /// Zoom level of the image, where 0.0 means that the image fits the viewer.
+        pub(super) zoom_level: Cell<f64>,
+        /// Set while a wide image is shown as a panorama, which fits the height of the viewer.
+        pub(super) panorama: Cell<bool>,
+        /// Scrolls the panorama automatically, until the user scrolls it.
+        pub(super) panorama_tick: RefCell<Option<gtk::TickCallbackId>>,
+        /// Set if the image is a 360° photo, which is shown in the photo sphere viewer.
+        #[cfg(feature = "use-photo-sphere")]
+        pub(super) is_photo_sphere: Cell<bool>,
+        /// Viewer of 360° photos, added to the image stack when first needed.
+        #[cfg(feature = "use-photo-sphere")]
+        pub(super) photo_sphere: OnceCell<MemoriesPhotoSphere>,
+        /// Set if the window was fullscreened by entering immersive
+        /// mode, so that it is only restored when leaving immersive mode.
+        pub(super) immersive_fullscreened: Cell<bool>,
+        /// Bounds of the faces found in the image, as fractions of its size.
+        #[cfg(feature = "use-face-detection")]
+        pub(super) faces: RefCell<Vec<gtk::graphene::Rect>>,
+        /// Actions of the viewer, kept to update their state when another file is shown.
+        pub(super) action_group: RefCell<Option<gio::SimpleActionGroup>>,
+        /// Watches the media file for changes, such as by an external editor.
+        pub(super) file_monitor: RefCell<Option<gio::FileMonitor>>,
+        pub(super) reload_timeout_source: RefCell<Option<glib::SourceId>>,
+        /// The decoded primary image, kept to switch back from an embedded image.
+        pub(super) primary_paintable: RefCell<Option<gdk::Paintable>>,
+        /// Alternate images embedded in the image file, such as depth maps.
+        pub(super) embedded_images: RefCell<Vec<glib::Bytes>>,
+        /// Media items that are stepped through with the previous and next
+        /// actions, in the order that they are shown in the library.
+        pub(super) siblings: RefCell<Vec<MemoriesMediaItem>>,
+        /// Name of the collection that the siblings are shown in, such
+        /// as "Photos" or the name of a folder, which titles the viewer.
+        pub(super) collection_title: RefCell<String>,
+        /// Scroll positions that keep the point under the pointer in place
+        /// after zooming, applied once the scrolled window is resized.
+        pub(super) pending_hscroll: Cell<Option<f64>>,
+        pub(super) pending_vscroll: Cell<Option<f64>>,
+        /// Horizontal distance travelled by the current touchpad swipe.
+        pub(super) swipe_distance: Cell<f64>,
+        /// Zoom level of the image when the current pinch gesture began.
+        pub(super) pinch_zoom_level: Cell<f64>,
 
+        #[template_child]
+        pub(super) toolbar_view: TemplateChild<adw::ToolbarView>,
         #[template_child]
         header_bar: TemplateChild<adw::HeaderBar>,
         #[template_child]
+        pub(super) window_title: TemplateChild<adw::WindowTitle>,
+        #[template_child]
         more_button: TemplateChild<gtk::MenuButton>,
         // TODO: Update to `adw::MultiLayoutView` once bindings for 1.6 are merged.
         #[template_child]
@@ -64,23 +149,43 @@ mod imp {
         #[template_child]
         pub(super) nav_overlay_revealer: TemplateChild<gtk::Revealer>,
         #[template_child]
-        nav_overlay_controls: TemplateChild<gtk::Box>,
+        pub(super) nav_overlay_controls: TemplateChild<gtk::Box>,
         #[template_child]
         pub(super) zoom_overlay_revealer: TemplateChild<gtk::Revealer>,
         #[template_child]
         zoom_overlay_controls: TemplateChild<gtk::Box>,
         #[template_child]
+        pub(super) panorama_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub(super) renditions_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub(super) renditions_box: TemplateChild<gtk::Box>,
+        #[template_child]
         pub(super) viewer_stack: TemplateChild<adw::ViewStack>,
         #[template_child]
         image_page: TemplateChild<adw::ViewStackPage>,
         #[template_child]
         video_page: TemplateChild<adw::ViewStackPage>,
         #[template_child]
-        scrolled_window: TemplateChild<gtk::ScrolledWindow>,
+        pub(super) image_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) loading_spinner: TemplateChild<gtk::Spinner>,
+        #[template_child]
+        pub(super) error_status_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub(super) scrolled_window: TemplateChild<gtk::ScrolledWindow>,
         #[template_child]
         pub(super) viewer_picture: TemplateChild<gtk::Picture>,
         #[template_child]
+        pub(super) faces_area: TemplateChild<gtk::DrawingArea>,
+        #[template_child]
         pub(super) viewer_video: TemplateChild<gtk::Video>,
+        #[template_child]
+        pub(super) similar_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub(super) similar_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) similar_box: TemplateChild<gtk::Box>,
     }
 
     #[glib::object_subclass]
@@ -110,6 +215,101 @@ mod imp {
                 .bind("autoplay-videos", &self.viewer_video.clone(), "autoplay")
                 .build();
 
+            // Keep the visible area of tiled images in sync with the scroll position.
+            for adjustment in [
+                self.scrolled_window.hadjustment(),
+                self.scrolled_window.vadjustment(),
+            ] {
+                adjustment.connect_value_changed(clone!(
+                    #[weak]
+                    obj,
+                    move |_: &gtk::Adjustment| obj.update_visible_area()
+                ));
+                adjustment.connect_changed(clone!(
+                    #[weak]
+                    obj,
+                    move |adjustment: &gtk::Adjustment| {
+                        obj.apply_pending_scroll(adjustment);
+                        obj.update_visible_area();
+                    }
+                ));
+            }
+
+            // Zooms with the scroll wheel, or shows the previous or next media, also by swiping
+            // on a touchpad. Panoramas are panned with the scroll wheel instead, which stops
+            // scrolling them automatically.
+            let scroll_controller: gtk::EventControllerScroll =
+                gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::BOTH_AXES);
+            scroll_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+
+            scroll_controller.connect_scroll(clone!(
+                #[weak]
+                obj,
+                #[upgrade_or]
+                glib::Propagation::Proceed,
+                move |controller: &gtk::EventControllerScroll, dx: f64, dy: f64| {
+                    obj.viewer_scrolled(controller, dx, dy)
+                }
+            ));
+            scroll_controller.connect_scroll_end(clone!(
+                #[weak]
+                obj,
+                move |_: &gtk::EventControllerScroll| obj.touchpad_swipe_ended()
+            ));
+            self.scrolled_window.add_controller(scroll_controller);
+
+            let zoom_gesture: gtk::GestureZoom = gtk::GestureZoom::new();
+
+            zoom_gesture.connect_begin(clone!(
+                #[weak]
+                obj,
+                move |_: &gtk::GestureZoom, _: Option<&gdk::EventSequence>| {
+                    obj.imp().pinch_zoom_level.set(obj.current_zoom_level());
+                }
+            ));
+            zoom_gesture.connect_scale_changed(clone!(
+                #[weak]
+                obj,
+                move |gesture: &gtk::GestureZoom, scale: f64| obj.image_pinched(gesture, scale)
+            ));
+            self.scrolled_window.add_controller(zoom_gesture);
+
+            // Swiping back with three fingers on a touchpad leaves the viewer.
+            let back_gesture: gtk::GestureSwipe = gtk::GestureSwipe::builder().n_points(3).build();
+
+            back_gesture.connect_swipe(clone!(
+                #[weak]
+                obj,
+                move |_: &gtk::GestureSwipe, velocity_x: f64, velocity_y: f64| {
+                    if velocity_x >= VIEWER_SWIPE_BACK_VELOCITY && velocity_x > velocity_y.abs() {
+                        obj.activate_action("viewer.exit", None)
+                            .expect("Action not found.");
+                    }
+                }
+            ));
+            obj.add_controller(back_gesture);
+
+            // The back and forward buttons of mice show the previous and next media. Going
+            // back from the first media is left to the window, which leaves the viewer.
+            let mouse_buttons_gesture: gtk::GestureClick = gtk::GestureClick::builder().button(0).build();
+
+            mouse_buttons_gesture.connect_pressed(clone!(
+                #[weak]
+                obj,
+                move |gesture: &gtk::GestureClick, _: i32, _: f64, _: f64| {
+                    let offset: isize = match gesture.current_button() {
+                        button if button == MOUSE_BACK_BUTTON => -1,
+                        button if button == MOUSE_FORWARD_BUTTON => 1,
+                        _ => return,
+                    };
+                    if obj.adjacent_item(offset).is_some() {
+                        gesture.set_state(gtk::EventSequenceState::Claimed);
+                        obj.show_adjacent(offset);
+                    }
+                }
+            ));
+            obj.add_controller(mouse_buttons_gesture);
+
             self.bottom_sheet.connect_notify_local(
                 Some("open"),
                 clone!(
@@ -127,7 +327,15 @@ mod imp {
         }
     }
 
-    impl WidgetImpl for MemoriesMediaViewer {}
+    impl WidgetImpl for MemoriesMediaViewer {
+        fn unmap(&self) {
+            // Leave immersive mode if the viewer page is popped while it is active.
+            if self.immersive.get() {
+                self.obj().set_immersive(false);
+            }
+            self.parent_unmap();
+        }
+    }
     impl BinImpl for MemoriesMediaViewer {}
     impl BreakpointBinImpl for MemoriesMediaViewer {}
 }
@@ -149,7 +357,9 @@ impl ViewerContentType {
 
         match ext_str {
             "svg" => ViewerContentType::VectorGraphics,
-            "png" | "jpg" | "jpeg" | "webp" | "heic" | "heif" => ViewerContentType::Image,
+            "png" | "jpg" | "jpeg" | "webp" | "heic" | "heif" | "avif" | "jxl" | "tif" | "tiff" => {
+                ViewerContentType::Image
+            }
             "mp4" | "webm" | "mkv" | "mov" | "avi" | "gif" => ViewerContentType::Video,
             _ => {
                 g_debug!("ViewerContentType", "from_ext() received invalid file extension.");
@@ -157,6 +367,137 @@ impl ViewerContentType {
             }
         }
     }
+
+    /// Returns the `ViewerContentType` that matches the extension of a file name,
+    /// or `None` if the file name has no extension, or an unknown extension.
+    pub fn from_file_name(file_name: &Path) -> Option<ViewerContentType> {
+        file_name
+            .extension()
+            .filter(|extension: &&OsStr| extension.to_str().is_some())
+            .map(ViewerContentType::from_ext)
+            .filter(|content_type: &ViewerContentType| !matches!(content_type, ViewerContentType::Invalid))
+    }
+
+    /// Returns the `ViewerContentType` that matches the MIME type of a supported
+    /// format. GIFs are played like videos, as they are in `from_ext()`.
+    pub fn from_mime_type(mime_type: &str) -> ViewerContentType {
+        match mime_type {
+            "image/svg+xml" => ViewerContentType::VectorGraphics,
+            "image/png" | "image/jpeg" | "image/webp" | "image/heif" | "image/heic" | "image/avif"
+            | "image/jxl" | "image/tiff" => ViewerContentType::Image,
+            "image/gif" | "video/mp4" | "video/webm" | "video/x-matroska" | "video/quicktime"
+            | "video/x-msvideo" => ViewerContentType::Video,
+            _ => ViewerContentType::Invalid,
+        }
+    }
+
+    /// Returns `true` if a MIME type is of photos or videos, including
+    /// formats that are not supported, which are opened in other apps.
+    pub fn is_media_mime_type(mime_type: &str) -> bool {
+        mime_type.starts_with("image/") || mime_type.starts_with("video/")
+    }
+
+    /// Returns the `ViewerContentType` that matches the magic bytes at the start of a
+    /// file, used if GIO can not tell the content type of a file from its contents.
+    pub fn from_magic(data: &[u8]) -> ViewerContentType {
+        let brand: Option<&[u8]> = match data.get(4..8) {
+            Some(b"ftyp") => data.get(8..12),
+            _ => None,
+        };
+        let text_start: &[u8] = data
+            .iter()
+            .position(|byte: &u8| !byte.is_ascii_whitespace())
+            .map_or(&[], |start: usize| &data[start..]);
+
+        let riff_format: Option<&[u8]> = match data.get(0..4) {
+            Some(b"RIFF") => data.get(8..12),
+            _ => None,
+        };
+
+        if data.starts_with(&[0xFF, 0xD8, 0xFF])
+            || data.starts_with(b"\x89PNG\r\n\x1A\n")
+            || riff_format == Some(b"WEBP")
+            // Little and big endian TIFF images.
+            || data.starts_with(b"II*\0")
+            || data.starts_with(b"MM\0*")
+            // JPEG XL codestreams and containers.
+            || data.starts_with(&[0xFF, 0x0A])
+            || data.starts_with(b"\0\0\0\x0CJXL \r\n\x87\n")
+        {
+            ViewerContentType::Image
+        } else if riff_format == Some(b"AVI ")
+            || data.starts_with(b"GIF87a")
+            || data.starts_with(b"GIF89a")
+            // Matroska and WebM videos.
+            || data.starts_with(&[0x1A, 0x45, 0xDF, 0xA3])
+        {
+            ViewerContentType::Video
+        } else if let Some(brand) = brand {
+            // HEIF and AVIF images and MP4 or QuickTime videos are all ISO media files.
+            match brand {
+                b"heic" | b"heix" | b"heim" | b"heis" | b"mif1" | b"avif" | b"avis" => {
+                    ViewerContentType::Image
+                }
+                b"isom" | b"iso2" | b"mp41" | b"mp42" | b"avc1" | b"M4V " | b"qt  " => {
+                    ViewerContentType::Video
+                }
+                _ => ViewerContentType::Invalid,
+            }
+        } else if text_start.starts_with(b"<svg")
+            || (text_start.starts_with(b"<?xml") && data.windows(4).any(|window: &[u8]| window == b"<svg"))
+        {
+            ViewerContentType::VectorGraphics
+        } else {
+            ViewerContentType::Invalid
+        }
+    }
+
+    /// Tells the `ViewerContentType` of a file from its contents, for files that have
+    /// a missing or wrong extension. Only the start of the file is read.
+    pub async fn sniff(file: &gio::File) -> ViewerContentType {
+        let data: glib::Bytes = match file.read_future(glib::Priority::LOW).await {
+            Ok(stream) => match stream
+                .read_bytes_future(CONTENT_SNIFF_BYTES, glib::Priority::LOW)
+                .await
+            {
+                Ok(data) => data,
+                Err(e) => {
+                    g_debug!("ViewerContentType", "Failed to read '{}': {}", file.uri(), e);
+                    return ViewerContentType::Invalid;
+                }
+            },
+            Err(e) => {
+                g_debug!("ViewerContentType", "Failed to open '{}': {}", file.uri(), e);
+                return ViewerContentType::Invalid;
+            }
+        };
+        let (content_type, uncertain) = gio::content_type_guess(None::<&Path>, &data);
+
+        if !uncertain {
+            let content_type: ViewerContentType = gio::content_type_get_mime_type(&content_type)
+                .map_or(ViewerContentType::Invalid, |mime_type: glib::GString| {
+                    ViewerContentType::from_mime_type(&mime_type)
+                });
+
+            if !matches!(content_type, ViewerContentType::Invalid) {
+                return content_type;
+            }
+        }
+        ViewerContentType::from_magic(&data)
+    }
+
+    /// Returns the `ViewerContentType` of a file. It is told by the file extension
+    /// if it is known, which is fast, or else by sniffing the contents of the file.
+    pub async fn for_file(file: &gio::File) -> ViewerContentType {
+        match file
+            .basename()
+            .as_deref()
+            .and_then(ViewerContentType::from_file_name)
+        {
+            Some(content_type) => content_type,
+            None => ViewerContentType::sniff(file).await,
+        }
+    }
 }
 
 glib::wrapper! {
@@ -170,17 +511,59 @@ impl MemoriesMediaViewer {
         glib::Object::new()
     }
 
-    fn window(&self) -> MemoriesApplicationWindow {
+    /// Opens a media file in a new media viewer page pushed onto the window,
+    /// unless a page is already pushed on top of the window. Returns the new
+    /// media viewer, so that its properties can be filled in by the caller.
+    pub fn open(
+        win: &MemoriesApplicationWindow,
+        file: &gio::File,
+        content_type: &ViewerContentType,
+    ) -> Option<MemoriesMediaViewer> {
+        let nav_view: adw::NavigationView = win.imp().window_navigation.clone();
+        let current_nav_page: adw::NavigationPage = nav_view.visible_page()?;
+
+        // Do not proceed to push a new nav page if one is already open.
+        // Media can also be opened from the page of a folder.
+        if !matches!(current_nav_page.tag().unwrap().as_str(), "window" | "folder") {
+            return None;
+        }
+        Some(Self::push_new(&nav_view, file, content_type))
+    }
+
+    /// Pushes a new media viewer page for a media file onto a navigation view,
+    /// which must already be in the widget tree of a window.
+    pub(super) fn push_new(
+        nav_view: &adw::NavigationView,
+        file: &gio::File,
+        content_type: &ViewerContentType,
+    ) -> MemoriesMediaViewer {
+        let viewer_content: MemoriesMediaViewer = MemoriesMediaViewer::default();
+        viewer_content.set_content_type(content_type);
+        viewer_content.set_content_file(file);
+
+        let nav_page: adw::NavigationPage = viewer_content.wrap_in_navigation_page();
+        viewer_content.update_title();
+
+        nav_view.push(&nav_page);
+
+        // See docstring of setup_gactions() for why we're calling it here.
+        viewer_content.setup_gactions();
+        viewer_content
+    }
+
+    /// Returns the window of the viewer, which is either the application
+    /// window or a viewer window opened with the `viewer.new_window` action.
+    fn window(&self) -> gtk::Window {
         self.root()
             .expect("Must be in a GtkApplicationWindow.")
             .downcast()
-            .expect("Failed to downcast to MemoriesApplicationWindow.")
+            .expect("Failed to downcast to GtkWindow.")
     }
 
     /// This function is public so that it can be called once we
     /// are placed in the widget tree and can access the window.
     pub fn setup_gactions(&self) {
-        let win: MemoriesApplicationWindow = self.window();
+        let win: gtk::Window = self.window();
         let action_group = gio::SimpleActionGroup::new();
 
         let exit_viewer_action = gio::ActionEntry::builder("exit")
@@ -188,8 +571,68 @@ impl MemoriesMediaViewer {
                 #[weak(rename_to = this)]
                 self,
                 move |_: &gio::SimpleActionGroup, _, _| {
-                    this.activate_action("navigation.pop", None)
-                        .expect("Action not found.");
+                    // The viewer is the only page of a viewer window, so exiting it closes the window.
+                    match this.window().downcast::<MemoriesViewerWindow>() {
+                        Ok(viewer_window) => viewer_window.close(),
+                        Err(_) => this
+                            .activate_action("navigation.pop", None)
+                            .expect("Action not found."),
+                    }
+                }
+            ))
+            .build();
+
+        // Opens the media file in a separate window, to compare it side by side with another one.
+        let new_window_action = gio::ActionEntry::builder("new_window")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    let Some(file) = this.content_file() else {
+                        return;
+                    };
+                    let content_type: ViewerContentType = file
+                        .path()
+                        .and_then(|path| path.extension().map(ViewerContentType::from_ext))
+                        .unwrap_or(ViewerContentType::Invalid);
+
+                    MemoriesViewerWindow::new(&MemoriesApplication::default(), &file, &content_type)
+                        .present();
+                }
+            ))
+            .build();
+
+        // Lets the user pick an application to edit the media file with, such as GIMP.
+        // The viewer and the thumbnail are refreshed once the editor saves the file.
+        let edit_with_action = gio::ActionEntry::builder("edit_with")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    let Some(file) = this.content_file() else {
+                        return;
+                    };
+                    let launcher: gtk::FileLauncher = gtk::FileLauncher::new(Some(&file));
+                    launcher.set_always_ask(true);
+
+                    launcher.launch(
+                        Some(&this.window()),
+                        None::<&gio::Cancellable>,
+                        clone!(
+                            #[strong]
+                            file,
+                            move |result: Result<(), glib::Error>| {
+                                if let Err(glib_error) = result {
+                                    g_warning!(
+                                        "Viewer",
+                                        "Failed to open '{}' in an external editor: {}",
+                                        file.uri(),
+                                        glib_error
+                                    );
+                                }
+                            }
+                        ),
+                    );
                 }
             ))
             .build();
@@ -212,125 +655,1550 @@ impl MemoriesMediaViewer {
             ))
             .build();
 
-        action_group.add_action_entries([exit_viewer_action, properties_action]);
-        win.insert_action_group("viewer", Some(&action_group));
-    }
+        let is_favorite: bool = self
+            .content_file()
+            .is_some_and(|file: gio::File| MemoriesApplication::default().is_favorite(&file));
 
-    /// Sets the content type setting for the viewer page.
-    /// The `ViewerContentType` enum given directly correlates
-    /// to a stack page that has the proper widget for the content.
-    pub fn set_content_type(&self, content_type: &ViewerContentType) {
-        match content_type {
-            ViewerContentType::VectorGraphics => self.imp().viewer_stack.set_visible_child_name("image"),
-            ViewerContentType::Image => self.imp().viewer_stack.set_visible_child_name("image"),
-            ViewerContentType::Video => self.imp().viewer_stack.set_visible_child_name("video"),
-            _ => g_debug!("Viewer", "Received invalid ViewerContentType enum!"),
-        }
-    }
+        let favorite_action = gio::ActionEntry::builder("favorite")
+            .state(is_favorite.to_variant())
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, action: &gio::SimpleAction, _| {
+                    let Some(file) = this.content_file() else {
+                        return;
+                    };
+                    let new_state: bool = !action
+                        .state()
+                        .and_then(|state| state.get::<bool>())
+                        .unwrap_or(false);
 
-    pub fn set_content_file(&self, file: &gio::File) {
-        let content_file_basename: String = file.basename().unwrap().to_string_lossy().to_string();
+                    MemoriesApplication::default().set_favorite(&file, new_state);
+                    action.set_state(&new_state.to_variant());
+                }
+            ))
+            .build();
 
-        match self.imp().viewer_stack.visible_child_name().unwrap().as_str() {
-            "render" => self.imp().viewer_picture.set_file(Some(file)),
-            "image" => {
-                glib::spawn_future_local(clone!(
-                    #[weak(rename_to = this)]
-                    self,
-                    #[strong]
-                    file,
-                    async move {
-                        #[allow(unused_mut)]
-                        let mut glycin_loader: glycin::Loader = glycin::Loader::new(file);
+        let rating: i32 = self
+            .content_file()
+            .map(|file: gio::File| MemoriesApplication::default().rating(&file) as i32)
+            .unwrap_or(0);
 
-                        #[cfg(feature = "disable-glycin-sandbox")]
-                        glycin_loader.sandbox_mechanism(Some(SandboxMechanism::NotSandboxed));
+        let rate_action = gio::ActionEntry::builder("rate")
+            .parameter_type(Some(&i32::static_variant_type()))
+            .state(rating.to_variant())
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup,
+                      action: &gio::SimpleAction,
+                      parameter: Option<&glib::Variant>| {
+                    let Some(file) = this.content_file() else {
+                        return;
+                    };
+                    let rating: i32 = parameter.and_then(|p| p.get::<i32>()).unwrap_or(0).clamp(0, 5);
 
-                        let image: glycin::Image = glycin_loader.load().await.expect("FIXME");
-                        let texture: gdk::Texture = image.next_frame().await.expect("FIXME").texture();
+                    MemoriesApplication::default().set_rating(&file, rating as u32);
+                    action.set_state(&rating.to_variant());
+                }
+            ))
+            .build();
 
-                        this.imp()
-                            .viewer_picture
-                            .update_property(&[gtk::accessible::Property::Label(&content_file_basename)]);
+        let similar_action = gio::ActionEntry::builder("similar")
+            .state(false.to_variant())
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, action: &gio::SimpleAction, _| {
+                    let new_state: bool = !this.imp().similar_revealer.reveals_child();
 
-                        this.imp().viewer_picture.set_paintable(Some(&texture));
+                    if new_state {
+                        this.update_similar_photos();
                     }
-                ));
-            }
-            "video" => {
-                self.imp().viewer_video.set_file(Some(file));
-
-                let video_overlay: gtk::Widget = self.imp().viewer_video.first_child().unwrap();
-                let graphics_offload: gtk::Widget = video_overlay.first_child().unwrap();
-                let video_picture: gtk::Widget = graphics_offload.property("child");
+                    this.imp().similar_revealer.set_reveal_child(new_state);
+                    action.set_state(&new_state.to_variant());
+                }
+            ))
+            .build();
 
-                video_picture.update_property(&[gtk::accessible::Property::Label(&content_file_basename)]);
-            }
-            _ => g_error!("Viewer", "Found unexpected visible child name in viewer stack."),
-        }
-    }
+        let adjust_date_action = gio::ActionEntry::builder("adjust_date")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    if let Some(file) = this.content_file() {
+                        MemoriesDateTimeDialog::new(&[file]).present(Some(&this));
+                    }
+                }
+            ))
+            .build();
 
-    /// Returns a new `AdwNavigationPage` object that
-    /// has its child set to the `&self` GObject.
-    pub fn wrap_in_navigation_page(&self) -> adw::NavigationPage {
-        let new_navigation_page: adw::NavigationPage = adw::NavigationPage::builder()
-            .title(gettext("Loading Content"))
-            .child(self)
+        let cast_action = gio::ActionEntry::builder("cast")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    if let Some(file) = this.content_file() {
+                        MemoriesCastDialog::new(&file).present(Some(&this));
+                    }
+                }
+            ))
             .build();
-        new_navigation_page
-    }
 
-    fn reveal_overlay_controls(&self) {
-        self.imp().nav_overlay_revealer.set_reveal_child(true);
-        self.imp().zoom_overlay_revealer.set_reveal_child(true);
+        let compress_action = gio::ActionEntry::builder("compress")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    if let Some(file) = this.content_file() {
+                        MemoriesCompressDialog::new(&file).present(Some(&this));
+                    }
+                }
+            ))
+            .build();
 
-        if let Some(src_id) = self.imp().overlay_timeout_source.replace(None) {
-            src_id.remove();
-        }
+        let tags_action = gio::ActionEntry::builder("tags")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    if let Some(file) = this.content_file() {
+                        MemoriesTagsDialog::new(&[file]).present(Some(&this));
+                    }
+                }
+            ))
+            .build();
 
-        let timeout: glib::SourceId = glib::timeout_add_local_once(
-            Duration::from_secs(3),
-            clone!(
+        let archive_action = gio::ActionEntry::builder("archive")
+            .activate(clone!(
                 #[weak(rename_to = this)]
                 self,
-                move || {
-                    this.imp().nav_overlay_revealer.set_reveal_child(false);
-                    this.imp().zoom_overlay_revealer.set_reveal_child(false);
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    if let Some(file) = this.content_file() {
+                        MemoriesApplication::default().set_archived(&[file], true);
+                        this.activate_action("viewer.exit", None)
+                            .expect("Action not found.");
+                    }
+                }
+            ))
+            .build();
 
-                    // If this closure is executed, `overlay_timeout_source` is
-                    // guaranteed to contain a `Some` option value, so we can unwrap().
-                    this.imp().overlay_timeout_source.replace(None).unwrap().remove();
+        let private_action = gio::ActionEntry::builder("private")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    if let Some(file) = this.content_file() {
+                        MemoriesApplication::default().set_private(&[file], true);
+                        this.activate_action("viewer.exit", None)
+                            .expect("Action not found.");
+                    }
                 }
-            ),
-        );
-        self.imp().overlay_timeout_source.set(Some(timeout));
-    }
+            ))
+            .build();
 
-    #[template_callback]
-    fn overlay_motion_handler(&self, x: f64, y: f64) {
-        // After the overlay is hidden again, the motion controller emits a motion
-        // event again, but with rounded values. Because of this, we have to get the
-        // delta of the mouse movement to the last movement detected and only reveal
-        // the overlay if the delta for **both** x and y are above 1.0 units.
+        let trash_action = gio::ActionEntry::builder("trash")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    let Some(file) = this.content_file() else {
+                        return;
+                    };
+                    glib::spawn_future_local(clone!(
+                        #[weak]
+                        this,
+                        async move {
+                            match MemoriesApplication::default().trash_file(&file).await {
+                                Ok(()) => {
+                                    feedback::emit(FeedbackEvent::DeleteConfirmed);
+                                    this.activate_action("viewer.exit", None)
+                                        .expect("Action not found.");
+                                }
+                                Err(err) => {
+                                    g_warning!("Viewer", "Failed to move '{}' to trash: {}", file.uri(), err);
 
-        let last_x: f64 = self.imp().motion_last_x.replace(x);
-        let last_y: f64 = self.imp().motion_last_y.replace(y);
+                                    let dialog: adw::AlertDialog = adw::AlertDialog::new(
+                                        Some(&gettext("Could Not Move to Trash")),
+                                        Some(err.message()),
+                                    );
+                                    dialog.add_response("close", &gettext("_Close"));
+                                    dialog.present(Some(&this));
+                                }
+                            }
+                        }
+                    ));
+                }
+            ))
+            .build();
 
-        if last_x != 0.0 || last_y != 0.0 {
-            let motion_delta_x: f64 = (last_x - x).abs();
-            let motion_delta_y: f64 = (last_y - y).abs();
+        let immersive_action = gio::ActionEntry::builder("immersive")
+            .state(false.to_variant())
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, action: &gio::SimpleAction, _| {
+                    let new_state: bool = !this.imp().immersive.get();
 
-            if (motion_delta_x <= 1_f64) && (motion_delta_y <= 1_f64) {
-                return;
-            }
-        }
-        self.reveal_overlay_controls();
-    }
+                    this.set_immersive(new_state);
+                    action.set_state(&new_state.to_variant());
+                }
+            ))
+            .build();
 
-    #[template_callback]
-    fn touch_gesture_handler(&self, _: i32, _: f64, _: f64) {
-        self.reveal_overlay_controls();
-    }
+        let toggle_mute_action = gio::ActionEntry::builder("toggle_mute")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    if let Some(media_stream) = this.imp().viewer_video.media_stream() {
+                        media_stream.set_muted(!media_stream.is_muted());
+                    }
+                }
+            ))
+            .build();
+
+        let panorama_action = gio::ActionEntry::builder("panorama")
+            .state(false.to_variant())
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, action: &gio::SimpleAction, _| {
+                    let new_state: bool = !action.state().and_then(|state| state.get()).unwrap_or(false);
+                    this.set_panorama(new_state);
+                }
+            ))
+            .build();
+
+        let zoom_in_action = gio::ActionEntry::builder("zoom_in")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    this.set_zoom_level(this.current_zoom_level() * VIEWER_ZOOM_STEP);
+                }
+            ))
+            .build();
+
+        let zoom_out_action = gio::ActionEntry::builder("zoom_out")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    this.set_zoom_level(this.current_zoom_level() / VIEWER_ZOOM_STEP);
+                }
+            ))
+            .build();
+
+        let zoom_best_fit_action = gio::ActionEntry::builder("zoom_best_fit")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    this.set_zoom_level(0.0);
+                }
+            ))
+            .build();
+
+        let previous_action = gio::ActionEntry::builder("previous")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    this.show_adjacent(-1);
+                }
+            ))
+            .build();
+
+        let next_action = gio::ActionEntry::builder("next")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    this.show_adjacent(1);
+                }
+            ))
+            .build();
+
+        let export_action = gio::ActionEntry::builder("export")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    if let Some(file) = this.imp().content_file.borrow().as_ref() {
+                        MemoriesExportDialog::new(file).present(Some(&this));
+                    }
+                }
+            ))
+            .build();
+
+        let print_action = gio::ActionEntry::builder("print")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gio::SimpleActionGroup, _, _| {
+                    let Some(texture) = this
+                        .imp()
+                        .viewer_picture
+                        .paintable()
+                        .and_downcast::<gdk::Texture>()
+                    else {
+                        return;
+                    };
+                    let job_name: String = this
+                        .imp()
+                        .content_file
+                        .borrow()
+                        .as_ref()
+                        .and_then(|file: &gio::File| file.basename())
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+
+                    print_texture(&texture, &job_name, Some(&this.window()));
+                }
+            ))
+            .build();
+
+        action_group.add_action_entries([
+            exit_viewer_action, new_window_action, edit_with_action, properties_action, favorite_action,
+            rate_action, similar_action, adjust_date_action, cast_action, compress_action, tags_action,
+            archive_action, private_action, trash_action, immersive_action, toggle_mute_action,
+            panorama_action, zoom_in_action, zoom_out_action, zoom_best_fit_action, previous_action,
+            next_action, export_action, print_action,
+        ]);
+
+        #[cfg(feature = "use-face-detection")]
+        {
+            let faces_action = gio::ActionEntry::builder("faces")
+                .state(false.to_variant())
+                .activate(clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    move |_: &gio::SimpleActionGroup, action: &gio::SimpleAction, _| {
+                        let new_state: bool = !this.imp().faces_area.is_visible();
+
+                        this.imp().faces_area.set_visible(new_state);
+                        action.set_state(&new_state.to_variant());
+                    }
+                ))
+                .build();
+
+            action_group.add_action_entries([faces_action]);
+
+            self.imp().faces_area.set_draw_func(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gtk::DrawingArea, cr: &gtk::cairo::Context, width: i32, height: i32| {
+                    this.draw_faces(cr, width, height);
+                }
+            ));
+        }
+
+        win.insert_action_group("viewer", Some(&action_group));
+        self.imp().action_group.replace(Some(action_group));
+        self.update_content_actions();
+        self.update_panorama_action();
+
+        // Leaving fullscreen from the window (e.g. by pressing Escape)
+        // also leaves immersive mode, so the viewer is not left without
+        // its header bar in a non-fullscreen window.
+        win.connect_fullscreened_notify(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |win: &gtk::Window| {
+                if !win.is_fullscreen() && this.imp().immersive.get() {
+                    this.activate_action("viewer.immersive", None)
+                        .expect("Action not found.");
+                }
+            }
+        ));
+    }
+
+    /// Enters or leaves the viewer's immersive mode. While immersive,
+    /// the window is fullscreened, the properties sidebar is closed,
+    /// and the header bar, overlay controls, and cursor are hidden
+    /// after a period of inactivity.
+    fn set_immersive(&self, immersive: bool) {
+        if self.imp().immersive.replace(immersive) == immersive {
+            return;
+        }
+        let win: gtk::Window = self.window();
+
+        // Let the content sit below the header and bottom bars, so it does not resize when they are hidden.
+        let toolbar_view: &adw::ToolbarView = &self.imp().toolbar_view;
+        toolbar_view.set_extend_content_to_top_edge(immersive);
+        toolbar_view.set_extend_content_to_bottom_edge(immersive);
+
+        if immersive {
+            self.imp().immersive_fullscreened.set(!win.is_fullscreen());
+            win.fullscreen();
+
+            if self.imp().split_view.shows_sidebar() {
+                self.activate_action("viewer.properties", None)
+                    .expect("Action not found.");
+            }
+        } else {
+            if self.imp().immersive_fullscreened.replace(false) {
+                win.unfullscreen();
+            }
+            self.imp().toolbar_view.set_reveal_top_bars(true);
+            self.imp().toolbar_view.set_reveal_bottom_bars(true);
+            self.set_cursor(None);
+        }
+        self.reveal_overlay_controls(true);
+    }
+
+    /// Sets the content type setting for the viewer page.
+    /// The `ViewerContentType` enum given directly correlates
+    /// to a stack page that has the proper widget for the content.
+    pub fn set_content_type(&self, content_type: &ViewerContentType) {
+        match content_type {
+            ViewerContentType::VectorGraphics => self.imp().viewer_stack.set_visible_child_name("image"),
+            ViewerContentType::Image => self.imp().viewer_stack.set_visible_child_name("image"),
+            ViewerContentType::Video => self.imp().viewer_stack.set_visible_child_name("video"),
+            _ => g_debug!("Viewer", "Received invalid ViewerContentType enum!"),
+        }
+    }
+
+    /// Returns the media file displayed by the viewer, if set.
+    pub fn content_file(&self) -> Option<gio::File> {
+        self.imp().content_file.borrow().clone()
+    }
+
+    pub fn set_content_file(&self, file: &gio::File) {
+        let content_file_basename: String = file.basename().unwrap().to_string_lossy().to_string();
+        self.imp().content_file.replace(Some(file.clone()));
+        self.next_load_generation();
+        MemoriesLibraryListModel::remember_recent_folder(file);
+
+        #[cfg(feature = "use-face-detection")]
+        self.load_faces(file);
+
+        self.monitor_content_file(file);
+
+        // Advances the media shown on the TV along with the viewer.
+        cast::cast_file(file);
+
+        match self.imp().viewer_stack.visible_child_name().unwrap().as_str() {
+            "render" => self.imp().viewer_picture.set_file(Some(file)),
+            "image" => self.load_image(file),
+            "video" => {
+                self.imp().viewer_video.set_file(Some(file));
+                self.restore_video_volume();
+
+                let video_overlay: gtk::Widget = self.imp().viewer_video.first_child().unwrap();
+                let graphics_offload: gtk::Widget = video_overlay.first_child().unwrap();
+                let video_picture: gtk::Widget = graphics_offload.property("child");
+
+                video_picture.update_property(&[gtk::accessible::Property::Label(&content_file_basename)]);
+            }
+            _ => g_warning!("Viewer", "Found unexpected visible child name in viewer stack."),
+        }
+    }
+
+    /// Sets the volume of the video to the volume that the last video was played
+    /// at, and remembers the volume and mute state that the user chooses next.
+    /// Videos that start playing on their own are muted if `autoplay-muted` is set.
+    fn restore_video_volume(&self) {
+        let Some(media_stream) = self.imp().viewer_video.media_stream() else {
+            return;
+        };
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+        let autoplay_muted: bool =
+            gsettings.boolean("autoplay-videos") && gsettings.boolean("autoplay-muted");
+
+        media_stream.set_volume(gsettings.double("video-volume"));
+        media_stream.set_muted(gsettings.boolean("video-muted") || autoplay_muted);
+
+        media_stream.connect_volume_notify(clone!(
+            #[strong]
+            gsettings,
+            move |media_stream: &gtk::MediaStream| {
+                if let Err(err) = gsettings.set_double("video-volume", media_stream.volume()) {
+                    g_warning!("Viewer", "Failed to save the video volume: {}", err);
+                }
+            }
+        ));
+        media_stream.connect_muted_notify(move |media_stream: &gtk::MediaStream| {
+            if let Err(err) = gsettings.set_boolean("video-muted", media_stream.is_muted()) {
+                g_warning!("Viewer", "Failed to save the video mute state: {}", err);
+            }
+        });
+    }
+
+    /// Watches the media file for changes, so that the viewer shows the new contents
+    /// once an external editor saves the file. Editors may replace the file by
+    /// moving a new one over it, so moves are watched as well.
+    fn monitor_content_file(&self, file: &gio::File) {
+        if let Some(old_monitor) = self.imp().file_monitor.take() {
+            old_monitor.cancel();
+        }
+        let monitor: gio::FileMonitor =
+            match file.monitor_file(gio::FileMonitorFlags::WATCH_MOVES, None::<&gio::Cancellable>) {
+                Ok(monitor) => monitor,
+                Err(glib_error) => {
+                    g_debug!("Viewer", "Failed to monitor the media file: {}", glib_error);
+                    return;
+                }
+            };
+        monitor.connect_changed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_: &gio::FileMonitor,
+                  _: &gio::File,
+                  _: Option<&gio::File>,
+                  event: gio::FileMonitorEvent| {
+                if matches!(
+                    event,
+                    gio::FileMonitorEvent::ChangesDoneHint
+                        | gio::FileMonitorEvent::Created
+                        | gio::FileMonitorEvent::MovedIn
+                        | gio::FileMonitorEvent::Renamed
+                ) {
+                    this.schedule_reload();
+                }
+            }
+        ));
+        self.imp().file_monitor.replace(Some(monitor));
+    }
+
+    /// Reloads the media file once it was not changed for a moment, and
+    /// notifies the library so that its thumbnail is generated again.
+    fn schedule_reload(&self) {
+        if let Some(src_id) = self.imp().reload_timeout_source.take() {
+            src_id.remove();
+        }
+        let timeout: glib::SourceId = glib::timeout_add_local_once(
+            Duration::from_millis(EXTERNAL_EDIT_RELOAD_DELAY),
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                move || {
+                    this.imp().reload_timeout_source.replace(None);
+
+                    let Some(file) = this.content_file() else {
+                        return;
+                    };
+                    g_debug!("Viewer", "Reloading '{}' after it was changed.", file.uri());
+
+                    this.set_content_file(&file);
+                    this.imp().properties_widget.update_file_details(&file);
+                    MemoriesApplication::default()
+                        .scan_service()
+                        .notify_file_changed(&file);
+                }
+            ),
+        );
+        self.imp().reload_timeout_source.replace(Some(timeout));
+    }
+
+    /// Fills the similar photos strip with the photos that look alike to the photo
+    /// in the viewer, as found by the perceptual hashes of the scan service.
+    fn update_similar_photos(&self) {
+        let similar_box: &gtk::Box = &self.imp().similar_box;
+
+        while let Some(child) = similar_box.first_child() {
+            similar_box.remove(&child);
+        }
+        let similar_photos: Vec<gio::File> = self
+            .content_file()
+            .map(|file: gio::File| {
+                MemoriesApplication::default()
+                    .scan_service()
+                    .similar_photos(&file)
+            })
+            .unwrap_or_default();
+
+        self.imp()
+            .similar_stack
+            .set_visible_child_name(if similar_photos.is_empty() {
+                "empty"
+            } else {
+                "photos"
+            });
+
+        for file in similar_photos {
+            let basename: String = file
+                .basename()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            let picture: gtk::Picture = gtk::Picture::builder()
+                .content_fit(gtk::ContentFit::Cover)
+                .width_request(SIMILAR_THUMBNAIL_SIZE)
+                .height_request(SIMILAR_THUMBNAIL_SIZE)
+                .build();
+            MemoriesJustifiedView::load_preview(&picture, &file);
+
+            let button: gtk::Button = gtk::Button::builder()
+                .child(&picture)
+                .tooltip_text(&basename)
+                .css_classes(["flat"])
+                .build();
+            button.update_property(&[gtk::accessible::Property::Label(&basename)]);
+
+            button.connect_clicked(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gtk::Button| {
+                    this.show_file(&file, &ViewerContentType::Image);
+                    this.imp().properties_widget.update_file_details(&file);
+                }
+            ));
+            similar_box.append(&button);
+        }
+    }
+
+    /// Shows other media in the viewer, such as one of the similar photos
+    /// or the next media in the library, without pushing a new viewer page.
+    fn show_file(&self, file: &gio::File, content_type: &ViewerContentType) {
+        // Stops the video that was shown before, if any.
+        self.imp().viewer_video.set_file(None::<&gio::File>);
+
+        self.set_content_type(content_type);
+        self.set_content_file(file);
+        self.update_content_actions();
+        self.update_title();
+
+        if let Some(action_group) = self.imp().action_group.borrow().as_ref() {
+            let memories: MemoriesApplication = MemoriesApplication::default();
+
+            if let Some(action) = action_group
+                .lookup_action("favorite")
+                .and_downcast::<gio::SimpleAction>()
+            {
+                action.set_state(&memories.is_favorite(file).to_variant());
+            }
+            if let Some(action) = action_group
+                .lookup_action("rate")
+                .and_downcast::<gio::SimpleAction>()
+            {
+                action.set_state(&(memories.rating(file) as i32).to_variant());
+            }
+        }
+        self.update_similar_photos();
+    }
+
+    /// Loads the image file with glycin in the background. A loading page is shown
+    /// while the image is decoded, and an error page with a retry button is
+    /// shown if glycin fails to load the image, instead of panicking.
+    fn load_image(&self, file: &gio::File) {
+        let content_file_basename: String = file.basename().unwrap().to_string_lossy().to_string();
+        let generation: u64 = self.next_load_generation();
+
+        self.imp().image_stack.set_visible_child_name("loading");
+        self.imp().loading_spinner.start();
+        self.set_embedded_images(Vec::new());
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[strong]
+            file,
+            async move {
+                #[allow(unused_mut)]
+                let mut glycin_loader: glycin::Loader = glycin::Loader::new(file);
+
+                #[cfg(feature = "disable-glycin-sandbox")]
+                glycin_loader.sandbox_mechanism(Some(SandboxMechanism::NotSandboxed));
+
+                let paintable: Result<gdk::Paintable, glycin::ErrorCtx> = async {
+                    let image: glycin::Image<'static> = glycin_loader.load().await?;
+                    let pixels: u64 = image.info().width as u64 * image.info().height as u64;
+
+                    #[cfg(feature = "use-photo-sphere")]
+                    if this.imp().load_generation.get() == generation {
+                        this.imp().is_photo_sphere.set(
+                            image
+                                .info()
+                                .details
+                                .xmp
+                                .as_ref()
+                                .and_then(|data| data.get_full().ok())
+                                .is_some_and(|xmp_bytes: Vec<u8>| is_equirectangular(&xmp_bytes)),
+                        );
+                    }
+
+                    // Huge images are drawn in tiles, instead of decoding them into a single texture.
+                    if pixels > TILED_RENDERING_MIN_PIXELS {
+                        Ok::<gdk::Paintable, glycin::ErrorCtx>(
+                            MemoriesTiledPaintable::new(image).await?.upcast(),
+                        )
+                    } else {
+                        Ok(image.next_frame().await?.texture().upcast())
+                    }
+                }
+                .await;
+
+                // Other media was shown in the meantime, which is loaded separately.
+                if this.imp().load_generation.get() != generation {
+                    return;
+                }
+                this.imp().loading_spinner.stop();
+
+                match paintable {
+                    Ok(paintable) => {
+                        this.imp()
+                            .viewer_picture
+                            .update_property(&[gtk::accessible::Property::Label(&content_file_basename)]);
+
+                        this.imp().viewer_picture.set_paintable(Some(&paintable));
+                        this.imp().primary_paintable.replace(Some(paintable.clone()));
+                        this.set_panorama(false);
+                        this.update_panorama_action();
+                        this.imp().image_stack.set_visible_child_name("picture");
+                        this.load_embedded_images(&file);
+
+                        #[cfg(feature = "use-photo-sphere")]
+                        this.show_photo_sphere(paintable.downcast_ref::<gdk::Texture>());
+                    }
+                    Err(err) => {
+                        g_warning!("Viewer", "Failed to load '{}': {}", content_file_basename, err);
+
+                        this.imp()
+                            .error_status_page
+                            .set_description(Some(&glycin_error_message(&err)));
+                        this.imp().image_stack.set_visible_child_name("error");
+                    }
+                }
+            }
+        ));
+    }
+
+    /// Finds the alternate images embedded in a JPEG file, such as the depth maps
+    /// and original photos that phones store after the primary image of portraits.
+    fn load_embedded_images(&self, file: &gio::File) {
+        let is_jpeg: bool = file
+            .path()
+            .and_then(|path| path.extension().map(|ext: &OsStr| ext.to_ascii_lowercase()))
+            .is_some_and(|ext| ext == "jpg" || ext == "jpeg");
+
+        if !is_jpeg {
+            return;
+        }
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[strong]
+            file,
+            async move {
+                let contents: glib::Bytes = match file.load_bytes_future().await {
+                    Ok((contents, _)) => contents,
+                    Err(err) => {
+                        g_debug!("Viewer", "Failed to read embedded images: {}", err);
+                        return;
+                    }
+                };
+                let images: Vec<glib::Bytes> = gio::spawn_blocking(move || {
+                    embedded_jpeg_images(&contents)
+                        .into_iter()
+                        .map(|range| glib::Bytes::from_bytes(&contents, range))
+                        .collect()
+                })
+                .await
+                .unwrap_or_default();
+
+                // Another file may have been opened while the file was read.
+                if this.content_file().as_ref() == Some(&file) {
+                    this.set_embedded_images(images);
+                }
+            }
+        ));
+    }
+
+    /// Sets the embedded images of the current image, and creates a button
+    /// for each of them next to a button for the primary image. The buttons
+    /// are only shown if the image has embedded images.
+    fn set_embedded_images(&self, images: Vec<glib::Bytes>) {
+        let renditions_box: &gtk::Box = &self.imp().renditions_box;
+
+        while let Some(child) = renditions_box.first_child() {
+            renditions_box.remove(&child);
+        }
+        renditions_box.set_visible(!images.is_empty());
+
+        let mut first_button: Option<gtk::ToggleButton> = None;
+
+        for index in 0..=images.len() {
+            let label: String = match index {
+                0 => gettext("Original"),
+                _ => gettext_f("Embedded Image {N}", &[("N", &index.to_string())]),
+            };
+            let button: gtk::ToggleButton = gtk::ToggleButton::builder()
+                .label(label)
+                .active(index == 0)
+                .css_classes(["osd"])
+                .build();
+
+            button.set_group(first_button.as_ref());
+            button.connect_toggled(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |button: &gtk::ToggleButton| {
+                    if button.is_active() {
+                        this.show_embedded_image(index);
+                    }
+                }
+            ));
+            renditions_box.append(&button);
+            first_button.get_or_insert(button);
+        }
+        self.imp().embedded_images.replace(images);
+    }
+
+    /// Shows the embedded image at the given index, counting from one,
+    /// or the primary image if the index is zero.
+    fn show_embedded_image(&self, index: usize) {
+        let paintable: Option<gdk::Paintable> = match index {
+            0 => self.imp().primary_paintable.borrow().clone(),
+            _ => {
+                let Some(bytes) = self.imp().embedded_images.borrow().get(index - 1).cloned() else {
+                    return;
+                };
+                match gdk::Texture::from_bytes(&bytes) {
+                    Ok(texture) => Some(texture.upcast()),
+                    Err(err) => {
+                        g_warning!("Viewer", "Failed to decode embedded image: {}", err);
+                        return;
+                    }
+                }
+            }
+        };
+        self.imp().viewer_picture.set_paintable(paintable.as_ref());
+        self.set_panorama(false);
+        self.update_panorama_action();
+    }
+
+    /// Loads the bounds of the faces found in the file from the face index.
+    #[cfg(feature = "use-face-detection")]
+    fn load_faces(&self, file: &gio::File) {
+        let uri: glib::GString = file.uri();
+        let faces: Vec<graphene::Rect> = FaceIndex::load()
+            .faces_of(uri.as_str())
+            .map(|face: &Face| graphene::Rect::new(face.x, face.y, face.width, face.height))
+            .collect();
+
+        self.imp().faces.replace(faces);
+        self.imp().faces_area.queue_draw();
+    }
+
+    /// Draws the outlines of the faces over the image. The drawing area has
+    /// the size of the picture, which fits the image within its bounds.
+    #[cfg(feature = "use-face-detection")]
+    fn draw_faces(&self, cr: &gtk::cairo::Context, width: i32, height: i32) {
+        let Some(paintable) = self.imp().viewer_picture.paintable() else {
+            return;
+        };
+        let (image_width, image_height) = (
+            paintable.intrinsic_width() as f64,
+            paintable.intrinsic_height() as f64,
+        );
+        if image_width <= 0.0 || image_height <= 0.0 {
+            return;
+        }
+        let scale: f64 = (width as f64 / image_width).min(height as f64 / image_height);
+        let offset_x: f64 = (width as f64 - image_width * scale) / 2.0;
+        let offset_y: f64 = (height as f64 - image_height * scale) / 2.0;
+
+        cr.set_line_width(2.0);
+        cr.set_source_rgba(1.0, 1.0, 1.0, 0.9);
+
+        for face in self.imp().faces.borrow().iter() {
+            cr.rectangle(
+                offset_x + face.x() as f64 * image_width * scale,
+                offset_y + face.y() as f64 * image_height * scale,
+                face.width() as f64 * image_width * scale,
+                face.height() as f64 * image_height * scale,
+            );
+        }
+        if let Err(err) = cr.stroke() {
+            g_warning!("Viewer", "Failed to draw face outlines: {}", err);
+        }
+    }
+
+    /// Returns the zoom level at which the image fits in the viewer.
+    fn best_fit_zoom_level(&self) -> f64 {
+        let Some(paintable) = self.imp().viewer_picture.paintable() else {
+            return 1.0;
+        };
+        let (width, height) = (paintable.intrinsic_width(), paintable.intrinsic_height());
+
+        if width <= 0 || height <= 0 {
+            return 1.0;
+        }
+        let viewport_width: f64 = self.imp().scrolled_window.width() as f64;
+        let viewport_height: f64 = self.imp().scrolled_window.height() as f64;
+
+        (viewport_width / width as f64).min(viewport_height / height as f64)
+    }
+
+    /// Returns the current zoom level, resolving the best fit zoom level.
+    fn current_zoom_level(&self) -> f64 {
+        match self.imp().zoom_level.get() {
+            zoom if zoom > 0.0 => zoom,
+            _ => self.best_fit_zoom_level(),
+        }
+    }
+
+    /// Sets the zoom level of the image, where 1.0 is the actual size of the
+    /// image. A zoom level of 0.0 (or any level below the best fit) fits the
+    /// image in the viewer, which follows the size of the viewer.
+    fn set_zoom_level(&self, zoom_level: f64) {
+        let best_fit: f64 = self.best_fit_zoom_level();
+        let max_zoom: f64 = VIEWER_MAX_ZOOM.max(best_fit);
+        let previous_zoom_level: f64 = self.imp().zoom_level.get();
+
+        self.imp().pending_hscroll.take();
+        self.imp().pending_vscroll.take();
+
+        let zoom_level: f64 = if zoom_level <= best_fit {
+            0.0
+        } else {
+            zoom_level.min(max_zoom)
+        };
+        self.imp().zoom_level.set(zoom_level);
+
+        // Let the user feel that they are pushing against a zoom limit.
+        if zoom_level == previous_zoom_level && (zoom_level == 0.0 || zoom_level == max_zoom) {
+            feedback::emit(FeedbackEvent::ZoomLimit);
+        }
+
+        let picture: &gtk::Picture = &self.imp().viewer_picture;
+
+        match picture.paintable() {
+            Some(paintable) if zoom_level > 0.0 => picture.set_size_request(
+                (paintable.intrinsic_width() as f64 * zoom_level) as i32,
+                (paintable.intrinsic_height() as f64 * zoom_level) as i32,
+            ),
+            _ => picture.set_size_request(-1, -1),
+        }
+        self.update_visible_area();
+    }
+
+    /// Zooms the image, keeping the point of the image at the given
+    /// position of the scrolled window in place, like under the pointer.
+    fn zoom_at(&self, zoom_level: f64, x: f64, y: f64) {
+        let Some(paintable) = self.imp().viewer_picture.paintable() else {
+            return;
+        };
+        let previous_zoom_level: f64 = self.current_zoom_level();
+        self.set_zoom_level(zoom_level);
+        let zoom_level: f64 = self.current_zoom_level();
+
+        if zoom_level == previous_zoom_level {
+            return;
+        }
+        let hadjustment: gtk::Adjustment = self.imp().scrolled_window.hadjustment();
+        let vadjustment: gtk::Adjustment = self.imp().scrolled_window.vadjustment();
+
+        // The new scroll positions can only be set once the
+        // adjustments are resized to the new size of the image.
+        for (adjustment, pending_scroll, position, image_size) in [
+            (
+                hadjustment,
+                &self.imp().pending_hscroll,
+                x,
+                paintable.intrinsic_width(),
+            ),
+            (
+                vadjustment,
+                &self.imp().pending_vscroll,
+                y,
+                paintable.intrinsic_height(),
+            ),
+        ] {
+            pending_scroll.set(Some(anchored_scroll_value(
+                adjustment.value(),
+                position,
+                adjustment.page_size(),
+                image_size as f64 * previous_zoom_level,
+                image_size as f64 * zoom_level,
+            )));
+        }
+    }
+
+    /// Scrolls to the position kept by `zoom_at()` once an adjustment
+    /// of the scrolled window is resized to the new size of the image.
+    fn apply_pending_scroll(&self, adjustment: &gtk::Adjustment) {
+        let pending_scroll: &Cell<Option<f64>> = if *adjustment == self.imp().scrolled_window.hadjustment() {
+            &self.imp().pending_hscroll
+        } else {
+            &self.imp().pending_vscroll
+        };
+        if let Some(value) = pending_scroll.take() {
+            adjustment.set_value(value);
+        }
+    }
+
+    /// Zooms the image at the pointer while scrolling with Ctrl held, or with the
+    /// scroll wheel if it is set to zoom in the preferences. Otherwise, the scroll
+    /// wheel shows the previous or next media, unless the image is zoomed in, in
+    /// which case it is scrolled instead. Touchpads swipe between media.
+    fn viewer_scrolled(
+        &self,
+        controller: &gtk::EventControllerScroll,
+        dx: f64,
+        dy: f64,
+    ) -> glib::Propagation {
+        let unit: gdk::ScrollUnit = controller.unit();
+        let zoom_held: bool = controller
+            .current_event_state()
+            .contains(gdk::ModifierType::CONTROL_MASK);
+
+        if !zoom_held {
+            if self.imp().panorama.get() {
+                return self.panorama_scrolled(unit, dx, dy);
+            }
+            match unit {
+                gdk::ScrollUnit::Wheel => (),
+                _ => return self.touchpad_swiped(dx, dy),
+            }
+            let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+
+            if ScrollWheelAction::from_value(gsettings.int("viewer-scroll-wheel"))
+                == ScrollWheelAction::Navigate
+            {
+                if self.imp().zoom_level.get() > 0.0 || dy == 0.0 {
+                    return glib::Propagation::Proceed;
+                }
+                self.show_adjacent(if dy > 0.0 { 1 } else { -1 });
+                return glib::Propagation::Stop;
+            }
+        }
+        let is_image: bool = self.imp().viewer_stack.visible_child_name().as_deref() == Some("image");
+
+        if !is_image || dy == 0.0 {
+            return glib::Propagation::Proceed;
+        }
+        let steps: f64 = match unit {
+            gdk::ScrollUnit::Wheel => dy,
+            _ => dy / 10.0,
+        };
+        let zoom_level: f64 = self.current_zoom_level() * VIEWER_ZOOM_STEP.powf(-steps);
+
+        match self.pointer_position(controller) {
+            Some((x, y)) => self.zoom_at(zoom_level, x, y),
+            None => self.set_zoom_level(zoom_level),
+        }
+        glib::Propagation::Stop
+    }
+
+    /// Follows a two-finger horizontal swipe on a touchpad, which shows the previous
+    /// or next media once it ends, unless the image is zoomed in and is scrolled
+    /// instead. Swipes with no media in their direction are left to the navigation
+    /// view, which leaves the viewer when swiping back from the first media.
+    fn touchpad_swiped(&self, dx: f64, dy: f64) -> glib::Propagation {
+        let swipe_distance: f64 = self.imp().swipe_distance.get();
+
+        if swipe_distance == 0.0 {
+            let offset: isize = if dx > 0.0 { 1 } else { -1 };
+
+            if self.imp().zoom_level.get() > 0.0
+                || dx.abs() <= dy.abs()
+                || self.adjacent_item(offset).is_none()
+            {
+                return glib::Propagation::Proceed;
+            }
+        }
+        self.imp().swipe_distance.set(swipe_distance + dx);
+        glib::Propagation::Stop
+    }
+
+    /// Shows the previous or next media when a touchpad swipe
+    /// that went far enough ends, as the user lifts their fingers.
+    fn touchpad_swipe_ended(&self) {
+        let swipe_distance: f64 = self.imp().swipe_distance.take();
+
+        if swipe_distance.abs() >= VIEWER_SWIPE_DISTANCE {
+            self.show_adjacent(if swipe_distance > 0.0 { 1 } else { -1 });
+        }
+    }
+
+    /// Zooms the image as it is pinched on a touchpad or a touchscreen,
+    /// keeping the point between the fingers in place.
+    fn image_pinched(&self, gesture: &gtk::GestureZoom, scale: f64) {
+        let is_image: bool = self.imp().viewer_stack.visible_child_name().as_deref() == Some("image");
+
+        if !is_image || self.imp().panorama.get() {
+            return;
+        }
+        let zoom_level: f64 = self.imp().pinch_zoom_level.get() * scale;
+
+        match gesture.bounding_box_center() {
+            Some((x, y)) => self.zoom_at(zoom_level, x, y),
+            None => self.set_zoom_level(zoom_level),
+        }
+    }
+
+    /// Returns the position of the pointer in the scrolled window,
+    /// from the event that is being handled by an event controller.
+    fn pointer_position(&self, controller: &impl IsA<gtk::EventController>) -> Option<(f64, f64)> {
+        let (x, y) = controller.current_event()?.position()?;
+        let native: gtk::Native = self.native()?;
+        let (surface_x, surface_y) = native.surface_transform();
+
+        let point: graphene::Point = native.compute_point(
+            &*self.imp().scrolled_window,
+            &graphene::Point::new((x - surface_x) as f32, (y - surface_y) as f32),
+        )?;
+        Some((point.x() as f64, point.y() as f64))
+    }
+
+    /// Only enables the actions that work with the media shown in the viewer.
+    fn update_content_actions(&self) {
+        let Some(action_group) = self.imp().action_group.borrow().clone() else {
+            return;
+        };
+        let set_enabled = |action_name: &str, enabled: bool| {
+            if let Some(action) = action_group
+                .lookup_action(action_name)
+                .and_downcast::<gio::SimpleAction>()
+            {
+                action.set_enabled(enabled);
+            }
+        };
+        // Zooming, exporting, and printing work on a single frame, so they are only available
+        // for images. Videos are not perceptually hashed, so they have no similar photos either.
+        let is_video: bool = self.imp().viewer_stack.visible_child_name().as_deref() == Some("video");
+
+        for action_name in ["compress", "toggle_mute"] {
+            set_enabled(action_name, is_video);
+        }
+        for action_name in [
+            "zoom_in", "zoom_out", "zoom_best_fit", "export", "print", "faces", "similar",
+        ] {
+            set_enabled(action_name, !is_video);
+        }
+        let position: Option<usize> = self.sibling_position();
+        let siblings: usize = self.imp().siblings.borrow().len();
+
+        set_enabled("previous", position.is_some_and(|position: usize| position > 0));
+        set_enabled(
+            "next",
+            position.is_some_and(|position: usize| position + 1 < siblings),
+        );
+    }
+
+    /// Sets the media items that are stepped through with the previous and
+    /// next actions, which should include the media shown in the viewer, and
+    /// the name of the collection that they are shown in, such as "Photos".
+    pub fn set_siblings(&self, items: Vec<MemoriesMediaItem>, collection_title: &str) {
+        self.imp().siblings.replace(items);
+        self.imp().collection_title.replace(collection_title.to_owned());
+        self.update_content_actions();
+        self.update_title();
+    }
+
+    /// Titles the viewer after the collection of its siblings and the position of
+    /// the media among them, such as "Photos — 33 of 410", with the date of the
+    /// media as the subtitle. Media outside of a collection is titled by its name.
+    /// The title of the navigation page is also the title of the window.
+    fn update_title(&self) {
+        let Some(file) = self.content_file() else {
+            return;
+        };
+        let position: Option<usize> = self.sibling_position();
+        let siblings = self.imp().siblings.borrow();
+        let collection_title = self.imp().collection_title.borrow();
+        let item: Option<&MemoriesMediaItem> = position.and_then(|position: usize| siblings.get(position));
+
+        let title: String = match position {
+            Some(position) if !collection_title.is_empty() => {
+                collection_position_title(&collection_title, position + 1, siblings.len())
+            }
+            _ => item.map(MemoriesMediaItem::display_name).unwrap_or_else(|| {
+                file.basename()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            }),
+        };
+        let date: Option<String> = item
+            .and_then(MemoriesMediaItem::modified)
+            .and_then(|date_time: glib::DateTime| date_time.to_local().ok())
+            .and_then(|date_time: glib::DateTime| format::date_time(&date_time));
+
+        self.imp().window_title.set_title(&title);
+        self.imp()
+            .window_title
+            .set_subtitle(date.as_deref().unwrap_or_default());
+
+        if let Some(nav_page) = self.parent().and_downcast::<adw::NavigationPage>() {
+            nav_page.set_title(&title);
+        }
+    }
+
+    /// Returns the position of the media shown in the viewer among its siblings.
+    fn sibling_position(&self) -> Option<usize> {
+        let file: gio::File = self.content_file()?;
+
+        self.imp()
+            .siblings
+            .borrow()
+            .iter()
+            .position(|sibling: &MemoriesMediaItem| sibling.file().equal(&file))
+    }
+
+    /// Returns the media item `offset` positions away from the media shown
+    /// in the viewer among its siblings, if there is one at that position.
+    fn adjacent_item(&self, offset: isize) -> Option<MemoriesMediaItem> {
+        let position: usize = self.sibling_position()?.checked_add_signed(offset)?;
+
+        self.imp().siblings.borrow().get(position).cloned()
+    }
+
+    /// Shows the media `offset` positions away from the media shown in the viewer,
+    /// among its siblings, if there is media at that position.
+    fn show_adjacent(&self, offset: isize) {
+        let Some(item) = self.adjacent_item(offset) else {
+            return;
+        };
+        if let Some(content_type) = item.viewer_content_type() {
+            self.show_item(&item, &content_type);
+            return;
+        }
+        let generation: u64 = self.next_load_generation();
+
+        // Media of an unknown format is shown by its contents.
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let content_type: ViewerContentType = ViewerContentType::sniff(&item.file()).await;
+
+                // Skips the media if other media was shown while it was sniffed.
+                if this.imp().load_generation.get() != generation {
+                    return;
+                }
+                match content_type {
+                    ViewerContentType::Invalid => (),
+                    content_type => this.show_item(&item, &content_type),
+                }
+            }
+        ));
+    }
+
+    /// Starts a new load generation, which makes loads in progress stale,
+    /// and returns it to check that a load is still current once it is done.
+    fn next_load_generation(&self) -> u64 {
+        let generation: u64 = self.imp().load_generation.get().wrapping_add(1);
+
+        self.imp().load_generation.set(generation);
+        generation
+    }
+
+    /// Shows the media of an item in the viewer, with the details of the item.
+    fn show_item(&self, item: &MemoriesMediaItem, content_type: &ViewerContentType) {
+        self.show_file(&item.file(), content_type);
+        self.imp().properties_widget.update_item_details(item);
+    }
+
+    /// Shows 360° photos in the photo sphere viewer instead of the picture. Tiled
+    /// images are too large to upload as a single texture, so they are shown flat.
+    #[cfg(feature = "use-photo-sphere")]
+    fn show_photo_sphere(&self, texture: Option<&gdk::Texture>) {
+        let Some(texture) = texture.filter(|_| self.imp().is_photo_sphere.get()) else {
+            if let Some(photo_sphere) = self.imp().photo_sphere.get() {
+                photo_sphere.set_texture(None);
+            }
+            return;
+        };
+        let photo_sphere: &MemoriesPhotoSphere = self.imp().photo_sphere.get_or_init(|| {
+            let photo_sphere: MemoriesPhotoSphere = MemoriesPhotoSphere::new();
+            self.imp().image_stack.add_named(&photo_sphere, Some("sphere"));
+            photo_sphere
+        });
+        photo_sphere.set_texture(Some(texture));
+        photo_sphere.update_property(&[gtk::accessible::Property::Label(
+            &self
+                .content_file()
+                .and_then(|file: gio::File| file.basename())
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        )]);
+        self.imp().image_stack.set_visible_child_name("sphere");
+    }
+
+    /// Returns `true` if the image is wide enough to be shown as a panorama.
+    fn is_panorama(&self) -> bool {
+        self.imp()
+            .viewer_picture
+            .paintable()
+            .is_some_and(|paintable: gdk::Paintable| {
+                paintable.intrinsic_height() > 0
+                    && paintable.intrinsic_width() as f64 / paintable.intrinsic_height() as f64
+                        >= PANORAMA_MIN_ASPECT_RATIO
+            })
+    }
+
+    fn panorama_action(&self) -> Option<gio::SimpleAction> {
+        self.imp()
+            .action_group
+            .borrow()
+            .as_ref()
+            .and_then(|action_group| action_group.lookup_action("panorama"))
+            .and_downcast::<gio::SimpleAction>()
+    }
+
+    /// Only offers the panorama mode for panoramas, by showing its button.
+    fn update_panorama_action(&self) {
+        let is_panorama: bool = self.is_panorama();
+
+        self.imp().panorama_button.set_visible(is_panorama);
+
+        if let Some(action) = self.panorama_action() {
+            action.set_enabled(is_panorama);
+            action.set_state(&self.imp().panorama.get().to_variant());
+        }
+    }
+
+    /// Shows the image as a panorama, which fits the height of the viewer and
+    /// is scrolled horizontally from one end to the other, or fits the image
+    /// in the viewer again.
+    fn set_panorama(&self, panorama: bool) {
+        self.imp().panorama.set(panorama);
+
+        if let Some(tick_id) = self.imp().panorama_tick.take() {
+            tick_id.remove();
+        }
+        let picture_height: i32 = self
+            .imp()
+            .viewer_picture
+            .paintable()
+            .map_or(0, |paintable: gdk::Paintable| paintable.intrinsic_height());
+
+        if panorama && picture_height > 0 {
+            self.set_zoom_level(self.imp().scrolled_window.height() as f64 / picture_height as f64);
+            self.start_panorama_scroll();
+        } else {
+            self.set_zoom_level(0.0);
+        }
+        if let Some(action) = self.panorama_action() {
+            action.set_state(&panorama.to_variant());
+        }
+    }
+
+    /// Scrolls the panorama smoothly on every frame, and turns
+    /// around once either end of the panorama is reached.
+    fn start_panorama_scroll(&self) {
+        let last_frame_time: Cell<Option<i64>> = Cell::new(None);
+        let direction: Cell<f64> = Cell::new(1.0);
+
+        let tick_id: gtk::TickCallbackId = self.imp().scrolled_window.add_tick_callback(
+            move |scrolled_window: &gtk::ScrolledWindow, frame_clock: &gdk::FrameClock| {
+                let frame_time: i64 = frame_clock.frame_time();
+                let Some(previous_frame_time) = last_frame_time.replace(Some(frame_time)) else {
+                    return glib::ControlFlow::Continue;
+                };
+                let seconds: f64 = (frame_time - previous_frame_time) as f64 / 1_000_000.0;
+                let hadjustment: gtk::Adjustment = scrolled_window.hadjustment();
+                let end: f64 = hadjustment.upper() - hadjustment.page_size();
+
+                let value: f64 = hadjustment.value() + direction.get() * PANORAMA_SCROLL_SPEED * seconds;
+
+                if value >= end {
+                    direction.set(-1.0);
+                } else if value <= hadjustment.lower() {
+                    direction.set(1.0);
+                }
+                hadjustment.set_value(value.clamp(hadjustment.lower(), end.max(hadjustment.lower())));
+                glib::ControlFlow::Continue
+            },
+        );
+        self.imp().panorama_tick.replace(Some(tick_id));
+    }
+
+    /// Stops scrolling the panorama automatically once the user scrolls it,
+    /// and pans the panorama horizontally with a vertical scroll wheel.
+    fn panorama_scrolled(&self, unit: gdk::ScrollUnit, dx: f64, dy: f64) -> glib::Propagation {
+        if !self.imp().panorama.get() {
+            return glib::Propagation::Proceed;
+        }
+        if let Some(tick_id) = self.imp().panorama_tick.take() {
+            tick_id.remove();
+        }
+        if dx != 0.0 {
+            return glib::Propagation::Proceed;
+        }
+        let hadjustment: gtk::Adjustment = self.imp().scrolled_window.hadjustment();
+        let distance: f64 = match unit {
+            gdk::ScrollUnit::Wheel => dy * hadjustment.step_increment(),
+            _ => dy,
+        };
+        hadjustment.set_value(hadjustment.value() + distance);
+        glib::Propagation::Stop
+    }
+
+    /// Updates the visible area of a tiled paintable from the scroll
+    /// position, so that it only decodes the tiles that are visible.
+    fn update_visible_area(&self) {
+        let Some(paintable) = self
+            .imp()
+            .viewer_picture
+            .paintable()
+            .and_downcast::<MemoriesTiledPaintable>()
+        else {
+            return;
+        };
+        let zoom_level: f64 = self.imp().zoom_level.get();
+
+        if zoom_level <= 0.0 {
+            paintable.set_visible_area(None);
+            return;
+        }
+        let hadjustment: gtk::Adjustment = self.imp().scrolled_window.hadjustment();
+        let vadjustment: gtk::Adjustment = self.imp().scrolled_window.vadjustment();
+
+        paintable.set_visible_area(Some(graphene::Rect::new(
+            (hadjustment.value() / zoom_level) as f32,
+            (vadjustment.value() / zoom_level) as f32,
+            (hadjustment.page_size() / zoom_level) as f32,
+            (vadjustment.page_size() / zoom_level) as f32,
+        )));
+    }
+
+    #[template_callback]
+    fn retry_clicked(&self) {
+        let content_file: Option<gio::File> = self.imp().content_file.borrow().clone();
+
+        if let Some(file) = content_file {
+            self.load_image(&file);
+        }
+    }
+
+    /// Returns a new `AdwNavigationPage` object that
+    /// has its child set to the `&self` GObject.
+    pub fn wrap_in_navigation_page(&self) -> adw::NavigationPage {
+        let new_navigation_page: adw::NavigationPage = adw::NavigationPage::builder()
+            .title(gettext("Loading Content"))
+            .child(self)
+            .build();
+        new_navigation_page
+    }
+
+    /// Reveals the overlay controls and hides them again after the timeout
+    /// configured in GSettings. The navigation arrows are only shown on pointer
+    /// motion if enabled in GSettings, as they are mainly useful on touch screens.
+    fn reveal_overlay_controls(&self, from_pointer: bool) {
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+
+        let show_nav_arrows: bool = !from_pointer || gsettings.boolean("viewer-show-nav-arrows");
+        self.imp().nav_overlay_controls.set_visible(show_nav_arrows);
+
+        self.imp().nav_overlay_revealer.set_reveal_child(true);
+        self.imp().zoom_overlay_revealer.set_reveal_child(true);
+        self.imp().renditions_revealer.set_reveal_child(true);
+
+        if self.imp().immersive.get() {
+            self.imp().toolbar_view.set_reveal_top_bars(true);
+            self.imp().toolbar_view.set_reveal_bottom_bars(true);
+            self.set_cursor(None);
+        }
+
+        if let Some(src_id) = self.imp().overlay_timeout_source.replace(None) {
+            src_id.remove();
+        }
+
+        let timeout: glib::SourceId = glib::timeout_add_local_once(
+            Duration::from_secs(gsettings.uint("viewer-overlay-timeout").into()),
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                move || {
+                    this.imp().nav_overlay_revealer.set_reveal_child(false);
+                    this.imp().zoom_overlay_revealer.set_reveal_child(false);
+                    this.imp().renditions_revealer.set_reveal_child(false);
+
+                    if this.imp().immersive.get() {
+                        this.imp().toolbar_view.set_reveal_top_bars(false);
+                        this.imp().toolbar_view.set_reveal_bottom_bars(false);
+                        this.set_cursor_from_name(Some("none"));
+                    }
+
+                    // If this closure is executed, `overlay_timeout_source` is
+                    // guaranteed to contain a `Some` option value, so we can unwrap().
+                    this.imp().overlay_timeout_source.replace(None).unwrap().remove();
+                }
+            ),
+        );
+        self.imp().overlay_timeout_source.set(Some(timeout));
+    }
+
+    #[template_callback]
+    fn overlay_motion_handler(&self, x: f64, y: f64) {
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+        let trigger = OverlayRevealTrigger::from_value(gsettings.int("viewer-overlay-trigger"));
+
+        // After the overlay is hidden again, the motion controller emits a motion
+        // event again, but with rounded values. Because of this, we have to get the
+        // delta of the mouse movement to the last movement detected and only reveal
+        // the overlay if the delta for **both** x and y are above 1.0 units.
+
+        let last_x: f64 = self.imp().motion_last_x.replace(x);
+        let last_y: f64 = self.imp().motion_last_y.replace(y);
+
+        if last_x != 0.0 || last_y != 0.0 {
+            let motion_delta_x: f64 = (last_x - x).abs();
+            let motion_delta_y: f64 = (last_y - y).abs();
+
+            if (motion_delta_x <= 1_f64) && (motion_delta_y <= 1_f64) {
+                return;
+            }
+        }
+        // Immersive mode always shows the cursor and header bar on motion.
+        if trigger.reveals_on_motion() || self.imp().immersive.get() {
+            self.reveal_overlay_controls(true);
+        }
+    }
+
+    #[template_callback]
+    fn touch_gesture_handler(&self, _: i32, _: f64, _: f64) {
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+        let trigger = OverlayRevealTrigger::from_value(gsettings.int("viewer-overlay-trigger"));
+
+        if trigger.reveals_on_touch() {
+            self.reveal_overlay_controls(false);
+        }
+    }
+}
+
+/// Converts a glycin loading error into a message that can be shown to the user.
+fn glycin_error_message(err: &glycin::ErrorCtx) -> String {
+    if let Some(mime_type) = err.unsupported_format() {
+        gettext_f(
+            "The image format “{MIME}” is not supported.",
+            &[("MIME", &mime_type)],
+        )
+    } else if err.is_out_of_memory() {
+        gettext("There is not enough memory available to load this image.")
+    } else if let glycin::Error::NoLoadersConfigured(_) = err.error() {
+        gettext("No image loaders are installed. You might need to install the glycin loaders package.")
+    } else {
+        gettext("The image file may be corrupt or incomplete.")
+    }
+}
+
+/// Returns the scroll value along one axis that keeps the point of the image under
+/// the pointer in place when the image is zoomed from `old_size` to `new_size`.
+/// Images smaller than the viewport are centered in it, so they are not scrolled.
+fn anchored_scroll_value(value: f64, pointer: f64, viewport: f64, old_size: f64, new_size: f64) -> f64 {
+    let old_offset: f64 = ((viewport - old_size) / 2.0).max(0.0);
+    let fraction: f64 = ((value + pointer - old_offset) / old_size).clamp(0.0, 1.0);
+
+    (fraction * new_size - pointer).clamp(0.0, (new_size - viewport).max(0.0))
+}
+
+/// Returns the title of media at a 1-based `position` among the `total` media
+/// of a collection, such as "Photos — 33 of 410".
+fn collection_position_title(collection: &str, position: usize, total: usize) -> String {
+    gettext_f(
+        "{COLLECTION} — {POSITION} of {TOTAL}",
+        &[
+            ("COLLECTION", collection),
+            ("POSITION", &position.to_string()),
+            ("TOTAL", &total.to_string()),
+        ],
+    )
 }
 
 impl Default for MemoriesMediaViewer {
@@ -338,3 +2206,150 @@ impl Default for MemoriesMediaViewer {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_types_from_mime_types() {
+        assert!(matches!(
+            ViewerContentType::from_mime_type("image/jpeg"),
+            ViewerContentType::Image
+        ));
+        assert!(matches!(
+            ViewerContentType::from_mime_type("image/gif"),
+            ViewerContentType::Video
+        ));
+        assert!(matches!(
+            ViewerContentType::from_mime_type("image/svg+xml"),
+            ViewerContentType::VectorGraphics
+        ));
+        assert!(matches!(
+            ViewerContentType::from_mime_type("video/mp4"),
+            ViewerContentType::Video
+        ));
+        assert!(matches!(
+            ViewerContentType::from_mime_type("text/plain"),
+            ViewerContentType::Invalid
+        ));
+        // Media that can not be decoded is opened in other apps instead.
+        assert!(matches!(
+            ViewerContentType::from_mime_type("video/mp2t"),
+            ViewerContentType::Invalid
+        ));
+        assert!(matches!(
+            ViewerContentType::from_mime_type("video/3gpp"),
+            ViewerContentType::Invalid
+        ));
+    }
+
+    #[test]
+    fn media_mime_types() {
+        assert!(ViewerContentType::is_media_mime_type("video/mp2t"));
+        assert!(ViewerContentType::is_media_mime_type("image/x-canon-cr2"));
+        assert!(!ViewerContentType::is_media_mime_type("audio/mpeg"));
+        assert!(!ViewerContentType::is_media_mime_type("text/plain"));
+    }
+
+    #[test]
+    fn content_types_from_magic_bytes() {
+        assert!(matches!(
+            ViewerContentType::from_magic(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            ViewerContentType::Image
+        ));
+        assert!(matches!(
+            ViewerContentType::from_magic(b"RIFF\0\0\0\0WEBPVP8 "),
+            ViewerContentType::Image
+        ));
+        assert!(matches!(
+            ViewerContentType::from_magic(b"RIFF\0\0\0\0AVI LIST"),
+            ViewerContentType::Video
+        ));
+        assert!(matches!(
+            ViewerContentType::from_magic(b"\0\0\0\x18ftypheic"),
+            ViewerContentType::Image
+        ));
+        assert!(matches!(
+            ViewerContentType::from_magic(b"\0\0\0\x18ftypisom"),
+            ViewerContentType::Video
+        ));
+        assert!(matches!(
+            ViewerContentType::from_magic(b"\0\0\0\x18ftyp3gp4"),
+            ViewerContentType::Invalid
+        ));
+        assert!(matches!(
+            ViewerContentType::from_magic(b"\0\0\0\x1CftypavifMA1B"),
+            ViewerContentType::Image
+        ));
+        assert!(matches!(
+            ViewerContentType::from_magic(b"II*\0\x08\0\0\0"),
+            ViewerContentType::Image
+        ));
+        assert!(matches!(
+            ViewerContentType::from_magic(b"MM\0*\0\0\0\x08"),
+            ViewerContentType::Image
+        ));
+        assert!(matches!(
+            ViewerContentType::from_magic(&[0xFF, 0x0A, 0xFA]),
+            ViewerContentType::Image
+        ));
+        assert!(matches!(
+            ViewerContentType::from_magic(b"\0\0\0\x0CJXL \r\n\x87\n"),
+            ViewerContentType::Image
+        ));
+        assert!(matches!(
+            ViewerContentType::from_magic(b"<?xml version=\"1.0\"?>\n<svg>"),
+            ViewerContentType::VectorGraphics
+        ));
+        assert!(matches!(
+            ViewerContentType::from_magic(b"plain text"),
+            ViewerContentType::Invalid
+        ));
+        assert!(matches!(
+            ViewerContentType::from_magic(&[]),
+            ViewerContentType::Invalid
+        ));
+    }
+
+    #[test]
+    fn content_types_from_file_names() {
+        assert!(matches!(
+            ViewerContentType::from_file_name(Path::new("photo.JPG")),
+            Some(ViewerContentType::Image)
+        ));
+        for file_name in ["photo.avif", "photo.jxl", "scan.tif", "scan.TIFF", "photo.heif"] {
+            assert!(matches!(
+                ViewerContentType::from_file_name(Path::new(file_name)),
+                Some(ViewerContentType::Image)
+            ));
+        }
+        assert!(ViewerContentType::from_file_name(Path::new("photo")).is_none());
+        assert!(ViewerContentType::from_file_name(Path::new("notes.txt")).is_none());
+    }
+
+    #[test]
+    fn zooming_keeps_point_under_pointer() {
+        // The middle of a 1000px image scrolled by 200px in a 600px viewport.
+        assert_eq!(anchored_scroll_value(200.0, 300.0, 600.0, 1000.0, 2000.0), 700.0);
+        assert_eq!(anchored_scroll_value(700.0, 300.0, 600.0, 2000.0, 1000.0), 200.0);
+    }
+
+    #[test]
+    fn zooming_from_fitted_image_accounts_for_centering() {
+        // A 400px image is centered in a 600px viewport, 100px from its edge.
+        assert_eq!(anchored_scroll_value(0.0, 200.0, 600.0, 400.0, 1200.0), 100.0);
+    }
+
+    #[test]
+    fn scroll_value_stays_in_bounds() {
+        assert_eq!(anchored_scroll_value(0.0, 0.0, 600.0, 1000.0, 2000.0), 0.0);
+        assert_eq!(anchored_scroll_value(400.0, 600.0, 600.0, 1000.0, 2000.0), 1400.0);
+        assert_eq!(anchored_scroll_value(400.0, 300.0, 600.0, 1000.0, 500.0), 0.0);
+    }
+
+    #[test]
+    fn collection_position_titles() {
+        assert_eq!(collection_position_title("Photos", 33, 410), "Photos — 33 of 410");
+    }
+}
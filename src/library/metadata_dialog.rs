@@ -0,0 +1,154 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::util::metadata::{MetadataStandard, MetadataTag};
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::clone;
+use gtk::glib;
+
+mod imp {
+    use crate::util::metadata::MetadataTag;
+    use adw::subclass::prelude::*;
+    use gtk::glib;
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/metadata-dialog.ui")]
+    pub struct MemoriesMetadataDialog {
+        pub(super) tags: RefCell<Vec<MetadataTag>>,
+        #[template_child]
+        pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) copy_all_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) metadata_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) preferences_page: TemplateChild<adw::PreferencesPage>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesMetadataDialog {
+        const NAME: &'static str = "MemoriesMetadataDialog";
+        type Type = super::MemoriesMetadataDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesMetadataDialog {}
+    impl WidgetImpl for MemoriesMetadataDialog {}
+    impl AdwDialogImpl for MemoriesMetadataDialog {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesMetadataDialog(ObjectSubclass<imp::MemoriesMetadataDialog>)
+        @extends gtk::Widget, adw::Dialog;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesMetadataDialog {
+    /// Creates a new dialog that lists all the metadata tags given,
+    /// grouped by the metadata standard that they were read from.
+    pub fn new(tags: Vec<MetadataTag>) -> Self {
+        let obj: Self = glib::Object::new();
+
+        if tags.is_empty() {
+            obj.imp().metadata_stack.set_visible_child_name("empty");
+            obj.imp().copy_all_button.set_sensitive(false);
+        } else {
+            for standard in [MetadataStandard::Exif, MetadataStandard::Xmp] {
+                obj.add_group(standard, &tags);
+            }
+        }
+        obj.imp().tags.replace(tags);
+        obj
+    }
+
+    fn add_group(&self, standard: MetadataStandard, tags: &[MetadataTag]) {
+        let group_tags: Vec<&MetadataTag> = tags
+            .iter()
+            .filter(|tag: &&MetadataTag| tag.standard == standard)
+            .collect();
+
+        if group_tags.is_empty() {
+            return;
+        }
+        let group: adw::PreferencesGroup = adw::PreferencesGroup::builder().title(standard.name()).build();
+
+        for tag in group_tags {
+            let row: adw::ActionRow = adw::ActionRow::builder()
+                .title(glib::markup_escape_text(&tag.key))
+                .subtitle(glib::markup_escape_text(&tag.value))
+                .subtitle_selectable(true)
+                .css_classes(["property"])
+                .build();
+
+            let copy_button: gtk::Button = gtk::Button::builder()
+                .icon_name("edit-copy-symbolic")
+                .tooltip_text(gettext("Copy Value"))
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+
+            let value: String = tag.value.clone();
+
+            copy_button.connect_clicked(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gtk::Button| {
+                    this.copy_to_clipboard(&value);
+                }
+            ));
+            row.add_suffix(&copy_button);
+            group.add(&row);
+        }
+        self.imp().preferences_page.add(&group);
+    }
+
+    fn copy_to_clipboard(&self, text: &str) {
+        self.clipboard().set_text(text);
+
+        let toast: adw::Toast = adw::Toast::new(&gettext("Copied to clipboard"));
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    #[template_callback]
+    fn copy_all(&self) {
+        let text: String = self
+            .imp()
+            .tags
+            .borrow()
+            .iter()
+            .map(|tag: &MetadataTag| format!("{} {}: {}", tag.standard.name(), tag.key, tag.value))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        self.copy_to_clipboard(&text);
+    }
+}
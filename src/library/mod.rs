@@ -18,37 +18,98 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+mod burst_dialog;
+mod bursts;
+mod catalog;
+pub(super) mod catalog_dialog;
+mod compress_dialog;
+mod date_time_dialog;
+pub(super) mod dbus_service;
+mod export_dialog;
+pub(super) mod integrity_dialog;
+mod justified_layout;
+pub(super) mod justified_view;
 pub(super) mod list_model;
 mod media_cell;
-mod media_grid;
-mod media_item;
-mod media_viewer;
+pub(super) mod media_grid;
+pub(super) mod media_item;
+pub(super) mod media_viewer;
+mod metadata_dialog;
+#[cfg(feature = "use-photo-sphere")]
+mod photo_sphere;
+mod print;
 mod properties;
+mod rename_dialog;
+pub(super) mod roots;
+pub(super) mod scan_service;
+pub(super) mod search;
+pub(super) mod share_dialog;
+pub(super) mod shotwell;
+mod slideshow_dialog;
+mod tags_dialog;
+mod tiled_paintable;
+mod viewer_window;
+mod web_gallery;
 
 use crate::application::MemoriesApplication;
-use crate::config::APP_NAME;
-use crate::globals::FFMPEG_BINARY;
-use crate::i18n::gettext_f;
+use crate::config::{APP_ID, APP_NAME};
+use crate::globals::{FFMPEG_BINARY, ONBOARDING_MAX_FILES_CHECKED, ONBOARDING_SUGGESTED_DIRS};
+use crate::i18n::{gettext_f, ngettext_f};
+#[cfg(feature = "use-embeddings")]
+use crate::util::embeddings;
+use crate::util::media_database::MediaList;
+use crate::util::mounts;
 use crate::window::MemoriesApplicationWindow;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
+use bursts::{Bursts, Frame};
 use gettextrs::gettext;
-use glib::{clone, g_debug, g_error};
+use glib::{clone, g_debug, g_warning};
 use gtk::{gio, glib};
 use list_model::MemoriesLibraryListModel;
+use media_grid::MemoriesMediaGridView;
+use media_item::MemoriesMediaItem;
+use media_viewer::ViewerContentType;
+use scan_service::MemoriesScanService;
+use search::SearchQuery;
+#[cfg(feature = "use-embeddings")]
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::io;
+use std::path::PathBuf;
 use std::process::Command;
 
 mod imp {
     use super::media_grid::MemoriesMediaGridView;
+    use super::search::SearchQuery;
     use adw::subclass::prelude::*;
     use gtk::glib;
-    use std::cell::Cell;
+    use std::cell::{Cell, OnceCell, RefCell};
+    #[cfg(feature = "use-embeddings")]
+    use std::collections::HashMap;
 
     #[derive(Debug, Default, gtk::CompositeTemplate)]
     #[template(resource = "/com/maxrdz/Memories/ui/library.ui")]
     pub struct MemoriesLibraryView {
         pub(super) view_mode: Cell<super::LibraryViewMode>,
+        pub(super) search_query: RefCell<SearchQuery>,
+        pub(super) search_filter: OnceCell<gtk::CustomFilter>,
+        pub(super) burst_filter: OnceCell<gtk::CustomFilter>,
+        /// Idle source that detects bursts once the library model stops changing.
+        pub(super) burst_detection_source: RefCell<Option<glib::SourceId>>,
+        /// Similarity of media to the search query by file URI,
+        /// for media similar enough to the query to match it.
+        #[cfg(feature = "use-embeddings")]
+        pub(super) similarities: RefCell<HashMap<String, f32>>,
+        /// Model that ranks media by similarity while searching with embeddings.
+        #[cfg(feature = "use-embeddings")]
+        pub(super) ranking_model: OnceCell<gtk::SortListModel>,
+        /// Library folders that could not be read during the current scan.
+        pub(super) unreadable_folders: RefCell<Vec<String>>,
+        /// Rows of the folders with media suggested on the onboarding page.
+        pub(super) suggested_folder_rows: RefCell<Vec<adw::ActionRow>>,
+        #[template_child]
+        pub(super) unavailable_folders_list: TemplateChild<gtk::ListBox>,
         #[template_child]
         pub(super) library_view_stack: TemplateChild<adw::ViewStack>,
         #[template_child]
@@ -56,12 +117,30 @@ mod imp {
         #[template_child]
         pub(super) spinner: TemplateChild<gtk::Spinner>,
         #[template_child]
+        pub(super) scan_progress_bar: TemplateChild<gtk::ProgressBar>,
+        #[template_child]
+        pub(super) scan_progress_label: TemplateChild<gtk::Label>,
+        #[template_child]
         pub(super) error_page: TemplateChild<adw::ViewStackPage>,
         #[template_child]
         pub(super) error_status_widget: TemplateChild<adw::StatusPage>,
         #[template_child]
+        pub(super) onboarding_toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) onboarding_status_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub(super) suggested_folders_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) permissions_status_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub(super) permissions_command_label: TemplateChild<gtk::Label>,
+        #[template_child]
         pub(super) gallery_page: TemplateChild<adw::ViewStackPage>,
         #[template_child]
+        pub(super) ffmpeg_banner: TemplateChild<adw::Banner>,
+        #[template_child]
+        pub(super) folders_banner: TemplateChild<adw::Banner>,
+        #[template_child]
         pub media_grid: TemplateChild<MemoriesMediaGridView>,
     }
 
@@ -73,6 +152,7 @@ mod imp {
 
         fn class_init(klass: &mut Self::Class) {
             klass.bind_template();
+            klass.bind_template_instance_callbacks();
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -102,6 +182,7 @@ glib::wrapper! {
         @extends gtk::Widget, adw::Bin;
 }
 
+#[gtk::template_callbacks]
 impl MemoriesLibraryView {
     pub fn new() -> Self {
         glib::Object::new()
@@ -120,32 +201,723 @@ impl MemoriesLibraryView {
         self.imp().view_mode.set(mode_variant);
     }
 
+    #[template_callback]
+    fn dismiss_ffmpeg_banner(&self) {
+        self.imp().ffmpeg_banner.set_revealed(false);
+    }
+
+    #[template_callback]
+    fn dismiss_folders_banner(&self) {
+        self.imp().folders_banner.set_revealed(false);
+    }
+
+    #[template_callback]
+    fn add_folder_clicked(&self) {
+        let file_dialog: gtk::FileDialog = gtk::FileDialog::builder()
+            .title(gettext("Add Folder to Library"))
+            .modal(true)
+            .build();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                // An error is returned if the user dismisses the file chooser.
+                if let Ok(folder) = file_dialog.select_folder_future(Some(&this.window())).await {
+                    let folder: String = match folder.path() {
+                        Some(path) => path.to_string_lossy().to_string(),
+                        None => folder.uri().to_string(),
+                    };
+                    this.add_library_folder(&folder).await;
+                }
+            }
+        ));
+    }
+
+    /// Adds a folder to the library from the onboarding page, which rebuilds the library.
+    async fn add_library_folder(&self, folder: &str) {
+        if let Err(message) = self.window().add_library_folder(folder).await {
+            self.imp()
+                .onboarding_toast_overlay
+                .add_toast(adw::Toast::new(&message));
+        }
+    }
+
+    /// Returns the library folders that cannot be read because the Flatpak
+    /// sandbox was not given access to them, which makes them appear missing.
+    fn sandboxed_folders(model: &MemoriesLibraryListModel) -> Vec<gio::File> {
+        if MemoriesApplication::is_flatpak().is_none() {
+            return vec![];
+        }
+        model
+            .unavailable_folders()
+            .into_iter()
+            .filter(|(folder, error): &(gio::File, glib::Error)| {
+                error.matches(gio::IOErrorEnum::PermissionDenied)
+                    || (error.matches(gio::IOErrorEnum::NotFound) && !mounts::can_mount_folder(folder))
+            })
+            .map(|(folder, _)| folder)
+            .collect()
+    }
+
+    /// Guides the user to give the Flatpak sandbox access to library folders.
+    /// Folders chosen through the file chooser portal are shared with Memories
+    /// by the document portal, which keeps access to them across restarts.
+    fn show_permissions_page(&self, folders: &[gio::File]) {
+        let folder_names: Vec<String> = folders
+            .iter()
+            .map(|folder: &gio::File| folder.parse_name().to_string())
+            .collect();
+
+        self.imp()
+            .permissions_status_page
+            .set_description(Some(&gettext_f(
+                "{APP} cannot read {FOLDERS}. Choose the folders with your photos and videos to let {APP} read them.",
+                &[("APP", APP_NAME), ("FOLDERS", &folder_names.join(", "))],
+            )));
+
+        let filesystem_args: Vec<String> = folders
+            .iter()
+            .filter_map(gio::File::path)
+            .map(|path: PathBuf| {
+                glib::shell_quote(format!("--filesystem={}", path.display()))
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+
+        self.imp().permissions_command_label.set_label(&format!(
+            "flatpak override --user {} {}",
+            filesystem_args.join(" "),
+            APP_ID
+        ));
+    }
+
+    /// Explains which folders were scanned when no media was found in the
+    /// library, and looks for other folders with media to suggest adding.
+    fn show_onboarding(&self) {
+        let folders: Vec<String> = MemoriesLibraryListModel::configured_folders()
+            .iter()
+            .map(|f: &glib::GStringPtr| {
+                gio::File::for_commandline_arg(f.as_str())
+                    .parse_name()
+                    .to_string()
+            })
+            .collect();
+
+        let description: String = if folders.is_empty() {
+            gettext("Add a folder to your library, or import media from a camera or phone.")
+        } else {
+            gettext_f(
+                "No photos or videos were found in {FOLDERS}. Add a folder to your library, or import media from a camera or phone.",
+                &[("FOLDERS", &folders.join(", "))],
+            )
+        };
+        self.imp()
+            .onboarding_status_page
+            .set_description(Some(&description));
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                this.update_suggested_folders().await;
+            }
+        ));
+    }
+
+    /// Lists the common folders that have media in them, but are not in the library.
+    async fn update_suggested_folders(&self) {
+        let configured_folders: glib::StrV = MemoriesLibraryListModel::configured_folders();
+        let mut suggestions: Vec<gio::File> = vec![];
+
+        for user_dir in ONBOARDING_SUGGESTED_DIRS {
+            // Unset user directories can fall back to the home folder.
+            let Some(path) = glib::user_special_dir(*user_dir).filter(|path| *path != glib::home_dir())
+            else {
+                continue;
+            };
+            let folder: gio::File = gio::File::for_path(path);
+
+            let in_library: bool = configured_folders
+                .iter()
+                .any(|f: &glib::GStringPtr| gio::File::for_commandline_arg(f.as_str()) == folder);
+
+            if !in_library && Self::folder_has_media(&folder).await {
+                suggestions.push(folder);
+            }
+        }
+        let group: &adw::PreferencesGroup = &self.imp().suggested_folders_group;
+
+        for row in self.imp().suggested_folder_rows.take() {
+            group.remove(&row);
+        }
+        group.set_visible(!suggestions.is_empty());
+
+        for folder in suggestions {
+            let row: adw::ActionRow = adw::ActionRow::builder()
+                .title(folder.parse_name())
+                .use_markup(false)
+                .build();
+            row.add_prefix(&gtk::Image::from_icon_name("folder-symbolic"));
+
+            let add_button: gtk::Button = gtk::Button::builder()
+                .label(gettext("_Add"))
+                .use_underline(true)
+                .valign(gtk::Align::Center)
+                .build();
+
+            add_button.connect_clicked(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |button: &gtk::Button| {
+                    button.set_sensitive(false);
+
+                    let folder: String = folder.parse_name().to_string();
+
+                    glib::spawn_future_local(clone!(
+                        #[weak]
+                        this,
+                        async move {
+                            this.add_library_folder(&folder).await;
+                        }
+                    ));
+                }
+            ));
+            row.add_suffix(&add_button);
+
+            group.add(&row);
+            self.imp().suggested_folder_rows.borrow_mut().push(row);
+        }
+    }
+
+    /// Returns whether any of the first files in a folder are photos or videos.
+    async fn folder_has_media(folder: &gio::File) -> bool {
+        let Ok(enumerator) = folder
+            .enumerate_children_future(
+                gio::FILE_ATTRIBUTE_STANDARD_NAME,
+                gio::FileQueryInfoFlags::NONE,
+                glib::Priority::LOW,
+            )
+            .await
+        else {
+            return false;
+        };
+        let Ok(file_infos) = enumerator
+            .next_files_future(ONBOARDING_MAX_FILES_CHECKED, glib::Priority::LOW)
+            .await
+        else {
+            return false;
+        };
+        file_infos.iter().any(|info: &gio::FileInfo| {
+            info.name()
+                .extension()
+                .is_some_and(|extension: &std::ffi::OsStr| {
+                    extension.to_str().is_some()
+                        && !matches!(ViewerContentType::from_ext(extension), ViewerContentType::Invalid)
+                })
+        })
+    }
+
+    /// Adds a folder that could not be enumerated to the banner that
+    /// lists the unreadable folders. The rest of the library still loads.
+    fn add_unreadable_folder(&self, folder: &str) {
+        let mut folders: std::cell::RefMut<'_, Vec<String>> = self.imp().unreadable_folders.borrow_mut();
+
+        if folders.iter().any(|f: &String| f == folder) {
+            return;
+        }
+        folders.push(folder.to_string());
+
+        let count: u32 = folders.len().try_into().unwrap();
+        let title: String = ngettext_f(
+            "A folder could not be read: {FOLDERS}",
+            "{COUNT} folders could not be read: {FOLDERS}",
+            count,
+            &[("COUNT", &count.to_string()), ("FOLDERS", &folders.join(", "))],
+        );
+        self.imp().folders_banner.set_title(&title);
+        self.imp().folders_banner.set_revealed(true);
+    }
+
+    /// Lists the library folders that could not be enumerated above the library,
+    /// with buttons to mount the drive or location of a folder, or to remove it.
+    fn update_unavailable_folders(&self, model: &MemoriesLibraryListModel) {
+        let list: &gtk::ListBox = &self.imp().unavailable_folders_list;
+        let folders: Vec<(gio::File, glib::Error)> = model.unavailable_folders();
+
+        list.remove_all();
+        list.set_visible(!folders.is_empty());
+
+        for (folder, error) in folders {
+            list.append(&self.unavailable_folder_row(model, &folder, &error));
+        }
+    }
+
+    fn unavailable_folder_row(
+        &self,
+        model: &MemoriesLibraryListModel,
+        folder: &gio::File,
+        error: &glib::Error,
+    ) -> adw::ActionRow {
+        let can_mount: bool = mounts::can_mount_folder(folder);
+
+        let subtitle: String = if error.matches(gio::IOErrorEnum::NotFound) && can_mount {
+            gettext("This folder is on a drive or location that is not mounted")
+        } else if error.matches(gio::IOErrorEnum::PermissionDenied) {
+            gettext_f(
+                "{APP} does not have permission to read this folder",
+                &[("APP", APP_NAME)],
+            )
+        } else {
+            error.message().to_string()
+        };
+        let row: adw::ActionRow = adw::ActionRow::builder()
+            .title(folder.parse_name())
+            .subtitle(subtitle)
+            .use_markup(false)
+            .build();
+        row.add_prefix(&gtk::Image::from_icon_name("dialog-warning-symbolic"));
+
+        if can_mount {
+            let mount_button: gtk::Button = gtk::Button::builder()
+                .label(gettext("_Mount"))
+                .use_underline(true)
+                .valign(gtk::Align::Center)
+                .build();
+
+            mount_button.connect_clicked(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                model,
+                #[strong]
+                folder,
+                move |button: &gtk::Button| {
+                    button.set_sensitive(false);
+
+                    glib::spawn_future_local(clone!(
+                        #[weak]
+                        this,
+                        #[weak]
+                        model,
+                        #[weak]
+                        button,
+                        #[strong]
+                        folder,
+                        async move {
+                            match mounts::mount_folder(&folder, &this.window()).await {
+                                Ok(()) => model.refresh(),
+                                Err(error) => {
+                                    g_warning!("LibraryView", "Failed to mount folder: {}", error);
+                                    button.set_sensitive(true);
+
+                                    let toast: adw::Toast = adw::Toast::new(&gettext_f(
+                                        "Could not mount {FOLDER}",
+                                        &[("FOLDER", &folder.parse_name())],
+                                    ));
+                                    this.imp().media_grid.imp().toast_overlay.add_toast(toast);
+                                }
+                            }
+                        }
+                    ));
+                }
+            ));
+            row.add_suffix(&mount_button);
+        }
+        let remove_button: gtk::Button = gtk::Button::builder()
+            .icon_name("list-remove-symbolic")
+            .tooltip_text(gettext("Remove From Library"))
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .build();
+
+        remove_button.connect_clicked(clone!(
+            #[weak]
+            model,
+            #[strong]
+            folder,
+            move |_: &gtk::Button| {
+                if !MemoriesLibraryListModel::remove_configured_folder(&folder) {
+                    model.remove_folder(&folder);
+                }
+            }
+        ));
+        row.add_suffix(&remove_button);
+        row
+    }
+
+    /// Updates the progress bar and item counts shown while the library is
+    /// loading, and the progress bar over the grid during rescans.
+    fn update_scan_progress(&self, model: &MemoriesLibraryListModel) {
+        let items: u32 = model.items_enumerated();
+        let pending: u32 = model.directories_pending();
+
+        let items_label: String = ngettext_f(
+            "{COUNT} item found",
+            "{COUNT} items found",
+            items,
+            &[("COUNT", &items.to_string())],
+        );
+        let folders_label: String = ngettext_f(
+            "{COUNT} folder remaining",
+            "{COUNT} folders remaining",
+            pending,
+            &[("COUNT", &pending.to_string())],
+        );
+        self.imp().scan_progress_bar.set_fraction(model.progress());
+        self.imp()
+            .scan_progress_label
+            .set_label(&format!("{}\n{}", items_label, folders_label));
+
+        // The grid is only visible once the initial scan is done, so show
+        // its progress bar for any directories enumerated after that.
+        let rescanning: bool = model.models_loaded() && pending > 0;
+        self.imp()
+            .media_grid
+            .set_scan_progress(rescanning.then(|| model.progress()));
+    }
+
+    /// Returns a filter for the library model that hides media rated below the
+    /// minimum rating set in GSettings, and updates it when the setting or ratings change.
+    fn create_rating_filter(&self) -> gtk::CustomFilter {
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+        let filter: gtk::CustomFilter =
+            gtk::CustomFilter::new(rating_filter_func(gsettings.int("rating-filter")));
+
+        gsettings.connect_changed(
+            Some("rating-filter"),
+            clone!(
+                #[weak]
+                filter,
+                move |gsettings: &gio::Settings, _: &str| {
+                    filter.set_filter_func(rating_filter_func(gsettings.int("rating-filter")));
+                }
+            ),
+        );
+        MemoriesApplication::default().connect_metadata_changed(
+            Some("ratings"),
+            clone!(
+                #[weak]
+                filter,
+                move |memories: &MemoriesApplication, _: &str| {
+                    if memories.gsettings().int("rating-filter") > 0 {
+                        filter.changed(gtk::FilterChange::Different);
+                    }
+                }
+            ),
+        );
+        filter
+    }
+
+    /// Returns a filter for the library model that hides the media in a list,
+    /// such as archived or private media, and updates it when media are
+    /// added to or removed from the list.
+    fn create_hidden_filter(&self, list: MediaList) -> gtk::CustomFilter {
+        let memories: MemoriesApplication = MemoriesApplication::default();
+        let filter: gtk::CustomFilter =
+            gtk::CustomFilter::new(hidden_filter_func(&memories.media_list(list)));
+
+        memories.connect_metadata_changed(
+            Some(list.name()),
+            clone!(
+                #[weak]
+                filter,
+                move |memories: &MemoriesApplication, _: &str| {
+                    filter.set_filter_func(hidden_filter_func(&memories.media_list(list)));
+                }
+            ),
+        );
+        filter
+    }
+
+    /// Removes the previews of private media from the preview index when
+    /// media are moved to the Private album, so they are not left in the cache.
+    fn connect_private_changed(&self) {
+        MemoriesApplication::default().connect_metadata_changed(
+            Some(MediaList::Private.name()),
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |memories: &MemoriesApplication, _: &str| {
+                    let mut previews = this.imp().media_grid.imp().previews.borrow_mut();
+
+                    for uri in memories.private().iter() {
+                        previews.remove(uri.as_str());
+                    }
+                    if let Err(err) = previews.save() {
+                        g_warning!("LibraryView", "Failed to save preview index: {}", err);
+                    }
+                }
+            ),
+        );
+    }
+
+    /// Returns a filter for the library model that matches media against the search
+    /// query, and updates it when tags are changed or text in screenshots is recognized.
+    fn create_search_filter(&self) -> gtk::CustomFilter {
+        let filter: gtk::CustomFilter = gtk::CustomFilter::new(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[upgrade_or]
+            true,
+            move |item: &glib::Object| {
+                let query = this.imp().search_query.borrow();
+
+                if query.is_empty() {
+                    return true;
+                }
+                let Some(item) = item.downcast_ref::<MemoriesMediaItem>() else {
+                    return false;
+                };
+                let file: gio::File = item.file();
+                let file_name: String = file
+                    .basename()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                let text: String = MemoriesApplication::default()
+                    .scan_service()
+                    .recognized_text(&file.uri())
+                    .unwrap_or_default();
+
+                let tags: Vec<String> = if query.has_tags() {
+                    MemoriesApplication::default().tags(&file)
+                } else {
+                    vec![]
+                };
+
+                #[cfg(feature = "use-embeddings")]
+                if query.matches_tags(&tags)
+                    && this.imp().similarities.borrow().contains_key(file.uri().as_str())
+                {
+                    return true;
+                }
+                query.matches(&file_name, &text, &tags)
+            }
+        ));
+
+        MemoriesApplication::default().connect_metadata_changed(
+            Some("tags"),
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                filter,
+                move |_: &MemoriesApplication, _: &str| {
+                    if this.imp().search_query.borrow().has_tags() {
+                        filter.changed(gtk::FilterChange::Different);
+                    }
+                }
+            ),
+        );
+
+        // Screenshots can match the query once their text is recognized.
+        MemoriesApplication::default()
+            .scan_service()
+            .connect_text_recognized(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                filter,
+                move |_: &MemoriesScanService, _: &str| {
+                    if !this.imp().search_query.borrow().is_empty() {
+                        filter.changed(gtk::FilterChange::LessStrict);
+                    }
+                }
+            ));
+        filter
+    }
+
+    /// Returns a filter for the library model that hides the frames of bursts
+    /// other than their cover, which is shown with a badge to expand the burst.
+    fn create_burst_filter(&self) -> gtk::CustomFilter {
+        gtk::CustomFilter::new(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[upgrade_or]
+            true,
+            move |item: &glib::Object| {
+                !item
+                    .downcast_ref::<MemoriesMediaItem>()
+                    .is_some_and(|item: &MemoriesMediaItem| {
+                        this.imp()
+                            .media_grid
+                            .imp()
+                            .bursts
+                            .borrow()
+                            .is_collapsed(item.file().uri().as_str())
+                    })
+            }
+        ))
+    }
+
+    /// Detects bursts in the library model once the main loop is idle,
+    /// so that bursts are detected once for many changes to the model.
+    fn schedule_burst_detection(&self, model: &MemoriesLibraryListModel) {
+        if !model.models_loaded() || self.imp().burst_detection_source.borrow().is_some() {
+            return;
+        }
+        let source_id: glib::SourceId = glib::idle_add_local_once(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            model,
+            move || {
+                this.imp().burst_detection_source.take();
+                this.detect_bursts(&model);
+            }
+        ));
+        self.imp().burst_detection_source.replace(Some(source_id));
+    }
+
+    /// Groups photos taken in rapid sequence into bursts. See the [`bursts`] module.
+    fn detect_bursts(&self, model: &MemoriesLibraryListModel) {
+        let items: Vec<(String, Option<i64>)> = model
+            .iter::<glib::Object>()
+            .filter_map(Result::ok)
+            .filter_map(|item: glib::Object| item.downcast::<MemoriesMediaItem>().ok())
+            .map(|item: MemoriesMediaItem| (item.file().uri().to_string(), item.unix_time()))
+            .collect();
+        let frames: Vec<Frame> = items
+            .iter()
+            .map(|(uri, unix_time)| Frame {
+                uri,
+                unix_time: *unix_time,
+            })
+            .collect();
+
+        let picks: glib::StrV = MemoriesApplication::default().burst_picks();
+        let picks: Vec<&str> = picks.iter().map(glib::GStringPtr::as_str).collect();
+
+        self.imp().media_grid.set_bursts(Bursts::detect(&frames, &picks));
+
+        if let Some(filter) = self.imp().burst_filter.get() {
+            filter.changed(gtk::FilterChange::Different);
+        }
+    }
+
+    /// Filters the library by the given search query. See the
+    /// [`search`] module for the syntax of search queries.
+    pub fn set_search_query(&self, text: &str) {
+        let query: SearchQuery = SearchQuery::parse(text);
+
+        if *self.imp().search_query.borrow() == query {
+            return;
+        }
+        self.imp().media_grid.set_searching(!query.is_empty());
+        self.imp().search_query.replace(query);
+
+        if let Some(filter) = self.imp().search_filter.get() {
+            filter.changed(gtk::FilterChange::Different);
+        }
+        #[cfg(feature = "use-embeddings")]
+        self.update_similarities();
+    }
+
+    /// Embeds the plain terms of the search query on a separate thread, then
+    /// matches media that are similar enough to the query and ranks them.
+    #[cfg(feature = "use-embeddings")]
+    fn update_similarities(&self) {
+        let text: String = self.imp().search_query.borrow().semantic_text();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let similarities: HashMap<String, f32> = if text.is_empty() {
+                    HashMap::new()
+                } else {
+                    let query_text: String = text.clone();
+
+                    match gio::spawn_blocking(move || embeddings::text_embedding(&query_text)).await {
+                        Ok(Ok(embedding)) => MemoriesApplication::default()
+                            .scan_service()
+                            .similarities(&embedding),
+                        Ok(Err(err)) => {
+                            g_warning!("LibraryView", "Failed to embed search query: {}", err);
+                            HashMap::new()
+                        }
+                        Err(_) => {
+                            g_warning!("LibraryView", "Embedding thread panicked.");
+                            HashMap::new()
+                        }
+                    }
+                };
+                // The query may have changed while it was being embedded.
+                if this.imp().search_query.borrow().semantic_text() != text {
+                    return;
+                }
+                let sorter: Option<gtk::CustomSorter> =
+                    (!similarities.is_empty()).then(|| this.create_similarity_sorter());
+
+                this.imp().similarities.replace(similarities);
+
+                if let Some(ranking_model) = this.imp().ranking_model.get() {
+                    ranking_model.set_sorter(sorter.as_ref());
+                }
+                if let Some(filter) = this.imp().search_filter.get() {
+                    filter.changed(gtk::FilterChange::Different);
+                }
+            }
+        ));
+    }
+
+    /// Returns a sorter that ranks media by their similarity to the search query.
+    /// Media that match the query without being similar to it are ranked last.
+    #[cfg(feature = "use-embeddings")]
+    fn create_similarity_sorter(&self) -> gtk::CustomSorter {
+        gtk::CustomSorter::new(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[upgrade_or]
+            gtk::Ordering::Equal,
+            move |a: &glib::Object, b: &glib::Object| {
+                let similarities = this.imp().similarities.borrow();
+                let similarity = |item: &glib::Object| -> f32 {
+                    item.downcast_ref::<MemoriesMediaItem>()
+                        .and_then(|item: &MemoriesMediaItem| {
+                            similarities.get(item.file().uri().as_str()).copied()
+                        })
+                        .unwrap_or(f32::MIN)
+                };
+                similarity(b).total_cmp(&similarity(a)).into()
+            }
+        ))
+    }
+
     /// Called by MasterWindow once the Library view stack page is visible on screen.
     pub fn load_library(&self) {
         // Since we should be under the application window's widget tree,
         // it is now safe to setup the media grid's gactions.
         self.imp().media_grid.setup_gactions();
 
-        // First things first, check that the ffmpeg binary is installed.
+        // First things first, check that the ffmpeg binary is installed. Images can
+        // still be shown without it, so only video thumbnails are disabled if missing.
         if let Err(e) = Command::new(FFMPEG_BINARY).output() {
-            self.imp().library_view_stack.set_visible_child_name("error_page");
-
-            match e.kind() {
-                io::ErrorKind::NotFound => {
-                    self.imp().error_status_widget.set_description(Some(&gettext_f(
-                        "{BIN} was not found on your system. {APP} requires {BIN} to run.",
-                        &[("BIN", FFMPEG_BINARY), ("APP", APP_NAME)],
-                    )));
-                    return;
-                }
-                io::ErrorKind::PermissionDenied => {
-                    self.imp().error_status_widget.set_description(Some(&gettext_f(
-                        "{APP} does not have the sufficient permissions to run {BIN}.",
-                        &[("BIN", FFMPEG_BINARY), ("APP", APP_NAME)],
-                    )));
-                    return;
+            let banner_title: String = match e.kind() {
+                io::ErrorKind::NotFound => gettext_f(
+                    "{BIN} was not found on your system. Install {BIN} to enable video thumbnails.",
+                    &[("BIN", FFMPEG_BINARY)],
+                ),
+                io::ErrorKind::PermissionDenied => gettext_f(
+                    "{APP} does not have the sufficient permissions to run {BIN}. Video thumbnails are disabled.",
+                    &[("BIN", FFMPEG_BINARY), ("APP", APP_NAME)],
+                ),
+                _ => {
+                    g_warning!("LibraryView", "Unexpected error received at ffmpeg binary check: {}", e);
+                    gettext_f(
+                        "{BIN} could not be run. Video thumbnails are disabled.",
+                        &[("BIN", FFMPEG_BINARY)],
+                    )
                 }
-                _ => g_error!("LibraryView", "Unexpected error received at ffmpeg binary check."),
+            };
+            self.imp().media_grid.set_ffmpeg_available(false);
+
+            // Video thumbnails are still generated in-process if built with libav.
+            if !cfg!(feature = "use-libav") {
+                self.imp().ffmpeg_banner.set_title(&banner_title);
+                self.imp().ffmpeg_banner.set_revealed(true);
             }
         }
         self.imp().spinner.start();
@@ -153,7 +925,80 @@ impl MemoriesLibraryView {
         let memories: MemoriesApplication = self.window().app().unwrap();
         let library_model: MemoriesLibraryListModel = memories.library_list_model();
 
-        let msm: gtk::MultiSelection = gtk::MultiSelection::new(Some(library_model.clone()));
+        let search_filter: gtk::CustomFilter = self.create_search_filter();
+        let _ = self.imp().search_filter.set(search_filter.clone());
+
+        let burst_filter: gtk::CustomFilter = self.create_burst_filter();
+        let _ = self.imp().burst_filter.set(burst_filter.clone());
+
+        let filter: gtk::EveryFilter = gtk::EveryFilter::new();
+        filter.append(self.create_hidden_filter(MediaList::Archived));
+        filter.append(self.create_hidden_filter(MediaList::Private));
+        filter.append(self.create_rating_filter());
+        filter.append(burst_filter);
+        self.connect_private_changed();
+
+        // Media is searched in a model of its own, so that the amount of media
+        // shown when not searching is known, which is shown in the grid footer.
+        let shown_model: gtk::FilterListModel =
+            gtk::FilterListModel::new(Some(library_model.clone()), Some(filter));
+        shown_model
+            .bind_property("n-items", &self.imp().media_grid.get(), "total-items")
+            .sync_create()
+            .build();
+
+        let filter_model: gtk::FilterListModel =
+            gtk::FilterListModel::new(Some(shown_model), Some(search_filter));
+
+        // Media are only sorted while searching with embeddings, to rank them.
+        #[cfg(feature = "use-embeddings")]
+        let filter_model: gtk::SortListModel = {
+            let ranking_model: gtk::SortListModel =
+                gtk::SortListModel::new(Some(filter_model), None::<gtk::Sorter>);
+            let _ = self.imp().ranking_model.set(ranking_model.clone());
+            ranking_model
+        };
+        let msm: gtk::MultiSelection = gtk::MultiSelection::new(Some(filter_model));
+
+        for property in [
+            "items-enumerated",
+            "directories-pending",
+            "progress",
+            "models-loaded",
+        ] {
+            library_model.connect_notify_local(
+                Some(property),
+                clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    move |model: &MemoriesLibraryListModel, _| this.update_scan_progress(model)
+                ),
+            );
+        }
+        self.update_scan_progress(&library_model);
+
+        // Bursts are detected again whenever the library changes, or another frame is picked.
+        library_model.connect_items_changed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |model: &MemoriesLibraryListModel, _, _, _| this.schedule_burst_detection(model)
+        ));
+        library_model.connect_models_loaded_notify(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |model: &MemoriesLibraryListModel| this.schedule_burst_detection(model)
+        ));
+        memories.connect_metadata_changed(
+            Some(MediaList::BurstPicks.name()),
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                library_model,
+                move |_: &MemoriesApplication, _: &str| this.schedule_burst_detection(&library_model)
+            ),
+        );
+        self.schedule_burst_detection(&library_model);
 
         if !library_model.models_loaded() {
             library_model.connect_models_loaded_notify(clone!(
@@ -170,7 +1015,16 @@ impl MemoriesLibraryView {
                         match this.imp().view_mode.get() {
                             LibraryViewMode::Album => placeholder_page = "album_placeholder_page",
                             LibraryViewMode::Favorites => placeholder_page = "favorites_placeholder_page",
-                            _ => (),
+                            LibraryViewMode::Library => {
+                                let sandboxed_folders: Vec<gio::File> = Self::sandboxed_folders(model);
+
+                                if sandboxed_folders.is_empty() {
+                                    this.show_onboarding();
+                                } else {
+                                    this.show_permissions_page(&sandboxed_folders);
+                                    placeholder_page = "permissions_page";
+                                }
+                            }
                         }
                         this.imp()
                             .library_view_stack
@@ -183,6 +1037,16 @@ impl MemoriesLibraryView {
 
                     this.imp().spinner.stop();
 
+                    this.announce(
+                        &ngettext_f(
+                            "{COUNT} item loaded",
+                            "{COUNT} items loaded",
+                            item_count,
+                            &[("COUNT", &item_count.to_string())],
+                        ),
+                        gtk::AccessibleAnnouncementPriority::Low,
+                    );
+
                     let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
 
                     // If our cache is not populated, warn the user that this may take a while.
@@ -196,6 +1060,7 @@ impl MemoriesLibraryView {
 
                         let _ = gsettings.set_boolean("fresh-cache", false);
                     }
+                    this.imp().media_grid.restore_session();
                 }
             ));
         } else {
@@ -204,25 +1069,119 @@ impl MemoriesLibraryView {
                 .set_visible_child_name("gallery_page");
 
             self.imp().spinner.stop();
+            self.imp().media_grid.restore_session();
         }
-        /* FIXME
-        library_model.connect_error_notify(move |dl: &gtk::DirectoryList| {
-            g_error!(
-                "LibraryView",
-                "MemoriesLibraryListModel returned an error!\n\n{}",
-                dl.error().unwrap()
-            );
-        });*/
+        library_model.connect_enumeration_error(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |model: &MemoriesLibraryListModel, folder: &str, _: &glib::Error| {
+                // Library folders are listed with their own rows instead.
+                let folder_file: gio::File = gio::File::for_parse_name(folder);
+                let is_library_folder: bool = model
+                    .unavailable_folders()
+                    .iter()
+                    .any(|(file, _): &(gio::File, glib::Error)| file.equal(&folder_file));
+
+                if is_library_folder {
+                    this.update_unavailable_folders(model);
+                } else {
+                    this.add_unreadable_folder(folder);
+                }
+            }
+        ));
+        // Folders are listed again if they still cannot be read after a rescan.
+        library_model.connect_models_loaded_notify(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |model: &MemoriesLibraryListModel| {
+                if !model.models_loaded() {
+                    this.imp().unreadable_folders.borrow_mut().clear();
+                    this.imp().folders_banner.set_revealed(false);
+                }
+                this.update_unavailable_folders(model);
+            }
+        ));
 
         self.imp().media_grid.imp().photo_grid_view.set_model(Some(&msm));
 
-        if let Err(err_str) = library_model.start_enumerating_items() {
+        // Show the progress of indexing the library over the grid.
+        let scan_service: MemoriesScanService = memories.scan_service();
+
+        scan_service.connect_progress(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_: &MemoriesScanService, indexed: u32, total: u32| {
+                if total > 0 {
+                    this.imp()
+                        .media_grid
+                        .set_scan_progress(Some(indexed as f64 / total as f64));
+                }
+            }
+        ));
+        scan_service.connect_finished(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_: &MemoriesScanService| {
+                this.imp().media_grid.set_scan_progress(None);
+            }
+        ));
+
+        // Thumbnails are generated for the grid as it is currently shown.
+        for property in [
+            "grid-widget-height", "scale-factor", "hardware-accel", "hardware-accel-key", "ffmpeg-available",
+        ] {
+            self.imp().media_grid.connect_notify_local(
+                Some(property),
+                clone!(
+                    #[weak]
+                    scan_service,
+                    move |media_grid: &MemoriesMediaGridView, _| {
+                        scan_service.set_thumbnail_settings(media_grid.thumbnail_settings());
+                    }
+                ),
+            );
+        }
+
+        let media_grid: &MemoriesMediaGridView = &self.imp().media_grid;
+
+        if let Err(err_str) =
+            scan_service.start(&library_model, media_grid.thumbnail_settings(), Some(media_grid))
+        {
             self.imp().library_view_stack.set_visible_child_name("error_page");
             self.imp().error_status_widget.set_description(Some(&err_str));
         }
     }
 }
 
+/// Returns a filter function that matches media rated at least `min_rating` stars.
+/// A minimum rating of 0 matches all media, including unrated media.
+fn rating_filter_func(min_rating: i32) -> impl Fn(&glib::Object) -> bool + 'static {
+    let min_rating: u32 = min_rating.max(0) as u32;
+
+    move |item: &glib::Object| {
+        min_rating == 0
+            || item
+                .downcast_ref::<MemoriesMediaItem>()
+                .is_some_and(|item: &MemoriesMediaItem| {
+                    MemoriesApplication::default().rating(&item.file()) >= min_rating
+                })
+    }
+}
+
+/// Returns a filter function that matches media that are not in the given list of URIs.
+pub(crate) fn hidden_filter_func(hidden: &glib::StrV) -> impl Fn(&glib::Object) -> bool + 'static {
+    let hidden: HashSet<String> = hidden
+        .iter()
+        .map(|uri: &glib::GStringPtr| uri.to_string())
+        .collect();
+
+    move |item: &glib::Object| {
+        !item
+            .downcast_ref::<MemoriesMediaItem>()
+            .is_some_and(|item: &MemoriesMediaItem| hidden.contains(item.file().uri().as_str()))
+    }
+}
+
 impl Default for MemoriesLibraryView {
     fn default() -> Self {
         Self::new()
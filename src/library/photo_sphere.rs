@@ -0,0 +1,262 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Viewer of 360° photos, which projects an equirectangular photo onto the
+//! inside of a sphere with a GL shader. Dragging looks around the sphere,
+//! and scrolling or pinching zooms in and out by narrowing the field of view.
+//! The photo is drawn flat if the GL shader cannot be used by the renderer.
+
+use crate::globals::{PHOTO_SPHERE_DEFAULT_FOV, PHOTO_SPHERE_MAX_FOV, PHOTO_SPHERE_MIN_FOV};
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::{clone, g_debug};
+use gtk::{gdk, glib, gsk};
+use std::f64::consts::FRAC_PI_2;
+
+mod imp {
+    use crate::globals::PHOTO_SPHERE_DEFAULT_FOV;
+    use adw::prelude::*;
+    use adw::subclass::prelude::*;
+    use gtk::{gdk, glib, graphene, gsk};
+    use std::cell::{Cell, OnceCell, RefCell};
+
+    #[derive(Debug)]
+    pub struct MemoriesPhotoSphere {
+        pub(super) texture: RefCell<Option<gdk::Texture>>,
+        /// Horizontal direction that the viewer looks in, in radians.
+        pub(super) yaw: Cell<f64>,
+        /// Vertical direction that the viewer looks in, in radians.
+        pub(super) pitch: Cell<f64>,
+        /// Vertical field of view, in radians.
+        pub(super) fov: Cell<f64>,
+        /// Direction and field of view when a drag or pinch gesture began.
+        pub(super) gesture_start: Cell<(f64, f64, f64)>,
+        /// The compiled shader, or `None` if the renderer cannot compile it.
+        pub(super) shader: OnceCell<Option<gsk::GLShader>>,
+    }
+
+    impl Default for MemoriesPhotoSphere {
+        fn default() -> Self {
+            Self {
+                texture: RefCell::default(),
+                yaw: Cell::new(0.0),
+                pitch: Cell::new(0.0),
+                fov: Cell::new(PHOTO_SPHERE_DEFAULT_FOV.to_radians()),
+                gesture_start: Cell::default(),
+                shader: OnceCell::new(),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesPhotoSphere {
+        const NAME: &'static str = "MemoriesPhotoSphere";
+        type Type = super::MemoriesPhotoSphere;
+        type ParentType = gtk::Widget;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.set_css_name("photosphere");
+            klass.set_accessible_role(gtk::AccessibleRole::Img);
+        }
+    }
+
+    impl ObjectImpl for MemoriesPhotoSphere {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_gestures();
+        }
+    }
+
+    impl WidgetImpl for MemoriesPhotoSphere {
+        fn snapshot(&self, snapshot: &gtk::Snapshot) {
+            let Some(texture) = self.texture.borrow().clone() else {
+                return;
+            };
+            let obj = self.obj();
+            let bounds: graphene::Rect =
+                graphene::Rect::new(0.0, 0.0, obj.width() as f32, obj.height() as f32);
+
+            match obj.shader() {
+                Some(shader) => {
+                    let args_builder: gsk::ShaderArgsBuilder = gsk::ShaderArgsBuilder::new(&shader, None);
+                    args_builder.set_float(shader.find_uniform_by_name("yaw"), self.yaw.get() as f32);
+                    args_builder.set_float(shader.find_uniform_by_name("pitch"), self.pitch.get() as f32);
+                    args_builder.set_float(shader.find_uniform_by_name("fov"), self.fov.get() as f32);
+
+                    snapshot.push_gl_shader(&shader, &bounds, args_builder.to_args());
+                    snapshot.append_texture(&texture, &bounds);
+                    snapshot.gl_shader_pop_texture();
+                    snapshot.pop();
+                }
+                // Fall back to showing the whole photo flat, like other photos.
+                None => {
+                    let scale: f32 = (bounds.width() / texture.width() as f32)
+                        .min(bounds.height() / texture.height() as f32);
+                    let (width, height) = (texture.width() as f32 * scale, texture.height() as f32 * scale);
+
+                    snapshot.append_texture(
+                        &texture,
+                        &graphene::Rect::new(
+                            (bounds.width() - width) / 2.0,
+                            (bounds.height() - height) / 2.0,
+                            width,
+                            height,
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct MemoriesPhotoSphere(ObjectSubclass<imp::MemoriesPhotoSphere>)
+        @extends gtk::Widget;
+}
+
+impl MemoriesPhotoSphere {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Sets the equirectangular photo shown, and looks at its center.
+    pub fn set_texture(&self, texture: Option<&gdk::Texture>) {
+        self.imp().texture.replace(texture.cloned());
+        self.imp().yaw.set(0.0);
+        self.imp().pitch.set(0.0);
+        self.imp().fov.set(PHOTO_SPHERE_DEFAULT_FOV.to_radians());
+        self.queue_draw();
+    }
+
+    /// Returns the shader, compiling it for the renderer of the window
+    /// on first use. Only the OpenGL renderer of GTK supports GL shaders.
+    fn shader(&self) -> Option<gsk::GLShader> {
+        let renderer: gsk::Renderer = self.native()?.renderer()?;
+
+        self.imp()
+            .shader
+            .get_or_init(|| {
+                let shader: gsk::GLShader =
+                    gsk::GLShader::from_resource("/com/maxrdz/Memories/shaders/photo-sphere.glsl");
+
+                match shader.compile(&renderer) {
+                    Ok(()) => Some(shader),
+                    Err(err) => {
+                        g_debug!(
+                            "PhotoSphere",
+                            "Showing 360° photos flat, as the shader failed: {}",
+                            err
+                        );
+                        None
+                    }
+                }
+            })
+            .clone()
+    }
+
+    /// Looks in the given direction, with the pitch limited to straight up and down.
+    fn look_at(&self, yaw: f64, pitch: f64) {
+        self.imp().yaw.set(yaw.rem_euclid(std::f64::consts::TAU));
+        self.imp().pitch.set(pitch.clamp(-FRAC_PI_2, FRAC_PI_2));
+        self.queue_draw();
+    }
+
+    fn set_fov(&self, fov: f64) {
+        self.imp().fov.set(fov.clamp(
+            PHOTO_SPHERE_MIN_FOV.to_radians(),
+            PHOTO_SPHERE_MAX_FOV.to_radians(),
+        ));
+        self.queue_draw();
+    }
+
+    fn setup_gestures(&self) {
+        // Dragging moves the photo with the pointer, so the angle
+        // moved per pixel follows the field of view.
+        let drag_gesture: gtk::GestureDrag = gtk::GestureDrag::new();
+
+        drag_gesture.connect_drag_begin(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_: &gtk::GestureDrag, _: f64, _: f64| this.save_gesture_start()
+        ));
+        drag_gesture.connect_drag_update(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_: &gtk::GestureDrag, offset_x: f64, offset_y: f64| {
+                let (yaw, pitch, fov) = this.imp().gesture_start.get();
+                let radians_per_pixel: f64 = fov / this.height().max(1) as f64;
+
+                this.look_at(
+                    yaw - offset_x * radians_per_pixel,
+                    pitch + offset_y * radians_per_pixel,
+                );
+            }
+        ));
+        self.add_controller(drag_gesture);
+
+        let zoom_gesture: gtk::GestureZoom = gtk::GestureZoom::new();
+
+        zoom_gesture.connect_begin(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_: &gtk::GestureZoom, _: Option<&gdk::EventSequence>| this.save_gesture_start()
+        ));
+        zoom_gesture.connect_scale_changed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_: &gtk::GestureZoom, scale: f64| {
+                let (_, _, fov) = this.imp().gesture_start.get();
+                this.set_fov(fov / scale.max(f64::EPSILON));
+            }
+        ));
+        self.add_controller(zoom_gesture);
+
+        let scroll_controller: gtk::EventControllerScroll =
+            gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::VERTICAL);
+
+        scroll_controller.connect_scroll(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |controller: &gtk::EventControllerScroll, _: f64, dy: f64| {
+                let steps: f64 = match controller.unit() {
+                    gdk::ScrollUnit::Wheel => dy,
+                    _ => dy / 10.0,
+                };
+                this.set_fov(this.imp().fov.get() * 1.1_f64.powf(steps));
+                glib::Propagation::Stop
+            }
+        ));
+        self.add_controller(scroll_controller);
+    }
+
+    fn save_gesture_start(&self) {
+        self.imp()
+            .gesture_start
+            .set((self.imp().yaw.get(), self.imp().pitch.get(), self.imp().fov.get()));
+    }
+}
+
+impl Default for MemoriesPhotoSphere {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,176 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use adw::prelude::*;
+use gettextrs::gettext;
+use glib::{clone, g_warning};
+use gtk::{cairo, gdk, glib};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Resolution used to map image pixels to physical
+/// units when printing an image at its actual size.
+const ACTUAL_SIZE_DPI: f64 = 96.0;
+
+/// Enum that represents how an image is scaled onto the printed page.
+/// Variant order matches the scaling drop down in the print dialog.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum PrintScaling {
+    /// Scale the image to fit inside the page, keeping its aspect ratio.
+    #[default]
+    Fit,
+    /// Scale the image to cover the whole page, cropping its edges.
+    Fill,
+    /// Print the image at its actual size, which may be cropped.
+    ActualSize,
+}
+
+impl PrintScaling {
+    pub fn from_index(index: u32) -> PrintScaling {
+        match index {
+            1 => PrintScaling::Fill,
+            2 => PrintScaling::ActualSize,
+            _ => PrintScaling::Fit,
+        }
+    }
+
+    /// Returns the factor that an image of the given size in pixels must be
+    /// scaled by to be drawn on a page of the given size in points.
+    pub fn scale_factor(&self, image_size: (f64, f64), page_size: (f64, f64)) -> f64 {
+        let horizontal: f64 = page_size.0 / image_size.0;
+        let vertical: f64 = page_size.1 / image_size.1;
+
+        match *self {
+            PrintScaling::Fit => horizontal.min(vertical),
+            PrintScaling::Fill => horizontal.max(vertical),
+            PrintScaling::ActualSize => 72.0 / ACTUAL_SIZE_DPI,
+        }
+    }
+}
+
+/// Runs a print operation for the given texture, presenting the print dialog
+/// with a page setup tab and an additional tab for the image scaling mode.
+pub fn print_texture(texture: &gdk::Texture, job_name: &str, parent: Option<&gtk::Window>) {
+    let scaling: Rc<Cell<PrintScaling>> = Rc::new(Cell::new(PrintScaling::default()));
+    let print_operation: gtk::PrintOperation = gtk::PrintOperation::new();
+
+    print_operation.set_job_name(job_name);
+    print_operation.set_n_pages(1);
+    print_operation.set_unit(gtk::Unit::Points);
+    print_operation.set_embed_page_setup(true);
+    print_operation.set_custom_tab_label(Some(&gettext("Image")));
+
+    print_operation.connect_create_custom_widget(|_: &gtk::PrintOperation| {
+        let scaling_label: gtk::Label = gtk::Label::new(Some(&gettext("Scaling")));
+        let scaling_dropdown: gtk::DropDown = gtk::DropDown::from_strings(&[
+            &gettext("Fit to Page"),
+            &gettext("Fill Page"),
+            &gettext("Actual Size"),
+        ]);
+
+        let custom_tab: gtk::Box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(12)
+            .margin_top(12)
+            .margin_bottom(12)
+            .margin_start(12)
+            .margin_end(12)
+            .build();
+
+        custom_tab.append(&scaling_label);
+        custom_tab.append(&scaling_dropdown);
+        Some(custom_tab.upcast())
+    });
+
+    print_operation.connect_custom_widget_apply(clone!(
+        #[strong]
+        scaling,
+        move |_: &gtk::PrintOperation, custom_tab: &gtk::Widget| {
+            if let Some(dropdown) = custom_tab.last_child().and_downcast::<gtk::DropDown>() {
+                scaling.set(PrintScaling::from_index(dropdown.selected()));
+            }
+        }
+    ));
+
+    print_operation.connect_draw_page(clone!(
+        #[strong]
+        texture,
+        #[strong]
+        scaling,
+        move |_: &gtk::PrintOperation, context: &gtk::PrintContext, _: i32| {
+            if let Err(err) = draw_texture(&texture, scaling.get(), context) {
+                g_warning!("Print", "Failed to draw image on the printed page: {}", err);
+            }
+        }
+    ));
+
+    if let Err(err) = print_operation.run(gtk::PrintOperationAction::PrintDialog, parent) {
+        g_warning!("Print", "Failed to run print operation: {}", err);
+    }
+}
+
+/// Draws the texture centered on the page of the given print context.
+fn draw_texture(
+    texture: &gdk::Texture,
+    scaling: PrintScaling,
+    context: &gtk::PrintContext,
+) -> Result<(), cairo::Error> {
+    let width: i32 = texture.width();
+    let height: i32 = texture.height();
+    let stride: i32 = cairo::Format::ARgb32.stride_for_width(width as u32)?;
+
+    // The default texture download format is premultiplied BGRA, which
+    // matches the memory layout of a cairo ARGB32 surface on little endian.
+    let mut data: Vec<u8> = vec![0; (stride * height) as usize];
+    texture.download(&mut data, stride as usize);
+
+    let surface: cairo::ImageSurface =
+        cairo::ImageSurface::create_for_data(data, cairo::Format::ARgb32, width, height, stride)?;
+
+    let page_size: (f64, f64) = (context.width(), context.height());
+    let factor: f64 = scaling.scale_factor((width as f64, height as f64), page_size);
+
+    let cr: cairo::Context = context.cairo_context();
+
+    cr.rectangle(0.0, 0.0, page_size.0, page_size.1);
+    cr.clip();
+    cr.translate(
+        (page_size.0 - width as f64 * factor) / 2.0,
+        (page_size.1 - height as f64 * factor) / 2.0,
+    );
+    cr.scale(factor, factor);
+    cr.set_source_surface(&surface, 0.0, 0.0)?;
+    cr.paint()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn print_scale_factors() {
+        let image: (f64, f64) = (2000.0, 1000.0);
+        let page: (f64, f64) = (500.0, 700.0);
+
+        assert_eq!(PrintScaling::Fit.scale_factor(image, page), 0.25);
+        assert_eq!(PrintScaling::Fill.scale_factor(image, page), 0.7);
+        assert_eq!(PrintScaling::ActualSize.scale_factor(image, page), 0.75);
+    }
+}
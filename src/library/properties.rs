@@ -18,23 +18,34 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use crate::globals::PROPERTIES_FOLDER_MAX_CHARS;
 use crate::library::media_cell::MemoriesMediaCell;
+use crate::library::media_item::MemoriesMediaItem;
+use crate::library::metadata_dialog::MemoriesMetadataDialog;
+use crate::util::format;
+use crate::util::metadata::{extract_exif_tags, extract_xmp_tags, MetadataTag};
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use gettextrs::gettext;
-use glib::g_warning;
+use glib::{clone, g_warning};
 use gtk::{gio, glib};
 use std::ffi::OsStr;
 use std::ops::Deref;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 mod imp {
+    use super::ContentDetails;
     use adw::subclass::prelude::*;
-    use gtk::glib;
+    use gtk::{gio, glib};
+    use std::cell::RefCell;
 
     #[derive(Default, gtk::CompositeTemplate)]
     #[template(resource = "/com/maxrdz/Memories/ui/media-properties.ui")]
     pub struct MemoriesProperties {
+        /// The file that the properties are currently displayed for.
+        pub(super) file: RefCell<Option<gio::File>>,
+        pub(super) details: RefCell<ContentDetails>,
+
         #[template_child]
         pub(super) folder: TemplateChild<adw::ActionRow>,
         #[template_child]
@@ -59,6 +70,8 @@ mod imp {
         pub(super) focal_length: TemplateChild<adw::ActionRow>,
         #[template_child]
         pub(super) make_model: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub(super) all_metadata: TemplateChild<adw::ActionRow>,
     }
 
     #[glib::object_subclass]
@@ -69,6 +82,7 @@ mod imp {
 
         fn class_init(klass: &mut Self::Class) {
             klass.bind_template();
+            klass.bind_template_instance_callbacks();
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -91,6 +105,43 @@ impl PictureDetails {
 
         format!("{} x {}", height, width)
     }
+
+    /// Returns the EXIF `DateTimeOriginal` value of the picture, if present.
+    pub fn original_timestamp(&self) -> Option<glib::DateTime> {
+        let exif_bytes: Vec<u8> = self.0.details.exif.as_ref()?.get_full().ok()?;
+        let exif_data: exif::Exif = exif::Reader::new().read_raw(exif_bytes).ok()?;
+        let field: &exif::Field = exif_data.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?;
+
+        match field.value {
+            exif::Value::Ascii(ref values) if !values.is_empty() => {
+                let dt: exif::DateTime = exif::DateTime::from_ascii(&values[0]).ok()?;
+
+                glib::DateTime::from_local(
+                    dt.year.into(),
+                    dt.month.into(),
+                    dt.day.into(),
+                    dt.hour.into(),
+                    dt.minute.into(),
+                    dt.second.into(),
+                )
+                .ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns every EXIF and XMP tag found in the picture.
+    pub fn metadata_tags(&self) -> Vec<MetadataTag> {
+        let mut tags: Vec<MetadataTag> = vec![];
+
+        if let Some(exif_bytes) = self.0.details.exif.as_ref().and_then(|data| data.get_full().ok()) {
+            tags.append(&mut extract_exif_tags(exif_bytes));
+        }
+        if let Some(xmp_bytes) = self.0.details.xmp.as_ref().and_then(|data| data.get_full().ok()) {
+            tags.append(&mut extract_xmp_tags(&xmp_bytes));
+        }
+        tags
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -111,51 +162,169 @@ glib::wrapper! {
         @extends gtk::Widget, adw::Bin;
 }
 
+#[gtk::template_callbacks]
 impl MemoriesProperties {
     pub fn new() -> Self {
         glib::Object::new()
     }
 
-    /// Updates the preference rows in the details widget with
-    /// the content metadata from the `MemoriesMediaCell` object passed.
-    pub fn update_details(&self, cell_data: &MemoriesMediaCell) {
+    /// Updates the preference rows in the details widget with the details of
+    /// the media item, and the content metadata that its grid cell has loaded.
+    pub fn update_details(&self, item: &MemoriesMediaItem, cell_data: &MemoriesMediaCell) {
+        self.update_item_details(item);
+        self.imp()
+            .details
+            .replace(cell_data.imp().content_details.borrow().clone());
+
+        if let ContentDetails::Picture(img_data) = cell_data.imp().content_details.borrow().deref() {
+            if let Some(metadata) = cell_data.imp().file_metadata.get() {
+                self.imp().size.set_subtitle(&metadata.pretty_print_bytes());
+            }
+            self.imp()
+                .dimensions
+                .set_subtitle(&img_data.pretty_print_dimensions());
+
+            Self::update_date_row(&self.imp().timestamp, img_data.original_timestamp());
+        }
+    }
+
+    /// Updates the preference rows in the details widget with the details of
+    /// the media item, which were queried when its folder was enumerated.
+    pub fn update_item_details(&self, item: &MemoriesMediaItem) {
+        let file: gio::File = item.file();
+
         self.clear_rows();
+        self.imp().file.replace(Some(file.clone()));
+        self.imp().details.replace(ContentDetails::Missing);
+        self.update_folder_row(&file);
+        self.update_format_row(item);
+        self.update_file_times(item);
+    }
 
-        let gfile: &gio::File = cell_data.imp().file.get().unwrap();
+    /// Sets the format row subtitle to the name of the file format, told by the
+    /// file extension, or else by the content type of files without an extension.
+    fn update_format_row(&self, item: &MemoriesMediaItem) {
+        let format: Option<String> = Path::new(item.basename().as_str())
+            .extension()
+            .map(format_name)
+            .or_else(|| {
+                let content_type: String = item.content_type();
 
-        match cell_data.imp().content_details.borrow().deref() {
-            ContentDetails::Picture(img_data) => {
-                self.update_file_info(gfile);
+                (!content_type.is_empty() && !gio::content_type_is_unknown(&content_type))
+                    .then(|| gio::content_type_get_description(&content_type).to_string())
+            });
 
-                let metadata = cell_data.imp().file_metadata.get().unwrap();
-                let size: String = metadata.pretty_print_bytes();
-                let dimensions: String = img_data.pretty_print_dimensions();
+        Self::update_row(&self.imp().format, format);
+    }
 
-                self.imp().dimensions.set_subtitle(&dimensions);
-                self.imp().size.set_subtitle(&size);
-            }
-            ContentDetails::Video(_) => {
-                self.update_file_info(gfile);
-            }
-            ContentDetails::Missing => {
-                self.update_file_info(gfile);
-            }
+    /// Sets the folder row subtitle to the parent directory path of the file.
+    /// Long paths are truncated, with the full path available as a tooltip.
+    fn update_folder_row(&self, file: &gio::File) {
+        let folder_row: &adw::ActionRow = &self.imp().folder;
+
+        let parent_path: Option<PathBuf> = file.parent().and_then(|parent: gio::File| parent.path());
+
+        if let Some(path) = parent_path {
+            let full_path: String = path.to_string_lossy().to_string();
+
+            Self::update_row(folder_row, Some(truncate_folder_path(&full_path)));
+            folder_row.set_tooltip_text(Some(&full_path));
+        } else {
+            Self::update_row(folder_row, None::<String>);
+            folder_row.set_tooltip_text(None);
         }
     }
 
-    /// Updates details that we get from the `GFileInfo` object.
-    fn update_file_info(&self, file: &gio::File) {
-        let filename: PathBuf = file.basename().unwrap();
-        let file_ext: Option<&OsStr> = filename.extension();
-        if file_ext.is_none() {
-            g_warning!(
-                "Details",
-                "Got a missing extension while trying to update details."
-            );
-        }
-        let ext_str = file_ext.unwrap().to_str().unwrap().to_uppercase();
+    #[template_callback]
+    fn open_folder(&self) {
+        let Some(file) = self.imp().file.borrow().clone() else {
+            return;
+        };
+        let window: Option<gtk::Window> = self.root().and_downcast();
+        let launcher: gtk::FileLauncher = gtk::FileLauncher::new(Some(&file));
 
-        self.imp().format.set_subtitle(&ext_str);
+        launcher.open_containing_folder(
+            window.as_ref(),
+            None::<&gio::Cancellable>,
+            clone!(
+                #[strong]
+                file,
+                move |result: Result<(), glib::Error>| {
+                    if let Err(glib_error) = result {
+                        g_warning!(
+                            "Details",
+                            "Failed to open the containing folder of '{}': {}",
+                            file.uri(),
+                            glib_error
+                        );
+                    }
+                }
+            ),
+        );
+    }
+
+    #[template_callback]
+    fn show_all_metadata(&self) {
+        let tags: Vec<MetadataTag> = match self.imp().details.borrow().deref() {
+            ContentDetails::Picture(img_data) => img_data.metadata_tags(),
+            _ => vec![],
+        };
+        let dialog: MemoriesMetadataDialog = MemoriesMetadataDialog::new(tags);
+        dialog.present(Some(self));
+    }
+
+    /// Updates the preference rows in the details widget with the details of a
+    /// media file that is not an item of the library, such as a similar photo, or
+    /// media that was changed since it was enumerated. Its file info is queried first.
+    pub fn update_file_details(&self, file: &gio::File) {
+        self.clear_rows();
+        self.imp().file.replace(Some(file.clone()));
+        self.imp().details.replace(ContentDetails::Missing);
+        self.update_folder_row(file);
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[strong]
+            file,
+            async move {
+                match MemoriesMediaItem::for_file(&file).await {
+                    // Make sure other media was not shown in the meantime.
+                    Ok(item) if this.imp().file.borrow().as_ref() == Some(&file) => {
+                        this.update_item_details(&item);
+                    }
+                    Ok(_) => (),
+                    Err(glib_error) => g_warning!(
+                        "Details",
+                        "Failed to query the file info of '{}': {}",
+                        file.uri(),
+                        glib_error
+                    ),
+                }
+            }
+        ));
+    }
+
+    /// Updates the created and modified rows with the times of the media item.
+    fn update_file_times(&self, item: &MemoriesMediaItem) {
+        let modified: Option<glib::DateTime> = item
+            .modified()
+            .and_then(|date_time: glib::DateTime| date_time.to_local().ok());
+
+        Self::update_date_row(&self.imp().created, item.created());
+        Self::update_date_row(&self.imp().modified, modified);
+    }
+
+    /// Sets the row subtitle to the date formatted per the user's
+    /// locale, and the row tooltip to the relative time since the date.
+    fn update_date_row(row: &adw::ActionRow, value: Option<glib::DateTime>) {
+        let formatted: Option<String> = value.as_ref().and_then(format::date_time);
+
+        if Self::update_row(row, formatted) {
+            row.set_tooltip_text(Some(&format::relative_time(&value.unwrap())));
+        } else {
+            row.set_tooltip_text(None);
+        }
     }
 
     /// Sets all `AdwActionRow` widget subtitles to their placeholder text.
@@ -165,9 +334,9 @@ impl MemoriesProperties {
         Self::update_row(&imp.dimensions, None::<String>);
         Self::update_row(&imp.folder, None::<String>);
         Self::update_row(&imp.size, None::<String>);
-        Self::update_row(&imp.created, None::<String>);
-        Self::update_row(&imp.modified, None::<String>);
-        Self::update_row(&imp.timestamp, None::<String>);
+        Self::update_date_row(&imp.created, None);
+        Self::update_date_row(&imp.modified, None);
+        Self::update_date_row(&imp.timestamp, None);
         Self::update_row(&imp.aperture, None::<String>);
         Self::update_row(&imp.exposure, None::<String>);
         Self::update_row(&imp.iso, None::<String>);
@@ -190,8 +359,61 @@ impl MemoriesProperties {
     }
 }
 
+/// Returns the name of the file format shown for a file extension. Extensions
+/// that are spelled in more than one way are shown with the same name.
+fn format_name(extension: &OsStr) -> String {
+    let extension: String = extension.to_string_lossy().to_lowercase();
+
+    match extension.as_str() {
+        "jpg" | "jpeg" => "JPEG".into(),
+        "tif" | "tiff" => "TIFF".into(),
+        "heic" | "heif" => "HEIF".into(),
+        "jxl" => "JPEG XL".into(),
+        _ => extension.to_uppercase(),
+    }
+}
+
+/// Returns the given folder path with the user's home directory
+/// abbreviated as '~', and the start of the path truncated if
+/// it exceeds `PROPERTIES_FOLDER_MAX_CHARS` characters.
+fn truncate_folder_path(path: &str) -> String {
+    let home_dir: PathBuf = glib::home_dir();
+
+    let path: String = match Path::new(path).strip_prefix(&home_dir) {
+        Ok(relative) => format!("~/{}", relative.to_string_lossy()),
+        Err(_) => path.to_string(),
+    };
+    let char_count: usize = path.chars().count();
+
+    if char_count <= PROPERTIES_FOLDER_MAX_CHARS {
+        return path;
+    }
+    let tail: String = path
+        .chars()
+        .skip(char_count - (PROPERTIES_FOLDER_MAX_CHARS - 1))
+        .collect();
+
+    format!("…{}", tail)
+}
+
 impl Default for MemoriesProperties {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_names_of_extensions() {
+        assert_eq!(format_name(OsStr::new("jpg")), "JPEG");
+        assert_eq!(format_name(OsStr::new("JPEG")), "JPEG");
+        assert_eq!(format_name(OsStr::new("tif")), "TIFF");
+        assert_eq!(format_name(OsStr::new("heic")), "HEIF");
+        assert_eq!(format_name(OsStr::new("jxl")), "JPEG XL");
+        assert_eq!(format_name(OsStr::new("avif")), "AVIF");
+        assert_eq!(format_name(OsStr::new("png")), "PNG");
+    }
+}
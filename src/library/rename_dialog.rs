@@ -0,0 +1,451 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::application::MemoriesApplication;
+use crate::i18n::ngettext_f;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::{clone, g_warning};
+use gtk::{gio, glib};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+mod imp {
+    use super::RenameEntry;
+    use adw::subclass::prelude::*;
+    use gtk::glib;
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/rename-dialog.ui")]
+    pub struct MemoriesRenameDialog {
+        pub(super) entries: RefCell<Vec<RenameEntry>>,
+        #[template_child]
+        pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) rename_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) spinner: TemplateChild<gtk::Spinner>,
+        #[template_child]
+        pub(super) pattern_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub(super) counter_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) preview_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) preview_list: TemplateChild<gtk::ListBox>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesRenameDialog {
+        const NAME: &'static str = "MemoriesRenameDialog";
+        type Type = super::MemoriesRenameDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesRenameDialog {}
+    impl WidgetImpl for MemoriesRenameDialog {}
+    impl AdwDialogImpl for MemoriesRenameDialog {}
+}
+
+/// A file to be renamed, with the details used to fill in the rename pattern.
+#[derive(Debug)]
+pub struct RenameEntry {
+    file: gio::File,
+    stem: String,
+    extension: Option<String>,
+    modified: Option<glib::DateTime>,
+}
+
+/// Returns the new file name (without its extension) for a rename pattern.
+///
+/// Supported tokens are `{NAME}` for the original file name, `{N}` for the
+/// counter (zero padded to the amount of `N` characters, such as `{NNN}`),
+/// and `{YYYY}`, `{MM}`, `{DD}`, `{hh}`, `{mm}`, `{ss}` for the date and time
+/// the file was last modified. Unknown tokens are kept as they are.
+pub fn format_file_name(pattern: &str, stem: &str, counter: u32, date: Option<&glib::DateTime>) -> String {
+    let mut name: String = String::with_capacity(pattern.len());
+    let mut rest: &str = pattern;
+
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+
+        let Some(length) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let token: &str = &rest[start + 1..start + length];
+        let date_field = |f: fn(&glib::DateTime) -> i32, width: usize| {
+            date.map(|date: &glib::DateTime| format!("{:0width$}", f(date), width = width))
+        };
+
+        let replacement: Option<String> = match token {
+            "NAME" => Some(stem.to_string()),
+            "YYYY" => date_field(glib::DateTime::year, 4),
+            "MM" => date_field(glib::DateTime::month, 2),
+            "DD" => date_field(glib::DateTime::day_of_month, 2),
+            "hh" => date_field(glib::DateTime::hour, 2),
+            "mm" => date_field(glib::DateTime::minute, 2),
+            "ss" => date_field(glib::DateTime::second, 2),
+            _ if !token.is_empty() && token.chars().all(|c: char| c == 'N') => {
+                Some(format!("{:0width$}", counter, width = token.len()))
+            }
+            _ => None,
+        };
+        match replacement {
+            Some(replacement) => name.push_str(&replacement),
+            None => name.push_str(&rest[start..=start + length]),
+        }
+        rest = &rest[start + length + 1..];
+    }
+    name.push_str(rest);
+    name
+}
+
+/// Returns whether each new file name is valid for its file, where files are given as
+/// pairs of the URI of their folder and their current name. A new name must not be blank,
+/// also before its extension, hidden, or contain a path separator, and must not be the
+/// new or current name of another file in the same folder.
+fn valid_new_names(files: &[(String, String)], new_names: &[String]) -> Vec<bool> {
+    let mut new_name_counts: HashMap<(&str, &str), u32> = HashMap::new();
+
+    for ((folder, _), new_name) in files.iter().zip(new_names) {
+        *new_name_counts.entry((folder, new_name)).or_default() += 1;
+    }
+    let current_names: HashSet<(&str, &str)> = files
+        .iter()
+        .map(|(folder, name)| (folder.as_str(), name.as_str()))
+        .collect();
+
+    files
+        .iter()
+        .zip(new_names)
+        .map(|((folder, name), new_name)| {
+            let stem: &str = new_name
+                .rsplit_once('.')
+                .map_or(new_name.as_str(), |(stem, _)| stem);
+            let target: (&str, &str) = (folder, new_name);
+
+            let collides: bool = new_name_counts.get(&target).is_some_and(|count: &u32| *count > 1)
+                || (new_name != name && current_names.contains(&target));
+
+            !stem.trim().is_empty() && !new_name.starts_with('.') && !new_name.contains('/') && !collides
+        })
+        .collect()
+}
+
+glib::wrapper! {
+    pub struct MemoriesRenameDialog(ObjectSubclass<imp::MemoriesRenameDialog>)
+        @extends gtk::Widget, adw::Dialog;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesRenameDialog {
+    /// Creates a new rename dialog for the given files, and queries
+    /// their modification dates in the background for the date tokens.
+    pub fn new(files: &[gio::File]) -> Self {
+        let obj: Self = glib::Object::new();
+
+        let entries: Vec<RenameEntry> = files
+            .iter()
+            .map(|file: &gio::File| {
+                let basename = file.basename().unwrap_or_default();
+                let path: &Path = basename.as_path();
+
+                RenameEntry {
+                    file: file.clone(),
+                    stem: path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    extension: path.extension().map(|ext| ext.to_string_lossy().to_string()),
+                    modified: None,
+                }
+            })
+            .collect();
+
+        obj.imp().entries.replace(entries);
+        obj.imp().preview_group.set_title(&ngettext_f(
+            "Renaming {COUNT} Item",
+            "Renaming {COUNT} Items",
+            files.len().try_into().unwrap_or(u32::MAX),
+            &[("COUNT", &files.len().to_string())],
+        ));
+        obj.update_preview();
+
+        glib::spawn_future_local(clone!(
+            #[weak]
+            obj,
+            async move {
+                obj.query_modification_dates().await;
+                obj.update_preview();
+            }
+        ));
+        obj
+    }
+
+    async fn query_modification_dates(&self) {
+        let files: Vec<gio::File> = self
+            .imp()
+            .entries
+            .borrow()
+            .iter()
+            .map(|entry: &RenameEntry| entry.file.clone())
+            .collect();
+
+        for (i, file) in files.iter().enumerate() {
+            let modified: Option<glib::DateTime> = match file
+                .query_info_future(
+                    gio::FILE_ATTRIBUTE_TIME_MODIFIED,
+                    gio::FileQueryInfoFlags::NONE,
+                    glib::Priority::DEFAULT,
+                )
+                .await
+            {
+                Ok(file_info) => file_info.modification_date_time(),
+                Err(glib_error) => {
+                    g_warning!(
+                        "RenameDialog",
+                        "Failed to query modification date: {}",
+                        glib_error
+                    );
+                    None
+                }
+            };
+            if let Some(entry) = self.imp().entries.borrow_mut().get_mut(i) {
+                entry.modified = modified.and_then(|date: glib::DateTime| date.to_local().ok());
+            }
+        }
+    }
+
+    /// Returns the new file names for all entries, including their extensions.
+    fn new_file_names(&self) -> Vec<String> {
+        let pattern: glib::GString = self.imp().pattern_row.text();
+        let first_counter: u32 = self.imp().counter_row.value() as u32;
+
+        self.imp()
+            .entries
+            .borrow()
+            .iter()
+            .enumerate()
+            .map(|(i, entry): (usize, &RenameEntry)| {
+                let counter: u32 = first_counter + i as u32;
+                let stem: String = format_file_name(&pattern, &entry.stem, counter, entry.modified.as_ref());
+
+                match &entry.extension {
+                    Some(extension) => format!("{}.{}", stem, extension),
+                    None => stem,
+                }
+            })
+            .collect()
+    }
+
+    /// Rebuilds the list of previewed file names, and only allows renaming
+    /// if all new file names are valid and do not collide within the selection.
+    #[template_callback]
+    fn update_preview(&self) {
+        let preview_list: &gtk::ListBox = &self.imp().preview_list;
+        preview_list.remove_all();
+
+        let new_names: Vec<String> = self.new_file_names();
+        let files: Vec<(String, String)> = self
+            .imp()
+            .entries
+            .borrow()
+            .iter()
+            .map(|entry: &RenameEntry| {
+                let folder: String = entry
+                    .file
+                    .parent()
+                    .map(|parent: gio::File| parent.uri().to_string())
+                    .unwrap_or_default();
+                let name: String = entry
+                    .file
+                    .basename()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                (folder, name)
+            })
+            .collect();
+        let valid_names: Vec<bool> = valid_new_names(&files, &new_names);
+
+        for (((_, old_name), new_name), is_valid) in files.iter().zip(&new_names).zip(&valid_names) {
+            let row: adw::ActionRow = adw::ActionRow::builder()
+                .title(glib::markup_escape_text(new_name))
+                .subtitle(glib::markup_escape_text(old_name))
+                .build();
+
+            if !is_valid {
+                row.add_css_class("error");
+            }
+            preview_list.append(&row);
+        }
+        self.imp()
+            .rename_button
+            .set_sensitive(valid_names.iter().all(|is_valid: &bool| *is_valid));
+    }
+
+    #[template_callback]
+    fn cancel_clicked(&self) {
+        self.close();
+    }
+
+    #[template_callback]
+    fn rename_clicked(&self) {
+        let new_names: Vec<String> = self.new_file_names();
+        let files: Vec<gio::File> = self
+            .imp()
+            .entries
+            .borrow()
+            .iter()
+            .map(|entry: &RenameEntry| entry.file.clone())
+            .collect();
+
+        self.imp().rename_button.set_sensitive(false);
+        self.imp().spinner.set_visible(true);
+        self.imp().spinner.start();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let mut failed: u32 = 0;
+                let mut renamed: Vec<(gio::File, gio::File)> = vec![];
+
+                for (file, new_name) in files.iter().zip(new_names.iter()) {
+                    if file
+                        .basename()
+                        .is_some_and(|name| name.as_os_str() == new_name.as_str())
+                    {
+                        continue;
+                    }
+                    match file
+                        .set_display_name_future(new_name, glib::Priority::DEFAULT)
+                        .await
+                    {
+                        Ok(new_file) => renamed.push((file.clone(), new_file)),
+                        Err(glib_error) => {
+                            g_warning!(
+                                "RenameDialog",
+                                "Failed to rename '{}' to '{}': {}",
+                                file.uri(),
+                                new_name,
+                                glib_error
+                            );
+                            failed += 1;
+                        }
+                    }
+                }
+                // Favorites, ratings, tags and sidecars are kept by file URI and name.
+                if !renamed.is_empty() {
+                    MemoriesApplication::default().move_media_metadata(&renamed).await;
+                }
+                this.imp().spinner.stop();
+                this.imp().spinner.set_visible(false);
+
+                if failed == 0 {
+                    this.close();
+                    return;
+                }
+                let toast: adw::Toast = adw::Toast::new(&ngettext_f(
+                    "Failed to rename {COUNT} item",
+                    "Failed to rename {COUNT} items",
+                    failed,
+                    &[("COUNT", &failed.to_string())],
+                ));
+                this.imp().toast_overlay.add_toast(toast);
+                this.imp().preview_group.set_description(Some(&gettext(
+                    "Some items were not renamed. Close this dialog to see the changes.",
+                )));
+            }
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_name_tokens() {
+        assert_eq!(format_file_name("{NAME}", "IMG_0001", 1, None), "IMG_0001");
+        assert_eq!(format_file_name("Trip-{NNN}", "IMG_0001", 7, None), "Trip-007");
+        assert_eq!(format_file_name("{N}", "IMG_0001", 1234, None), "1234");
+        assert_eq!(format_file_name("{FOO}-{N", "IMG_0001", 1, None), "{FOO}-{N");
+    }
+
+    #[test]
+    fn format_name_date_tokens() {
+        let date: glib::DateTime = glib::DateTime::from_utc(2024, 3, 9, 14, 5, 7.0).unwrap();
+
+        assert_eq!(
+            format_file_name("{YYYY}-{MM}-{DD}_{hh}{mm}{ss}", "IMG", 1, Some(&date)),
+            "2024-03-09_140507"
+        );
+        assert_eq!(format_file_name("{YYYY}", "IMG", 1, None), "{YYYY}");
+    }
+
+    #[test]
+    fn new_name_validation() {
+        let files = |names: &[(&str, &str)]| -> Vec<(String, String)> {
+            names
+                .iter()
+                .map(|(folder, name)| (folder.to_string(), name.to_string()))
+                .collect()
+        };
+        let new_names =
+            |names: &[&str]| -> Vec<String> { names.iter().map(|name| name.to_string()).collect() };
+
+        let single: Vec<(String, String)> = files(&[("file:///a", "IMG_1.jpg")]);
+        assert_eq!(valid_new_names(&single, &new_names(&["Trip.jpg"])), [true]);
+        assert_eq!(valid_new_names(&single, &new_names(&["IMG_1.jpg"])), [true]);
+        assert_eq!(valid_new_names(&single, &new_names(&[""])), [false]);
+        assert_eq!(valid_new_names(&single, &new_names(&["   "])), [false]);
+        assert_eq!(valid_new_names(&single, &new_names(&["  .jpg"])), [false]);
+        assert_eq!(valid_new_names(&single, &new_names(&[".jpg"])), [false]);
+        assert_eq!(valid_new_names(&single, &new_names(&["a/b.jpg"])), [false]);
+
+        // Names only collide with names of files in the same folder.
+        let batch: Vec<(String, String)> = files(&[
+            ("file:///a", "IMG_1.jpg"),
+            ("file:///a", "IMG_2.jpg"),
+            ("file:///b", "IMG_3.jpg"),
+        ]);
+        assert_eq!(
+            valid_new_names(&batch, &new_names(&["Trip.jpg", "Trip.jpg", "Trip.jpg"])),
+            [false, false, true]
+        );
+        assert_eq!(
+            valid_new_names(&batch, &new_names(&["IMG_2.jpg", "IMG_3.jpg", "IMG_1.jpg"])),
+            [false, true, true]
+        );
+    }
+}
@@ -0,0 +1,211 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Library roots, which are the folders that the library is made of.
+//!
+//! Roots are stored in GSettings as paths, as URIs of GVFS locations, or as
+//! IDs of documents shared by the document portal. Folders chosen through the
+//! file chooser portal in a sandbox are only readable through the portal's
+//! mount point, which is looked up again at startup instead of being stored.
+
+use crate::application::MemoriesApplication;
+use crate::globals::{
+    DOCUMENT_PORTAL_BUS_NAME, DOCUMENT_PORTAL_FALLBACK_DIR, DOCUMENT_PORTAL_INTERFACE,
+    DOCUMENT_PORTAL_OBJECT_PATH,
+};
+use adw::prelude::*;
+use glib::g_debug;
+use gtk::{gio, glib};
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Prefix of the GSettings value of a root shared by the document portal.
+const DOCUMENT_PREFIX: &str = "document:";
+
+/// A folder that was added to the library in the preferences.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LibraryRoot {
+    /// A local folder by its path, or a GVFS location by its URI.
+    Location(String),
+    /// A folder shared by the document portal, by its document ID
+    /// and its path relative to the document's folder.
+    Document { id: String, path: PathBuf },
+}
+
+impl LibraryRoot {
+    /// Parses a root from its value in the `library-folders` GSettings key.
+    pub fn from_setting(value: &str) -> Self {
+        value
+            .strip_prefix(DOCUMENT_PREFIX)
+            .and_then(|document: &str| document.split_once('/'))
+            .map(|(id, path): (&str, &str)| Self::Document {
+                id: id.to_string(),
+                path: PathBuf::from(path),
+            })
+            .unwrap_or_else(|| Self::Location(value.to_string()))
+    }
+
+    /// Returns the value of the root stored in the `library-folders` GSettings key.
+    pub fn to_setting(&self) -> String {
+        match self {
+            Self::Location(location) => location.clone(),
+            Self::Document { id, path } => format!("{}{}/{}", DOCUMENT_PREFIX, id, path.display()),
+        }
+    }
+
+    /// Returns the root of a folder. Folders in the document portal's mount
+    /// point are stored by their document ID, since the mount point may change.
+    pub fn for_file(file: &gio::File) -> Self {
+        let path: Option<PathBuf> = file.path().filter(|_| file.is_native());
+
+        match path {
+            Some(path) => Self::for_path_in(&path, document_mount_point())
+                .unwrap_or_else(|| Self::Location(path.to_string_lossy().to_string())),
+            None => Self::Location(file.uri().to_string()),
+        }
+    }
+
+    /// Returns the root of a path in the document portal's `mount_point`.
+    fn for_path_in(path: &Path, mount_point: &Path) -> Option<Self> {
+        let relative_path: &Path = path.strip_prefix(mount_point).ok()?;
+        let mut components = relative_path.iter();
+
+        let id: &OsStr = components.next()?;
+        let path: PathBuf = components.collect();
+
+        if path.as_os_str().is_empty() {
+            return None;
+        }
+        Some(Self::Document {
+            id: id.to_string_lossy().to_string(),
+            path,
+        })
+    }
+
+    /// Returns the folder of the root, which is resolved
+    /// through the document portal's current mount point.
+    pub fn file(&self) -> gio::File {
+        match self {
+            Self::Location(location) => gio::File::for_commandline_arg(location),
+            Self::Document { id, path } => gio::File::for_path(document_mount_point().join(id).join(path)),
+        }
+    }
+}
+
+/// Returns the roots added to the library in the preferences.
+pub fn configured_roots() -> Vec<LibraryRoot> {
+    MemoriesApplication::default()
+        .gsettings()
+        .strv("library-folders")
+        .iter()
+        .map(|value: &glib::GStringPtr| LibraryRoot::from_setting(value.as_str()))
+        .collect()
+}
+
+/// Returns the path that the document portal mounts shared documents at.
+/// Looked up from the portal once, since it does not change while running.
+fn document_mount_point() -> &'static Path {
+    static MOUNT_POINT: OnceLock<PathBuf> = OnceLock::new();
+
+    MOUNT_POINT.get_or_init(|| {
+        let reply: Result<glib::Variant, glib::Error> =
+            gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE).and_then(
+                |connection: gio::DBusConnection| {
+                    connection.call_sync(
+                        Some(DOCUMENT_PORTAL_BUS_NAME),
+                        DOCUMENT_PORTAL_OBJECT_PATH,
+                        DOCUMENT_PORTAL_INTERFACE,
+                        "GetMountPoint",
+                        None,
+                        Some(glib::VariantTy::new("(ay)").unwrap()),
+                        gio::DBusCallFlags::NONE,
+                        -1,
+                        gio::Cancellable::NONE,
+                    )
+                },
+            );
+
+        match reply {
+            Ok(reply) => {
+                // The mount point is a NUL terminated byte string.
+                let bytes: Vec<u8> = reply.child_value(0).get::<Vec<u8>>().unwrap_or_default();
+                let bytes: &[u8] = bytes.strip_suffix(&[0]).unwrap_or(&bytes);
+
+                PathBuf::from(OsStr::from_bytes(bytes))
+            }
+            Err(err) => {
+                g_debug!("LibraryRoots", "Document portal is not available: {}", err);
+                glib::user_runtime_dir().join(DOCUMENT_PORTAL_FALLBACK_DIR)
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip() {
+        let roots: [LibraryRoot; 3] = [
+            LibraryRoot::Location("/home/user/Photos".to_string()),
+            LibraryRoot::Location("smb://server/share/Photos".to_string()),
+            LibraryRoot::Document {
+                id: "a1b2c3".to_string(),
+                path: PathBuf::from("Photos"),
+            },
+        ];
+        for root in roots {
+            assert_eq!(LibraryRoot::from_setting(&root.to_setting()), root);
+        }
+        assert_eq!(
+            LibraryRoot::from_setting("document:a1b2c3/Camera/2024").to_setting(),
+            "document:a1b2c3/Camera/2024"
+        );
+    }
+
+    #[test]
+    fn document_paths() {
+        let mount_point: &Path = Path::new("/run/user/1000/doc");
+
+        assert_eq!(
+            LibraryRoot::for_path_in(Path::new("/run/user/1000/doc/a1b2c3/Photos"), mount_point),
+            Some(LibraryRoot::Document {
+                id: "a1b2c3".to_string(),
+                path: PathBuf::from("Photos"),
+            })
+        );
+        // The mount point and document folders themselves are not roots.
+        assert_eq!(
+            LibraryRoot::for_path_in(Path::new("/run/user/1000/doc/a1b2c3"), mount_point),
+            None
+        );
+        assert_eq!(
+            LibraryRoot::for_path_in(Path::new("/run/user/1000/doc"), mount_point),
+            None
+        );
+        assert_eq!(
+            LibraryRoot::for_path_in(Path::new("/home/user/Photos"), mount_point),
+            None
+        );
+    }
+}
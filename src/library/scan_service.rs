@@ -0,0 +1,819 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Background indexing of the library. The scan service enumerates the
+//! library folders, then reads the metadata of each media file and generates
+//! its thumbnail ahead of time, so that scrolling through the grid is fast.
+//! The scan can be paused, such as while running on battery or in power
+//! saver mode, and is cancelled when the application shuts down. The system
+//! does not suspend until the library is first indexed. When built
+//! with the `use-ocr` feature, the text in screenshots is also recognized, and
+//! when built with the `use-embeddings` feature, thumbnails are also embedded.
+//! Photos are also perceptually hashed, to find photos that look alike, and
+//! the contents of all media are hashed, to recognize media that is imported
+//! again from a device.
+//! Several items are indexed at once, so that reading their metadata and
+//! hashing them runs in parallel on the blocking thread pool.
+
+use crate::application::{MemoriesApplication, SuspendInhibitor};
+#[cfg(feature = "use-embeddings")]
+use crate::globals::EMBEDDING_MATCH_THRESHOLD;
+use crate::globals::{
+    SCAN_MAX_CONCURRENT_ITEMS, SIMILAR_MAX_DISTANCE, SIMILAR_MAX_RESULTS, UPOWER_BUS_NAME, UPOWER_INTERFACE,
+    UPOWER_OBJECT_PATH,
+};
+use crate::library::list_model::MemoriesLibraryListModel;
+use crate::library::media_cell::imp::MemoriesMediaCell;
+use crate::library::media_grid::{self, MemoriesMediaGridView};
+use crate::library::media_item::MemoriesMediaItem;
+use crate::library::media_viewer::ViewerContentType;
+use crate::util::content_hash;
+#[cfg(feature = "use-embeddings")]
+use crate::util::embeddings;
+use crate::util::hwaccel::HardwareAccel;
+use crate::util::metadata::get_metadata_with_hash;
+#[cfg(feature = "use-ocr")]
+use crate::util::ocr;
+use crate::util::phash;
+use crate::util::power;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use async_semaphore::Semaphore;
+use gettextrs::gettext;
+use glib::{clone, g_debug, g_warning};
+use gtk::{gio, glib};
+#[cfg(feature = "use-embeddings")]
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Poll;
+
+mod imp {
+    use super::ThumbnailSettings;
+    use crate::application::{MemoriesApplication, SuspendInhibitor};
+    use crate::library::list_model::MemoriesLibraryListModel;
+    use crate::library::media_grid::MemoriesMediaGridView;
+    use crate::util::content_hash::ContentHashIndex;
+    #[cfg(feature = "use-embeddings")]
+    use crate::util::embeddings::EmbeddingIndex;
+    use crate::util::ocr::TextIndex;
+    use crate::util::phash::PerceptualHashIndex;
+    use adw::prelude::*;
+    use adw::subclass::prelude::*;
+    use async_semaphore::Semaphore;
+    use glib::clone;
+    use glib::subclass::Signal;
+    use gtk::{gio, glib};
+    use std::cell::{Cell, RefCell};
+    use std::sync::{Arc, OnceLock};
+
+    #[derive(Debug, Default, glib::Properties)]
+    #[properties(wrapper_type = super::MemoriesScanService)]
+    pub struct MemoriesScanService {
+        /// Whether a scan was started, and was not cancelled since.
+        #[property(get)]
+        pub(super) running: Cell<bool>,
+        /// Whether the scan was paused by calling `pause()`.
+        #[property(get)]
+        pub(super) paused: Cell<bool>,
+        /// Whether the system is running on battery, as reported by UPower.
+        #[property(get)]
+        pub(super) on_battery: Cell<bool>,
+        /// Whether indexing is waiting to be resumed, because it was paused, or to save power.
+        #[property(get)]
+        pub(super) waiting: Cell<bool>,
+        pub(super) library_model: RefCell<Option<MemoriesLibraryListModel>>,
+        pub(super) thumbnail_settings: Cell<Option<ThumbnailSettings>>,
+        /// Grid of the library, if shown, which previews are cached for.
+        pub(super) media_grid: glib::WeakRef<MemoriesMediaGridView>,
+        /// Limits the thumbnail processes spawned at once while the library is not shown.
+        pub(super) subprocess_semaphore: RefCell<Option<Arc<Semaphore>>>,
+        pub(super) models_loaded_handler: RefCell<Option<glib::SignalHandlerId>>,
+        /// Cancelled to stop the scan. A new one is made for every scan.
+        pub(super) cancellable: RefCell<Option<gio::Cancellable>>,
+        /// Position in the library model of the next item to index.
+        pub(super) next_position: Cell<u32>,
+        /// Whether a pass over the library items is in progress.
+        pub(super) pass_active: Cell<bool>,
+        /// Whether every library item was indexed once since the application started.
+        pub(super) indexed_once: Cell<bool>,
+        /// Keeps the system from suspending while the library is first indexed.
+        pub(super) suspend_inhibitor: RefCell<Option<SuspendInhibitor>>,
+        pub(super) upower_proxy: RefCell<Option<gio::DBusProxy>>,
+        pub(super) power_profile_monitor: RefCell<Option<gio::PowerProfileMonitor>>,
+        pub(super) text_index: RefCell<TextIndex>,
+        pub(super) hash_index: RefCell<PerceptualHashIndex>,
+        pub(super) content_index: RefCell<ContentHashIndex>,
+        #[cfg(feature = "use-embeddings")]
+        pub(super) embedding_index: RefCell<EmbeddingIndex>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesScanService {
+        const NAME: &'static str = "MemoriesScanService";
+        type Type = super::MemoriesScanService;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for MemoriesScanService {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    // Emitted with the amount of items indexed and the total
+                    // amount of items in the library, after every item.
+                    Signal::builder("progress")
+                        .param_types([u32::static_type(), u32::static_type()])
+                        .build(),
+                    // Emitted once every item in the library is indexed.
+                    Signal::builder("finished").build(),
+                    // Emitted with the URI of a screenshot after its text is recognized.
+                    Signal::builder("text-recognized")
+                        .param_types([String::static_type()])
+                        .build(),
+                    // Emitted with the URI of a media file after it was changed, such as
+                    // by an external editor, so that its thumbnail is generated again.
+                    Signal::builder("file-changed")
+                        .param_types([String::static_type()])
+                        .build(),
+                ]
+            })
+        }
+
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+
+            self.text_index.replace(TextIndex::load());
+            self.hash_index.replace(PerceptualHashIndex::load());
+            self.content_index.replace(ContentHashIndex::load());
+            #[cfg(feature = "use-embeddings")]
+            self.embedding_index.replace(EmbeddingIndex::load());
+
+            let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+
+            for key in ["pause-indexing-on-battery", "power-saver-throttling"] {
+                gsettings.connect_changed(
+                    Some(key),
+                    clone!(
+                        #[weak]
+                        obj,
+                        move |_: &gio::Settings, _: &str| {
+                            obj.continue_pass();
+                        }
+                    ),
+                );
+            }
+            let power_profile_monitor: gio::PowerProfileMonitor = gio::PowerProfileMonitor::get_default();
+
+            power_profile_monitor.connect_power_saver_enabled_notify(clone!(
+                #[weak]
+                obj,
+                move |_: &gio::PowerProfileMonitor| {
+                    obj.continue_pass();
+                }
+            ));
+            self.power_profile_monitor.replace(Some(power_profile_monitor));
+            glib::spawn_future_local(clone!(
+                #[weak]
+                obj,
+                async move {
+                    obj.watch_battery().await;
+                }
+            ));
+        }
+
+        fn dispose(&self) {
+            self.obj().cancel();
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct MemoriesScanService(ObjectSubclass<imp::MemoriesScanService>);
+}
+
+/// Settings that the scan service generates thumbnails with, which are those
+/// of the library grid, so that the thumbnails are cached for the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThumbnailSettings {
+    /// Edge length in pixels of the thumbnails.
+    pub size: u32,
+    pub hardware_accel: Option<HardwareAccel>,
+    /// Whether the ffmpeg binary is available, without which videos are not thumbnailed.
+    pub ffmpeg_available: bool,
+}
+
+impl MemoriesScanService {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Starts enumerating the library model, and indexes its items every
+    /// time it is done loading, including after the library is refreshed.
+    /// Thumbnails are generated with the given settings. If the library is
+    /// shown, previews are cached for its grid, which also limits the thumbnail
+    /// processes spawned at once for each library folder.
+    pub fn start(
+        &self,
+        library_model: &MemoriesLibraryListModel,
+        thumbnail_settings: ThumbnailSettings,
+        media_grid: Option<&MemoriesMediaGridView>,
+    ) -> Result<(), String> {
+        self.cancel();
+
+        let models_loaded_handler: glib::SignalHandlerId =
+            library_model.connect_models_loaded_notify(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |model: &MemoriesLibraryListModel| {
+                    if model.models_loaded() {
+                        this.imp().next_position.set(0);
+                        this.continue_pass();
+                    }
+                }
+            ));
+        self.imp()
+            .models_loaded_handler
+            .replace(Some(models_loaded_handler));
+        self.imp().library_model.replace(Some(library_model.clone()));
+        self.imp().thumbnail_settings.set(Some(thumbnail_settings));
+        self.imp().media_grid.set(media_grid);
+        self.imp()
+            .subprocess_semaphore
+            .replace(Some(Arc::new(Semaphore::new(media_grid::subprocess_permits()))));
+        self.imp().cancellable.replace(Some(gio::Cancellable::new()));
+        self.imp().next_position.set(0);
+        self.imp().running.set(true);
+        self.notify_running();
+
+        library_model.start_enumerating_items()
+    }
+
+    /// Sets the settings that thumbnails are generated with from then on,
+    /// such as after the grid of the library was zoomed.
+    pub fn set_thumbnail_settings(&self, thumbnail_settings: ThumbnailSettings) {
+        self.imp().thumbnail_settings.set(Some(thumbnail_settings));
+    }
+
+    /// Pauses indexing after the item that is currently being indexed.
+    pub fn pause(&self) {
+        if !self.imp().paused.replace(true) {
+            self.notify_paused();
+        }
+    }
+
+    /// Resumes indexing from the item that it was paused at.
+    pub fn resume(&self) {
+        if self.imp().paused.replace(false) {
+            self.notify_paused();
+        }
+        self.continue_pass();
+    }
+
+    /// Stops indexing. The library model is not enumerated again
+    /// by the scan service until it is started again.
+    pub fn cancel(&self) {
+        if let Some(cancellable) = self.imp().cancellable.take() {
+            cancellable.cancel();
+        }
+        if let Some(library_model) = self.imp().library_model.take() {
+            if let Some(handler_id) = self.imp().models_loaded_handler.take() {
+                library_model.disconnect(handler_id);
+            }
+        }
+        self.imp().pass_active.set(false);
+        self.imp().suspend_inhibitor.take();
+        self.set_waiting(false);
+
+        self.save_hash_index();
+        self.save_content_index();
+        #[cfg(feature = "use-ocr")]
+        self.save_text_index();
+        #[cfg(feature = "use-embeddings")]
+        self.save_embedding_index();
+
+        if self.imp().running.replace(false) {
+            self.notify_running();
+        }
+    }
+
+    pub fn connect_progress<F: Fn(&Self, u32, u32) + 'static>(&self, callback: F) -> glib::SignalHandlerId {
+        self.connect_closure(
+            "progress",
+            false,
+            glib::closure_local!(move |service: &Self, indexed: u32, total: u32| {
+                callback(service, indexed, total);
+            }),
+        )
+    }
+
+    pub fn connect_finished<F: Fn(&Self) + 'static>(&self, callback: F) -> glib::SignalHandlerId {
+        self.connect_closure(
+            "finished",
+            false,
+            glib::closure_local!(move |service: &Self| {
+                callback(service);
+            }),
+        )
+    }
+
+    pub fn connect_text_recognized<F: Fn(&Self, &str) + 'static>(
+        &self,
+        callback: F,
+    ) -> glib::SignalHandlerId {
+        self.connect_closure(
+            "text-recognized",
+            false,
+            glib::closure_local!(move |service: &Self, uri: &str| {
+                callback(service, uri);
+            }),
+        )
+    }
+
+    pub fn connect_file_changed<F: Fn(&Self, &str) + 'static>(&self, callback: F) -> glib::SignalHandlerId {
+        self.connect_closure(
+            "file-changed",
+            false,
+            glib::closure_local!(move |service: &Self, uri: &str| {
+                callback(service, uri);
+            }),
+        )
+    }
+
+    /// Notifies the library that the contents of a media file changed, so that
+    /// its thumbnail is generated and its contents are hashed again.
+    pub fn notify_file_changed(&self, file: &gio::File) {
+        self.imp().content_index.borrow_mut().remove(file.uri().as_str());
+        self.emit_by_name::<()>("file-changed", &[&file.uri().to_string()]);
+    }
+
+    /// Returns the text recognized in a screenshot, in lowercase.
+    pub fn recognized_text(&self, uri: &str) -> Option<String> {
+        self.imp().text_index.borrow().get(uri).map(str::to_string)
+    }
+
+    /// Returns the content hashes of the media in the library that were hashed,
+    /// which identify media that was already imported, under any file name.
+    pub fn content_hashes(&self) -> HashSet<String> {
+        self.imp().content_index.borrow().hashes()
+    }
+
+    /// Returns the photos that look alike to a photo, from the most to the least
+    /// similar. Private photos, and photos that were not perceptually hashed
+    /// yet, are not included.
+    pub fn similar_photos(&self, file: &gio::File) -> Vec<gio::File> {
+        let private: glib::StrV = MemoriesApplication::default().private();
+
+        self.imp()
+            .hash_index
+            .borrow()
+            .similar(file.uri().as_str(), SIMILAR_MAX_DISTANCE, SIMILAR_MAX_RESULTS)
+            .iter()
+            .filter(|uri: &&String| {
+                !private
+                    .iter()
+                    .any(|p: &glib::GStringPtr| p.as_str() == uri.as_str())
+            })
+            .map(|uri: &String| gio::File::for_uri(uri))
+            .collect()
+    }
+
+    /// Returns the similarity of every embedded media to the embedding of
+    /// a search query by file URI, keeping only media similar enough to match.
+    #[cfg(feature = "use-embeddings")]
+    pub fn similarities(&self, query: &[f32]) -> HashMap<String, f32> {
+        self.imp()
+            .embedding_index
+            .borrow()
+            .similarities(query, EMBEDDING_MATCH_THRESHOLD)
+    }
+
+    /// Returns whether indexing should wait, because it was paused by
+    /// calling `pause()`, the system is running on battery and indexing is
+    /// set to be paused on battery in the preferences, or because power saver
+    /// mode is active. Media shown in the grid still get their thumbnails.
+    fn should_wait(&self) -> bool {
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+
+        self.imp().paused.get()
+            || (self.imp().on_battery.get() && gsettings.boolean("pause-indexing-on-battery"))
+            || power::should_throttle()
+    }
+
+    fn set_waiting(&self, waiting: bool) {
+        if self.imp().waiting.replace(waiting) != waiting {
+            self.notify_waiting();
+        }
+    }
+
+    /// Indexes the remaining library items, unless indexing should wait.
+    fn continue_pass(&self) {
+        if !self.imp().running.get() || self.imp().pass_active.get() {
+            return;
+        }
+        if self.should_wait() {
+            self.set_waiting(true);
+            return;
+        }
+        let Some(cancellable) = self.imp().cancellable.borrow().clone() else {
+            return;
+        };
+        self.imp().pass_active.set(true);
+        self.set_waiting(false);
+
+        if !self.imp().indexed_once.get() {
+            let inhibitor: SuspendInhibitor =
+                MemoriesApplication::default().inhibit_suspend(&gettext("Indexing the library"));
+            self.imp().suspend_inhibitor.replace(Some(inhibitor));
+        }
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let result = gio::CancellableFuture::new(this.run_pass(), cancellable).await;
+
+                // The scan was cancelled, and may have been started again since.
+                let Ok(finished) = result else {
+                    return;
+                };
+                this.imp().pass_active.set(false);
+                this.imp().suspend_inhibitor.take();
+
+                if finished {
+                    g_debug!("ScanService", "Indexed all library items.");
+                    this.imp().indexed_once.set(true);
+
+                    this.save_hash_index();
+                    this.save_content_index();
+                    #[cfg(feature = "use-ocr")]
+                    this.save_text_index();
+                    #[cfg(feature = "use-embeddings")]
+                    this.save_embedding_index();
+
+                    this.emit_by_name::<()>("finished", &[]);
+                }
+            }
+        ));
+    }
+
+    /// Indexes library items until every item is indexed, in which case
+    /// `true` is returned, or until indexing should wait.
+    async fn run_pass(&self) -> bool {
+        loop {
+            if self.should_wait() {
+                self.set_waiting(true);
+                return false;
+            }
+            let Some(library_model) = self.imp().library_model.borrow().clone() else {
+                return false;
+            };
+            let position: u32 = self.imp().next_position.get();
+            let total: u32 = library_model.n_items();
+
+            if position >= total {
+                return true;
+            }
+            let batch: Range<u32> = position..(position + Self::concurrent_items()).min(total);
+            let items: Vec<MemoriesMediaItem> = batch
+                .clone()
+                .filter_map(|position: u32| library_model.item(position).and_downcast())
+                .collect();
+
+            join_all(items.iter().map(|item: &MemoriesMediaItem| self.index_item(item))).await;
+
+            // The position is reset if the library is reloaded in the meantime.
+            if self.imp().next_position.get() == position {
+                self.imp().next_position.set(batch.end);
+            }
+            self.emit_by_name::<()>("progress", &[&batch.end, &total]);
+        }
+    }
+
+    /// Returns the number of library items to index at once, which is the number
+    /// of cores, or a single item while the system should save power.
+    fn concurrent_items() -> u32 {
+        if power::should_throttle() {
+            return 1;
+        }
+        let cores: usize = std::thread::available_parallelism()
+            .map(NonZeroUsize::get)
+            .unwrap_or(1);
+
+        cores.min(SCAN_MAX_CONCURRENT_ITEMS) as u32
+    }
+
+    /// Reads the metadata of a media file and generates its thumbnail,
+    /// if it is not cached yet, along with its low fidelity preview.
+    async fn index_item(&self, item: &MemoriesMediaItem) {
+        let Some(settings) = self.imp().thumbnail_settings.get() else {
+            return;
+        };
+        let file: gio::File = item.file();
+        let Some(file_path) = file.path() else {
+            return;
+        };
+        // Private media are not indexed, so their thumbnails are not cached.
+        let private: glib::StrV = MemoriesApplication::default().private();
+
+        if private
+            .iter()
+            .any(|uri: &glib::GStringPtr| uri.as_str() == file.uri().as_str())
+        {
+            return;
+        }
+        self.hash_contents(item).await;
+
+        // Media with a missing or unknown extension is told by its contents, like in the viewer.
+        let content_type: ViewerContentType = ViewerContentType::for_file(&file).await;
+
+        let has_thumbnail: bool = match content_type {
+            ViewerContentType::Image => true,
+            ViewerContentType::Video => settings.ffmpeg_available || cfg!(feature = "use-libav"),
+            _ => false,
+        };
+        if !has_thumbnail {
+            return;
+        }
+        let hash: String = match get_metadata_with_hash(&file_path).await {
+            Ok((_, hash)) => hash,
+            Err(err) => {
+                g_debug!(
+                    "ScanService",
+                    "Failed to read metadata of '{}': {}",
+                    file.uri(),
+                    err
+                );
+                return;
+            }
+        };
+
+        let media_grid: Option<MemoriesMediaGridView> = self.imp().media_grid.upgrade();
+
+        let Some(semaphore) = media_grid
+            .as_ref()
+            .map(|media_grid: &MemoriesMediaGridView| media_grid.subprocess_semaphore_for(&file))
+            .or_else(|| self.imp().subprocess_semaphore.borrow().clone())
+        else {
+            return;
+        };
+        let thumbnail = MemoriesMediaCell::generate_thumbnail_image(
+            &file_path,
+            &hash,
+            semaphore,
+            settings.hardware_accel,
+            settings.ffmpeg_available,
+            settings.size,
+        )
+        .await;
+
+        match thumbnail {
+            Ok(thumbnail_path) => {
+                if matches!(content_type, ViewerContentType::Image) {
+                    self.hash_photo(&file, PathBuf::from(&thumbnail_path)).await;
+                }
+                #[cfg(feature = "use-embeddings")]
+                self.embed_media(&file, PathBuf::from(&thumbnail_path)).await;
+
+                if let Some(media_grid) = media_grid {
+                    let has_preview: bool = media_grid
+                        .imp()
+                        .previews
+                        .borrow()
+                        .get(file.uri().as_str())
+                        .is_some();
+
+                    if !has_preview {
+                        media_grid
+                            .imp()
+                            .cache_preview(&file, PathBuf::from(thumbnail_path))
+                            .await;
+                    }
+                }
+            }
+            Err(err) => g_debug!("ScanService", "Failed to index '{}': {}", file.uri(), err),
+        }
+
+        #[cfg(feature = "use-ocr")]
+        if matches!(content_type, ViewerContentType::Image) && ocr::is_screenshot(&file_path) {
+            self.recognize_text(&file, file_path).await;
+        }
+    }
+
+    /// Perceptually hashes the thumbnail of a photo on a separate thread,
+    /// unless it was hashed before.
+    async fn hash_photo(&self, file: &gio::File, thumbnail_path: PathBuf) {
+        let uri: String = file.uri().to_string();
+
+        if self.imp().hash_index.borrow().contains(&uri) {
+            return;
+        }
+        match gio::spawn_blocking(move || phash::thumbnail_hash(&thumbnail_path)).await {
+            Ok(Ok(hash)) => self.imp().hash_index.borrow_mut().insert(uri, hash),
+            Ok(Err(err)) => g_debug!("ScanService", "Failed to hash '{}': {}", uri, err),
+            Err(_) => g_warning!("ScanService", "Perceptual hashing thread panicked."),
+        }
+    }
+
+    /// Hashes the size and start of a media file, unless it was hashed before.
+    async fn hash_contents(&self, item: &MemoriesMediaItem) {
+        let uri: String = item.uri();
+
+        if self.imp().content_index.borrow().contains(&uri) {
+            return;
+        }
+        match content_hash::file_content_hash(&item.file(), item.size()).await {
+            Ok(hash) => self.imp().content_index.borrow_mut().insert(uri, hash),
+            Err(err) => g_debug!("ScanService", "Failed to hash contents of '{}': {}", uri, err),
+        }
+    }
+
+    /// Recognizes the text in a screenshot on a separate thread, unless it
+    /// was recognized before. Screenshots whose text fails to be recognized
+    /// are indexed without text, so that they are not tried again.
+    #[cfg(feature = "use-ocr")]
+    async fn recognize_text(&self, file: &gio::File, file_path: PathBuf) {
+        let uri: String = file.uri().to_string();
+
+        if self.imp().text_index.borrow().contains(&uri) {
+            return;
+        }
+        let text: String = match gio::spawn_blocking(move || ocr::recognize_text(&file_path)).await {
+            Ok(Ok(text)) => text,
+            Ok(Err(err)) => {
+                g_debug!("ScanService", "Failed to recognize text in '{}': {}", uri, err);
+                String::new()
+            }
+            Err(_) => {
+                g_warning!("ScanService", "Text recognition thread panicked.");
+                return;
+            }
+        };
+        self.imp().text_index.borrow_mut().insert(uri.clone(), &text);
+        self.emit_by_name::<()>("text-recognized", &[&uri]);
+    }
+
+    /// Embeds the thumbnail of a media file on a separate thread, unless it was
+    /// embedded before. Videos are embedded by the frame in their thumbnail.
+    #[cfg(feature = "use-embeddings")]
+    async fn embed_media(&self, file: &gio::File, thumbnail_path: PathBuf) {
+        let uri: String = file.uri().to_string();
+
+        if self.imp().embedding_index.borrow().contains(&uri) {
+            return;
+        }
+        match gio::spawn_blocking(move || embeddings::image_embedding(&thumbnail_path)).await {
+            Ok(Ok(embedding)) => self.imp().embedding_index.borrow_mut().insert(uri, &embedding),
+            Ok(Err(err)) => g_debug!("ScanService", "Failed to embed '{}': {}", uri, err),
+            Err(_) => g_warning!("ScanService", "Embedding thread panicked."),
+        }
+    }
+
+    fn save_hash_index(&self) {
+        if let Err(err) = self.imp().hash_index.borrow().save() {
+            g_warning!("ScanService", "Failed to save perceptual hash index: {}", err);
+        }
+    }
+
+    fn save_content_index(&self) {
+        if let Err(err) = self.imp().content_index.borrow().save() {
+            g_warning!("ScanService", "Failed to save content hash index: {}", err);
+        }
+    }
+
+    #[cfg(feature = "use-embeddings")]
+    fn save_embedding_index(&self) {
+        if let Err(err) = self.imp().embedding_index.borrow().save() {
+            g_warning!("ScanService", "Failed to save embedding index: {}", err);
+        }
+    }
+
+    #[cfg(feature = "use-ocr")]
+    fn save_text_index(&self) {
+        if let Err(err) = self.imp().text_index.borrow().save() {
+            g_warning!("ScanService", "Failed to save text index: {}", err);
+        }
+    }
+
+    /// Follows whether the system is running on battery through UPower.
+    /// If UPower is not available, the system is assumed to be on AC power.
+    async fn watch_battery(&self) {
+        let proxy: gio::DBusProxy = match gio::DBusProxy::for_bus_future(
+            gio::BusType::System,
+            gio::DBusProxyFlags::NONE,
+            None,
+            UPOWER_BUS_NAME,
+            UPOWER_OBJECT_PATH,
+            UPOWER_INTERFACE,
+        )
+        .await
+        {
+            Ok(proxy) => proxy,
+            Err(err) => {
+                g_debug!("ScanService", "UPower is not available: {}", err);
+                return;
+            }
+        };
+        proxy.connect_local(
+            "g-properties-changed",
+            false,
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[upgrade_or]
+                None,
+                move |values: &[glib::Value]| {
+                    if let Ok(proxy) = values[0].get::<gio::DBusProxy>() {
+                        this.update_on_battery(&proxy);
+                    }
+                    None
+                }
+            ),
+        );
+        self.update_on_battery(&proxy);
+        self.imp().upower_proxy.replace(Some(proxy));
+    }
+
+    fn update_on_battery(&self, proxy: &gio::DBusProxy) {
+        let on_battery: bool = proxy
+            .cached_property("OnBattery")
+            .and_then(|value: glib::Variant| value.get::<bool>())
+            .unwrap_or(false);
+
+        if self.imp().on_battery.replace(on_battery) != on_battery {
+            self.notify_on_battery();
+            self.continue_pass();
+        }
+    }
+}
+
+/// Polls the given futures concurrently on the current thread until all of them
+/// are complete. The futures are dropped along with the returned future.
+async fn join_all<F: Future<Output = ()>>(futures: impl IntoIterator<Item = F>) {
+    let mut futures: Vec<Pin<Box<F>>> = futures.into_iter().map(Box::pin).collect();
+
+    std::future::poll_fn(|cx| {
+        futures.retain_mut(|future: &mut Pin<Box<F>>| future.as_mut().poll(cx).is_pending());
+
+        if futures.is_empty() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+impl Default for MemoriesScanService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_lite::future;
+    use std::cell::RefCell;
+
+    #[test]
+    fn futures_are_joined_concurrently() {
+        let order: RefCell<Vec<u32>> = RefCell::new(vec![]);
+
+        let task = |id: u32, yields: u32| {
+            let order: &RefCell<Vec<u32>> = &order;
+            async move {
+                for _ in 0..yields {
+                    future::yield_now().await;
+                }
+                order.borrow_mut().push(id);
+            }
+        };
+        future::block_on(join_all([task(1, 3), task(2, 0), task(3, 1)]));
+
+        assert_eq!(*order.borrow(), vec![2, 3, 1]);
+    }
+}
@@ -0,0 +1,159 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Query language of the library search bar.
+//!
+//! A query is made of terms separated by whitespace, which must all match.
+//! Plain terms match part of the file name, or of the text recognized in
+//! screenshots if it was recognized, and `tag:name` terms match media
+//! tagged with `name`. Terms can be quoted to include whitespace, such as
+//! `tag:"New York"`. All terms are matched case insensitively.
+//!
+//! When built with embeddings, the plain terms are also searched together
+//! as natural language, which media match if they are similar enough.
+
+/// Prefix of query terms that match tags.
+pub const TAG_PREFIX: &str = "tag:";
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchQuery {
+    name_terms: Vec<String>,
+    tags: Vec<String>,
+}
+
+impl SearchQuery {
+    pub fn parse(text: &str) -> Self {
+        let mut query: Self = Self::default();
+
+        for term in split_terms(text) {
+            let term: String = term.to_lowercase();
+
+            match term.strip_prefix(TAG_PREFIX) {
+                Some(tag) if !tag.is_empty() => query.tags.push(tag.to_string()),
+                Some(_) => (),
+                None => query.name_terms.push(term),
+            }
+        }
+        query
+    }
+
+    /// Returns `true` if the query has no terms, and so matches everything.
+    pub fn is_empty(&self) -> bool {
+        self.name_terms.is_empty() && self.tags.is_empty()
+    }
+
+    /// Returns `true` if the query contains `tag:` terms, which need the tags of media to match.
+    pub fn has_tags(&self) -> bool {
+        !self.tags.is_empty()
+    }
+
+    /// Returns `true` if media matches the query. The `text` recognized in the
+    /// media is expected to be lowercase already, as it is in the text index.
+    pub fn matches(&self, file_name: &str, text: &str, tags: &[String]) -> bool {
+        let file_name: String = file_name.to_lowercase();
+
+        self.name_terms
+            .iter()
+            .all(|term: &String| file_name.contains(term.as_str()) || text.contains(term.as_str()))
+            && self.matches_tags(tags)
+    }
+
+    /// Returns `true` if media has every tag of the `tag:` terms of the query.
+    pub fn matches_tags(&self, tags: &[String]) -> bool {
+        self.tags
+            .iter()
+            .all(|tag: &String| tags.iter().any(|t: &String| t.to_lowercase() == *tag))
+    }
+
+    /// Returns the plain terms of the query joined by spaces, which
+    /// are searched with natural language when built with embeddings.
+    #[cfg(feature = "use-embeddings")]
+    pub fn semantic_text(&self) -> String {
+        self.name_terms.join(" ")
+    }
+}
+
+/// Returns the query term for searching a tag, quoted if needed.
+pub fn tag_term(tag: &str) -> String {
+    if tag.contains(char::is_whitespace) {
+        format!("{}\"{}\"", TAG_PREFIX, tag.replace('"', ""))
+    } else {
+        format!("{}{}", TAG_PREFIX, tag)
+    }
+}
+
+/// Splits a query into terms separated by whitespace, where
+/// text between double quotes is kept in the same term.
+fn split_terms(text: &str) -> Vec<String> {
+    let mut terms: Vec<String> = vec![];
+    let mut term: String = String::new();
+    let mut quoted: bool = false;
+
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !term.is_empty() {
+                    terms.push(std::mem::take(&mut term));
+                }
+            }
+            c => term.push(c),
+        }
+    }
+    if !term.is_empty() {
+        terms.push(term);
+    }
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_query_terms() {
+        let query: SearchQuery = SearchQuery::parse(" IMG tag:Beach  tag:\"New York\" tag: ");
+
+        assert_eq!(query.name_terms, vec!["img"]);
+        assert_eq!(query.tags, vec!["beach", "new york"]);
+        assert!(SearchQuery::parse("   ").is_empty());
+    }
+
+    #[test]
+    fn match_name_and_tags() {
+        let query: SearchQuery = SearchQuery::parse("img tag:beach");
+        let tags: Vec<String> = vec!["Beach".into(), "Family".into()];
+
+        assert!(query.matches("IMG_0001.jpg", "", &tags));
+        assert!(!query.matches("VID_0001.mp4", "", &tags));
+        assert!(!query.matches("IMG_0001.jpg", "", &[]));
+        assert!(SearchQuery::parse(&tag_term("New York")).matches("a.png", "", &["new york".into()]));
+    }
+
+    #[test]
+    fn match_recognized_text() {
+        let query: SearchQuery = SearchQuery::parse("\"file not found\" screenshot");
+        let text: &str = "error: file not found";
+
+        assert!(query.matches("Screenshot.png", text, &[]));
+        assert!(!query.matches("Screenshot.png", "", &[]));
+        assert!(!query.matches("IMG_0001.png", text, &[]));
+    }
+}
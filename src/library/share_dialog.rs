@@ -0,0 +1,677 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Dialog that exports media to a folder, a zip archive, or a static web gallery,
+//! such as to hand photos to someone else. Photos can be downscaled while they
+//! are exported.
+
+use super::export_dialog::{ffmpeg_scale_filter, ExportFormat};
+use super::media_viewer::ViewerContentType;
+use super::web_gallery::{gallery_html, GalleryItem};
+use crate::application::{MemoriesApplication, RunningJob};
+use crate::globals::{
+    EXPORT_RESIZE_PRESETS, FFMPEG_BINARY, SHARE_JPEG_QUALITY, SHARE_STAGING_DIR_TEMPLATE,
+    WEB_GALLERY_MEDIA_DIR, WEB_GALLERY_THUMBNAILS_DIR, WEB_GALLERY_THUMBNAIL_SIZE,
+};
+use crate::i18n::{gettext_f, ngettext_f};
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use async_process::{Command, Output};
+use gettextrs::gettext;
+use glib::{clone, g_warning};
+use gtk::{gio, glib};
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+mod imp {
+    use super::ShareDestination;
+    use adw::subclass::prelude::*;
+    use gtk::{gio, glib};
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/share-dialog.ui")]
+    pub struct MemoriesShareDialog {
+        pub(super) files: RefCell<Vec<gio::File>>,
+        /// Name that the exported zip archive is suggested to be saved as.
+        pub(super) name: RefCell<String>,
+        /// Cancels the export in progress. `None` while not exporting.
+        pub(super) cancellable: RefCell<Option<gio::Cancellable>>,
+        /// Where the media was exported to, once the export is done.
+        pub(super) destination: RefCell<Option<(gio::File, ShareDestination)>>,
+        #[template_child]
+        pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) cancel_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) export_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) content_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) summary_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) destination_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub(super) size_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub(super) progress_status_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub(super) progress_bar: TemplateChild<gtk::ProgressBar>,
+        #[template_child]
+        pub(super) show_button: TemplateChild<gtk::Button>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesShareDialog {
+        const NAME: &'static str = "MemoriesShareDialog";
+        type Type = super::MemoriesShareDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesShareDialog {}
+    impl WidgetImpl for MemoriesShareDialog {}
+    impl AdwDialogImpl for MemoriesShareDialog {}
+}
+
+/// Enum that represents where media is exported to.
+/// Variant order matches the destination combo row model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareDestination {
+    Folder,
+    Zip,
+    WebGallery,
+}
+
+impl ShareDestination {
+    pub fn from_index(index: u32) -> ShareDestination {
+        match index {
+            1 => ShareDestination::Zip,
+            2 => ShareDestination::WebGallery,
+            _ => ShareDestination::Folder,
+        }
+    }
+}
+
+/// Returns `name`, or `name` with a number added before its extension if it
+/// is already in `taken`, and adds the returned name to `taken`. Names are
+/// compared ignoring case, since some file systems do not tell case apart.
+pub fn unique_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let (stem, extension): (&str, Option<&str>) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+        _ => (name, None),
+    };
+    let mut unique_name: String = name.to_string();
+    let mut number: u32 = 2;
+
+    while taken.contains(&unique_name.to_lowercase()) {
+        unique_name = match extension {
+            Some(extension) => format!("{} ({}).{}", stem, number, extension),
+            None => format!("{} ({})", stem, number),
+        };
+        number += 1;
+    }
+    taken.insert(unique_name.to_lowercase());
+    unique_name
+}
+
+/// Returns the edge length that a file is downscaled to when exported, or `None`
+/// if it is exported as it is. Only photos are downscaled, not videos or SVGs.
+fn resize_edge(file: &gio::File, max_edge: Option<u32>) -> Option<u32> {
+    let path: PathBuf = file.basename()?;
+    let extension = path
+        .extension()
+        .filter(|extension| extension.to_str().is_some())?;
+
+    match ViewerContentType::from_ext(extension) {
+        ViewerContentType::Image => max_edge,
+        _ => None,
+    }
+}
+
+/// Downscales a photo with ffmpeg, and writes it to `destination` as a JPEG.
+/// The first frame of a video is written if `source` is a video.
+async fn resize_photo(source: &gio::File, destination: &Path, max_edge: u32) -> Result<(), String> {
+    let source_path: PathBuf = source.path().ok_or("The photo is not a local file")?;
+
+    let mut ffmpeg_command: Command = Command::new(FFMPEG_BINARY);
+
+    ffmpeg_command
+        .kill_on_drop(true)
+        .arg("-y")
+        .arg("-i")
+        .arg(source_path)
+        .args(["-loglevel", "error", "-frames:v", "1"])
+        .args(["-vf", &ffmpeg_scale_filter(max_edge)])
+        .args(ExportFormat::Jpeg.ffmpeg_arguments(SHARE_JPEG_QUALITY))
+        .arg(destination);
+
+    let ffmpeg_output: std::io::Result<Output> = ffmpeg_command.output().await;
+
+    match ffmpeg_output {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(String::from_utf8_lossy(&output.stderr).to_string()),
+        Err(io_error) => Err(format!("Failed to execute ffmpeg: {}", io_error)),
+    }
+}
+
+/// Writes a file into the zip archive. Photos and videos are already
+/// compressed, so they are stored as they are to save time.
+fn add_zip_entry(writer: &Mutex<ZipWriter<File>>, name: String, path: &Path) -> zip::result::ZipResult<()> {
+    let mut writer = writer.lock().unwrap();
+    let options: SimpleFileOptions = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+
+    writer.start_file(name, options)?;
+    std::io::copy(&mut File::open(path)?, &mut *writer)?;
+    Ok(())
+}
+
+glib::wrapper! {
+    pub struct MemoriesShareDialog(ObjectSubclass<imp::MemoriesShareDialog>)
+        @extends gtk::Widget, adw::Dialog;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesShareDialog {
+    /// Creates a dialog that exports the given files. The zip archive
+    /// is suggested to be saved with the given `name`.
+    pub fn new(files: &[gio::File], name: &str) -> Self {
+        let obj: Self = glib::Object::new();
+        obj.imp().files.replace(files.to_vec());
+        obj.imp().name.replace(name.to_string());
+
+        let count: u32 = files.len().try_into().unwrap_or(u32::MAX);
+
+        obj.imp().summary_group.set_description(Some(&ngettext_f(
+            "{COUNT} item will be exported",
+            "{COUNT} items will be exported",
+            count,
+            &[("COUNT", &count.to_string())],
+        )));
+        obj
+    }
+
+    #[template_callback]
+    fn cancel_clicked(&self) {
+        match self.imp().cancellable.borrow().as_ref() {
+            Some(cancellable) => cancellable.cancel(),
+            None => {
+                self.close();
+            }
+        }
+    }
+
+    /// Closing the dialog while exporting cancels the export instead.
+    #[template_callback]
+    fn close_attempted(&self) {
+        if let Some(cancellable) = self.imp().cancellable.borrow().as_ref() {
+            cancellable.cancel();
+        }
+    }
+
+    #[template_callback]
+    fn export_clicked(&self) {
+        let destination: ShareDestination =
+            ShareDestination::from_index(self.imp().destination_row.selected());
+        let window: Option<gtk::Window> = self.root().and_downcast();
+
+        let file_dialog: gtk::FileDialog = match destination {
+            ShareDestination::Folder => gtk::FileDialog::builder()
+                .title(gettext("Export to Folder"))
+                .modal(true)
+                .build(),
+            ShareDestination::WebGallery => gtk::FileDialog::builder()
+                .title(gettext("Export Web Gallery to Folder"))
+                .modal(true)
+                .build(),
+            ShareDestination::Zip => {
+                let filter: gtk::FileFilter = gtk::FileFilter::new();
+                filter.set_name(Some(&gettext("Zip Archive")));
+                filter.add_mime_type("application/zip");
+
+                let filters: gio::ListStore = gio::ListStore::new::<gtk::FileFilter>();
+                filters.append(&filter);
+
+                gtk::FileDialog::builder()
+                    .title(gettext("Export to Zip Archive"))
+                    .initial_name(format!("{}.zip", self.imp().name.borrow()))
+                    .filters(&filters)
+                    .modal(true)
+                    .build()
+            }
+        };
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                // An error is returned if the user dismisses the file chooser.
+                let chosen: Result<gio::File, glib::Error> = match destination {
+                    ShareDestination::Folder | ShareDestination::WebGallery => {
+                        file_dialog.select_folder_future(window.as_ref()).await
+                    }
+                    ShareDestination::Zip => file_dialog.save_future(window.as_ref()).await,
+                };
+                if let Ok(file) = chosen {
+                    this.export(&file, destination).await;
+                }
+            }
+        ));
+    }
+
+    #[template_callback]
+    fn show_clicked(&self) {
+        let Some((file, destination)) = self.imp().destination.borrow().clone() else {
+            return;
+        };
+        let launcher: gtk::FileLauncher = gtk::FileLauncher::new(Some(&file));
+        let window: Option<gtk::Window> = self.root().and_downcast();
+
+        glib::spawn_future_local(async move {
+            let result: Result<(), glib::Error> = match destination {
+                ShareDestination::Folder => launcher.launch_future(window.as_ref()).await,
+                // Opens the gallery page in the web browser.
+                ShareDestination::WebGallery => {
+                    launcher.set_file(Some(&file.child("index.html")));
+                    launcher.launch_future(window.as_ref()).await
+                }
+                ShareDestination::Zip => launcher.open_containing_folder_future(window.as_ref()).await,
+            };
+            if let Err(err) = result {
+                g_warning!("ShareDialog", "Failed to show the exported files: {}", err);
+            }
+        });
+    }
+
+    /// Exports every file of the dialog to `destination`, which is a folder, the
+    /// zip archive to create, or the folder of a web gallery. Files that fail
+    /// to export are skipped.
+    async fn export(&self, destination: &gio::File, kind: ShareDestination) {
+        let files: Vec<gio::File> = self.imp().files.borrow().clone();
+        let size_index: usize = self.imp().size_row.selected().try_into().unwrap();
+        let max_edge: Option<u32> = EXPORT_RESIZE_PRESETS.get(size_index).copied().flatten();
+
+        let Some(staging_dir) = glib::mkdtemp(glib::tmp_dir().join(SHARE_STAGING_DIR_TEMPLATE)) else {
+            self.show_error_toast();
+            return;
+        };
+        let zip_writer: Option<Arc<Mutex<ZipWriter<File>>>> = match kind {
+            ShareDestination::Folder | ShareDestination::WebGallery => None,
+            ShareDestination::Zip => match destination.path().map(File::create) {
+                Some(Ok(zip_file)) => Some(Arc::new(Mutex::new(ZipWriter::new(zip_file)))),
+                _ => {
+                    let _ = std::fs::remove_dir_all(&staging_dir);
+                    self.show_error_toast();
+                    return;
+                }
+            },
+        };
+
+        let cancellable: gio::Cancellable = gio::Cancellable::new();
+        self.imp().cancellable.replace(Some(cancellable.clone()));
+        self.set_can_close(false);
+        self.imp().export_button.set_sensitive(false);
+        self.imp().content_stack.set_visible_child_name("progress");
+
+        let application: MemoriesApplication = MemoriesApplication::default();
+        let _job: RunningJob = application.start_job(&gettext("Exporting media"));
+
+        // Media of a web gallery is exported into a subfolder, next to its thumbnails.
+        let media_folder: gio::File = match kind {
+            ShareDestination::WebGallery => {
+                let media_folder: gio::File = destination.child(WEB_GALLERY_MEDIA_DIR);
+                let thumbnails_folder: gio::File = destination.child(WEB_GALLERY_THUMBNAILS_DIR);
+
+                for folder in [&media_folder, &thumbnails_folder] {
+                    match folder.make_directory_future(glib::Priority::DEFAULT).await {
+                        Err(err) if !err.matches(gio::IOErrorEnum::Exists) => {
+                            let _ = std::fs::remove_dir_all(&staging_dir);
+                            self.show_error_toast();
+                            return;
+                        }
+                        _ => (),
+                    }
+                }
+                media_folder
+            }
+            _ => destination.clone(),
+        };
+        let mut gallery_items: Vec<GalleryItem> = vec![];
+
+        let mut taken_names: HashSet<String> = HashSet::new();
+        let mut exported: u32 = 0;
+        let mut failed: u32 = 0;
+
+        for (index, file) in files.iter().enumerate() {
+            self.update_progress(index, files.len());
+
+            let export_file = async {
+                match &zip_writer {
+                    Some(zip_writer) => {
+                        Self::add_to_zip(file, zip_writer, &staging_dir, max_edge, &mut taken_names).await
+                    }
+                    None => {
+                        let name: String =
+                            Self::copy_to_folder(file, &media_folder, max_edge, &mut taken_names).await?;
+
+                        if kind == ShareDestination::WebGallery {
+                            gallery_items.push(Self::add_gallery_thumbnail(file, destination, name).await?);
+                        }
+                        Ok(())
+                    }
+                }
+            };
+            match gio::CancellableFuture::new(export_file, cancellable.clone()).await {
+                Ok(Ok(())) => exported += 1,
+                Ok(Err(err)) => {
+                    g_warning!("ShareDialog", "Failed to export '{}': {}", file.uri(), err);
+                    failed += 1;
+                }
+                Err(_) => break,
+            }
+        }
+
+        // The zip archive is only valid once its central directory is written.
+        let mut zip_failed: bool = false;
+
+        if let Some(zip_writer) = zip_writer {
+            let finished: bool = !cancellable.is_cancelled()
+                && gio::spawn_blocking(move || {
+                    Arc::into_inner(zip_writer)
+                        .and_then(|writer: Mutex<ZipWriter<File>>| writer.into_inner().ok())
+                        .is_some_and(|writer: ZipWriter<File>| writer.finish().is_ok())
+                })
+                .await
+                .unwrap_or(false);
+
+            if !finished {
+                zip_failed = !cancellable.is_cancelled();
+
+                if let Some(path) = destination.path() {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+        }
+        let _ = std::fs::remove_dir_all(&staging_dir);
+
+        let mut gallery_failed: bool = false;
+
+        if kind == ShareDestination::WebGallery && !cancellable.is_cancelled() {
+            let html: String = gallery_html(&self.imp().name.borrow(), &gallery_items);
+            let written = destination
+                .child("index.html")
+                .replace_contents_future(html.into_bytes(), None, false, gio::FileCreateFlags::NONE)
+                .await;
+
+            if let Err((_, err)) = written {
+                g_warning!("ShareDialog", "Failed to write the web gallery: {}", err);
+                gallery_failed = true;
+            }
+        }
+
+        self.imp().cancellable.take();
+        self.set_can_close(true);
+
+        if cancellable.is_cancelled() || zip_failed || gallery_failed {
+            self.imp().export_button.set_sensitive(true);
+            self.imp().content_stack.set_visible_child_name("options");
+
+            if zip_failed || gallery_failed {
+                self.show_error_toast();
+            } else {
+                self.imp()
+                    .toast_overlay
+                    .add_toast(adw::Toast::new(&gettext("Export canceled")));
+            }
+            return;
+        }
+        self.show_results(exported, failed);
+        self.imp().destination.replace(Some((destination.clone(), kind)));
+
+        let shown_file: Option<gio::File> = match kind {
+            ShareDestination::Folder => Some(destination.clone()),
+            ShareDestination::WebGallery => Some(destination.child("index.html")),
+            ShareDestination::Zip => destination.parent(),
+        };
+        application.notify_job_finished(
+            "export",
+            &gettext("Export Complete"),
+            &Self::results_description(exported, failed),
+            shown_file.as_ref().filter(|_| exported > 0),
+        );
+    }
+
+    /// Copies a file into `folder`, or writes a downscaled copy of a photo.
+    /// Returns the name of the file written into `folder`.
+    async fn copy_to_folder(
+        file: &gio::File,
+        folder: &gio::File,
+        max_edge: Option<u32>,
+        taken_names: &mut HashSet<String>,
+    ) -> Result<String, String> {
+        let basename: PathBuf = file.basename().ok_or("The file has no name")?;
+
+        match resize_edge(file, max_edge) {
+            Some(max_edge) => {
+                let name: String = unique_name(&jpeg_name(&basename), taken_names);
+                let target: PathBuf = folder
+                    .child(&name)
+                    .path()
+                    .ok_or("The folder is not a local folder")?;
+
+                resize_photo(file, &target, max_edge).await?;
+                Ok(name)
+            }
+            None => {
+                let name: String = unique_name(&basename.to_string_lossy(), taken_names);
+                let (copy, _) = file.copy_future(
+                    &folder.child(&name),
+                    gio::FileCopyFlags::NONE,
+                    glib::Priority::LOW,
+                );
+
+                copy.await.map_err(|err: glib::Error| err.to_string())?;
+                Ok(name)
+            }
+        }
+    }
+
+    /// Writes the thumbnail of a file exported into the media folder of a web
+    /// gallery as `name`, and returns the gallery item of the file. Names in the
+    /// media folder are unique, so thumbnails are named after them.
+    async fn add_gallery_thumbnail(
+        file: &gio::File,
+        gallery_folder: &gio::File,
+        name: String,
+    ) -> Result<GalleryItem, String> {
+        let thumbnail_name: String = format!("{}.jpg", name);
+        let thumbnail_path: PathBuf = gallery_folder
+            .child(WEB_GALLERY_THUMBNAILS_DIR)
+            .child(&thumbnail_name)
+            .path()
+            .ok_or("The folder is not a local folder")?;
+
+        resize_photo(file, &thumbnail_path, WEB_GALLERY_THUMBNAIL_SIZE).await?;
+
+        let is_video: bool = file
+            .basename()
+            .and_then(|basename: PathBuf| basename.extension().map(ViewerContentType::from_ext))
+            .is_some_and(|content_type: ViewerContentType| matches!(content_type, ViewerContentType::Video));
+
+        Ok(GalleryItem {
+            media: format!("{}/{}", WEB_GALLERY_MEDIA_DIR, name),
+            thumbnail: format!("{}/{}", WEB_GALLERY_THUMBNAILS_DIR, thumbnail_name),
+            is_video,
+        })
+    }
+
+    /// Adds a file to the zip archive, or a downscaled copy of a photo. Files that are
+    /// not local, and downscaled photos, are written to `staging_dir` while being added.
+    async fn add_to_zip(
+        file: &gio::File,
+        zip_writer: &Arc<Mutex<ZipWriter<File>>>,
+        staging_dir: &Path,
+        max_edge: Option<u32>,
+        taken_names: &mut HashSet<String>,
+    ) -> Result<(), String> {
+        let basename: PathBuf = file.basename().ok_or("The file has no name")?;
+
+        let (name, path, staged): (String, PathBuf, bool) = match (resize_edge(file, max_edge), file.path()) {
+            (Some(max_edge), _) => {
+                let name: String = unique_name(&jpeg_name(&basename), taken_names);
+                let staged_path: PathBuf = staging_dir.join(&name);
+
+                resize_photo(file, &staged_path, max_edge).await?;
+                (name, staged_path, true)
+            }
+            (None, Some(path)) => (unique_name(&basename.to_string_lossy(), taken_names), path, false),
+            (None, None) => {
+                let name: String = unique_name(&basename.to_string_lossy(), taken_names);
+                let staged_path: PathBuf = staging_dir.join(&name);
+                let (copy, _) = file.copy_future(
+                    &gio::File::for_path(&staged_path),
+                    gio::FileCopyFlags::NONE,
+                    glib::Priority::LOW,
+                );
+                copy.await.map_err(|err: glib::Error| err.to_string())?;
+                (name, staged_path, true)
+            }
+        };
+        let zip_writer: Arc<Mutex<ZipWriter<File>>> = zip_writer.clone();
+        let entry_path: PathBuf = path.clone();
+
+        let result: Result<(), String> = gio::spawn_blocking(move || {
+            add_zip_entry(&zip_writer, name, &entry_path).map_err(|e| e.to_string())
+        })
+        .await
+        .unwrap_or_else(|_| Err("The zip archive could not be written".to_string()));
+
+        if staged {
+            let _ = std::fs::remove_file(&path);
+        }
+        result
+    }
+
+    fn update_progress(&self, done: usize, total: usize) {
+        self.imp()
+            .progress_bar
+            .set_fraction(done as f64 / total.max(1) as f64);
+        self.imp().progress_status_page.set_description(Some(&gettext_f(
+            "{DONE} of {TOTAL}",
+            &[("DONE", &done.to_string()), ("TOTAL", &total.to_string())],
+        )));
+    }
+
+    /// Shows how many files were exported, and offers to show them.
+    fn show_results(&self, exported: u32, failed: u32) {
+        self.imp()
+            .progress_status_page
+            .set_title(&gettext("Export Complete"));
+        self.imp()
+            .progress_status_page
+            .set_description(Some(&Self::results_description(exported, failed)));
+        self.imp().progress_bar.set_visible(false);
+        self.imp().show_button.set_visible(exported > 0);
+        self.imp().export_button.set_visible(false);
+        self.imp().cancel_button.set_label(&gettext("_Close"));
+    }
+
+    fn results_description(exported: u32, failed: u32) -> String {
+        if failed > 0 {
+            ngettext_f(
+                "{COUNT} item could not be exported",
+                "{COUNT} items could not be exported",
+                failed,
+                &[("COUNT", &failed.to_string())],
+            )
+        } else {
+            ngettext_f(
+                "{COUNT} item was exported",
+                "{COUNT} items were exported",
+                exported,
+                &[("COUNT", &exported.to_string())],
+            )
+        }
+    }
+
+    fn show_error_toast(&self) {
+        let toast: adw::Toast = adw::Toast::new(&gettext("Failed to export the media"));
+        self.imp().toast_overlay.add_toast(toast);
+    }
+}
+
+/// Returns the name of a photo after it is converted to a JPEG.
+fn jpeg_name(basename: &Path) -> String {
+    format!(
+        "{}.{}",
+        basename
+            .file_stem()
+            .unwrap_or(basename.as_os_str())
+            .to_string_lossy(),
+        ExportFormat::Jpeg.extension()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn share_destination_from_index() {
+        assert_eq!(ShareDestination::from_index(0), ShareDestination::Folder);
+        assert_eq!(ShareDestination::from_index(1), ShareDestination::Zip);
+        assert_eq!(ShareDestination::from_index(2), ShareDestination::WebGallery);
+        assert_eq!(ShareDestination::from_index(42), ShareDestination::Folder);
+    }
+
+    #[test]
+    fn unique_names() {
+        let mut taken: HashSet<String> = HashSet::new();
+
+        assert_eq!(unique_name("IMG_0001.jpg", &mut taken), "IMG_0001.jpg");
+        assert_eq!(unique_name("IMG_0001.JPG", &mut taken), "IMG_0001 (2).JPG");
+        assert_eq!(unique_name("IMG_0001.jpg", &mut taken), "IMG_0001 (3).jpg");
+        assert_eq!(unique_name(".hidden", &mut taken), ".hidden");
+        assert_eq!(unique_name(".hidden", &mut taken), ".hidden (2)");
+        assert_eq!(unique_name("README", &mut taken), "README");
+        assert_eq!(unique_name("README", &mut taken), "README (2)");
+    }
+
+    #[test]
+    fn jpeg_names() {
+        assert_eq!(jpeg_name(Path::new("IMG_0001.heic")), "IMG_0001.jpg");
+        assert_eq!(jpeg_name(Path::new("photo.tar.png")), "photo.tar.jpg");
+        assert_eq!(jpeg_name(Path::new("photo")), "photo.jpg");
+    }
+}
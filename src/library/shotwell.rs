@@ -0,0 +1,293 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Reading of the ratings, favorites, tags, and events of a Shotwell library
+//! from its SQLite database, so that they can be migrated to Memories. Memories
+//! has no albums of its own yet, so Shotwell events are imported as tags.
+
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use rusqlite::{Connection, OpenFlags};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Flags of `PhotoTable` rows, from `LibraryPhoto` in Shotwell.
+const PHOTO_FLAG_FAVORITE: i64 = 0x02;
+const PHOTO_FLAG_TRASH: i64 = 0x04;
+const PHOTO_FLAG_FLAGGED: i64 = 0x10;
+/// Flags of `VideoTable` rows, from `Video` in Shotwell.
+const VIDEO_FLAG_TRASH: i64 = 0x01;
+const VIDEO_FLAG_FLAGGED: i64 = 0x04;
+
+/// Ratings, favorites, and tags of media files, by file URI.
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportedMetadata {
+    pub favorites: Vec<String>,
+    pub ratings: HashMap<String, u32>,
+    pub tags: HashMap<String, Vec<String>>,
+}
+
+impl ImportedMetadata {
+    /// Returns the number of media files that have imported metadata.
+    pub fn media_count(&self) -> usize {
+        let mut uris: Vec<&String> = self
+            .favorites
+            .iter()
+            .chain(self.ratings.keys())
+            .chain(self.tags.keys())
+            .collect();
+        uris.sort();
+        uris.dedup();
+        uris.len()
+    }
+
+    /// Merges the imported metadata into the metadata of the library. Media that is
+    /// already rated in Memories keeps its rating, and imported tags are appended.
+    pub fn merge_into(
+        self,
+        favorites: &mut Vec<String>,
+        ratings: &mut HashMap<String, u32>,
+        tags: &mut HashMap<String, Vec<String>>,
+    ) {
+        for uri in self.favorites {
+            if !favorites.contains(&uri) {
+                favorites.push(uri);
+            }
+        }
+        for (uri, rating) in self.ratings {
+            ratings.entry(uri).or_insert(rating);
+        }
+        for (uri, imported_tags) in self.tags {
+            let file_tags: &mut Vec<String> = tags.entry(uri).or_default();
+
+            for tag in imported_tags {
+                if !file_tags.contains(&tag) {
+                    file_tags.push(tag);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the path of the Shotwell database of the user, if Shotwell has been used,
+/// either as a Flatpak or from the system, or in the location of older versions.
+pub fn database_path() -> Option<PathBuf> {
+    let home: PathBuf = glib::home_dir();
+
+    [
+        home.join(".var/app/org.gnome.Shotwell/data/shotwell/data/photo.db"),
+        home.join(".local/share/shotwell/data/photo.db"),
+        home.join(".shotwell/data/photo.db"),
+    ]
+    .into_iter()
+    .find(|path: &PathBuf| path.is_file())
+}
+
+/// Reads the metadata of the media in a Shotwell database. The database is
+/// opened as read-only, so it is left untouched for Shotwell. Trashed media
+/// is skipped, and media rejected in Shotwell is imported without a rating.
+pub fn read_database(path: &Path) -> Result<ImportedMetadata, rusqlite::Error> {
+    let connection: Connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let mut events: HashMap<i64, String> = HashMap::new();
+    let mut statement = connection.prepare("SELECT id, name FROM EventTable WHERE name IS NOT NULL")?;
+    let rows = statement.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+
+    for row in rows {
+        let (id, name) = row?;
+        if !name.trim().is_empty() {
+            events.insert(id, name.trim().to_string());
+        }
+    }
+
+    let mut metadata: ImportedMetadata = ImportedMetadata::default();
+    let mut uris: HashMap<SourceId, String> = HashMap::new();
+
+    for kind in [SourceKind::Photo, SourceKind::Video] {
+        let mut statement = connection.prepare(&format!(
+            "SELECT id, filename, rating, flags, event_id FROM {}",
+            kind.table()
+        ))?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<i64>>(2)?.unwrap_or(0),
+                row.get::<_, Option<i64>>(3)?.unwrap_or(0),
+                row.get::<_, Option<i64>>(4)?,
+            ))
+        })?;
+
+        for row in rows {
+            let (id, filename, rating, flags, event_id) = row?;
+
+            if flags & kind.trash_flag() != 0 {
+                continue;
+            }
+            let uri: String = gio::File::for_path(&filename).uri().to_string();
+
+            if flags & kind.favorite_flags() != 0 {
+                metadata.favorites.push(uri.clone());
+            }
+            // Rejected media has a rating of -1 in Shotwell.
+            if rating > 0 {
+                metadata.ratings.insert(uri.clone(), rating.min(5) as u32);
+            }
+            if let Some(event) = event_id.and_then(|id: i64| events.get(&id)) {
+                metadata.tags.entry(uri.clone()).or_default().push(event.clone());
+            }
+            uris.insert(SourceId(kind, id), uri);
+        }
+    }
+
+    let mut statement = connection.prepare("SELECT name, photo_id_list FROM TagTable")?;
+    let rows = statement.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+    })?;
+
+    for row in rows {
+        let (name, source_ids) = row?;
+        let Some(tag) = tag_name(&name) else {
+            continue;
+        };
+        for source_id in source_ids.as_deref().unwrap_or_default().split(',') {
+            let Some(uri) = SourceId::parse(source_id).and_then(|id: SourceId| uris.get(&id)) else {
+                continue;
+            };
+            let file_tags: &mut Vec<String> = metadata.tags.entry(uri.clone()).or_default();
+
+            if !file_tags.iter().any(|file_tag: &String| file_tag == tag) {
+                file_tags.push(tag.to_string());
+            }
+        }
+    }
+    Ok(metadata)
+}
+
+/// Returns the name of a Shotwell tag as a Memories tag. Hierarchical tags are
+/// named by their path in Shotwell, such as `/Family/Kids`, and are named by
+/// their last component, since each parent tag is also stored as its own tag.
+fn tag_name(name: &str) -> Option<&str> {
+    let tag: &str = match name.strip_prefix('/') {
+        Some(path) => path.rsplit('/').next().unwrap_or(path),
+        None => name,
+    };
+    Some(tag.trim()).filter(|tag: &&str| !tag.is_empty())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum SourceKind {
+    Photo,
+    Video,
+}
+
+impl SourceKind {
+    fn table(self) -> &'static str {
+        match self {
+            Self::Photo => "PhotoTable",
+            Self::Video => "VideoTable",
+        }
+    }
+
+    fn trash_flag(self) -> i64 {
+        match self {
+            Self::Photo => PHOTO_FLAG_TRASH,
+            Self::Video => VIDEO_FLAG_TRASH,
+        }
+    }
+
+    /// Shotwell marks favorites as flagged, which replaced the favorite flag of photos.
+    fn favorite_flags(self) -> i64 {
+        match self {
+            Self::Photo => PHOTO_FLAG_FAVORITE | PHOTO_FLAG_FLAGGED,
+            Self::Video => VIDEO_FLAG_FLAGGED,
+        }
+    }
+}
+
+/// Identifies a photo or video in the tags of a Shotwell database.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct SourceId(SourceKind, i64);
+
+impl SourceId {
+    /// Parses a source ID of Shotwell, which is the row ID of the media as 16
+    /// hexadecimal digits, prefixed with `thumb` for photos or `video-` for videos.
+    fn parse(source_id: &str) -> Option<Self> {
+        let source_id: &str = source_id.trim();
+
+        let (kind, id) = if let Some(id) = source_id.strip_prefix("thumb") {
+            (SourceKind::Photo, id)
+        } else if let Some(id) = source_id.strip_prefix("video-") {
+            (SourceKind::Video, id)
+        } else {
+            return None;
+        };
+        i64::from_str_radix(id, 16).ok().map(|id: i64| Self(kind, id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_ids() {
+        assert_eq!(
+            SourceId::parse("thumb000000000000002a"),
+            Some(SourceId(SourceKind::Photo, 42))
+        );
+        assert_eq!(
+            SourceId::parse("video-0000000000000007"),
+            Some(SourceId(SourceKind::Video, 7))
+        );
+        assert_eq!(SourceId::parse("event-0000000000000001"), None);
+        assert_eq!(SourceId::parse(""), None);
+    }
+
+    #[test]
+    fn tag_names() {
+        assert_eq!(tag_name("Holidays"), Some("Holidays"));
+        assert_eq!(tag_name("/Family/Kids"), Some("Kids"));
+        assert_eq!(tag_name("/Family"), Some("Family"));
+        assert_eq!(tag_name("/"), None);
+    }
+
+    #[test]
+    fn merging_keeps_existing_metadata() {
+        let imported: ImportedMetadata = ImportedMetadata {
+            favorites: vec!["file:///a.jpg".into(), "file:///b.jpg".into()],
+            ratings: HashMap::from([("file:///a.jpg".into(), 5), ("file:///b.jpg".into(), 2)]),
+            tags: HashMap::from([("file:///a.jpg".into(), vec!["Trip".into(), "Beach".into()])]),
+        };
+        assert_eq!(imported.media_count(), 2);
+
+        let mut favorites: Vec<String> = vec!["file:///a.jpg".into()];
+        let mut ratings: HashMap<String, u32> = HashMap::from([("file:///a.jpg".into(), 3)]);
+        let mut tags: HashMap<String, Vec<String>> =
+            HashMap::from([("file:///a.jpg".into(), vec!["Beach".into()])]);
+
+        imported.merge_into(&mut favorites, &mut ratings, &mut tags);
+
+        assert_eq!(favorites, vec!["file:///a.jpg", "file:///b.jpg"]);
+        assert_eq!(ratings["file:///a.jpg"], 3);
+        assert_eq!(ratings["file:///b.jpg"], 2);
+        assert_eq!(tags["file:///a.jpg"], vec!["Beach", "Trip"]);
+    }
+}
@@ -0,0 +1,626 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Dialog that renders photos into an MP4 slideshow video with ffmpeg.
+
+use super::media_viewer::ViewerContentType;
+use crate::application::{MemoriesApplication, RunningJob};
+use crate::globals::{
+    FFMPEG_BINARY, SLIDESHOW_FRAME_RATE, SLIDESHOW_MUSIC_FADE_SECONDS, SLIDESHOW_TRANSITION_SECONDS,
+};
+use crate::i18n::ngettext_f;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use async_process::{Child, Command, Output, Stdio};
+use futures_lite::io::{AsyncBufReadExt, BufReader};
+use futures_lite::StreamExt;
+use gettextrs::gettext;
+use glib::{clone, g_warning};
+use gtk::{gio, glib};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+mod imp {
+    use adw::subclass::prelude::*;
+    use gtk::{gio, glib};
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/slideshow-dialog.ui")]
+    pub struct MemoriesSlideshowDialog {
+        pub(super) photos: RefCell<Vec<gio::File>>,
+        pub(super) music: RefCell<Option<gio::File>>,
+        /// Cancels the slideshow being rendered. `None` while not rendering.
+        pub(super) cancellable: RefCell<Option<gio::Cancellable>>,
+        /// Toast overlay that the saved slideshow is announced in,
+        /// since the dialog is closed once the slideshow is saved.
+        pub(super) parent_toast_overlay: glib::WeakRef<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) create_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) content_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) summary_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) duration_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) transition_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub(super) resolution_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub(super) music_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub(super) clear_music_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) progress_bar: TemplateChild<gtk::ProgressBar>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesSlideshowDialog {
+        const NAME: &'static str = "MemoriesSlideshowDialog";
+        type Type = super::MemoriesSlideshowDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesSlideshowDialog {}
+    impl WidgetImpl for MemoriesSlideshowDialog {}
+    impl AdwDialogImpl for MemoriesSlideshowDialog {}
+}
+
+/// Enum that represents the transition shown between photos.
+/// Variant order matches the transition combo row model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlideshowTransition {
+    None,
+    Fade,
+    Slide,
+    Wipe,
+}
+
+impl SlideshowTransition {
+    pub fn from_index(index: u32) -> SlideshowTransition {
+        match index {
+            1 => SlideshowTransition::Fade,
+            2 => SlideshowTransition::Slide,
+            3 => SlideshowTransition::Wipe,
+            _ => SlideshowTransition::None,
+        }
+    }
+
+    /// Returns the name of the transition in ffmpeg's `xfade` filter.
+    pub fn xfade_name(&self) -> Option<&str> {
+        match *self {
+            SlideshowTransition::None => None,
+            SlideshowTransition::Fade => Some("fade"),
+            SlideshowTransition::Slide => Some("slideleft"),
+            SlideshowTransition::Wipe => Some("wipeleft"),
+        }
+    }
+}
+
+/// Enum that represents the resolution of the slideshow video.
+/// Variant order matches the resolution combo row model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlideshowResolution {
+    Hd,
+    FullHd,
+    Uhd,
+}
+
+impl SlideshowResolution {
+    pub fn from_index(index: u32) -> SlideshowResolution {
+        match index {
+            0 => SlideshowResolution::Hd,
+            2 => SlideshowResolution::Uhd,
+            _ => SlideshowResolution::FullHd,
+        }
+    }
+
+    /// Returns the width and height of the video, in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        match *self {
+            SlideshowResolution::Hd => (1280, 720),
+            SlideshowResolution::FullHd => (1920, 1080),
+            SlideshowResolution::Uhd => (3840, 2160),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlideshowOptions {
+    /// Seconds that each photo is shown for, including its transitions.
+    pub photo_duration: f64,
+    pub transition: SlideshowTransition,
+    pub resolution: SlideshowResolution,
+}
+
+impl SlideshowOptions {
+    /// Returns how many seconds each transition lasts. Transitions
+    /// take at most half of the time that each photo is shown for.
+    fn transition_duration(&self) -> f64 {
+        match self.transition {
+            SlideshowTransition::None => 0.0,
+            _ => SLIDESHOW_TRANSITION_SECONDS.min(self.photo_duration / 2.0),
+        }
+    }
+
+    /// Returns the length, in seconds, of a slideshow of `count` photos.
+    /// Photos overlap with the next photo while transitioning to it.
+    pub fn total_duration(&self, count: usize) -> f64 {
+        match count {
+            0 => 0.0,
+            _ => count as f64 * self.photo_duration - (count - 1) as f64 * self.transition_duration(),
+        }
+    }
+
+    /// Returns the ffmpeg filter graph that fits `count` photo inputs into the
+    /// video frame and joins them together. Its output is labeled `[video]`.
+    fn filter_graph(&self, count: usize) -> String {
+        let (width, height): (u32, u32) = self.resolution.size();
+        let mut filters: Vec<String> = vec![];
+
+        for i in 0..count {
+            filters.push(format!(
+                "[{0}:v]scale={1}:{2}:force_original_aspect_ratio=decrease,\
+                 pad={1}:{2}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={3},format=yuv420p[p{0}]",
+                i, width, height, SLIDESHOW_FRAME_RATE
+            ));
+        }
+
+        match self.transition.xfade_name() {
+            Some(xfade_name) if count > 1 => {
+                let transition_duration: f64 = self.transition_duration();
+                let mut previous: String = "p0".to_string();
+
+                for i in 1..count {
+                    let label: String = match i == count - 1 {
+                        true => "video".to_string(),
+                        false => format!("x{}", i),
+                    };
+                    filters.push(format!(
+                        "[{}][p{}]xfade=transition={}:duration={:.3}:offset={:.3}[{}]",
+                        previous,
+                        i,
+                        xfade_name,
+                        transition_duration,
+                        i as f64 * (self.photo_duration - transition_duration),
+                        label
+                    ));
+                    previous = label;
+                }
+            }
+            _ => {
+                let inputs: String = (0..count).map(|i: usize| format!("[p{}]", i)).collect();
+                filters.push(format!("{}concat=n={}:v=1:a=0[video]", inputs, count));
+            }
+        }
+        filters.join(";")
+    }
+
+    /// Returns the ffmpeg arguments that render `photos` into the MP4 video at
+    /// `output`. The optional `music` is looped, and faded out at the end.
+    pub fn ffmpeg_arguments(&self, photos: &[PathBuf], music: Option<&Path>, output: &Path) -> Vec<OsString> {
+        let photo_duration: String = format!("{:.3}", self.photo_duration);
+        let total_duration: f64 = self.total_duration(photos.len());
+
+        // Progress is written to stdout as `key=value` lines.
+        let mut arguments: Vec<OsString> = ["-y", "-loglevel", "error", "-nostats", "-progress", "pipe:1"]
+            .map(OsString::from)
+            .to_vec();
+
+        for photo in photos {
+            arguments.extend(["-loop", "1", "-t", photo_duration.as_str(), "-i"].map(OsString::from));
+            arguments.push(photo.into());
+        }
+        if let Some(music) = music {
+            arguments.extend(["-stream_loop", "-1", "-i"].map(OsString::from));
+            arguments.push(music.into());
+        }
+        let filter_graph: String = self.filter_graph(photos.len());
+        arguments.extend(["-filter_complex", filter_graph.as_str(), "-map", "[video]"].map(OsString::from));
+
+        if music.is_some() {
+            let music_stream: String = format!("{}:a", photos.len());
+            let fade_start: f64 = (total_duration - SLIDESHOW_MUSIC_FADE_SECONDS).max(0.0);
+            let fade_out: String = format!(
+                "afade=t=out:st={:.3}:d={:.3}",
+                fade_start, SLIDESHOW_MUSIC_FADE_SECONDS
+            );
+
+            arguments.extend(
+                [
+                    "-map",
+                    music_stream.as_str(),
+                    "-af",
+                    fade_out.as_str(),
+                    "-c:a",
+                    "aac",
+                ]
+                .map(OsString::from),
+            );
+        }
+        let total_duration: String = format!("{:.3}", total_duration);
+        arguments.extend(
+            [
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+                "-movflags",
+                "+faststart",
+                "-t",
+                total_duration.as_str(),
+            ]
+            .map(OsString::from),
+        );
+        arguments.push(output.into());
+        arguments
+    }
+}
+
+/// Parses a line of ffmpeg's `-progress` output. Returns how many
+/// seconds of video were rendered, if the line tells that.
+pub fn parse_progress(line: &str) -> Option<f64> {
+    let microseconds: u64 = line.strip_prefix("out_time_us=")?.trim().parse().ok()?;
+    Some(microseconds as f64 / 1_000_000.0)
+}
+
+/// Returns `true` if the file is a photo that can be shown in a slideshow.
+pub fn is_photo(file: &gio::File) -> bool {
+    file.basename().is_some_and(|path: PathBuf| {
+        path.extension()
+            .filter(|extension: &&OsStr| extension.to_str().is_some())
+            .is_some_and(|extension: &OsStr| {
+                matches!(ViewerContentType::from_ext(extension), ViewerContentType::Image)
+            })
+    })
+}
+
+glib::wrapper! {
+    pub struct MemoriesSlideshowDialog(ObjectSubclass<imp::MemoriesSlideshowDialog>)
+        @extends gtk::Widget, adw::Dialog;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesSlideshowDialog {
+    /// Creates a dialog that renders the given photos into a slideshow. Once the
+    /// slideshow is saved, a toast that opens it is shown in `toast_overlay`.
+    pub fn new(photos: &[gio::File], toast_overlay: &adw::ToastOverlay) -> Self {
+        let obj: Self = glib::Object::new();
+        obj.imp().photos.replace(photos.to_vec());
+        obj.imp().parent_toast_overlay.set(Some(toast_overlay));
+
+        let count: u32 = photos.len().try_into().unwrap_or(u32::MAX);
+
+        obj.imp().summary_group.set_description(Some(&ngettext_f(
+            "{COUNT} photo will be shown",
+            "{COUNT} photos will be shown",
+            count,
+            &[("COUNT", &count.to_string())],
+        )));
+        obj
+    }
+
+    fn options(&self) -> SlideshowOptions {
+        SlideshowOptions {
+            photo_duration: self.imp().duration_row.value(),
+            transition: SlideshowTransition::from_index(self.imp().transition_row.selected()),
+            resolution: SlideshowResolution::from_index(self.imp().resolution_row.selected()),
+        }
+    }
+
+    #[template_callback]
+    fn cancel_clicked(&self) {
+        match self.imp().cancellable.borrow().as_ref() {
+            Some(cancellable) => cancellable.cancel(),
+            None => {
+                self.close();
+            }
+        }
+    }
+
+    /// Closing the dialog while rendering cancels the slideshow instead.
+    #[template_callback]
+    fn close_attempted(&self) {
+        if let Some(cancellable) = self.imp().cancellable.borrow().as_ref() {
+            cancellable.cancel();
+        }
+    }
+
+    #[template_callback]
+    fn choose_music_clicked(&self) {
+        let filter: gtk::FileFilter = gtk::FileFilter::new();
+        filter.set_name(Some(&gettext("Audio Files")));
+        filter.add_mime_type("audio/*");
+
+        let filters: gio::ListStore = gio::ListStore::new::<gtk::FileFilter>();
+        filters.append(&filter);
+
+        let file_dialog: gtk::FileDialog = gtk::FileDialog::builder()
+            .title(gettext("Choose Background Music"))
+            .filters(&filters)
+            .modal(true)
+            .build();
+        let window: Option<gtk::Window> = self.root().and_downcast();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                // An error is returned if the user dismisses the file chooser.
+                if let Ok(file) = file_dialog.open_future(window.as_ref()).await {
+                    this.set_music(Some(file));
+                }
+            }
+        ));
+    }
+
+    #[template_callback]
+    fn clear_music_clicked(&self) {
+        self.set_music(None);
+    }
+
+    fn set_music(&self, music: Option<gio::File>) {
+        let subtitle: String = match music.as_ref().and_then(|file: &gio::File| file.basename()) {
+            Some(basename) => basename.to_string_lossy().to_string(),
+            None => gettext("None"),
+        };
+        self.imp().music_row.set_subtitle(&subtitle);
+        self.imp().clear_music_button.set_visible(music.is_some());
+        self.imp().music.replace(music);
+    }
+
+    #[template_callback]
+    fn create_clicked(&self) {
+        let filter: gtk::FileFilter = gtk::FileFilter::new();
+        filter.set_name(Some(&gettext("MP4 Video")));
+        filter.add_mime_type("video/mp4");
+
+        let filters: gio::ListStore = gio::ListStore::new::<gtk::FileFilter>();
+        filters.append(&filter);
+
+        let file_dialog: gtk::FileDialog = gtk::FileDialog::builder()
+            .title(gettext("Save Slideshow"))
+            .initial_name(format!("{}.mp4", gettext("Slideshow")))
+            .filters(&filters)
+            .modal(true)
+            .build();
+        let window: Option<gtk::Window> = self.root().and_downcast();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                if let Ok(file) = file_dialog.save_future(window.as_ref()).await {
+                    this.render(&file).await;
+                }
+            }
+        ));
+    }
+
+    /// Renders the slideshow into `output` with ffmpeg, updating the progress
+    /// bar from ffmpeg's progress output. Closes the dialog once it is saved.
+    async fn render(&self, output: &gio::File) {
+        let photos: Vec<PathBuf> = self
+            .imp()
+            .photos
+            .borrow()
+            .iter()
+            .filter_map(gio::File::path)
+            .collect();
+        let music: Option<PathBuf> = self.imp().music.borrow().as_ref().and_then(gio::File::path);
+
+        let Some(output_path) = output.path().filter(|_| !photos.is_empty()) else {
+            self.show_error_toast();
+            return;
+        };
+        let options: SlideshowOptions = self.options();
+        let total_duration: f64 = options.total_duration(photos.len());
+
+        let mut ffmpeg_command: Command = Command::new(FFMPEG_BINARY);
+
+        ffmpeg_command
+            .kill_on_drop(true)
+            .args(options.ffmpeg_arguments(&photos, music.as_deref(), &output_path))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut ffmpeg_child: Child = match ffmpeg_command.spawn() {
+            Ok(child) => child,
+            Err(io_error) => {
+                g_warning!("SlideshowDialog", "Failed to execute ffmpeg: {}", io_error);
+                self.show_error_toast();
+                return;
+            }
+        };
+        let ffmpeg_stdout = ffmpeg_child.stdout.take().unwrap();
+
+        let cancellable: gio::Cancellable = gio::Cancellable::new();
+        self.imp().cancellable.replace(Some(cancellable.clone()));
+        self.set_can_close(false);
+        self.imp().create_button.set_sensitive(false);
+        self.imp().progress_bar.set_fraction(0.0);
+        self.imp().content_stack.set_visible_child_name("progress");
+
+        let _job: RunningJob =
+            MemoriesApplication::default().start_job(&gettext("Creating a slideshow video"));
+
+        // Dropping the ffmpeg child process kills it, so canceling this future stops rendering.
+        let rendering = async {
+            let mut lines = BufReader::new(ffmpeg_stdout).lines();
+
+            while let Some(Ok(line)) = lines.next().await {
+                if let Some(seconds) = parse_progress(&line) {
+                    self.imp()
+                        .progress_bar
+                        .set_fraction((seconds / total_duration).clamp(0.0, 1.0));
+                }
+            }
+            ffmpeg_child.output().await
+        };
+        let ffmpeg_output: Result<std::io::Result<Output>, gio::Cancelled> =
+            gio::CancellableFuture::new(rendering, cancellable.clone()).await;
+
+        self.imp().cancellable.take();
+        self.set_can_close(true);
+        self.imp().create_button.set_sensitive(true);
+
+        match ffmpeg_output {
+            Ok(Ok(output)) if output.status.success() => {
+                self.close();
+                self.show_saved_toast(&gio::File::for_path(output_path));
+                return;
+            }
+            Ok(Ok(output)) => {
+                g_warning!(
+                    "SlideshowDialog",
+                    "FFmpeg failed to render the slideshow: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                self.show_error_toast();
+            }
+            Ok(Err(io_error)) => {
+                g_warning!("SlideshowDialog", "Failed to render the slideshow: {}", io_error);
+                self.show_error_toast();
+            }
+            Err(_) => {
+                self.imp()
+                    .toast_overlay
+                    .add_toast(adw::Toast::new(&gettext("Slideshow canceled")));
+            }
+        }
+        // Do not leave a partially rendered video behind.
+        let _ = std::fs::remove_file(&output_path);
+        self.imp().content_stack.set_visible_child_name("options");
+    }
+
+    /// Shows a toast, outside of the closed dialog, that opens the saved slideshow.
+    fn show_saved_toast(&self, video: &gio::File) {
+        MemoriesApplication::default().notify_job_finished(
+            "slideshow",
+            &gettext("Slideshow Saved"),
+            &video.basename().unwrap_or_default().to_string_lossy(),
+            Some(video),
+        );
+
+        let Some(toast_overlay) = self.imp().parent_toast_overlay.upgrade() else {
+            return;
+        };
+        let toast: adw::Toast = adw::Toast::builder()
+            .title(gettext("Slideshow saved"))
+            .button_label(gettext("_Open"))
+            .build();
+
+        toast.connect_button_clicked(clone!(
+            #[weak]
+            toast_overlay,
+            #[strong]
+            video,
+            move |_: &adw::Toast| {
+                let launcher: gtk::FileLauncher = gtk::FileLauncher::new(Some(&video));
+                let window: Option<gtk::Window> = toast_overlay.root().and_downcast();
+
+                glib::spawn_future_local(async move {
+                    if let Err(err) = launcher.launch_future(window.as_ref()).await {
+                        g_warning!("SlideshowDialog", "Failed to open the slideshow: {}", err);
+                    }
+                });
+            }
+        ));
+        toast_overlay.add_toast(toast);
+    }
+
+    fn show_error_toast(&self) {
+        let toast: adw::Toast = adw::Toast::new(&gettext("Failed to create the slideshow"));
+        self.imp().toast_overlay.add_toast(toast);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(transition: SlideshowTransition) -> SlideshowOptions {
+        SlideshowOptions {
+            photo_duration: 4.0,
+            transition,
+            resolution: SlideshowResolution::Hd,
+        }
+    }
+
+    #[test]
+    fn slideshow_durations() {
+        assert_eq!(options(SlideshowTransition::None).total_duration(0), 0.0);
+        assert_eq!(options(SlideshowTransition::None).total_duration(3), 12.0);
+        assert_eq!(options(SlideshowTransition::Fade).total_duration(1), 4.0);
+        assert_eq!(options(SlideshowTransition::Fade).total_duration(3), 10.0);
+
+        let short_photos: SlideshowOptions = SlideshowOptions {
+            photo_duration: 1.0,
+            ..options(SlideshowTransition::Wipe)
+        };
+        assert_eq!(short_photos.total_duration(3), 2.0);
+    }
+
+    #[test]
+    fn slideshow_filter_graphs() {
+        let scale: &str = "scale=1280:720:force_original_aspect_ratio=decrease,\
+                           pad=1280:720:(ow-iw)/2:(oh-ih)/2,setsar=1,fps=30,format=yuv420p";
+
+        assert_eq!(
+            options(SlideshowTransition::None).filter_graph(2),
+            format!(
+                "[0:v]{0}[p0];[1:v]{0}[p1];[p0][p1]concat=n=2:v=1:a=0[video]",
+                scale
+            )
+        );
+        assert_eq!(
+            options(SlideshowTransition::Fade).filter_graph(1),
+            format!("[0:v]{0}[p0];[p0]concat=n=1:v=1:a=0[video]", scale)
+        );
+        assert_eq!(
+            options(SlideshowTransition::Slide).filter_graph(3),
+            format!(
+                "[0:v]{0}[p0];[1:v]{0}[p1];[2:v]{0}[p2];\
+                 [p0][p1]xfade=transition=slideleft:duration=1.000:offset=3.000[x1];\
+                 [x1][p2]xfade=transition=slideleft:duration=1.000:offset=6.000[video]",
+                scale
+            )
+        );
+    }
+
+    #[test]
+    fn ffmpeg_progress_lines() {
+        assert_eq!(parse_progress("out_time_us=2500000"), Some(2.5));
+        assert_eq!(parse_progress("out_time_us=N/A"), None);
+        assert_eq!(parse_progress("progress=continue"), None);
+    }
+}
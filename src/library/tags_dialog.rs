@@ -0,0 +1,262 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::application::MemoriesApplication;
+use crate::i18n::{gettext_f, ngettext_f};
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::clone;
+use gtk::{gio, glib};
+
+mod imp {
+    use adw::subclass::prelude::*;
+    use gtk::{gio, glib};
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/tags-dialog.ui")]
+    pub struct MemoriesTagsDialog {
+        pub(super) files: RefCell<Vec<gio::File>>,
+        #[template_child]
+        pub(super) tag_entry_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub(super) tags_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) tags_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub(super) other_tags_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) other_tags_list: TemplateChild<gtk::ListBox>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesTagsDialog {
+        const NAME: &'static str = "MemoriesTagsDialog";
+        type Type = super::MemoriesTagsDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesTagsDialog {}
+    impl WidgetImpl for MemoriesTagsDialog {}
+    impl AdwDialogImpl for MemoriesTagsDialog {}
+}
+
+/// Splits user input into tags, separated by commas. Whitespace around
+/// each tag is trimmed, and empty or repeated tags are skipped.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+
+    for tag in text.split(',').map(str::trim) {
+        if !tag.is_empty() && !tags.iter().any(|existing: &String| existing == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+glib::wrapper! {
+    pub struct MemoriesTagsDialog(ObjectSubclass<imp::MemoriesTagsDialog>)
+        @extends gtk::Widget, adw::Dialog;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesTagsDialog {
+    /// Creates a new dialog for editing the tags of the given files.
+    /// Changes are applied to all files as soon as they are made.
+    pub fn new(files: &[gio::File]) -> Self {
+        let obj: Self = glib::Object::new();
+
+        obj.imp().files.replace(files.to_vec());
+        obj.update_tags();
+        obj
+    }
+
+    /// Adds the tags typed in the entry row to all files.
+    #[template_callback]
+    fn add_entered_tags(&self) {
+        let tags: Vec<String> = parse_tags(&self.imp().tag_entry_row.text());
+
+        self.imp().tag_entry_row.set_text("");
+        self.add_tags(&tags);
+    }
+
+    fn add_tags(&self, tags: &[String]) {
+        let app: MemoriesApplication = MemoriesApplication::default();
+
+        for file in self.imp().files.borrow().iter() {
+            let mut file_tags: Vec<String> = app.tags(file);
+            let length: usize = file_tags.len();
+
+            for tag in tags {
+                if !file_tags.contains(tag) {
+                    file_tags.push(tag.clone());
+                }
+            }
+            if file_tags.len() != length {
+                app.set_tags(file, file_tags);
+            }
+        }
+        self.update_tags();
+    }
+
+    fn remove_tag(&self, tag: &str) {
+        let app: MemoriesApplication = MemoriesApplication::default();
+
+        for file in self.imp().files.borrow().iter() {
+            let mut file_tags: Vec<String> = app.tags(file);
+            let length: usize = file_tags.len();
+
+            file_tags.retain(|file_tag: &String| file_tag != tag);
+
+            if file_tags.len() != length {
+                app.set_tags(file, file_tags);
+            }
+        }
+        self.update_tags();
+    }
+
+    /// Rebuilds the list of tags of the files, and the list of
+    /// other tags in the library that can be added with one click.
+    fn update_tags(&self) {
+        let app: MemoriesApplication = MemoriesApplication::default();
+        let files = self.imp().files.borrow();
+        let file_count: usize = files.len();
+
+        // Tags in the order they were first added, with the amount of files that have them.
+        let mut tags: Vec<(String, usize)> = vec![];
+
+        for file in files.iter() {
+            for tag in app.tags(file) {
+                match tags.iter_mut().find(|(existing, _)| *existing == tag) {
+                    Some((_, count)) => *count += 1,
+                    None => tags.push((tag, 1)),
+                }
+            }
+        }
+
+        let tags_list: &gtk::ListBox = &self.imp().tags_list;
+        tags_list.remove_all();
+
+        for (tag, count) in &tags {
+            let row: adw::ActionRow = adw::ActionRow::builder()
+                .title(glib::markup_escape_text(tag))
+                .build();
+
+            if *count < file_count {
+                row.set_subtitle(&gettext_f(
+                    "On {COUNT} of {TOTAL} items",
+                    &[("COUNT", &count.to_string()), ("TOTAL", &file_count.to_string())],
+                ));
+            }
+            let remove_button: gtk::Button = gtk::Button::builder()
+                .icon_name("edit-delete-symbolic")
+                .tooltip_text(gettext("Remove Tag"))
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+
+            remove_button.connect_clicked(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[strong]
+                tag,
+                move |_: &gtk::Button| {
+                    this.remove_tag(&tag);
+                }
+            ));
+            row.add_suffix(&remove_button);
+            tags_list.append(&row);
+        }
+        tags_list.set_visible(!tags.is_empty());
+
+        self.imp().tags_group.set_description(Some(&if tags.is_empty() {
+            ngettext_f(
+                "This item has no tags",
+                "These {COUNT} items have no tags",
+                file_count.try_into().unwrap_or(u32::MAX),
+                &[("COUNT", &file_count.to_string())],
+            )
+        } else {
+            ngettext_f(
+                "Tags of this item",
+                "Tags of these {COUNT} items",
+                file_count.try_into().unwrap_or(u32::MAX),
+                &[("COUNT", &file_count.to_string())],
+            )
+        }));
+
+        let other_tags_list: &gtk::ListBox = &self.imp().other_tags_list;
+        other_tags_list.remove_all();
+
+        let other_tags: Vec<(String, u32)> = app
+            .all_tags()
+            .into_iter()
+            .filter(|(tag, _)| !tags.iter().any(|(existing, _)| existing == tag))
+            .collect();
+
+        for (tag, count) in &other_tags {
+            let row: adw::ActionRow = adw::ActionRow::builder()
+                .title(glib::markup_escape_text(tag))
+                .subtitle(ngettext_f(
+                    "{COUNT} item",
+                    "{COUNT} items",
+                    *count,
+                    &[("COUNT", &count.to_string())],
+                ))
+                .activatable(true)
+                .build();
+
+            row.add_suffix(&gtk::Image::from_icon_name("list-add-symbolic"));
+            row.connect_activated(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[strong]
+                tag,
+                move |_: &adw::ActionRow| {
+                    this.add_tags(&[tag.clone()]);
+                }
+            ));
+            other_tags_list.append(&row);
+        }
+        self.imp().other_tags_group.set_visible(!other_tags.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_comma_separated_tags() {
+        assert_eq!(parse_tags(" beach, family ,,beach"), vec!["beach", "family"]);
+        assert_eq!(parse_tags("  "), Vec::<String>::new());
+        assert_eq!(parse_tags("New York"), vec!["New York"]);
+    }
+}
@@ -0,0 +1,251 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::globals::{TILED_RENDERING_MAX_TILES, TILED_RENDERING_PREVIEW_SIZE, TILED_RENDERING_TILE_SIZE};
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::{clone, g_warning};
+use gtk::{gdk, glib, graphene};
+use std::rc::Rc;
+
+mod imp {
+    use super::TileKey;
+    use adw::prelude::*;
+    use adw::subclass::prelude::*;
+    use gtk::{gdk, glib, graphene};
+    use std::cell::{Cell, OnceCell, RefCell};
+    use std::collections::{HashMap, HashSet, VecDeque};
+    use std::rc::Rc;
+
+    /// `GdkPaintable` implementation for very large images. Instead of
+    /// decoding the whole image into a single texture, a downscaled preview
+    /// is drawn, and tiles for the visible area are decoded on demand
+    /// through glycin at the resolution that they are displayed at.
+    #[derive(Default)]
+    pub struct MemoriesTiledPaintable {
+        pub(super) image: OnceCell<Rc<glycin::Image<'static>>>,
+        pub(super) image_width: Cell<u32>,
+        pub(super) image_height: Cell<u32>,
+        pub(super) preview: RefCell<Option<gdk::Texture>>,
+        /// Visible area of the image, in image pixel coordinates.
+        /// If `None`, the whole image is considered visible.
+        pub(super) visible_area: Cell<Option<graphene::Rect>>,
+        pub(super) tiles: RefCell<HashMap<TileKey, gdk::Texture>>,
+        /// Keys of cached tiles from least to most recently used.
+        pub(super) tile_usage: RefCell<VecDeque<TileKey>>,
+        pub(super) pending_tiles: RefCell<HashSet<TileKey>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesTiledPaintable {
+        const NAME: &'static str = "MemoriesTiledPaintable";
+        type Type = super::MemoriesTiledPaintable;
+        type Interfaces = (gdk::Paintable,);
+    }
+
+    impl ObjectImpl for MemoriesTiledPaintable {}
+
+    impl PaintableImpl for MemoriesTiledPaintable {
+        fn intrinsic_width(&self) -> i32 {
+            self.image_width.get() as i32
+        }
+
+        fn intrinsic_height(&self) -> i32 {
+            self.image_height.get() as i32
+        }
+
+        fn snapshot(&self, snapshot: &gdk::Snapshot, width: f64, height: f64) {
+            let snapshot: &gtk::Snapshot = snapshot.downcast_ref().unwrap();
+            let bounds: graphene::Rect = graphene::Rect::new(0.0, 0.0, width as f32, height as f32);
+
+            if let Some(preview) = self.preview.borrow().as_ref() {
+                snapshot.append_texture(preview, &bounds);
+
+                // The preview is sharp enough if it is not upscaled.
+                if width <= preview.width() as f64 && height <= preview.height() as f64 {
+                    return;
+                }
+            }
+            self.obj()
+                .snapshot_tiles(snapshot, width / self.image_width.get() as f64);
+        }
+    }
+}
+
+/// Identifies a tile by its downscale factor, column, and row. A tile covers
+/// a square region of `TILED_RENDERING_TILE_SIZE * scale_down` image pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileKey {
+    scale_down: u32,
+    column: u32,
+    row: u32,
+}
+
+glib::wrapper! {
+    pub struct MemoriesTiledPaintable(ObjectSubclass<imp::MemoriesTiledPaintable>)
+        @implements gdk::Paintable;
+}
+
+impl MemoriesTiledPaintable {
+    /// Creates a new tiled paintable for a loaded glycin image,
+    /// and decodes its downscaled preview texture.
+    pub async fn new(image: glycin::Image<'static>) -> Result<Self, glycin::ErrorCtx> {
+        let obj: Self = glib::Object::new();
+        let (width, height) = (image.info().width, image.info().height);
+
+        // Decode a preview that fits within the preview size, keeping the aspect ratio.
+        let preview_scale: f64 = (TILED_RENDERING_PREVIEW_SIZE as f64 / width.max(height) as f64).min(1.0);
+        let preview_width: u32 = ((width as f64 * preview_scale) as u32).max(1);
+        let preview_height: u32 = ((height as f64 * preview_scale) as u32).max(1);
+
+        let preview: glycin::Frame = image
+            .specific_frame(glycin::FrameRequest::new().scale(preview_width, preview_height))
+            .await?;
+
+        obj.imp().image_width.set(width);
+        obj.imp().image_height.set(height);
+        obj.imp().preview.replace(Some(preview.texture()));
+        let _ = obj.imp().image.set(Rc::new(image));
+
+        Ok(obj)
+    }
+
+    /// Sets the area of the image that is visible, in image pixel coordinates,
+    /// so that only tiles within this area are decoded. `None` means that
+    /// the whole image is visible.
+    pub fn set_visible_area(&self, area: Option<graphene::Rect>) {
+        self.imp().visible_area.set(area);
+        self.invalidate_contents();
+    }
+
+    /// Draws the cached tiles that intersect the visible area at the given
+    /// display scale, and requests decoding of the tiles that are missing.
+    fn snapshot_tiles(&self, snapshot: &gtk::Snapshot, scale: f64) {
+        let (image_width, image_height) = (self.imp().image_width.get(), self.imp().image_height.get());
+
+        // Use the largest power of two downscale factor that is still sharp at this scale.
+        let mut scale_down: u32 = 1;
+        while scale * (scale_down * 2) as f64 <= 1.0 {
+            scale_down *= 2;
+        }
+        let tile_extent: u32 = TILED_RENDERING_TILE_SIZE * scale_down;
+
+        let visible: graphene::Rect = self
+            .imp()
+            .visible_area
+            .get()
+            .unwrap_or_else(|| graphene::Rect::new(0.0, 0.0, image_width as f32, image_height as f32));
+
+        let first_column: u32 = (visible.x().max(0.0) as u32) / tile_extent;
+        let first_row: u32 = (visible.y().max(0.0) as u32) / tile_extent;
+        let last_column: u32 = ((visible.x() + visible.width()).min(image_width as f32) as u32) / tile_extent;
+        let last_row: u32 = ((visible.y() + visible.height()).min(image_height as f32) as u32) / tile_extent;
+
+        for row in first_row..=last_row {
+            for column in first_column..=last_column {
+                let key: TileKey = TileKey {
+                    scale_down,
+                    column,
+                    row,
+                };
+                let x: u32 = column * tile_extent;
+                let y: u32 = row * tile_extent;
+
+                if x >= image_width || y >= image_height {
+                    continue;
+                }
+                let extent_width: u32 = tile_extent.min(image_width - x);
+                let extent_height: u32 = tile_extent.min(image_height - y);
+
+                match self.cached_tile(key) {
+                    Some(texture) => snapshot.append_texture(
+                        &texture,
+                        &graphene::Rect::new(
+                            (x as f64 * scale) as f32,
+                            (y as f64 * scale) as f32,
+                            (extent_width as f64 * scale) as f32,
+                            (extent_height as f64 * scale) as f32,
+                        ),
+                    ),
+                    None => self.request_tile(key, (x, y, extent_width, extent_height)),
+                }
+            }
+        }
+    }
+
+    /// Returns the cached tile texture, marking it as the most recently used.
+    fn cached_tile(&self, key: TileKey) -> Option<gdk::Texture> {
+        let texture: gdk::Texture = self.imp().tiles.borrow().get(&key)?.clone();
+
+        let mut usage = self.imp().tile_usage.borrow_mut();
+        usage.retain(|used: &TileKey| *used != key);
+        usage.push_back(key);
+
+        Some(texture)
+    }
+
+    /// Decodes the region of the image for a tile in the background,
+    /// and redraws the paintable once the tile is cached.
+    fn request_tile(&self, key: TileKey, region: (u32, u32, u32, u32)) {
+        if !self.imp().pending_tiles.borrow_mut().insert(key) {
+            return;
+        }
+        let image: Rc<glycin::Image<'static>> = self.imp().image.get().unwrap().clone();
+        let (x, y, width, height) = region;
+
+        let frame_request: glycin::FrameRequest = glycin::FrameRequest::new()
+            .clip(x, y, width, height)
+            .scale((width / key.scale_down).max(1), (height / key.scale_down).max(1));
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let frame: Result<glycin::Frame, glycin::ErrorCtx> =
+                    image.specific_frame(frame_request).await;
+                this.imp().pending_tiles.borrow_mut().remove(&key);
+
+                match frame {
+                    Ok(frame) => {
+                        this.insert_tile(key, frame.texture());
+                        this.invalidate_contents();
+                    }
+                    Err(err) => g_warning!("TiledPaintable", "Failed to decode tile {:?}: {}", key, err),
+                }
+            }
+        ));
+    }
+
+    /// Caches a tile, evicting the least recently used
+    /// tiles to keep the amount of memory used bounded.
+    fn insert_tile(&self, key: TileKey, texture: gdk::Texture) {
+        let mut tiles = self.imp().tiles.borrow_mut();
+        let mut usage = self.imp().tile_usage.borrow_mut();
+
+        while tiles.len() >= TILED_RENDERING_MAX_TILES {
+            match usage.pop_front() {
+                Some(oldest) => tiles.remove(&oldest),
+                None => break,
+            };
+        }
+        tiles.insert(key, texture);
+        usage.push_back(key);
+    }
+}
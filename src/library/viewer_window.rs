@@ -0,0 +1,113 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Window that shows a single media file in a media viewer, which is opened
+//! next to the application window to compare media side by side. It shares
+//! the library of the application, but does not show the library itself.
+
+use super::media_viewer::{MemoriesMediaViewer, ViewerContentType};
+use crate::application::MemoriesApplication;
+use crate::globals::DEVELOPMENT_BUILD;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gtk::{gio, glib};
+
+mod imp {
+    use adw::subclass::prelude::*;
+    use gtk::glib;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/viewer-window.ui")]
+    pub struct MemoriesViewerWindow {
+        #[template_child]
+        pub(super) window_navigation: TemplateChild<adw::NavigationView>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesViewerWindow {
+        const NAME: &'static str = "MemoriesViewerWindow";
+        type Type = super::MemoriesViewerWindow;
+        type ParentType = adw::ApplicationWindow;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesViewerWindow {}
+    impl WidgetImpl for MemoriesViewerWindow {}
+    impl WindowImpl for MemoriesViewerWindow {}
+    impl ApplicationWindowImpl for MemoriesViewerWindow {}
+    impl AdwApplicationWindowImpl for MemoriesViewerWindow {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesViewerWindow(ObjectSubclass<imp::MemoriesViewerWindow>)
+        @extends gtk::Widget, gtk::Window, gtk::ApplicationWindow, adw::ApplicationWindow,
+        @implements gio::ActionGroup, gio::ActionMap, gtk::Root;
+}
+
+impl MemoriesViewerWindow {
+    /// Creates a new window that shows the given media file in a media viewer.
+    pub fn new(
+        application: &MemoriesApplication,
+        file: &gio::File,
+        content_type: &ViewerContentType,
+    ) -> Self {
+        let obj: Self = glib::Object::builder()
+            .property("application", application)
+            .build();
+
+        if DEVELOPMENT_BUILD {
+            obj.add_css_class("devel");
+        }
+        obj.setup_gactions();
+        obj.set_title(
+            file.basename()
+                .map(|name| name.to_string_lossy().to_string())
+                .as_deref(),
+        );
+
+        let viewer: MemoriesMediaViewer =
+            MemoriesMediaViewer::push_new(&obj.imp().window_navigation, file, content_type);
+        viewer.imp().properties_widget.update_file_details(file);
+        obj
+    }
+
+    /// Unlike the application window, the fullscreen state of
+    /// a viewer window is not persisted in GSettings.
+    fn setup_gactions(&self) {
+        let toggle_fullscreen_action = gio::ActionEntry::builder("fullscreen")
+            .state(false.to_variant())
+            .activate(move |win: &Self, action: &gio::SimpleAction, _| {
+                let new_state: bool = !win.is_fullscreen();
+
+                win.set_fullscreened(new_state);
+                action.set_state(&new_state.to_variant());
+            })
+            .build();
+
+        self.add_action_entries([toggle_fullscreen_action]);
+    }
+}
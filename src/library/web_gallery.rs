@@ -0,0 +1,216 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Static HTML gallery of exported media, which can be opened in any web
+//! browser without a server, such as from a USB stick or a web host.
+//! Thumbnails link to the full media, which is shown in a lightbox with
+//! a little JavaScript, or opened directly if JavaScript is disabled.
+
+/// A media file in the gallery, with paths relative to the gallery folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GalleryItem {
+    pub media: String,
+    pub thumbnail: String,
+    pub is_video: bool,
+}
+
+const GALLERY_STYLE: &str = "\
+body { margin: 0; padding: 12px; background: #1e1e1e; color: #fff; font-family: sans-serif; }
+h1 { font-size: 1.5em; font-weight: 800; margin: 12px 6px; }
+.grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(160px, 1fr)); gap: 6px; }
+.grid a { position: relative; display: block; aspect-ratio: 1; }
+.grid img { width: 100%; height: 100%; object-fit: cover; border-radius: 6px; }
+.grid a.video::after { content: '\\25B6'; position: absolute; right: 8px; bottom: 6px; }
+#lightbox { display: none; position: fixed; inset: 0; background: #000; align-items: center; justify-content: center; }
+#lightbox.open { display: flex; }
+#lightbox img, #lightbox video { max-width: 100%; max-height: 100%; }
+#lightbox button { position: absolute; background: none; border: none; color: #fff; font-size: 2em; cursor: pointer; padding: 12px; }
+#close { top: 0; right: 0; }
+#previous { left: 0; }
+#next { right: 0; }
+";
+
+const GALLERY_SCRIPT: &str = "\
+const links = Array.from(document.querySelectorAll('.grid a'));
+const lightbox = document.getElementById('lightbox');
+const content = document.getElementById('content');
+let current = -1;
+
+function show(index) {
+  current = (index + links.length) % links.length;
+  const link = links[current];
+  const media = document.createElement(link.classList.contains('video') ? 'video' : 'img');
+  media.src = link.href;
+  if (media.tagName === 'VIDEO') {
+    media.controls = true;
+    media.autoplay = true;
+  } else {
+    media.alt = link.querySelector('img').alt;
+  }
+  content.replaceChildren(media);
+  lightbox.classList.add('open');
+}
+
+function close() {
+  content.replaceChildren();
+  lightbox.classList.remove('open');
+  current = -1;
+}
+
+links.forEach((link, index) => link.addEventListener('click', (event) => {
+  event.preventDefault();
+  show(index);
+}));
+document.getElementById('close').addEventListener('click', close);
+document.getElementById('previous').addEventListener('click', () => show(current - 1));
+document.getElementById('next').addEventListener('click', () => show(current + 1));
+document.addEventListener('keydown', (event) => {
+  if (current < 0) return;
+  if (event.key === 'Escape') close();
+  if (event.key === 'ArrowLeft') show(current - 1);
+  if (event.key === 'ArrowRight') show(current + 1);
+});
+";
+
+/// Returns the `index.html` page of a gallery with the given title and media.
+pub fn gallery_html(title: &str, items: &[GalleryItem]) -> String {
+    let title: String = escape_html(title);
+    let mut links: String = String::new();
+
+    for item in items {
+        let name: &str = item.media.rsplit('/').next().unwrap_or(&item.media);
+
+        links.push_str(&format!(
+            "<a href=\"{}\"{}><img src=\"{}\" alt=\"{}\" loading=\"lazy\"></a>\n",
+            escape_path(&item.media),
+            if item.is_video { " class=\"video\"" } else { "" },
+            escape_path(&item.thumbnail),
+            escape_html(name),
+        ));
+    }
+
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{title}</title>
+<style>
+{GALLERY_STYLE}</style>
+</head>
+<body>
+<h1>{title}</h1>
+<div class=\"grid\">
+{links}</div>
+<div id=\"lightbox\">
+<div id=\"content\"></div>
+<button id=\"previous\" aria-label=\"Previous\">&#8249;</button>
+<button id=\"next\" aria-label=\"Next\">&#8250;</button>
+<button id=\"close\" aria-label=\"Close\">&#215;</button>
+</div>
+<script>
+{GALLERY_SCRIPT}</script>
+</body>
+</html>
+"
+    )
+}
+
+/// Escapes text for use in HTML content and attribute values.
+fn escape_html(text: &str) -> String {
+    let mut escaped: String = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+/// Percent-encodes a relative path for use in a URL, keeping its slashes.
+fn escape_path(path: &str) -> String {
+    let mut escaped: String = String::with_capacity(path.len());
+
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                escaped.push(byte as char)
+            }
+            _ => escaped.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_html() {
+        assert_eq!(
+            escape_html("Tom & Jerry's <Trip>"),
+            "Tom &amp; Jerry&#39;s &lt;Trip&gt;"
+        );
+        assert_eq!(escape_html("\"Beach\""), "&quot;Beach&quot;");
+    }
+
+    #[test]
+    fn escaped_paths() {
+        assert_eq!(escape_path("media/IMG_0001.jpg"), "media/IMG_0001.jpg");
+        assert_eq!(
+            escape_path("media/Beach #2 (2).jpg"),
+            "media/Beach%20%232%20%282%29.jpg"
+        );
+        assert_eq!(escape_path("media/café.jpg"), "media/caf%C3%A9.jpg");
+    }
+
+    #[test]
+    fn gallery_links_media() {
+        let items: Vec<GalleryItem> = vec![
+            GalleryItem {
+                media: "media/a b.jpg".to_string(),
+                thumbnail: "thumbnails/a b.jpg".to_string(),
+                is_video: false,
+            },
+            GalleryItem {
+                media: "media/<clip>.mp4".to_string(),
+                thumbnail: "thumbnails/<clip>.jpg".to_string(),
+                is_video: true,
+            },
+        ];
+        let html: String = gallery_html("Trip & Friends", &items);
+
+        assert!(html.contains("<title>Trip &amp; Friends</title>"));
+        assert!(
+            html.contains("<a href=\"media/a%20b.jpg\"><img src=\"thumbnails/a%20b.jpg\" alt=\"a b.jpg\"")
+        );
+        assert!(html.contains(
+            "<a href=\"media/%3Cclip%3E.mp4\" class=\"video\"><img src=\"thumbnails/%3Cclip%3E.jpg\" alt=\"&lt;clip&gt;.mp4\""
+        ));
+    }
+}
@@ -70,18 +70,29 @@ Simplified widget tree including the most important widgets.
 
 mod albums;
 mod application;
+mod archive;
+mod cast;
 mod config;
+mod devices;
+mod folders;
 #[allow(dead_code)]
 mod globals;
 #[allow(dead_code)]
 mod i18n;
 mod library;
+#[cfg(feature = "use-face-detection")]
+mod people;
+mod private;
+mod statistics;
+#[cfg(feature = "use-sync")]
+mod sync;
+mod trash;
 mod util;
 mod window;
 
 use application::MemoriesApplication;
 use config::{APP_ID, APP_NAME, GETTEXT_DOMAIN, LOCALEDIR, PKGDATADIR, VERSION};
-use gettextrs::{bind_textdomain_codeset, bindtextdomain, textdomain};
+use gettextrs::{bind_textdomain_codeset, bindtextdomain, gettext, textdomain};
 #[cfg(feature = "use-feedbackd")]
 use gtk::glib::g_error;
 use gtk::glib::{g_debug, g_info};
@@ -91,6 +102,8 @@ use std::env;
 use std::process::Command;
 
 fn main() -> glib::ExitCode {
+    util::profiling::init();
+
     if let Ok(v) = env::var("RUST_LOG") {
         if v.as_str() == "debug" {
             env::set_var("G_MESSAGES_DEBUG", "all");
@@ -152,6 +165,26 @@ fn main() -> glib::ExitCode {
         );
     }
 
+    // Save pending state and write a crash report if Memories panics.
+    util::crash::install_panic_hook();
+
+    // Prefer the hardware video decoders of the selected backend for playback.
+    let gsettings: gio::Settings = gio::Settings::new(APP_ID);
+
+    util::hwaccel::apply_playback_decoder_ranks(util::hwaccel::HardwareAccel::selected(
+        gsettings.boolean("ffmpeg-hardware-acceleration"),
+        &gsettings.string("ffmpeg-hardware-acceleration-backend"),
+    ));
+
     let app = MemoriesApplication::new(APP_ID, &gio::ApplicationFlags::empty());
+
+    app.add_main_option(
+        "index-only",
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::None,
+        &gettext("Index the library and generate thumbnails without a window, then quit"),
+        None,
+    );
     app.run()
 }
@@ -0,0 +1,453 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! On-device detection of faces and grouping of faces into people.
+//!
+//! Faces are found with the SeetaFace frontal face detector, and described
+//! with histograms of uniform local binary patterns (LBP), which are compared
+//! to group the faces of the same person. Nothing leaves the device.
+
+use crate::config::{APP_NAME, PKGDATADIR};
+use crate::globals::{
+    FACE_DETECTION_MAX_EDGE, FACE_DETECTION_MIN_FACE_SIZE, FACE_DETECTION_MODEL_FILE, FACE_MATCH_THRESHOLD,
+    FACE_THUMBNAIL_SIZE,
+};
+use glib::g_warning;
+use gtk::glib;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Edge length in pixels that faces are resampled to before describing them.
+const DESCRIPTOR_FACE_SIZE: usize = 64;
+/// Faces are split in a grid of this many cells per row and column, which
+/// are described by separate histograms, to keep the layout of the face.
+const DESCRIPTOR_GRID: usize = 4;
+/// Amount of uniform binary patterns of 8 bits, plus one bin for the rest.
+const UNIFORM_PATTERNS: usize = 59;
+
+/// A face found in a media file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Face {
+    pub uri: String,
+    /// Bounds of the face, as fractions of the width and height of the image.
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub descriptor: Vec<f32>,
+    pub person: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Person {
+    pub id: u32,
+    pub name: Option<String>,
+}
+
+/// Faces found in the library, and the people they are grouped into.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FaceIndex {
+    pub faces: Vec<Face>,
+    pub people: Vec<Person>,
+    /// URIs of the media files that were scanned, including those without faces.
+    pub scanned: HashSet<String>,
+}
+
+impl FaceIndex {
+    /// Loads the face index from disk, or returns an empty index if there is none.
+    pub fn load() -> Self {
+        let contents: Vec<u8> = match std::fs::read(faces_directory().join("index.json")) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => {
+                g_warning!("PeopleView", "Failed to read face index: {}", err);
+                return Self::default();
+            }
+        };
+        serde_json::from_slice(&contents).unwrap_or_else(|err| {
+            g_warning!("PeopleView", "Failed to parse face index: {}", err);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        std::fs::create_dir_all(faces_directory())?;
+        std::fs::write(faces_directory().join("index.json"), serde_json::to_vec(self)?)
+    }
+
+    pub fn faces_of<'a>(&'a self, uri: &'a str) -> impl Iterator<Item = &'a Face> + 'a {
+        self.faces.iter().filter(move |face: &&Face| face.uri == uri)
+    }
+
+    /// Returns the amount of media files that the person appears in.
+    pub fn media_count(&self, person: u32) -> usize {
+        self.faces
+            .iter()
+            .filter(|face: &&Face| face.person == person)
+            .map(|face: &Face| face.uri.as_str())
+            .collect::<HashSet<&str>>()
+            .len()
+    }
+
+    pub fn set_person_name(&mut self, id: u32, name: Option<String>) {
+        if let Some(person) = self
+            .people
+            .iter_mut()
+            .find(|person: &&mut Person| person.id == id)
+        {
+            person.name = name;
+        }
+    }
+
+    /// Adds a face to the person whose faces are the most similar on average,
+    /// or to a new person if no person is similar enough. Returns the id of
+    /// the person, and `true` if a new person was added for the face.
+    pub fn add_face(&mut self, mut face: Face) -> (u32, bool) {
+        let closest: Option<(u32, f32)> = self
+            .people
+            .iter()
+            .filter_map(|person: &Person| {
+                let centroid: Vec<f32> = self.centroid(person.id)?;
+                Some((person.id, descriptor_distance(&centroid, &face.descriptor)))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        let (id, is_new): (u32, bool) = match closest {
+            Some((id, distance)) if distance <= FACE_MATCH_THRESHOLD => (id, false),
+            _ => {
+                let id: u32 = self
+                    .people
+                    .iter()
+                    .map(|person: &Person| person.id + 1)
+                    .max()
+                    .unwrap_or(0);
+                self.people.push(Person { id, name: None });
+                (id, true)
+            }
+        };
+        face.person = id;
+        self.faces.push(face);
+        (id, is_new)
+    }
+
+    /// Returns the mean descriptor of the faces of a person.
+    fn centroid(&self, person: u32) -> Option<Vec<f32>> {
+        let mut centroid: Vec<f32> = vec![];
+        let mut count: usize = 0;
+
+        for face in self.faces.iter().filter(|face: &&Face| face.person == person) {
+            if centroid.is_empty() {
+                centroid = vec![0.0; face.descriptor.len()];
+            }
+            for (sum, value) in centroid.iter_mut().zip(face.descriptor.iter()) {
+                *sum += value;
+            }
+            count += 1;
+        }
+        if count == 0 {
+            return None;
+        }
+        centroid.iter_mut().for_each(|sum: &mut f32| *sum /= count as f32);
+        Some(centroid)
+    }
+}
+
+/// Returns the directory of the face index and the thumbnails of people.
+/// Unlike thumbnails, it is not kept in the cache, since it holds the names of people.
+pub fn faces_directory() -> PathBuf {
+    glib::user_data_dir().join(APP_NAME).join("faces")
+}
+
+/// Returns the path of the face thumbnail shown for a person.
+pub fn person_thumbnail_path(person: u32) -> PathBuf {
+    faces_directory().join(format!("{}.png", person))
+}
+
+/// Returns the chi-square distance between two face descriptors,
+/// which is 0 for identical faces and at most 2 for unrelated ones.
+pub fn descriptor_distance(a: &[f32], b: &[f32]) -> f32 {
+    let sum: f32 = a
+        .iter()
+        .zip(b.iter())
+        .filter(|(a, b)| **a + **b > 0.0)
+        .map(|(a, b)| (a - b).powi(2) / (a + b))
+        .sum();
+
+    sum / (DESCRIPTOR_GRID * DESCRIPTOR_GRID) as f32
+}
+
+/// Returns a table that maps 8 bit binary patterns to their histogram bin. Patterns
+/// with at most two bitwise transitions are uniform and have their own bin, and
+/// the other patterns share the last bin.
+fn uniform_pattern_bins() -> [usize; 256] {
+    let mut bins: [usize; 256] = [UNIFORM_PATTERNS - 1; 256];
+    let mut next_bin: usize = 0;
+
+    for (pattern, bin) in bins.iter_mut().enumerate() {
+        let pattern: u8 = pattern as u8;
+
+        if (pattern ^ pattern.rotate_left(1)).count_ones() <= 2 {
+            *bin = next_bin;
+            next_bin += 1;
+        }
+    }
+    bins
+}
+
+/// Returns the LBP descriptor of a face in a grayscale image, given its bounds in pixels.
+pub fn lbp_descriptor(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    bounds: (usize, usize, usize, usize),
+) -> Vec<f32> {
+    let (x, y, w, h) = bounds;
+    let size: usize = DESCRIPTOR_FACE_SIZE;
+
+    // Resample the face to a fixed size, so faces of any size can be compared.
+    let face: Vec<u8> = (0..size * size)
+        .map(|i: usize| {
+            let sx: usize = (x + (i % size) * w / size).min(width - 1);
+            let sy: usize = (y + (i / size) * h / size).min(height - 1);
+            gray[sy * width + sx]
+        })
+        .collect();
+
+    let bins: [usize; 256] = uniform_pattern_bins();
+    let cell_size: usize = size / DESCRIPTOR_GRID;
+    let mut descriptor: Vec<f32> = vec![0.0; DESCRIPTOR_GRID * DESCRIPTOR_GRID * UNIFORM_PATTERNS];
+
+    for py in 1..size - 1 {
+        for px in 1..size - 1 {
+            let center: u8 = face[py * size + px];
+            let neighbors: [(usize, usize); 8] = [
+                (px - 1, py - 1),
+                (px, py - 1),
+                (px + 1, py - 1),
+                (px + 1, py),
+                (px + 1, py + 1),
+                (px, py + 1),
+                (px - 1, py + 1),
+                (px - 1, py),
+            ];
+            let pattern: usize = neighbors
+                .iter()
+                .enumerate()
+                .filter(|(_, (nx, ny))| face[ny * size + nx] >= center)
+                .fold(0, |pattern: usize, (bit, _)| pattern | 1 << bit);
+
+            let cell: usize = (py / cell_size) * DESCRIPTOR_GRID + px / cell_size;
+            descriptor[cell * UNIFORM_PATTERNS + bins[pattern]] += 1.0;
+        }
+    }
+
+    // Normalize each cell, so that every cell has the same weight.
+    for cell in descriptor.chunks_mut(UNIFORM_PATTERNS) {
+        let total: f32 = cell.iter().sum();
+
+        if total > 0.0 {
+            cell.iter_mut().for_each(|value: &mut f32| *value /= total);
+        }
+    }
+    descriptor
+}
+
+/// Converts an RGBA image to grayscale, scaling it down so that
+/// its longest edge is at most `max_edge` pixels. Returns the
+/// grayscale pixels with the width and height of the result.
+pub fn grayscale(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    max_edge: usize,
+) -> (Vec<u8>, usize, usize) {
+    let scale: f64 = (width.max(height) as f64 / max_edge as f64).max(1.0);
+    let (gray_width, gray_height) = ((width as f64 / scale) as usize, (height as f64 / scale) as usize);
+
+    let gray: Vec<u8> = (0..gray_width * gray_height)
+        .map(|i: usize| {
+            let sx: usize = (((i % gray_width) as f64 * scale) as usize).min(width - 1);
+            let sy: usize = (((i / gray_width) as f64 * scale) as usize).min(height - 1);
+            let pixel: &[u8] = &rgba[sy * stride + sx * 4..sy * stride + sx * 4 + 3];
+
+            // ITU-R BT.601 luma
+            ((pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114) / 1000) as u8
+        })
+        .collect();
+
+    (gray, gray_width, gray_height)
+}
+
+/// Returns a square RGBA thumbnail of a region of an RGBA image, given as fractions of the image size.
+fn crop_thumbnail(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    region: (f32, f32, f32, f32),
+) -> Vec<u8> {
+    let size: usize = FACE_THUMBNAIL_SIZE as usize;
+    let (x, y, w, h) = region;
+    let mut thumbnail: Vec<u8> = Vec::with_capacity(size * size * 4);
+
+    for ty in 0..size {
+        for tx in 0..size {
+            let sx: usize = (((x + w * tx as f32 / size as f32) * width as f32) as usize).min(width - 1);
+            let sy: usize = (((y + h * ty as f32 / size as f32) * height as f32) as usize).min(height - 1);
+
+            thumbnail.extend_from_slice(&rgba[sy * stride + sx * 4..sy * stride + sx * 4 + 4]);
+        }
+    }
+    thumbnail
+}
+
+/// A face found by [`detect_faces`], before it is grouped into a person.
+#[derive(Debug)]
+pub struct DetectedFace {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub descriptor: Vec<f32>,
+    /// Square RGBA thumbnail of the face, `FACE_THUMBNAIL_SIZE` pixels wide.
+    pub thumbnail: Vec<u8>,
+}
+
+thread_local! {
+    /// Face detectors are not thread safe, so each thread loads its own.
+    static DETECTOR: RefCell<Option<Box<dyn rustface::Detector>>> = const { RefCell::new(None) };
+}
+
+/// Detects the faces in an RGBA image. This is CPU intensive,
+/// so it should be run outside of the main thread.
+pub fn detect_faces(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+) -> Result<Vec<DetectedFace>, String> {
+    let (gray, gray_width, gray_height) =
+        grayscale(rgba, width, height, stride, FACE_DETECTION_MAX_EDGE as usize);
+
+    DETECTOR.with_borrow_mut(|detector: &mut Option<Box<dyn rustface::Detector>>| {
+        if detector.is_none() {
+            let model_path: PathBuf = PathBuf::from(PKGDATADIR).join(FACE_DETECTION_MODEL_FILE);
+            let mut new_detector = rustface::create_detector(&model_path.to_string_lossy())
+                .map_err(|err| format!("Failed to load face detection model: {}", err))?;
+
+            new_detector.set_min_face_size(FACE_DETECTION_MIN_FACE_SIZE);
+            new_detector.set_score_thresh(2.0);
+            new_detector.set_pyramid_scale_factor(0.8);
+            new_detector.set_slide_window_step(4, 4);
+            *detector = Some(new_detector);
+        }
+        let detector: &mut Box<dyn rustface::Detector> = detector.as_mut().unwrap();
+        let mut image = rustface::ImageData::new(&gray, gray_width as u32, gray_height as u32);
+
+        Ok(detector
+            .detect(&mut image)
+            .iter()
+            .map(|face: &rustface::FaceInfo| {
+                let bbox = face.bbox();
+                let x: usize = bbox.x().max(0) as usize;
+                let y: usize = bbox.y().max(0) as usize;
+                let w: usize = (bbox.width() as usize).min(gray_width - x);
+                let h: usize = (bbox.height() as usize).min(gray_height - y);
+
+                let region: (f32, f32, f32, f32) = (
+                    x as f32 / gray_width as f32,
+                    y as f32 / gray_height as f32,
+                    w as f32 / gray_width as f32,
+                    h as f32 / gray_height as f32,
+                );
+                DetectedFace {
+                    x: region.0,
+                    y: region.1,
+                    width: region.2,
+                    height: region.3,
+                    descriptor: lbp_descriptor(&gray, gray_width, gray_height, (x, y, w, h)),
+                    thumbnail: crop_thumbnail(rgba, width, height, stride, region),
+                }
+            })
+            .collect())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn face(descriptor: Vec<f32>) -> Face {
+        Face {
+            uri: String::from("file:///a.jpg"),
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 1.0,
+            descriptor,
+            person: 0,
+        }
+    }
+
+    #[test]
+    fn uniform_patterns_have_own_bins() {
+        let bins: [usize; 256] = uniform_pattern_bins();
+
+        assert_eq!(bins[0b0000_0000], 0);
+        assert_eq!(bins[0b1111_1111], UNIFORM_PATTERNS - 2);
+        assert_eq!(bins[0b0101_0101], UNIFORM_PATTERNS - 1);
+        assert_eq!(
+            bins.iter().filter(|bin| **bin == UNIFORM_PATTERNS - 1).count(),
+            256 - 58
+        );
+    }
+
+    #[test]
+    fn similar_faces_are_grouped() {
+        let mut index: FaceIndex = FaceIndex::default();
+        let length: usize = DESCRIPTOR_GRID * DESCRIPTOR_GRID * UNIFORM_PATTERNS;
+
+        // Descriptors where every cell only has patterns of the first or second bin.
+        let first: Vec<f32> = (0..length)
+            .map(|i| (i % UNIFORM_PATTERNS == 0) as u8 as f32)
+            .collect();
+        let other: Vec<f32> = (0..length)
+            .map(|i| (i % UNIFORM_PATTERNS == 1) as u8 as f32)
+            .collect();
+
+        assert_eq!(index.add_face(face(first.clone())), (0, true));
+        assert_eq!(index.add_face(face(first)), (0, false));
+        assert_eq!(index.add_face(face(other)), (1, true));
+        assert_eq!(index.media_count(0), 1);
+    }
+
+    #[test]
+    fn descriptor_of_flat_image_is_uniform() {
+        let gray: Vec<u8> = vec![128; 100 * 100];
+        let descriptor: Vec<f32> = lbp_descriptor(&gray, 100, 100, (10, 10, 80, 80));
+
+        assert_eq!(descriptor_distance(&descriptor, &descriptor), 0.0);
+        // Every pixel equals its neighbors, so each cell only has the all-ones pattern.
+        assert_eq!(descriptor[UNIFORM_PATTERNS - 2], 1.0);
+    }
+}
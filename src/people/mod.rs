@@ -0,0 +1,374 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! People page, which groups the faces found in the library into people.
+//! Only built with the `use-face-detection` feature.
+
+pub mod faces;
+
+use crate::application::MemoriesApplication;
+use crate::globals::{FACE_THUMBNAIL_SIZE, TILED_RENDERING_MIN_PIXELS};
+use crate::i18n::ngettext_f;
+use crate::library::media_item::MemoriesMediaItem;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use faces::{DetectedFace, Face, FaceIndex};
+use gettextrs::gettext;
+use glib::{clone, g_debug, g_warning};
+#[cfg(feature = "disable-glycin-sandbox")]
+use glycin::SandboxMechanism;
+use gtk::{gdk, gio, glib};
+
+mod imp {
+    use super::faces::FaceIndex;
+    use adw::subclass::prelude::*;
+    use gtk::glib;
+    use std::cell::{Cell, RefCell};
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/people.ui")]
+    pub struct MemoriesPeopleView {
+        pub(super) index: RefCell<FaceIndex>,
+        pub(super) scanning: Cell<bool>,
+        #[template_child]
+        pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) scan_progress_bar: TemplateChild<gtk::ProgressBar>,
+        #[template_child]
+        pub(super) people_stack: TemplateChild<adw::ViewStack>,
+        #[template_child]
+        pub(super) people_flow_box: TemplateChild<gtk::FlowBox>,
+        #[template_child]
+        pub(super) placeholder_scan_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) scan_button: TemplateChild<gtk::Button>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesPeopleView {
+        const NAME: &'static str = "MemoriesPeopleView";
+        type Type = super::MemoriesPeopleView;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesPeopleView {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            self.index.replace(FaceIndex::load());
+            self.obj().update_people();
+        }
+    }
+
+    impl WidgetImpl for MemoriesPeopleView {}
+    impl BinImpl for MemoriesPeopleView {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesPeopleView(ObjectSubclass<imp::MemoriesPeopleView>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesPeopleView {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Rebuilds the grid of people, with the people that appear in the most media first.
+    fn update_people(&self) {
+        let index = self.imp().index.borrow();
+        let flow_box: &gtk::FlowBox = &self.imp().people_flow_box;
+        flow_box.remove_all();
+
+        let mut people: Vec<(u32, Option<String>, usize)> = index
+            .people
+            .iter()
+            .map(|person| (person.id, person.name.clone(), index.media_count(person.id)))
+            .filter(|(_, _, count)| *count > 0)
+            .collect();
+
+        people.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+
+        for (id, name, count) in &people {
+            let display_name: String = name.clone().unwrap_or_else(|| gettext("Unnamed"));
+
+            let avatar: adw::Avatar =
+                adw::Avatar::new(FACE_THUMBNAIL_SIZE as i32, name.as_deref(), name.is_some());
+
+            if let Ok(texture) = gdk::Texture::from_filename(faces::person_thumbnail_path(*id)) {
+                avatar.set_custom_image(Some(&texture));
+            }
+            let name_label: gtk::Label = gtk::Label::builder()
+                .label(&display_name)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .css_classes(["heading"])
+                .build();
+            let count_label: gtk::Label = gtk::Label::builder()
+                .label(ngettext_f(
+                    "{COUNT} item",
+                    "{COUNT} items",
+                    *count as u32,
+                    &[("COUNT", &count.to_string())],
+                ))
+                .css_classes(["dim-label", "caption"])
+                .build();
+
+            let tile: gtk::Box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(6)
+                .build();
+            tile.append(&avatar);
+            tile.append(&name_label);
+            tile.append(&count_label);
+
+            let child: gtk::FlowBoxChild = gtk::FlowBoxChild::builder().child(&tile).build();
+            child.set_widget_name(&id.to_string());
+            child.update_property(&[gtk::accessible::Property::Label(&display_name)]);
+            flow_box.append(&child);
+        }
+
+        self.imp()
+            .people_stack
+            .set_visible_child_name(if people.is_empty() {
+                "placeholder_page"
+            } else {
+                "people_page"
+            });
+    }
+
+    /// Asks for the name of the activated person.
+    #[template_callback]
+    fn person_activated(&self, child: &gtk::FlowBoxChild) {
+        let Ok(id) = child.widget_name().parse::<u32>() else {
+            return;
+        };
+        let name: Option<String> = self
+            .imp()
+            .index
+            .borrow()
+            .people
+            .iter()
+            .find(|person| person.id == id)
+            .and_then(|person| person.name.clone());
+
+        let entry: gtk::Entry = gtk::Entry::builder()
+            .text(name.unwrap_or_default())
+            .activates_default(true)
+            .build();
+
+        let dialog: adw::AlertDialog = adw::AlertDialog::builder()
+            .heading(gettext("Name Person"))
+            .body(gettext(
+                "Names are only used to label the faces found by Memories.",
+            ))
+            .extra_child(&entry)
+            .default_response("save")
+            .close_response("cancel")
+            .build();
+
+        dialog.add_responses(&[("cancel", &gettext("_Cancel")), ("save", &gettext("_Save"))]);
+        dialog.set_response_appearance("save", adw::ResponseAppearance::Suggested);
+
+        dialog.connect_response(
+            Some("save"),
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                entry,
+                move |_: &adw::AlertDialog, _: &str| {
+                    let name: String = entry.text().trim().to_string();
+                    let name: Option<String> = (!name.is_empty()).then_some(name);
+
+                    this.imp().index.borrow_mut().set_person_name(id, name);
+                    this.save_index();
+                    this.update_people();
+                }
+            ),
+        );
+        dialog.present(Some(self));
+    }
+
+    fn save_index(&self) {
+        if let Err(err) = self.imp().index.borrow().save() {
+            g_warning!("PeopleView", "Failed to save face index: {}", err);
+        }
+    }
+
+    #[template_callback]
+    fn scan_clicked(&self) {
+        if self.imp().scanning.get() {
+            return;
+        }
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                this.scan_library().await;
+            }
+        ));
+    }
+
+    /// Detects the faces in the images of the library that were not scanned yet.
+    async fn scan_library(&self) {
+        let library_model = MemoriesApplication::default().library_list_model();
+
+        let files: Vec<gio::File> = {
+            let index = self.imp().index.borrow();
+
+            (0..library_model.n_items())
+                .filter_map(|i: u32| library_model.item(i).and_downcast::<MemoriesMediaItem>())
+                .map(|item: MemoriesMediaItem| item.file())
+                .filter(|file: &gio::File| !is_video(file) && !index.scanned.contains(file.uri().as_str()))
+                .collect()
+        };
+
+        if files.is_empty() {
+            let toast: adw::Toast = adw::Toast::new(&gettext("No new photos to scan"));
+            self.imp().toast_overlay.add_toast(toast);
+            return;
+        }
+        g_debug!("PeopleView", "Scanning {} files for faces.", files.len());
+
+        self.imp().scanning.set(true);
+        self.imp().scan_button.set_sensitive(false);
+        self.imp().placeholder_scan_button.set_sensitive(false);
+        self.imp().scan_progress_bar.set_fraction(0.0);
+        self.imp().scan_progress_bar.set_visible(true);
+
+        for (i, file) in files.iter().enumerate() {
+            match detect_faces_in_file(file).await {
+                Ok(detected) => self.add_faces(file, detected),
+                Err(err) => g_warning!(
+                    "PeopleView",
+                    "Failed to detect faces in '{}': {}",
+                    file.uri(),
+                    err
+                ),
+            }
+            // Files that failed are not scanned again, since they are likely not supported.
+            self.imp()
+                .index
+                .borrow_mut()
+                .scanned
+                .insert(file.uri().to_string());
+
+            self.imp()
+                .scan_progress_bar
+                .set_fraction((i + 1) as f64 / files.len() as f64);
+        }
+        self.save_index();
+        self.update_people();
+
+        self.imp().scan_progress_bar.set_visible(false);
+        self.imp().scan_button.set_sensitive(true);
+        self.imp().placeholder_scan_button.set_sensitive(true);
+        self.imp().scanning.set(false);
+    }
+
+    /// Adds detected faces to the index, and saves the thumbnails of the new people.
+    fn add_faces(&self, file: &gio::File, detected: Vec<DetectedFace>) {
+        for detected_face in detected {
+            let (id, is_new) = self.imp().index.borrow_mut().add_face(Face {
+                uri: file.uri().to_string(),
+                x: detected_face.x,
+                y: detected_face.y,
+                width: detected_face.width,
+                height: detected_face.height,
+                descriptor: detected_face.descriptor,
+                person: 0,
+            });
+            if !is_new {
+                continue;
+            }
+            let size: usize = FACE_THUMBNAIL_SIZE as usize;
+            let texture: gdk::MemoryTexture = gdk::MemoryTexture::new(
+                size as i32,
+                size as i32,
+                gdk::MemoryFormat::R8g8b8a8,
+                &glib::Bytes::from_owned(detected_face.thumbnail),
+                size * 4,
+            );
+            let result = std::fs::create_dir_all(faces::faces_directory())
+                .map_err(|err| err.to_string())
+                .and_then(|_| {
+                    texture
+                        .save_to_png(faces::person_thumbnail_path(id))
+                        .map_err(|err| err.to_string())
+                });
+
+            if let Err(err) = result {
+                g_warning!("PeopleView", "Failed to save face thumbnail: {}", err);
+            }
+        }
+    }
+}
+
+impl Default for MemoriesPeopleView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_video(file: &gio::File) -> bool {
+    let extension: String = file
+        .path()
+        .and_then(|path| path.extension().map(|ext| ext.to_string_lossy().to_lowercase()))
+        .unwrap_or_default();
+
+    matches!(extension.as_str(), "mp4" | "webm" | "mkv" | "mov" | "avi" | "gif")
+}
+
+/// Decodes an image file and detects its faces in a background thread.
+async fn detect_faces_in_file(file: &gio::File) -> Result<Vec<DetectedFace>, String> {
+    #[allow(unused_mut)]
+    let mut glycin_loader: glycin::Loader = glycin::Loader::new(file.clone());
+
+    #[cfg(feature = "disable-glycin-sandbox")]
+    glycin_loader.sandbox_mechanism(Some(SandboxMechanism::NotSandboxed));
+
+    let image: glycin::Image<'static> = glycin_loader.load().await.map_err(|err| err.to_string())?;
+
+    // Decoding huge images at once takes too much memory for a background scan.
+    if image.info().width as u64 * image.info().height as u64 > TILED_RENDERING_MIN_PIXELS {
+        return Err(String::from("Image is too large to scan."));
+    }
+    let texture: gdk::Texture = image.next_frame().await.map_err(|err| err.to_string())?.texture();
+
+    let mut downloader: gdk::TextureDownloader = gdk::TextureDownloader::new(&texture);
+    downloader.set_format(gdk::MemoryFormat::R8g8b8a8);
+
+    let (bytes, stride) = downloader.download_bytes();
+    let (width, height) = (texture.width() as usize, texture.height() as usize);
+
+    gio::spawn_blocking(move || faces::detect_faces(&bytes, width, height, stride))
+        .await
+        .map_err(|_| String::from("Face detection thread panicked."))?
+}
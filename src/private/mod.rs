@@ -0,0 +1,258 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Private album page, which lists the media files that were moved to the
+//! Private album. Private media files are hidden from the library and search,
+//! and are only listed here after the album is unlocked with its passphrase.
+//! A passphrase is set up the first time that the page is opened.
+
+use crate::application::MemoriesApplication;
+use crate::globals::PRIVATE_THUMBNAIL_SIZE;
+use crate::library::justified_view::MemoriesJustifiedView;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::clone;
+use gtk::{gio, glib};
+
+mod imp {
+    use crate::application::MemoriesApplication;
+    use crate::util::media_database::MediaList;
+    use adw::prelude::*;
+    use adw::subclass::prelude::*;
+    use glib::clone;
+    use gtk::glib;
+    use std::cell::Cell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/private.ui")]
+    pub struct MemoriesPrivateView {
+        #[template_child]
+        pub(super) lock_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) private_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) new_passphrase_row: TemplateChild<adw::PasswordEntryRow>,
+        #[template_child]
+        pub(super) confirm_passphrase_row: TemplateChild<adw::PasswordEntryRow>,
+        #[template_child]
+        pub(super) set_passphrase_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) unlock_row: TemplateChild<adw::PasswordEntryRow>,
+        #[template_child]
+        pub(super) items_list: TemplateChild<gtk::ListBox>,
+        pub(super) unlocked: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesPrivateView {
+        const NAME: &'static str = "MemoriesPrivateView";
+        type Type = super::MemoriesPrivateView;
+        type ParentType = adw::NavigationPage;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesPrivateView {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+
+            MemoriesApplication::default().connect_metadata_changed(
+                Some(MediaList::Private.name()),
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_: &MemoriesApplication, _: &str| obj.update_list()
+                ),
+            );
+            obj.update_list();
+        }
+    }
+
+    impl WidgetImpl for MemoriesPrivateView {}
+
+    impl NavigationPageImpl for MemoriesPrivateView {
+        fn shown(&self) {
+            self.parent_shown();
+
+            let focus_row: &adw::PasswordEntryRow =
+                match MemoriesApplication::default().has_private_passphrase() {
+                    true => &self.unlock_row,
+                    false => &self.new_passphrase_row,
+                };
+            focus_row.grab_focus();
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct MemoriesPrivateView(ObjectSubclass<imp::MemoriesPrivateView>)
+        @extends gtk::Widget, adw::NavigationPage;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesPrivateView {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    fn private_files() -> Vec<gio::File> {
+        MemoriesApplication::default()
+            .private()
+            .iter()
+            .map(|uri: &glib::GStringPtr| gio::File::for_uri(uri.as_str()))
+            .collect()
+    }
+
+    /// Shows the page that the album is in, and lists the private
+    /// media files only while the album is unlocked.
+    fn update_list(&self) {
+        let imp = self.imp();
+        let items_list: &gtk::ListBox = &imp.items_list;
+        items_list.remove_all();
+
+        imp.lock_button.set_visible(imp.unlocked.get());
+
+        if !imp.unlocked.get() {
+            let locked_page: &str = match MemoriesApplication::default().has_private_passphrase() {
+                true => "locked",
+                false => "setup",
+            };
+            imp.private_stack.set_visible_child_name(locked_page);
+            return;
+        }
+        let files: Vec<gio::File> = Self::private_files();
+
+        // The most recently added files are listed first.
+        for file in files.iter().rev() {
+            let row: adw::ActionRow = adw::ActionRow::builder()
+                .title(glib::markup_escape_text(
+                    &file
+                        .basename()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                ))
+                .subtitle(glib::markup_escape_text(
+                    &file
+                        .parent()
+                        .map(|parent: gio::File| parent.parse_name().to_string())
+                        .unwrap_or_default(),
+                ))
+                .build();
+
+            let picture: gtk::Picture = gtk::Picture::builder()
+                .content_fit(gtk::ContentFit::Cover)
+                .width_request(PRIVATE_THUMBNAIL_SIZE)
+                .height_request(PRIVATE_THUMBNAIL_SIZE)
+                .valign(gtk::Align::Center)
+                .build();
+            // Previews are decoded in memory, so nothing is written to the thumbnail cache.
+            MemoriesJustifiedView::load_preview(&picture, file);
+
+            let remove_button: gtk::Button = gtk::Button::builder()
+                .icon_name("edit-undo-symbolic")
+                .tooltip_text(gettext("Move Back to Library"))
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+
+            remove_button.connect_clicked(clone!(
+                #[strong]
+                file,
+                move |_: &gtk::Button| {
+                    MemoriesApplication::default().set_private(&[file.clone()], false);
+                }
+            ));
+            row.add_prefix(&picture);
+            row.add_suffix(&remove_button);
+            items_list.append(&row);
+        }
+
+        imp.private_stack
+            .set_visible_child_name(if files.is_empty() { "empty" } else { "items" });
+    }
+
+    fn set_unlocked(&self, unlocked: bool) {
+        self.imp().unlocked.set(unlocked);
+        self.update_list();
+    }
+
+    #[template_callback]
+    fn new_passphrase_changed(&self) {
+        let imp = self.imp();
+        let new_passphrase: glib::GString = imp.new_passphrase_row.text();
+
+        imp.set_passphrase_button
+            .set_sensitive(!new_passphrase.is_empty() && new_passphrase == imp.confirm_passphrase_row.text());
+    }
+
+    #[template_callback]
+    fn set_passphrase_clicked(&self) {
+        let imp = self.imp();
+
+        if !imp.set_passphrase_button.is_sensitive() {
+            return;
+        }
+        if !MemoriesApplication::default().set_private_passphrase(&imp.new_passphrase_row.text()) {
+            return;
+        }
+
+        imp.new_passphrase_row.set_text("");
+        imp.confirm_passphrase_row.set_text("");
+        self.set_unlocked(true);
+    }
+
+    #[template_callback]
+    fn unlock_clicked(&self) {
+        let unlock_row: &adw::PasswordEntryRow = &self.imp().unlock_row;
+
+        if MemoriesApplication::default().verify_private_passphrase(&unlock_row.text()) {
+            unlock_row.remove_css_class("error");
+            unlock_row.set_text("");
+            self.set_unlocked(true);
+        } else {
+            unlock_row.add_css_class("error");
+            unlock_row.announce(
+                &gettext("Incorrect passphrase"),
+                gtk::AccessibleAnnouncementPriority::High,
+            );
+        }
+    }
+
+    #[template_callback]
+    fn lock_clicked(&self) {
+        self.set_unlocked(false);
+        self.imp().unlock_row.grab_focus();
+    }
+}
+
+impl Default for MemoriesPrivateView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
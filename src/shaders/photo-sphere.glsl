@@ -0,0 +1,29 @@
+// Projects an equirectangular photo onto the inside of a sphere, as seen
+// from its center while looking in the direction of `yaw` and `pitch`.
+
+uniform float yaw;
+uniform float pitch;
+uniform float fov;
+uniform sampler2D u_texture1;
+
+#define PI 3.14159265358979
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord, in vec2 resolution, in vec2 uv) {
+  float aspect = resolution.x / resolution.y;
+  float scale = tan(fov / 2.0);
+  vec2 ndc = vec2(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+  vec3 dir = normalize(vec3(ndc.x * aspect * scale, ndc.y * scale, -1.0));
+
+  float cp = cos(pitch);
+  float sp = sin(pitch);
+  dir = vec3(dir.x, dir.y * cp - dir.z * sp, dir.y * sp + dir.z * cp);
+
+  float cy = cos(yaw);
+  float sy = sin(yaw);
+  dir = vec3(dir.x * cy - dir.z * sy, dir.y, dir.x * sy + dir.z * cy);
+
+  float longitude = atan(dir.x, -dir.z);
+  float latitude = asin(clamp(dir.y, -1.0, 1.0));
+
+  fragColor = GskTexture(u_texture1, vec2(longitude / (2.0 * PI) + 0.5, 0.5 - latitude / PI));
+}
@@ -0,0 +1,386 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Statistics page, which summarizes the composition of the library: the amount
+//! of photos and videos, the media of each year, the cameras and folders with the
+//! most media, and the largest files. Each entry opens the media it counts.
+
+mod summary;
+
+use crate::application::MemoriesApplication;
+use crate::folders::MemoriesFolderPage;
+use crate::globals::{EXIF_DETAILS_READ_SIZE, STATISTICS_BAR_WIDTH, STATISTICS_RANKING_SIZE};
+use crate::i18n::ngettext_f;
+use crate::library::hidden_filter_func;
+use crate::library::media_item::MemoriesMediaItem;
+use crate::util::format::{self, SizeUnits};
+use crate::util::metadata;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::{clone, g_warning};
+use gtk::{gio, glib};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use summary::{LibrarySummary, MediaEntry};
+
+mod imp {
+    use adw::subclass::prelude::*;
+    use gtk::glib;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/statistics.ui")]
+    pub struct MemoriesStatisticsView {
+        #[template_child]
+        pub(super) statistics_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) photos_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub(super) videos_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub(super) size_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub(super) years_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) cameras_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) cameras_loading_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub(super) folders_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) largest_group: TemplateChild<adw::PreferencesGroup>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesStatisticsView {
+        const NAME: &'static str = "MemoriesStatisticsView";
+        type Type = super::MemoriesStatisticsView;
+        type ParentType = adw::NavigationPage;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesStatisticsView {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().update_statistics();
+        }
+    }
+
+    impl WidgetImpl for MemoriesStatisticsView {}
+    impl NavigationPageImpl for MemoriesStatisticsView {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesStatisticsView(ObjectSubclass<imp::MemoriesStatisticsView>)
+        @extends gtk::Widget, adw::NavigationPage;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesStatisticsView {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Returns the media of the library, without archived and private media,
+    /// which are hidden from the library and so are not counted either.
+    fn library_items() -> Vec<MemoriesMediaItem> {
+        let memories: MemoriesApplication = MemoriesApplication::default();
+        let not_archived = hidden_filter_func(&memories.archived());
+        let not_private = hidden_filter_func(&memories.private());
+
+        memories
+            .library_list_model()
+            .iter::<glib::Object>()
+            .filter_map(Result::ok)
+            .filter(|item: &glib::Object| not_archived(item) && not_private(item))
+            .filter_map(|item: glib::Object| item.downcast().ok())
+            .collect()
+    }
+
+    /// Returns the year of a media item, which is the year
+    /// of its modification time, like the library sorts by.
+    fn item_year(item: &MemoriesMediaItem) -> Option<i32> {
+        item.modified()
+            .and_then(|modified: glib::DateTime| modified.to_local().ok())
+            .map(|modified: glib::DateTime| modified.year())
+    }
+
+    fn update_statistics(&self) {
+        let items: Vec<MemoriesMediaItem> = Self::library_items();
+
+        if items.is_empty() {
+            self.imp().statistics_stack.set_visible_child_name("empty");
+            return;
+        }
+        self.imp().statistics_stack.set_visible_child_name("statistics");
+
+        let entries: Vec<MediaEntry> = items
+            .iter()
+            .map(|item: &MemoriesMediaItem| MediaEntry {
+                uri: item.uri(),
+                folder: item
+                    .file()
+                    .parent()
+                    .map(|folder: gio::File| folder.uri().to_string()),
+                year: Self::item_year(item),
+                size: item.size(),
+                is_video: item.is_video(),
+            })
+            .collect();
+
+        self.show_summary(&LibrarySummary::new(&entries, STATISTICS_RANKING_SIZE));
+        self.load_cameras(&items);
+    }
+
+    fn show_summary(&self, summary: &LibrarySummary) {
+        let imp = self.imp();
+
+        imp.photos_row.set_subtitle(&summary.photos.to_string());
+        imp.videos_row.set_subtitle(&summary.videos.to_string());
+        imp.size_row
+            .set_subtitle(&format::file_size(summary.total_size, SizeUnits::Decimal));
+
+        let most_in_year: u32 = summary.years.iter().map(|(_, count)| *count).max().unwrap_or(1);
+
+        for &(year, count) in &summary.years {
+            let row: adw::ActionRow = Self::chart_row(
+                &year.to_string(),
+                &Self::items_label(count),
+                f64::from(count) / f64::from(most_in_year),
+            );
+            if count > 0 {
+                self.link_row(&row, move || {
+                    MemoriesFolderPage::with_filter(&year.to_string(), move |item: &MemoriesMediaItem| {
+                        Self::item_year(item) == Some(year)
+                    })
+                });
+            }
+            imp.years_group.add(&row);
+        }
+
+        let most_in_folder: u32 = summary.folders.first().map_or(1, |(_, count)| *count);
+
+        for (uri, count) in &summary.folders {
+            let folder: gio::File = gio::File::for_uri(uri);
+            let row: adw::ActionRow = Self::chart_row(
+                &folder
+                    .basename()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| folder.parse_name().to_string()),
+                &Self::items_label(*count),
+                f64::from(*count) / f64::from(most_in_folder),
+            );
+            row.set_tooltip_text(Some(&folder.parse_name()));
+
+            self.link_row(&row, move || MemoriesFolderPage::new(&folder));
+            imp.folders_group.add(&row);
+        }
+
+        let largest_size: u64 = summary.largest.first().map_or(1, |(_, size)| (*size).max(1));
+        let largest_uris: Rc<HashSet<String>> =
+            Rc::new(summary.largest.iter().map(|(uri, _)| uri.clone()).collect());
+
+        for (uri, size) in &summary.largest {
+            let file: gio::File = gio::File::for_uri(uri);
+            let row: adw::ActionRow = Self::chart_row(
+                &file
+                    .basename()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                &format::file_size(*size, SizeUnits::Decimal),
+                *size as f64 / largest_size as f64,
+            );
+            row.set_tooltip_text(Some(&file.parse_name()));
+
+            let largest_uris: Rc<HashSet<String>> = largest_uris.clone();
+            self.link_row(&row, move || {
+                let largest_uris: Rc<HashSet<String>> = largest_uris.clone();
+
+                MemoriesFolderPage::with_filter(&gettext("Largest Files"), move |item: &MemoriesMediaItem| {
+                    largest_uris.contains(&item.uri())
+                })
+            });
+            imp.largest_group.add(&row);
+        }
+    }
+
+    /// Reads the camera of each photo on a separate thread, since it reads
+    /// the start of every photo file, and shows the cameras once done.
+    /// Reading stops early if the page is closed in the meantime.
+    fn load_cameras(&self, items: &[MemoriesMediaItem]) {
+        let photos: Vec<(String, PathBuf)> = items
+            .iter()
+            .filter(|item: &&MemoriesMediaItem| !item.is_video())
+            .filter_map(|item: &MemoriesMediaItem| Some((item.uri(), item.file().path()?)))
+            .collect();
+        let page: glib::WeakRef<Self> = self.downgrade();
+
+        glib::spawn_future_local(async move {
+            let mut cameras: HashMap<String, HashSet<String>> = HashMap::new();
+
+            for (uri, path) in photos {
+                if page.upgrade().is_none() {
+                    return;
+                }
+                match gio::spawn_blocking(move || Self::read_camera(&path)).await {
+                    Ok(Some(camera)) => {
+                        cameras.entry(camera).or_default().insert(uri);
+                    }
+                    Ok(None) => (),
+                    Err(_) => g_warning!("StatisticsView", "Camera reading thread panicked."),
+                }
+            }
+            if let Some(this) = page.upgrade() {
+                this.show_cameras(cameras);
+            }
+        });
+    }
+
+    /// Returns the camera that took a photo, from the EXIF data at the start of
+    /// its file.
+    fn read_camera(path: &Path) -> Option<String> {
+        let mut file_head: Vec<u8> = vec![];
+        std::fs::File::open(path)
+            .ok()?
+            .take(EXIF_DETAILS_READ_SIZE)
+            .read_to_end(&mut file_head)
+            .ok()?;
+
+        metadata::read_camera(&file_head)
+    }
+
+    fn show_cameras(&self, mut cameras: HashMap<String, HashSet<String>>) {
+        let imp = self.imp();
+        imp.cameras_group.remove(&imp.cameras_loading_row.get());
+
+        let counts: HashMap<String, u32> = cameras
+            .iter()
+            .map(|(camera, uris)| (camera.clone(), uris.len().try_into().unwrap_or(u32::MAX)))
+            .collect();
+        let ranking: Vec<(String, u32)> = summary::ranking(counts, STATISTICS_RANKING_SIZE);
+
+        if ranking.is_empty() {
+            imp.cameras_group
+                .set_description(Some(&gettext("None of the photos tell which camera took them")));
+            return;
+        }
+        let most_by_camera: u32 = ranking.first().map_or(1, |(_, count)| *count);
+
+        for (camera, count) in ranking {
+            let row: adw::ActionRow = Self::chart_row(
+                &camera,
+                &Self::items_label(count),
+                f64::from(count) / f64::from(most_by_camera),
+            );
+            let uris: Rc<HashSet<String>> = Rc::new(cameras.remove(&camera).unwrap_or_default());
+
+            self.link_row(&row, move || {
+                let uris: Rc<HashSet<String>> = uris.clone();
+                MemoriesFolderPage::with_filter(&camera, move |item: &MemoriesMediaItem| {
+                    uris.contains(&item.uri())
+                })
+            });
+            imp.cameras_group.add(&row);
+        }
+    }
+
+    /// Returns a row of a chart, with a bar that is filled by `fraction`.
+    fn chart_row(title: &str, subtitle: &str, fraction: f64) -> adw::ActionRow {
+        let row: adw::ActionRow = adw::ActionRow::builder()
+            .title(glib::markup_escape_text(title))
+            .subtitle(subtitle)
+            .build();
+
+        let bar: gtk::LevelBar = gtk::LevelBar::builder()
+            .value(fraction)
+            .width_request(STATISTICS_BAR_WIDTH)
+            .valign(gtk::Align::Center)
+            .build();
+        // The bars are of amounts, not of levels that are low or high.
+        bar.remove_offset_value(Some(gtk::LEVEL_BAR_OFFSET_LOW));
+        bar.remove_offset_value(Some(gtk::LEVEL_BAR_OFFSET_HIGH));
+        bar.remove_offset_value(Some(gtk::LEVEL_BAR_OFFSET_FULL));
+
+        row.add_suffix(&bar);
+        row
+    }
+
+    /// Makes a row open the page that `open_page` returns when it is activated.
+    fn link_row(&self, row: &adw::ActionRow, open_page: impl Fn() -> MemoriesFolderPage + 'static) {
+        row.set_activatable(true);
+        row.add_suffix(&gtk::Image::from_icon_name("go-next-symbolic"));
+
+        row.connect_activated(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_: &adw::ActionRow| this.push_page(&open_page())
+        ));
+    }
+
+    fn push_page(&self, page: &MemoriesFolderPage) {
+        if let Some(nav_view) = self
+            .ancestor(adw::NavigationView::static_type())
+            .and_downcast::<adw::NavigationView>()
+        {
+            nav_view.push(page);
+        }
+    }
+
+    fn items_label(count: u32) -> String {
+        ngettext_f(
+            "{COUNT} item",
+            "{COUNT} items",
+            count,
+            &[("COUNT", &count.to_string())],
+        )
+    }
+
+    #[template_callback]
+    fn photos_activated(&self) {
+        self.push_page(&MemoriesFolderPage::with_filter(
+            &gettext("Photos"),
+            |item: &MemoriesMediaItem| !item.is_video(),
+        ));
+    }
+
+    #[template_callback]
+    fn videos_activated(&self) {
+        self.push_page(&MemoriesFolderPage::with_filter(
+            &gettext("Videos"),
+            |item: &MemoriesMediaItem| item.is_video(),
+        ));
+    }
+}
+
+impl Default for MemoriesStatisticsView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
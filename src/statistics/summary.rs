@@ -0,0 +1,156 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Aggregation of the library media into the figures shown on the Statistics page.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A library media file, with what is needed of it to summarize the library.
+#[derive(Debug, Clone)]
+pub struct MediaEntry {
+    pub uri: String,
+    /// URI of the folder that contains the file.
+    pub folder: Option<String>,
+    /// Year that the file was last modified in, which the library sorts by.
+    pub year: Option<i32>,
+    pub size: u64,
+    pub is_video: bool,
+}
+
+/// Composition of the library, from a list of media entries.
+#[derive(Debug, Default, PartialEq)]
+pub struct LibrarySummary {
+    pub photos: u32,
+    pub videos: u32,
+    /// Total size of the media files in bytes.
+    pub total_size: u64,
+    /// Number of files of each year, from the newest year to the oldest,
+    /// including the years in between that have no files.
+    pub years: Vec<(i32, u32)>,
+    /// Folders with the most files, by folder URI.
+    pub folders: Vec<(String, u32)>,
+    /// Largest files, by file URI, from the largest.
+    pub largest: Vec<(String, u64)>,
+}
+
+impl LibrarySummary {
+    /// Summarizes the entries, with at most `ranking_size` folders and files.
+    pub fn new(entries: &[MediaEntry], ranking_size: usize) -> Self {
+        let mut summary: Self = Self::default();
+        let mut years: HashMap<i32, u32> = HashMap::new();
+        let mut folders: HashMap<String, u32> = HashMap::new();
+
+        for entry in entries {
+            if entry.is_video {
+                summary.videos += 1;
+            } else {
+                summary.photos += 1;
+            }
+            summary.total_size += entry.size;
+
+            if let Some(year) = entry.year {
+                *years.entry(year).or_default() += 1;
+            }
+            if let Some(folder) = &entry.folder {
+                *folders.entry(folder.clone()).or_default() += 1;
+            }
+        }
+        if let (Some(&oldest), Some(&newest)) = (years.keys().min(), years.keys().max()) {
+            summary.years = (oldest..=newest)
+                .rev()
+                .map(|year: i32| (year, years.get(&year).copied().unwrap_or_default()))
+                .collect();
+        }
+        summary.folders = ranking(folders, ranking_size);
+
+        let mut largest: Vec<&MediaEntry> = entries.iter().collect();
+        largest
+            .sort_by(|a: &&MediaEntry, b: &&MediaEntry| b.size.cmp(&a.size).then_with(|| a.uri.cmp(&b.uri)));
+        summary.largest = largest
+            .into_iter()
+            .take(ranking_size)
+            .map(|entry: &MediaEntry| (entry.uri.clone(), entry.size))
+            .collect();
+
+        summary
+    }
+}
+
+/// Returns at most `size` of the keys with the highest counts, from the highest
+/// count. Keys with equal counts are ordered by key, so the ranking is stable.
+pub fn ranking<K: Ord + Hash>(counts: HashMap<K, u32>, size: usize) -> Vec<(K, u32)> {
+    let mut ranking: Vec<(K, u32)> = counts.into_iter().collect();
+    ranking.sort_by(|(a_key, a_count), (b_key, b_count)| b_count.cmp(a_count).then_with(|| a_key.cmp(b_key)));
+    ranking.truncate(size);
+    ranking
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(uri: &str, year: i32, size: u64, is_video: bool) -> MediaEntry {
+        MediaEntry {
+            uri: uri.to_string(),
+            folder: uri.rsplit_once('/').map(|(folder, _)| folder.to_string()),
+            year: Some(year),
+            size,
+            is_video,
+        }
+    }
+
+    #[test]
+    fn summarizes_entries() {
+        let entries: Vec<MediaEntry> = vec![
+            entry("file:///a/1.jpg", 2020, 300, false),
+            entry("file:///a/2.jpg", 2022, 100, false),
+            entry("file:///b/3.mp4", 2022, 900, true),
+            entry("file:///a/4.jpg", 2023, 200, false),
+        ];
+        let summary: LibrarySummary = LibrarySummary::new(&entries, 2);
+
+        assert_eq!((summary.photos, summary.videos, summary.total_size), (3, 1, 1500));
+        assert_eq!(summary.years, vec![(2023, 1), (2022, 2), (2021, 0), (2020, 1)]);
+        assert_eq!(
+            summary.folders,
+            vec![("file:///a".to_string(), 3), ("file:///b".to_string(), 1)]
+        );
+        assert_eq!(
+            summary.largest,
+            vec![
+                ("file:///b/3.mp4".to_string(), 900),
+                ("file:///a/1.jpg".to_string(), 300)
+            ]
+        );
+        assert_eq!(LibrarySummary::new(&[], 2), LibrarySummary::default());
+    }
+
+    #[test]
+    fn rankings_are_stable() {
+        let counts: HashMap<&str, u32> =
+            HashMap::from([("Pixel 7", 2), ("Canon EOS R6", 5), ("Apple iPhone 12", 2)]);
+
+        assert_eq!(
+            ranking(counts, 2),
+            vec![("Canon EOS R6", 5), ("Apple iPhone 12", 2)]
+        );
+    }
+}
@@ -0,0 +1,331 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Sync provider that mirrors the library folders into a backup folder. Each
+//! library folder is copied into a folder of the same name in the backup folder.
+//! Only files that are new or changed since the last backup are copied, and
+//! each copy is checked against the original before it replaces the old copy.
+//! Files are never deleted from the backup folder. The backup folder must not
+//! be in a library folder, since every backup would then be copied into the next.
+
+use super::{SyncProvider, SyncReport};
+use md5::{Digest, Md5};
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs::{File, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Mirrors the library folders into a local folder.
+#[derive(Debug, Clone)]
+pub struct FolderBackup {
+    target: PathBuf,
+}
+
+impl FolderBackup {
+    pub fn new(target: PathBuf) -> Self {
+        Self { target }
+    }
+
+    /// Returns whether a folder can not be backed up into the backup folder,
+    /// which is the case if either folder is in the other, or they are the same.
+    pub fn overlaps(&self, folder: &Path) -> bool {
+        self.target.starts_with(folder) || folder.starts_with(&self.target)
+    }
+
+    /// Copies `source` to `destination`, through a hidden file next to it that
+    /// only replaces `destination` once its contents match the original.
+    fn copy_verified(source: &Path, destination: &Path, source_metadata: &Metadata) -> io::Result<()> {
+        let mut partial_name: OsString = OsString::from(".");
+        partial_name.push(destination.file_name().unwrap_or_default());
+        partial_name.push(".partial");
+        let partial: PathBuf = destination.with_file_name(partial_name);
+
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let result: io::Result<()> = (|| {
+            std::fs::copy(source, &partial)?;
+            File::options()
+                .write(true)
+                .open(&partial)?
+                .set_modified(source_metadata.modified()?)?;
+
+            if file_digest(source)? != file_digest(&partial)? {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "The copy does not match the original",
+                ));
+            }
+            std::fs::rename(&partial, destination)
+        })();
+
+        if result.is_err() {
+            let _ = std::fs::remove_file(&partial);
+        }
+        result
+    }
+}
+
+impl SyncProvider for FolderBackup {
+    fn destination_name(&self) -> String {
+        self.target
+            .file_name()
+            .unwrap_or(self.target.as_os_str())
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn sync(
+        &self,
+        folders: &[PathBuf],
+        cancelled: &AtomicBool,
+        progress: &dyn Fn(usize, usize),
+    ) -> SyncReport {
+        let mut report: SyncReport = SyncReport::default();
+        let mut files: Vec<(PathBuf, PathBuf)> = vec![];
+        let mut taken_names: HashSet<OsString> = HashSet::new();
+
+        for folder in folders {
+            if folder.starts_with(&self.target) {
+                report.failed.push((
+                    folder.clone(),
+                    "The library folder is in the backup folder".to_string(),
+                ));
+                continue;
+            }
+            let name: OsString = unique_folder_name(folder, &mut taken_names);
+            let mut folder_files: Vec<PathBuf> = vec![];
+
+            if let Err(err) = list_files(folder, &self.target, &mut folder_files) {
+                report.failed.push((folder.clone(), err.to_string()));
+                continue;
+            }
+            for file in folder_files {
+                let Ok(relative) = file.strip_prefix(folder) else {
+                    continue;
+                };
+                let destination: PathBuf = self.target.join(&name).join(relative);
+                files.push((file, destination));
+            }
+        }
+
+        for (index, (source, destination)) in files.iter().enumerate() {
+            if cancelled.load(Ordering::Relaxed) {
+                report.cancelled = true;
+                break;
+            }
+            progress(index, files.len());
+
+            let result: io::Result<bool> = source.metadata().and_then(|source_metadata: Metadata| {
+                if !needs_copy(&source_metadata, destination.metadata().ok().as_ref()) {
+                    return Ok(false);
+                }
+                Self::copy_verified(source, destination, &source_metadata).map(|()| true)
+            });
+            match result {
+                Ok(true) => report.copied += 1,
+                Ok(false) => report.up_to_date += 1,
+                Err(err) => report.failed.push((source.clone(), err.to_string())),
+            }
+        }
+        progress(files.len(), files.len());
+        report
+    }
+}
+
+/// Returns whether a file must be copied over its backup, which is
+/// the case if there is no backup yet, or if the file was changed.
+fn needs_copy(source: &Metadata, destination: Option<&Metadata>) -> bool {
+    let Some(destination) = destination else {
+        return true;
+    };
+    source.len() != destination.len() || source.modified().ok() != destination.modified().ok()
+}
+
+/// Returns the name of the folder in the backup folder that a library folder is
+/// copied into. Library folders of the same name are told apart with a number.
+fn unique_folder_name(folder: &Path, taken: &mut HashSet<OsString>) -> OsString {
+    let name: OsString = folder
+        .file_name()
+        .map(OsString::from)
+        .unwrap_or_else(|| OsString::from("Library"));
+    let mut unique_name: OsString = name.clone();
+    let mut number: u32 = 2;
+
+    while taken.contains(&unique_name) {
+        unique_name = name.clone();
+        unique_name.push(format!(" ({})", number));
+        number += 1;
+    }
+    taken.insert(unique_name.clone());
+    unique_name
+}
+
+/// Adds the files in `folder` and its subfolders to `files`. Hidden files and
+/// folders are skipped, and so is the backup folder `target` if it is in `folder`,
+/// so that earlier backups are not backed up. Symbolic links are not followed.
+fn list_files(folder: &Path, target: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in std::fs::read_dir(folder)? {
+        let entry: std::fs::DirEntry = entry?;
+
+        if entry.file_name().to_string_lossy().starts_with('.') || entry.path() == target {
+            continue;
+        }
+        let file_type: std::fs::FileType = entry.file_type()?;
+
+        if file_type.is_dir() {
+            list_files(&entry.path(), target, files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Returns the MD5 digest of the contents of a file.
+fn file_digest(path: &Path) -> io::Result<Vec<u8>> {
+    let mut hasher: Md5 = Md5::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+
+    fn create_file(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn sync(backup: &FolderBackup, folders: &[PathBuf]) -> SyncReport {
+        backup.sync(folders, &AtomicBool::new(false), &|_, _| {})
+    }
+
+    #[test]
+    fn unique_folder_names() {
+        let mut taken: HashSet<OsString> = HashSet::new();
+
+        assert_eq!(
+            unique_folder_name(Path::new("/home/me/Pictures"), &mut taken),
+            "Pictures"
+        );
+        assert_eq!(
+            unique_folder_name(Path::new("/media/Pictures"), &mut taken),
+            "Pictures (2)"
+        );
+        assert_eq!(unique_folder_name(Path::new("/"), &mut taken), "Library");
+    }
+
+    #[test]
+    fn backs_up_new_and_changed_files() {
+        let library = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let folder: PathBuf = library.path().join("Pictures");
+
+        create_file(&folder.join("a.jpg"), "a");
+        create_file(&folder.join("trip/b.mp4"), "b");
+        create_file(&folder.join(".thumbnails/c.png"), "c");
+
+        let backup: FolderBackup = FolderBackup::new(target.path().to_path_buf());
+        let report: SyncReport = sync(&backup, std::slice::from_ref(&folder));
+
+        assert_eq!((report.copied, report.up_to_date), (2, 0));
+        assert!(report.failed.is_empty());
+        assert_eq!(
+            fs::read_to_string(target.path().join("Pictures/trip/b.mp4")).unwrap(),
+            "b"
+        );
+        assert!(!target.path().join("Pictures/.thumbnails").exists());
+
+        // Only the changed file is copied again.
+        create_file(&folder.join("a.jpg"), "changed");
+        File::options()
+            .write(true)
+            .open(folder.join("a.jpg"))
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+
+        let report: SyncReport = sync(&backup, &[folder]);
+
+        assert_eq!((report.copied, report.up_to_date), (1, 1));
+        assert_eq!(
+            fs::read_to_string(target.path().join("Pictures/a.jpg")).unwrap(),
+            "changed"
+        );
+    }
+
+    #[test]
+    fn overlapping_folders() {
+        let backup: FolderBackup = FolderBackup::new(PathBuf::from("/home/me/Pictures/Backup"));
+
+        assert!(backup.overlaps(Path::new("/home/me/Pictures")));
+        assert!(backup.overlaps(Path::new("/home/me/Pictures/Backup")));
+        assert!(backup.overlaps(Path::new("/home/me/Pictures/Backup/Old")));
+        assert!(!backup.overlaps(Path::new("/home/me/Pictures/Backups")));
+        assert!(!backup.overlaps(Path::new("/home/me/Videos")));
+    }
+
+    #[test]
+    fn backup_folder_in_library_folder_is_skipped() {
+        let library = tempfile::tempdir().unwrap();
+        let folder: PathBuf = library.path().join("Pictures");
+        let target: PathBuf = folder.join("Backup");
+
+        create_file(&folder.join("a.jpg"), "a");
+
+        let backup: FolderBackup = FolderBackup::new(target.clone());
+
+        for _ in 0..2 {
+            let report: SyncReport = sync(&backup, std::slice::from_ref(&folder));
+            assert!(report.failed.is_empty());
+        }
+        assert!(target.join("Pictures/a.jpg").is_file());
+        assert!(!target.join("Pictures/Backup").exists());
+
+        // A library folder in the backup folder is not backed up into itself.
+        let report: SyncReport = sync(&FolderBackup::new(library.path().to_path_buf()), &[folder]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.copied, 0);
+    }
+
+    #[test]
+    fn cancelled_sync_stops() {
+        let library = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+
+        create_file(&library.path().join("a.jpg"), "a");
+
+        let backup: FolderBackup = FolderBackup::new(target.path().to_path_buf());
+        let report: SyncReport = backup.sync(
+            &[library.path().to_path_buf()],
+            &AtomicBool::new(true),
+            &|_, _| {},
+        );
+
+        assert!(report.cancelled);
+        assert_eq!(report.copied, 0);
+    }
+}
@@ -0,0 +1,65 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Sync providers, which copy the library elsewhere to keep a backup of it.
+//!
+//! Providers are compiled in with Cargo features, and implement [`SyncProvider`]
+//! to sync the chosen library folders. Syncs run on a separate thread, so
+//! providers use blocking I/O. The first provider, [`FolderBackup`], mirrors
+//! the folders into a local folder, such as on a removable drive.
+
+mod folder_backup;
+mod page;
+
+pub use folder_backup::FolderBackup;
+pub use page::MemoriesBackupPage;
+
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+
+/// Outcome of a sync of the library folders.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// Files that were copied, since they were new or changed.
+    pub copied: u32,
+    /// Files that were already up to date.
+    pub up_to_date: u32,
+    /// Files that failed to sync, with the reason why.
+    pub failed: Vec<(PathBuf, String)>,
+    /// Set if the sync was cancelled before every file was synced.
+    pub cancelled: bool,
+}
+
+/// A place that the library folders are synced to.
+pub trait SyncProvider: Send {
+    /// Returns the name of the place that the folders are synced to,
+    /// such as the name of a folder, to show it to the user.
+    fn destination_name(&self) -> String;
+
+    /// Syncs the files of the given folders, and returns how the files were synced.
+    /// Called on a separate thread. Stops early once `cancelled` is set, and calls
+    /// `progress` with the number of files synced and the number of files to sync.
+    fn sync(
+        &self,
+        folders: &[PathBuf],
+        cancelled: &AtomicBool,
+        progress: &dyn Fn(usize, usize),
+    ) -> SyncReport;
+}
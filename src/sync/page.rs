@@ -0,0 +1,379 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Preferences page that backs up the library with a sync provider, and shows
+//! the status of the last backup. The library folders to back up are chosen
+//! on the page, and the backup is only made when the user asks for it.
+
+use super::{FolderBackup, SyncProvider, SyncReport};
+use crate::application::MemoriesApplication;
+use crate::i18n::{gettext_f, ngettext_f};
+use crate::library::list_model::MemoriesLibraryListModel;
+use crate::util::format;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::{clone, g_debug, g_warning};
+use gtk::{gio, glib};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+mod imp {
+    use adw::subclass::prelude::*;
+    use gtk::glib;
+    use std::cell::RefCell;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/backup-page.ui")]
+    pub struct MemoriesBackupPage {
+        /// Set to stop the backup in progress. `None` while not backing up.
+        pub(super) cancelled: RefCell<Option<Arc<AtomicBool>>>,
+        #[template_child]
+        pub(super) target_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub(super) folders_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) status_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub(super) backup_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) progress_bar: TemplateChild<gtk::ProgressBar>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesBackupPage {
+        const NAME: &'static str = "MemoriesBackupPage";
+        type Type = super::MemoriesBackupPage;
+        type ParentType = adw::PreferencesPage;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesBackupPage {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+
+            obj.update_target_row();
+            obj.update_folder_rows();
+            obj.update_status_row();
+        }
+
+        fn dispose(&self) {
+            if let Some(cancelled) = self.cancelled.take() {
+                cancelled.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    impl WidgetImpl for MemoriesBackupPage {}
+    impl PreferencesPageImpl for MemoriesBackupPage {}
+}
+
+glib::wrapper! {
+    pub struct MemoriesBackupPage(ObjectSubclass<imp::MemoriesBackupPage>)
+        @extends gtk::Widget, adw::PreferencesPage;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesBackupPage {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Returns the sync provider that the library is backed up with,
+    /// or `None` if no backup folder was chosen.
+    fn provider() -> Option<FolderBackup> {
+        let target: glib::GString = MemoriesApplication::default().gsettings().string("backup-folder");
+
+        (!target.is_empty()).then(|| FolderBackup::new(PathBuf::from(target.as_str())))
+    }
+
+    #[template_callback]
+    fn choose_folder_clicked(&self) {
+        let file_dialog: gtk::FileDialog = gtk::FileDialog::builder()
+            .title(gettext("Choose Backup Folder"))
+            .modal(true)
+            .build();
+        let window: Option<gtk::Window> = self.root().and_downcast();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                // An error is returned if the user dismisses the file chooser.
+                let Ok(folder) = file_dialog.select_folder_future(window.as_ref()).await else {
+                    return;
+                };
+                let Some(path) = folder.path() else {
+                    g_warning!("BackupPage", "'{}' is not a local folder.", folder.uri());
+                    return;
+                };
+                // Backing up into a library folder would copy each backup into the next.
+                let backup: FolderBackup = FolderBackup::new(path.clone());
+                let sources: glib::StrV = MemoriesApplication::default().gsettings().strv("backup-sources");
+
+                if Self::library_folders()
+                    .into_iter()
+                    .chain(
+                        sources
+                            .iter()
+                            .map(|source: &glib::GStringPtr| PathBuf::from(source.as_str())),
+                    )
+                    .any(|folder: PathBuf| backup.overlaps(&folder))
+                {
+                    this.show_toast(&gettext(
+                        "The backup folder can not be in a library folder, or contain one",
+                    ));
+                    return;
+                }
+                let _ = MemoriesApplication::default()
+                    .gsettings()
+                    .set_string("backup-folder", &path.to_string_lossy());
+                this.update_target_row();
+            }
+        ));
+    }
+
+    #[template_callback]
+    fn backup_clicked(&self) {
+        match self.imp().cancelled.borrow().as_ref() {
+            Some(cancelled) => cancelled.store(true, Ordering::Relaxed),
+            None => {
+                glib::spawn_future_local(clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    async move {
+                        this.backup().await;
+                    }
+                ));
+            }
+        }
+    }
+
+    /// Backs up the chosen library folders on a separate thread, while
+    /// showing its progress. The preferences can not be closed meanwhile.
+    async fn backup(&self) {
+        let Some(provider) = Self::provider() else {
+            return;
+        };
+        let folders: Vec<PathBuf> = MemoriesApplication::default()
+            .gsettings()
+            .strv("backup-sources")
+            .iter()
+            .map(|folder: &glib::GStringPtr| PathBuf::from(folder.as_str()))
+            .collect();
+
+        let cancelled: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        self.imp().cancelled.replace(Some(cancelled.clone()));
+        self.set_backing_up(true);
+
+        let (sender, receiver) = async_channel::unbounded::<(usize, usize)>();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                while let Ok((done, total)) = receiver.recv().await {
+                    this.imp()
+                        .progress_bar
+                        .set_fraction(done as f64 / total.max(1) as f64);
+                }
+            }
+        ));
+
+        let report: SyncReport = gio::spawn_blocking(move || {
+            provider.sync(&folders, &cancelled, &|done: usize, total: usize| {
+                let _ = sender.send_blocking((done, total));
+            })
+        })
+        .await
+        .unwrap_or_else(|_| SyncReport {
+            failed: vec![(PathBuf::new(), "The backup thread panicked".to_string())],
+            ..SyncReport::default()
+        });
+
+        g_debug!(
+            "BackupPage",
+            "Copied {} items, and {} items were up to date.",
+            report.copied,
+            report.up_to_date
+        );
+        for (path, err) in &report.failed {
+            g_warning!("BackupPage", "Failed to back up '{}': {}", path.display(), err);
+        }
+        if !report.cancelled && report.failed.is_empty() {
+            if let Ok(now) = glib::DateTime::now_utc() {
+                let _ = MemoriesApplication::default()
+                    .gsettings()
+                    .set_int64("backup-last-sync", now.to_unix());
+            }
+        }
+        self.imp().cancelled.take();
+        self.set_backing_up(false);
+        self.update_status_row();
+        self.show_report(&report);
+    }
+
+    fn set_backing_up(&self, backing_up: bool) {
+        let button: &gtk::Button = &self.imp().backup_button;
+
+        button.set_label(&match backing_up {
+            true => gettext("_Cancel"),
+            false => gettext("_Back Up Now"),
+        });
+        if backing_up {
+            button.remove_css_class("suggested-action");
+        } else {
+            button.add_css_class("suggested-action");
+        }
+        self.imp().progress_bar.set_fraction(0.0);
+        self.imp().progress_bar.set_visible(backing_up);
+        self.imp().target_row.set_sensitive(!backing_up);
+        self.imp().folders_group.set_sensitive(!backing_up);
+
+        if let Some(dialog) = self.ancestor(adw::PreferencesDialog::static_type()) {
+            dialog
+                .downcast::<adw::PreferencesDialog>()
+                .unwrap()
+                .set_can_close(!backing_up);
+        }
+    }
+
+    /// Tells the user how the backup went, in a toast of the preferences dialog.
+    fn show_report(&self, report: &SyncReport) {
+        let message: String = if report.cancelled {
+            gettext("Backup canceled")
+        } else if !report.failed.is_empty() {
+            let count: u32 = report.failed.len().try_into().unwrap_or(u32::MAX);
+            ngettext_f(
+                "{COUNT} item could not be backed up",
+                "{COUNT} items could not be backed up",
+                count,
+                &[("COUNT", &count.to_string())],
+            )
+        } else {
+            ngettext_f(
+                "{COUNT} item was backed up",
+                "{COUNT} items were backed up",
+                report.copied,
+                &[("COUNT", &report.copied.to_string())],
+            )
+        };
+        self.show_toast(&message);
+    }
+
+    /// Shows a message in a toast of the preferences dialog.
+    fn show_toast(&self, message: &str) {
+        if let Some(dialog) = self.ancestor(adw::PreferencesDialog::static_type()) {
+            dialog
+                .downcast::<adw::PreferencesDialog>()
+                .unwrap()
+                .add_toast(adw::Toast::new(message));
+        }
+    }
+
+    fn update_target_row(&self) {
+        let subtitle: String = match Self::provider() {
+            Some(provider) => provider.destination_name(),
+            None => gettext("No folder chosen"),
+        };
+        self.imp().target_row.set_subtitle(&subtitle);
+        self.imp().backup_button.set_sensitive(Self::provider().is_some());
+    }
+
+    /// Returns the paths of the local library folders.
+    fn library_folders() -> Vec<PathBuf> {
+        MemoriesLibraryListModel::configured_folders()
+            .iter()
+            .filter_map(|folder: &glib::GStringPtr| gio::File::for_commandline_arg(folder.as_str()).path())
+            .collect()
+    }
+
+    /// Lists the local library folders, with a switch to back up each of them.
+    fn update_folder_rows(&self) {
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+
+        for path in Self::library_folders() {
+            let path: String = path.to_string_lossy().to_string();
+            let selected: bool = gsettings
+                .strv("backup-sources")
+                .iter()
+                .any(|source: &glib::GStringPtr| source.as_str() == path);
+
+            let row: adw::SwitchRow = adw::SwitchRow::builder()
+                .title(&path)
+                .use_markup(false)
+                .active(selected)
+                .build();
+
+            row.connect_active_notify(clone!(
+                #[strong]
+                gsettings,
+                move |row: &adw::SwitchRow| {
+                    let mut sources: Vec<String> = gsettings
+                        .strv("backup-sources")
+                        .iter()
+                        .map(|source: &glib::GStringPtr| source.to_string())
+                        .filter(|source: &String| *source != path)
+                        .collect();
+
+                    if row.is_active() {
+                        sources.push(path.clone());
+                    }
+                    let _ = gsettings.set_strv("backup-sources", sources);
+                }
+            ));
+            self.imp().folders_group.add(&row);
+        }
+    }
+
+    /// Shows when the library was last backed up completely.
+    fn update_status_row(&self) {
+        let last_sync: i64 = MemoriesApplication::default()
+            .gsettings()
+            .int64("backup-last-sync");
+
+        let subtitle: String = glib::DateTime::from_unix_local(last_sync)
+            .ok()
+            .filter(|_| last_sync > 0)
+            .and_then(|date_time: glib::DateTime| format::date_time(&date_time))
+            .map(|date: String| gettext_f("Last backed up on {DATE}", &[("DATE", &date)]))
+            .unwrap_or_else(|| gettext("Never backed up"));
+
+        self.imp().status_row.set_subtitle(&subtitle);
+    }
+}
+
+impl Default for MemoriesBackupPage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,471 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Recently Deleted page, which lists the media files that were moved
+//! to the trash from Memories, so that they can be restored or deleted.
+
+use crate::application::MemoriesApplication;
+use crate::i18n::{gettext_f, ngettext_f};
+use crate::util::feedback::{self, FeedbackEvent};
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use glib::{clone, g_warning};
+use gtk::{gio, glib};
+use std::collections::HashMap;
+
+mod imp {
+    use super::TrashEntry;
+    use adw::subclass::prelude::*;
+    use glib::clone;
+    use gtk::glib;
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/maxrdz/Memories/ui/trash.ui")]
+    pub struct MemoriesTrashView {
+        pub(super) entries: RefCell<Vec<TrashEntry>>,
+        #[template_child]
+        pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub(super) delete_all_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) trash_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) spinner: TemplateChild<gtk::Spinner>,
+        #[template_child]
+        pub(super) items_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) items_list: TemplateChild<gtk::ListBox>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoriesTrashView {
+        const NAME: &'static str = "MemoriesTrashView";
+        type Type = super::MemoriesTrashView;
+        type ParentType = adw::NavigationPage;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MemoriesTrashView {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+
+            glib::spawn_future_local(clone!(
+                #[weak]
+                obj,
+                async move {
+                    obj.refresh().await;
+                }
+            ));
+        }
+    }
+
+    impl WidgetImpl for MemoriesTrashView {}
+    impl NavigationPageImpl for MemoriesTrashView {}
+}
+
+/// A media file in the trash that was moved there by Memories.
+#[derive(Debug, Clone)]
+pub struct TrashEntry {
+    /// The file in the `trash:///` location.
+    trash_file: gio::File,
+    /// The location that the file was moved to the trash from.
+    original_file: gio::File,
+    display_name: String,
+    icon: Option<gio::Icon>,
+    /// Time the file was moved to the trash, in seconds since the Unix epoch.
+    deleted: i64,
+}
+
+/// Returns the amount of whole days left until a file in the trash is deleted,
+/// given the time it was moved to the trash and the current time, in seconds
+/// since the Unix epoch, and the amount of days files are kept in the trash.
+pub fn days_remaining(deleted: i64, now: i64, max_age_days: u32) -> u32 {
+    let days_elapsed: i64 = (now - deleted).max(0) / (24 * 60 * 60);
+
+    (max_age_days as i64 - days_elapsed).max(0) as u32
+}
+
+/// Returns the amount of days that files are kept in the trash, as configured
+/// in the GNOME privacy settings, or `None` if they are kept until the trash
+/// is emptied, or if the settings are not available.
+fn trash_max_age_days() -> Option<u32> {
+    let schema_id: &str = "org.gnome.desktop.privacy";
+
+    gio::SettingsSchemaSource::default()?.lookup(schema_id, true)?;
+
+    let settings: gio::Settings = gio::Settings::new(schema_id);
+    settings
+        .boolean("remove-old-trash-files")
+        .then(|| settings.uint("old-files-age"))
+}
+
+glib::wrapper! {
+    pub struct MemoriesTrashView(ObjectSubclass<imp::MemoriesTrashView>)
+        @extends gtk::Widget, adw::NavigationPage;
+}
+
+#[gtk::template_callbacks]
+impl MemoriesTrashView {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Lists the files in the trash that were moved there by Memories. Files
+    /// that are no longer in the trash, such as if the trash was emptied by
+    /// another application, are forgotten.
+    async fn refresh(&self) {
+        self.imp().trash_stack.set_visible_child_name("loading");
+        self.imp().spinner.start();
+
+        let app: MemoriesApplication = MemoriesApplication::default();
+        let trashed_files: HashMap<String, i64> = app.trashed_files();
+
+        let entries: Vec<TrashEntry> = match Self::enumerate_trash(&trashed_files).await {
+            Ok(entries) => entries,
+            Err(err) => {
+                g_warning!("TrashView", "Failed to enumerate trash: {}", err);
+                vec![]
+            }
+        };
+
+        let stale: Vec<String> = trashed_files
+            .keys()
+            .filter(|uri| {
+                !entries
+                    .iter()
+                    .any(|entry: &TrashEntry| entry.original_file.uri().as_str() == uri.as_str())
+            })
+            .cloned()
+            .collect();
+
+        if !stale.is_empty() {
+            app.forget_trashed_files(&stale);
+        }
+        self.imp().entries.replace(entries);
+        self.imp().spinner.stop();
+        self.update_list();
+    }
+
+    async fn enumerate_trash(trashed_files: &HashMap<String, i64>) -> Result<Vec<TrashEntry>, glib::Error> {
+        let trash: gio::File = gio::File::for_uri("trash:///");
+        let attributes: String = [
+            gio::FILE_ATTRIBUTE_STANDARD_NAME,
+            gio::FILE_ATTRIBUTE_STANDARD_DISPLAY_NAME,
+            gio::FILE_ATTRIBUTE_STANDARD_ICON,
+            gio::FILE_ATTRIBUTE_TRASH_ORIG_PATH,
+        ]
+        .map(glib::GStr::as_str)
+        .join(",");
+
+        let enumerator: gio::FileEnumerator = trash
+            .enumerate_children_future(
+                &attributes,
+                gio::FileQueryInfoFlags::NONE,
+                glib::Priority::DEFAULT,
+            )
+            .await?;
+
+        let mut entries: Vec<TrashEntry> = vec![];
+
+        loop {
+            let file_infos: Vec<gio::FileInfo> =
+                enumerator.next_files_future(64, glib::Priority::DEFAULT).await?;
+
+            if file_infos.is_empty() {
+                break;
+            }
+            for file_info in file_infos {
+                let Some(original_path) =
+                    file_info.attribute_byte_string(gio::FILE_ATTRIBUTE_TRASH_ORIG_PATH)
+                else {
+                    continue;
+                };
+                let original_file: gio::File = gio::File::for_path(original_path.as_str());
+
+                if let Some(deleted) = trashed_files.get(original_file.uri().as_str()) {
+                    entries.push(TrashEntry {
+                        trash_file: trash.child(file_info.name()),
+                        original_file,
+                        display_name: file_info.display_name().to_string(),
+                        icon: file_info.icon(),
+                        deleted: *deleted,
+                    });
+                }
+            }
+        }
+        // The most recently deleted files are listed first.
+        entries.sort_by(|a: &TrashEntry, b: &TrashEntry| b.deleted.cmp(&a.deleted));
+        Ok(entries)
+    }
+
+    fn update_list(&self) {
+        let entries = self.imp().entries.borrow();
+        let items_list: &gtk::ListBox = &self.imp().items_list;
+        items_list.remove_all();
+
+        let max_age_days: Option<u32> = trash_max_age_days();
+        let now: i64 = glib::DateTime::now_utc().map(|now| now.to_unix()).unwrap_or(0);
+
+        self.imp().items_group.set_description(Some(&match max_age_days {
+            Some(days) => ngettext_f(
+                "Items are permanently deleted after {DAYS} day",
+                "Items are permanently deleted after {DAYS} days",
+                days,
+                &[("DAYS", &days.to_string())],
+            ),
+            None => gettext("Items are kept until the trash is emptied"),
+        }));
+
+        for (i, entry) in entries.iter().enumerate() {
+            let subtitle: String = match max_age_days {
+                Some(max_age_days) => {
+                    let days: u32 = days_remaining(entry.deleted, now, max_age_days);
+
+                    if days == 0 {
+                        gettext("Deleted today")
+                    } else {
+                        ngettext_f(
+                            "{DAYS} day remaining",
+                            "{DAYS} days remaining",
+                            days,
+                            &[("DAYS", &days.to_string())],
+                        )
+                    }
+                }
+                None => {
+                    let days: u32 = ((now - entry.deleted).max(0) / (24 * 60 * 60)) as u32;
+
+                    ngettext_f(
+                        "Moved to trash {DAYS} day ago",
+                        "Moved to trash {DAYS} days ago",
+                        days,
+                        &[("DAYS", &days.to_string())],
+                    )
+                }
+            };
+            let row: adw::ActionRow = adw::ActionRow::builder()
+                .title(glib::markup_escape_text(&entry.display_name))
+                .subtitle(subtitle)
+                .build();
+
+            if let Some(icon) = &entry.icon {
+                row.add_prefix(&gtk::Image::from_gicon(icon));
+            }
+            let restore_button: gtk::Button = gtk::Button::builder()
+                .icon_name("edit-undo-symbolic")
+                .tooltip_text(gettext("Restore"))
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+            let delete_button: gtk::Button = gtk::Button::builder()
+                .icon_name("user-trash-symbolic")
+                .tooltip_text(gettext("Delete Permanently"))
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+
+            restore_button.connect_clicked(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gtk::Button| {
+                    this.restore(i);
+                }
+            ));
+            delete_button.connect_clicked(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &gtk::Button| {
+                    this.delete(vec![i]);
+                }
+            ));
+            row.add_suffix(&restore_button);
+            row.add_suffix(&delete_button);
+            items_list.append(&row);
+        }
+
+        self.imp().delete_all_button.set_sensitive(!entries.is_empty());
+        self.imp()
+            .trash_stack
+            .set_visible_child_name(if entries.is_empty() { "empty" } else { "items" });
+    }
+
+    /// Moves an entry back to its original location.
+    fn restore(&self, index: usize) {
+        let Some(entry) = self.imp().entries.borrow().get(index).cloned() else {
+            return;
+        };
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let trash_file: gio::File = entry.trash_file.clone();
+                let original_file: gio::File = entry.original_file.clone();
+
+                let result: Result<(), glib::Error> = gio::spawn_blocking(move || {
+                    // The original folder may have been removed after the file was trashed.
+                    if let Some(parent) = original_file.parent() {
+                        if let Err(err) = parent.make_directory_with_parents(gio::Cancellable::NONE) {
+                            if !err.matches(gio::IOErrorEnum::Exists) {
+                                return Err(err);
+                            }
+                        }
+                    }
+                    trash_file.move_(
+                        &original_file,
+                        gio::FileCopyFlags::NONE,
+                        gio::Cancellable::NONE,
+                        None,
+                    )
+                })
+                .await
+                .unwrap_or_else(|_| {
+                    Err(glib::Error::new(
+                        gio::IOErrorEnum::Failed,
+                        "Restore thread panicked.",
+                    ))
+                });
+
+                match result {
+                    Ok(()) => {
+                        MemoriesApplication::default()
+                            .forget_trashed_files(&[entry.original_file.uri().to_string()]);
+                    }
+                    Err(err) => {
+                        g_warning!("TrashView", "Failed to restore '{}': {}", entry.display_name, err);
+
+                        this.imp().toast_overlay.add_toast(adw::Toast::new(&gettext_f(
+                            "Failed to restore {NAME}",
+                            &[("NAME", &entry.display_name)],
+                        )));
+                    }
+                }
+                this.refresh().await;
+            }
+        ));
+    }
+
+    /// Permanently deletes the entries at the given indices.
+    fn delete(&self, indices: Vec<usize>) {
+        let entries: Vec<TrashEntry> = indices
+            .iter()
+            .filter_map(|i: &usize| self.imp().entries.borrow().get(*i).cloned())
+            .collect();
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let mut deleted: Vec<String> = vec![];
+                let mut failed: u32 = 0;
+
+                for entry in &entries {
+                    match entry.trash_file.delete_future(glib::Priority::DEFAULT).await {
+                        Ok(()) => deleted.push(entry.original_file.uri().to_string()),
+                        Err(err) => {
+                            g_warning!("TrashView", "Failed to delete '{}': {}", entry.display_name, err);
+                            failed += 1;
+                        }
+                    }
+                }
+                MemoriesApplication::default().forget_trashed_files(&deleted);
+
+                if !deleted.is_empty() {
+                    feedback::emit(FeedbackEvent::DeleteConfirmed);
+                }
+
+                if failed > 0 {
+                    this.imp().toast_overlay.add_toast(adw::Toast::new(&ngettext_f(
+                        "Failed to delete {COUNT} item",
+                        "Failed to delete {COUNT} items",
+                        failed,
+                        &[("COUNT", &failed.to_string())],
+                    )));
+                }
+                this.refresh().await;
+            }
+        ));
+    }
+
+    #[template_callback]
+    fn delete_all_clicked(&self) {
+        let count: usize = self.imp().entries.borrow().len();
+
+        let dialog: adw::AlertDialog = adw::AlertDialog::builder()
+            .heading(gettext("Delete All Items Permanently?"))
+            .body(ngettext_f(
+                "{COUNT} item will be permanently deleted. This cannot be undone.",
+                "{COUNT} items will be permanently deleted. This cannot be undone.",
+                count.try_into().unwrap_or(u32::MAX),
+                &[("COUNT", &count.to_string())],
+            ))
+            .default_response("cancel")
+            .close_response("cancel")
+            .build();
+
+        dialog.add_responses(&[("cancel", &gettext("_Cancel")), ("delete", &gettext("_Delete"))]);
+        dialog.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+
+        dialog.connect_response(
+            Some("delete"),
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_: &adw::AlertDialog, _: &str| {
+                    this.delete((0..count).collect());
+                }
+            ),
+        );
+        dialog.present(Some(self));
+    }
+}
+
+impl Default for MemoriesTrashView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_remaining_in_trash() {
+        let day: i64 = 24 * 60 * 60;
+
+        assert_eq!(days_remaining(0, 0, 30), 30);
+        assert_eq!(days_remaining(0, day - 1, 30), 30);
+        assert_eq!(days_remaining(0, 3 * day, 30), 27);
+        assert_eq!(days_remaining(0, 45 * day, 30), 0);
+        // Clock changes should not add days.
+        assert_eq!(days_remaining(day, 0, 30), 30);
+    }
+}
@@ -0,0 +1,112 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Asks the background portal for permission to keep running after the
+//! window is closed, so that the desktop does not stop Memories while
+//! it runs in the background.
+
+use crate::globals::{
+    BACKGROUND_PORTAL_INTERFACE, DESKTOP_PORTAL_BUS_NAME, DESKTOP_PORTAL_OBJECT_PATH,
+    PORTAL_REQUEST_INTERFACE, PORTAL_REQUEST_OBJECT_PATH,
+};
+use gtk::prelude::*;
+use gtk::{gio, glib};
+
+/// Requests to run in the background, with `reason` shown to the user if they
+/// are asked. Returns `true` if running in the background was allowed.
+pub async fn request_background(reason: &str) -> Result<bool, glib::Error> {
+    let connection: gio::DBusConnection = gio::bus_get_future(gio::BusType::Session).await?;
+
+    let token: String = format!("memories{}", glib::random_int());
+    let handle: String = request_handle(connection.unique_name().as_deref().unwrap_or_default(), &token);
+
+    // Subscribed to before the request is made, so that a quick response is not missed.
+    let (sender, receiver) = async_channel::bounded::<glib::Variant>(1);
+    let subscription_id: gio::SignalSubscriptionId = connection.signal_subscribe(
+        Some(DESKTOP_PORTAL_BUS_NAME),
+        Some(PORTAL_REQUEST_INTERFACE),
+        Some("Response"),
+        Some(&handle),
+        None,
+        gio::DBusSignalFlags::NONE,
+        move |_, _, _, _, _, parameters: &glib::Variant| {
+            let _ = sender.try_send(parameters.clone());
+        },
+    );
+
+    let options: glib::VariantDict = glib::VariantDict::new(None);
+    options.insert("handle_token", &token);
+    options.insert("reason", reason);
+    options.insert("autostart", false);
+    options.insert("dbus-activatable", true);
+
+    // The parent window is left empty, since the window may be closed by now.
+    let parameters: glib::Variant = glib::Variant::tuple_from_iter(["".to_variant(), options.end()]);
+
+    let request = connection
+        .call_future(
+            Some(DESKTOP_PORTAL_BUS_NAME),
+            DESKTOP_PORTAL_OBJECT_PATH,
+            BACKGROUND_PORTAL_INTERFACE,
+            "RequestBackground",
+            Some(&parameters),
+            Some(glib::VariantTy::new("(o)").unwrap()),
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await;
+
+    let response: Option<glib::Variant> = match request {
+        Ok(_) => receiver.recv().await.ok(),
+        Err(err) => {
+            connection.signal_unsubscribe(subscription_id);
+            return Err(err);
+        }
+    };
+    connection.signal_unsubscribe(subscription_id);
+
+    // A response code of 0 means that the request succeeded, and its
+    // results tell whether running in the background is allowed.
+    Ok(response
+        .and_then(|response: glib::Variant| response.get::<(u32, glib::VariantDict)>())
+        .is_some_and(|(code, results): (u32, glib::VariantDict)| {
+            code == 0 && results.lookup::<bool>("background").ok().flatten() == Some(true)
+        }))
+}
+
+/// Returns the object path of the portal request made with `token`,
+/// from the unique name of the D-Bus connection that makes it.
+fn request_handle(unique_name: &str, token: &str) -> String {
+    let sender: String = unique_name.trim_start_matches(':').replace('.', "_");
+    format!("{}/{}/{}", PORTAL_REQUEST_OBJECT_PATH, sender, token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_handle_from_unique_name() {
+        assert_eq!(
+            request_handle(":1.234", "memories42"),
+            "/org/freedesktop/portal/desktop/request/1_234/memories42"
+        );
+    }
+}
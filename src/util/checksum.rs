@@ -0,0 +1,237 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Checksums of the contents of media files, which are recorded once and
+//! verified later to find files that were corrupted on disk, such as by bit
+//! rot or a failing drive, before the corruption is copied into backups.
+//! Files that were modified since, such as by an editor, are told apart from
+//! corrupted files by their modification time, which corruption leaves as is.
+//! Recording and verifying checksums read whole files, so both are done on
+//! a separate thread, such as with `gio::spawn_blocking`.
+
+use crate::config::APP_NAME;
+use glib::g_warning;
+use gtk::glib;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Checksum of the contents of a file, along with the size and
+/// modification time of the file when its checksum was recorded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChecksum {
+    pub checksum: String,
+    pub size: u64,
+    /// Modification time of the file, in seconds since the Unix epoch.
+    pub modified: u64,
+}
+
+/// Result of verifying a file against its recorded checksum.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    /// The contents of the file match its checksum.
+    Intact,
+    /// The file was modified since its checksum was recorded, with its new checksum.
+    Modified(FileChecksum),
+    /// The contents of the file changed without it being modified, or it can not be read.
+    Corrupted,
+    /// The file no longer exists.
+    Missing,
+}
+
+/// Checksums of the media in the library by file URI. Unlike the other indexes,
+/// it is not kept in the cache, since it can not be recorded again once files
+/// are corrupted.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ChecksumIndex {
+    checksums: HashMap<String, FileChecksum>,
+}
+
+impl ChecksumIndex {
+    /// Loads the checksum index from disk, or returns an empty index if there is none.
+    pub fn load() -> Self {
+        let contents: Vec<u8> = match std::fs::read(checksum_index_path()) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => {
+                g_warning!("Checksum", "Failed to read checksum index: {}", err);
+                return Self::default();
+            }
+        };
+        serde_json::from_slice(&contents).unwrap_or_else(|err| {
+            g_warning!("Checksum", "Failed to parse checksum index: {}", err);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path: PathBuf = checksum_index_path();
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.checksums.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checksums.is_empty()
+    }
+
+    pub fn contains(&self, uri: &str) -> bool {
+        self.checksums.contains_key(uri)
+    }
+
+    pub fn insert(&mut self, uri: String, checksum: FileChecksum) {
+        self.checksums.insert(uri, checksum);
+    }
+
+    /// Removes the checksum of a file, so that the file is no longer verified.
+    pub fn remove(&mut self, uri: &str) {
+        self.checksums.remove(uri);
+    }
+
+    /// Returns the recorded checksums by file URI.
+    pub fn checksums(&self) -> Vec<(String, FileChecksum)> {
+        self.checksums
+            .iter()
+            .map(|(uri, checksum): (&String, &FileChecksum)| (uri.clone(), checksum.clone()))
+            .collect()
+    }
+}
+
+fn checksum_index_path() -> PathBuf {
+    glib::user_data_dir().join(APP_NAME).join("checksums.json")
+}
+
+/// Returns the checksum of the file at `path`, which is read in whole.
+pub fn record(path: &Path) -> io::Result<FileChecksum> {
+    let metadata: Metadata = std::fs::metadata(path)?;
+
+    Ok(FileChecksum {
+        checksum: file_checksum(path)?,
+        size: metadata.len(),
+        modified: modified_seconds(&metadata)?,
+    })
+}
+
+/// Verifies the file at `path` against its recorded checksum. Files that were
+/// modified since are read again, so that their new checksum can be recorded.
+pub fn verify(path: &Path, recorded: &FileChecksum) -> Verification {
+    let metadata: Metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Verification::Missing,
+        Err(_) => return Verification::Corrupted,
+    };
+    let Ok(modified) = modified_seconds(&metadata) else {
+        return Verification::Corrupted;
+    };
+    let Ok(checksum) = file_checksum(path) else {
+        return Verification::Corrupted;
+    };
+    let current: FileChecksum = FileChecksum {
+        checksum,
+        size: metadata.len(),
+        modified,
+    };
+
+    if current.modified != recorded.modified {
+        Verification::Modified(current)
+    } else if current == *recorded {
+        Verification::Intact
+    } else {
+        Verification::Corrupted
+    }
+}
+
+fn file_checksum(path: &Path) -> io::Result<String> {
+    let mut md5_hasher: Md5 = Md5::new();
+    io::copy(&mut File::open(path)?, &mut md5_hasher)?;
+
+    Ok(format!("{:x}", md5_hasher.finalize()))
+}
+
+fn modified_seconds(metadata: &Metadata) -> io::Result<u64> {
+    Ok(metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+
+    fn set_modified(path: &Path, modified: SystemTime) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn recorded_checksums() {
+        let dir = tempfile::tempdir().unwrap();
+        let path: PathBuf = dir.path().join("photo.jpg");
+        fs::write(&path, "abc").unwrap();
+
+        let recorded: FileChecksum = record(&path).unwrap();
+
+        assert_eq!(recorded.checksum, "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(recorded.size, 3);
+        assert_eq!(verify(&path, &recorded), Verification::Intact);
+    }
+
+    #[test]
+    fn corrupted_files_keep_their_modification_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let path: PathBuf = dir.path().join("photo.jpg");
+        let modified: SystemTime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        fs::write(&path, "abc").unwrap();
+        set_modified(&path, modified);
+        let recorded: FileChecksum = record(&path).unwrap();
+
+        fs::write(&path, "abd").unwrap();
+        set_modified(&path, modified);
+        assert_eq!(verify(&path, &recorded), Verification::Corrupted);
+
+        set_modified(&path, modified + Duration::from_secs(60));
+        assert!(matches!(
+            verify(&path, &recorded),
+            Verification::Modified(FileChecksum { size: 3, .. })
+        ));
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(verify(&path, &recorded), Verification::Missing);
+    }
+}
@@ -0,0 +1,144 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Content hashes of media files, which identify media by its contents rather
+//! than its location, so that media on a connected phone or camera can be told
+//! apart from media that was already imported into the library under any name.
+//! Only the size and the start of a file are hashed, since reading whole files
+//! over MTP or PTP is slow, and these are enough to tell photos and videos apart.
+
+use crate::application::MemoriesApplication;
+use crate::globals::CACHE_CONTENT_HASH_INDEX_FILE;
+use glib::g_warning;
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Amount of bytes at the start of a file that are hashed.
+const HASHED_HEAD_SIZE: usize = 64 * 1024;
+
+/// Content hashes of the media in the library by file URI.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ContentHashIndex {
+    hashes: HashMap<String, String>,
+}
+
+impl ContentHashIndex {
+    /// Loads the content hash index from disk, or returns an empty index if there is none.
+    pub fn load() -> Self {
+        let contents: Vec<u8> = match std::fs::read(content_hash_index_path()) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => {
+                g_warning!("ContentHash", "Failed to read content hash index: {}", err);
+                return Self::default();
+            }
+        };
+        serde_json::from_slice(&contents).unwrap_or_else(|err| {
+            g_warning!("ContentHash", "Failed to parse content hash index: {}", err);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path: PathBuf = content_hash_index_path();
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    pub fn contains(&self, uri: &str) -> bool {
+        self.hashes.contains_key(uri)
+    }
+
+    pub fn insert(&mut self, uri: String, hash: String) {
+        self.hashes.insert(uri, hash);
+    }
+
+    /// Removes the hash of a media file, such as after its contents changed.
+    pub fn remove(&mut self, uri: &str) {
+        self.hashes.remove(uri);
+    }
+
+    /// Returns the content hashes of every media file in the index.
+    pub fn hashes(&self) -> HashSet<String> {
+        self.hashes.values().cloned().collect()
+    }
+}
+
+fn content_hash_index_path() -> PathBuf {
+    Path::new(&MemoriesApplication::get_app_cache_directory()).join(CACHE_CONTENT_HASH_INDEX_FILE)
+}
+
+/// Returns the content hash of a file of `size` bytes, which is read from the
+/// start of the file. Works with files on any GVFS location, such as `mtp://`.
+pub async fn file_content_hash(file: &gio::File, size: u64) -> Result<String, glib::Error> {
+    let stream: gio::FileInputStream = file.read_future(glib::Priority::LOW).await?;
+
+    let (head, read, _) = stream
+        .read_all_future(vec![0; HASHED_HEAD_SIZE], glib::Priority::LOW)
+        .await
+        .map_err(|(_, err)| err)?;
+
+    if let Err(err) = stream.close_future(glib::Priority::LOW).await {
+        g_warning!("ContentHash", "Failed to close '{}': {}", file.uri(), err);
+    }
+    Ok(content_hash(size, &head[..read]))
+}
+
+/// Returns the MD5 digest, in hexadecimal format, of the size of a file and the
+/// bytes at its start, which are at most `HASHED_HEAD_SIZE` bytes.
+fn content_hash(size: u64, head: &[u8]) -> String {
+    let mut md5_hasher: Md5 = Md5::new();
+    md5_hasher.update(size.to_le_bytes());
+    md5_hasher.update(&head[..head.len().min(HASHED_HEAD_SIZE)]);
+
+    format!("{:x}", md5_hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_hashes() {
+        let head: Vec<u8> = (0..=255).collect();
+
+        assert_eq!(content_hash(256, &head), content_hash(256, &head));
+        // Files that start alike are told apart by their size.
+        assert_ne!(content_hash(256, &head), content_hash(512, &head));
+        assert_ne!(content_hash(256, &head), content_hash(256, &head[1..]));
+        assert_eq!(content_hash(256, &head).len(), 32);
+    }
+
+    #[test]
+    fn only_the_head_is_hashed() {
+        let mut contents: Vec<u8> = vec![7; HASHED_HEAD_SIZE + 16];
+        let hash: String = content_hash(contents.len() as u64, &contents);
+
+        contents[HASHED_HEAD_SIZE + 1] = 0;
+        assert_eq!(content_hash(contents.len() as u64, &contents), hash);
+    }
+}
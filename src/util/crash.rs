@@ -0,0 +1,190 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Crash handling. A panic hook saves pending state and writes a crash report
+//! to the app cache, which is shown on the next launch. Failures that Memories
+//! can recover from are reported with a dialog instead of aborting.
+
+use crate::application::MemoriesApplication;
+use crate::config::{APP_NAME, VCS_TAG, VERSION};
+use crate::globals::CACHE_CRASH_REPORT_FILE;
+use adw::prelude::*;
+use gettextrs::gettext;
+use glib::g_critical;
+use gtk::{gio, glib};
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::panic;
+use std::path::PathBuf;
+
+thread_local! {
+    /// Callbacks that save state kept in memory, run if the thread panics.
+    static STATE_SAVERS: RefCell<Vec<Box<dyn Fn()>>> = RefCell::default();
+}
+
+/// Installs a panic hook that saves pending state and writes a crash report,
+/// before running the default hook, which prints the panic message.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        // Settings are written in the background, so write any pending changes now.
+        gio::Settings::sync();
+
+        STATE_SAVERS.with(|savers| {
+            if let Ok(savers) = savers.try_borrow() {
+                savers.iter().for_each(|save| save());
+            }
+        });
+
+        let message: &str = info
+            .payload()
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Unknown panic payload");
+        let location: String = info.location().map(ToString::to_string).unwrap_or_default();
+
+        let report: String = diagnostics(&panic_details(message, &location));
+        let _ = std::fs::write(crash_report_path(), report);
+
+        default_hook(info);
+    }));
+}
+
+/// Registers a callback that saves state kept in memory if the current
+/// thread panics, such as data that is only written to disk periodically.
+pub fn save_state_on_panic<F: Fn() + 'static>(save: F) {
+    STATE_SAVERS.with(|savers| savers.borrow_mut().push(Box::new(save)));
+}
+
+/// Returns the crash report written the last time Memories crashed,
+/// if any, and removes it so that it is only shown once.
+pub fn take_crash_report() -> Option<String> {
+    let path: PathBuf = crash_report_path();
+    let report: String = std::fs::read_to_string(&path).ok()?;
+
+    let _ = std::fs::remove_file(&path);
+    Some(report)
+}
+
+/// Logs an unexpected failure that Memories can recover from, and shows
+/// a dialog that offers to copy diagnostics to report the issue.
+pub fn report_error(domain: &str, message: &str) {
+    g_critical!(domain, "{}", message);
+
+    show_error_dialog(
+        &gettext(
+            "An unexpected error occurred. You can help fix it by reporting it with the diagnostics below.",
+        ),
+        &diagnostics(&format!("{}: {}", domain, message)),
+    );
+}
+
+/// Shows the crash report from the last launch, if Memories crashed.
+pub fn show_crash_report() {
+    if let Some(report) = take_crash_report() {
+        show_error_dialog(
+            &gettext("Memories closed unexpectedly. You can help fix it by reporting it with the diagnostics below."),
+            &report,
+        );
+    }
+}
+
+fn show_error_dialog(body: &str, diagnostics: &str) {
+    let Some(window) = MemoriesApplication::default().active_window() else {
+        return;
+    };
+    let dialog: adw::AlertDialog = adw::AlertDialog::builder()
+        .heading(gettext("Something Went Wrong"))
+        .body(body)
+        .extra_child(
+            &gtk::ScrolledWindow::builder()
+                .min_content_height(120)
+                .child(
+                    &gtk::Label::builder()
+                        .label(diagnostics)
+                        .selectable(true)
+                        .wrap(true)
+                        .xalign(0.0)
+                        .css_classes(["monospace", "caption"])
+                        .build(),
+                )
+                .build(),
+        )
+        .build();
+
+    dialog.add_responses(&[
+        ("close", &gettext("_Close")),
+        ("copy", &gettext("C_opy Diagnostics")),
+    ]);
+    dialog.set_response_appearance("copy", adw::ResponseAppearance::Suggested);
+
+    let diagnostics: String = diagnostics.to_owned();
+
+    dialog.connect_response(Some("copy"), move |dialog: &adw::AlertDialog, _: &str| {
+        dialog.clipboard().set_text(&diagnostics);
+    });
+    dialog.present(Some(&window));
+}
+
+/// Returns the message of a panic, where it happened, and a backtrace.
+fn panic_details(message: &str, location: &str) -> String {
+    let thread = std::thread::current();
+
+    format!(
+        "Thread '{}' panicked at {}: {}\n\n{}",
+        thread.name().unwrap_or("unnamed"),
+        location,
+        message,
+        Backtrace::force_capture()
+    )
+}
+
+/// Returns details about the build and system to include in issue reports.
+fn diagnostics(details: &str) -> String {
+    format!(
+        "{} {} ({})\nGTK {}.{}.{}, libadwaita {}.{}.{}\nFlatpak: {}\n\n{}",
+        APP_NAME,
+        VERSION,
+        VCS_TAG,
+        gtk::major_version(),
+        gtk::minor_version(),
+        gtk::micro_version(),
+        adw::major_version(),
+        adw::minor_version(),
+        adw::micro_version(),
+        std::env::var_os("FLATPAK_ID").is_some(),
+        details
+    )
+}
+
+/// Returns the path of the crash report, in the same directory as
+/// [`MemoriesApplication::get_app_cache_directory`]. It is resolved without
+/// helpers that can fail, since the panic hook must not panic itself.
+fn crash_report_path() -> PathBuf {
+    let cache_dir: PathBuf = glib::user_cache_dir();
+
+    if std::env::var_os("FLATPAK_ID").is_some() {
+        cache_dir.join(APP_NAME).join(CACHE_CRASH_REPORT_FILE)
+    } else {
+        cache_dir.join(CACHE_CRASH_REPORT_FILE)
+    }
+}
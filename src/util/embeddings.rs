@@ -0,0 +1,354 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! On-device image embeddings, which let the library be searched with
+//! natural language, such as "beach sunset" or "dog".
+//!
+//! Media thumbnails and search queries are embedded into the same vector
+//! space by the image and text encoders of a CLIP model, run with ONNX
+//! Runtime. Media whose embedding is similar enough to the embedding of
+//! the query match it, and are ranked by their similarity. Embeddings are
+//! kept in an index in the app cache directory. Nothing leaves the device.
+
+use crate::application::MemoriesApplication;
+use crate::config::PKGDATADIR;
+use crate::globals::{
+    CACHE_EMBEDDING_INDEX_FILE, EMBEDDING_IMAGE_MODEL_FILE, EMBEDDING_IMAGE_SIZE, EMBEDDING_TEXT_LENGTH,
+    EMBEDDING_TEXT_MODEL_FILE, EMBEDDING_TOKENIZER_FILE,
+};
+use glib::g_warning;
+use gtk::gdk_pixbuf::{InterpType, Pixbuf};
+use gtk::glib;
+use gtk::prelude::*;
+use ort::session::Session;
+use ort::value::Tensor;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokenizers::Tokenizer;
+
+/// Per channel mean and standard deviation of the images the model
+/// was trained on, which input pixel values are normalized with.
+const PIXEL_MEAN: [f32; 3] = [0.481_454_66, 0.457_827_5, 0.408_210_73];
+const PIXEL_STD: [f32; 3] = [0.268_629_54, 0.261_302_58, 0.275_777_1];
+
+/// Names of the inputs and outputs of the ONNX models.
+const IMAGE_MODEL_INPUT: &str = "pixel_values";
+const IMAGE_MODEL_OUTPUT: &str = "image_embeds";
+const TEXT_MODEL_INPUT_IDS: &str = "input_ids";
+const TEXT_MODEL_ATTENTION_MASK: &str = "attention_mask";
+const TEXT_MODEL_OUTPUT: &str = "text_embeds";
+
+/// Embeddings are unit vectors, so their components are quantized from
+/// the range of -1.0 to 1.0 to bytes, to keep the index small.
+const QUANTIZATION_SCALE: f32 = 127.0;
+
+/// Embeddings of the media in the library by file URI.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EmbeddingIndex {
+    embeddings: HashMap<String, Vec<i8>>,
+}
+
+impl EmbeddingIndex {
+    /// Loads the embedding index from disk, or returns an empty index if there is none.
+    pub fn load() -> Self {
+        let contents: Vec<u8> = match std::fs::read(embedding_index_path()) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => {
+                g_warning!("Embeddings", "Failed to read embedding index: {}", err);
+                return Self::default();
+            }
+        };
+        serde_json::from_slice(&contents).unwrap_or_else(|err| {
+            g_warning!("Embeddings", "Failed to parse embedding index: {}", err);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path: PathBuf = embedding_index_path();
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    pub fn contains(&self, uri: &str) -> bool {
+        self.embeddings.contains_key(uri)
+    }
+
+    pub fn insert(&mut self, uri: String, embedding: &[f32]) {
+        self.embeddings.insert(uri, quantize(embedding));
+    }
+
+    /// Returns the similarity of every media in the index to the embedding of a query,
+    /// by file URI, keeping only media that are at least `threshold` similar.
+    pub fn similarities(&self, query: &[f32], threshold: f32) -> HashMap<String, f32> {
+        self.embeddings
+            .iter()
+            .map(|(uri, embedding): (&String, &Vec<i8>)| (uri, similarity(embedding, query)))
+            .filter(|(_, similarity): &(&String, f32)| *similarity >= threshold)
+            .map(|(uri, similarity): (&String, f32)| (uri.clone(), similarity))
+            .collect()
+    }
+}
+
+fn embedding_index_path() -> PathBuf {
+    Path::new(&MemoriesApplication::get_app_cache_directory()).join(CACHE_EMBEDDING_INDEX_FILE)
+}
+
+/// Returns the cosine similarity of a quantized embedding and a unit vector.
+fn similarity(quantized: &[i8], embedding: &[f32]) -> f32 {
+    quantized
+        .iter()
+        .zip(embedding)
+        .map(|(a, b): (&i8, &f32)| *a as f32 / QUANTIZATION_SCALE * b)
+        .sum()
+}
+
+fn quantize(embedding: &[f32]) -> Vec<i8> {
+    embedding
+        .iter()
+        .map(|x: &f32| (x.clamp(-1.0, 1.0) * QUANTIZATION_SCALE).round() as i8)
+        .collect()
+}
+
+/// Scales a vector to a length of 1, so that the dot product
+/// of two embeddings is their cosine similarity.
+fn normalize(vector: &mut [f32]) {
+    let length: f32 = vector.iter().map(|x: &f32| x * x).sum::<f32>().sqrt();
+
+    if length > 0.0 {
+        vector.iter_mut().for_each(|x: &mut f32| *x /= length);
+    }
+}
+
+/// Returns the normalized pixel values of a square RGB(A) image in
+/// channel, row, column order, as the image model takes them.
+fn pixel_values(pixels: &[u8], size: usize, rowstride: usize, n_channels: usize) -> Vec<f32> {
+    let mut values: Vec<f32> = vec![0.0; 3 * size * size];
+
+    for y in 0..size {
+        for x in 0..size {
+            let pixel: usize = y * rowstride + x * n_channels;
+
+            for channel in 0..3 {
+                let value: f32 = pixels[pixel + channel] as f32 / 255.0;
+                values[channel * size * size + y * size + x] =
+                    (value - PIXEL_MEAN[channel]) / PIXEL_STD[channel];
+            }
+        }
+    }
+    values
+}
+
+/// Returns the token IDs of a query, and the attention mask of the tokens,
+/// padded or truncated to the amount of tokens that the text model takes.
+fn text_inputs(ids: &[u32], length: usize) -> (Vec<i64>, Vec<i64>) {
+    let count: usize = ids.len().min(length);
+    let mut input_ids: Vec<i64> = ids[..count].iter().map(|id: &u32| *id as i64).collect();
+    let mut attention_mask: Vec<i64> = vec![1; count];
+
+    input_ids.resize(length, 0);
+    attention_mask.resize(length, 0);
+    (input_ids, attention_mask)
+}
+
+struct TextEncoder {
+    session: Session,
+    tokenizer: Tokenizer,
+}
+
+thread_local! {
+    /// Models are loaded once per thread that runs them, when first used.
+    static IMAGE_ENCODER: RefCell<Option<Session>> = const { RefCell::new(None) };
+    static TEXT_ENCODER: RefCell<Option<TextEncoder>> = const { RefCell::new(None) };
+}
+
+fn load_session(file_name: &str) -> Result<Session, String> {
+    let model_path: PathBuf = PathBuf::from(PKGDATADIR).join(file_name);
+
+    Session::builder()
+        .and_then(|builder| builder.commit_from_file(&model_path))
+        .map_err(|err| {
+            format!(
+                "Failed to load embedding model '{}': {}",
+                model_path.display(),
+                err
+            )
+        })
+}
+
+/// Returns the embedding of an image file, such as a thumbnail. This is
+/// CPU intensive, so it should be run outside of the main thread.
+pub fn image_embedding(path: &Path) -> Result<Vec<f32>, String> {
+    let size: i32 = EMBEDDING_IMAGE_SIZE as i32;
+    let pixbuf: Pixbuf = Pixbuf::from_file(path).map_err(|err| err.to_string())?;
+
+    // Scale the shortest edge to the input size, and crop the center.
+    let scale: f64 = size as f64 / pixbuf.width().min(pixbuf.height()) as f64;
+    let (width, height): (i32, i32) = (
+        ((pixbuf.width() as f64 * scale).round() as i32).max(size),
+        ((pixbuf.height() as f64 * scale).round() as i32).max(size),
+    );
+    let scaled: Pixbuf = pixbuf
+        .scale_simple(width, height, InterpType::Bilinear)
+        .ok_or("Failed to scale the image")?;
+    let cropped: Pixbuf = scaled.new_subpixbuf((width - size) / 2, (height - size) / 2, size, size);
+
+    let bytes: glib::Bytes = cropped.read_pixel_bytes();
+    let values: Vec<f32> = pixel_values(
+        &bytes,
+        EMBEDDING_IMAGE_SIZE,
+        cropped.rowstride() as usize,
+        cropped.n_channels() as usize,
+    );
+
+    IMAGE_ENCODER.with_borrow_mut(|encoder: &mut Option<Session>| {
+        if encoder.is_none() {
+            *encoder = Some(load_session(EMBEDDING_IMAGE_MODEL_FILE)?);
+        }
+        let session: &Session = encoder.as_ref().unwrap();
+        let shape: [usize; 4] = [1, 3, EMBEDDING_IMAGE_SIZE, EMBEDDING_IMAGE_SIZE];
+
+        let run = || -> ort::Result<Vec<f32>> {
+            let input: Tensor<f32> = Tensor::from_array((shape, values))?;
+            let outputs = session.run(ort::inputs![IMAGE_MODEL_INPUT => input]?)?;
+            let (_, embedding) = outputs[IMAGE_MODEL_OUTPUT].try_extract_raw_tensor::<f32>()?;
+
+            Ok(embedding.to_vec())
+        };
+        let mut embedding: Vec<f32> = run().map_err(|err| err.to_string())?;
+
+        normalize(&mut embedding);
+        Ok(embedding)
+    })
+}
+
+/// Returns the embedding of a search query. This is CPU
+/// intensive, so it should be run outside of the main thread.
+pub fn text_embedding(text: &str) -> Result<Vec<f32>, String> {
+    TEXT_ENCODER.with_borrow_mut(|encoder: &mut Option<TextEncoder>| {
+        if encoder.is_none() {
+            let tokenizer_path: PathBuf = PathBuf::from(PKGDATADIR).join(EMBEDDING_TOKENIZER_FILE);
+
+            *encoder = Some(TextEncoder {
+                session: load_session(EMBEDDING_TEXT_MODEL_FILE)?,
+                tokenizer: Tokenizer::from_file(tokenizer_path)
+                    .map_err(|err| format!("Failed to load embedding tokenizer: {}", err))?,
+            });
+        }
+        let encoder: &TextEncoder = encoder.as_ref().unwrap();
+
+        let encoding = encoder
+            .tokenizer
+            .encode(text, true)
+            .map_err(|err| err.to_string())?;
+        let (input_ids, attention_mask) = text_inputs(encoding.get_ids(), EMBEDDING_TEXT_LENGTH);
+        let shape: [usize; 2] = [1, EMBEDDING_TEXT_LENGTH];
+
+        let run = || -> ort::Result<Vec<f32>> {
+            let input_ids: Tensor<i64> = Tensor::from_array((shape, input_ids))?;
+            let attention_mask: Tensor<i64> = Tensor::from_array((shape, attention_mask))?;
+            let outputs = encoder.session.run(ort::inputs![
+                TEXT_MODEL_INPUT_IDS => input_ids,
+                TEXT_MODEL_ATTENTION_MASK => attention_mask,
+            ]?)?;
+            let (_, embedding) = outputs[TEXT_MODEL_OUTPUT].try_extract_raw_tensor::<f32>()?;
+
+            Ok(embedding.to_vec())
+        };
+        let mut embedding: Vec<f32> = run().map_err(|err| err.to_string())?;
+
+        normalize(&mut embedding);
+        Ok(embedding)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalized_embeddings() {
+        let mut vector: Vec<f32> = vec![3.0, 0.0, 4.0];
+        normalize(&mut vector);
+        assert_eq!(vector, vec![0.6, 0.0, 0.8]);
+
+        let mut zero: Vec<f32> = vec![0.0, 0.0];
+        normalize(&mut zero);
+        assert_eq!(zero, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn quantized_similarity() {
+        let a: Vec<f32> = vec![0.6, 0.0, 0.8];
+        let b: Vec<f32> = vec![0.0, 1.0, 0.0];
+
+        assert!((similarity(&quantize(&a), &a) - 1.0).abs() < 0.01);
+        assert!(similarity(&quantize(&a), &b).abs() < 0.01);
+        assert_eq!(quantize(&[1.5, -1.0, 0.5]), vec![127, -127, 64]);
+    }
+
+    #[test]
+    fn image_pixel_values() {
+        // A 2x2 RGBA image, with padding at the end of each row.
+        let pixels: Vec<u8> = vec![
+            255, 0, 0, 255, 0, 255, 0, 255, 0, 0, //
+            0, 0, 255, 255, 255, 255, 255, 255, 0, 0,
+        ];
+        let values: Vec<f32> = pixel_values(&pixels, 2, 10, 4);
+        let normalized = |value: f32, channel: usize| (value - PIXEL_MEAN[channel]) / PIXEL_STD[channel];
+
+        assert_eq!(values.len(), 12);
+        assert_eq!(
+            &values[0..4],
+            &[
+                normalized(1.0, 0),
+                normalized(0.0, 0),
+                normalized(0.0, 0),
+                normalized(1.0, 0)
+            ]
+        );
+        assert_eq!(
+            &values[4..8],
+            &[
+                normalized(0.0, 1),
+                normalized(1.0, 1),
+                normalized(0.0, 1),
+                normalized(1.0, 1)
+            ]
+        );
+        assert_eq!(values[8], normalized(0.0, 2));
+        assert_eq!(values[10], normalized(1.0, 2));
+    }
+
+    #[test]
+    fn padded_text_inputs() {
+        assert_eq!(
+            text_inputs(&[49406, 320, 49407], 5),
+            (vec![49406, 320, 49407, 0, 0], vec![1, 1, 1, 0, 0])
+        );
+        assert_eq!(text_inputs(&[1, 2, 3, 4], 2), (vec![1, 2], vec![1, 1]));
+    }
+}
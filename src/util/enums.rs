@@ -19,6 +19,7 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::globals::{FALLBACK_XDG_PICTURES_DIR, FALLBACK_XDG_VIDEOS_DIR};
+use gtk::glib;
 use std::process::{Command, Output};
 use std::str::from_utf8;
 
@@ -42,6 +43,82 @@ impl PreferredAdwaitaTheme {
     }
 }
 
+/// Representation of the input events that reveal the
+/// overlay controls in the media viewer page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverlayRevealTrigger {
+    MotionAndTouch = 0,
+    Touch = 1,
+    Motion = 2,
+}
+
+// Must be in range of the 'viewer-overlay-trigger' GSchema key.
+impl OverlayRevealTrigger {
+    pub fn from_value(value: i32) -> OverlayRevealTrigger {
+        match value {
+            1 => OverlayRevealTrigger::Touch,
+            2 => OverlayRevealTrigger::Motion,
+            _ => OverlayRevealTrigger::MotionAndTouch,
+        }
+    }
+
+    pub fn reveals_on_motion(&self) -> bool {
+        *self != OverlayRevealTrigger::Touch
+    }
+
+    pub fn reveals_on_touch(&self) -> bool {
+        *self != OverlayRevealTrigger::Motion
+    }
+}
+
+/// Representation of the ways that media in the
+/// library are opened in the media viewer page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ItemActivation {
+    SingleClick = 0,
+    DoubleClick = 1,
+    EnterOnly = 2,
+}
+
+// Must be in range of the 'item-activation' GSchema key.
+impl ItemActivation {
+    pub fn from_value(value: i32) -> ItemActivation {
+        match value {
+            1 => ItemActivation::DoubleClick,
+            2 => ItemActivation::EnterOnly,
+            _ => ItemActivation::SingleClick,
+        }
+    }
+
+    /// Returns whether a click of the given number of presses opens media.
+    /// Media are opened with the Enter key regardless of the activation.
+    pub fn opens_on_click(&self, n_press: i32) -> bool {
+        match *self {
+            ItemActivation::SingleClick => n_press == 1,
+            ItemActivation::DoubleClick => n_press == 2,
+            ItemActivation::EnterOnly => false,
+        }
+    }
+}
+
+/// Representation of what the scroll wheel does in the media
+/// viewer page. Scrolling with Ctrl held zooms regardless.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrollWheelAction {
+    Zoom = 0,
+    Navigate = 1,
+}
+
+// Must be in range of the 'viewer-scroll-wheel' GSchema key.
+impl ScrollWheelAction {
+    pub fn from_value(value: i32) -> ScrollWheelAction {
+        match value {
+            1 => ScrollWheelAction::Navigate,
+            _ => ScrollWheelAction::Zoom,
+        }
+    }
+}
+
 fn trim_newline(string: &mut String) {
     if string.ends_with('\n') {
         string.pop();
@@ -92,7 +169,7 @@ impl XDGUserDir {
     /// binary utility is used to search for the configured path.
     pub fn get_path(&self) -> String {
         let variant_value: (&str, &str) = self.value();
-        let home: String = std::env::var("HOME").expect("$HOME env var not set.");
+        let home: String = glib::home_dir().to_string_lossy().to_string();
 
         if let Ok(dir) = std::env::var(variant_value.0) {
             dir
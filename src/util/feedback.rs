@@ -0,0 +1,71 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Haptic feedback for key interactions on mobile devices, emitted through
+//! feedbackd when Memories is built with the `use-feedbackd` feature.
+//! Without the feature, emitting feedback does nothing.
+
+/// Interactions that give haptic feedback. Each is mapped to an event
+/// name of the feedback theme, which decides how it is felt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedbackEvent {
+    /// An item in the grid was selected or unselected.
+    SelectionToggled,
+    /// The grid or viewer was zoomed to the next zoom level.
+    ZoomStep,
+    /// The grid or viewer can not be zoomed any further.
+    ZoomLimit,
+    /// Media was moved to the trash or deleted permanently.
+    DeleteConfirmed,
+}
+
+impl FeedbackEvent {
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            Self::SelectionToggled => "button-pressed",
+            Self::ZoomStep => "button-released",
+            Self::ZoomLimit => "bell-terminal",
+            Self::DeleteConfirmed => "trash-empty",
+        }
+    }
+}
+
+/// Emits haptic feedback for an interaction, without waiting for it to be played.
+pub fn emit(event: FeedbackEvent) {
+    #[cfg(feature = "use-feedbackd")]
+    {
+        use gtk::gio;
+
+        let lfb_event: libfeedback::Event = libfeedback::Event::new(event.event_name());
+
+        lfb_event.trigger_feedback_async(gio::Cancellable::NONE, move |result| {
+            if let Err(err) = result {
+                gtk::glib::g_debug!(
+                    "Feedback",
+                    "Failed to trigger feedback for '{}': {}",
+                    event.event_name(),
+                    err
+                );
+            }
+        });
+    }
+    #[cfg(not(feature = "use-feedbackd"))]
+    let _ = event;
+}
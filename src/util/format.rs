@@ -0,0 +1,180 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Locale-aware formatting of dates, times, durations, and file
+//! sizes for display, using the formats of the user's locale.
+
+use crate::i18n::ngettext_f;
+use gettextrs::gettext;
+use gtk::glib;
+
+/// Units that file sizes are formatted with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SizeUnits {
+    /// Powers of 1000, such as "1.5 MB". Used by GNOME by default.
+    Decimal,
+    /// Powers of 1024, such as "1.4 MiB".
+    Binary,
+}
+
+/// Returns the date and time in the user's locale's date and time formats.
+pub fn date_time(date_time: &glib::DateTime) -> Option<String> {
+    date_time.format("%x %X").ok().map(String::from)
+}
+
+/// Returns the time elapsed since the given date, such as "3 days ago".
+pub fn relative_time(date_time: &glib::DateTime) -> String {
+    match glib::DateTime::now_local() {
+        Ok(now) => relative_time_since(date_time, &now),
+        Err(_) => gettext("Just now"),
+    }
+}
+
+/// Returns the time elapsed from `date_time` to `now`, such as "3 days ago".
+/// Dates in the future, such as from a wrong camera clock, are given as is.
+pub fn relative_time_since(date_time: &glib::DateTime, now: &glib::DateTime) -> String {
+    let span: glib::TimeSpan = now.difference(date_time);
+
+    if span.as_seconds() < 0 {
+        return self::date_time(date_time).unwrap_or_default();
+    }
+
+    let days: u32 = span.as_days().try_into().unwrap_or(0);
+    let hours: u32 = span.as_hours().try_into().unwrap_or(0);
+    let minutes: u32 = span.as_minutes().try_into().unwrap_or(0);
+
+    if days >= 365 {
+        let years: u32 = days / 365;
+        ngettext_f(
+            "{N} year ago",
+            "{N} years ago",
+            years,
+            &[("N", &years.to_string())],
+        )
+    } else if days >= 30 {
+        let months: u32 = days / 30;
+        ngettext_f(
+            "{N} month ago",
+            "{N} months ago",
+            months,
+            &[("N", &months.to_string())],
+        )
+    } else if days >= 1 {
+        ngettext_f("{N} day ago", "{N} days ago", days, &[("N", &days.to_string())])
+    } else if hours >= 1 {
+        ngettext_f(
+            "{N} hour ago",
+            "{N} hours ago",
+            hours,
+            &[("N", &hours.to_string())],
+        )
+    } else if minutes >= 1 {
+        ngettext_f(
+            "{N} minute ago",
+            "{N} minutes ago",
+            minutes,
+            &[("N", &minutes.to_string())],
+        )
+    } else {
+        gettext("Just now")
+    }
+}
+
+/// Returns the file size with the given units, such as "1.5 MB".
+pub fn file_size(bytes: u64, units: SizeUnits) -> String {
+    let flags: glib::FormatSizeFlags = match units {
+        SizeUnits::Decimal => glib::FormatSizeFlags::DEFAULT,
+        SizeUnits::Binary => glib::FormatSizeFlags::IEC_UNITS,
+    };
+    glib::format_size_full(bytes, flags).to_string()
+}
+
+/// Returns the duration of a video, such as "1:05", or "1:02:03"
+/// for videos that are at least an hour long.
+pub fn duration(seconds: u32) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(unix: i64) -> glib::DateTime {
+        glib::DateTime::from_unix_utc(unix).unwrap()
+    }
+
+    #[test]
+    fn relative_time_units() {
+        let now: glib::DateTime = utc(1_000_000_000);
+
+        assert_eq!(relative_time_since(&utc(1_000_000_000 - 30), &now), "Just now");
+        assert_eq!(
+            relative_time_since(&utc(1_000_000_000 - 60), &now),
+            "1 minute ago"
+        );
+        assert_eq!(
+            relative_time_since(&utc(1_000_000_000 - 3 * 3600), &now),
+            "3 hours ago"
+        );
+        assert_eq!(
+            relative_time_since(&utc(1_000_000_000 - 2 * 86400), &now),
+            "2 days ago"
+        );
+        assert_eq!(
+            relative_time_since(&utc(1_000_000_000 - 60 * 86400), &now),
+            "2 months ago"
+        );
+        assert_eq!(
+            relative_time_since(&utc(1_000_000_000 - 400 * 86400), &now),
+            "1 year ago"
+        );
+    }
+
+    #[test]
+    fn relative_time_in_future_is_the_date() {
+        assert_eq!(
+            relative_time_since(&utc(2_000), &utc(1_000)),
+            date_time(&utc(2_000)).unwrap()
+        );
+    }
+
+    #[test]
+    fn file_size_units() {
+        // GLib may separate the unit with a no-break space.
+        let normalize = |size: String| size.replace('\u{a0}', " ");
+
+        assert_eq!(normalize(file_size(1_500_000, SizeUnits::Decimal)), "1.5 MB");
+        assert_eq!(normalize(file_size(1_572_864, SizeUnits::Binary)), "1.5 MiB");
+    }
+
+    #[test]
+    fn duration_with_and_without_hours() {
+        assert_eq!(duration(0), "0:00");
+        assert_eq!(duration(65), "1:05");
+        assert_eq!(duration(3599), "59:59");
+        assert_eq!(duration(3723), "1:02:03");
+    }
+}
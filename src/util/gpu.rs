@@ -0,0 +1,144 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Scaling thumbnails on the GPU. Photos are decoded by glycin into a texture,
+//! which is cropped to a square and scaled down by rendering it offscreen with
+//! GSK, instead of being scaled on the CPU by ffmpeg or gdk-pixbuf.
+
+use glib::g_debug;
+#[cfg(feature = "disable-glycin-sandbox")]
+use glycin::SandboxMechanism;
+use gtk::gdk_pixbuf::{Colorspace, Pixbuf};
+use gtk::prelude::*;
+use gtk::{gdk, gio, glib, graphene, gsk};
+use std::cell::OnceCell;
+use std::io;
+use std::path::Path;
+
+thread_local! {
+    /// Renderer that textures are scaled with, realized once for the default display.
+    static RENDERER: OnceCell<Option<gsk::Renderer>> = const { OnceCell::new() };
+}
+
+/// Returns the renderer that textures are scaled with, or `None`
+/// if it could not be realized, such as without a GPU.
+fn renderer() -> Option<gsk::Renderer> {
+    RENDERER.with(|renderer: &OnceCell<Option<gsk::Renderer>>| {
+        renderer
+            .get_or_init(|| {
+                let display: gdk::Display = gdk::Display::default()?;
+                let renderer: gsk::Renderer = gsk::GLRenderer::new().upcast();
+
+                match renderer.realize_for_display(&display) {
+                    Ok(()) => Some(renderer),
+                    Err(err) => {
+                        g_debug!("Gpu", "Failed to realize a renderer: {}", err);
+                        None
+                    }
+                }
+            })
+            .clone()
+    })
+}
+
+/// Decodes a photo, then crops it to a centered square of `size` pixels on
+/// the GPU, and saves it as a JPEG thumbnail at `out_path`. Must be called
+/// on the main thread, since the renderer belongs to the default display.
+pub async fn generate_thumbnail(file_path: &Path, out_path: &str, size: u32) -> io::Result<()> {
+    let to_io_error = |e: glycin::ErrorCtx| io::Error::new(io::ErrorKind::Other, e.to_string());
+
+    #[allow(unused_mut)]
+    let mut glycin_loader: glycin::Loader = glycin::Loader::new(gio::File::for_path(file_path));
+
+    #[cfg(feature = "disable-glycin-sandbox")]
+    glycin_loader.sandbox_mechanism(Some(SandboxMechanism::NotSandboxed));
+
+    let image: glycin::Image<'static> = glycin_loader.load().await.map_err(to_io_error)?;
+    let frame: glycin::Frame = image.next_frame().await.map_err(to_io_error)?;
+
+    let Some(thumbnail) = square_thumbnail(&frame.texture(), size) else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "GPU rendering is not available.",
+        ));
+    };
+    let mut downloader: gdk::TextureDownloader = gdk::TextureDownloader::new(&thumbnail);
+    downloader.set_format(gdk::MemoryFormat::R8g8b8);
+
+    let (bytes, stride): (glib::Bytes, usize) = downloader.download_bytes();
+    let (width, height): (i32, i32) = (thumbnail.width(), thumbnail.height());
+    let out_path: String = out_path.to_owned();
+
+    // Only the JPEG encoding is done on a separate thread.
+    gio::spawn_blocking(move || {
+        Pixbuf::from_bytes(&bytes, Colorspace::Rgb, false, 8, width, height, stride as i32)
+            .savev(&out_path, "jpeg", &[])
+            .map_err(|e: glib::Error| io::Error::new(io::ErrorKind::Other, e.to_string()))
+    })
+    .await
+    .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "Thumbnail thread panicked.")))
+}
+
+/// Renders a texture cropped to a centered square and scaled to `size` pixels.
+fn square_thumbnail(texture: &gdk::Texture, size: u32) -> Option<gdk::Texture> {
+    let renderer: gsk::Renderer = renderer()?;
+    let (x, y, width, height) = square_crop_bounds(texture.width(), texture.height(), size);
+    let bounds: graphene::Rect = graphene::Rect::new(0.0, 0.0, size as f32, size as f32);
+
+    let snapshot: gtk::Snapshot = gtk::Snapshot::new();
+    snapshot.push_clip(&bounds);
+    snapshot.append_scaled_texture(
+        texture,
+        gsk::ScalingFilter::Trilinear,
+        &graphene::Rect::new(x, y, width, height),
+    );
+    snapshot.pop();
+
+    let node: gsk::RenderNode = snapshot.to_node()?;
+    Some(renderer.render_texture(&node, Some(&bounds)))
+}
+
+/// Returns the position and size that an image of the given size is drawn
+/// at, so that its shortest edge fills a square of `size` pixels, centered.
+fn square_crop_bounds(width: i32, height: i32, size: u32) -> (f32, f32, f32, f32) {
+    let (width, height): (f32, f32) = (width.max(1) as f32, height.max(1) as f32);
+    let size: f32 = size as f32;
+    let scale: f32 = size / width.min(height);
+    let (scaled_width, scaled_height): (f32, f32) = (width * scale, height * scale);
+
+    (
+        (size - scaled_width) / 2.0,
+        (size - scaled_height) / 2.0,
+        scaled_width,
+        scaled_height,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_crop_of_landscape_and_portrait() {
+        assert_eq!(square_crop_bounds(400, 200, 100), (-50.0, 0.0, 200.0, 100.0));
+        assert_eq!(square_crop_bounds(200, 400, 100), (0.0, -50.0, 100.0, 200.0));
+        assert_eq!(square_crop_bounds(50, 50, 100), (0.0, 0.0, 100.0, 100.0));
+    }
+}
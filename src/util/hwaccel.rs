@@ -0,0 +1,197 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Detection and selection of hardware video decoding backends.
+
+#[cfg(not(feature = "use-libav"))]
+use crate::globals::FFMPEG_BINARY;
+use gettextrs::gettext;
+use glib::g_debug;
+use gtk::glib;
+use std::env;
+use std::path::Path;
+#[cfg(not(feature = "use-libav"))]
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Representation of the hardware acceleration backends that
+/// can be selected in the application preferences dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HardwareAccel {
+    Auto,
+    Vaapi,
+    Vdpau,
+    V4l2,
+}
+
+// Translates enum variants to and from the string values
+// stored in the 'ffmpeg-hardware-acceleration-backend' GSchema key.
+impl HardwareAccel {
+    pub fn from_key(key: &str) -> HardwareAccel {
+        match key {
+            "vaapi" => HardwareAccel::Vaapi,
+            "vdpau" => HardwareAccel::Vdpau,
+            "v4l2" => HardwareAccel::V4l2,
+            _ => HardwareAccel::Auto,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        match *self {
+            HardwareAccel::Auto => "auto",
+            HardwareAccel::Vaapi => "vaapi",
+            HardwareAccel::Vdpau => "vdpau",
+            HardwareAccel::V4l2 => "v4l2",
+        }
+    }
+
+    pub fn display_name(&self) -> String {
+        match *self {
+            HardwareAccel::Auto => gettext("Automatic"),
+            HardwareAccel::Vaapi => "VA-API".to_string(),
+            HardwareAccel::Vdpau => "VDPAU".to_string(),
+            HardwareAccel::V4l2 => "V4L2".to_string(),
+        }
+    }
+
+    /// Returns the name of the backend as passed to ffmpeg's `-hwaccel` option.
+    /// V4L2 stateless decoders are exposed by ffmpeg through the DRM hwaccel.
+    pub fn ffmpeg_name(&self) -> &str {
+        match *self {
+            HardwareAccel::Auto => "auto",
+            HardwareAccel::Vaapi => "vaapi",
+            HardwareAccel::Vdpau => "vdpau",
+            HardwareAccel::V4l2 => "drm",
+        }
+    }
+
+    /// Returns the GStreamer decoder elements used for video playback with the backend.
+    fn gstreamer_decoders(&self) -> &[&str] {
+        match *self {
+            HardwareAccel::Auto => &[],
+            HardwareAccel::Vaapi => &["vah264dec", "vah265dec", "vavp9dec", "vaav1dec"],
+            HardwareAccel::Vdpau => &["nvh264dec", "nvh265dec", "nvvp9dec", "nvav1dec"],
+            HardwareAccel::V4l2 => &["v4l2slh264dec", "v4l2slh265dec", "v4l2slvp9dec", "v4l2h264dec"],
+        }
+    }
+
+    /// Returns the backend to use given the hardware acceleration GSettings keys.
+    /// Returns `None` if hardware acceleration is disabled. If the selected backend
+    /// is no longer available on the system, the automatic backend is used instead.
+    pub fn selected(enabled: bool, key: &str) -> Option<HardwareAccel> {
+        if !enabled {
+            return None;
+        }
+        let backend: HardwareAccel = HardwareAccel::from_key(key);
+
+        if backend != HardwareAccel::Auto && !available_backends().contains(&backend) {
+            return Some(HardwareAccel::Auto);
+        }
+        Some(backend)
+    }
+}
+
+/// Returns the hardware acceleration backends available on the system.
+/// Detection is done once, the first time this function is called.
+pub fn available_backends() -> &'static [HardwareAccel] {
+    static BACKENDS: OnceLock<Vec<HardwareAccel>> = OnceLock::new();
+
+    BACKENDS.get_or_init(|| {
+        let backends: Vec<HardwareAccel> = detect_backends();
+        g_debug!(
+            "HardwareAccel",
+            "Detected hardware acceleration backends: {:?}",
+            backends
+        );
+        backends
+    })
+}
+
+/// Returns the hwaccel names that the ffmpeg build on the system supports.
+#[cfg(not(feature = "use-libav"))]
+fn ffmpeg_hwaccels() -> Vec<String> {
+    match Command::new(FFMPEG_BINARY)
+        .args(["-hide_banner", "-hwaccels"])
+        .output()
+    {
+        // The first line of the output is a "Hardware acceleration methods:" header.
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .skip(1)
+            .map(|line: &str| line.trim().to_string())
+            .filter(|line: &String| !line.is_empty())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// Returns the hardware device types that the linked libav libraries support.
+#[cfg(feature = "use-libav")]
+fn ffmpeg_hwaccels() -> Vec<String> {
+    crate::util::libav::hardware_device_names()
+}
+
+/// Returns `true` if a device node in `dir` starts with `prefix`.
+fn has_device_node(dir: &str, prefix: &str) -> bool {
+    let Ok(entries) = Path::new(dir).read_dir() else {
+        return false;
+    };
+    entries
+        .flatten()
+        .any(|entry| entry.file_name().to_string_lossy().starts_with(prefix))
+}
+
+fn detect_backends() -> Vec<HardwareAccel> {
+    let hwaccels: Vec<String> = ffmpeg_hwaccels();
+    let has_hwaccel = |name: &str| hwaccels.iter().any(|hwaccel: &String| hwaccel == name);
+
+    let has_render_node: bool = has_device_node("/dev/dri", "renderD");
+    let mut backends: Vec<HardwareAccel> = vec![];
+
+    if has_hwaccel("vaapi") && has_render_node {
+        backends.push(HardwareAccel::Vaapi);
+    }
+    if has_hwaccel("vdpau") && (has_render_node || Path::new("/dev/nvidia0").exists()) {
+        backends.push(HardwareAccel::Vdpau);
+    }
+    if has_hwaccel("drm") && has_device_node("/dev", "video") {
+        backends.push(HardwareAccel::V4l2);
+    }
+    backends
+}
+
+/// Raises the rank of the GStreamer decoders of the backend, so that they are
+/// preferred for video playback. Must be called before GStreamer is initialized.
+/// A `GST_PLUGIN_FEATURE_RANK` value already set by the user is left untouched.
+pub fn apply_playback_decoder_ranks(backend: Option<HardwareAccel>) {
+    let Some(backend) = backend else {
+        return;
+    };
+    if backend == HardwareAccel::Auto || env::var_os("GST_PLUGIN_FEATURE_RANK").is_some() {
+        return;
+    }
+    let feature_ranks: Vec<String> = backend
+        .gstreamer_decoders()
+        .iter()
+        .map(|decoder: &&str| format!("{}:MAX", decoder))
+        .collect();
+
+    env::set_var("GST_PLUGIN_FEATURE_RANK", feature_ranks.join(","));
+}
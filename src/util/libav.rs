@@ -0,0 +1,240 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! In-process thumbnail frame extraction using the libav* libraries
+//! via the `ffmpeg-next` bindings. Enabled with the `use-libav` feature.
+
+use crate::util::hwaccel::HardwareAccel;
+use ffmpeg::format::Pixel;
+use ffmpeg::media::Type;
+use ffmpeg::software::scaling;
+use ffmpeg::{codec, ffi, frame};
+use ffmpeg_next as ffmpeg;
+use glib::g_debug;
+use gtk::gdk_pixbuf::{Colorspace, Pixbuf};
+use gtk::glib;
+use std::ffi::{c_char, CStr};
+use std::io;
+use std::path::Path;
+use std::ptr;
+use std::sync::OnceLock;
+
+/// Initializes the libav* libraries once per process.
+fn init() -> Result<(), ffmpeg::Error> {
+    static INIT_RESULT: OnceLock<Result<(), ffmpeg::Error>> = OnceLock::new();
+
+    *INIT_RESULT.get_or_init(|| {
+        ffmpeg::init()?;
+        ffmpeg::log::set_level(ffmpeg::log::Level::Error);
+        Ok(())
+    })
+}
+
+fn to_io_error<E: ToString>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err.to_string())
+}
+
+/// Returns the names of the hardware device types supported by the libav build.
+pub fn hardware_device_names() -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+
+    unsafe {
+        let mut device_type = ffi::av_hwdevice_iterate_types(ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_NONE);
+
+        while device_type != ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_NONE {
+            let name: *const c_char = ffi::av_hwdevice_get_type_name(device_type);
+
+            if !name.is_null() {
+                names.push(CStr::from_ptr(name).to_string_lossy().to_string());
+            }
+            device_type = ffi::av_hwdevice_iterate_types(device_type);
+        }
+    }
+    names
+}
+
+/// Tries to create a hardware device context of the given type and
+/// attach it to the decoder context. Returns `true` on success.
+unsafe fn create_hardware_device(context: &mut codec::Context, device_type: ffi::AVHWDeviceType) -> bool {
+    let mut device_ctx: *mut ffi::AVBufferRef = ptr::null_mut();
+
+    if ffi::av_hwdevice_ctx_create(&mut device_ctx, device_type, ptr::null(), ptr::null_mut(), 0) >= 0 {
+        (*context.as_mut_ptr()).hw_device_ctx = device_ctx;
+        return true;
+    }
+    false
+}
+
+/// Attaches a hardware device context for the backend to the decoder
+/// context. For the automatic backend, each hardware device type supported
+/// by libav is tried in order, and the first device that can be created
+/// is used. Returns `false` if no hardware device could be created, in
+/// which case software decoding is used.
+fn attach_hardware_device(context: &mut codec::Context, backend: HardwareAccel) -> bool {
+    unsafe {
+        match backend {
+            HardwareAccel::Vaapi => {
+                create_hardware_device(context, ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_VAAPI)
+            }
+            HardwareAccel::Vdpau => {
+                create_hardware_device(context, ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_VDPAU)
+            }
+            HardwareAccel::V4l2 => create_hardware_device(context, ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_DRM),
+            HardwareAccel::Auto => {
+                let mut device_type =
+                    ffi::av_hwdevice_iterate_types(ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_NONE);
+
+                while device_type != ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_NONE {
+                    if create_hardware_device(context, device_type) {
+                        return true;
+                    }
+                    device_type = ffi::av_hwdevice_iterate_types(device_type);
+                }
+                false
+            }
+        }
+    }
+}
+
+/// Decodes the first frame of the best video stream, after seeking to
+/// a tenth of the media's duration for videos, so that the thumbnail
+/// is not a black frame from the start of the video.
+fn decode_frame(file_path: &Path, hwaccel: Option<HardwareAccel>) -> Result<frame::Video, ffmpeg::Error> {
+    let mut input = ffmpeg::format::input(file_path)?;
+
+    let stream = input
+        .streams()
+        .best(Type::Video)
+        .ok_or(ffmpeg::Error::StreamNotFound)?;
+    let stream_index: usize = stream.index();
+
+    let mut context = codec::Context::from_parameters(stream.parameters())?;
+
+    if let Some(backend) = hwaccel {
+        if !attach_hardware_device(&mut context, backend) {
+            g_debug!(
+                "LibAV",
+                "No {:?} hardware device available, using software decoding.",
+                backend
+            );
+        }
+    }
+    let mut decoder = context.decoder().video()?;
+
+    let duration: i64 = input.duration();
+
+    if duration > 0 {
+        // Seeking may fail for some containers, in which case we decode from the start.
+        let _ = input.seek(duration / 10, ..duration / 10);
+    }
+
+    let mut decoded: frame::Video = frame::Video::empty();
+
+    for (stream, packet) in input.packets() {
+        if stream.index() != stream_index {
+            continue;
+        }
+        decoder.send_packet(&packet)?;
+
+        if decoder.receive_frame(&mut decoded).is_ok() {
+            return download_frame(decoded);
+        }
+    }
+    decoder.send_eof()?;
+    decoder.receive_frame(&mut decoded)?;
+
+    download_frame(decoded)
+}
+
+/// Copies a frame decoded on a hardware device into system memory.
+/// Frames decoded in software are returned as is.
+fn download_frame(decoded: frame::Video) -> Result<frame::Video, ffmpeg::Error> {
+    unsafe {
+        if (*decoded.as_ptr()).hw_frames_ctx.is_null() {
+            return Ok(decoded);
+        }
+        let mut software_frame: frame::Video = frame::Video::empty();
+
+        match ffi::av_hwframe_transfer_data(software_frame.as_mut_ptr(), decoded.as_ptr(), 0) {
+            0 => Ok(software_frame),
+            e => Err(ffmpeg::Error::from(e)),
+        }
+    }
+}
+
+/// Writes a cropped square JPEG thumbnail of an image or video file to `out_path`,
+/// with an edge length of `size` pixels. If `hwaccel` is set, video frames are
+/// decoded on a hardware device of the given backend. Decoding happens in process
+/// and blocks the calling thread, so thumbnails are generated on a worker thread.
+pub fn generate_thumbnail(
+    file_path: &Path,
+    out_path: &str,
+    size: u32,
+    hwaccel: Option<HardwareAccel>,
+) -> io::Result<()> {
+    init().map_err(to_io_error)?;
+
+    let decoded: frame::Video = decode_frame(file_path, hwaccel).map_err(to_io_error)?;
+
+    let (width, height) = (decoded.width(), decoded.height());
+
+    if width == 0 || height == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Decoded frame has no size.",
+        ));
+    }
+
+    // Scale the frame so that its shortest edge matches the thumbnail size.
+    let scale: f64 = size as f64 / width.min(height) as f64;
+    let scaled_width: u32 = ((width as f64 * scale).round() as u32).max(size);
+    let scaled_height: u32 = ((height as f64 * scale).round() as u32).max(size);
+
+    let mut scaler = scaling::Context::get(
+        decoded.format(),
+        width,
+        height,
+        Pixel::RGB24,
+        scaled_width,
+        scaled_height,
+        scaling::Flags::BILINEAR,
+    )
+    .map_err(to_io_error)?;
+
+    let mut scaled: frame::Video = frame::Video::empty();
+    scaler.run(&decoded, &mut scaled).map_err(to_io_error)?;
+
+    let pixbuf: Pixbuf = Pixbuf::from_bytes(
+        &glib::Bytes::from(scaled.data(0)),
+        Colorspace::Rgb,
+        false,
+        8,
+        scaled_width as i32,
+        scaled_height as i32,
+        scaled.stride(0) as i32,
+    );
+    let thumbnail: Pixbuf = pixbuf.new_subpixbuf(
+        ((scaled_width - size) / 2) as i32,
+        ((scaled_height - size) / 2) as i32,
+        size as i32,
+        size as i32,
+    );
+    thumbnail.savev(out_path, "jpeg", &[]).map_err(to_io_error)
+}
@@ -0,0 +1,402 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Database of the metadata that users give to media files in Memories, such as
+//! favorites, ratings and tags, by file URI. It is kept in the app data folder
+//! instead of the cache, since it can not be recreated from the media files.
+
+use crate::config::APP_NAME;
+use gtk::glib;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Lists of media files in the database, which only store the URIs of the files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaList {
+    Favorites,
+    Archived,
+    Private,
+    /// Photos picked as the best frame of their burst.
+    BurstPicks,
+}
+
+impl MediaList {
+    /// Name of the list, which is also the detail of the
+    /// `metadata-changed` signal of the application for it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Favorites => "favorites",
+            Self::Archived => "archived",
+            Self::Private => "private",
+            Self::BurstPicks => "burst-picks",
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            Self::Favorites => "favorites",
+            Self::Archived => "archived",
+            Self::Private => "private",
+            Self::BurstPicks => "burst_picks",
+        }
+    }
+}
+
+/// Names of all the metadata in the database, which are the details
+/// of the `metadata-changed` signal of the application.
+pub const METADATA_NAMES: [&str; 7] = [
+    "favorites", "archived", "private", "burst-picks", "ratings", "tags", "trashed-files",
+];
+
+/// Tables of the database, which all have the URI of a media file in their `uri` column.
+const TABLES: [&str; 7] = [
+    "favorites", "archived", "private", "burst_picks", "ratings", "tags", "trashed_files",
+];
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS favorites (uri TEXT PRIMARY KEY);
+    CREATE TABLE IF NOT EXISTS archived (uri TEXT PRIMARY KEY);
+    CREATE TABLE IF NOT EXISTS private (uri TEXT PRIMARY KEY);
+    CREATE TABLE IF NOT EXISTS burst_picks (uri TEXT PRIMARY KEY);
+    CREATE TABLE IF NOT EXISTS ratings (uri TEXT PRIMARY KEY, rating INTEGER NOT NULL);
+    CREATE TABLE IF NOT EXISTS tags (
+        uri TEXT NOT NULL,
+        tag TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (uri, tag)
+    );
+    CREATE TABLE IF NOT EXISTS trashed_files (uri TEXT PRIMARY KEY, trashed INTEGER NOT NULL);
+";
+
+#[derive(Debug)]
+pub struct MediaDatabase {
+    connection: Connection,
+}
+
+impl MediaDatabase {
+    /// Opens the database in the app data folder, and creates it if there is none.
+    pub fn open_default() -> rusqlite::Result<Self> {
+        let path: PathBuf = database_path();
+
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        Self::open(&path)
+    }
+
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Opens a database that is only kept in memory, for
+    /// when the database in the app data folder can not be opened.
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> rusqlite::Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    /// Returns the URIs of the media files in a list, in the order they were added.
+    pub fn list(&self, list: MediaList) -> rusqlite::Result<Vec<String>> {
+        let mut statement = self
+            .connection
+            .prepare_cached(&format!("SELECT uri FROM {} ORDER BY rowid", list.table()))?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+
+        rows.collect()
+    }
+
+    pub fn is_listed(&self, list: MediaList, uri: &str) -> rusqlite::Result<bool> {
+        let mut statement = self
+            .connection
+            .prepare_cached(&format!("SELECT 1 FROM {} WHERE uri = ?1", list.table()))?;
+
+        statement.exists([uri])
+    }
+
+    /// Adds media files to a list, or removes them from it if `listed` is `false`.
+    pub fn set_listed(&mut self, list: MediaList, uris: &[String], listed: bool) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        {
+            let sql: String = match listed {
+                true => format!("INSERT OR IGNORE INTO {} (uri) VALUES (?1)", list.table()),
+                false => format!("DELETE FROM {} WHERE uri = ?1", list.table()),
+            };
+            let mut statement = transaction.prepare(&sql)?;
+
+            for uri in uris {
+                statement.execute([uri])?;
+            }
+        }
+        transaction.commit()
+    }
+
+    /// Returns the star ratings of the rated media files, by file URI.
+    pub fn ratings(&self) -> rusqlite::Result<HashMap<String, u32>> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT uri, rating FROM ratings")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?)))?;
+
+        rows.collect()
+    }
+
+    /// Sets the star rating of a media file, where 0 removes its rating.
+    pub fn set_rating(&self, uri: &str, rating: u32) -> rusqlite::Result<()> {
+        match rating {
+            0 => self
+                .connection
+                .execute("DELETE FROM ratings WHERE uri = ?1", [uri])?,
+            _ => self.connection.execute(
+                "INSERT OR REPLACE INTO ratings (uri, rating) VALUES (?1, ?2)",
+                params![uri, rating],
+            )?,
+        };
+        Ok(())
+    }
+
+    /// Returns the tags of the tagged media files, in the order they were added, by file URI.
+    pub fn tags(&self) -> rusqlite::Result<HashMap<String, Vec<String>>> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT uri, tag FROM tags ORDER BY uri, position")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+
+        let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+
+        for row in rows {
+            let (uri, tag) = row?;
+            tags.entry(uri).or_default().push(tag);
+        }
+        Ok(tags)
+    }
+
+    /// Sets the tags of a media file, where an empty list removes all of its tags.
+    pub fn set_tags(&mut self, uri: &str, tags: &[String]) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute("DELETE FROM tags WHERE uri = ?1", [uri])?;
+        {
+            let mut statement =
+                transaction.prepare("INSERT OR IGNORE INTO tags (uri, tag, position) VALUES (?1, ?2, ?3)")?;
+
+            for (position, tag) in tags.iter().enumerate() {
+                statement.execute(params![uri, tag, position as i64])?;
+            }
+        }
+        transaction.commit()
+    }
+
+    /// Returns the media files moved to the trash by Memories, as a map of their original
+    /// URIs to the time that they were moved to the trash, in seconds since the Unix epoch.
+    pub fn trashed_files(&self) -> rusqlite::Result<HashMap<String, i64>> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT uri, trashed FROM trashed_files")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+
+        rows.collect()
+    }
+
+    pub fn add_trashed_file(&self, uri: &str, unix_time: i64) -> rusqlite::Result<()> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO trashed_files (uri, trashed) VALUES (?1, ?2)",
+            params![uri, unix_time],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_trashed_files(&mut self, uris: &[String]) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut statement = transaction.prepare("DELETE FROM trashed_files WHERE uri = ?1")?;
+
+            for uri in uris {
+                statement.execute([uri])?;
+            }
+        }
+        transaction.commit()
+    }
+
+    /// Replaces the favorites, ratings and tags of the media files given,
+    /// such as after merging metadata imported from another app into them.
+    pub fn import(
+        &mut self,
+        favorites: &[String],
+        ratings: &HashMap<String, u32>,
+        tags: &HashMap<String, Vec<String>>,
+    ) -> rusqlite::Result<()> {
+        self.set_listed(MediaList::Favorites, favorites, true)?;
+
+        let transaction = self.connection.transaction()?;
+        {
+            let mut statement =
+                transaction.prepare("INSERT OR REPLACE INTO ratings (uri, rating) VALUES (?1, ?2)")?;
+
+            for (uri, rating) in ratings {
+                statement.execute(params![uri, rating])?;
+            }
+        }
+        transaction.commit()?;
+
+        for (uri, file_tags) in tags {
+            self.set_tags(uri, file_tags)?;
+        }
+        Ok(())
+    }
+
+    /// Moves all the metadata of media files that were renamed or moved
+    /// from their old URI to their new URI, given as pairs of URIs.
+    pub fn move_files(&mut self, moves: &[(String, String)]) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+
+        for table in TABLES {
+            let mut statement = transaction.prepare(&format!(
+                "UPDATE OR REPLACE {} SET uri = ?2 WHERE uri = ?1",
+                table
+            ))?;
+
+            for (old_uri, new_uri) in moves {
+                statement.execute([old_uri, new_uri])?;
+            }
+        }
+        transaction.commit()
+    }
+}
+
+fn database_path() -> PathBuf {
+    glib::user_data_dir().join(APP_NAME).join("media.db")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists() {
+        let folder = tempfile::tempdir().unwrap();
+        let path: PathBuf = folder.path().join("media.db");
+        let uris: Vec<String> = vec!["file:///a.jpg".into(), "file:///b.jpg".into()];
+
+        let mut database: MediaDatabase = MediaDatabase::open(&path).unwrap();
+        database.set_listed(MediaList::Archived, &uris, true).unwrap();
+        database
+            .set_listed(MediaList::Archived, &uris[..1], true)
+            .unwrap();
+        database.set_listed(MediaList::Private, &uris[1..], true).unwrap();
+
+        assert_eq!(database.list(MediaList::Archived).unwrap(), uris);
+        assert!(database.is_listed(MediaList::Private, "file:///b.jpg").unwrap());
+        assert!(!database.is_listed(MediaList::Favorites, "file:///b.jpg").unwrap());
+
+        database
+            .set_listed(MediaList::Archived, &uris[..1], false)
+            .unwrap();
+        drop(database);
+
+        // The lists are kept in the database file.
+        let database: MediaDatabase = MediaDatabase::open(&path).unwrap();
+        assert_eq!(database.list(MediaList::Archived).unwrap(), &uris[1..]);
+        assert_eq!(database.list(MediaList::Private).unwrap(), &uris[1..]);
+    }
+
+    #[test]
+    fn ratings_and_tags() {
+        let mut database: MediaDatabase = MediaDatabase::open_in_memory().unwrap();
+
+        database.set_rating("file:///a.jpg", 4).unwrap();
+        database.set_rating("file:///b.jpg", 2).unwrap();
+        database.set_rating("file:///b.jpg", 0).unwrap();
+        assert_eq!(
+            database.ratings().unwrap(),
+            HashMap::from([("file:///a.jpg".into(), 4)])
+        );
+
+        database
+            .set_tags("file:///a.jpg", &["Trip".into(), "Beach".into()])
+            .unwrap();
+        database.set_tags("file:///b.jpg", &["Home".into()]).unwrap();
+        database.set_tags("file:///b.jpg", &[]).unwrap();
+        assert_eq!(
+            database.tags().unwrap(),
+            HashMap::from([("file:///a.jpg".into(), vec!["Trip".into(), "Beach".into()])])
+        );
+    }
+
+    #[test]
+    fn moving_files() {
+        let mut database: MediaDatabase = MediaDatabase::open_in_memory().unwrap();
+        let (old_uri, new_uri): (String, String) = ("file:///a.jpg".into(), "file:///b.jpg".into());
+
+        for list in [
+            MediaList::Favorites,
+            MediaList::Archived,
+            MediaList::Private,
+            MediaList::BurstPicks,
+        ] {
+            database
+                .set_listed(list, std::slice::from_ref(&old_uri), true)
+                .unwrap();
+        }
+        database.set_rating(&old_uri, 3).unwrap();
+        database.set_tags(&old_uri, &["Trip".into()]).unwrap();
+        database.add_trashed_file(&old_uri, 100).unwrap();
+        database.set_rating("file:///c.jpg", 1).unwrap();
+
+        database
+            .move_files(&[(old_uri.clone(), new_uri.clone())])
+            .unwrap();
+
+        for list in [
+            MediaList::Favorites,
+            MediaList::Archived,
+            MediaList::Private,
+            MediaList::BurstPicks,
+        ] {
+            assert_eq!(database.list(list).unwrap(), vec![new_uri.clone()]);
+        }
+        assert_eq!(
+            database.ratings().unwrap(),
+            HashMap::from([(new_uri.clone(), 3), ("file:///c.jpg".into(), 1)])
+        );
+        assert_eq!(
+            database.tags().unwrap(),
+            HashMap::from([(new_uri.clone(), vec!["Trip".into()])])
+        );
+        assert_eq!(database.trashed_files().unwrap(), HashMap::from([(new_uri, 100)]));
+    }
+
+    #[test]
+    fn trashed_files() {
+        let mut database: MediaDatabase = MediaDatabase::open_in_memory().unwrap();
+
+        database.add_trashed_file("file:///a.jpg", 100).unwrap();
+        database.add_trashed_file("file:///b.jpg", 200).unwrap();
+        database.remove_trashed_files(&["file:///a.jpg".into()]).unwrap();
+
+        assert_eq!(
+            database.trashed_files().unwrap(),
+            HashMap::from([("file:///b.jpg".into(), 200)])
+        );
+    }
+}
@@ -18,12 +18,15 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use async_fs::{File, Metadata};
-use gtk::glib;
+use crate::util::format::{self, SizeUnits};
+use async_fs::Metadata;
+use gtk::gio;
 use md5::{Digest, Md5};
 use serde::Serialize;
 use std::io;
-use std::time::SystemTime;
+use std::ops::Range;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// A data structure that contains the file metadata information
 /// that the thumbnailer needs to serialize and fingerprint hash.
@@ -38,7 +41,7 @@ pub struct MetadataInfo {
 
 impl MetadataInfo {
     pub fn pretty_print_bytes(&self) -> String {
-        glib::format_size(self.size).to_string()
+        format::file_size(self.size, SizeUnits::Decimal)
     }
 }
 
@@ -54,15 +57,1044 @@ pub fn pack_metadata_as_struct(metadata: &Metadata) -> io::Result<MetadataInfo>
     })
 }
 
-/// Returns `MetadataInfo` struct and a `String` that
-/// contains the metadata MD5 digest in hexadecimal format.
-pub async fn get_metadata_with_hash(file: File) -> io::Result<(MetadataInfo, String)> {
-    let in_metadata: Metadata = file.metadata().await?;
+/// Returns `MetadataInfo` struct and a `String` that contains the key
+/// of the file in the thumbnail cache. The contents of the file are not read.
+pub async fn get_metadata_with_hash(file_path: &Path) -> io::Result<(MetadataInfo, String)> {
+    let in_metadata: Metadata = async_fs::metadata(file_path).await?;
+    let metadata = pack_metadata_as_struct(&in_metadata)?;
+
+    let uri: String = gio::File::for_path(file_path).uri().to_string();
+    let hash: String = thumbnail_cache_key(&uri, metadata.size, metadata.modified);
+
+    Ok((metadata, hash))
+}
+
+/// Returns the MD5 digest, in hexadecimal format, of the URI, size and modification
+/// time of a file. Like in the freedesktop.org thumbnail specification, these are
+/// enough to tell whether a cached thumbnail is of the current contents of the file.
+pub fn thumbnail_cache_key(uri: &str, size: u64, modified: SystemTime) -> String {
+    let modified_nanos: u128 = modified
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
 
     let mut md5_hasher: Md5 = Md5::new();
-    let metadata = pack_metadata_as_struct(&in_metadata)?;
+    md5_hasher.update(format!("{}\n{}\n{}", uri, size, modified_nanos));
+
+    format!("{:x}", md5_hasher.finalize())
+}
+
+/// Identifies the APP1 segment of a JPEG file that contains EXIF data.
+const JPEG_EXIF_HEADER: &[u8] = b"Exif\0\0";
+
+/// Represents the metadata standard that a `MetadataTag` was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataStandard {
+    Exif,
+    Xmp,
+}
+
+impl MetadataStandard {
+    pub fn name(&self) -> &str {
+        match *self {
+            MetadataStandard::Exif => "EXIF",
+            MetadataStandard::Xmp => "XMP",
+        }
+    }
+}
+
+/// A single key/value metadata tag found in a media file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataTag {
+    pub standard: MetadataStandard,
+    pub key: String,
+    pub value: String,
+}
+
+/// Returns all tags in the primary image of the raw EXIF data given.
+pub fn extract_exif_tags(exif_bytes: Vec<u8>) -> Vec<MetadataTag> {
+    let Ok(exif_data) = exif::Reader::new().read_raw(exif_bytes) else {
+        return vec![];
+    };
+    exif_data
+        .fields()
+        .filter(|field: &&exif::Field| field.ifd_num == exif::In::PRIMARY)
+        .map(|field: &exif::Field| MetadataTag {
+            standard: MetadataStandard::Exif,
+            key: field.tag.to_string(),
+            value: field.display_value().with_unit(&exif_data).to_string(),
+        })
+        .collect()
+}
+
+/// Returns the JPEG thumbnail embedded in the EXIF data of a JPEG file, with
+/// the EXIF orientation of the image. Only the start of the file is needed,
+/// since JPEG files store their EXIF data before the image data.
+pub fn embedded_jpeg_thumbnail(file_head: &[u8]) -> Option<(Vec<u8>, u32)> {
+    let exif_data: exif::Exif = exif::Reader::new()
+        .read_from_container(&mut io::Cursor::new(file_head))
+        .ok()?;
+
+    let uint_field = |tag: exif::Tag, ifd_num: exif::In| -> Option<u32> {
+        exif_data.get_field(tag, ifd_num)?.value.get_uint(0)
+    };
+    let offset: usize = uint_field(exif::Tag::JPEGInterchangeFormat, exif::In::THUMBNAIL)? as usize;
+    let length: usize = uint_field(exif::Tag::JPEGInterchangeFormatLength, exif::In::THUMBNAIL)? as usize;
+    let orientation: u32 = uint_field(exif::Tag::Orientation, exif::In::PRIMARY).unwrap_or(1);
+
+    let thumbnail: &[u8] = exif_data.buf().get(offset..offset.checked_add(length)?)?;
+    Some((thumbnail.to_vec(), orientation))
+}
+
+/// Returns the EXIF `DateTimeOriginal` value of a media file, read from its
+/// contents, if the file format has EXIF data and the value is present.
+pub fn read_date_time_original(contents: &[u8]) -> Option<exif::DateTime> {
+    let exif_data: exif::Exif = exif::Reader::new()
+        .read_from_container(&mut io::Cursor::new(contents))
+        .ok()?;
+    let field: &exif::Field = exif_data.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?;
+
+    match field.value {
+        exif::Value::Ascii(ref values) if !values.is_empty() => exif::DateTime::from_ascii(&values[0]).ok(),
+        _ => None,
+    }
+}
+
+/// EXIF values of a photo that describe how and where it was taken.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExifDetails {
+    pub date_time_original: Option<exif::DateTime>,
+    pub camera: Option<String>,
+    /// Width and height of the image in pixels.
+    pub dimensions: Option<(u32, u32)>,
+    /// Latitude and longitude in decimal degrees, negative to the south and west.
+    pub location: Option<(f64, f64)>,
+}
+
+/// Returns the EXIF details of a photo, read from the contents of its file,
+/// if the file format has EXIF data. Values that are missing are `None`.
+pub fn read_exif_details(contents: &[u8]) -> Option<ExifDetails> {
+    let exif_data: exif::Exif = exif::Reader::new()
+        .read_from_container(&mut io::Cursor::new(contents))
+        .ok()?;
+
+    let value = |tag: exif::Tag| -> Option<&exif::Value> {
+        exif_data
+            .get_field(tag, exif::In::PRIMARY)
+            .map(|field| &field.value)
+    };
+    let ascii_field = |tag: exif::Tag| -> String {
+        match value(tag) {
+            Some(exif::Value::Ascii(values)) if !values.is_empty() => String::from_utf8_lossy(&values[0])
+                .trim_end_matches('\0')
+                .to_string(),
+            _ => String::new(),
+        }
+    };
+    let coordinate = |tag: exif::Tag, ref_tag: exif::Tag| -> Option<f64> {
+        match value(tag)? {
+            exif::Value::Rational(dms) if dms.len() == 3 => gps_coordinate(
+                [dms[0].to_f64(), dms[1].to_f64(), dms[2].to_f64()],
+                &ascii_field(ref_tag),
+            ),
+            _ => None,
+        }
+    };
+    let uint_field =
+        |tag: exif::Tag| -> Option<u32> { value(tag)?.get_uint(0).filter(|value: &u32| *value > 0) };
+
+    Some(ExifDetails {
+        date_time_original: match value(exif::Tag::DateTimeOriginal) {
+            Some(exif::Value::Ascii(values)) if !values.is_empty() => {
+                exif::DateTime::from_ascii(&values[0]).ok()
+            }
+            _ => None,
+        },
+        camera: camera_name(&ascii_field(exif::Tag::Make), &ascii_field(exif::Tag::Model)),
+        dimensions: uint_field(exif::Tag::PixelXDimension).zip(uint_field(exif::Tag::PixelYDimension)),
+        location: coordinate(exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef)
+            .zip(coordinate(exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef)),
+    })
+}
+
+/// Returns the camera that took a photo, from the EXIF `Make` and `Model` values
+/// read from the contents of its file, if the file format has EXIF data.
+pub fn read_camera(contents: &[u8]) -> Option<String> {
+    read_exif_details(contents)?.camera
+}
+
+/// Returns a GPS coordinate in decimal degrees, from its degrees, minutes, and
+/// seconds, and its reference, which is `S` or `W` for negative coordinates.
+fn gps_coordinate(dms: [f64; 3], reference: &str) -> Option<f64> {
+    let degrees: f64 = dms[0] + dms[1] / 60.0 + dms[2] / 3600.0;
+
+    if !degrees.is_finite() {
+        return None;
+    }
+    match reference.trim() {
+        "S" | "W" => Some(-degrees),
+        _ => Some(degrees),
+    }
+}
+
+/// Returns the name of a camera from its EXIF make and model. Many cameras
+/// already start their model with the make, such as "Canon EOS R6", in which
+/// case the make is not repeated.
+fn camera_name(make: &str, model: &str) -> Option<String> {
+    let (make, model) = (make.trim(), model.trim());
+
+    if model.is_empty() {
+        return Some(make.to_string()).filter(|make: &String| !make.is_empty());
+    }
+    let make_word: &str = make.split_whitespace().next().unwrap_or_default();
+
+    if make.is_empty() || model.to_lowercase().starts_with(&make_word.to_lowercase()) {
+        Some(model.to_string())
+    } else {
+        Some(format!("{} {}", make, model))
+    }
+}
+
+/// TIFF data of an EXIF segment, which is edited without moving any existing bytes,
+/// so that offsets inside values that are not understood, such as maker notes, stay valid.
+struct TiffData {
+    data: Vec<u8>,
+    little_endian: bool,
+}
+
+/// Entry of an IFD in TIFF data, with its value or the offset of its value.
+struct IfdEntry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    value: u32,
+}
+
+impl TiffData {
+    fn new(data: Vec<u8>) -> Result<Self, exif::Error> {
+        let little_endian: bool = match data.get(..4) {
+            Some(b"II\x2a\0") => true,
+            Some(b"MM\0\x2a") => false,
+            _ => return Err(exif::Error::InvalidFormat("Invalid TIFF header")),
+        };
+        Ok(Self { data, little_endian })
+    }
+
+    fn bytes(&self, offset: usize, length: usize) -> Result<&[u8], exif::Error> {
+        self.data
+            .get(offset..offset.saturating_add(length))
+            .ok_or(exif::Error::InvalidFormat("Truncated TIFF data"))
+    }
+
+    fn u16_at(&self, offset: usize) -> Result<u16, exif::Error> {
+        let bytes: [u8; 2] = self.bytes(offset, 2)?.try_into().unwrap();
+        Ok(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, offset: usize) -> Result<u32, exif::Error> {
+        let bytes: [u8; 4] = self.bytes(offset, 4)?.try_into().unwrap();
+        Ok(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn u16_bytes(&self, value: u16) -> [u8; 2] {
+        if self.little_endian {
+            value.to_le_bytes()
+        } else {
+            value.to_be_bytes()
+        }
+    }
+
+    fn u32_bytes(&self, value: u32) -> [u8; 4] {
+        if self.little_endian {
+            value.to_le_bytes()
+        } else {
+            value.to_be_bytes()
+        }
+    }
+
+    fn set_u32(&mut self, offset: usize, value: u32) -> Result<(), exif::Error> {
+        self.bytes(offset, 4)?;
+        let bytes: [u8; 4] = self.u32_bytes(value);
+        self.data[offset..offset + 4].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// Returns the offset of the entry with the tag in the IFD at `ifd`, if there is one.
+    fn find_entry(&self, ifd: usize, tag: exif::Tag) -> Result<Option<usize>, exif::Error> {
+        for index in 0..self.u16_at(ifd)? as usize {
+            let entry: usize = ifd + 2 + index * 12;
+
+            if self.u16_at(entry)? == tag.number() {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Overwrites the ASCII value of the entry at `entry` with `value`, which
+    /// must have the same length as the existing value, without its NUL terminator.
+    fn set_ascii(&mut self, entry: usize, value: &[u8]) -> Result<(), exif::Error> {
+        if self.u16_at(entry + 2)? != 2 || self.u32_at(entry + 4)? as usize != value.len() + 1 {
+            return Err(exif::Error::InvalidFormat("Unexpected EXIF date and time value"));
+        }
+        let offset: usize = self.u32_at(entry + 8)? as usize;
+        self.bytes(offset, value.len() + 1)?;
+
+        self.data[offset..offset + value.len()].copy_from_slice(value);
+        self.data[offset + value.len()] = 0;
+        Ok(())
+    }
+
+    /// Appends `bytes` at a word boundary, and returns their offset.
+    fn append(&mut self, bytes: &[u8]) -> Result<u32, exif::Error> {
+        if self.data.len() % 2 == 1 {
+            self.data.push(0);
+        }
+        let offset: u32 = self
+            .data
+            .len()
+            .try_into()
+            .map_err(|_| exif::Error::TooBig("TIFF data is too big"))?;
+
+        self.data.extend(bytes);
+        Ok(offset)
+    }
+
+    /// Appends a copy of the IFD at `ifd`, or of an empty IFD, with `entry` added,
+    /// and returns the offset of the copy. The original IFD is left in place.
+    fn append_ifd(&mut self, ifd: Option<usize>, entry: IfdEntry) -> Result<u32, exif::Error> {
+        let mut entries: Vec<(u16, Vec<u8>)> = vec![];
+        let mut next_ifd: u32 = 0;
+
+        if let Some(ifd) = ifd {
+            let count: usize = self.u16_at(ifd)? as usize;
+
+            for index in 0..count {
+                let offset: usize = ifd + 2 + index * 12;
+                entries.push((self.u16_at(offset)?, self.bytes(offset, 12)?.to_vec()));
+            }
+            next_ifd = self.u32_at(ifd + 2 + count * 12)?;
+        }
+        let new_entry: Vec<u8> = [
+            &self.u16_bytes(entry.tag)[..],
+            &self.u16_bytes(entry.kind),
+            &self.u32_bytes(entry.count),
+            &self.u32_bytes(entry.value),
+        ]
+        .concat();
+
+        // Entries of an IFD are sorted by their tag.
+        let position: usize = entries
+            .iter()
+            .position(|(tag, _)| *tag > entry.tag)
+            .unwrap_or(entries.len());
+        entries.insert(position, (entry.tag, new_entry));
+
+        let count: u16 = entries
+            .len()
+            .try_into()
+            .map_err(|_| exif::Error::TooBig("Too many IFD entries"))?;
+        let mut copy: Vec<u8> = self.u16_bytes(count).to_vec();
+        copy.extend(entries.into_iter().flat_map(|(_, bytes)| bytes));
+        copy.extend(self.u32_bytes(next_ifd));
+        self.append(&copy)
+    }
+}
+
+/// Returns the contents of a JPEG file with its EXIF `DateTimeOriginal` value set to
+/// `date_time`. The existing `DateTimeOriginal`, `DateTime` and `DateTimeDigitized`
+/// values are overwritten in place, and all other bytes are kept as they are.
+/// `DateTimeOriginal` is added if it is missing, as is an EXIF segment if there is none.
+pub fn write_date_time_original(jpeg: &[u8], date_time: &exif::DateTime) -> Result<Vec<u8>, exif::Error> {
+    if !jpeg.starts_with(&[0xff, 0xd8]) {
+        return Err(exif::Error::InvalidFormat("Not a JPEG file"));
+    }
+    // Find the APP1 segment with the EXIF data, which is among the
+    // application segments at the start of the file.
+    let mut position: usize = 2;
+    let mut exif_segment: Option<(usize, usize)> = None;
+    let mut insert_position: usize = 2;
+
+    while let Some(&[0xff, marker, length_high, length_low]) = jpeg.get(position..position + 4) {
+        if !(0xe0..=0xef).contains(&marker) {
+            break;
+        }
+        let end: usize = position + 2 + u16::from_be_bytes([length_high, length_low]) as usize;
+
+        if end > jpeg.len() {
+            return Err(exif::Error::InvalidFormat("Truncated JPEG segment"));
+        }
+        if marker == 0xe1 && jpeg[position + 4..end].starts_with(JPEG_EXIF_HEADER) {
+            exif_segment = Some((position, end));
+            break;
+        }
+        // A new EXIF segment goes after the JFIF segment, which must be first.
+        if marker == 0xe0 && position == 2 {
+            insert_position = end;
+        }
+        position = end;
+    }
+
+    // TIFF data with an empty IFD0, for files without EXIF data.
+    let tiff: Vec<u8> = match exif_segment {
+        Some((start, end)) => jpeg[start + 4 + JPEG_EXIF_HEADER.len()..end].to_vec(),
+        None => b"MM\0\x2a\0\0\0\x08\0\0\0\0\0\0".to_vec(),
+    };
+    let mut tiff: TiffData = TiffData::new(tiff)?;
+    let date: Vec<u8> = format!(
+        "{:04}:{:02}:{:02} {:02}:{:02}:{:02}",
+        date_time.year, date_time.month, date_time.day, date_time.hour, date_time.minute, date_time.second
+    )
+    .into_bytes();
+
+    let ifd0: usize = tiff.u32_at(4)? as usize;
+    if let Some(entry) = tiff.find_entry(ifd0, exif::Tag::DateTime)? {
+        tiff.set_ascii(entry, &date)?;
+    }
+    let exif_pointer: Option<usize> = tiff.find_entry(ifd0, exif::Tag::ExifIFDPointer)?;
+    let exif_ifd: Option<usize> = match exif_pointer {
+        Some(entry) => Some(tiff.u32_at(entry + 8)? as usize),
+        None => None,
+    };
+    let mut has_date_time_original: bool = false;
+
+    if let Some(exif_ifd) = exif_ifd {
+        if let Some(entry) = tiff.find_entry(exif_ifd, exif::Tag::DateTimeDigitized)? {
+            tiff.set_ascii(entry, &date)?;
+        }
+        if let Some(entry) = tiff.find_entry(exif_ifd, exif::Tag::DateTimeOriginal)? {
+            tiff.set_ascii(entry, &date)?;
+            has_date_time_original = true;
+        }
+    }
+    // Adding an entry changes the size of an IFD, so a copy of the IFD with the
+    // entry is appended instead, and the pointer to the IFD is set to the copy.
+    if !has_date_time_original {
+        let value: u32 = tiff.append(&[&date[..], &[0]].concat())?;
+        let new_exif_ifd: u32 = tiff.append_ifd(
+            exif_ifd,
+            IfdEntry {
+                tag: exif::Tag::DateTimeOriginal.number(),
+                kind: 2,
+                count: date.len() as u32 + 1,
+                value,
+            },
+        )?;
+        match exif_pointer {
+            Some(entry) => tiff.set_u32(entry + 8, new_exif_ifd)?,
+            None => {
+                let new_ifd0: u32 = tiff.append_ifd(
+                    Some(ifd0),
+                    IfdEntry {
+                        tag: exif::Tag::ExifIFDPointer.number(),
+                        kind: 4,
+                        count: 1,
+                        value: new_exif_ifd,
+                    },
+                )?;
+                tiff.set_u32(4, new_ifd0)?;
+            }
+        }
+    }
+    let tiff: Vec<u8> = tiff.data;
+
+    let length: u16 = (2 + JPEG_EXIF_HEADER.len() + tiff.len())
+        .try_into()
+        .map_err(|_| exif::Error::TooBig("EXIF data does not fit in a JPEG segment"))?;
+
+    let (start, end) = exif_segment.unwrap_or((insert_position, insert_position));
+    let mut contents: Vec<u8> = Vec::with_capacity(jpeg.len() + tiff.len());
+
+    contents.extend(&jpeg[..start]);
+    contents.extend([0xff, 0xe1]);
+    contents.extend(length.to_be_bytes());
+    contents.extend(JPEG_EXIF_HEADER);
+    contents.extend(tiff);
+    contents.extend(&jpeg[end..]);
+    Ok(contents)
+}
+
+/// Returns the position right after the end of image marker of the JPEG image
+/// that starts at `start`, or `None` if it is not a complete JPEG image. Markers
+/// in the entropy coded data are skipped, as are images embedded in segments,
+/// such as EXIF thumbnails.
+fn jpeg_image_end(jpeg: &[u8], start: usize) -> Option<usize> {
+    if jpeg.get(start..start + 2)? != [0xff, 0xd8] {
+        return None;
+    }
+    let mut position: usize = start + 2;
+
+    loop {
+        if *jpeg.get(position)? != 0xff {
+            return None;
+        }
+        let marker: u8 = *jpeg.get(position + 1)?;
+
+        match marker {
+            // Markers may be preceded by any amount of fill bytes.
+            0xff => position += 1,
+            0xd9 => return Some(position + 2),
+            0x01 | 0xd0..=0xd7 => position += 2,
+            _ => {
+                let length: &[u8] = jpeg.get(position + 2..position + 4)?;
+                position += 2 + u16::from_be_bytes([length[0], length[1]]) as usize;
 
-    md5_hasher.update(serde_json::to_vec(&metadata)?);
+                // The entropy coded data of a scan ends at the next marker, other than
+                // restart markers. Bytes of 0xff in the data are followed by 0x00.
+                if marker == 0xda {
+                    while *jpeg.get(position)? != 0xff
+                        || matches!(jpeg.get(position + 1)?, 0x00 | 0xd0..=0xd7)
+                    {
+                        position += 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the ranges of the JPEG images appended to a JPEG file after its
+/// primary image, such as the depth maps and alternate frames of portrait
+/// photos taken with phones. Both the Multi-Picture Format and the container
+/// format of Android cameras store the other images this way.
+pub fn embedded_jpeg_images(jpeg: &[u8]) -> Vec<Range<usize>> {
+    let mut images: Vec<Range<usize>> = vec![];
+    let Some(mut position) = jpeg_image_end(jpeg, 0) else {
+        return images;
+    };
+
+    while let Some(offset) = jpeg[position..]
+        .windows(3)
+        .position(|bytes: &[u8]| bytes == [0xff, 0xd8, 0xff])
+    {
+        let start: usize = position + offset;
+        let Some(end) = jpeg_image_end(jpeg, start) else {
+            break;
+        };
+        images.push(start..end);
+        position = end;
+    }
+    images
+}
+
+/// Returns all properties found in the XMP packet given. IPTC
+/// properties are included since they are embedded as XMP.
+///
+/// This is not a complete XML parser; it reads properties written in
+/// attribute form (`ns:Name="value"`) and element form (`<ns:Name>value</ns:Name>`),
+/// and collects `rdf:li` items under their enclosing property name.
+/// Entity and character references in values are decoded.
+pub fn extract_xmp_tags(xmp_bytes: &[u8]) -> Vec<MetadataTag> {
+    let xmp: String = String::from_utf8_lossy(xmp_bytes).to_string();
+    let mut tags: Vec<MetadataTag> = vec![];
+    let mut current_property: Option<&str> = None;
+
+    let is_property = |name: &str| -> bool {
+        name.contains(':')
+            && !name.starts_with("xmlns")
+            && !name.starts_with("rdf:")
+            && !name.starts_with("x:")
+    };
+
+    let mut push_tag = |key: &str, value: &str| {
+        let value: &str = value.trim();
+        if !value.is_empty() {
+            tags.push(MetadataTag {
+                standard: MetadataStandard::Xmp,
+                key: key.to_string(),
+                value: decode_xml_text(value),
+            });
+        }
+    };
+
+    for (tag_start, _) in xmp.match_indices('<') {
+        let rest: &str = &xmp[tag_start + 1..];
+
+        if rest.starts_with('/') || rest.starts_with('?') || rest.starts_with('!') {
+            continue;
+        }
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        let tag_body: &str = rest[..tag_end].trim_end_matches('/');
+        let name: &str = tag_body.split_whitespace().next().unwrap_or_default();
+
+        // Attribute form, e.g. `<rdf:Description xmp:Rating="5">`
+        let mut attributes: &str = &tag_body[name.len()..];
+
+        while let Some(eq_index) = attributes.find("=\"") {
+            let key: &str = attributes[..eq_index].trim();
+            let value_rest: &str = &attributes[eq_index + 2..];
+
+            let Some(value_end) = value_rest.find('"') else {
+                break;
+            };
+            if is_property(key) {
+                push_tag(key, &value_rest[..value_end]);
+            }
+            attributes = &value_rest[value_end + 1..];
+        }
+
+        if is_property(name) {
+            current_property = Some(name);
+        }
+
+        // Element form, e.g. `<xmp:CreatorTool>GIMP</xmp:CreatorTool>`
+        let text: &str = &rest[tag_end + 1..];
+        if let Some(text_end) = text.find('<') {
+            let closing_tag: String = format!("</{}>", name);
+
+            if text[text_end..].starts_with(&closing_tag) {
+                if is_property(name) {
+                    push_tag(name, &text[..text_end]);
+                } else if name == "rdf:li" {
+                    if let Some(property) = current_property {
+                        push_tag(property, &text[..text_end]);
+                    }
+                }
+            }
+        }
+    }
+    tags
+}
+
+/// Replaces the predefined XML entities, such as `&amp;`, and character
+/// references, such as `&#233;`, with the characters that they stand for.
+/// Malformed references are kept as is.
+fn decode_xml_text(text: &str) -> String {
+    let mut decoded: String = String::with_capacity(text.len());
+    let mut rest: &str = text;
+
+    while let Some(amp_index) = rest.find('&') {
+        decoded.push_str(&rest[..amp_index]);
+        rest = &rest[amp_index..];
+
+        let reference: Option<(char, usize)> = rest.find(';').and_then(|end: usize| {
+            let character: Option<char> = match &rest[1..end] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                name => name
+                    .strip_prefix('#')
+                    .and_then(|number: &str| match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => number.parse::<u32>().ok(),
+                    })
+                    .and_then(char::from_u32),
+            };
+            character.map(|character: char| (character, end))
+        });
+
+        if let Some((character, end)) = reference {
+            decoded.push(character);
+            rest = &rest[end + 1..];
+        } else {
+            decoded.push('&');
+            rest = &rest[1..];
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Returns `true` if the XMP packet given marks the photo as a 360° photo
+/// sphere, which is stored in the equirectangular projection.
+#[cfg(feature = "use-photo-sphere")]
+pub fn is_equirectangular(xmp_bytes: &[u8]) -> bool {
+    extract_xmp_tags(xmp_bytes)
+        .iter()
+        .any(|tag: &MetadataTag| tag.key == "GPano:ProjectionType" && tag.value == "equirectangular")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Returns the start of a JPEG file with an APP1 segment of EXIF data, in which
+    /// the primary image has the given orientation and the thumbnail is `thumbnail`.
+    fn jpeg_with_thumbnail(thumbnail: &[u8], orientation: u16) -> Vec<u8> {
+        let entry = |tag: u16, kind: u16, value: u32| -> Vec<u8> {
+            [
+                &tag.to_be_bytes()[..],
+                &kind.to_be_bytes(),
+                &1_u32.to_be_bytes(),
+                &value.to_be_bytes(),
+            ]
+            .concat()
+        };
+        // TIFF header, IFD0 with the orientation at offset 8, and IFD1 at offset 26.
+        let mut tiff: Vec<u8> = b"MM\0\x2a\0\0\0\x08".to_vec();
+        tiff.extend(1_u16.to_be_bytes());
+        tiff.extend(entry(0x112, 3, (orientation as u32) << 16));
+        tiff.extend(26_u32.to_be_bytes());
+        tiff.extend(2_u16.to_be_bytes());
+        tiff.extend(entry(0x201, 4, 56));
+        tiff.extend(entry(0x202, 4, thumbnail.len() as u32));
+        tiff.extend(0_u32.to_be_bytes());
+        tiff.extend(thumbnail);
+
+        let mut jpeg: Vec<u8> = vec![0xff, 0xd8, 0xff, 0xe1];
+        jpeg.extend((tiff.len() as u16 + 8).to_be_bytes());
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(tiff);
+        // The start of the image data, which is cut off.
+        jpeg.extend([0xff, 0xdb, 0x00, 0x43]);
+        jpeg
+    }
+
+    #[test]
+    fn embedded_thumbnail_from_file_head() {
+        let jpeg: Vec<u8> = jpeg_with_thumbnail(b"thumbnail", 6);
+
+        assert_eq!(embedded_jpeg_thumbnail(&jpeg), Some((b"thumbnail".to_vec(), 6)));
+        assert_eq!(embedded_jpeg_thumbnail(&jpeg[..20]), None);
+        assert_eq!(embedded_jpeg_thumbnail(b"not a jpeg"), None);
+    }
+
+    #[test]
+    fn gps_coordinates() {
+        assert_eq!(gps_coordinate([48.0, 51.0, 36.0], "N"), Some(48.86));
+        assert_eq!(gps_coordinate([2.0, 21.0, 0.0], "W"), Some(-2.35));
+        assert_eq!(gps_coordinate([f64::NAN, 0.0, 0.0], "N"), None);
+    }
+
+    #[test]
+    fn camera_names() {
+        assert_eq!(camera_name("Canon", "Canon EOS R6"), Some("Canon EOS R6".into()));
+        assert_eq!(
+            camera_name("NIKON CORPORATION", "NIKON D750"),
+            Some("NIKON D750".into())
+        );
+        assert_eq!(camera_name("Apple", "iPhone 12 "), Some("Apple iPhone 12".into()));
+        assert_eq!(camera_name("", "Pixel 7"), Some("Pixel 7".into()));
+        assert_eq!(camera_name("Google", ""), Some("Google".into()));
+        assert_eq!(camera_name(" ", ""), None);
+    }
+
+    fn date_time(year: u16, hour: u8) -> exif::DateTime {
+        exif::DateTime {
+            year,
+            month: 6,
+            day: 15,
+            hour,
+            minute: 30,
+            second: 0,
+            nanosecond: None,
+            offset: None,
+        }
+    }
+
+    fn assert_date_time(contents: &[u8], year: u16, hour: u8) {
+        let read: exif::DateTime =
+            read_date_time_original(contents).expect("DateTimeOriginal was not written");
+
+        assert_eq!((read.year, read.month, read.day), (year, 6, 15));
+        assert_eq!((read.hour, read.minute, read.second), (hour, 30, 0));
+    }
+
+    #[test]
+    fn date_time_original_keeps_existing_exif() {
+        let jpeg: Vec<u8> = jpeg_with_thumbnail(b"thumbnail", 6);
+        let written: Vec<u8> = write_date_time_original(&jpeg, &date_time(1998, 9)).unwrap();
+
+        assert_date_time(&written, 1998, 9);
+        assert_eq!(
+            embedded_jpeg_thumbnail(&written),
+            Some((b"thumbnail".to_vec(), 6))
+        );
+        assert!(written.ends_with(&[0xff, 0xdb, 0x00, 0x43]));
+
+        // Setting the date again replaces the value instead of adding another segment.
+        let rewritten: Vec<u8> = write_date_time_original(&written, &date_time(2001, 18)).unwrap();
+
+        assert_date_time(&rewritten, 2001, 18);
+        assert_eq!(rewritten.len(), written.len());
+    }
+
+    #[test]
+    fn date_time_original_adds_exif_segment() {
+        // A JFIF segment, followed by the start of the image data.
+        let mut jpeg: Vec<u8> = vec![0xff, 0xd8, 0xff, 0xe0, 0x00, 0x07];
+        jpeg.extend(b"JFIF\0");
+        jpeg.extend([0xff, 0xdb, 0x00, 0x43]);
+
+        let written: Vec<u8> = write_date_time_original(&jpeg, &date_time(1987, 7)).unwrap();
 
-    Ok((metadata, format!("{:x}", md5_hasher.finalize())))
+        assert_date_time(&written, 1987, 7);
+        assert!(written.starts_with(&jpeg[..11]));
+        assert_eq!(&written[11..13], &[0xff, 0xe1]);
+    }
+
+    /// Returns a JPEG file with EXIF data in little endian byte order, in which IFD0 has
+    /// a `DateTime` value, and the Exif IFD has a `DateTimeDigitized` value, a maker note
+    /// with an offset into itself, a tag of an unknown type, and optionally `DateTimeOriginal`.
+    fn jpeg_with_maker_note(date_time_original: bool) -> Vec<u8> {
+        let entry = |tag: u16, kind: u16, count: u32, value: u32| -> Vec<u8> {
+            [
+                &tag.to_le_bytes()[..],
+                &kind.to_le_bytes(),
+                &count.to_le_bytes(),
+                &value.to_le_bytes(),
+            ]
+            .concat()
+        };
+        let exif_entries: u32 = if date_time_original { 4 } else { 3 };
+        let exif_ifd: u32 = 8 + 30 + 20;
+        let digitized: u32 = exif_ifd + 2 + exif_entries * 12 + 4;
+        let original: u32 = digitized + 20;
+        let maker_note: u32 = original + if date_time_original { 20 } else { 0 };
+
+        let mut tiff: Vec<u8> = b"II\x2a\0\x08\0\0\0".to_vec();
+        tiff.extend(2_u16.to_le_bytes());
+        tiff.extend(entry(0x132, 2, 20, 38));
+        tiff.extend(entry(0x8769, 4, 1, exif_ifd));
+        tiff.extend(0_u32.to_le_bytes());
+        tiff.extend(b"2010:01:01 10:00:00\0");
+
+        tiff.extend((exif_entries as u16).to_le_bytes());
+        if date_time_original {
+            tiff.extend(entry(0x9003, 2, 20, original));
+        }
+        tiff.extend(entry(0x9004, 2, 20, digitized));
+        tiff.extend(entry(0x927c, 7, 16, maker_note));
+        tiff.extend(entry(0xfe00, 99, 1, 0x12345678));
+        tiff.extend(0_u32.to_le_bytes());
+        tiff.extend(b"2010:01:01 12:00:00\0");
+        if date_time_original {
+            tiff.extend(b"2010:01:01 11:00:00\0");
+        }
+        tiff.extend(b"Maker\0\0\0");
+        tiff.extend(maker_note.to_le_bytes());
+        tiff.extend(b"note");
+
+        let mut jpeg: Vec<u8> = vec![0xff, 0xd8, 0xff, 0xe1];
+        jpeg.extend((tiff.len() as u16 + 8).to_be_bytes());
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(tiff);
+        jpeg.extend([0xff, 0xdb, 0x00, 0x43]);
+        jpeg
+    }
+
+    /// Returns the contents with the dates of `jpeg_with_maker_note` set to 1998-06-15 09:30.
+    fn with_dates_replaced(contents: &[u8]) -> Vec<u8> {
+        let mut contents: Vec<u8> = contents.to_vec();
+
+        for old_date in [
+            &b"2010:01:01 10:00:00"[..],
+            b"2010:01:01 11:00:00",
+            b"2010:01:01 12:00:00",
+        ] {
+            if let Some(position) = contents
+                .windows(old_date.len())
+                .position(|bytes: &[u8]| bytes == old_date)
+            {
+                contents[position..position + old_date.len()].copy_from_slice(b"1998:06:15 09:30:00");
+            }
+        }
+        contents
+    }
+
+    #[test]
+    fn date_time_original_overwrites_dates_in_place() {
+        let jpeg: Vec<u8> = jpeg_with_maker_note(true);
+        let written: Vec<u8> = write_date_time_original(&jpeg, &date_time(1998, 9)).unwrap();
+
+        // Only the bytes of the three dates change.
+        assert_eq!(written, with_dates_replaced(&jpeg));
+        assert_date_time(&written, 1998, 9);
+    }
+
+    #[test]
+    fn date_time_original_adds_missing_tag() {
+        let jpeg: Vec<u8> = jpeg_with_maker_note(false);
+        let written: Vec<u8> = write_date_time_original(&jpeg, &date_time(1998, 9)).unwrap();
+        let expected: Vec<u8> = with_dates_replaced(&jpeg);
+        let tiff_end: usize = jpeg.len() - 4;
+
+        // Existing bytes are kept, except for the dates, the segment length and the pointer
+        // to the Exif IFD, which points to a copy of the Exif IFD after the existing bytes.
+        let exif_pointer: usize = 10 + 8 + 2 + 12 + 8;
+        assert_eq!(written[..4], expected[..4]);
+        assert_eq!(written[6..exif_pointer], expected[6..exif_pointer]);
+        assert_ne!(
+            written[exif_pointer..exif_pointer + 4],
+            expected[exif_pointer..exif_pointer + 4]
+        );
+        assert_eq!(
+            written[exif_pointer + 4..tiff_end],
+            expected[exif_pointer + 4..tiff_end]
+        );
+        assert!(written.ends_with(&[0xff, 0xdb, 0x00, 0x43]));
+        assert_date_time(&written, 1998, 9);
+
+        let exif_data: exif::Exif = exif::Reader::new()
+            .read_from_container(&mut io::Cursor::new(&written))
+            .unwrap();
+        let maker_note: &exif::Field = exif_data
+            .get_field(exif::Tag::MakerNote, exif::In::PRIMARY)
+            .unwrap();
+        assert!(matches!(
+            &maker_note.value,
+            exif::Value::Undefined(bytes, _) if bytes[..] == jpeg[tiff_end - 16..tiff_end]
+        ));
+    }
+
+    #[test]
+    fn date_time_original_invalid_data() {
+        assert!(write_date_time_original(b"not a jpeg", &date_time(2000, 0)).is_err());
+        assert!(
+            write_date_time_original(&[0xff, 0xd8, 0xff, 0xe1, 0xff, 0xff], &date_time(2000, 0)).is_err()
+        );
+        assert!(read_date_time_original(b"not a jpeg").is_none());
+    }
+
+    /// Returns a JPEG image with an APP1 segment and a scan of the given entropy coded data.
+    fn jpeg_image(app1: &[u8], scan: &[u8]) -> Vec<u8> {
+        let mut jpeg: Vec<u8> = vec![0xff, 0xd8, 0xff, 0xe1];
+        jpeg.extend((app1.len() as u16 + 2).to_be_bytes());
+        jpeg.extend(app1);
+        jpeg.extend([0xff, 0xda, 0x00, 0x02]);
+        jpeg.extend(scan);
+        jpeg.extend([0xff, 0xd9]);
+        jpeg
+    }
+
+    #[test]
+    fn embedded_jpeg_images_after_primary_image() {
+        // The thumbnail in the APP1 segment and the markers in the scan are not images.
+        let thumbnail: Vec<u8> = jpeg_image(b"", &[1, 2]);
+        let primary: Vec<u8> = jpeg_image(&thumbnail, &[0xff, 0x00, 0xff, 0xd0, 3]);
+        let depth_map: Vec<u8> = jpeg_image(b"depth", &[4, 5, 6]);
+        let alternate: Vec<u8> = jpeg_image(b"", &[7, 0xff, 0xd3, 8]);
+
+        let mut contents: Vec<u8> = primary.clone();
+        contents.extend(b"padding");
+        contents.extend(&depth_map);
+        contents.extend(&alternate);
+
+        let depth_start: usize = primary.len() + b"padding".len();
+        let alternate_start: usize = depth_start + depth_map.len();
+
+        assert_eq!(
+            embedded_jpeg_images(&contents),
+            vec![depth_start..alternate_start, alternate_start..contents.len()]
+        );
+        assert!(embedded_jpeg_images(&primary).is_empty());
+    }
+
+    #[test]
+    fn embedded_jpeg_images_invalid_data() {
+        let mut truncated: Vec<u8> = jpeg_image(b"", &[1]);
+        truncated.extend(&jpeg_image(b"", &[2])[..6]);
+
+        assert!(embedded_jpeg_images(&truncated).is_empty());
+        assert!(embedded_jpeg_images(b"not a jpeg").is_empty());
+        assert!(embedded_jpeg_images(&[0xff, 0xd8, 0xff, 0xda, 0x00]).is_empty());
+    }
+
+    fn xmp_tag(key: &str, value: &str) -> MetadataTag {
+        MetadataTag {
+            standard: MetadataStandard::Xmp,
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn xmp_attribute_properties() {
+        let xmp = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF>
+            <rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:Rating="4"
+                xmp:CreatorTool="GIMP 2.10"/>
+            </rdf:RDF></x:xmpmeta>"#;
+
+        assert_eq!(
+            extract_xmp_tags(xmp),
+            vec![
+                xmp_tag("xmp:Rating", "4"),
+                xmp_tag("xmp:CreatorTool", "GIMP 2.10")
+            ]
+        );
+    }
+
+    #[test]
+    fn xmp_element_properties() {
+        let xmp = br#"<rdf:Description><photoshop:City>Tokyo</photoshop:City>
+            <dc:subject><rdf:Bag><rdf:li>cat</rdf:li><rdf:li>night</rdf:li></rdf:Bag></dc:subject>
+            </rdf:Description>"#;
+
+        assert_eq!(
+            extract_xmp_tags(xmp),
+            vec![
+                xmp_tag("photoshop:City", "Tokyo"),
+                xmp_tag("dc:subject", "cat"),
+                xmp_tag("dc:subject", "night"),
+            ]
+        );
+    }
+
+    #[test]
+    fn xmp_entity_references() {
+        let xmp = br#"<rdf:Description xmp:Label="R&amp;D &#8220;draft&#x201D;">
+            <dc:subject><rdf:Bag><rdf:li>Tom &amp; Jerry</rdf:li><rdf:li>&lt;3 &amp lost</rdf:li></rdf:Bag></dc:subject>
+            </rdf:Description>"#;
+
+        assert_eq!(
+            extract_xmp_tags(xmp),
+            vec![
+                xmp_tag("xmp:Label", "R&D \u{201c}draft\u{201d}"),
+                xmp_tag("dc:subject", "Tom & Jerry"),
+                xmp_tag("dc:subject", "<3 &amp lost"),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "use-photo-sphere")]
+    fn xmp_equirectangular_projection() {
+        let attribute = br#"<rdf:Description xmlns:GPano="http://ns.google.com/photos/1.0/panorama/"
+            GPano:ProjectionType="equirectangular" GPano:UsePanoramaViewer="True"/>"#;
+        let element = br#"<rdf:Description>
+            <GPano:ProjectionType>equirectangular</GPano:ProjectionType></rdf:Description>"#;
+        let cylindrical = br#"<rdf:Description GPano:ProjectionType="cylindrical"/>"#;
+
+        assert!(is_equirectangular(attribute));
+        assert!(is_equirectangular(element));
+        assert!(!is_equirectangular(cylindrical));
+        assert!(!is_equirectangular(b"not xmp"));
+    }
+
+    #[test]
+    fn xmp_invalid_data() {
+        assert!(extract_xmp_tags(b"not xmp").is_empty());
+        assert!(extract_xmp_tags(b"<unterminated").is_empty());
+    }
+
+    #[test]
+    fn exif_invalid_data() {
+        assert!(extract_exif_tags(b"not exif".to_vec()).is_empty());
+    }
+
+    #[test]
+    fn thumbnail_cache_key_changes_with_file() {
+        let modified: SystemTime = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let key: String = thumbnail_cache_key("file:///photo.jpg", 1024, modified);
+        let later: SystemTime = modified + Duration::from_secs(1);
+
+        assert_eq!(key, thumbnail_cache_key("file:///photo.jpg", 1024, modified));
+        assert_eq!(key.len(), 32);
+        assert_ne!(key, thumbnail_cache_key("file:///other.jpg", 1024, modified));
+        assert_ne!(key, thumbnail_cache_key("file:///photo.jpg", 2048, modified));
+        assert_ne!(key, thumbnail_cache_key("file:///photo.jpg", 1024, later));
+    }
 }
@@ -20,5 +20,30 @@
 
 //! Utility functions used at seldom in Memories source.
 
+pub mod background;
+pub mod checksum;
+pub mod content_hash;
+pub mod crash;
+#[cfg(feature = "use-embeddings")]
+pub mod embeddings;
 pub mod enums;
+pub mod feedback;
+pub mod format;
+pub mod gpu;
+pub mod hwaccel;
+#[cfg(feature = "use-libav")]
+pub mod libav;
+pub mod media_database;
 pub mod metadata;
+pub mod mounts;
+pub mod ocr;
+pub mod passphrase;
+pub mod phash;
+pub mod power;
+pub mod preview;
+pub mod process;
+pub mod profiling;
+pub mod shortcuts;
+pub mod storage;
+pub mod wallpaper;
+pub mod xmp;
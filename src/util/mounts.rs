@@ -0,0 +1,99 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Mounting the drives and network locations of library folders
+//! that are not available, such as folders on an unplugged drive.
+
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use std::path::{Path, PathBuf};
+
+/// URI schemes of the GVFS backends that mount phones and cameras connected
+/// over USB, such as Android phones over MTP and iPhones over PTP.
+const DEVICE_URI_SCHEMES: &[&str] = &["mtp", "gphoto2"];
+
+/// Returns an unmounted volume that the given folder is likely to be on,
+/// going by where removable drives are mounted, or by the activation root
+/// of the volume for network locations.
+pub fn volume_for_folder(folder: &gio::File) -> Option<gio::Volume> {
+    let path: Option<PathBuf> = folder.path();
+    let user_name: String = glib::user_name().to_string_lossy().to_string();
+
+    gio::VolumeMonitor::get()
+        .volumes()
+        .into_iter()
+        .filter(|volume: &gio::Volume| volume.get_mount().is_none() && volume.can_mount())
+        .find(|volume: &gio::Volume| {
+            if let Some(root) = volume.activation_root() {
+                return folder.has_prefix(&root) || folder.equal(&root);
+            }
+            let Some(path) = path.as_deref() else {
+                return false;
+            };
+            let name: String = volume.name().to_string();
+
+            // Drives are mounted by udisks under the name of their volume.
+            [
+                Path::new("/run/media").join(&user_name),
+                Path::new("/media").join(&user_name),
+            ]
+            .iter()
+            .any(|media_dir: &PathBuf| path.starts_with(media_dir.join(&name)))
+        })
+}
+
+/// Returns whether a mount is on removable media, such as
+/// a memory card, a USB drive, or a camera connected over USB.
+pub fn is_removable_mount(mount: &gio::Mount) -> bool {
+    mount.can_eject()
+        || mount
+            .drive()
+            .is_some_and(|drive: gio::Drive| drive.is_removable() || drive.is_media_removable())
+}
+
+/// Returns whether a mount is a phone or camera mounted by GVFS over MTP or PTP,
+/// which has no drive, and may keep its DCIM folder in one of its storages.
+pub fn is_device_mount(mount: &gio::Mount) -> bool {
+    mount
+        .root()
+        .uri_scheme()
+        .is_some_and(|scheme: glib::GString| DEVICE_URI_SCHEMES.contains(&scheme.as_str()))
+}
+
+/// Returns whether Memories can try to mount the given folder.
+pub fn can_mount_folder(folder: &gio::File) -> bool {
+    !folder.is_native() || volume_for_folder(folder).is_some()
+}
+
+/// Mounts the volume of the given folder, prompting the user for any
+/// passwords or questions with dialogs over the given window.
+pub async fn mount_folder(folder: &gio::File, window: &impl IsA<gtk::Window>) -> Result<(), glib::Error> {
+    let mount_operation: gtk::MountOperation = gtk::MountOperation::new(Some(window));
+
+    if let Some(volume) = volume_for_folder(folder) {
+        volume
+            .mount_future(gio::MountMountFlags::NONE, Some(&mount_operation))
+            .await
+    } else {
+        folder
+            .mount_enclosing_volume_future(gio::MountMountFlags::NONE, Some(&mount_operation))
+            .await
+    }
+}
@@ -0,0 +1,163 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Text recognized in screenshots, so that screenshots can be found by
+//! searching for the text shown in them. Text is recognized on the device
+//! with Tesseract when Memories is built with the `use-ocr` feature, and
+//! is kept in an index in the app cache directory. Recognizing text takes
+//! a while, so it never runs on the main thread.
+
+use crate::application::MemoriesApplication;
+use crate::globals::CACHE_TEXT_INDEX_FILE;
+#[cfg(feature = "use-ocr")]
+use crate::globals::{DEFAULT_SCREENSHOTS_REL_DIR, OCR_LANGUAGES};
+use glib::g_warning;
+use gtk::glib;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Prefixes of the file names that screenshot tools give to screenshots.
+#[cfg(feature = "use-ocr")]
+const SCREENSHOT_NAME_PREFIXES: &[&str] = &["screenshot", "screen shot", "screen_shot", "scrot"];
+
+/// Text recognized in screenshots by file URI. Screenshots that were
+/// scanned and have no text in them have an empty string, so that
+/// they are not scanned again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TextIndex {
+    texts: HashMap<String, String>,
+}
+
+impl TextIndex {
+    /// Loads the text index from disk, or returns an empty index if there is none.
+    pub fn load() -> Self {
+        let contents: Vec<u8> = match std::fs::read(text_index_path()) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => {
+                g_warning!("OCR", "Failed to read text index: {}", err);
+                return Self::default();
+            }
+        };
+        serde_json::from_slice(&contents).unwrap_or_else(|err| {
+            g_warning!("OCR", "Failed to parse text index: {}", err);
+            Self::default()
+        })
+    }
+
+    #[cfg(feature = "use-ocr")]
+    pub fn save(&self) -> std::io::Result<()> {
+        let path: PathBuf = text_index_path();
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    /// Returns the text recognized in a screenshot, in lowercase.
+    pub fn get(&self, uri: &str) -> Option<&str> {
+        self.texts.get(uri).map(String::as_str)
+    }
+
+    #[cfg(feature = "use-ocr")]
+    pub fn contains(&self, uri: &str) -> bool {
+        self.texts.contains_key(uri)
+    }
+
+    #[cfg(feature = "use-ocr")]
+    pub fn insert(&mut self, uri: String, text: &str) {
+        self.texts.insert(uri, normalize_text(text));
+    }
+}
+
+fn text_index_path() -> PathBuf {
+    Path::new(&MemoriesApplication::get_app_cache_directory()).join(CACHE_TEXT_INDEX_FILE)
+}
+
+/// Returns `true` if the image is likely a screenshot, because it is in
+/// a screenshots folder, or is named like screenshot tools name them.
+#[cfg(feature = "use-ocr")]
+pub fn is_screenshot(path: &Path) -> bool {
+    let in_screenshots_folder: bool = path
+        .parent()
+        .and_then(Path::file_name)
+        .is_some_and(|folder| folder.eq_ignore_ascii_case(DEFAULT_SCREENSHOTS_REL_DIR));
+
+    let file_name: String = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    in_screenshots_folder
+        || SCREENSHOT_NAME_PREFIXES
+            .iter()
+            .any(|prefix: &&str| file_name.starts_with(prefix))
+}
+
+/// Returns the text recognized in an image with Tesseract.
+#[cfg(feature = "use-ocr")]
+pub fn recognize_text(path: &Path) -> Result<String, String> {
+    let path: &str = path.to_str().ok_or("The image path is not valid UTF-8")?;
+
+    tesseract::ocr(path, OCR_LANGUAGES).map_err(|err| err.to_string())
+}
+
+/// Lowercases recognized text for searching, and joins its
+/// lines and words with single spaces, so that a search can
+/// match words that were recognized on separate lines.
+#[cfg(feature = "use-ocr")]
+fn normalize_text(text: &str) -> String {
+    text.split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(all(test, feature = "use-ocr"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screenshot_paths() {
+        assert!(is_screenshot(Path::new(
+            "/home/user/Pictures/Screenshots/image.png"
+        )));
+        assert!(is_screenshot(Path::new(
+            "/home/user/Pictures/Screenshot From 2024-05-01.png"
+        )));
+        assert!(is_screenshot(Path::new(
+            "/home/user/Desktop/Screen Shot 2024-05-01.png"
+        )));
+        assert!(!is_screenshot(Path::new(
+            "/home/user/Pictures/Camera/IMG_0001.jpg"
+        )));
+    }
+
+    #[test]
+    fn normalized_text() {
+        assert_eq!(
+            normalize_text("  Error:\n  File NOT\tfound \n"),
+            "error: file not found"
+        );
+        assert_eq!(normalize_text("\n \n"), "");
+    }
+}
@@ -0,0 +1,101 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Hashing and verification of the passphrase that locks the Private album.
+//! Passphrases are salted and hashed with many rounds of SHA-256, so that
+//! the stored hash is slow to brute force if it is read from GSettings.
+
+use gtk::glib;
+
+/// Rounds of SHA-256 that a salted passphrase is hashed with.
+const HASH_ROUNDS: u32 = 100_000;
+/// Bytes of randomness in the salt of a stored passphrase hash.
+const SALT_BYTES: usize = 16;
+/// Separates the salt from the hash in a stored passphrase hash.
+const SEPARATOR: char = '$';
+
+/// Returns a new stored hash of `passphrase`, with a random salt read from
+/// the operating system's secure random number generator, which can fail.
+pub fn hash_passphrase(passphrase: &str) -> Result<String, getrandom::Error> {
+    let mut salt_bytes: [u8; SALT_BYTES] = [0; SALT_BYTES];
+    getrandom::getrandom(&mut salt_bytes)?;
+
+    let salt: String = salt_bytes
+        .iter()
+        .map(|byte: &u8| format!("{:02x}", byte))
+        .collect();
+
+    Ok(format!("{}{}{}", salt, SEPARATOR, salted_hash(passphrase, &salt)))
+}
+
+/// Returns whether `passphrase` matches a hash returned by `hash_passphrase()`.
+pub fn verify_passphrase(passphrase: &str, stored: &str) -> bool {
+    let Some((salt, hash)) = stored.split_once(SEPARATOR) else {
+        return false;
+    };
+    let computed: String = salted_hash(passphrase, salt);
+
+    // Compare every byte so that the time taken does not leak the matching prefix.
+    computed.len() == hash.len()
+        && computed
+            .bytes()
+            .zip(hash.bytes())
+            .fold(0, |diff: u8, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn salted_hash(passphrase: &str, salt: &str) -> String {
+    let mut digest: String = format!("{}{}", salt, passphrase);
+
+    for _ in 0..HASH_ROUNDS {
+        digest = glib::compute_checksum_for_string(glib::ChecksumType::Sha256, &digest)
+            .map(|checksum: glib::GString| checksum.to_string())
+            .unwrap_or_default();
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_hashed_passphrase() {
+        let stored: String = hash_passphrase("correct horse").unwrap();
+
+        assert!(verify_passphrase("correct horse", &stored));
+        assert!(!verify_passphrase("correct horse ", &stored));
+        assert!(!verify_passphrase("", &stored));
+    }
+
+    #[test]
+    fn salts_every_hash() {
+        assert_ne!(
+            hash_passphrase("secret").unwrap(),
+            hash_passphrase("secret").unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_hash() {
+        assert!(!verify_passphrase("secret", ""));
+        assert!(!verify_passphrase("secret", "no-separator"));
+    }
+}
@@ -0,0 +1,182 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Perceptual hashes of photos, which are alike for photos that look alike,
+//! such as near-duplicate photos taken in a burst. Photos are hashed with the
+//! difference hash (dHash) of their thumbnail, and hashes are compared by
+//! their Hamming distance, the amount of bits that differ between them.
+//! Thumbnails are loaded from disk to be hashed, off the main thread.
+
+use crate::application::MemoriesApplication;
+use crate::globals::CACHE_PERCEPTUAL_HASH_INDEX_FILE;
+use glib::g_warning;
+use gtk::gdk_pixbuf::{InterpType, Pixbuf};
+use gtk::glib;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Width and height that images are downscaled to before hashing. Each row
+/// gives 8 bits by comparing its 9 neighboring pixels, making a 64-bit hash.
+const HASH_SAMPLE_SIZE: (usize, usize) = (9, 8);
+
+/// Perceptual hashes of the photos in the library by file URI.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PerceptualHashIndex {
+    hashes: HashMap<String, u64>,
+}
+
+impl PerceptualHashIndex {
+    /// Loads the perceptual hash index from disk, or returns an empty index if there is none.
+    pub fn load() -> Self {
+        let contents: Vec<u8> = match std::fs::read(hash_index_path()) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => {
+                g_warning!("PerceptualHash", "Failed to read perceptual hash index: {}", err);
+                return Self::default();
+            }
+        };
+        serde_json::from_slice(&contents).unwrap_or_else(|err| {
+            g_warning!("PerceptualHash", "Failed to parse perceptual hash index: {}", err);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path: PathBuf = hash_index_path();
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    pub fn contains(&self, uri: &str) -> bool {
+        self.hashes.contains_key(uri)
+    }
+
+    pub fn insert(&mut self, uri: String, hash: u64) {
+        self.hashes.insert(uri, hash);
+    }
+
+    /// Returns the URIs of the photos whose hash is at most `max_distance` bits away
+    /// from the hash of the photo at `uri`, from the most to the least similar.
+    pub fn similar(&self, uri: &str, max_distance: u32, limit: usize) -> Vec<String> {
+        let Some(hash) = self.hashes.get(uri) else {
+            return vec![];
+        };
+        let mut similar: Vec<(&String, u32)> = self
+            .hashes
+            .iter()
+            .filter(|(other_uri, _): &(&String, &u64)| other_uri.as_str() != uri)
+            .map(|(other_uri, other_hash): (&String, &u64)| (other_uri, hamming_distance(*hash, *other_hash)))
+            .filter(|(_, distance): &(&String, u32)| *distance <= max_distance)
+            .collect();
+
+        // Sort by URI too, so that equally similar photos keep their order.
+        similar.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+        similar
+            .into_iter()
+            .take(limit)
+            .map(|(other_uri, _): (&String, u32)| other_uri.clone())
+            .collect()
+    }
+}
+
+fn hash_index_path() -> PathBuf {
+    Path::new(&MemoriesApplication::get_app_cache_directory()).join(CACHE_PERCEPTUAL_HASH_INDEX_FILE)
+}
+
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Returns the perceptual hash of a thumbnail image file.
+pub fn thumbnail_hash(path: &Path) -> Result<u64, glib::Error> {
+    let (width, height): (usize, usize) = HASH_SAMPLE_SIZE;
+    let pixbuf: Pixbuf = Pixbuf::from_file(path)?;
+    let sample: Pixbuf = pixbuf
+        .scale_simple(width as i32, height as i32, InterpType::Bilinear)
+        .unwrap_or(pixbuf);
+
+    let bytes: glib::Bytes = sample.read_pixel_bytes();
+    let rowstride: usize = sample.rowstride() as usize;
+    let n_channels: usize = sample.n_channels() as usize;
+
+    let luma: Vec<u32> = (0..height)
+        .flat_map(|y: usize| (0..width).map(move |x: usize| y * rowstride + x * n_channels))
+        .map(|i: usize| {
+            (299 * bytes[i] as u32 + 587 * bytes[i + 1] as u32 + 114 * bytes[i + 2] as u32) / 1000
+        })
+        .collect();
+
+    Ok(difference_hash(&luma, width))
+}
+
+/// Returns the difference hash of the luma values of a downscaled image, `width`
+/// values per row. Each bit tells if a pixel is brighter than the pixel on its right.
+fn difference_hash(luma: &[u32], width: usize) -> u64 {
+    luma.chunks_exact(width)
+        .flat_map(|row: &[u32]| row.windows(2).map(|pair: &[u32]| pair[0] > pair[1]))
+        .take(64)
+        .fold(0, |hash: u64, brighter: bool| (hash << 1) | brighter as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn difference_hashes() {
+        let increasing: Vec<u32> = (0..72).map(|i: u32| i % 9).collect();
+        let decreasing: Vec<u32> = (0..72).map(|i: u32| 8 - i % 9).collect();
+
+        assert_eq!(difference_hash(&increasing, 9), 0);
+        assert_eq!(difference_hash(&decreasing, 9), u64::MAX);
+
+        let mut one_pixel_brighter: Vec<u32> = increasing.clone();
+        one_pixel_brighter[0] = 5;
+        assert_eq!(
+            hamming_distance(
+                difference_hash(&increasing, 9),
+                difference_hash(&one_pixel_brighter, 9)
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn similar_photos() {
+        let mut index: PerceptualHashIndex = PerceptualHashIndex::default();
+        index.insert("file:///a.jpg".into(), 0b0000);
+        index.insert("file:///b.jpg".into(), 0b0111);
+        index.insert("file:///c.jpg".into(), 0b0001);
+        index.insert("file:///d.jpg".into(), u64::MAX);
+
+        assert_eq!(
+            index.similar("file:///a.jpg", 3, 10),
+            vec!["file:///c.jpg", "file:///b.jpg"]
+        );
+        assert_eq!(index.similar("file:///a.jpg", 3, 1), vec!["file:///c.jpg"]);
+        assert!(index.similar("file:///d.jpg", 3, 10).is_empty());
+        assert!(index.similar("file:///missing.jpg", 64, 10).is_empty());
+    }
+}
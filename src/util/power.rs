@@ -0,0 +1,35 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Power saving, which throttles the work Memories does in the
+//! background while the system's power saver mode is active.
+
+use crate::application::MemoriesApplication;
+use adw::prelude::*;
+use gtk::gio;
+
+/// Returns whether background work should be throttled, because power
+/// saver mode is active and throttling is enabled in the preferences.
+pub fn should_throttle() -> bool {
+    let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+
+    gsettings.boolean("power-saver-throttling")
+        && gio::PowerProfileMonitor::get_default().is_power_saver_enabled()
+}
@@ -0,0 +1,321 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Low fidelity previews of media, shown in place of thumbnails while the
+//! thumbnails are being loaded. Previews are encoded as BlurHash strings,
+//! which are small enough to keep an index of for the whole library.
+//! See <https://github.com/woltapp/blurhash> for the algorithm. Thumbnails are
+//! read from disk to be encoded, so they are encoded on a separate thread.
+
+use crate::application::MemoriesApplication;
+use crate::globals::CACHE_PREVIEW_INDEX_FILE;
+use glib::g_warning;
+use gtk::gdk_pixbuf::{InterpType, Pixbuf};
+use gtk::prelude::*;
+use gtk::{gdk, glib};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::path::{Path, PathBuf};
+
+/// Amount of horizontal and vertical components encoded in a preview.
+const COMPONENTS: (usize, usize) = (4, 3);
+/// Edge length in pixels that a thumbnail is downscaled to before
+/// encoding, since only its lowest frequencies are encoded anyway.
+const ENCODE_SAMPLE_SIZE: i32 = 32;
+/// Edge length in pixels of the texture that a preview is decoded to.
+const DECODE_SIZE: usize = 16;
+
+const BASE83_CHARACTERS: &[u8; 83] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+/// Previews of the media in the library by file URI, persisted in the
+/// app cache directory so that they can be shown instantly on startup.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PreviewIndex {
+    previews: HashMap<String, String>,
+}
+
+impl PreviewIndex {
+    /// Loads the preview index from disk, or returns an empty index if there is none.
+    pub fn load() -> Self {
+        let contents: Vec<u8> = match std::fs::read(preview_index_path()) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => {
+                g_warning!("Preview", "Failed to read preview index: {}", err);
+                return Self::default();
+            }
+        };
+        serde_json::from_slice(&contents).unwrap_or_else(|err| {
+            g_warning!("Preview", "Failed to parse preview index: {}", err);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path: PathBuf = preview_index_path();
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    pub fn get(&self, uri: &str) -> Option<&str> {
+        self.previews.get(uri).map(String::as_str)
+    }
+
+    pub fn insert(&mut self, uri: String, blurhash: String) {
+        self.previews.insert(uri, blurhash);
+    }
+
+    pub fn remove(&mut self, uri: &str) {
+        self.previews.remove(uri);
+    }
+}
+
+fn preview_index_path() -> PathBuf {
+    Path::new(&MemoriesApplication::get_app_cache_directory()).join(CACHE_PREVIEW_INDEX_FILE)
+}
+
+/// Returns the BlurHash of a thumbnail image file.
+pub fn thumbnail_blurhash(path: &Path) -> Result<String, glib::Error> {
+    let pixbuf: Pixbuf = Pixbuf::from_file(path)?;
+    let sample: Pixbuf = pixbuf
+        .scale_simple(ENCODE_SAMPLE_SIZE, ENCODE_SAMPLE_SIZE, InterpType::Bilinear)
+        .unwrap_or(pixbuf);
+
+    let bytes: glib::Bytes = sample.read_pixel_bytes();
+
+    Ok(encode_blurhash(
+        &bytes,
+        sample.width() as usize,
+        sample.height() as usize,
+        sample.rowstride() as usize,
+        sample.n_channels() as usize,
+    ))
+}
+
+/// Returns a small texture of a decoded BlurHash, which is
+/// stretched over the area of a thumbnail as its placeholder.
+pub fn blurhash_texture(blurhash: &str) -> Option<gdk::Texture> {
+    let pixels: Vec<u8> = decode_blurhash(blurhash, DECODE_SIZE, DECODE_SIZE)?;
+    let bytes: glib::Bytes = glib::Bytes::from_owned(pixels);
+
+    let texture = gdk::MemoryTexture::new(
+        DECODE_SIZE as i32,
+        DECODE_SIZE as i32,
+        gdk::MemoryFormat::R8g8b8,
+        &bytes,
+        DECODE_SIZE * 3,
+    );
+    Some(texture.upcast())
+}
+
+/// Encodes packed 8-bit RGB(A) pixel data as a BlurHash string.
+pub fn encode_blurhash(
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+    rowstride: usize,
+    n_channels: usize,
+) -> String {
+    let (components_x, components_y) = COMPONENTS;
+    let mut factors: Vec<[f64; 3]> = Vec::with_capacity(components_x * components_y);
+
+    for j in 0..components_y {
+        for i in 0..components_x {
+            let normalisation: f64 = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            let mut factor: [f64; 3] = [0.0; 3];
+
+            for y in 0..height {
+                for x in 0..width {
+                    let basis: f64 = (PI * i as f64 * x as f64 / width as f64).cos()
+                        * (PI * j as f64 * y as f64 / height as f64).cos();
+                    let offset: usize = y * rowstride + x * n_channels;
+
+                    for (channel, value) in factor.iter_mut().enumerate() {
+                        *value += basis * srgb_to_linear(pixels[offset + channel]);
+                    }
+                }
+            }
+            let scale: f64 = normalisation / (width * height).max(1) as f64;
+            factors.push(factor.map(|value: f64| value * scale));
+        }
+    }
+    let dc: [f64; 3] = factors[0];
+    let ac: &[[f64; 3]] = &factors[1..];
+
+    let mut blurhash: String = String::new();
+    encode_base83(
+        (components_x - 1 + (components_y - 1) * 9) as u32,
+        1,
+        &mut blurhash,
+    );
+
+    let actual_max: f64 = ac
+        .iter()
+        .flatten()
+        .fold(0.0, |max: f64, value: &f64| max.max(value.abs()));
+    let quantised_max: u32 = (actual_max * 166.0 - 0.5).floor().clamp(0.0, 82.0) as u32;
+    let max_value: f64 = (quantised_max + 1) as f64 / 166.0;
+    encode_base83(if ac.is_empty() { 0 } else { quantised_max }, 1, &mut blurhash);
+
+    let dc_value: u32 = (linear_to_srgb(dc[0]) << 16) + (linear_to_srgb(dc[1]) << 8) + linear_to_srgb(dc[2]);
+    encode_base83(dc_value, 4, &mut blurhash);
+
+    for factor in ac {
+        let [r, g, b] = factor.map(|value: f64| {
+            (sign_pow(value / max_value, 0.5) * 9.0 + 9.5)
+                .floor()
+                .clamp(0.0, 18.0) as u32
+        });
+        encode_base83(r * 19 * 19 + g * 19 + b, 2, &mut blurhash);
+    }
+    blurhash
+}
+
+/// Decodes a BlurHash string into packed 8-bit RGB pixel data of the
+/// given size. Returns `None` if the string is not a valid BlurHash.
+pub fn decode_blurhash(blurhash: &str, width: usize, height: usize) -> Option<Vec<u8>> {
+    if !blurhash.is_ascii() || blurhash.len() < 6 {
+        return None;
+    }
+    let size_flag: u32 = decode_base83(&blurhash[0..1])?;
+    let components_x: usize = (size_flag % 9 + 1) as usize;
+    let components_y: usize = (size_flag / 9 + 1) as usize;
+
+    if blurhash.len() != 4 + 2 * components_x * components_y {
+        return None;
+    }
+    let max_value: f64 = (decode_base83(&blurhash[1..2])? + 1) as f64 / 166.0;
+
+    let mut colors: Vec<[f64; 3]> = Vec::with_capacity(components_x * components_y);
+    let dc_value: u32 = decode_base83(&blurhash[2..6])?;
+    colors.push([
+        srgb_to_linear((dc_value >> 16) as u8),
+        srgb_to_linear((dc_value >> 8) as u8),
+        srgb_to_linear(dc_value as u8),
+    ]);
+    for i in 1..components_x * components_y {
+        let ac_value: u32 = decode_base83(&blurhash[4 + i * 2..6 + i * 2])?;
+        let quantised: [u32; 3] = [ac_value / (19 * 19), (ac_value / 19) % 19, ac_value % 19];
+
+        colors.push(quantised.map(|value: u32| sign_pow((value as f64 - 9.0) / 9.0, 2.0) * max_value));
+    }
+
+    let mut pixels: Vec<u8> = Vec::with_capacity(width * height * 3);
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut pixel: [f64; 3] = [0.0; 3];
+
+            for j in 0..components_y {
+                for i in 0..components_x {
+                    let basis: f64 = (PI * x as f64 * i as f64 / width as f64).cos()
+                        * (PI * y as f64 * j as f64 / height as f64).cos();
+                    let color: [f64; 3] = colors[i + j * components_x];
+
+                    for (value, component) in pixel.iter_mut().zip(color) {
+                        *value += component * basis;
+                    }
+                }
+            }
+            pixels.extend(pixel.map(|value: f64| linear_to_srgb(value) as u8));
+        }
+    }
+    Some(pixels)
+}
+
+fn encode_base83(value: u32, length: u32, output: &mut String) {
+    for i in 1..=length {
+        let digit: u32 = (value / 83u32.pow(length - i)) % 83;
+        output.push(BASE83_CHARACTERS[digit as usize] as char);
+    }
+}
+
+fn decode_base83(string: &str) -> Option<u32> {
+    string.bytes().try_fold(0, |value: u32, character: u8| {
+        let digit: usize = BASE83_CHARACTERS.iter().position(|c: &u8| *c == character)?;
+        Some(value * 83 + digit as u32)
+    })
+}
+
+fn srgb_to_linear(value: u8) -> f64 {
+    let value: f64 = value as f64 / 255.0;
+
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> u32 {
+    let value: f64 = value.clamp(0.0, 1.0);
+
+    if value <= 0.0031308 {
+        (value * 12.92 * 255.0 + 0.5) as u32
+    } else {
+        ((1.055 * value.powf(1.0 / 2.4) - 0.055) * 255.0 + 0.5) as u32
+    }
+}
+
+fn sign_pow(value: f64, exponent: f64) -> f64 {
+    value.abs().powf(exponent).copysign(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base83_round_trip() {
+        let mut string: String = String::new();
+        encode_base83(123_456, 4, &mut string);
+
+        assert_eq!(string.len(), 4);
+        assert_eq!(decode_base83(&string), Some(123_456));
+        assert_eq!(decode_base83("\""), None);
+    }
+
+    #[test]
+    fn solid_color_round_trip() {
+        let pixels: Vec<u8> = [200, 100, 50].repeat(8 * 8);
+        let blurhash: String = encode_blurhash(&pixels, 8, 8, 8 * 3, 3);
+
+        assert_eq!(blurhash.len(), 4 + 2 * COMPONENTS.0 * COMPONENTS.1);
+
+        // The average color of the image is encoded as is.
+        assert_eq!(
+            decode_base83(&blurhash[2..6]),
+            Some((200 << 16) + (100 << 8) + 50)
+        );
+        assert_eq!(decode_blurhash(&blurhash, 4, 2).unwrap().len(), 4 * 2 * 3);
+    }
+
+    #[test]
+    fn invalid_blurhashes() {
+        assert_eq!(decode_blurhash("", 4, 4), None);
+        assert_eq!(decode_blurhash("L00000", 4, 4), None);
+        assert_eq!(decode_blurhash("0000é0", 4, 4), None);
+    }
+}
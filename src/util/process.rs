@@ -0,0 +1,85 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Spawning of helper processes, such as ffmpeg for generating thumbnails.
+
+use crate::globals::{IONICE_BINARY, NICE_BINARY, THUMBNAIL_NICE_LEVEL};
+use async_process::Command;
+use gtk::glib;
+
+/// Returns a command that runs the given program at a lower CPU and I/O
+/// priority, so that generating thumbnails does not slow down the desktop.
+/// The program is run as usual if `nice` and `ionice` are not installed.
+pub fn low_priority_command(program: &str) -> Command {
+    let has_nice: bool = glib::find_program_in_path(NICE_BINARY).is_some();
+    let has_ionice: bool = glib::find_program_in_path(IONICE_BINARY).is_some();
+
+    let mut args: Vec<String> = priority_wrapper_args(has_nice, has_ionice);
+    args.push(program.to_owned());
+
+    let mut command: Command = Command::new(&args[0]);
+    command.args(&args[1..]);
+    command
+}
+
+/// Returns the programs and arguments that lower the priority of a
+/// command, given which of `nice` and `ionice` are available.
+fn priority_wrapper_args(has_nice: bool, has_ionice: bool) -> Vec<String> {
+    let mut args: Vec<String> = vec![];
+
+    if has_nice {
+        args.extend([NICE_BINARY.into(), "-n".into(), THUMBNAIL_NICE_LEVEL.to_string()]);
+    }
+    if has_ionice {
+        // The idle class only does I/O when no other program needs the disk.
+        args.extend([IONICE_BINARY.into(), "-c".into(), "3".into()]);
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapper_args_with_both_programs() {
+        assert_eq!(
+            priority_wrapper_args(true, true),
+            [
+                "nice",
+                "-n",
+                &THUMBNAIL_NICE_LEVEL.to_string(),
+                "ionice",
+                "-c",
+                "3"
+            ]
+        );
+    }
+
+    #[test]
+    fn wrapper_args_without_nice() {
+        assert_eq!(priority_wrapper_args(false, true), ["ionice", "-c", "3"]);
+    }
+
+    #[test]
+    fn wrapper_args_without_programs() {
+        assert!(priority_wrapper_args(false, false).is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Startup profiling, built with the `use-profiling` feature. The time taken to
+//! enumerate the library, to generate each thumbnail and to render the first
+//! frame of the window is logged under the "Profiling" log domain, which is
+//! shown with `G_MESSAGES_DEBUG=Profiling`.
+//! Without the feature, profiling does nothing.
+
+use gtk::prelude::*;
+use std::fmt::Display;
+#[cfg(feature = "use-profiling")]
+use std::sync::OnceLock;
+#[cfg(feature = "use-profiling")]
+use std::time::Instant;
+
+/// Time that profiling was started at, close to when the process started.
+#[cfg(feature = "use-profiling")]
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Starts profiling. Should be called as early as possible in `main()`,
+/// since marks are logged with the time passed since this call.
+pub fn init() {
+    #[cfg(feature = "use-profiling")]
+    STARTED.get_or_init(Instant::now);
+}
+
+/// Measures a stage of work from when it is started until it is dropped,
+/// and logs the time taken once it is dropped.
+#[derive(Debug)]
+pub struct ProfileSpan {
+    #[cfg(feature = "use-profiling")]
+    stage: &'static str,
+    #[cfg(feature = "use-profiling")]
+    detail: String,
+    #[cfg(feature = "use-profiling")]
+    started: Instant,
+}
+
+impl ProfileSpan {
+    /// Starts measuring a stage, such as "thumbnail". The detail tells apart
+    /// spans of the same stage in the log, such as the file that is thumbnailed.
+    pub fn start(stage: &'static str, detail: impl Display) -> Self {
+        #[cfg(not(feature = "use-profiling"))]
+        let _ = (stage, detail);
+
+        Self {
+            #[cfg(feature = "use-profiling")]
+            stage,
+            #[cfg(feature = "use-profiling")]
+            detail: detail.to_string(),
+            #[cfg(feature = "use-profiling")]
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for ProfileSpan {
+    fn drop(&mut self) {
+        #[cfg(feature = "use-profiling")]
+        gtk::glib::g_debug!(
+            "Profiling",
+            "{} ({}) took {:.1} ms",
+            self.stage,
+            self.detail,
+            self.started.elapsed().as_secs_f64() * 1000.0
+        );
+    }
+}
+
+/// Logs the time passed since `init()` once the widget draws its first frame.
+pub fn mark_first_frame(widget: &impl IsA<gtk::Widget>) {
+    #[cfg(feature = "use-profiling")]
+    widget.add_tick_callback(|widget: &gtk::Widget, _| {
+        gtk::glib::g_debug!(
+            "Profiling",
+            "First frame of {} drawn {:.1} ms after startup",
+            widget.type_().name(),
+            STARTED.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+        );
+        gtk::glib::ControlFlow::Break
+    });
+    #[cfg(not(feature = "use-profiling"))]
+    let _ = widget;
+}
@@ -0,0 +1,345 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Keyboard shortcuts of the actions in Memories. The defaults can
+//! be replaced by the user from the preferences dialog.
+
+use crate::application::MemoriesApplication;
+use adw::prelude::*;
+use gettextrs::gettext;
+use glib::g_critical;
+use gtk::{gdk, glib};
+use std::collections::HashMap;
+
+/// A keyboard shortcut of an action, with its default accelerators.
+#[derive(Debug)]
+pub struct Shortcut {
+    pub action: &'static str,
+    pub defaults: &'static [&'static str],
+}
+
+/// Every action that has a keyboard shortcut, in the order they are listed.
+pub const SHORTCUTS: &[Shortcut] = &[
+    // crate::application::MemoriesApplication
+    Shortcut {
+        action: "app.system-theme",
+        defaults: &["<primary><shift>s"],
+    },
+    Shortcut {
+        action: "app.light-theme",
+        defaults: &["<primary><shift>l"],
+    },
+    Shortcut {
+        action: "app.dark-theme",
+        defaults: &["<primary><shift>d"],
+    },
+    Shortcut {
+        action: "app.quit",
+        defaults: &["<primary>q"],
+    },
+    // crate::window::MemoriesApplicationWindow
+    Shortcut {
+        action: "win.preferences",
+        defaults: &["<primary>comma"],
+    },
+    Shortcut {
+        action: "win.show-help-overlay",
+        defaults: &["<primary>question"],
+    },
+    Shortcut {
+        action: "win.fullscreen",
+        defaults: &["F11"],
+    },
+    Shortcut {
+        action: "win.leave-fullscreen",
+        defaults: &["Escape"],
+    },
+    Shortcut {
+        action: "win.refresh",
+        defaults: &["F5"],
+    },
+    // crate::library::media_grid::MemoriesMediaGridView
+    Shortcut {
+        action: "mediagrid.rename",
+        defaults: &["F2"],
+    },
+    // crate::library::viewer::MemoriesViewer
+    Shortcut {
+        action: "viewer.favorite",
+        defaults: &["<Ctrl>f"],
+    },
+    Shortcut {
+        action: "viewer.tags",
+        defaults: &["<Ctrl>t"],
+    },
+    Shortcut {
+        action: "viewer.rate(0)",
+        defaults: &["0"],
+    },
+    Shortcut {
+        action: "viewer.rate(1)",
+        defaults: &["1"],
+    },
+    Shortcut {
+        action: "viewer.rate(2)",
+        defaults: &["2"],
+    },
+    Shortcut {
+        action: "viewer.rate(3)",
+        defaults: &["3"],
+    },
+    Shortcut {
+        action: "viewer.rate(4)",
+        defaults: &["4"],
+    },
+    Shortcut {
+        action: "viewer.rate(5)",
+        defaults: &["5"],
+    },
+    Shortcut {
+        action: "viewer.add",
+        defaults: &["<Ctrl>a"],
+    },
+    Shortcut {
+        action: "viewer.set_background",
+        defaults: &["<Ctrl>F8"],
+    },
+    Shortcut {
+        action: "viewer.copy",
+        defaults: &["<Ctrl>c"],
+    },
+    Shortcut {
+        action: "viewer.export",
+        defaults: &["<Ctrl><Shift>e"],
+    },
+    Shortcut {
+        action: "viewer.print",
+        defaults: &["<Ctrl>p"],
+    },
+    Shortcut {
+        action: "viewer.trash",
+        defaults: &["Delete"],
+    },
+    Shortcut {
+        action: "viewer.delete",
+        defaults: &["<shift>Delete"],
+    },
+    Shortcut {
+        action: "viewer.immersive",
+        defaults: &["f"],
+    },
+    Shortcut {
+        action: "viewer.toggle_mute",
+        defaults: &["m"],
+    },
+    Shortcut {
+        action: "viewer.zoom_in",
+        defaults: &["<Ctrl>plus", "<Ctrl>equal"],
+    },
+    Shortcut {
+        action: "viewer.zoom_out",
+        defaults: &["<Ctrl>minus"],
+    },
+    Shortcut {
+        action: "viewer.zoom_best_fit",
+        defaults: &["<Ctrl>0"],
+    },
+    Shortcut {
+        action: "viewer.properties",
+        defaults: &["F9", "<Alt>Return"],
+    },
+    Shortcut {
+        action: "viewer.new_window",
+        defaults: &["<Ctrl>n"],
+    },
+    Shortcut {
+        action: "viewer.exit",
+        defaults: &["<Ctrl>w"],
+    },
+];
+
+/// Group of shortcuts listed together in the preferences dialog.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShortcutSection {
+    General,
+    Library,
+    Viewer,
+}
+
+impl ShortcutSection {
+    pub const ALL: [ShortcutSection; 3] = [Self::General, Self::Library, Self::Viewer];
+
+    pub fn title(&self) -> String {
+        match self {
+            Self::General => gettext("General"),
+            Self::Library => gettext("Library"),
+            Self::Viewer => gettext("Media Viewer"),
+        }
+    }
+}
+
+impl Shortcut {
+    /// Returns the name of the shortcut shown in the preferences dialog,
+    /// or `None` if the shortcut cannot be changed by the user.
+    pub fn title(&self) -> Option<String> {
+        let title: String = match self.action {
+            "app.system-theme" => gettext("Follow System Style"),
+            "app.light-theme" => gettext("Light Style"),
+            "app.dark-theme" => gettext("Dark Style"),
+            "app.quit" => gettext("Quit Memories"),
+            "win.preferences" => gettext("Show Preferences"),
+            "win.show-help-overlay" => gettext("Show Keyboard Shortcuts"),
+            "win.fullscreen" => gettext("Toggle Fullscreen"),
+            "win.leave-fullscreen" => gettext("Leave Fullscreen"),
+            "win.refresh" => gettext("Refresh Library"),
+            "mediagrid.rename" => gettext("Rename Selected Items"),
+            "viewer.favorite" => gettext("Favorite Item"),
+            "viewer.tags" => gettext("Edit Tags"),
+            "viewer.add" => gettext("Add to Album"),
+            "viewer.set_background" => gettext("Set as Background"),
+            "viewer.copy" => gettext("Copy to Clipboard"),
+            "viewer.export" => gettext("Export As"),
+            "viewer.print" => gettext("Print"),
+            "viewer.trash" => gettext("Move to Trash"),
+            "viewer.delete" => gettext("Permanently Delete"),
+            "viewer.immersive" => gettext("Toggle Immersive Mode"),
+            "viewer.toggle_mute" => gettext("Toggle Mute"),
+            "viewer.zoom_in" => gettext("Zoom In"),
+            "viewer.zoom_out" => gettext("Zoom Out"),
+            "viewer.zoom_best_fit" => gettext("Best Fit"),
+            "viewer.properties" => gettext("Toggle Properties"),
+            "viewer.new_window" => gettext("Open in New Window"),
+            "viewer.exit" => gettext("Pop Navigation Page"),
+            // Rating shortcuts are bound to the number keys of each rating.
+            _ => return None,
+        };
+        Some(title)
+    }
+
+    pub fn section(&self) -> ShortcutSection {
+        match self.action.split_once('.') {
+            Some(("mediagrid", _)) => ShortcutSection::Library,
+            Some(("viewer", _)) => ShortcutSection::Viewer,
+            _ => ShortcutSection::General,
+        }
+    }
+
+    /// Returns the accelerators of the shortcut, which are the user's
+    /// accelerators from `custom_accels` if they were changed.
+    pub fn accels(&self, custom_accels: &HashMap<String, Vec<String>>) -> Vec<String> {
+        match custom_accels.get(self.action) {
+            Some(accels) => accels.clone(),
+            None => self.defaults.iter().map(|accel| accel.to_string()).collect(),
+        }
+    }
+
+    pub fn is_customized(&self, custom_accels: &HashMap<String, Vec<String>>) -> bool {
+        custom_accels.contains_key(self.action)
+    }
+}
+
+/// Returns the accelerators changed by the user, by action name.
+pub fn custom_accels() -> HashMap<String, Vec<String>> {
+    MemoriesApplication::default()
+        .gsettings()
+        .get::<HashMap<String, Vec<String>>>("custom-accels")
+}
+
+/// Changes the accelerators of an action. If `accels` is `None`,
+/// the action's default accelerators are restored.
+pub fn set_custom_accels(action: &str, accels: Option<Vec<String>>) {
+    let mut custom_accels: HashMap<String, Vec<String>> = custom_accels();
+
+    match accels {
+        Some(accels) => custom_accels.insert(action.to_string(), accels),
+        None => custom_accels.remove(action),
+    };
+    if let Err(err_msg) = MemoriesApplication::default()
+        .gsettings()
+        .set_value("custom-accels", &custom_accels.to_variant())
+    {
+        g_critical!("Shortcuts", "GSettings returned error: {}", err_msg);
+    }
+}
+
+/// Returns the shortcut, other than the one of `action`, that already uses `accel`.
+pub fn find_conflict(
+    accel: &str,
+    action: &str,
+    custom_accels: &HashMap<String, Vec<String>>,
+) -> Option<&'static Shortcut> {
+    // Compare parsed accelerators, since the same one can be written differently.
+    let parsed: Option<(gdk::Key, gdk::ModifierType)> = gtk::accelerator_parse(accel);
+
+    SHORTCUTS.iter().find(|shortcut: &&Shortcut| {
+        shortcut.action != action
+            && shortcut
+                .accels(custom_accels)
+                .iter()
+                .any(|other: &String| gtk::accelerator_parse(other) == parsed)
+    })
+}
+
+/// Sets the accelerators of every action on the application.
+pub fn apply(application: &impl IsA<gtk::Application>) {
+    let custom_accels: HashMap<String, Vec<String>> = custom_accels();
+
+    for shortcut in SHORTCUTS {
+        let accels: Vec<String> = shortcut.accels(&custom_accels);
+        let accels: Vec<&str> = accels.iter().map(String::as_str).collect();
+
+        application.set_accels_for_action(shortcut.action, &accels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_accels_replace_defaults() {
+        let shortcut: &Shortcut = &SHORTCUTS[0];
+        let mut custom_accels: HashMap<String, Vec<String>> = HashMap::new();
+
+        assert_eq!(shortcut.accels(&custom_accels), shortcut.defaults);
+        assert!(!shortcut.is_customized(&custom_accels));
+
+        custom_accels.insert(shortcut.action.to_string(), vec!["<Ctrl>k".to_string()]);
+        assert_eq!(shortcut.accels(&custom_accels), ["<Ctrl>k"]);
+
+        // An empty list disables the shortcut instead of restoring the defaults.
+        custom_accels.insert(shortcut.action.to_string(), vec![]);
+        assert!(shortcut.accels(&custom_accels).is_empty());
+        assert!(shortcut.is_customized(&custom_accels));
+    }
+
+    #[test]
+    fn actions_are_unique() {
+        for (i, shortcut) in SHORTCUTS.iter().enumerate() {
+            assert!(
+                SHORTCUTS[i + 1..]
+                    .iter()
+                    .all(|other| other.action != shortcut.action),
+                "{} is listed more than once",
+                shortcut.action
+            );
+        }
+    }
+}
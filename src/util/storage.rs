@@ -0,0 +1,127 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Detecting the kind of storage that library folders are on, so that fewer
+//! thumbnails are made at once from storage that is slow to seek, such as
+//! spinning hard drives and memory cards, or slow to read, like network locations.
+
+use crate::globals::{STORAGE_NETWORK_PROCESSES, STORAGE_SEEKING_PROCESSES};
+use crate::util::mounts;
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// The kind of storage that a folder is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageKind {
+    SolidState,
+    Rotational,
+    Removable,
+    Network,
+}
+
+impl StorageKind {
+    /// Returns the amount of thumbnail processes to spawn at once for media
+    /// on this kind of storage, which is at most the `configured` amount.
+    pub fn process_limit(&self, configured: usize) -> usize {
+        match self {
+            Self::SolidState => configured,
+            Self::Rotational | Self::Removable => configured.min(STORAGE_SEEKING_PROCESSES),
+            Self::Network => configured.min(STORAGE_NETWORK_PROCESSES),
+        }
+    }
+}
+
+/// Detects the kind of storage that a folder is on. Folders that
+/// fail to be detected are assumed to be on solid state storage.
+pub async fn detect(folder: &gio::File) -> StorageKind {
+    if !folder.is_native() {
+        return StorageKind::Network;
+    }
+    if let Ok(mount) = folder.find_enclosing_mount_future(glib::Priority::LOW).await {
+        if mounts::is_removable_mount(&mount) {
+            return StorageKind::Removable;
+        }
+    }
+    let Some(path) = folder.path() else {
+        return StorageKind::SolidState;
+    };
+    gio::spawn_blocking(move || detect_local(&path))
+        .await
+        .unwrap_or(StorageKind::SolidState)
+}
+
+/// Detects whether a local path is on a network filesystem, or on a rotational
+/// block device going by sysfs.
+fn detect_local(path: &Path) -> StorageKind {
+    let remote: bool = gio::File::for_path(path)
+        .query_filesystem_info(gio::FILE_ATTRIBUTE_FILESYSTEM_REMOTE, gio::Cancellable::NONE)
+        .is_ok_and(|info: gio::FileInfo| info.boolean(gio::FILE_ATTRIBUTE_FILESYSTEM_REMOTE));
+
+    if remote {
+        return StorageKind::Network;
+    }
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return StorageKind::SolidState;
+    };
+    let (major, minor) = device_numbers(metadata.dev());
+    let device_dir: PathBuf = PathBuf::from(format!("/sys/dev/block/{}:{}", major, minor));
+
+    // Partitions have no queue of their own, it is in the directory of their disk.
+    for queue_dir in [device_dir.join("queue"), device_dir.join("../queue")] {
+        if let Ok(rotational) = std::fs::read_to_string(queue_dir.join("rotational")) {
+            return match rotational.trim() {
+                "1" => StorageKind::Rotational,
+                _ => StorageKind::SolidState,
+            };
+        }
+    }
+    StorageKind::SolidState
+}
+
+/// Returns the major and minor numbers of a device ID, as encoded by glibc.
+fn device_numbers(dev: u64) -> (u64, u64) {
+    let major: u64 = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor: u64 = (dev & 0xff) | ((dev >> 12) & !0xff);
+    (major, minor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_numbers_of_disks() {
+        assert_eq!(device_numbers(0x0801), (8, 1));
+        assert_eq!(device_numbers(0x10303), (259, 3));
+        assert_eq!(device_numbers(0x100800), (8, 256));
+    }
+
+    #[test]
+    fn process_limit_by_storage() {
+        assert_eq!(StorageKind::SolidState.process_limit(5), 5);
+        assert_eq!(
+            StorageKind::Rotational.process_limit(5),
+            STORAGE_SEEKING_PROCESSES
+        );
+        assert_eq!(StorageKind::Network.process_limit(1), 1);
+    }
+}
@@ -0,0 +1,84 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Changes the desktop wallpaper through the wallpaper portal,
+//! which is used to rotate the wallpaper through chosen photos.
+
+use crate::globals::{DESKTOP_PORTAL_BUS_NAME, DESKTOP_PORTAL_OBJECT_PATH, WALLPAPER_PORTAL_INTERFACE};
+use gtk::{gio, glib};
+
+/// Sets the desktop background to the image at the given URI,
+/// without asking the user to confirm it with a preview.
+pub async fn set_wallpaper_uri(uri: &str) -> Result<(), glib::Error> {
+    let connection: gio::DBusConnection = gio::bus_get_future(gio::BusType::Session).await?;
+
+    let options: glib::VariantDict = glib::VariantDict::new(None);
+    options.insert("show-preview", false);
+    options.insert("set-on", "background");
+
+    // The parent window is left empty, since the wallpaper
+    // is changed while no window may be open.
+    let parameters: glib::Variant =
+        glib::Variant::tuple_from_iter(["".to_variant(), uri.to_variant(), options.end()]);
+
+    connection
+        .call_future(
+            Some(DESKTOP_PORTAL_BUS_NAME),
+            DESKTOP_PORTAL_OBJECT_PATH,
+            WALLPAPER_PORTAL_INTERFACE,
+            "SetWallpaperURI",
+            Some(&parameters),
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await
+        .map(|_| ())
+}
+
+/// Returns the number of seconds until the wallpaper should be changed next,
+/// given the Unix times that it was last changed and of now. A wallpaper that
+/// was never changed, or changed in the future by a clock that went backwards,
+/// is changed right away.
+pub fn next_change_delay(last_changed: i64, now: i64, interval: u64) -> u64 {
+    if last_changed <= 0 || last_changed > now {
+        return 0;
+    }
+    let elapsed: u64 = (now - last_changed).unsigned_abs();
+    interval.saturating_sub(elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_change_delay_waits_out_the_interval() {
+        assert_eq!(next_change_delay(1_000, 1_000, 600), 600);
+        assert_eq!(next_change_delay(1_000, 1_450, 600), 150);
+        assert_eq!(next_change_delay(1_000, 5_000, 600), 0);
+    }
+
+    #[test]
+    fn next_change_delay_changes_right_away_if_never_changed() {
+        assert_eq!(next_change_delay(0, 1_000, 600), 0);
+        assert_eq!(next_change_delay(2_000, 1_000, 600), 0);
+    }
+}
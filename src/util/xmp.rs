@@ -0,0 +1,296 @@
+// This file is part of Memories.
+//
+// Copyright (c) 2024 Max Rodriguez
+// All rights reserved.
+//
+// Memories is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Memories is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Memories.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Writing of media ratings, favorites, and tags to XMP sidecar files, so
+//! that they are shared with other applications, such as darktable and
+//! digiKam, which read `xmp:Rating` and `dc:subject` from `<file>.<ext>.xmp`.
+
+use gtk::prelude::*;
+use gtk::{gio, glib};
+
+const XMP_NAMESPACE: &str = "http://ns.adobe.com/xap/1.0/";
+const MEMORIES_NAMESPACE: &str = "http://maxrdz.com/ns/memories/1.0/";
+const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+
+/// Returns the sidecar file for a media file, which is
+/// the media file name with the `.xmp` extension appended.
+pub fn sidecar_file(file: &gio::File) -> Option<gio::File> {
+    let parent: gio::File = file.parent()?;
+    let mut basename = file.basename()?.into_os_string();
+    basename.push(".xmp");
+
+    Some(parent.child(basename))
+}
+
+/// Returns a new XMP packet with an empty `rdf:Description` element.
+fn empty_packet() -> String {
+    format!(
+        concat!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n",
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n",
+            " <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n",
+            "  <rdf:Description rdf:about=\"\" xmlns:xmp=\"{}\">\n",
+            "  </rdf:Description>\n",
+            " </rdf:RDF>\n",
+            "</x:xmpmeta>\n",
+            "<?xpacket end=\"w\"?>\n",
+        ),
+        XMP_NAMESPACE
+    )
+}
+
+/// Sets an attribute in the start tag of an element, replacing its value if present.
+fn set_attribute(tag: &mut String, name: &str, value: &str) {
+    let prefix: String = format!(" {}=\"", name);
+
+    if let Some(start) = tag.find(&prefix) {
+        let value_start: usize = start + prefix.len();
+
+        if let Some(length) = tag[value_start..].find('"') {
+            tag.replace_range(value_start..value_start + length, value);
+            return;
+        }
+    }
+    let insert_at: usize = tag.trim_end_matches('>').trim_end_matches('/').len();
+    tag.insert_str(insert_at, &format!(" {}=\"{}\"", name, value));
+}
+
+/// Sets a simple property of the first `rdf:Description` element of an XMP
+/// packet. Properties are written as attributes, unless the packet already
+/// contains the property as an element, in which case its value is replaced.
+fn set_property(packet: &mut String, name: &str, value: &str) {
+    let element_start: String = format!("<{}>", name);
+    let element_end: String = format!("</{}>", name);
+
+    if let Some(start) = packet.find(&element_start) {
+        let value_start: usize = start + element_start.len();
+
+        if let Some(length) = packet[value_start..].find(&element_end) {
+            packet.replace_range(value_start..value_start + length, value);
+            return;
+        }
+    }
+    let Some(tag_start) = packet.find("<rdf:Description") else {
+        return;
+    };
+    let Some(tag_length) = packet[tag_start..].find('>') else {
+        return;
+    };
+    let mut tag: String = packet[tag_start..=tag_start + tag_length].to_string();
+    set_attribute(&mut tag, name, value);
+
+    packet.replace_range(tag_start..=tag_start + tag_length, &tag);
+}
+
+/// Declares a namespace on the first `rdf:Description` element, if missing.
+fn declare_namespace(packet: &mut String, prefix: &str, uri: &str) {
+    if packet.contains(&format!("xmlns:{}=", prefix)) {
+        return;
+    }
+    set_property(packet, &format!("xmlns:{}", prefix), uri);
+}
+
+/// Escapes the characters of a text value that are special in XML.
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Sets the `dc:subject` bag of keywords of the first `rdf:Description`
+/// element, replacing the existing bag if present.
+fn set_subject(packet: &mut String, keywords: &[String]) {
+    let mut subject: String = String::from("<dc:subject><rdf:Bag>");
+
+    for keyword in keywords {
+        subject.push_str(&format!("<rdf:li>{}</rdf:li>", escape_text(keyword)));
+    }
+    subject.push_str("</rdf:Bag></dc:subject>");
+
+    if let Some(start) = packet.find("<dc:subject>") {
+        if let Some(length) = packet[start..].find("</dc:subject>") {
+            packet.replace_range(start..start + length + "</dc:subject>".len(), &subject);
+            return;
+        }
+    }
+    let Some(tag_start) = packet.find("<rdf:Description") else {
+        return;
+    };
+    let Some(tag_length) = packet[tag_start..].find('>') else {
+        return;
+    };
+    let tag_end: usize = tag_start + tag_length;
+
+    // A self closing element has to be opened to add child elements.
+    if packet[..tag_end].ends_with('/') {
+        packet.replace_range(tag_end - 1..=tag_end, &format!(">{}</rdf:Description>", subject));
+    } else {
+        packet.insert_str(tag_end + 1, &subject);
+    }
+}
+
+/// Changes to write to an XMP sidecar file. `None` values are left unchanged.
+#[derive(Debug, Default, Clone)]
+pub struct SidecarUpdate {
+    pub rating: Option<u8>,
+    pub favorite: Option<bool>,
+    /// Keywords, written to the `dc:subject` bag.
+    pub keywords: Option<Vec<String>>,
+}
+
+/// Returns the XMP packet with the given changes applied.
+/// Any other metadata in an existing packet is kept as is.
+pub fn update_packet(packet: Option<&str>, update: &SidecarUpdate) -> String {
+    let mut packet: String = match packet {
+        Some(packet) if packet.contains("<rdf:Description") => packet.to_string(),
+        _ => empty_packet(),
+    };
+
+    if let Some(rating) = update.rating {
+        declare_namespace(&mut packet, "xmp", XMP_NAMESPACE);
+        set_property(&mut packet, "xmp:Rating", &rating.min(5).to_string());
+    }
+    if let Some(favorite) = update.favorite {
+        declare_namespace(&mut packet, "memories", MEMORIES_NAMESPACE);
+        set_property(
+            &mut packet,
+            "memories:Favorite",
+            if favorite { "True" } else { "False" },
+        );
+    }
+    if let Some(keywords) = &update.keywords {
+        declare_namespace(&mut packet, "dc", DC_NAMESPACE);
+        set_subject(&mut packet, keywords);
+    }
+    packet
+}
+
+/// Writes the given changes to the sidecar file of a media
+/// file, creating the sidecar file if it does not exist.
+pub async fn write_sidecar(file: &gio::File, update: SidecarUpdate) -> Result<(), glib::Error> {
+    let sidecar: gio::File = sidecar_file(file).ok_or_else(|| {
+        glib::Error::new(
+            gio::IOErrorEnum::InvalidFilename,
+            "Media file has no parent directory.",
+        )
+    })?;
+
+    let existing: Option<String> = match sidecar.load_contents_future().await {
+        Ok((contents, _)) => Some(String::from_utf8_lossy(&contents).to_string()),
+        Err(err) if err.matches(gio::IOErrorEnum::NotFound) => None,
+        Err(err) => return Err(err),
+    };
+    let packet: String = update_packet(existing.as_deref(), &update);
+
+    sidecar
+        .replace_contents_future(packet.into_bytes(), None, false, gio::FileCreateFlags::NONE)
+        .await
+        .map(|_| ())
+        .map_err(|(_, err)| err)
+}
+
+/// Renames the sidecar file of a media file that was renamed to `new_file`,
+/// if it has one, so that the sidecar stays with the media file.
+pub async fn rename_sidecar(file: &gio::File, new_file: &gio::File) -> Result<(), glib::Error> {
+    let (Some(sidecar), Some(new_sidecar)) = (sidecar_file(file), sidecar_file(new_file)) else {
+        return Ok(());
+    };
+    let new_name: String = new_sidecar
+        .basename()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    match sidecar
+        .set_display_name_future(&new_name, glib::Priority::DEFAULT)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(err) if err.matches(gio::IOErrorEnum::NotFound) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_packet_with_rating_and_favorite() {
+        let update: SidecarUpdate = SidecarUpdate {
+            rating: Some(4),
+            favorite: Some(true),
+            ..Default::default()
+        };
+        let packet: String = update_packet(None, &update);
+
+        assert!(packet.contains(" xmp:Rating=\"4\""));
+        assert!(packet.contains(" memories:Favorite=\"True\""));
+        assert!(packet.contains(&format!("xmlns:memories=\"{}\"", MEMORIES_NAMESPACE)));
+    }
+
+    #[test]
+    fn existing_packet_is_updated() {
+        let existing: &str = concat!(
+            "<x:xmpmeta><rdf:RDF><rdf:Description rdf:about=\"\" xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" ",
+            "xmp:Rating=\"1\" darktable:xmp_version=\"5\"/></rdf:RDF></x:xmpmeta>",
+        );
+        let update: SidecarUpdate = SidecarUpdate {
+            rating: Some(9),
+            ..Default::default()
+        };
+        let packet: String = update_packet(Some(existing), &update);
+
+        assert!(packet.contains(" xmp:Rating=\"5\" darktable:xmp_version=\"5\"/>"));
+        assert!(!packet.contains("memories:"));
+
+        let update: SidecarUpdate = SidecarUpdate {
+            rating: Some(3),
+            ..Default::default()
+        };
+        let packet: String = update_packet(Some("<rdf:Description><xmp:Rating>2</xmp:Rating>"), &update);
+        assert!(packet.contains("<xmp:Rating>3</xmp:Rating>"));
+    }
+
+    #[test]
+    fn keywords_are_written_to_subject() {
+        let update: SidecarUpdate = SidecarUpdate {
+            keywords: Some(vec!["beach".into(), "R&D".into()]),
+            ..Default::default()
+        };
+        let packet: String = update_packet(Some("<rdf:Description rdf:about=\"\"/>"), &update);
+
+        assert_eq!(
+            packet,
+            concat!(
+                "<rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">",
+                "<dc:subject><rdf:Bag><rdf:li>beach</rdf:li><rdf:li>R&amp;D</rdf:li></rdf:Bag></dc:subject>",
+                "</rdf:Description>",
+            )
+        );
+
+        let update: SidecarUpdate = SidecarUpdate {
+            keywords: Some(vec![]),
+            ..Default::default()
+        };
+        let packet: String = update_packet(Some(&packet), &update);
+        assert!(packet.contains("<dc:subject><rdf:Bag></rdf:Bag></dc:subject></rdf:Description>"));
+    }
+}
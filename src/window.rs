@@ -19,27 +19,57 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::application::MemoriesApplication;
+use crate::archive::MemoriesArchiveView;
+use crate::devices::MemoriesDevicesView;
+use crate::i18n::{gettext_f, ngettext_f};
+use crate::library::catalog_dialog::MemoriesCatalogDialog;
+use crate::library::integrity_dialog::MemoriesIntegrityDialog;
+use crate::library::list_model::MemoriesLibraryListModel;
+use crate::library::media_grid::MemoriesMediaGridView;
+use crate::library::media_viewer::MemoriesMediaViewer;
+use crate::library::roots::{self, LibraryRoot};
+use crate::library::scan_service::MemoriesScanService;
+use crate::library::search::tag_term;
+use crate::library::shotwell::{self, ImportedMetadata};
+use crate::private::MemoriesPrivateView;
+use crate::statistics::MemoriesStatisticsView;
+#[cfg(feature = "use-sync")]
+use crate::sync::MemoriesBackupPage;
+use crate::trash::MemoriesTrashView;
+use crate::util::hwaccel::{self, HardwareAccel};
+use crate::util::mounts;
+use crate::util::shortcuts::{self, Shortcut, ShortcutSection, SHORTCUTS};
 // We manually include only the traits we need to use
 // to avoid ambiguity errors when multiple traits share
 // the same methods, such as WidgetExt & ActionGroupExt.
 use adw::prelude::{
-    ActionMapExtManual, AdwDialogExt, ApplicationWindowExt, CastNone, GtkWindowExt, SettingsExt, ToVariant,
-    ToggleButtonExt, WidgetExt,
+    ActionMapExt, ActionMapExtManual, ActionRowExt, AdwDialogExt, AlertDialogExt, ApplicationWindowExt,
+    ButtonExt, CastNone, ComboRowExt, EditableExt, EntryRowExt, EventControllerExt, FileExt, GtkWindowExt,
+    NavigationPageExt, ObjectExt, PreferencesDialogExt, PreferencesGroupExt, PreferencesPageExt, SettingsExt,
+    SettingsExtManual, SpinRowExt, StaticVariantType, ToVariant, ToggleButtonExt, WidgetExt,
 };
 use adw::subclass::prelude::*;
 use gettextrs::gettext;
-use glib::g_error;
-use gtk::{gio, glib};
+use glib::{clone, g_critical, g_warning};
+use gtk::{gdk, gio, glib};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 
 mod imp {
     use crate::albums::MemoriesAlbumsView;
     use crate::application::MemoriesApplication;
     use crate::config::GRESOURCE_DOMAIN;
-    use crate::globals::DEVELOPMENT_BUILD;
+    use crate::folders::MemoriesFoldersView;
+    use crate::globals::{DEVELOPMENT_BUILD, MOUSE_BACK_BUTTON};
     use crate::library::list_model::MemoriesLibraryListModel;
     use crate::library::MemoriesLibraryView;
+    #[cfg(feature = "use-face-detection")]
+    use crate::people::MemoriesPeopleView;
     use adw::prelude::*;
     use adw::subclass::prelude::*;
+    #[cfg(feature = "use-face-detection")]
+    use gettextrs::gettext;
     use glib::clone;
     use gtk::{gio, glib};
 
@@ -57,10 +87,14 @@ mod imp {
         #[template_child]
         primary_menu: TemplateChild<gtk::PopoverMenu>,
         #[template_child]
+        pub(super) search_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
         pub(super) search_bar: TemplateChild<gtk::SearchBar>,
         #[template_child]
         pub(super) search_entry: TemplateChild<gtk::SearchEntry>,
         #[template_child]
+        pub(super) tag_filter_section: TemplateChild<gio::Menu>,
+        #[template_child]
         pub(super) master_stack: TemplateChild<adw::ViewStack>,
         #[template_child]
         pub(super) library_page: TemplateChild<adw::ViewStackPage>,
@@ -69,6 +103,8 @@ mod imp {
         #[template_child]
         albums_view: TemplateChild<MemoriesAlbumsView>,
         #[template_child]
+        folders_view: TemplateChild<MemoriesFoldersView>,
+        #[template_child]
         pub(super) library_view: TemplateChild<MemoriesLibraryView>,
     }
 
@@ -113,17 +149,56 @@ mod imp {
             self.primary_menu.add_child(&new_theme_selector, "theme-selector");
 
             obj.setup_gactions();
+            obj.update_tag_filter_section();
+
+            #[cfg(feature = "use-face-detection")]
+            self.master_stack.add_titled_with_icon(
+                &MemoriesPeopleView::new(),
+                Some("people"),
+                &gettext("People"),
+                "avatar-default-symbolic",
+            );
 
             obj.connect_show(move |window: &super::MemoriesApplicationWindow| {
                 // MemoriesLibraryListModel instance MUST be initialized after
                 // the application window, but before the library view.
-                MemoriesLibraryListModel::initialize_new_model(window);
+                MemoriesLibraryListModel::initialize_new_model(&window.app().unwrap());
 
                 // This callback wont be triggered on start up by itself, so we
                 // want to check the very first visible child in the master view stack.
                 window.master_stack_child_visible();
             });
 
+            // The back button of mice leaves pages pushed onto the window, like the viewer.
+            let back_gesture: gtk::GestureClick =
+                gtk::GestureClick::builder().button(MOUSE_BACK_BUTTON).build();
+
+            back_gesture.connect_pressed(clone!(
+                #[weak]
+                obj,
+                move |gesture: &gtk::GestureClick, _: i32, _: f64, _: f64| {
+                    if obj.imp().window_navigation.pop() {
+                        gesture.set_state(gtk::EventSequenceState::Claimed);
+                    }
+                }
+            ));
+            obj.add_controller(back_gesture);
+
+            // Title the window after the visible page, such as the media open in
+            // the viewer, so that it can be told apart in the taskbar.
+            let visible_page = self.window_navigation.property_expression("visible-page");
+            let page_title = visible_page.chain_property::<adw::NavigationPage>("title");
+
+            gtk::ClosureExpression::new::<String>(
+                [visible_page.upcast(), page_title.upcast()],
+                glib::closure_local!(|_: Option<glib::Object>,
+                                      page: Option<adw::NavigationPage>,
+                                      _: Option<String>| {
+                    super::window_title(page.as_ref())
+                }),
+            )
+            .bind(&*obj, "title", gtk::Widget::NONE);
+
             // Persist application window state (width, height, maximized, etc) with GSettings
             let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
 
@@ -153,6 +228,13 @@ mod imp {
                     gsettings.set_int("window-width", win.width()).unwrap();
                     gsettings.set_int("window-height", win.height()).unwrap();
                 }
+                win.save_session_state(&gsettings);
+
+                // Jobs like exports keep running after the window is closed,
+                // and so does Memories if it is set to run in the background.
+                if MemoriesApplication::default().hide_closed_window(win) {
+                    return glib::Propagation::Stop;
+                }
                 glib::Propagation::Proceed
             });
         }
@@ -187,6 +269,102 @@ impl MemoriesApplicationWindow {
             .activate(move |win: &Self, _, _| {
                 let builder = gtk::Builder::from_resource("/com/maxrdz/Memories/ui/preferences.ui");
                 let dialog: adw::PreferencesDialog = builder.object("preferences_dialog").unwrap();
+
+                let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+                let overlay_timeout_row: adw::SpinRow = builder.object("overlay_timeout_row").unwrap();
+                let overlay_trigger_row: adw::ComboRow = builder.object("overlay_trigger_row").unwrap();
+                let item_activation_row: adw::ComboRow = builder.object("item_activation_row").unwrap();
+                let autoplay_muted_row: adw::SwitchRow = builder.object("autoplay_muted_row").unwrap();
+                let scroll_wheel_row: adw::ComboRow = builder.object("scroll_wheel_row").unwrap();
+                let nav_arrows_row: adw::SwitchRow = builder.object("nav_arrows_row").unwrap();
+                let xmp_sidecars_row: adw::SwitchRow = builder.object("xmp_sidecars_row").unwrap();
+                let run_in_background_row: adw::SwitchRow = builder.object("run_in_background_row").unwrap();
+                let lazy_enumeration_row: adw::SwitchRow = builder.object("lazy_enumeration_row").unwrap();
+                let pause_indexing_row: adw::SwitchRow = builder.object("pause_indexing_row").unwrap();
+                let power_saver_row: adw::SwitchRow = builder.object("power_saver_row").unwrap();
+                let thumbnail_processes_row: adw::SpinRow =
+                    builder.object("thumbnail_processes_row").unwrap();
+                let adaptive_processes_row: adw::SwitchRow =
+                    builder.object("adaptive_processes_row").unwrap();
+                let gpu_scaling_row: adw::SwitchRow = builder.object("gpu_scaling_row").unwrap();
+                let wallpaper_interval_row: adw::SpinRow = builder.object("wallpaper_interval_row").unwrap();
+
+                gsettings
+                    .bind("item-activation", &item_activation_row, "selected")
+                    .build();
+                gsettings
+                    .bind("autoplay-muted", &autoplay_muted_row, "active")
+                    .build();
+                gsettings
+                    .bind("viewer-overlay-timeout", &overlay_timeout_row, "value")
+                    .build();
+                gsettings
+                    .bind("viewer-overlay-trigger", &overlay_trigger_row, "selected")
+                    .build();
+                gsettings
+                    .bind("viewer-scroll-wheel", &scroll_wheel_row, "selected")
+                    .build();
+                gsettings
+                    .bind("viewer-show-nav-arrows", &nav_arrows_row, "active")
+                    .build();
+                gsettings
+                    .bind("write-xmp-sidecars", &xmp_sidecars_row, "active")
+                    .build();
+                gsettings
+                    .bind("run-in-background", &run_in_background_row, "active")
+                    .build();
+                gsettings
+                    .bind("lazy-folder-enumeration", &lazy_enumeration_row, "active")
+                    .build();
+                gsettings
+                    .bind("pause-indexing-on-battery", &pause_indexing_row, "active")
+                    .build();
+                gsettings
+                    .bind("power-saver-throttling", &power_saver_row, "active")
+                    .build();
+                gsettings
+                    .bind("thumbnail-processes", &thumbnail_processes_row, "value")
+                    .build();
+                gsettings
+                    .bind("storage-adaptive-concurrency", &adaptive_processes_row, "active")
+                    .build();
+                gsettings
+                    .bind("gpu-thumbnail-scaling", &gpu_scaling_row, "active")
+                    .build();
+                gsettings
+                    .bind("wallpaper-slideshow-interval", &wallpaper_interval_row, "value")
+                    .build();
+
+                // A value of 0 uses the default for the device.
+                thumbnail_processes_row.connect_output(|row: &adw::SpinRow| {
+                    if row.value() == 0.0 {
+                        row.set_text(&gettext("Automatic"));
+                        return true;
+                    }
+                    false
+                });
+
+                #[cfg(feature = "use-sync")]
+                dialog.add(&MemoriesBackupPage::new());
+
+                win.setup_hwaccel_backend_row(&builder.object("hwaccel_backend_row").unwrap());
+                win.setup_wallpaper_slideshow_row(
+                    &dialog,
+                    &builder.object("wallpaper_slideshow_row").unwrap(),
+                    &builder.object("wallpaper_stop_button").unwrap(),
+                );
+                win.setup_shotwell_import_row(
+                    &dialog,
+                    &builder.object("shotwell_import_row").unwrap(),
+                    &builder.object("shotwell_import_button").unwrap(),
+                );
+                win.setup_shortcuts_page(&dialog, &builder.object("shortcuts_page").unwrap());
+                win.setup_library_folders_group(
+                    &dialog,
+                    &builder.object("library_folders_group").unwrap(),
+                    &builder.object("library_folder_entry").unwrap(),
+                );
+
                 dialog.present(Some(win));
             })
             .build();
@@ -223,12 +401,648 @@ impl MemoriesApplicationWindow {
             })
             .build();
 
+        let archive_action = gio::ActionEntry::builder("archive")
+            .activate(move |win: &Self, _, _| {
+                let nav_view: &adw::NavigationView = &win.imp().window_navigation;
+
+                if nav_view.visible_page().and_then(|page| page.tag()).as_deref() == Some("window") {
+                    nav_view.push(&MemoriesArchiveView::new());
+                }
+            })
+            .build();
+
+        let private_action = gio::ActionEntry::builder("private")
+            .activate(move |win: &Self, _, _| {
+                let nav_view: &adw::NavigationView = &win.imp().window_navigation;
+
+                if nav_view.visible_page().and_then(|page| page.tag()).as_deref() == Some("window") {
+                    nav_view.push(&MemoriesPrivateView::new());
+                }
+            })
+            .build();
+
+        let trash_action = gio::ActionEntry::builder("trash")
+            .activate(move |win: &Self, _, _| {
+                let nav_view: &adw::NavigationView = &win.imp().window_navigation;
+
+                // Only open the page from the main window page, not over the viewer.
+                if nav_view.visible_page().and_then(|page| page.tag()).as_deref() == Some("window") {
+                    nav_view.push(&MemoriesTrashView::new());
+                }
+            })
+            .build();
+
+        let devices_action = gio::ActionEntry::builder("devices")
+            .activate(move |win: &Self, _, _| {
+                let nav_view: &adw::NavigationView = &win.imp().window_navigation;
+
+                if nav_view.visible_page().and_then(|page| page.tag()).as_deref() == Some("window") {
+                    nav_view.push(&MemoriesDevicesView::new());
+                }
+            })
+            .build();
+
+        let statistics_action = gio::ActionEntry::builder("statistics")
+            .activate(move |win: &Self, _, _| {
+                let nav_view: &adw::NavigationView = &win.imp().window_navigation;
+
+                if nav_view.visible_page().and_then(|page| page.tag()).as_deref() == Some("window") {
+                    nav_view.push(&MemoriesStatisticsView::new());
+                }
+            })
+            .build();
+
+        let integrity_action = gio::ActionEntry::builder("integrity")
+            .activate(move |win: &Self, _, _| {
+                MemoriesIntegrityDialog::new().present(Some(win));
+            })
+            .build();
+
+        let export_catalog_action = gio::ActionEntry::builder("export-catalog")
+            .activate(move |win: &Self, _, _| {
+                MemoriesCatalogDialog::new().present(Some(win));
+            })
+            .build();
+
+        let pause_indexing_action = gio::ActionEntry::builder("pause-indexing")
+            .state(false.to_variant())
+            .activate(move |win: &Self, action: &gio::SimpleAction, _| {
+                let scan_service: MemoriesScanService = win.app().unwrap().scan_service();
+
+                if scan_service.paused() {
+                    scan_service.resume();
+                } else {
+                    scan_service.pause();
+                }
+                action.set_state(&scan_service.paused().to_variant());
+            })
+            .build();
+
+        let refresh_action = gio::ActionEntry::builder("refresh")
+            .activate(move |win: &Self, _, _| {
+                win.app().unwrap().library_list_model().refresh();
+            })
+            .build();
+
         self.add_action_entries([
             preferences_action,
             shortcuts_window_action,
             toggle_fullscreen_action,
             leave_fullscreen_action,
+            archive_action,
+            private_action,
+            trash_action,
+            devices_action,
+            statistics_action,
+            integrity_action,
+            export_catalog_action,
+            refresh_action,
+            pause_indexing_action,
         ]);
+
+        // Stateful action for the rating filter of the search bar's filter menu.
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+        self.add_action(&gsettings.create_action("rating-filter"));
+
+        // Searches the library for the media with the tag given as parameter.
+        let search_tag_action = gio::SimpleAction::new("search-tag", Some(&String::static_variant_type()));
+
+        search_tag_action.connect_activate(clone!(
+            #[weak(rename_to = win)]
+            self,
+            move |_: &gio::SimpleAction, parameter: Option<&glib::Variant>| {
+                let Some(tag) = parameter.and_then(|p| p.get::<String>()) else {
+                    return;
+                };
+                win.imp().master_stack.set_visible_child_name("library");
+                win.imp().search_button.set_active(true);
+                win.imp().search_entry.set_text(&tag_term(&tag));
+            }
+        ));
+        self.add_action(&search_tag_action);
+
+        MemoriesApplication::default().connect_metadata_changed(
+            Some("tags"),
+            clone!(
+                #[weak(rename_to = win)]
+                self,
+                move |_: &MemoriesApplication, _: &str| win.update_tag_filter_section()
+            ),
+        );
+    }
+
+    /// Lists every tag in the library in the search bar's filter menu.
+    fn update_tag_filter_section(&self) {
+        let section: &gio::Menu = &self.imp().tag_filter_section;
+        section.remove_all();
+
+        for (tag, _) in MemoriesApplication::default().all_tags() {
+            let item: gio::MenuItem = gio::MenuItem::new(Some(&tag), None);
+            item.set_action_and_target_value(Some("win.search-tag"), Some(&tag.to_variant()));
+            section.append_item(&item);
+        }
+    }
+
+    /// Persists the scroll position of the library grid and the media file
+    /// open in the viewer, so that they are restored on the next startup.
+    fn save_session_state(&self, gsettings: &gio::Settings) {
+        let media_grid: &MemoriesMediaGridView = &self.imp().library_view.imp().media_grid;
+
+        // Only overwrite the grid position if the library was loaded in this session.
+        if media_grid.imp().photo_grid_view.model().is_some() {
+            gsettings
+                .set_uint("last-grid-position", media_grid.first_visible_position())
+                .unwrap();
+        }
+
+        let viewer_file: Option<gio::File> = self
+            .imp()
+            .window_navigation
+            .visible_page()
+            .and_then(|page: adw::NavigationPage| page.child())
+            .and_downcast::<MemoriesMediaViewer>()
+            .and_then(|viewer: MemoriesMediaViewer| viewer.content_file());
+
+        let uri: String = viewer_file
+            .map(|file: gio::File| file.uri().to_string())
+            .unwrap_or_default();
+        gsettings.set_string("last-viewed-file", &uri).unwrap();
+    }
+
+    /// Lists the folders added to the library in the preferences dialog,
+    /// and adds the folder or network location entered in the entry row.
+    fn setup_library_folders_group(
+        &self,
+        dialog: &adw::PreferencesDialog,
+        group: &adw::PreferencesGroup,
+        entry: &adw::EntryRow,
+    ) {
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+        let rows: Rc<RefCell<Vec<adw::ActionRow>>> = Rc::default();
+
+        let update_rows = clone!(
+            #[weak]
+            group,
+            #[strong]
+            rows,
+            move || {
+                for row in rows.take() {
+                    group.remove(&row);
+                }
+                for root in roots::configured_roots() {
+                    let folder: gio::File = root.file();
+
+                    let row: adw::ActionRow = adw::ActionRow::builder()
+                        .title(folder.parse_name())
+                        .use_markup(false)
+                        .build();
+
+                    let remove_button: gtk::Button = gtk::Button::builder()
+                        .icon_name("list-remove-symbolic")
+                        .tooltip_text(gettext("Remove From Library"))
+                        .valign(gtk::Align::Center)
+                        .css_classes(["flat"])
+                        .build();
+
+                    remove_button.connect_clicked(move |_: &gtk::Button| {
+                        MemoriesLibraryListModel::remove_configured_folder(&folder);
+                    });
+                    row.add_suffix(&remove_button);
+
+                    group.add(&row);
+                    rows.borrow_mut().push(row);
+                }
+            }
+        );
+        update_rows();
+
+        let handler_id: glib::SignalHandlerId = gsettings
+            .connect_changed(Some("library-folders"), move |_: &gio::Settings, _: &str| {
+                update_rows()
+            });
+        let handler_id: RefCell<Option<glib::SignalHandlerId>> = RefCell::new(Some(handler_id));
+
+        dialog.connect_closed(move |_: &adw::PreferencesDialog| {
+            if let Some(handler_id) = handler_id.take() {
+                gsettings.disconnect(handler_id);
+            }
+        });
+
+        entry.connect_apply(clone!(
+            #[weak(rename_to = win)]
+            self,
+            #[weak]
+            dialog,
+            move |entry: &adw::EntryRow| {
+                let folder: String = entry.text().trim().to_string();
+
+                if folder.is_empty() {
+                    return;
+                }
+                entry.set_text("");
+
+                glib::spawn_future_local(clone!(
+                    #[weak]
+                    win,
+                    #[weak]
+                    dialog,
+                    async move {
+                        if let Err(message) = win.add_library_folder(&folder).await {
+                            dialog.add_toast(adw::Toast::new(&message));
+                        }
+                    }
+                ));
+            }
+        ));
+    }
+
+    /// Adds a folder to the library folders of the preferences. GVFS locations,
+    /// such as smb:// shares, are mounted first, which may prompt for a password.
+    pub async fn add_library_folder(&self, folder: &str) -> Result<(), String> {
+        let file: gio::File = gio::File::for_commandline_arg(folder);
+
+        if !file.is_native() {
+            if let Err(error) = mounts::mount_folder(&file, self).await {
+                if !error.matches(gio::IOErrorEnum::AlreadyMounted) {
+                    g_warning!("ApplicationWindow", "Failed to mount '{}': {}", folder, error);
+                    return Err(gettext("Could not connect to the location"));
+                }
+            }
+        }
+        match file
+            .query_info_future(
+                gio::FILE_ATTRIBUTE_STANDARD_TYPE,
+                gio::FileQueryInfoFlags::NONE,
+                glib::Priority::DEFAULT,
+            )
+            .await
+        {
+            Ok(info) if info.file_type() == gio::FileType::Directory => (),
+            _ => return Err(gettext("The folder does not exist")),
+        }
+
+        // Local folders are stored as paths, other locations as URIs, and
+        // folders shared by the document portal by their document ID.
+        let folder: String = LibraryRoot::for_file(&file).to_setting();
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+        let mut folders: Vec<String> = gsettings
+            .strv("library-folders")
+            .iter()
+            .map(|f: &glib::GStringPtr| f.to_string())
+            .collect();
+
+        if !folders.contains(&folder) {
+            folders.push(folder);
+            let _ = gsettings.set_strv("library-folders", folders);
+        }
+        Ok(())
+    }
+
+    /// Populates the hardware acceleration backend combo row of the preferences
+    /// dialog with the backends detected on the system, and binds it to GSettings.
+    fn setup_hwaccel_backend_row(&self, row: &adw::ComboRow) {
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+
+        let mut backends: Vec<HardwareAccel> = vec![HardwareAccel::Auto];
+        backends.extend_from_slice(hwaccel::available_backends());
+
+        let names: Vec<String> = backends.iter().map(HardwareAccel::display_name).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        row.set_model(Some(&gtk::StringList::new(&names)));
+
+        let selected: HardwareAccel =
+            HardwareAccel::selected(true, &gsettings.string("ffmpeg-hardware-acceleration-backend"))
+                .unwrap_or(HardwareAccel::Auto);
+        let position: usize = backends.iter().position(|b| *b == selected).unwrap_or(0);
+        row.set_selected(position as u32);
+
+        row.connect_selected_notify(move |row: &adw::ComboRow| {
+            if let Some(backend) = backends.get(row.selected() as usize) {
+                if let Err(err_msg) =
+                    gsettings.set_string("ffmpeg-hardware-acceleration-backend", backend.key())
+                {
+                    g_critical!("ApplicationWindow", "GSettings returned error: {}", err_msg);
+                }
+            }
+        });
+
+        MemoriesApplication::default()
+            .gsettings()
+            .bind("ffmpeg-hardware-acceleration", row, "sensitive")
+            .get_only()
+            .build();
+    }
+
+    /// Shows how many photos the wallpaper slideshow rotates through, with a button to stop it.
+    fn setup_wallpaper_slideshow_row(
+        &self,
+        dialog: &adw::PreferencesDialog,
+        row: &adw::ActionRow,
+        stop_button: &gtk::Button,
+    ) {
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+
+        let update_row = clone!(
+            #[weak]
+            row,
+            #[weak]
+            stop_button,
+            move |gsettings: &gio::Settings, _: &str| {
+                let count: usize = gsettings.strv("wallpaper-slideshow").len();
+
+                row.set_subtitle(&match count {
+                    0 => gettext("No wallpaper slideshow"),
+                    _ => ngettext_f(
+                        "Rotating through {COUNT} photo",
+                        "Rotating through {COUNT} photos",
+                        count.try_into().unwrap_or(u32::MAX),
+                        &[("COUNT", &count.to_string())],
+                    ),
+                });
+                stop_button.set_sensitive(count > 0);
+            }
+        );
+        update_row(&gsettings, "wallpaper-slideshow");
+
+        let handler_id: glib::SignalHandlerId =
+            gsettings.connect_changed(Some("wallpaper-slideshow"), update_row);
+        let handler_id: RefCell<Option<glib::SignalHandlerId>> = RefCell::new(Some(handler_id));
+
+        dialog.connect_closed(move |_: &adw::PreferencesDialog| {
+            if let Some(handler_id) = handler_id.take() {
+                gsettings.disconnect(handler_id);
+            }
+        });
+
+        stop_button.connect_clicked(|_: &gtk::Button| {
+            MemoriesApplication::default().set_wallpaper_slideshow(&[]);
+        });
+    }
+
+    /// Offers to import the ratings, favorites, tags, and events of a Shotwell library
+    /// once. The row is hidden if Shotwell has not been used by the user.
+    fn setup_shotwell_import_row(
+        &self,
+        dialog: &adw::PreferencesDialog,
+        row: &adw::ActionRow,
+        import_button: &gtk::Button,
+    ) {
+        let Some(database) = shotwell::database_path() else {
+            row.set_visible(false);
+            return;
+        };
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+
+        if gsettings.boolean("shotwell-imported") {
+            row.set_subtitle(&gettext("Your Shotwell library has been imported"));
+            import_button.set_sensitive(false);
+            return;
+        }
+        row.set_subtitle(&gettext(
+            "Bring over ratings, favorites, and tags. Events are imported as tags.",
+        ));
+
+        import_button.connect_clicked(clone!(
+            #[weak]
+            dialog,
+            #[weak]
+            row,
+            move |import_button: &gtk::Button| {
+                import_button.set_sensitive(false);
+
+                glib::spawn_future_local(clone!(
+                    #[weak]
+                    dialog,
+                    #[weak]
+                    row,
+                    #[weak]
+                    import_button,
+                    #[strong]
+                    database,
+                    async move {
+                        let result = gio::spawn_blocking(move || shotwell::read_database(&database)).await;
+                        import_button.set_sensitive(true);
+
+                        match result {
+                            Ok(Ok(metadata)) => {
+                                Self::confirm_shotwell_import(&dialog, &row, &import_button, metadata)
+                            }
+                            Ok(Err(err)) => {
+                                g_warning!("ApplicationWindow", "Failed to read Shotwell library: {}", err);
+                                dialog.add_toast(adw::Toast::new(&gettext(
+                                    "Could not read the Shotwell library",
+                                )));
+                            }
+                            Err(_) => (),
+                        }
+                    }
+                ));
+            }
+        ));
+    }
+
+    /// Asks to import the metadata read from a Shotwell library, and imports it.
+    fn confirm_shotwell_import(
+        dialog: &adw::PreferencesDialog,
+        row: &adw::ActionRow,
+        import_button: &gtk::Button,
+        metadata: ImportedMetadata,
+    ) {
+        let count: usize = metadata.media_count();
+
+        let alert_dialog: adw::AlertDialog = adw::AlertDialog::builder()
+            .heading(gettext("Import Shotwell Library?"))
+            .body(ngettext_f(
+                "The ratings, favorites, and tags of {COUNT} photo or video will be added to your library. Ratings already set in Memories are kept.",
+                "The ratings, favorites, and tags of {COUNT} photos and videos will be added to your library. Ratings already set in Memories are kept.",
+                count.try_into().unwrap_or(u32::MAX),
+                &[("COUNT", &count.to_string())],
+            ))
+            .default_response("import")
+            .close_response("cancel")
+            .build();
+
+        alert_dialog.add_responses(&[("cancel", &gettext("_Cancel")), ("import", &gettext("_Import"))]);
+        alert_dialog.set_response_appearance("import", adw::ResponseAppearance::Suggested);
+
+        let metadata: RefCell<Option<ImportedMetadata>> = RefCell::new(Some(metadata));
+
+        alert_dialog.connect_response(
+            Some("import"),
+            clone!(
+                #[weak]
+                dialog,
+                #[weak]
+                row,
+                #[weak]
+                import_button,
+                move |_: &adw::AlertDialog, _: &str| {
+                    let Some(metadata) = metadata.take() else {
+                        return;
+                    };
+                    let app: MemoriesApplication = MemoriesApplication::default();
+
+                    app.import_metadata(metadata);
+                    app.toggle_gschema_key("shotwell-imported", true);
+
+                    row.set_subtitle(&gettext("Your Shotwell library has been imported"));
+                    import_button.set_sensitive(false);
+                    dialog.add_toast(adw::Toast::new(&ngettext_f(
+                        "Imported {COUNT} item from Shotwell",
+                        "Imported {COUNT} items from Shotwell",
+                        count.try_into().unwrap_or(u32::MAX),
+                        &[("COUNT", &count.to_string())],
+                    )));
+                }
+            ),
+        );
+        alert_dialog.present(Some(dialog));
+    }
+
+    /// Lists the keyboard shortcuts that can be changed in the preferences dialog.
+    /// Activating a row asks for a new shortcut, which is stored in GSettings.
+    fn setup_shortcuts_page(&self, dialog: &adw::PreferencesDialog, page: &adw::PreferencesPage) {
+        let gsettings: gio::Settings = MemoriesApplication::default().gsettings();
+        let mut rows: Vec<(&'static Shortcut, gtk::ShortcutLabel, gtk::Button)> = vec![];
+
+        for section in ShortcutSection::ALL {
+            let group: adw::PreferencesGroup =
+                adw::PreferencesGroup::builder().title(section.title()).build();
+
+            for shortcut in SHORTCUTS.iter().filter(|s: &&Shortcut| s.section() == section) {
+                let Some(title) = shortcut.title() else {
+                    continue;
+                };
+                let row: adw::ActionRow = adw::ActionRow::builder().title(title).activatable(true).build();
+
+                let shortcut_label: gtk::ShortcutLabel = gtk::ShortcutLabel::builder()
+                    .disabled_text(gettext("Disabled"))
+                    .valign(gtk::Align::Center)
+                    .build();
+
+                let reset_button: gtk::Button = gtk::Button::builder()
+                    .icon_name("edit-undo-symbolic")
+                    .tooltip_text(gettext("Reset to Default"))
+                    .valign(gtk::Align::Center)
+                    .css_classes(["flat"])
+                    .build();
+
+                reset_button.connect_clicked(move |_: &gtk::Button| {
+                    shortcuts::set_custom_accels(shortcut.action, None);
+                });
+                row.connect_activated(clone!(
+                    #[weak]
+                    dialog,
+                    move |_: &adw::ActionRow| Self::capture_shortcut(&dialog, shortcut)
+                ));
+                row.add_suffix(&shortcut_label);
+                row.add_suffix(&reset_button);
+
+                group.add(&row);
+                rows.push((shortcut, shortcut_label, reset_button));
+            }
+            page.add(&group);
+        }
+
+        let update_rows = move || {
+            let custom_accels: HashMap<String, Vec<String>> = shortcuts::custom_accels();
+
+            for (shortcut, shortcut_label, reset_button) in &rows {
+                shortcut_label.set_accelerator(&shortcut.accels(&custom_accels).join(" "));
+                reset_button.set_visible(shortcut.is_customized(&custom_accels));
+            }
+        };
+        update_rows();
+
+        let handler_id: glib::SignalHandlerId = gsettings
+            .connect_changed(Some("custom-accels"), move |_: &gio::Settings, _: &str| {
+                update_rows()
+            });
+        let handler_id: RefCell<Option<glib::SignalHandlerId>> = RefCell::new(Some(handler_id));
+
+        dialog.connect_closed(move |_: &adw::PreferencesDialog| {
+            if let Some(handler_id) = handler_id.take() {
+                gsettings.disconnect(handler_id);
+            }
+        });
+    }
+
+    /// Asks for a new key combination for the shortcut of an action. Escape
+    /// cancels, and Backspace disables the shortcut.
+    fn capture_shortcut(dialog: &adw::PreferencesDialog, shortcut: &'static Shortcut) {
+        let capture_dialog: adw::AlertDialog = adw::AlertDialog::builder()
+            .heading(gettext("Set Shortcut"))
+            .body(gettext_f(
+                "Press the new shortcut for “{ACTION}”, or Backspace to disable it.",
+                &[("ACTION", &shortcut.title().unwrap_or_default())],
+            ))
+            .close_response("cancel")
+            .build();
+        capture_dialog.add_response("cancel", &gettext("_Cancel"));
+
+        let key_controller: gtk::EventControllerKey = gtk::EventControllerKey::new();
+        key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+
+        key_controller.connect_key_pressed(clone!(
+            #[weak]
+            capture_dialog,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |controller: &gtk::EventControllerKey, keyval: gdk::Key, _, state: gdk::ModifierType| {
+                let keyval: gdk::Key = keyval.to_lower();
+                let modifiers: gdk::ModifierType = state & gtk::accelerator_get_default_mod_mask();
+
+                let is_modifier: bool = controller
+                    .current_event()
+                    .and_then(|event: gdk::Event| event.downcast::<gdk::KeyEvent>().ok())
+                    .is_some_and(|event: gdk::KeyEvent| event.is_modifier());
+
+                // Wait until a key other than a modifier key is pressed.
+                if is_modifier {
+                    return glib::Propagation::Stop;
+                }
+                if modifiers.is_empty() {
+                    match keyval {
+                        gdk::Key::Escape => {
+                            capture_dialog.close();
+                            return glib::Propagation::Stop;
+                        }
+                        gdk::Key::BackSpace => {
+                            shortcuts::set_custom_accels(shortcut.action, Some(vec![]));
+                            capture_dialog.close();
+                            return glib::Propagation::Stop;
+                        }
+                        _ => (),
+                    }
+                }
+                if !gtk::accelerator_valid(keyval, modifiers) {
+                    return glib::Propagation::Stop;
+                }
+                let accel: String = gtk::accelerator_name(keyval, modifiers).to_string();
+
+                if let Some(conflict) =
+                    shortcuts::find_conflict(&accel, shortcut.action, &shortcuts::custom_accels())
+                {
+                    capture_dialog.set_body(&gettext_f(
+                        "{SHORTCUT} is already used for “{ACTION}”.",
+                        &[
+                            ("SHORTCUT", &gtk::accelerator_get_label(keyval, modifiers)),
+                            (
+                                "ACTION",
+                                &conflict.title().unwrap_or_else(|| conflict.action.to_string()),
+                            ),
+                        ],
+                    ));
+                    return glib::Propagation::Stop;
+                }
+                shortcuts::set_custom_accels(shortcut.action, Some(vec![accel]));
+                capture_dialog.close();
+                glib::Propagation::Stop
+            }
+        ));
+        capture_dialog.add_controller(key_controller);
+
+        capture_dialog.present(Some(dialog));
     }
 
     #[template_callback]
@@ -251,11 +1065,19 @@ impl MemoriesApplicationWindow {
                     .imp()
                     .search_entry
                     .set_placeholder_text(Some(&gettext("Search Albums"))),
+                "folders" => self
+                    .imp()
+                    .search_entry
+                    .set_placeholder_text(Some(&gettext("Search Photos"))),
                 "favorites" => self
                     .imp()
                     .search_entry
                     .set_placeholder_text(Some(&gettext("Search Favorites"))),
-                _ => g_error!("ApplicationWindow", "Unexpected master stack child found."),
+                "people" => self
+                    .imp()
+                    .search_entry
+                    .set_placeholder_text(Some(&gettext("Search Photos"))),
+                _ => g_warning!("ApplicationWindow", "Unexpected master stack child found."),
             }
         }
     }
@@ -264,4 +1086,20 @@ impl MemoriesApplicationWindow {
     fn toggle_search_bar(&self, toggle_button: &gtk::ToggleButton) {
         self.imp().search_bar.set_search_mode(toggle_button.is_active());
     }
+
+    #[template_callback]
+    fn search_changed(&self, search_entry: &gtk::SearchEntry) {
+        self.imp().library_view.set_search_query(&search_entry.text());
+    }
+}
+
+/// Returns the title of the window when `page` is its visible navigation page.
+/// Pages pushed onto the main window, like the viewer, are named in the title.
+fn window_title(page: Option<&adw::NavigationPage>) -> String {
+    match page {
+        Some(page) if page.tag().as_deref() != Some("window") => {
+            gettext_f("{PAGE} — Memories", &[("PAGE", &page.title())])
+        }
+        _ => gettext("Memories"),
+    }
 }